@@ -0,0 +1,143 @@
+//! Programmable authorization for accounts
+//!
+//! An account may name a controller as its authorizer. Before running an
+//! instruction that targets the account or an object it controls, the
+//! runtime calls the authorizer's `authorize` function with the account as
+//! its target and an [`AuthorizationRequest`] as its parameters. The call
+//! approves by succeeding and denies by failing, so an account can enforce
+//! any rule its authorizer can express: spending limits, multi-party
+//! approval, session keys and other smart-wallet policies.
+//!
+//! Designations live in a single system object that controllers cannot
+//! modify.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::constants::SYSTEM_LOADER_ID;
+use crate::error::StorageError;
+use crate::id::UnitsObjectId;
+use crate::objects::UnitsObject;
+use crate::transaction::{Instruction, TransactionHash};
+
+/// Well-known ID of the authorizer registry object
+pub const AUTHORIZER_REGISTRY_ID: UnitsObjectId = UnitsObjectId::new([0xa7; 32]);
+
+/// Function invoked on an authorizer to approve an instruction
+pub const AUTHORIZE_FUNCTION: &str = "authorize";
+
+/// Authorizer designated by each account
+///
+/// Stored as the data of the object at [`AUTHORIZER_REGISTRY_ID`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthorizerRegistry {
+    pub authorizers: BTreeMap<UnitsObjectId, UnitsObjectId>,
+}
+
+impl AuthorizerRegistry {
+    /// Decode a registry from its storage object
+    pub fn from_object(object: &UnitsObject) -> Result<Self, StorageError> {
+        bincode::deserialize(object.data())
+            .map_err(|e| StorageError::Serialization(format!("Invalid authorizer registry: {}", e)))
+    }
+
+    /// Encode the registry as its storage object
+    pub fn to_object(&self) -> Result<UnitsObject, StorageError> {
+        let data = bincode::serialize(self)
+            .map_err(|e| StorageError::Serialization(format!("Authorizer registry: {}", e)))?;
+        Ok(UnitsObject::new_data(AUTHORIZER_REGISTRY_ID, SYSTEM_LOADER_ID, data))
+    }
+
+    /// Designate `authorizer` for `account`, or with `None` remove its
+    /// authorizer, returning the previous one
+    pub fn set(&mut self, account: UnitsObjectId, authorizer: Option<UnitsObjectId>) -> Option<UnitsObjectId> {
+        match authorizer {
+            Some(authorizer) => self.authorizers.insert(account, authorizer),
+            None => self.authorizers.remove(&account),
+        }
+    }
+
+    pub fn authorizer_of(&self, account: &UnitsObjectId) -> Option<&UnitsObjectId> {
+        self.authorizers.get(account)
+    }
+
+    /// Accounts whose authorizer must approve `instruction`, with that
+    /// authorizer, in target order
+    ///
+    /// A target is guarded by its own authorizer and by that of the
+    /// account controlling it, looked up in `objects`.
+    pub fn guarding(
+        &self,
+        instruction: &Instruction,
+        objects: &HashMap<UnitsObjectId, UnitsObject>,
+    ) -> Vec<(UnitsObjectId, UnitsObjectId)> {
+        let mut guards = Vec::new();
+        for id in &instruction.target_objects {
+            let controller = objects.get(id).map(|object| object.controller_id);
+            for account in std::iter::once(*id).chain(controller) {
+                if let Some(authorizer) = self.authorizer_of(&account) {
+                    if !guards.iter().any(|(guarded, _)| *guarded == account) {
+                        guards.push((account, *authorizer));
+                    }
+                }
+            }
+        }
+        guards
+    }
+}
+
+/// Parameters of an authorizer's `authorize` call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorizationRequest {
+    /// Account whose approval is asked
+    pub account: UnitsObjectId,
+    pub transaction_hash: TransactionHash,
+    /// Index of the instruction within its transaction
+    pub instruction_index: usize,
+    /// Instruction awaiting approval
+    pub instruction: Instruction,
+}
+
+impl AuthorizationRequest {
+    /// Decode a request from an `authorize` call's parameters
+    pub fn from_params(params: &[u8]) -> Result<Self, StorageError> {
+        bincode::deserialize(params)
+            .map_err(|e| StorageError::Serialization(format!("Invalid authorization request: {}", e)))
+    }
+
+    /// Call to `authorizer` asking it to approve the request
+    pub fn to_instruction(&self, authorizer: UnitsObjectId) -> Result<Instruction, StorageError> {
+        let params = bincode::serialize(self)
+            .map_err(|e| StorageError::Serialization(format!("Authorization request: {}", e)))?;
+        Ok(Instruction::new(authorizer, AUTHORIZE_FUNCTION.to_string(), vec![self.account], params))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accounts_guard_their_objects() {
+        let wallet = UnitsObjectId::new([1; 32]);
+        let authorizer = UnitsObjectId::new([2; 32]);
+        let owned = UnitsObject::new_data(UnitsObjectId::new([3; 32]), wallet, vec![]);
+        let other = UnitsObject::new_data(UnitsObjectId::new([4; 32]), UnitsObjectId::new([5; 32]), vec![]);
+
+        let mut registry = AuthorizerRegistry::default();
+        assert_eq!(registry.set(wallet, Some(authorizer)), None);
+        let registry = AuthorizerRegistry::from_object(&registry.to_object().unwrap()).unwrap();
+
+        let objects = HashMap::from([(owned.id, owned.clone()), (other.id, other.clone())]);
+        let instruction = Instruction::new(wallet, "transfer".to_string(), vec![other.id, owned.id, wallet], vec![]);
+        assert_eq!(registry.guarding(&instruction, &objects), vec![(wallet, authorizer)]);
+
+        let instruction = Instruction::new(wallet, "transfer".to_string(), vec![other.id], vec![]);
+        assert!(registry.guarding(&instruction, &objects).is_empty());
+
+        let request = AuthorizationRequest { account: wallet, transaction_hash: [7; 32], instruction_index: 0, instruction };
+        let call = request.to_instruction(authorizer).unwrap();
+        assert_eq!((call.controller_id, call.target_function.as_str()), (authorizer, AUTHORIZE_FUNCTION));
+        assert_eq!(AuthorizationRequest::from_params(&call.params).unwrap().account, wallet);
+    }
+}
@@ -6,6 +6,7 @@ pub const SYSTEM_LOADER_ID: UnitsObjectId = UnitsObjectId::new([0; 32]);
 pub const TOKEN_CONTROLLER_ID: UnitsObjectId = UnitsObjectId::new([1; 32]);
 pub const ACCOUNT_CONTROLLER_ID: UnitsObjectId = UnitsObjectId::new([2; 32]);
 pub const MODULE_MANAGER_ID: UnitsObjectId = UnitsObjectId::new([3; 32]);
+pub const ATTEST_CONTROLLER_ID: UnitsObjectId = UnitsObjectId::new([4; 32]);
 
 /// Validate that an object ID is a system controller
 pub fn is_system_controller(id: &UnitsObjectId) -> bool {
@@ -13,6 +14,7 @@ pub fn is_system_controller(id: &UnitsObjectId) -> bool {
         || *id == TOKEN_CONTROLLER_ID
         || *id == ACCOUNT_CONTROLLER_ID
         || *id == MODULE_MANAGER_ID
+        || *id == ATTEST_CONTROLLER_ID
 }
 
 #[cfg(test)]
@@ -26,6 +28,7 @@ mod tests {
         assert!(is_system_controller(&TOKEN_CONTROLLER_ID));
         assert!(is_system_controller(&ACCOUNT_CONTROLLER_ID));
         assert!(is_system_controller(&MODULE_MANAGER_ID));
+        assert!(is_system_controller(&ATTEST_CONTROLLER_ID));
 
         // Test random ID is not a system controller
         let random_id = UnitsObjectId::new([99; 32]);
@@ -40,6 +43,7 @@ mod tests {
             TOKEN_CONTROLLER_ID,
             ACCOUNT_CONTROLLER_ID,
             MODULE_MANAGER_ID,
+            ATTEST_CONTROLLER_ID,
         ];
 
         for i in 0..ids.len() {
@@ -0,0 +1,33 @@
+//! Ed25519 signature verification
+//!
+//! Built on the same curve arithmetic as object IDs, so checking a
+//! signature needs nothing beyond this crate and works in kernel modules.
+
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use sha2::{Digest, Sha512};
+
+/// Whether `signature` is `public_key`'s Ed25519 signature over `message`
+pub fn verify(public_key: &[u8; 32], message: &[u8], signature: &[u8; 64]) -> bool {
+    let Some(a) = CompressedEdwardsY(*public_key).decompress() else {
+        return false;
+    };
+    let mut r = [0u8; 32];
+    r.copy_from_slice(&signature[..32]);
+    let Some(r_point) = CompressedEdwardsY(r).decompress() else {
+        return false;
+    };
+    let mut s = [0u8; 32];
+    s.copy_from_slice(&signature[32..]);
+    let Some(s) = Option::<Scalar>::from(Scalar::from_canonical_bytes(s)) else {
+        return false;
+    };
+
+    let digest = Sha512::new()
+        .chain_update(r)
+        .chain_update(public_key)
+        .chain_update(message)
+        .finalize();
+    let k = Scalar::from_bytes_mod_order_wide(&digest.into());
+    EdwardsPoint::mul_base(&s) == r_point + k * a
+}
@@ -0,0 +1,96 @@
+//! Plugins that review a transaction's effects before they commit
+//!
+//! A runtime runs its effect processors, in registration order, once every
+//! instruction of a transaction has succeeded. Each sees the transaction and
+//! its receipt, with the effects already validated against the objects they
+//! change. A processor can let the transaction through, attach annotations
+//! to the receipt, or veto it, which fails the transaction as if an
+//! instruction had. Deployments pick processors for fee accounting,
+//! analytics or compliance; like instructions, they must be deterministic.
+
+use std::sync::Arc;
+
+use crate::transaction::{ReceiptAnnotation, Transaction, TransactionReceipt};
+
+/// What an effect processor decided about a transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EffectVerdict {
+    Accept,
+    /// Accept, recording these key/value pairs on the receipt
+    Annotate(Vec<(String, String)>),
+    /// Fail the transaction with this reason
+    Veto(String),
+}
+
+/// Post-execution plugin reviewing a transaction's effects
+pub trait EffectProcessor: Send + Sync {
+    /// Name recorded on the processor's annotations and vetoes
+    fn name(&self) -> &str;
+
+    fn process(&self, transaction: &Transaction, receipt: &TransactionReceipt) -> EffectVerdict;
+}
+
+/// Run `processors` in order over the receipt of a successful transaction
+///
+/// Annotations are added to the receipt as they are made. The first veto
+/// stops the run and is returned as the transaction's error; annotations
+/// made before it stay on the receipt.
+pub fn apply_effect_processors(
+    processors: &[Arc<dyn EffectProcessor>],
+    transaction: &Transaction,
+    receipt: &mut TransactionReceipt,
+) -> Result<(), String> {
+    for processor in processors {
+        match processor.process(transaction, receipt) {
+            EffectVerdict::Accept => {}
+            EffectVerdict::Annotate(notes) => {
+                receipt.annotations.extend(notes.into_iter().map(|(key, value)| ReceiptAnnotation {
+                    processor: processor.name().to_string(),
+                    key,
+                    value,
+                }));
+            }
+            EffectVerdict::Veto(reason) => {
+                return Err(format!("Vetoed by {}: {}", processor.name(), reason));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(&'static str, EffectVerdict);
+
+    impl EffectProcessor for Fixed {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn process(&self, _transaction: &Transaction, _receipt: &TransactionReceipt) -> EffectVerdict {
+            self.1.clone()
+        }
+    }
+
+    #[test]
+    fn test_processors_run_in_order_until_a_veto() {
+        let transaction = Transaction::new(vec![], [1; 32]);
+        let mut receipt = TransactionReceipt::new(transaction.hash, 1, true, 0);
+        let tag = Arc::new(Fixed("tag", EffectVerdict::Annotate(vec![("k".to_string(), "v".to_string())])));
+        let veto = Arc::new(Fixed("veto", EffectVerdict::Veto("blocked".to_string())));
+        let accept = Arc::new(Fixed("accept", EffectVerdict::Accept));
+
+        let processors: Vec<Arc<dyn EffectProcessor>> = vec![accept.clone(), tag.clone()];
+        assert!(apply_effect_processors(&processors, &transaction, &mut receipt).is_ok());
+        assert_eq!(receipt.annotations.len(), 1);
+        assert_eq!(receipt.annotations[0].processor, "tag");
+
+        // Processors after a veto never run
+        let processors: Vec<Arc<dyn EffectProcessor>> = vec![veto, tag];
+        let error = apply_effect_processors(&processors, &transaction, &mut receipt).unwrap_err();
+        assert_eq!(error, "Vetoed by veto: blocked");
+        assert_eq!(receipt.annotations.len(), 1);
+    }
+}
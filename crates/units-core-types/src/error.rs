@@ -107,6 +107,14 @@ pub enum StorageError {
     /// Lock-related errors
     #[error("Lock error: {0}")]
     LockError(String),
+
+    /// Timed out waiting for another holder to release an object lock
+    #[error("Timed out waiting for lock on {0}")]
+    LockTimeout(crate::id::UnitsObjectId),
+
+    /// Waiting for an object lock would deadlock, so this transaction was aborted
+    #[error("Deadlock detected waiting for lock on {0}")]
+    DeadlockDetected(crate::id::UnitsObjectId),
     
     /// Receipt not found error
     #[error("Receipt not found: {0:?}")]
@@ -0,0 +1,189 @@
+//! Cache of controller executables shared across transactions
+//!
+//! Every instruction passes its controller's executable to the VM, so
+//! without a cache each one costs a storage read of an object that rarely
+//! changes. [`ExecutableCache`] keeps the executables it has resolved and,
+//! as a [`StorageObserver`] of the storage it reads from, forgets one as
+//! soon as it is upgraded or deleted.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+use crate::error::StorageError;
+use crate::id::UnitsObjectId;
+use crate::objects::UnitsObject;
+use crate::proofs::UnitsObjectProof;
+use crate::storage::StorageObserver;
+
+/// Executables kept by [`ExecutableCache::new`]
+pub const DEFAULT_EXECUTABLE_CACHE_CAPACITY: usize = 256;
+
+/// Hit and miss counts of an [`ExecutableCache`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExecutableCacheStats {
+    /// Resolutions answered without reading storage
+    pub hits: u64,
+    /// Resolutions that read storage
+    pub misses: u64,
+    /// Executables dropped because they were upgraded or deleted
+    pub invalidations: u64,
+    /// Executables currently cached
+    pub entries: usize,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    by_id: HashMap<UnitsObjectId, UnitsObject>,
+    /// Insertion order, for evicting the oldest entry when full
+    order: VecDeque<UnitsObjectId>,
+}
+
+/// Controller executables by ID, invalidated on writes to them
+#[derive(Debug)]
+pub struct ExecutableCache {
+    capacity: usize,
+    entries: RwLock<Entries>,
+    /// Bumped by every invalidation, so a load racing a write is not cached
+    generation: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+impl Default for ExecutableCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExecutableCache {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_EXECUTABLE_CACHE_CAPACITY)
+    }
+
+    /// Cache at most `capacity` executables, evicting the oldest first
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: RwLock::new(Entries::default()),
+            generation: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+        }
+    }
+
+    /// Executable `id`, read through `load` unless cached
+    ///
+    /// Objects that are not executable are returned but not cached.
+    pub fn resolve<F>(&self, id: &UnitsObjectId, load: F) -> Result<Option<UnitsObject>, StorageError>
+    where
+        F: FnOnce(&UnitsObjectId) -> Result<Option<UnitsObject>, StorageError>,
+    {
+        if let Some(object) = self.entries.read().unwrap().by_id.get(id) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(Some(object.clone()));
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let generation = self.generation.load(Ordering::Acquire);
+        let object = load(id)?;
+        if let Some(object) = object.as_ref().filter(|object| object.is_executable()) {
+            let mut entries = self.entries.write().unwrap();
+            if self.capacity > 0 && self.generation.load(Ordering::Acquire) == generation && !entries.by_id.contains_key(id) {
+                if entries.by_id.len() >= self.capacity {
+                    if let Some(oldest) = entries.order.pop_front() {
+                        entries.by_id.remove(&oldest);
+                    }
+                }
+                entries.by_id.insert(*id, object.clone());
+                entries.order.push_back(*id);
+            }
+        }
+        Ok(object)
+    }
+
+    /// Drop executable `id`, if cached
+    pub fn invalidate(&self, id: &UnitsObjectId) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        let mut entries = self.entries.write().unwrap();
+        if entries.by_id.remove(id).is_some() {
+            entries.order.retain(|cached| cached != id);
+            self.invalidations.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Drop every cached executable
+    pub fn clear(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        let mut entries = self.entries.write().unwrap();
+        entries.by_id.clear();
+        entries.order.clear();
+    }
+
+    pub fn stats(&self) -> ExecutableCacheStats {
+        ExecutableCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            entries: self.entries.read().unwrap().by_id.len(),
+        }
+    }
+}
+
+impl StorageObserver for ExecutableCache {
+    fn on_set(&self, object: &UnitsObject, _proof: &UnitsObjectProof) {
+        self.invalidate(object.id());
+    }
+
+    fn on_delete(&self, id: &UnitsObjectId, _proof: &UnitsObjectProof) {
+        self.invalidate(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::VMType;
+    use std::cell::Cell;
+
+    fn executable(seed: u8, code: Vec<u8>) -> UnitsObject {
+        let id = UnitsObjectId::new([seed; 32]);
+        UnitsObject::new_executable(id, id, VMType::RiscV, code)
+    }
+
+    #[test]
+    fn test_executables_are_cached_until_upgraded() {
+        let cache = ExecutableCache::with_capacity(1);
+        let stored = std::cell::RefCell::new(executable(1, vec![1]));
+        let reads = Cell::new(0);
+        let load = |id: &UnitsObjectId| {
+            reads.set(reads.get() + 1);
+            let stored = stored.borrow();
+            Ok((stored.id() == id).then(|| stored.clone()))
+        };
+        let id = *stored.borrow().id();
+
+        for _ in 0..3 {
+            assert_eq!(cache.resolve(&id, load).unwrap(), Some(executable(1, vec![1])));
+        }
+        assert_eq!(reads.get(), 1);
+
+        // An upgrade written to storage is seen on the next resolution
+        *stored.borrow_mut() = executable(1, vec![2]);
+        cache.invalidate(&id);
+        assert_eq!(cache.resolve(&id, load).unwrap(), Some(executable(1, vec![2])));
+        assert_eq!(reads.get(), 2);
+
+        // Data objects and missing objects always read through
+        let data = UnitsObject::new_data(UnitsObjectId::new([2; 32]), id, vec![]);
+        let load_data = |_: &UnitsObjectId| Ok(Some(data.clone()));
+        cache.resolve(data.id(), load_data).unwrap();
+        cache.resolve(data.id(), load_data).unwrap();
+        assert_eq!(cache.resolve(&UnitsObjectId::new([3; 32]), load).unwrap(), None);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.invalidations, stats.entries), (2, 5, 1, 1));
+    }
+}
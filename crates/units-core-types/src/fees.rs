@@ -0,0 +1,154 @@
+//! Fee accounts charged for transaction priority fees
+//!
+//! Fees are paid from balances held in a single ledger object, like storage
+//! deposits. A transaction is charged only when a sponsor has signed for its
+//! fee; the payment is recorded in its receipt. The ledger also holds each
+//! sponsor's next nonce, so a signed sponsorship is charged at most once.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::constants::SYSTEM_LOADER_ID;
+use crate::id::UnitsObjectId;
+use crate::objects::UnitsObject;
+use crate::vm_executor::{ObjectEffect, VMExecutionError};
+
+/// Well-known ID of the fee ledger object
+pub const FEE_LEDGER_ID: UnitsObjectId = UnitsObjectId::new([0xfe; 32]);
+
+/// Balances available to pay fees, by account
+///
+/// Stored as the data of the object at [`FEE_LEDGER_ID`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeLedger {
+    pub balances: BTreeMap<UnitsObjectId, u64>,
+    /// Nonce the next sponsorship charged to each account must carry
+    pub nonces: BTreeMap<UnitsObjectId, u64>,
+}
+
+/// Ledger as stored before sponsorship nonces
+#[derive(Deserialize)]
+struct LegacyFeeLedger {
+    balances: BTreeMap<UnitsObjectId, u64>,
+}
+
+impl FeeLedger {
+    /// Decode a ledger from its storage object
+    ///
+    /// Ledgers written before nonces lack the trailing map, so they fail
+    /// the current layout and decode through the legacy one with no
+    /// sponsorship charged yet.
+    pub fn from_object(object: &UnitsObject) -> Result<Self, VMExecutionError> {
+        bincode::deserialize(object.data())
+            .or_else(|_| {
+                bincode::deserialize::<LegacyFeeLedger>(object.data()).map(|legacy| Self {
+                    balances: legacy.balances,
+                    nonces: BTreeMap::new(),
+                })
+            })
+            .map_err(|e| VMExecutionError::SerializationError(format!("Invalid fee ledger: {}", e)))
+    }
+
+    /// Encode the ledger as its storage object
+    pub fn to_object(&self) -> Result<UnitsObject, VMExecutionError> {
+        let data = bincode::serialize(self)
+            .map_err(|e| VMExecutionError::SerializationError(format!("Fee ledger: {}", e)))?;
+        Ok(UnitsObject::new_data(FEE_LEDGER_ID, SYSTEM_LOADER_ID, data))
+    }
+
+    /// Credit funds to an account
+    pub fn fund(&mut self, account: UnitsObjectId, amount: u64) {
+        let balance = self.balances.entry(account).or_insert(0);
+        *balance = balance.saturating_add(amount);
+    }
+
+    pub fn balance_of(&self, account: &UnitsObjectId) -> u64 {
+        self.balances.get(account).copied().unwrap_or(0)
+    }
+
+    /// Nonce the next sponsorship charged to `account` must carry
+    pub fn next_nonce(&self, account: &UnitsObjectId) -> u64 {
+        self.nonces.get(account).copied().unwrap_or(0)
+    }
+
+    /// Charge `amount` to `payer` for the sponsorship with `nonce`, in the
+    /// ledger stored as `ledger_object`, returning the effect that updates it
+    pub fn charge_effect(
+        ledger_object: Option<&UnitsObject>,
+        payer: UnitsObjectId,
+        amount: u64,
+        nonce: u64,
+    ) -> Result<ObjectEffect, VMExecutionError> {
+        let mut ledger = match ledger_object {
+            Some(object) => Self::from_object(object)?,
+            None => Self::default(),
+        };
+        ledger.charge_sponsored(payer, amount, nonce)?;
+
+        let after = ledger.to_object()?;
+        Ok(match ledger_object {
+            Some(before) => ObjectEffect::modification(before.clone(), after),
+            None => ObjectEffect::creation(after),
+        })
+    }
+
+    /// Take `amount` from `payer` for the sponsorship with `nonce` and
+    /// advance its nonce, failing without change unless `nonce` is its next
+    pub fn charge_sponsored(&mut self, payer: UnitsObjectId, amount: u64, nonce: u64) -> Result<(), VMExecutionError> {
+        let expected = self.next_nonce(&payer);
+        if nonce != expected {
+            return Err(VMExecutionError::InvalidSponsorshipNonce(format!(
+                "{} used nonce {}, expected {}",
+                payer, nonce, expected
+            )));
+        }
+        self.charge(payer, amount)?;
+        self.nonces.insert(payer, expected + 1);
+        Ok(())
+    }
+
+    /// Take `amount` from `payer`, failing without change if it has less
+    pub fn charge(&mut self, payer: UnitsObjectId, amount: u64) -> Result<(), VMExecutionError> {
+        let available = self.balance_of(&payer);
+        if available < amount {
+            return Err(VMExecutionError::InsufficientFeeBalance(format!(
+                "fee {} exceeds the {} held by {}",
+                amount, available, payer
+            )));
+        }
+        self.balances.insert(payer, available - amount);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sponsorship_nonces_advance_once_per_charge() {
+        let payer = UnitsObjectId::new([7; 32]);
+        let mut ledger = FeeLedger::default();
+        ledger.fund(payer, 10);
+
+        ledger.charge_sponsored(payer, 4, 0).unwrap();
+        assert!(matches!(ledger.charge_sponsored(payer, 4, 0), Err(VMExecutionError::InvalidSponsorshipNonce(_))));
+        assert!(ledger.charge_sponsored(payer, 7, 1).is_err());
+        assert_eq!((ledger.balance_of(&payer), ledger.next_nonce(&payer)), (6, 1));
+    }
+
+    #[test]
+    fn test_ledgers_written_before_nonces_still_decode() {
+        #[derive(Serialize)]
+        struct Legacy {
+            balances: BTreeMap<UnitsObjectId, u64>,
+        }
+        let payer = UnitsObjectId::new([7; 32]);
+        let legacy = Legacy { balances: [(payer, 10)].into_iter().collect() };
+        let object = UnitsObject::new_data(FEE_LEDGER_ID, SYSTEM_LOADER_ID, bincode::serialize(&legacy).unwrap());
+
+        let ledger = FeeLedger::from_object(&object).unwrap();
+        assert_eq!((ledger.balance_of(&payer), ledger.next_nonce(&payer)), (10, 0));
+        assert_eq!(FeeLedger::from_object(&ledger.to_object().unwrap()).unwrap(), ledger);
+    }
+}
@@ -0,0 +1,268 @@
+//! Conversions between host types and the kernel wire types
+//!
+//! Kernel modules see the Borsh types from `units-types-ffi`. The host-side
+//! equivalents in this crate derive the same Borsh layout, so a context
+//! serialized here deserializes directly in a module and vice versa. The
+//! `From` impls below cover code that needs to move between the two
+//! representations in memory.
+
+use units_types_ffi as wire;
+
+use crate::id::UnitsObjectId;
+use crate::objects::{ObjectType, UnitsObject, VMType};
+use crate::transaction::Instruction;
+use crate::vm_executor::{ExecutionContext, KernelEvent, ObjectEffect};
+
+impl From<UnitsObjectId> for wire::UnitsObjectId {
+    fn from(id: UnitsObjectId) -> Self {
+        wire::UnitsObjectId::new(*id)
+    }
+}
+
+impl From<wire::UnitsObjectId> for UnitsObjectId {
+    fn from(id: wire::UnitsObjectId) -> Self {
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(id.bytes());
+        UnitsObjectId::new(bytes)
+    }
+}
+
+impl From<VMType> for wire::VMType {
+    fn from(vm_type: VMType) -> Self {
+        match vm_type {
+            VMType::RiscV => wire::VMType::RiscV,
+            VMType::Wasm => wire::VMType::Wasm,
+        }
+    }
+}
+
+impl From<wire::VMType> for VMType {
+    fn from(vm_type: wire::VMType) -> Self {
+        match vm_type {
+            wire::VMType::RiscV => VMType::RiscV,
+            wire::VMType::Wasm => VMType::Wasm,
+        }
+    }
+}
+
+impl From<ObjectType> for wire::ObjectType {
+    fn from(object_type: ObjectType) -> Self {
+        match object_type {
+            ObjectType::Data => wire::ObjectType::Data,
+            ObjectType::Executable(vm_type) => wire::ObjectType::Executable(vm_type.into()),
+            ObjectType::Ephemeral { expires_at_slot } => wire::ObjectType::Ephemeral { expires_at_slot },
+        }
+    }
+}
+
+impl From<wire::ObjectType> for ObjectType {
+    fn from(object_type: wire::ObjectType) -> Self {
+        match object_type {
+            wire::ObjectType::Data => ObjectType::Data,
+            wire::ObjectType::Executable(vm_type) => ObjectType::Executable(vm_type.into()),
+            wire::ObjectType::Ephemeral { expires_at_slot } => ObjectType::Ephemeral { expires_at_slot },
+        }
+    }
+}
+
+impl From<UnitsObject> for wire::UnitsObject {
+    fn from(object: UnitsObject) -> Self {
+        wire::UnitsObject {
+            id: object.id.into(),
+            controller_id: object.controller_id.into(),
+            object_type: object.object_type.into(),
+            data: object.data,
+        }
+    }
+}
+
+impl From<wire::UnitsObject> for UnitsObject {
+    fn from(object: wire::UnitsObject) -> Self {
+        UnitsObject {
+            id: object.id.into(),
+            controller_id: object.controller_id.into(),
+            object_type: object.object_type.into(),
+            data: object.data,
+        }
+    }
+}
+
+impl From<Instruction> for wire::Instruction {
+    fn from(instruction: Instruction) -> Self {
+        wire::Instruction {
+            controller_id: instruction.controller_id.into(),
+            target_function: instruction.target_function,
+            target_objects: instruction.target_objects.into_iter().map(Into::into).collect(),
+            params: instruction.params,
+        }
+    }
+}
+
+impl From<wire::Instruction> for Instruction {
+    fn from(instruction: wire::Instruction) -> Self {
+        Instruction {
+            controller_id: instruction.controller_id.into(),
+            target_function: instruction.target_function,
+            target_objects: instruction.target_objects.into_iter().map(Into::into).collect(),
+            params: instruction.params,
+        }
+    }
+}
+
+impl From<ExecutionContext> for wire::ExecutionContext {
+    fn from(context: ExecutionContext) -> Self {
+        wire::ExecutionContext {
+            instruction: context.instruction.into(),
+            objects: context
+                .objects
+                .into_iter()
+                .map(|(id, object)| (id.into(), object.into()))
+                .collect(),
+            slot: context.slot,
+            timestamp: context.timestamp,
+        }
+    }
+}
+
+impl From<wire::ExecutionContext> for ExecutionContext {
+    fn from(context: wire::ExecutionContext) -> Self {
+        ExecutionContext {
+            instruction: context.instruction.into(),
+            objects: context
+                .objects
+                .into_iter()
+                .map(|(id, object)| (id.into(), object.into()))
+                .collect(),
+            slot: context.slot,
+            timestamp: context.timestamp,
+        }
+    }
+}
+
+impl From<ObjectEffect> for wire::ObjectEffect {
+    fn from(effect: ObjectEffect) -> Self {
+        wire::ObjectEffect {
+            object_id: effect.object_id.into(),
+            before_image: effect.before_image.map(Into::into),
+            after_image: effect.after_image.map(Into::into),
+        }
+    }
+}
+
+impl From<wire::ObjectEffect> for ObjectEffect {
+    fn from(effect: wire::ObjectEffect) -> Self {
+        ObjectEffect {
+            object_id: effect.object_id.into(),
+            before_image: effect.before_image.map(Into::into),
+            after_image: effect.after_image.map(Into::into),
+        }
+    }
+}
+
+impl From<KernelEvent> for wire::KernelEvent {
+    fn from(event: KernelEvent) -> Self {
+        wire::KernelEvent {
+            object_id: event.object_id.into(),
+            name: event.name,
+            data: event.data,
+        }
+    }
+}
+
+impl From<wire::KernelEvent> for KernelEvent {
+    fn from(event: wire::KernelEvent) -> Self {
+        KernelEvent {
+            object_id: event.object_id.into(),
+            name: event.name,
+            data: event.data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn object(seed: u8, object_type: ObjectType) -> UnitsObject {
+        UnitsObject {
+            id: UnitsObjectId::new([seed; 32]),
+            controller_id: UnitsObjectId::new([seed.wrapping_add(1); 32]),
+            object_type,
+            data: vec![seed; seed as usize],
+        }
+    }
+
+    fn context() -> ExecutionContext {
+        let instruction = Instruction::new(
+            UnitsObjectId::new([7; 32]),
+            "transfer".to_string(),
+            vec![UnitsObjectId::new([1; 32]), UnitsObjectId::new([3; 32])],
+            vec![1, 2, 3, 4],
+        );
+        // Enough objects that HashMap iteration order differs from key order
+        let objects: HashMap<_, _> = (1..=16)
+            .map(|seed| {
+                let object_type = if seed % 2 == 0 {
+                    ObjectType::Executable(VMType::RiscV)
+                } else {
+                    ObjectType::Data
+                };
+                let obj = object(seed, object_type);
+                (obj.id, obj)
+            })
+            .collect();
+        ExecutionContext::new(instruction, objects, 42, 1_700_000_000)
+    }
+
+    #[test]
+    fn test_context_layout_matches_wire_types() {
+        let host = context();
+        let host_bytes = borsh::to_vec(&host).unwrap();
+
+        // Host bytes decode as the wire type the SDK hands to modules
+        let decoded: wire::ExecutionContext = borsh::from_slice(&host_bytes).unwrap();
+        assert_eq!(decoded.slot, 42);
+        assert_eq!(decoded.objects.len(), 16);
+
+        // Converting and re-encoding on the wire side yields identical bytes
+        let wire_bytes = borsh::to_vec(&wire::ExecutionContext::from(host.clone())).unwrap();
+        assert_eq!(host_bytes, wire_bytes);
+
+        let round_trip: ExecutionContext = borsh::from_slice(&wire_bytes).unwrap();
+        assert_eq!(round_trip.objects, host.objects);
+        assert_eq!(round_trip.instruction.params, host.instruction.params);
+    }
+
+    #[test]
+    fn test_effect_layout_matches_wire_types() {
+        let effects = vec![
+            wire::ObjectEffect::creation(object(1, ObjectType::Data).into()),
+            wire::ObjectEffect::modification(
+                object(2, ObjectType::Data).into(),
+                object(3, ObjectType::Executable(VMType::RiscV)).into(),
+            ),
+            wire::ObjectEffect::deletion(object(4, ObjectType::Data).into()),
+        ];
+
+        // Effects written by a module decode as host effects
+        let wire_bytes = borsh::to_vec(&effects).unwrap();
+        let host: Vec<ObjectEffect> = borsh::from_slice(&wire_bytes).unwrap();
+        assert_eq!(host.len(), 3);
+        assert!(host[0].before_image.is_none());
+        assert_eq!(host[1].after_image, Some(object(3, ObjectType::Executable(VMType::RiscV))));
+        assert!(host[2].after_image.is_none());
+
+        assert_eq!(borsh::to_vec(&host).unwrap(), wire_bytes);
+
+        let converted: Vec<wire::ObjectEffect> = host.into_iter().map(Into::into).collect();
+        assert_eq!(converted, effects);
+
+        // Events follow the effects in the same layout on both sides
+        let events = vec![wire::KernelEvent::new(object(1, ObjectType::Data).id.into(), "Transfer", vec![1, 2])];
+        let wire_bytes = borsh::to_vec(&events).unwrap();
+        let host: Vec<KernelEvent> = borsh::from_slice(&wire_bytes).unwrap();
+        assert_eq!(host, vec![KernelEvent::new(UnitsObjectId::new([1; 32]), "Transfer", vec![1, 2])]);
+        assert_eq!(host.into_iter().map(wire::KernelEvent::from).collect::<Vec<_>>(), events);
+    }
+}
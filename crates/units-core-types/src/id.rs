@@ -1,12 +1,20 @@
 use curve25519_dalek::edwards::CompressedEdwardsY;
+use borsh::{BorshDeserialize, BorshSerialize};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::fmt;
-use std::ops::Deref;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{Bound, Deref};
+
+/// Domain separating IDs derived from external identifiers from all others
+const EXTERNAL_ID_DOMAIN: &[u8] = b"UNITS_ExternalId";
 
 // UnitsObjectId uniquely identifies an instance of tokenized object.
 // It is a 32 byte long unique identifier, resembling a public key.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
 pub struct UnitsObjectId([u8; 32]);
 
 impl fmt::Display for UnitsObjectId {
@@ -18,13 +26,13 @@ impl fmt::Display for UnitsObjectId {
 }
 
 impl Ord for UnitsObjectId {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         self.0.cmp(&other.0)
     }
 }
 
 impl PartialOrd for UnitsObjectId {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
@@ -58,7 +66,31 @@ impl UnitsObjectId {
         &self.0
     }
 
+    /// Bounds of the IDs starting with `prefix`, for range scans in ID order
+    ///
+    /// Returns `None` for prefixes longer than an ID, which no ID starts with.
+    pub fn prefix_range(prefix: &[u8]) -> Option<(Bound<UnitsObjectId>, Bound<UnitsObjectId>)> {
+        if prefix.len() > 32 {
+            return None;
+        }
+        let mut start = [0u8; 32];
+        start[..prefix.len()].copy_from_slice(prefix);
+
+        // The first ID past the prefix increments its last byte below 0xff
+        let end = match prefix.iter().rposition(|byte| *byte != 0xff) {
+            Some(last) => {
+                let mut end = [0u8; 32];
+                end[..=last].copy_from_slice(&prefix[..=last]);
+                end[last] += 1;
+                Bound::Excluded(UnitsObjectId(end))
+            }
+            None => Bound::Unbounded,
+        };
+        Some((Bound::Included(UnitsObjectId(start)), end))
+    }
+
     /// Create a random UnitsObjectId for testing
+    #[cfg(feature = "std")]
     pub fn random() -> Self {
         // Generate a random ID using system time
         let now = std::time::SystemTime::now()
@@ -73,6 +105,7 @@ impl UnitsObjectId {
     }
 
     /// Generate a unique UnitsObjectId for testing purposes - exposed for testing in other crates
+    #[cfg(feature = "std")]
     pub fn unique_id_for_tests() -> Self {
         // Use current timestamp as basis for uniqueness
         let timestamp = std::time::SystemTime::now()
@@ -131,6 +164,226 @@ impl UnitsObjectId {
     pub fn find_uid(seeds: &[&[u8]]) -> (UnitsObjectId, u8) {
         UnitsObjectId::try_find_uid(seeds).expect("Failed to find a valid UnitsObjectId")
     }
+
+    /// ID of an external identifier, already in `namespace`'s canonical form
+    ///
+    /// The hash covers a domain of its own, then the namespace, version and
+    /// identifier, each length-prefixed. IDs of different namespaces or
+    /// versions therefore never share an input, and none shares one with
+    /// `create_object_id`. Like `find_uid`, the first bump giving an
+    /// off-curve ID is used, so a derived ID is never a public key.
+    pub fn derive_external(namespace: &str, version: u16, identifier: &str) -> Option<(UnitsObjectId, u8)> {
+        for bump in 0..255 {
+            let id: [u8; 32] = Sha256::new()
+                .chain_update(EXTERNAL_ID_DOMAIN)
+                .chain_update((namespace.len() as u32).to_le_bytes())
+                .chain_update(namespace)
+                .chain_update(version.to_le_bytes())
+                .chain_update((identifier.len() as u32).to_le_bytes())
+                .chain_update(identifier)
+                .chain_update([bump])
+                .finalize()
+                .into();
+            if UnitsObjectId::is_off_curve(&id) {
+                return Some((UnitsObjectId(id), bump));
+            }
+        }
+        None
+    }
+}
+
+/// Error mapping an external identifier into ID space
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdDerivationError {
+    UnknownNamespace(String),
+    UnknownVersion { namespace: String, version: u16 },
+    AlreadyRegistered { namespace: String, version: u16 },
+    InvalidIdentifier { namespace: String, reason: String },
+    /// No bump gave an off-curve ID, which is vanishingly unlikely
+    NoValidId,
+}
+
+impl fmt::Display for IdDerivationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownNamespace(namespace) => write!(f, "Unknown ID namespace {}", namespace),
+            Self::UnknownVersion { namespace, version } => {
+                write!(f, "ID namespace {} has no version {}", namespace, version)
+            }
+            Self::AlreadyRegistered { namespace, version } => {
+                write!(f, "ID namespace {} version {} is already registered", namespace, version)
+            }
+            Self::InvalidIdentifier { namespace, reason } => write!(f, "Invalid {} identifier: {}", namespace, reason),
+            Self::NoValidId => write!(f, "No off-curve ID for identifier"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for IdDerivationError {}
+
+/// Scheme mapping one namespace of external identifiers into ID space
+///
+/// A scheme only decides what identifiers it accepts and their canonical
+/// form; [`UnitsObjectId::derive_external`] does the hashing, so every
+/// scheme gets the same separation from the others.
+pub trait IdScheme: Send + Sync {
+    /// Namespace, such as "isin" or "did"
+    fn namespace(&self) -> &str;
+
+    /// Version, bumped whenever the canonical form changes so IDs derived
+    /// under earlier versions can still be reproduced
+    fn version(&self) -> u16;
+
+    /// Canonical form of `identifier`, or why the namespace rejects it
+    fn normalize(&self, identifier: &str) -> Result<String, String>;
+}
+
+/// Case applied by a [`NamespacedScheme`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IdCase {
+    #[default]
+    Preserve,
+    Upper,
+    Lower,
+}
+
+/// Scheme described by data, for namespaces configured without code
+///
+/// Identifiers are trimmed and case-folded, then checked against the
+/// length and prefix, when given.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NamespacedScheme {
+    pub namespace: String,
+    pub version: u16,
+    #[serde(default)]
+    pub case: IdCase,
+    /// Exact length in characters
+    #[serde(default)]
+    pub length: Option<usize>,
+    /// Prefix every identifier starts with, after case folding
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
+impl NamespacedScheme {
+    /// International Securities Identification Numbers, upper-cased
+    pub fn isin() -> Self {
+        Self { namespace: "isin".to_string(), version: 1, case: IdCase::Upper, length: Some(12), prefix: None }
+    }
+
+    /// Decentralized identifiers, taken as written
+    pub fn did() -> Self {
+        Self { namespace: "did".to_string(), version: 1, case: IdCase::Preserve, length: None, prefix: Some("did:".to_string()) }
+    }
+}
+
+impl IdScheme for NamespacedScheme {
+    fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    fn version(&self) -> u16 {
+        self.version
+    }
+
+    fn normalize(&self, identifier: &str) -> Result<String, String> {
+        let identifier = identifier.trim();
+        if identifier.is_empty() {
+            return Err("identifier is empty".to_string());
+        }
+        let identifier = match self.case {
+            IdCase::Preserve => identifier.to_string(),
+            IdCase::Upper => identifier.to_uppercase(),
+            IdCase::Lower => identifier.to_lowercase(),
+        };
+        if let Some(length) = self.length {
+            let actual = identifier.chars().count();
+            if actual != length {
+                return Err(alloc::format!("expected {} characters, got {}", length, actual));
+            }
+        }
+        if let Some(prefix) = &self.prefix {
+            if !identifier.starts_with(prefix.as_str()) {
+                return Err(alloc::format!("must start with {}", prefix));
+            }
+        }
+        Ok(identifier)
+    }
+}
+
+/// Registered ID schemes, by namespace and version
+///
+/// Deriving without a version uses a namespace's latest one.
+#[derive(Default)]
+pub struct IdDerivationRegistry {
+    schemes: BTreeMap<(String, u16), Box<dyn IdScheme>>,
+}
+
+impl IdDerivationRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry with the built-in ISIN and DID schemes
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        for scheme in [NamespacedScheme::isin(), NamespacedScheme::did()] {
+            registry.register(Box::new(scheme)).expect("Built-in ID schemes are distinct");
+        }
+        registry
+    }
+
+    /// Add a scheme, refusing to replace one with the same namespace and version
+    pub fn register(&mut self, scheme: Box<dyn IdScheme>) -> Result<(), IdDerivationError> {
+        let key = (scheme.namespace().to_string(), scheme.version());
+        if self.schemes.contains_key(&key) {
+            return Err(IdDerivationError::AlreadyRegistered { namespace: key.0, version: key.1 });
+        }
+        self.schemes.insert(key, scheme);
+        Ok(())
+    }
+
+    /// Namespaces and versions registered, in order
+    pub fn schemes(&self) -> Vec<(String, u16)> {
+        self.schemes.keys().cloned().collect()
+    }
+
+    /// Latest version registered for `namespace`
+    pub fn latest_version(&self, namespace: &str) -> Option<u16> {
+        self.schemes
+            .keys()
+            .filter(|(registered, _)| registered == namespace)
+            .map(|(_, version)| *version)
+            .max()
+    }
+
+    /// ID of `identifier` under the latest version of `namespace`
+    pub fn derive(&self, namespace: &str, identifier: &str) -> Result<UnitsObjectId, IdDerivationError> {
+        let version = self
+            .latest_version(namespace)
+            .ok_or_else(|| IdDerivationError::UnknownNamespace(namespace.to_string()))?;
+        self.derive_versioned(namespace, version, identifier)
+    }
+
+    /// ID of `identifier` under a given version of `namespace`
+    pub fn derive_versioned(&self, namespace: &str, version: u16, identifier: &str) -> Result<UnitsObjectId, IdDerivationError> {
+        let scheme = self.schemes.get(&(namespace.to_string(), version)).ok_or_else(|| {
+            if self.latest_version(namespace).is_some() {
+                IdDerivationError::UnknownVersion { namespace: namespace.to_string(), version }
+            } else {
+                IdDerivationError::UnknownNamespace(namespace.to_string())
+            }
+        })?;
+        let canonical = scheme.normalize(identifier).map_err(|reason| IdDerivationError::InvalidIdentifier {
+            namespace: namespace.to_string(),
+            reason,
+        })?;
+        UnitsObjectId::derive_external(namespace, version, &canonical)
+            .map(|(id, _)| id)
+            .ok_or(IdDerivationError::NoValidId)
+    }
 }
 
 #[cfg(test)]
@@ -167,6 +420,28 @@ pub mod tests {
         assert_ne!(id2, UnitsObjectId::default());
     }
 
+    #[test]
+    fn test_prefix_range() {
+        use core::ops::RangeBounds;
+
+        let range = UnitsObjectId::prefix_range(&[1, 0xff]).unwrap();
+        let mut inside = [0xff; 32];
+        inside[0] = 1;
+        assert!(range.contains(&UnitsObjectId::new(inside)));
+        assert!(!range.contains(&UnitsObjectId::new([1; 32])));
+        let mut start = [0; 32];
+        start[..2].copy_from_slice(&[1, 0xff]);
+        assert!(range.contains(&UnitsObjectId::new(start)));
+        let mut next = [0; 32];
+        next[0] = 2;
+        assert_eq!(range.1, Bound::Excluded(UnitsObjectId::new(next)));
+
+        // All-0xff and empty prefixes run to the last ID
+        assert_eq!(UnitsObjectId::prefix_range(&[0xff, 0xff]).unwrap().1, Bound::Unbounded);
+        assert_eq!(UnitsObjectId::prefix_range(&[]).unwrap().1, Bound::Unbounded);
+        assert!(UnitsObjectId::prefix_range(&[0; 33]).is_none());
+    }
+
     #[test]
     fn test_default_id() {
         let default_id = UnitsObjectId::default();
@@ -229,6 +504,37 @@ pub mod tests {
         assert_ne!(id, id2);
     }
 
+    #[test]
+    fn test_external_ids_are_canonical_and_namespaced() {
+        let mut registry = IdDerivationRegistry::with_defaults();
+
+        // Case and surrounding whitespace do not change an ISIN's ID
+        let isin = registry.derive("isin", "US0378331005").unwrap();
+        assert_eq!(registry.derive("isin", " us0378331005 ").unwrap(), isin);
+        assert!(UnitsObjectId::is_off_curve(&isin));
+        assert!(matches!(registry.derive("isin", "US03783"), Err(IdDerivationError::InvalidIdentifier { .. })));
+        assert!(matches!(registry.derive("did", "example:1"), Err(IdDerivationError::InvalidIdentifier { .. })));
+        assert!(matches!(registry.derive("lei", "x"), Err(IdDerivationError::UnknownNamespace(_))));
+
+        // The same identifier maps apart across namespaces and versions
+        let ticker = NamespacedScheme { namespace: "ticker".to_string(), version: 1, case: IdCase::Upper, length: None, prefix: None };
+        registry.register(Box::new(ticker.clone())).unwrap();
+        assert!(matches!(registry.register(Box::new(ticker.clone())), Err(IdDerivationError::AlreadyRegistered { .. })));
+        let v1 = registry.derive("ticker", "US0378331005").unwrap();
+        assert_ne!(v1, isin);
+        registry.register(Box::new(NamespacedScheme { version: 2, ..ticker })).unwrap();
+        let v2 = registry.derive("ticker", "US0378331005").unwrap();
+        assert_ne!(v1, v2);
+        assert_eq!(registry.derive_versioned("ticker", 1, "US0378331005").unwrap(), v1);
+        assert!(matches!(registry.derive_versioned("ticker", 3, "x"), Err(IdDerivationError::UnknownVersion { .. })));
+
+        // Length prefixes keep namespace and identifier from running together
+        assert_ne!(
+            UnitsObjectId::derive_external("ab", 1, "c").unwrap().0,
+            UnitsObjectId::derive_external("a", 1, "bc").unwrap().0
+        );
+    }
+
     #[test]
     fn test_try_find_uid() {
         let seed = b"try_find_test";
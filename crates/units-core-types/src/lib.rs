@@ -1,16 +1,51 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Core type definitions for UNITS
+//!
+//! With the default `std` feature this crate also defines the storage,
+//! runtime and VM traits the node is built on. Without it, only ids,
+//! objects, transactions and proofs are built, on `core` and `alloc`, so
+//! kernel modules can share these types with the host.
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod authorizers;
 pub mod constants;
+pub mod ed25519;
+#[cfg(feature = "std")]
+pub mod effect_processor;
+#[cfg(feature = "std")]
 pub mod error;
+#[cfg(feature = "std")]
+pub mod executable_cache;
+#[cfg(feature = "std")]
+pub mod fees;
+#[cfg(feature = "std")]
+pub mod ffi;
 pub mod id;
+#[cfg(feature = "std")]
 pub mod locks;
+#[cfg(feature = "std")]
+pub mod module_registry;
 pub mod objects;
 pub mod proofs;
+#[cfg(feature = "std")]
+pub mod rent;
 pub mod transaction;
+#[cfg(feature = "std")]
 pub mod scheduler;
+#[cfg(feature = "std")]
 pub mod storage;
+#[cfg(feature = "std")]
 pub mod runtime;
+#[cfg(feature = "std")]
 pub mod vm_executor;
+#[cfg(feature = "std")]
 pub mod transaction_manager;
+#[cfg(feature = "std")]
 pub mod verification;
+#[cfg(feature = "std")]
 pub mod units_storage_trait;
 
 // Re-export the main types for convenience
@@ -19,17 +54,21 @@ pub use constants::{
     TOKEN_CONTROLLER_ID,
     ACCOUNT_CONTROLLER_ID,
     MODULE_MANAGER_ID,
+    ATTEST_CONTROLLER_ID,
     is_system_controller,
 };
+#[cfg(feature = "std")]
 pub use error::StorageError;
-pub use id::UnitsObjectId;
+pub use id::{IdCase, IdDerivationError, IdDerivationRegistry, IdScheme, NamespacedScheme, UnitsObjectId};
 pub use objects::{
     VMType,
     ObjectType,
     UnitsObject,
+    VersionedObject,
 };
 
 // Re-export lock types
+#[cfg(feature = "std")]
 pub use locks::{
     AccessIntent,
     LockInfo,
@@ -42,57 +81,133 @@ pub use locks::{
 pub use transaction::{
     CommitmentLevel,
     ConflictResult,
+    ExecutionFailure,
+    ExecutionMetrics,
+    ExpectedVersion,
+    FeePayment,
+    FeeSponsorship,
     Instruction,
+    MAX_MEMO_LEN,
+    ObjectRead,
+    ReceiptAnnotation,
     Transaction,
     TransactionEffect,
+    TransactionEvent,
     TransactionHash,
     TransactionReceipt,
 };
 
 // Re-export scheduler types
+#[cfg(feature = "std")]
 pub use scheduler::{
     ConflictChecker,
     BasicConflictChecker,
+    AccessSet,
+    ParallelScheduler,
+    ParallelSchedulerConfig,
+    SchedulePlan,
+    AdaptiveBatchConfig,
+    AdaptiveBatchSizer,
+    Admission,
+    DEFAULT_FEE_MARKET_WINDOW,
+    FeeDistribution,
+    FeeEstimate,
+    FeeMarket,
+    SlotFeeStats,
 };
 
 // Re-export proof types
 pub use proofs::{
+    HashAlgorithm,
     Proof,
     SlotNumber,
     StateProof,
     UnitsObjectProof,
     VerificationResult,
     MerkleNode,
-    ProofStorageError,
+};
+#[cfg(feature = "std")]
+pub use proofs::ProofStorageError;
+
+// Re-export storage rent types
+#[cfg(feature = "std")]
+pub use rent::{
+    DEPOSIT_LEDGER_ID,
+    DepositLedger,
+    DepositRecord,
+    StorageRentConfig,
+};
+
+// Re-export account authorizer types
+#[cfg(feature = "std")]
+pub use authorizers::{AuthorizationRequest, AuthorizerRegistry, AUTHORIZER_REGISTRY_ID, AUTHORIZE_FUNCTION};
+
+// Re-export fee ledger types
+#[cfg(feature = "std")]
+pub use fees::{FeeLedger, FEE_LEDGER_ID};
+
+// Re-export module registry types
+#[cfg(feature = "std")]
+pub use module_registry::{
+    MODULE_ARTIFACT_MAGIC,
+    MODULE_ARTIFACT_VERSION,
+    MODULE_REGISTRY_ID,
+    ModuleAbi,
+    ModuleArtifact,
+    ModuleEntry,
+    ModuleErrorCode,
+    ModuleRegistry,
+    PrefetchRule,
+    PrefetchSeed,
+    ResourceClass,
 };
 
 // Re-export storage traits
+#[cfg(feature = "std")]
 pub use storage::{
+    BatchOp,
     ObjectStorage,
     HistoricalStorage,
     ProofStorage,
     WriteAheadLog,
+    WalTail,
     ReceiptStorage,
+    ReceiptPage,
+    SlotReceiptsIter,
     LockManager,
+    StorageObserver,
+    ObservedProof,
     UnitsStorageStruct,
 };
 
 // Re-export unified storage trait
+#[cfg(feature = "std")]
 pub use units_storage_trait::UnitsStorage;
 
 // Re-export runtime traits
-pub use runtime::Runtime;
+#[cfg(feature = "std")]
+pub use runtime::{ObjectLoader, Runtime, TransactionView, VersionLoader};
+#[cfg(feature = "std")]
+pub use executable_cache::{ExecutableCache, ExecutableCacheStats, DEFAULT_EXECUTABLE_CACHE_CAPACITY};
+#[cfg(feature = "std")]
+pub use effect_processor::{apply_effect_processors, EffectProcessor, EffectVerdict};
 
 // Re-export VM executor traits and types
+#[cfg(feature = "std")]
 pub use vm_executor::{
     VMExecutor,
+    ContextLimits,
+    ContextStream,
     ExecutionContext,
+    ExecutionOutput,
+    KernelEvent,
     ObjectEffect,
     VMExecutionError,
     validate_object_effects,
 };
 
 // Re-export transaction manager traits and types
+#[cfg(feature = "std")]
 pub use transaction_manager::{
     TransactionManager,
     TransactionFilter,
@@ -100,6 +215,5 @@ pub use transaction_manager::{
 };
 
 // Re-export verification traits
+#[cfg(feature = "std")]
 pub use verification::Verifier;
-
-
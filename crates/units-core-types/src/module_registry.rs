@@ -0,0 +1,450 @@
+//! Registry of deployed controllers
+//!
+//! The module manager keeps a single system object listing every deployed
+//! executable controller with its code hash, version and ABI location, so
+//! clients can discover what is deployed on a node without scanning storage.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::constants::MODULE_MANAGER_ID;
+use crate::error::StorageError;
+use crate::id::UnitsObjectId;
+use crate::objects::{UnitsObject, VMType};
+use crate::transaction::Instruction;
+
+/// Well-known ID of the module registry object
+pub const MODULE_REGISTRY_ID: UnitsObjectId = UnitsObjectId::new([0x3d; 32]);
+
+/// Registry entry for one deployed controller
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleEntry {
+    pub controller_id: UnitsObjectId,
+    /// Hash of the controller's executable code
+    pub code_hash: [u8; 32],
+    pub vm_type: VMType,
+    /// Starts at 1 and increases each time the code changes
+    pub version: u64,
+    /// Where clients can fetch the controller's ABI, if published
+    pub abi_location: Option<String>,
+    /// Slot of the first deployment
+    pub deployed_at_slot: u64,
+    /// Slot of the deployment that produced the current code
+    pub updated_at_slot: u64,
+    /// Objects each function reads beyond its targets, by function name
+    #[serde(default)]
+    pub prefetch: BTreeMap<String, Vec<PrefetchRule>>,
+    /// Meaning of the controller's non-zero exit codes
+    #[serde(default)]
+    pub error_codes: BTreeMap<u32, ModuleErrorCode>,
+    /// Execution budgets the controller runs with
+    #[serde(default)]
+    pub resource_class: ResourceClass,
+}
+
+/// Preset execution budget of a controller
+///
+/// Each VM maps the classes to its own memory, instruction and time
+/// limits. Controllers run as [`ResourceClass::Standard`] until an admin
+/// assigns another class.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ResourceClass {
+    Small,
+    #[default]
+    Standard,
+    Large,
+}
+
+/// Registered meaning of one controller exit code
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleErrorCode {
+    /// Name of the module's constant, such as `ERROR_INVALID_USERNAME`
+    pub name: String,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Where one seed of a [`PrefetchRule`] comes from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PrefetchSeed {
+    /// Fixed bytes, such as a domain tag
+    Literal(Vec<u8>),
+    /// ID of the instruction's target object at this index
+    Target(usize),
+    /// ID of the invoked controller
+    Controller,
+}
+
+/// Object a function implicitly reads, derived from its instruction
+///
+/// The ID is `UnitsObjectId::find_uid` over the seeds, so a rule such as
+/// `[Target(0), Target(1)]` names the object derived from the first two
+/// targets, like the balance of an owner in a token.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrefetchRule {
+    pub seeds: Vec<PrefetchSeed>,
+}
+
+impl PrefetchRule {
+    /// ID this rule implies for `instruction`, or None if a seed names a missing target
+    pub fn derive(&self, instruction: &Instruction) -> Option<UnitsObjectId> {
+        let seeds = self
+            .seeds
+            .iter()
+            .map(|seed| match seed {
+                PrefetchSeed::Literal(bytes) => Some(bytes.as_slice()),
+                PrefetchSeed::Target(index) => instruction.target_objects.get(*index).map(|id| id.bytes()),
+                PrefetchSeed::Controller => Some(instruction.controller_id.bytes()),
+            })
+            .collect::<Option<Vec<&[u8]>>>()?;
+        UnitsObjectId::try_find_uid(&seeds).map(|(id, _)| id)
+    }
+}
+
+/// Leading bytes of an encoded [`ModuleArtifact`]
+pub const MODULE_ARTIFACT_MAGIC: &[u8; 4] = b"UMOD";
+
+/// Encoding version of [`ModuleArtifact`]s this build writes and reads
+pub const MODULE_ARTIFACT_VERSION: u32 = 1;
+
+/// ABI metadata shipped with a module's code
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleAbi {
+    /// Functions the module handles, by instruction `target_function`
+    #[serde(default)]
+    pub functions: Vec<String>,
+    /// Where clients can fetch the full ABI, if published
+    #[serde(default)]
+    pub abi_location: Option<String>,
+    /// Objects each function reads beyond its targets, by function name
+    #[serde(default)]
+    pub prefetch: BTreeMap<String, Vec<PrefetchRule>>,
+    /// Meaning of the module's non-zero exit codes
+    #[serde(default)]
+    pub error_codes: BTreeMap<u32, ModuleErrorCode>,
+}
+
+/// Deployable package of a built kernel module
+///
+/// Produced by `units-build` from a validated, stripped ELF. The module
+/// manager deploys the code as an executable controller and registers its
+/// ABI metadata alongside.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleArtifact {
+    pub vm_type: VMType,
+    /// Executable image, as stored in the controller object
+    pub code: Vec<u8>,
+    /// Hash of `code`, as recorded in the controller's [`ModuleEntry`]
+    pub code_hash: [u8; 32],
+    pub abi: ModuleAbi,
+}
+
+impl ModuleArtifact {
+    pub fn new(vm_type: VMType, code: Vec<u8>, abi: ModuleAbi) -> Self {
+        let code_hash = *blake3::hash(&code).as_bytes();
+        Self { vm_type, code, code_hash, abi }
+    }
+
+    /// Encode as magic, version and bincode body
+    pub fn encode(&self) -> Result<Vec<u8>, StorageError> {
+        let body = bincode::serialize(self)
+            .map_err(|e| StorageError::Serialization(format!("Module artifact: {}", e)))?;
+        let mut bytes = Vec::with_capacity(8 + body.len());
+        bytes.extend_from_slice(MODULE_ARTIFACT_MAGIC);
+        bytes.extend_from_slice(&MODULE_ARTIFACT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&body);
+        Ok(bytes)
+    }
+
+    /// Decode an encoded artifact, rejecting one whose code does not match its hash
+    pub fn decode(bytes: &[u8]) -> Result<Self, StorageError> {
+        let invalid = |reason: String| StorageError::Serialization(format!("Invalid module artifact: {}", reason));
+        if bytes.len() < 8 || &bytes[..4] != MODULE_ARTIFACT_MAGIC {
+            return Err(invalid("missing magic".to_string()));
+        }
+        let version = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+        if version != MODULE_ARTIFACT_VERSION {
+            return Err(invalid(format!("unsupported version {}", version)));
+        }
+        let artifact: Self = bincode::deserialize(&bytes[8..]).map_err(|e| invalid(e.to_string()))?;
+        artifact.verify()?;
+        Ok(artifact)
+    }
+
+    /// Check the code against the recorded hash
+    pub fn verify(&self) -> Result<(), StorageError> {
+        if *blake3::hash(&self.code).as_bytes() != self.code_hash {
+            return Err(StorageError::Serialization(
+                "Invalid module artifact: code does not match its hash".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Executable controller object at `controller_id` running this module
+    pub fn to_controller(&self, controller_id: UnitsObjectId) -> UnitsObject {
+        UnitsObject::new_executable(controller_id, controller_id, self.vm_type, self.code.clone())
+    }
+}
+
+/// Deployed controllers keyed by ID
+///
+/// Stored as the data of the object at [`MODULE_REGISTRY_ID`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleRegistry {
+    pub modules: BTreeMap<UnitsObjectId, ModuleEntry>,
+}
+
+impl ModuleRegistry {
+    /// Decode a registry from its storage object
+    pub fn from_object(object: &UnitsObject) -> Result<Self, StorageError> {
+        bincode::deserialize(object.data())
+            .map_err(|e| StorageError::Serialization(format!("Invalid module registry: {}", e)))
+    }
+
+    /// Encode the registry as its storage object
+    pub fn to_object(&self) -> Result<UnitsObject, StorageError> {
+        let data = bincode::serialize(self)
+            .map_err(|e| StorageError::Serialization(format!("Module registry: {}", e)))?;
+        Ok(UnitsObject::new_data(MODULE_REGISTRY_ID, MODULE_MANAGER_ID, data))
+    }
+
+    /// Record the deployment of an executable controller at `slot`
+    ///
+    /// Redeploying identical code leaves the entry unchanged; new code bumps
+    /// the version. Returns `None` if `controller` is not executable.
+    pub fn record_deployment(&mut self, controller: &UnitsObject, slot: u64) -> Option<&ModuleEntry> {
+        let vm_type = controller.vm_type()?;
+        let code_hash = *blake3::hash(controller.data()).as_bytes();
+
+        let entry = self
+            .modules
+            .entry(*controller.id())
+            .and_modify(|entry| {
+                if entry.code_hash != code_hash || entry.vm_type != vm_type {
+                    entry.code_hash = code_hash;
+                    entry.vm_type = vm_type;
+                    entry.version += 1;
+                    entry.updated_at_slot = slot;
+                }
+            })
+            .or_insert(ModuleEntry {
+                controller_id: *controller.id(),
+                code_hash,
+                vm_type,
+                version: 1,
+                abi_location: None,
+                deployed_at_slot: slot,
+                updated_at_slot: slot,
+                prefetch: BTreeMap::new(),
+                error_codes: BTreeMap::new(),
+                resource_class: ResourceClass::default(),
+            });
+        Some(entry)
+    }
+
+    /// Record the deployment of `artifact` as `controller` and apply its ABI metadata
+    ///
+    /// Prefetch rules the artifact declares replace those of the same
+    /// functions; rules for other functions are kept.
+    pub fn record_artifact(&mut self, controller: &UnitsObject, artifact: &ModuleArtifact, slot: u64) -> Option<&ModuleEntry> {
+        let controller_id = *controller.id();
+        self.record_deployment(controller, slot)?;
+        let entry = self.modules.get_mut(&controller_id)?;
+        if artifact.abi.abi_location.is_some() {
+            entry.abi_location = artifact.abi.abi_location.clone();
+        }
+        for (function, rules) in &artifact.abi.prefetch {
+            entry.prefetch.insert(function.clone(), rules.clone());
+        }
+        if !artifact.abi.error_codes.is_empty() {
+            entry.error_codes = artifact.abi.error_codes.clone();
+        }
+        Some(entry)
+    }
+
+    /// Set or clear where a controller's ABI is published
+    ///
+    /// Returns false if the controller is not registered.
+    pub fn set_abi_location(&mut self, controller_id: &UnitsObjectId, location: Option<String>) -> bool {
+        match self.modules.get_mut(controller_id) {
+            Some(entry) => {
+                entry.abi_location = location;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Assign the execution budget of a controller
+    ///
+    /// Returns false if the controller is not registered.
+    pub fn set_resource_class(&mut self, controller_id: &UnitsObjectId, class: ResourceClass) -> bool {
+        match self.modules.get_mut(controller_id) {
+            Some(entry) => {
+                entry.resource_class = class;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Execution budget of a controller; unregistered ones run as standard
+    pub fn resource_class(&self, controller_id: &UnitsObjectId) -> ResourceClass {
+        self.modules.get(controller_id).map(|entry| entry.resource_class).unwrap_or_default()
+    }
+
+    /// Set the prefetch rules of one controller function, clearing them when `rules` is empty
+    ///
+    /// Returns false if the controller is not registered.
+    pub fn set_prefetch_rules(&mut self, controller_id: &UnitsObjectId, function: &str, rules: Vec<PrefetchRule>) -> bool {
+        match self.modules.get_mut(controller_id) {
+            Some(entry) => {
+                if rules.is_empty() {
+                    entry.prefetch.remove(function);
+                } else {
+                    entry.prefetch.insert(function.to_string(), rules);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replace a controller's error code table
+    ///
+    /// Returns false if the controller is not registered.
+    pub fn set_error_codes(&mut self, controller_id: &UnitsObjectId, error_codes: BTreeMap<u32, ModuleErrorCode>) -> bool {
+        match self.modules.get_mut(controller_id) {
+            Some(entry) => {
+                entry.error_codes = error_codes;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Registered meaning of exit `code` of `controller_id`
+    pub fn error_code(&self, controller_id: &UnitsObjectId, code: u32) -> Option<&ModuleErrorCode> {
+        self.modules.get(controller_id)?.error_codes.get(&code)
+    }
+
+    /// IDs the invoked function declares it reads beyond the instruction's targets
+    pub fn prefetch_ids(&self, instruction: &Instruction) -> Vec<UnitsObjectId> {
+        self.modules
+            .get(&instruction.controller_id)
+            .and_then(|entry| entry.prefetch.get(&instruction.target_function))
+            .into_iter()
+            .flatten()
+            .filter_map(|rule| rule.derive(instruction))
+            .collect()
+    }
+
+    /// Remove a controller, returning its entry
+    pub fn remove(&mut self, controller_id: &UnitsObjectId) -> Option<ModuleEntry> {
+        self.modules.remove(controller_id)
+    }
+
+    /// Registered controllers in ID order
+    pub fn entries(&self) -> impl Iterator<Item = &ModuleEntry> {
+        self.modules.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn controller(code: &[u8]) -> UnitsObject {
+        let id = UnitsObjectId::new([5; 32]);
+        UnitsObject::new_executable(id, id, VMType::RiscV, code.to_vec())
+    }
+
+    #[test]
+    fn test_deployments_bump_version_on_code_change() {
+        let mut registry = ModuleRegistry::default();
+        assert_eq!(registry.record_deployment(&controller(b"v1"), 3).unwrap().version, 1);
+
+        // Identical code is not a new version
+        let entry = registry.record_deployment(&controller(b"v1"), 4).unwrap();
+        assert_eq!((entry.version, entry.updated_at_slot), (1, 3));
+
+        assert!(registry.set_abi_location(controller(b"").id(), Some("ipfs://abi".into())));
+        let entry = registry.record_deployment(&controller(b"v2"), 9).unwrap().clone();
+        assert_eq!(entry.version, 2);
+        assert_eq!(entry.deployed_at_slot, 3);
+        assert_eq!(entry.updated_at_slot, 9);
+        assert_eq!(entry.code_hash, *blake3::hash(b"v2").as_bytes());
+        assert_eq!(entry.abi_location.as_deref(), Some("ipfs://abi"));
+
+        // Data objects are not modules
+        let data = UnitsObject::new_data(UnitsObjectId::new([6; 32]), MODULE_MANAGER_ID, vec![]);
+        assert!(registry.record_deployment(&data, 9).is_none());
+
+        let object = registry.to_object().unwrap();
+        assert_eq!(*object.id(), MODULE_REGISTRY_ID);
+        assert_eq!(*object.controller_id(), MODULE_MANAGER_ID);
+        assert_eq!(ModuleRegistry::from_object(&object).unwrap(), registry);
+    }
+
+    #[test]
+    fn test_prefetch_rules_derive_implied_objects() {
+        let mut registry = ModuleRegistry::default();
+        let module = controller(b"v1");
+        registry.record_deployment(&module, 1);
+
+        let token = UnitsObjectId::new([1; 32]);
+        let owner = UnitsObjectId::new([2; 32]);
+        let balance = PrefetchRule {
+            seeds: vec![PrefetchSeed::Literal(b"balance".to_vec()), PrefetchSeed::Target(0), PrefetchSeed::Target(1)],
+        };
+        assert!(registry.set_prefetch_rules(module.id(), "transfer", vec![balance]));
+        assert!(!registry.set_prefetch_rules(&owner, "transfer", vec![]));
+
+        let instruction = Instruction::new(*module.id(), "transfer".to_string(), vec![token, owner], vec![]);
+        let (expected, _) = UnitsObjectId::find_uid(&[b"balance", token.bytes(), owner.bytes()]);
+        assert_eq!(registry.prefetch_ids(&instruction), vec![expected]);
+
+        // Rules survive redeployment, and apply only to their own function
+        registry.record_deployment(&controller(b"v2"), 2);
+        assert_eq!(registry.prefetch_ids(&instruction).len(), 1);
+        let other = Instruction::new(*module.id(), "mint".to_string(), vec![token, owner], vec![]);
+        assert!(registry.prefetch_ids(&other).is_empty());
+
+        // A rule naming a missing target implies nothing
+        let short = Instruction::new(*module.id(), "transfer".to_string(), vec![token], vec![]);
+        assert!(registry.prefetch_ids(&short).is_empty());
+
+        assert!(registry.set_prefetch_rules(module.id(), "transfer", vec![]));
+        assert!(registry.prefetch_ids(&instruction).is_empty());
+    }
+
+    #[test]
+    fn test_module_artifacts_round_trip_and_register_their_abi() {
+        let abi = ModuleAbi {
+            functions: vec!["transfer".to_string()],
+            abi_location: Some("ipfs://abi".to_string()),
+            prefetch: BTreeMap::from([("transfer".to_string(), vec![PrefetchRule { seeds: vec![PrefetchSeed::Target(0)] }])]),
+            error_codes: BTreeMap::from([(1, ModuleErrorCode { name: "ERROR_FROZEN".to_string(), message: None })]),
+        };
+        let artifact = ModuleArtifact::new(VMType::RiscV, b"\x7fELF code".to_vec(), abi);
+        let bytes = artifact.encode().unwrap();
+        assert_eq!(ModuleArtifact::decode(&bytes).unwrap(), artifact);
+
+        // Tampered code no longer matches its hash
+        let mut tampered = artifact.clone();
+        tampered.code.push(0);
+        assert!(ModuleArtifact::decode(&tampered.encode().unwrap()).is_err());
+        assert!(ModuleArtifact::decode(b"UMOD").is_err());
+
+        let mut registry = ModuleRegistry::default();
+        let id = UnitsObjectId::new([7; 32]);
+        let entry = registry.record_artifact(&artifact.to_controller(id), &artifact, 4).unwrap();
+        assert_eq!(entry.code_hash, artifact.code_hash);
+        assert_eq!(entry.abi_location.as_deref(), Some("ipfs://abi"));
+        assert_eq!(entry.prefetch["transfer"].len(), 1);
+        assert_eq!(registry.error_code(&id, 1).unwrap().name, "ERROR_FROZEN");
+    }
+}
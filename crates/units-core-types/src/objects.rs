@@ -1,26 +1,36 @@
+use alloc::vec::Vec;
 use crate::id::UnitsObjectId;
+use borsh::{BorshDeserialize, BorshSerialize};
 use serde::{Deserialize, Serialize};
+use crate::proofs::SlotNumber;
 use crate::Proof;
 
 /// VM types for executable objects
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
 #[non_exhaustive]
 pub enum VMType {
     /// RISC-V ELF shared objects (primary implementation)
     RiscV,
+    /// WebAssembly modules built for wasm32-unknown-unknown
+    Wasm,
 }
 
 /// Object type distinguishing data from executable objects
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
 pub enum ObjectType {
     /// Data object - not executable
     Data,
     /// Executable object with specific VM type
     Executable(VMType),
+    /// Scratch data object removed once `expires_at_slot` is reached
+    ///
+    /// Ephemeral objects are proven on write like any other object, so
+    /// receipts track them, but they are left out of state proofs.
+    Ephemeral { expires_at_slot: SlotNumber },
 }
 
 /// Unified object structure for all UNITS entities
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, BorshSerialize, BorshDeserialize)]
 pub struct UnitsObject {
     /// Unique identifier - how object is indexed in storage
     pub id: UnitsObjectId,
@@ -62,6 +72,21 @@ impl UnitsObject {
         }
     }
 
+    /// Create a scratch object that expires at `expires_at_slot`
+    pub fn new_ephemeral(
+        id: UnitsObjectId,
+        controller_id: UnitsObjectId,
+        data: Vec<u8>,
+        expires_at_slot: SlotNumber,
+    ) -> Self {
+        Self {
+            id,
+            controller_id,
+            object_type: ObjectType::Ephemeral { expires_at_slot },
+            data,
+        }
+    }
+
     /// Get the object ID
     pub fn id(&self) -> &UnitsObjectId {
         &self.id
@@ -87,6 +112,16 @@ impl UnitsObject {
         matches!(self.object_type, ObjectType::Executable(_))
     }
 
+    /// Check if this is an ephemeral scratch object
+    pub fn is_ephemeral(&self) -> bool {
+        matches!(self.object_type, ObjectType::Ephemeral { .. })
+    }
+
+    /// Whether this is an ephemeral object that has expired by `slot`
+    pub fn is_expired(&self, slot: SlotNumber) -> bool {
+        matches!(self.object_type, ObjectType::Ephemeral { expires_at_slot } if expires_at_slot <= slot)
+    }
+
     /// Get VM type if this is an executable object
     pub fn vm_type(&self) -> Option<VMType> {
         match &self.object_type {
@@ -94,6 +129,49 @@ impl UnitsObject {
             _ => None,
         }
     }
+
+    /// Hash of the object's full state
+    ///
+    /// This is the digest object proofs commit to in `object_hash`. The
+    /// hashed bytes are the object's bincode encoding, written out field by
+    /// field so the hash needs neither `std` nor an intermediate buffer.
+    pub fn state_hash(&self) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(self.id.bytes());
+        hasher.update(self.controller_id.bytes());
+        match self.object_type {
+            ObjectType::Data => {
+                hasher.update(&0u32.to_le_bytes());
+            }
+            ObjectType::Executable(vm_type) => {
+                let vm_index: u32 = match vm_type {
+                    VMType::RiscV => 0,
+                    VMType::Wasm => 1,
+                };
+                hasher.update(&1u32.to_le_bytes());
+                hasher.update(&vm_index.to_le_bytes());
+            }
+            ObjectType::Ephemeral { expires_at_slot } => {
+                hasher.update(&2u32.to_le_bytes());
+                hasher.update(&expires_at_slot.to_le_bytes());
+            }
+        }
+        hasher.update(&(self.data.len() as u64).to_le_bytes());
+        hasher.update(&self.data);
+        *hasher.finalize().as_bytes()
+    }
+}
+
+/// Object together with its write version, as returned to clients
+///
+/// The version is serialized alongside the object's own fields, so readers
+/// that only know `UnitsObject` keep working.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VersionedObject {
+    #[serde(flatten)]
+    pub object: UnitsObject,
+    /// Number of writes applied to the object; see `ObjectStorage::version`
+    pub version: u64,
 }
 
 impl Proof for UnitsObject {
@@ -147,14 +225,46 @@ mod tests {
 
     #[test]
     fn test_vm_types() {
-        // Test RISC-V VM type (only supported type)
         let id = UnitsObjectId::new([1; 32]);
         let controller_id = UnitsObjectId::new([2; 32]);
         let bytecode = vec![1, 2, 3, 4];
 
-        let riscv_obj = UnitsObject::new_executable(id, controller_id, VMType::RiscV, bytecode);
+        let riscv_obj = UnitsObject::new_executable(id, controller_id, VMType::RiscV, bytecode.clone());
         assert_eq!(riscv_obj.vm_type(), Some(VMType::RiscV));
         assert!(riscv_obj.is_executable());
         assert!(!riscv_obj.is_data());
+
+        let wasm_obj = UnitsObject::new_executable(id, controller_id, VMType::Wasm, bytecode);
+        assert_eq!(wasm_obj.vm_type(), Some(VMType::Wasm));
+        assert_ne!(wasm_obj.state_hash(), riscv_obj.state_hash());
+    }
+
+    #[test]
+    fn test_state_hash_matches_bincode_encoding() {
+        let id = UnitsObjectId::new([1; 32]);
+        let controller_id = UnitsObjectId::new([2; 32]);
+        let objects = [
+            UnitsObject::new_data(id, controller_id, vec![1, 2, 3]),
+            UnitsObject::new_executable(id, controller_id, VMType::RiscV, vec![0x7f]),
+            UnitsObject::new_executable(id, controller_id, VMType::Wasm, vec![0x00]),
+            UnitsObject::new_ephemeral(id, controller_id, vec![], 42),
+        ];
+        for object in objects {
+            let encoded = bincode::serialize(&object).unwrap();
+            assert_eq!(object.state_hash(), *blake3::hash(&encoded).as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_ephemeral_object() {
+        let id = UnitsObjectId::new([1; 32]);
+        let controller_id = UnitsObjectId::new([2; 32]);
+
+        let scratch = UnitsObject::new_ephemeral(id, controller_id, vec![1], 10);
+        assert!(scratch.is_ephemeral());
+        assert!(!scratch.is_data() && !scratch.is_executable());
+        assert!(!scratch.is_expired(9));
+        assert!(scratch.is_expired(10));
+        assert!(!UnitsObject::new_data(id, controller_id, vec![]).is_expired(u64::MAX));
     }
 }
@@ -1,3 +1,5 @@
+use alloc::string::String;
+use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 use crate::UnitsObjectId;
 
@@ -10,6 +12,31 @@ pub trait Proof: Serialize {
 /// Slot number type (represents points in time)
 pub type SlotNumber = u64;
 
+/// Hash function a deployment computes its object hashes, proof data and Merkle roots with
+///
+/// Object and state proofs do not carry it, keeping their stored encoding
+/// unchanged; state proofs record it in their proof data instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HashAlgorithm {
+    #[default]
+    Blake3,
+    Sha256,
+    /// Ethereum's Keccak-256, for roots checked by EVM contracts
+    Keccak256,
+}
+
+impl HashAlgorithm {
+    /// Stable one-byte tag of the algorithm
+    pub fn tag(&self) -> u8 {
+        match self {
+            Self::Blake3 => 0,
+            Self::Sha256 => 1,
+            Self::Keccak256 => 2,
+        }
+    }
+}
+
 /// A cryptographic proof for a UNITS object
 ///
 /// This proof commits to the state of a UnitsObject at a particular slot,
@@ -163,6 +190,9 @@ pub enum VerificationResult {
 
     /// Missing data needed to complete verification
     MissingData(String),
+
+    /// The proof's signature is missing, malformed, or not from a trusted signer
+    InvalidSignature(String),
 }
 
 /// Merkle tree node for inclusion proofs
@@ -173,6 +203,7 @@ pub struct MerkleNode {
 }
 
 /// Storage error type for proof operations
+#[cfg(feature = "std")]
 #[derive(Debug, thiserror::Error)]
 pub enum ProofStorageError {
     #[error("Serialization error: {0}")]
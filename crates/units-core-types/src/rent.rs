@@ -0,0 +1,300 @@
+//! Storage rent / deposit accounting for the UNITS system
+//!
+//! When enabled, every live object must be backed by a deposit proportional
+//! to its size. Deposits are held in a single ledger object and refunded to
+//! the original payer when the object shrinks or is deleted.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::constants::SYSTEM_LOADER_ID;
+use crate::id::UnitsObjectId;
+use crate::objects::UnitsObject;
+use crate::vm_executor::{ObjectEffect, VMExecutionError};
+
+/// Well-known ID of the deposit ledger object
+pub const DEPOSIT_LEDGER_ID: UnitsObjectId = UnitsObjectId::new([0xde; 32]);
+
+/// Pricing parameters for storage deposits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageRentConfig {
+    /// Flat deposit charged for every object regardless of size
+    pub base_deposit: u64,
+    /// Additional deposit charged per byte of object data
+    pub deposit_per_byte: u64,
+}
+
+impl StorageRentConfig {
+    /// Create a new rent configuration
+    pub fn new(base_deposit: u64, deposit_per_byte: u64) -> Self {
+        Self {
+            base_deposit,
+            deposit_per_byte,
+        }
+    }
+
+    /// Deposit required to keep the given object alive
+    pub fn deposit_for(&self, object: &UnitsObject) -> u64 {
+        self.deposit_per_byte
+            .saturating_mul(object.data.len() as u64)
+            .saturating_add(self.base_deposit)
+    }
+
+    /// Charge and refund deposits for a set of effects
+    ///
+    /// A new object's deposit is paid by its owner, the controller recorded
+    /// on the object, whichever controller created it. Growth is charged to
+    /// whoever opened the deposit. Returns the effect that updates the ledger
+    /// object so it can be applied alongside `effects`, or `None` when no
+    /// deposit changed, as when effects keep every object's size.
+    pub fn charge_effects(
+        &self,
+        effects: &[ObjectEffect],
+        ledger_object: Option<&UnitsObject>,
+    ) -> Result<Option<ObjectEffect>, VMExecutionError> {
+        let mut ledger = match ledger_object {
+            Some(object) => DepositLedger::from_object(object)?,
+            None => DepositLedger::default(),
+        };
+        let unchanged = ledger.clone();
+
+        for effect in effects {
+            if effect.object_id == DEPOSIT_LEDGER_ID {
+                return Err(VMExecutionError::ControllerValidationFailed(
+                    "Controllers cannot modify the deposit ledger".into(),
+                ));
+            }
+            let required = effect.after_image.as_ref().map_or(0, |obj| self.deposit_for(obj));
+            let payer = match (ledger.deposits.get(&effect.object_id), &effect.after_image) {
+                (Some(record), _) => record.payer,
+                (None, Some(object)) => object.controller_id,
+                // Nothing held and nothing required
+                (None, None) => continue,
+            };
+            ledger.settle(effect.object_id, required, payer)?;
+        }
+
+        if ledger == unchanged {
+            return Ok(None);
+        }
+        let after = ledger.to_object()?;
+        Ok(Some(match ledger_object {
+            Some(before) => ObjectEffect::modification(before.clone(), after),
+            None => ObjectEffect::creation(after),
+        }))
+    }
+}
+
+/// Deposit held against a single live object
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DepositRecord {
+    /// Account that opened the deposit and receives refunds
+    pub payer: UnitsObjectId,
+    /// Amount currently held
+    pub amount: u64,
+}
+
+/// Ledger tracking payer balances and per-object deposits
+///
+/// Stored as the data of the object at [`DEPOSIT_LEDGER_ID`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DepositLedger {
+    /// Funds available to each payer for new deposits
+    pub balances: BTreeMap<UnitsObjectId, u64>,
+    /// Deposits currently held against live objects
+    pub deposits: BTreeMap<UnitsObjectId, DepositRecord>,
+}
+
+impl DepositLedger {
+    /// Decode a ledger from its storage object
+    pub fn from_object(object: &UnitsObject) -> Result<Self, VMExecutionError> {
+        bincode::deserialize(object.data())
+            .map_err(|e| VMExecutionError::SerializationError(format!("Invalid deposit ledger: {}", e)))
+    }
+
+    /// Encode the ledger as its storage object
+    pub fn to_object(&self) -> Result<UnitsObject, VMExecutionError> {
+        let data = bincode::serialize(self)
+            .map_err(|e| VMExecutionError::SerializationError(format!("Deposit ledger: {}", e)))?;
+        Ok(UnitsObject::new_data(DEPOSIT_LEDGER_ID, SYSTEM_LOADER_ID, data))
+    }
+
+    /// Credit funds to a payer's available balance
+    pub fn fund(&mut self, payer: UnitsObjectId, amount: u64) {
+        let balance = self.balances.entry(payer).or_insert(0);
+        *balance = balance.saturating_add(amount);
+    }
+
+    /// Available balance for a payer
+    pub fn balance_of(&self, payer: &UnitsObjectId) -> u64 {
+        self.balances.get(payer).copied().unwrap_or(0)
+    }
+
+    /// Deposit currently held for an object
+    pub fn deposit_held(&self, object_id: &UnitsObjectId) -> u64 {
+        self.deposits.get(object_id).map_or(0, |record| record.amount)
+    }
+
+    /// Adjust the deposit held for `object_id` to `required`
+    ///
+    /// Increases are charged to `payer`; decreases are refunded to whoever
+    /// opened the deposit. A `required` of zero closes the deposit.
+    pub fn settle(
+        &mut self,
+        object_id: UnitsObjectId,
+        required: u64,
+        payer: UnitsObjectId,
+    ) -> Result<(), VMExecutionError> {
+        let held = self.deposit_held(&object_id);
+
+        match required.cmp(&held) {
+            Ordering::Greater => {
+                let shortfall = required - held;
+                let available = self.balance_of(&payer);
+                if available < shortfall {
+                    return Err(VMExecutionError::InsufficientDeposit(format!(
+                        "object {} requires {} more, payer {} has {}",
+                        object_id, shortfall, payer, available
+                    )));
+                }
+                self.balances.insert(payer, available - shortfall);
+                self.deposits
+                    .entry(object_id)
+                    .or_insert(DepositRecord { payer, amount: 0 })
+                    .amount = required;
+            }
+            Ordering::Less => {
+                let record = self.deposits[&object_id];
+                self.fund(record.payer, held - required);
+                if required == 0 {
+                    self.deposits.remove(&object_id);
+                } else {
+                    self.deposits.insert(object_id, DepositRecord { amount: required, ..record });
+                }
+            }
+            Ordering::Equal => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(id: u8, size: usize) -> UnitsObject {
+        UnitsObject::new_data(UnitsObjectId::new([id; 32]), UnitsObjectId::new([9; 32]), vec![0; size])
+    }
+
+    fn funded_ledger(payer: UnitsObjectId, amount: u64) -> UnitsObject {
+        let mut ledger = DepositLedger::default();
+        ledger.fund(payer, amount);
+        ledger.to_object().unwrap()
+    }
+
+    #[test]
+    fn test_deposit_for_size() {
+        let config = StorageRentConfig::new(10, 2);
+        assert_eq!(config.deposit_for(&object(1, 0)), 10);
+        assert_eq!(config.deposit_for(&object(1, 100)), 210);
+    }
+
+    #[test]
+    fn test_creation_charges_and_deletion_refunds() {
+        let config = StorageRentConfig::new(10, 1);
+        let owner = UnitsObjectId::new([9; 32]);
+        let ledger_obj = funded_ledger(owner, 100);
+        let obj = object(1, 40);
+
+        let effect = config
+            .charge_effects(&[ObjectEffect::creation(obj.clone())], Some(&ledger_obj))
+            .unwrap()
+            .unwrap();
+        let ledger_obj = effect.after_image.unwrap();
+        let ledger = DepositLedger::from_object(&ledger_obj).unwrap();
+        assert_eq!(ledger.balance_of(&owner), 50);
+        assert_eq!(ledger.deposit_held(obj.id()), 50);
+
+        let effect = config
+            .charge_effects(&[ObjectEffect::deletion(obj.clone())], Some(&ledger_obj))
+            .unwrap()
+            .unwrap();
+        let ledger = DepositLedger::from_object(effect.after_image.as_ref().unwrap()).unwrap();
+        assert_eq!(ledger.balance_of(&owner), 100);
+        assert_eq!(ledger.deposit_held(obj.id()), 0);
+    }
+
+    #[test]
+    fn test_creator_pays_for_growth() {
+        let config = StorageRentConfig::new(0, 1);
+        let (creator, owner) = (UnitsObjectId::new([7; 32]), UnitsObjectId::new([9; 32]));
+        let mut ledger = DepositLedger::default();
+        ledger.fund(creator, 100);
+        ledger.fund(owner, 100);
+        ledger.settle(UnitsObjectId::new([1; 32]), 10, creator).unwrap();
+        let ledger_obj = ledger.to_object().unwrap();
+
+        // The object is owned by another account, yet growth stays with its creator
+        let growth = ObjectEffect::modification(object(1, 10), object(1, 30));
+        let effect = config.charge_effects(&[growth], Some(&ledger_obj)).unwrap().unwrap();
+        let ledger = DepositLedger::from_object(effect.after_image.as_ref().unwrap()).unwrap();
+        assert_eq!((ledger.balance_of(&creator), ledger.balance_of(&owner)), (70, 100));
+        assert_eq!(ledger.deposit_held(&UnitsObjectId::new([1; 32])), 30);
+    }
+
+    #[test]
+    fn test_unchanged_deposits_leave_ledger_alone() {
+        let config = StorageRentConfig::new(0, 1);
+        let mut ledger = DepositLedger::default();
+        ledger.fund(UnitsObjectId::new([9; 32]), 100);
+        ledger.settle(UnitsObjectId::new([1; 32]), 10, UnitsObjectId::new([9; 32])).unwrap();
+        let ledger_obj = ledger.to_object().unwrap();
+
+        // A rewrite at the same size needs no ledger write, so no ledger lock
+        let rewrite = ObjectEffect::modification(object(1, 10), object(1, 10));
+        assert!(config.charge_effects(&[rewrite], Some(&ledger_obj)).unwrap().is_none());
+        let growth = ObjectEffect::modification(object(1, 10), object(1, 11));
+        assert!(config.charge_effects(&[growth], Some(&ledger_obj)).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_insufficient_deposit_rejected() {
+        let config = StorageRentConfig::new(0, 1);
+        let owner = UnitsObjectId::new([9; 32]);
+        let ledger_obj = funded_ledger(owner, 10);
+
+        let result = config.charge_effects(&[ObjectEffect::creation(object(1, 11))], Some(&ledger_obj));
+        assert!(matches!(result, Err(VMExecutionError::InsufficientDeposit(_))));
+
+        let result = config.charge_effects(&[ObjectEffect::creation(object(1, 1))], None);
+        assert!(matches!(result, Err(VMExecutionError::InsufficientDeposit(_))));
+    }
+
+    #[test]
+    fn test_resize_adjusts_deposit() {
+        let payer = UnitsObjectId::new([7; 32]);
+        let mut ledger = DepositLedger::default();
+        ledger.fund(payer, 100);
+
+        ledger.settle(UnitsObjectId::new([1; 32]), 30, payer).unwrap();
+        ledger.settle(UnitsObjectId::new([1; 32]), 60, payer).unwrap();
+        assert_eq!(ledger.balance_of(&payer), 40);
+        ledger.settle(UnitsObjectId::new([1; 32]), 20, payer).unwrap();
+        assert_eq!(ledger.balance_of(&payer), 80);
+        assert_eq!(ledger.deposit_held(&UnitsObjectId::new([1; 32])), 20);
+    }
+
+    #[test]
+    fn test_ledger_cannot_be_modified_by_controller() {
+        let config = StorageRentConfig::new(0, 1);
+        let ledger_obj = funded_ledger(UnitsObjectId::new([7; 32]), 10);
+
+        let result = config.charge_effects(
+            &[ObjectEffect::modification(ledger_obj.clone(), ledger_obj.clone())],
+            Some(&ledger_obj),
+        );
+        assert!(matches!(result, Err(VMExecutionError::ControllerValidationFailed(_))));
+    }
+}
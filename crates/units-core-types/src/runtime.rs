@@ -2,23 +2,38 @@
 //!
 //! This module provides the core runtime interfaces without any concrete implementations.
 
-use crate::error::RuntimeError;
+use crate::effect_processor::{apply_effect_processors, EffectProcessor};
+use crate::error::{RuntimeError, StorageError};
+use crate::executable_cache::ExecutableCache;
 use crate::id::UnitsObjectId;
 use crate::objects::{UnitsObject, VMType};
 use crate::transaction::{
-    CommitmentLevel, ConflictResult, Instruction, Transaction, TransactionHash, TransactionReceipt,
+    CommitmentLevel, ConflictResult, ExecutionFailure, FeePayment, Instruction, Transaction,
+    TransactionHash, TransactionReceipt,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 
 // Forward declare types that will be defined in vm_executor module
-use crate::vm_executor::{ExecutionContext, VMExecutionError, VMExecutor, ObjectEffect};
+use crate::vm_executor::{ContextLimits, ExecutionContext, ExecutionMetrics, ExecutionOutput, VMExecutionError, VMExecutor, ObjectEffect};
 use crate::verification::Verifier;
+use crate::rent::{StorageRentConfig, DEPOSIT_LEDGER_ID};
+use crate::fees::{FeeLedger, FEE_LEDGER_ID};
+use crate::module_registry::{ModuleRegistry, ResourceClass, MODULE_REGISTRY_ID};
+use crate::authorizers::{AuthorizationRequest, AuthorizerRegistry, AUTHORIZER_REGISTRY_ID};
 
 /// Runtime for executing transactions and programs in the UNITS system
 pub trait Runtime {
     /// Get the VM executors available to this runtime
     fn get_vm_executor(&self, vm_type: VMType) -> Option<Box<dyn VMExecutor>>;
 
+    /// Get a VM executor enforcing the budgets of `class`
+    ///
+    /// Runtimes without per-class budgets use the same executor for every class.
+    fn get_vm_executor_for_class(&self, vm_type: VMType, _class: ResourceClass) -> Option<Box<dyn VMExecutor>> {
+        self.get_vm_executor(vm_type)
+    }
+
     //--------------------------------------------------------------------------
     // TRANSACTION EXECUTION
     //--------------------------------------------------------------------------
@@ -26,6 +41,198 @@ pub trait Runtime {
     /// Execute a transaction and return a transaction receipt with proofs
     fn execute_transaction(&self, transaction: Transaction) -> TransactionReceipt;
 
+    /// Execute every instruction of a transaction atomically against `view`
+    ///
+    /// Instructions run in order, possibly under different controllers, and
+    /// each sees the objects as the ones before it left them. Every effect
+    /// must start from the object the view held, so effects chain from one
+    /// instruction to the next. The single receipt carries one net effect
+    /// per changed object, merged across instructions, and each
+    /// instruction's metrics.
+    ///
+    /// An instruction touching an account that designated an authorizer
+    /// runs only once the authorizer approves it (see
+    /// [`crate::authorizers`]); a denial fails it like an error of its own.
+    ///
+    /// If any instruction fails, the receipt carries its error and no
+    /// effects, and the view is rolled back, so applying
+    /// [`TransactionView::into_writes`] commits all or nothing.
+    ///
+    /// The transaction's expected versions are checked before anything
+    /// runs; a mismatch fails the transaction the same way. Once every
+    /// instruction has succeeded the runtime's effect processors review the
+    /// receipt, and a veto also fails the transaction and rolls back.
+    fn execute_transaction_atomic(
+        &self,
+        transaction: &Transaction,
+        view: &mut TransactionView<'_>,
+        slot: u64,
+        timestamp: u64,
+    ) -> Result<TransactionReceipt, StorageError> {
+        let mut receipt = TransactionReceipt::new(transaction.hash, slot, true, timestamp);
+        receipt.memo = transaction.memo.clone();
+        if let Err(reason) = transaction.check_memo() {
+            receipt.set_error(reason);
+            return Ok(receipt);
+        }
+
+        // The sponsor's signature covers only the fee, so it is checked on
+        // its own, before anything the instructions authorize
+        if let Some(sponsorship) = &transaction.sponsorship {
+            let checked = sponsorship.verify(transaction).and_then(|()| {
+                (transaction.priority_fee <= sponsorship.max_fee).then_some(()).ok_or_else(|| {
+                    format!(
+                        "Priority fee {} exceeds the sponsor's maximum of {}",
+                        transaction.priority_fee, sponsorship.max_fee
+                    )
+                })
+            });
+            if let Err(reason) = checked {
+                receipt.set_error(format!("Invalid fee sponsorship: {}", reason));
+                return Ok(receipt);
+            }
+        }
+
+        for expected in &transaction.expected_versions {
+            let found = view.version(&expected.object_id)?;
+            if found != expected.version {
+                receipt.set_error(format!(
+                    "Version precondition failed for {}: expected {}, found {}",
+                    expected.object_id, expected.version, found
+                ));
+                return Ok(receipt);
+            }
+        }
+
+        // Fees are kept even if the instructions fail, so they are staged
+        // and recorded ahead of the checkpoint failures roll back to.
+        // Charging takes the sponsor's nonce, so a sponsorship pays once;
+        // one charging nothing leaves the ledger alone.
+        if let Some(sponsorship) = &transaction.sponsorship {
+            let payer = sponsorship.payer_id();
+            if transaction.priority_fee > 0 {
+                let ledger = view.get(&FEE_LEDGER_ID)?;
+                let charge = FeeLedger::charge_effect(ledger.as_ref(), payer, transaction.priority_fee, sponsorship.nonce)
+                    .and_then(|effect| view.apply(std::slice::from_ref(&effect)).map(|()| effect));
+                match charge {
+                    Ok(effect) => receipt.add_object_effect(
+                        transaction.hash,
+                        effect.object_id,
+                        effect.before_image,
+                        effect.after_image,
+                    ),
+                    Err(error) => {
+                        receipt.set_error(format!("Fee sponsor cannot pay: {}", error));
+                        return Ok(receipt);
+                    }
+                }
+            }
+            receipt.fee = Some(FeePayment { payer, amount: transaction.priority_fee, sponsored: true });
+        }
+        let checkpoint = view.staged.clone();
+        let charged = receipt.effects.len();
+
+        for (index, instruction) in transaction.instructions.iter().enumerate() {
+            let mut extra = Vec::new();
+            if self.storage_rent_config().is_some() {
+                extra.push(DEPOSIT_LEDGER_ID);
+            }
+            extra.extend(view.prefetch_hints(instruction)?);
+            let mut objects = view.objects_for(instruction, &extra)?;
+
+            // Authorizers of the accounts the instruction touches approve it
+            // first, each as a call of its own whose effects stay with it
+            let mut steps = Vec::new();
+            let mut denied = None;
+            for (account, authorizer) in view.guarding(instruction, &objects)? {
+                let request = AuthorizationRequest {
+                    account,
+                    transaction_hash: transaction.hash,
+                    instruction_index: index,
+                    instruction: instruction.clone(),
+                };
+                let call = request.to_instruction(authorizer)?;
+                let call_objects = view.objects_for(&call, &extra)?;
+                let class = view.resource_class(&authorizer)?;
+                match self.execute_step(&call, call_objects, class, view, slot, timestamp) {
+                    Ok(step) => steps.push((authorizer, step)),
+                    Err(error) => {
+                        denied = Some((account, call, error));
+                        break;
+                    }
+                }
+            }
+
+            let failure = match denied {
+                Some((account, call, error)) => Some((Some(account), call, error)),
+                None => {
+                    if !steps.is_empty() {
+                        objects = view.objects_for(instruction, &extra)?;
+                    }
+                    let class = view.resource_class(&instruction.controller_id)?;
+                    match self.execute_step(instruction, objects, class, view, slot, timestamp) {
+                        Ok(step) => {
+                            steps.push((instruction.controller_id, step));
+                            None
+                        }
+                        Err(error) => Some((None, instruction.clone(), error)),
+                    }
+                }
+            };
+
+            let Some((account, failed, error)) = failure else {
+                let mut total = ExecutionMetrics::default();
+                for (controller_id, output) in steps {
+                    for effect in output.effects {
+                        receipt.merge_object_effect(
+                            transaction.hash,
+                            effect.object_id,
+                            effect.before_image,
+                            effect.after_image,
+                        );
+                    }
+                    receipt.events.extend(
+                        output.events.into_iter().map(|event| event.into_transaction_event(transaction.hash, controller_id)),
+                    );
+                    total.accumulate(&output.metrics);
+                }
+                receipt.add_instruction_metrics(total);
+                continue;
+            };
+
+            view.staged = checkpoint;
+            receipt.effects.truncate(charged);
+            receipt.events.clear();
+            let reason = match error {
+                VMExecutionError::ModuleError(code) => {
+                    let failure = view.execution_failure(index, &failed, code);
+                    let reason = failure.to_string();
+                    receipt.failure = Some(failure);
+                    reason
+                }
+                error => error.to_string(),
+            };
+            let reason = match account {
+                Some(account) => format!("Authorizer {} of {} denied it: {}", failed.controller_id, account, reason),
+                None => reason,
+            };
+            receipt.set_error(format!(
+                "Instruction {} ({}) failed: {}",
+                index, instruction.target_function, reason
+            ));
+            return Ok(receipt);
+        }
+
+        if let Err(veto) = apply_effect_processors(self.effect_processors(), transaction, &mut receipt) {
+            view.staged = checkpoint;
+            receipt.effects.truncate(charged);
+            receipt.events.clear();
+            receipt.set_error(veto);
+        }
+
+        Ok(receipt)
+    }
+
     /// Try to execute a transaction with conflict checking
     fn try_execute_transaction(
         &self,
@@ -52,6 +259,54 @@ pub trait Runtime {
     // PROGRAM EXECUTION
     //--------------------------------------------------------------------------
 
+    /// Storage rent pricing, or None if deposit accounting is disabled
+    ///
+    /// When enabled, callers of `execute_instruction` must include the
+    /// deposit ledger object in `objects` so payer balances are visible.
+    fn storage_rent_config(&self) -> Option<StorageRentConfig> {
+        None
+    }
+
+    /// Limits on the number and size of objects passed to the VM
+    fn context_limits(&self) -> ContextLimits {
+        ContextLimits::default()
+    }
+
+    /// Plugins reviewing each transaction's effects before commit, in order
+    fn effect_processors(&self) -> &[Arc<dyn EffectProcessor>] {
+        &[]
+    }
+
+    /// Execute one call of a transaction within the budgets of `class` and
+    /// stage its effects in `view`
+    ///
+    /// Effects on the fee ledger or the authorizer registry are rejected:
+    /// those objects only change through the runtime and the node.
+    fn execute_step(
+        &self,
+        instruction: &Instruction,
+        objects: HashMap<UnitsObjectId, UnitsObject>,
+        class: ResourceClass,
+        view: &mut TransactionView<'_>,
+        slot: u64,
+        timestamp: u64,
+    ) -> Result<ExecutionOutput, VMExecutionError> {
+        let output = self.execute_instruction_in_class(instruction, objects, class, slot, timestamp)?;
+        let effects = &output.effects;
+        if effects.iter().any(|effect| effect.object_id == FEE_LEDGER_ID) {
+            return Err(VMExecutionError::ControllerValidationFailed(
+                "Controllers cannot modify the fee ledger".into(),
+            ));
+        }
+        if effects.iter().any(|effect| effect.object_id == AUTHORIZER_REGISTRY_ID) {
+            return Err(VMExecutionError::ControllerValidationFailed(
+                "Controllers cannot modify the authorizer registry".into(),
+            ));
+        }
+        view.apply(effects)?;
+        Ok(output)
+    }
+
     /// Execute a program call instruction
     fn execute_instruction(
         &self,
@@ -60,6 +315,42 @@ pub trait Runtime {
         slot: u64,
         timestamp: u64,
     ) -> Result<Vec<ObjectEffect>, VMExecutionError> {
+        self.execute_instruction_with_metrics(instruction, objects, slot, timestamp)
+            .map(|(effects, _)| effects)
+    }
+
+    /// Execute a program call instruction, reporting the VM resources it used
+    ///
+    /// The metrics are meant to be recorded in the transaction receipt via
+    /// `TransactionReceipt::add_instruction_metrics`.
+    fn execute_instruction_with_metrics(
+        &self,
+        instruction: &Instruction,
+        objects: HashMap<UnitsObjectId, UnitsObject>,
+        slot: u64,
+        timestamp: u64,
+    ) -> Result<(Vec<ObjectEffect>, ExecutionMetrics), VMExecutionError> {
+        self.execute_instruction_in_class(instruction, objects, ResourceClass::Standard, slot, timestamp)
+            .map(|output| (output.effects, output.metrics))
+    }
+
+    /// Execute a program call instruction on an executor sized for `class`,
+    /// reporting the events it emitted and the VM resources it used
+    ///
+    /// Transactions run each instruction in the class the module registry
+    /// assigns to its controller.
+    fn execute_instruction_in_class(
+        &self,
+        instruction: &Instruction,
+        objects: HashMap<UnitsObjectId, UnitsObject>,
+        class: ResourceClass,
+        slot: u64,
+        timestamp: u64,
+    ) -> Result<ExecutionOutput, VMExecutionError> {
+        // Reject oversized contexts before doing any other work
+        let limits = self.context_limits();
+        limits.check_object_count(objects.len())?;
+
         // Get the controller object 
         let controller = objects.get(&instruction.controller_id)
             .ok_or_else(|| VMExecutionError::InvalidBytecode("Controller object not found".to_string()))?
@@ -70,7 +361,7 @@ pub trait Runtime {
             .ok_or_else(|| VMExecutionError::InvalidBytecode("Controller is not executable".to_string()))?;
 
         // Get appropriate VM executor
-        let executor = self.get_vm_executor(vm_type)
+        let executor = self.get_vm_executor_for_class(vm_type, class)
             .ok_or_else(|| VMExecutionError::UnsupportedVMType(format!("{:?}", vm_type)))?;
 
         // Create execution context
@@ -80,9 +371,19 @@ pub trait Runtime {
             slot,
             timestamp,
         );
+        limits.check(&context)?;
 
         // Execute the instruction
-        executor.load_and_execute(controller.data(), &context)
+        let mut output = executor.load_and_execute_with_events(controller.data(), &context)?;
+
+        // Charge deposits for the effects to the objects' owners
+        if let Some(rent) = self.storage_rent_config() {
+            if let Some(ledger_effect) = rent.charge_effects(&output.effects, context.objects.get(&DEPOSIT_LEDGER_ID))? {
+                output.effects.push(ledger_effect);
+            }
+        }
+
+        Ok(output)
     }
 
     //--------------------------------------------------------------------------
@@ -126,4 +427,300 @@ pub trait Runtime {
     /// All runtime implementations must provide verification capabilities
     /// to ensure transaction and proof integrity.
     fn get_verifier(&self) -> &dyn Verifier;
-}
\ No newline at end of file
+}
+/// Loads committed objects for a [`TransactionView`]
+pub type ObjectLoader<'a> = dyn Fn(&UnitsObjectId) -> Result<Option<UnitsObject>, StorageError> + 'a;
+
+/// Loads committed object versions for a [`TransactionView`]
+pub type VersionLoader<'a> = dyn Fn(&UnitsObjectId) -> Result<u64, StorageError> + 'a;
+
+/// Objects changed by a transaction so far, layered over committed state
+///
+/// Reads fall through to the loader for objects the transaction has not
+/// touched. Nothing reaches storage until the caller applies
+/// [`TransactionView::into_writes`].
+pub struct TransactionView<'a> {
+    load: &'a ObjectLoader<'a>,
+    versions: Option<&'a VersionLoader<'a>>,
+    executables: Option<&'a ExecutableCache>,
+    /// Latest image of each touched object; `None` once deleted
+    staged: BTreeMap<UnitsObjectId, Option<UnitsObject>>,
+}
+
+impl<'a> TransactionView<'a> {
+    pub fn new(load: &'a ObjectLoader<'a>) -> Self {
+        Self {
+            load,
+            versions: None,
+            executables: None,
+            staged: BTreeMap::new(),
+        }
+    }
+
+    /// Look up committed versions through `versions`, enabling
+    /// [`Transaction::expected_versions`] checks
+    pub fn with_versions(mut self, versions: &'a VersionLoader<'a>) -> Self {
+        self.versions = Some(versions);
+        self
+    }
+
+    /// Resolve instruction controllers through `executables`, reading
+    /// storage only for controllers it does not hold
+    pub fn with_executables(mut self, executables: &'a ExecutableCache) -> Self {
+        self.executables = Some(executables);
+        self
+    }
+
+    /// Version the object will have once the staged writes commit
+    ///
+    /// Committing writes each touched object once, so a staged change adds
+    /// one to the committed version.
+    pub fn version(&self, id: &UnitsObjectId) -> Result<u64, StorageError> {
+        let versions = self.versions.ok_or_else(|| {
+            StorageError::Unimplemented("Object versions are not available to this transaction".to_string())
+        })?;
+        Ok(versions(id)? + self.staged.contains_key(id) as u64)
+    }
+
+    /// Current image of an object as the transaction sees it
+    pub fn get(&self, id: &UnitsObjectId) -> Result<Option<UnitsObject>, StorageError> {
+        match self.staged.get(id) {
+            Some(object) => Ok(object.clone()),
+            None => (self.load)(id),
+        }
+    }
+
+    /// Current image of the executable `id`, through the executable cache
+    /// when the view has one
+    pub fn get_executable(&self, id: &UnitsObjectId) -> Result<Option<UnitsObject>, StorageError> {
+        match (self.staged.get(id), self.executables) {
+            (None, Some(executables)) => executables.resolve(id, self.load),
+            _ => self.get(id),
+        }
+    }
+
+    /// Objects passed to `instruction`: its controller, its targets and `extra`
+    ///
+    /// Objects that do not exist are left out.
+    pub fn objects_for(
+        &self,
+        instruction: &Instruction,
+        extra: &[UnitsObjectId],
+    ) -> Result<HashMap<UnitsObjectId, UnitsObject>, StorageError> {
+        let mut objects = HashMap::new();
+        if let Some(controller) = self.get_executable(&instruction.controller_id)? {
+            objects.insert(instruction.controller_id, controller);
+        }
+        for id in instruction.target_objects.iter().chain(extra) {
+            if objects.contains_key(id) {
+                continue;
+            }
+            if let Some(object) = self.get(id)? {
+                objects.insert(*id, object);
+            }
+        }
+        Ok(objects)
+    }
+
+    /// Objects the invoked function declares it reads, per the module registry
+    ///
+    /// Passing them as `extra` to [`TransactionView::objects_for`] saves
+    /// clients from listing derived objects, such as balances, as targets.
+    pub fn prefetch_hints(&self, instruction: &Instruction) -> Result<Vec<UnitsObjectId>, StorageError> {
+        match self.get(&MODULE_REGISTRY_ID)? {
+            Some(object) => Ok(ModuleRegistry::from_object(&object)?.prefetch_ids(instruction)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Accounts whose authorizer must approve `instruction`, given the
+    /// `objects` passed to it, with that authorizer
+    pub fn guarding(
+        &self,
+        instruction: &Instruction,
+        objects: &HashMap<UnitsObjectId, UnitsObject>,
+    ) -> Result<Vec<(UnitsObjectId, UnitsObjectId)>, StorageError> {
+        match self.get(&AUTHORIZER_REGISTRY_ID)? {
+            Some(object) => Ok(AuthorizerRegistry::from_object(&object)?.guarding(instruction, objects)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Execution budget the module registry assigns to `controller_id`
+    pub fn resource_class(&self, controller_id: &UnitsObjectId) -> Result<ResourceClass, StorageError> {
+        match self.get(&MODULE_REGISTRY_ID)? {
+            Some(object) => Ok(ModuleRegistry::from_object(&object)?.resource_class(controller_id)),
+            None => Ok(ResourceClass::default()),
+        }
+    }
+
+    /// Failure of instruction `index`, whose controller exited with `code`,
+    /// named from the controller's registered error table
+    pub fn execution_failure(&self, index: usize, instruction: &Instruction, code: u32) -> ExecutionFailure {
+        let registered = match self.get(&MODULE_REGISTRY_ID) {
+            Ok(Some(object)) => ModuleRegistry::from_object(&object)
+                .ok()
+                .and_then(|registry| registry.error_code(&instruction.controller_id, code).cloned()),
+            _ => None,
+        };
+        ExecutionFailure {
+            instruction_index: index,
+            controller_id: instruction.controller_id,
+            code,
+            name: registered.as_ref().map(|error| error.name.clone()),
+            message: registered.and_then(|error| error.message),
+        }
+    }
+
+    /// Stage an instruction's effects
+    ///
+    /// Fails without staging anything if an effect's before image is not
+    /// the object the view currently holds.
+    pub fn apply(&mut self, effects: &[ObjectEffect]) -> Result<(), VMExecutionError> {
+        let mut touched = BTreeMap::new();
+        for effect in effects {
+            let current = match touched.get(&effect.object_id) {
+                Some(object) => Option::clone(object),
+                None => self.get(&effect.object_id).map_err(|e| VMExecutionError::ExecutionFailed(e.to_string()))?,
+            };
+            if current != effect.before_image {
+                return Err(VMExecutionError::ExecutionFailed(format!(
+                    "Effect on {} does not start from the object's current state",
+                    effect.object_id
+                )));
+            }
+            touched.insert(effect.object_id, effect.after_image.clone());
+        }
+        self.staged.extend(touched);
+        Ok(())
+    }
+
+    /// Whether any object has been changed
+    pub fn is_empty(&self) -> bool {
+        self.staged.is_empty()
+    }
+
+    /// Final image of every touched object, in ID order; `None` means delete
+    pub fn into_writes(self) -> BTreeMap<UnitsObjectId, Option<UnitsObject>> {
+        self.staged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(seed: u8, data: Vec<u8>) -> UnitsObject {
+        UnitsObject::new_data(UnitsObjectId::new([seed; 32]), UnitsObjectId::new([9; 32]), data)
+    }
+
+    #[test]
+    fn test_view_chains_effects() {
+        let committed = object(1, vec![1]);
+        let load = |id: &UnitsObjectId| Ok((*id == committed.id).then(|| committed.clone()));
+        let mut view = TransactionView::new(&load);
+
+        let first = object(1, vec![1, 2]);
+        view.apply(&[ObjectEffect::modification(committed.clone(), first.clone())]).unwrap();
+        assert_eq!(view.get(&committed.id).unwrap(), Some(first.clone()));
+
+        // A second effect must start from the first one's result
+        let stale = ObjectEffect::modification(committed.clone(), object(1, vec![3]));
+        assert!(view.apply(&[stale]).is_err());
+        assert_eq!(view.get(&committed.id).unwrap(), Some(first.clone()));
+
+        // Effects within one instruction chain too, and deletions stage as `None`
+        let created = object(2, vec![]);
+        view.apply(&[
+            ObjectEffect::creation(created.clone()),
+            ObjectEffect::deletion(created.clone()),
+            ObjectEffect::deletion(first),
+        ])
+        .unwrap();
+
+        let instruction = Instruction::new(UnitsObjectId::new([9; 32]), "f".to_string(), vec![committed.id, created.id], vec![]);
+        assert!(view.objects_for(&instruction, &[]).unwrap().is_empty());
+
+        let writes = view.into_writes();
+        assert_eq!(writes.len(), 2);
+        assert!(writes.values().all(Option::is_none));
+    }
+
+    #[test]
+    fn test_prefetch_hints_load_derived_objects() {
+        use crate::module_registry::{PrefetchRule, PrefetchSeed};
+
+        let controller = UnitsObject::new_executable(UnitsObjectId::new([9; 32]), UnitsObjectId::new([9; 32]), VMType::RiscV, vec![1]);
+        let target = UnitsObjectId::new([1; 32]);
+        let (derived_id, _) = UnitsObjectId::find_uid(&[b"extra", target.bytes()]);
+        let derived = UnitsObject::new_data(derived_id, *controller.id(), vec![7]);
+
+        let mut registry = ModuleRegistry::default();
+        registry.record_deployment(&controller, 1);
+        registry.set_prefetch_rules(
+            controller.id(),
+            "f",
+            vec![PrefetchRule { seeds: vec![PrefetchSeed::Literal(b"extra".to_vec()), PrefetchSeed::Target(0)] }],
+        );
+        let registry = registry.to_object().unwrap();
+
+        let load = |id: &UnitsObjectId| {
+            Ok([&controller, &derived, &registry].into_iter().find(|object| object.id() == id).cloned())
+        };
+        let view = TransactionView::new(&load);
+        let instruction = Instruction::new(*controller.id(), "f".to_string(), vec![target], vec![]);
+
+        let hints = view.prefetch_hints(&instruction).unwrap();
+        assert_eq!(hints, vec![derived_id]);
+        let objects = view.objects_for(&instruction, &hints).unwrap();
+        assert_eq!(objects.get(&derived_id), Some(&derived));
+    }
+
+    #[test]
+    fn test_views_share_cached_controllers() {
+        let controller = UnitsObject::new_executable(UnitsObjectId::new([9; 32]), UnitsObjectId::new([9; 32]), VMType::RiscV, vec![1]);
+        let target = object(1, vec![1]);
+        let reads = std::cell::Cell::new(0);
+        let load = |id: &UnitsObjectId| {
+            reads.set(reads.get() + 1);
+            Ok([&controller, &target].into_iter().find(|object| object.id() == id).cloned())
+        };
+        let executables = ExecutableCache::new();
+        let instruction = Instruction::new(*controller.id(), "f".to_string(), vec![target.id], vec![]);
+
+        for _ in 0..4 {
+            let view = TransactionView::new(&load).with_executables(&executables);
+            let objects = view.objects_for(&instruction, &[]).unwrap();
+            assert_eq!(objects.get(controller.id()), Some(&controller));
+        }
+        // One read of the controller, then one of the target per instruction
+        assert_eq!(reads.get(), 5);
+        assert_eq!(executables.stats().hits, 3);
+    }
+
+    #[test]
+    fn test_execution_failures_are_named_from_the_error_table() {
+        use crate::module_registry::ModuleErrorCode;
+
+        let controller = UnitsObject::new_executable(UnitsObjectId::new([9; 32]), UnitsObjectId::new([9; 32]), VMType::RiscV, vec![1]);
+        let mut registry = ModuleRegistry::default();
+        registry.record_deployment(&controller, 1);
+        let invalid_username = ModuleErrorCode { name: "ERROR_INVALID_USERNAME".to_string(), message: Some("Invalid username".to_string()) };
+        registry.set_error_codes(controller.id(), BTreeMap::from([(1001, invalid_username)]));
+        let registry = registry.to_object().unwrap();
+
+        let load = |id: &UnitsObjectId| Ok((id == registry.id()).then(|| registry.clone()));
+        let view = TransactionView::new(&load);
+        let instruction = Instruction::new(*controller.id(), "create_account".to_string(), vec![], vec![]);
+
+        let failure = view.execution_failure(2, &instruction, 1001);
+        assert_eq!((failure.instruction_index, failure.controller_id), (2, *controller.id()));
+        assert_eq!(failure.name.as_deref(), Some("ERROR_INVALID_USERNAME"));
+        assert_eq!(failure.to_string(), "ERROR_INVALID_USERNAME (1001): Invalid username");
+
+        // Unregistered codes keep the bare exit status
+        let failure = view.execution_failure(0, &instruction, 7);
+        assert!(failure.name.is_none() && failure.message.is_none());
+        assert_eq!(failure.to_string(), "Program exited with code: 7");
+    }
+}
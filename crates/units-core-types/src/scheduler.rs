@@ -1,5 +1,12 @@
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use crate::fees::FEE_LEDGER_ID;
 use crate::id::UnitsObjectId;
+use crate::locks::AccessIntent;
+use crate::proofs::SlotNumber;
 use crate::transaction::{ConflictResult, Transaction};
 
 /// Trait for transaction conflict checking
@@ -37,6 +44,169 @@ pub trait ConflictChecker {
         }
         write_objects
     }
+
+    /// Objects a transaction may read or write
+    ///
+    /// Controllers are read and targets written. A sponsored transaction
+    /// also writes the fee ledger when it pays a fee.
+    fn access_set(&self, transaction: &Transaction) -> AccessSet {
+        let mut access = AccessSet::default();
+        for instruction in &transaction.instructions {
+            access.insert(instruction.controller_id, AccessIntent::Read);
+        }
+        for id in self.extract_write_objects(transaction) {
+            access.insert(id, AccessIntent::Write);
+        }
+        if transaction.sponsorship.is_some() && transaction.priority_fee > 0 {
+            access.insert(FEE_LEDGER_ID, AccessIntent::Write);
+        }
+        access
+    }
+}
+
+/// Objects a transaction reads and writes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessSet {
+    pub reads: HashSet<UnitsObjectId>,
+    /// Written objects; an object written is never listed as read too
+    pub writes: HashSet<UnitsObjectId>,
+}
+
+impl AccessSet {
+    /// Record access to `id`; a write subsumes a read
+    pub fn insert(&mut self, id: UnitsObjectId, intent: AccessIntent) {
+        match intent {
+            AccessIntent::Read if !self.writes.contains(&id) => {
+                self.reads.insert(id);
+            }
+            AccessIntent::Read => {}
+            AccessIntent::Write => {
+                self.reads.remove(&id);
+                self.writes.insert(id);
+            }
+        }
+    }
+
+    /// Add everything `other` accesses
+    pub fn extend(&mut self, other: &AccessSet) {
+        for id in &other.writes {
+            self.insert(*id, AccessIntent::Write);
+        }
+        for id in &other.reads {
+            self.insert(*id, AccessIntent::Read);
+        }
+    }
+
+    /// Whether running alongside `other` could change either's outcome,
+    /// i.e. one writes an object the other reads or writes
+    pub fn conflicts_with(&self, other: &AccessSet) -> bool {
+        let touched = |set: &AccessSet, id: &UnitsObjectId| set.reads.contains(id) || set.writes.contains(id);
+        self.writes.iter().any(|id| touched(other, id)) || other.writes.iter().any(|id| self.reads.contains(id))
+    }
+}
+
+/// Execution parallelism settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParallelSchedulerConfig {
+    /// Threads executing non-conflicting transactions; 1 executes serially
+    pub threads: usize,
+}
+
+impl Default for ParallelSchedulerConfig {
+    fn default() -> Self {
+        Self { threads: 4 }
+    }
+}
+
+/// Which transactions of a batch run concurrently and which in order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchedulePlan {
+    /// Indices of transactions that conflict with no earlier transaction
+    pub parallel: Vec<usize>,
+    /// Indices of the rest, in batch order
+    pub serial: Vec<usize>,
+}
+
+/// Runs a batch's non-conflicting transactions concurrently
+///
+/// A transaction runs in parallel only if its [`AccessSet`] is disjoint
+/// from those of every transaction before it in the batch, whether they
+/// run in parallel or not. The rest run serially, in batch order, once
+/// the parallel ones have finished. Every transaction therefore sees the
+/// same state it would if the whole batch ran in order, so results do not
+/// depend on thread timing. Access sets must cover every object a
+/// transaction may write; anything left out can race.
+#[derive(Debug, Clone)]
+pub struct ParallelScheduler {
+    config: ParallelSchedulerConfig,
+}
+
+impl ParallelScheduler {
+    pub fn new(config: ParallelSchedulerConfig) -> Self {
+        Self { config }
+    }
+
+    /// Split a batch, given each transaction's access set in batch order
+    pub fn plan(&self, access: &[AccessSet]) -> SchedulePlan {
+        let mut plan = SchedulePlan::default();
+        if self.config.threads <= 1 {
+            plan.serial = (0..access.len()).collect();
+            return plan;
+        }
+
+        let mut claimed = AccessSet::default();
+        for (index, set) in access.iter().enumerate() {
+            if set.conflicts_with(&claimed) {
+                plan.serial.push(index);
+            } else {
+                plan.parallel.push(index);
+            }
+            claimed.extend(set);
+        }
+        plan
+    }
+
+    /// Run `run` on every item as planned from `access`, returning the
+    /// results in batch order
+    pub fn execute<T, R, F>(&self, items: Vec<T>, access: &[AccessSet], run: F) -> Vec<R>
+    where
+        T: Send,
+        R: Send,
+        F: Fn(T) -> R + Sync,
+    {
+        assert_eq!(items.len(), access.len(), "every item needs an access set");
+        let plan = self.plan(access);
+        let items: Vec<Mutex<Option<T>>> = items.into_iter().map(|item| Mutex::new(Some(item))).collect();
+        let results: Vec<Mutex<Option<R>>> = items.iter().map(|_| Mutex::new(None)).collect();
+        let run_one = |index: usize| {
+            let item = items[index].lock().unwrap().take().expect("each item runs once");
+            let result = run(item);
+            *results[index].lock().unwrap() = Some(result);
+        };
+
+        let workers = self.config.threads.min(plan.parallel.len());
+        if workers > 1 {
+            let next = AtomicUsize::new(0);
+            std::thread::scope(|scope| {
+                for _ in 0..workers {
+                    scope.spawn(|| {
+                        while let Some(&index) = plan.parallel.get(next.fetch_add(1, Ordering::Relaxed)) {
+                            run_one(index);
+                        }
+                    });
+                }
+            });
+        } else {
+            plan.parallel.iter().for_each(|&index| run_one(index));
+        }
+        plan.serial.iter().for_each(|&index| run_one(index));
+
+        results
+            .into_iter()
+            .map(|result| result.into_inner().unwrap().expect("every item ran"))
+            .collect()
+    }
 }
 
 /// Basic implementation of the ConflictChecker trait
@@ -100,4 +270,422 @@ impl ConflictChecker for BasicConflictChecker {
             Ok(ConflictResult::Conflict(conflicts))
         }
     }
-}
\ No newline at end of file
+}
+
+/// Tuning parameters for adaptive batch sizing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdaptiveBatchConfig {
+    /// Target wall-clock time to execute one slot's batch, in milliseconds
+    pub target_slot_ms: u64,
+    /// Smallest number of transactions admitted per slot
+    pub min_batch_size: usize,
+    /// Largest number of transactions admitted per slot
+    pub max_batch_size: usize,
+    /// Batch size used before any slot has been measured
+    pub initial_batch_size: usize,
+    /// How many slots worth of transactions may queue before submissions are rejected
+    pub max_queued_slots: usize,
+}
+
+impl Default for AdaptiveBatchConfig {
+    fn default() -> Self {
+        Self {
+            target_slot_ms: 400,
+            min_batch_size: 16,
+            max_batch_size: 4096,
+            initial_batch_size: 256,
+            max_queued_slots: 4,
+        }
+    }
+}
+
+impl AdaptiveBatchConfig {
+    /// Reject settings the batch sizer cannot work with
+    ///
+    /// The sizer clamps batch sizes into `[min_batch_size, max_batch_size]`,
+    /// so an empty or inverted range would stall or panic the pipeline.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_batch_size == 0 {
+            return Err("min_batch_size must be at least 1".to_string());
+        }
+        if self.min_batch_size > self.max_batch_size {
+            return Err(format!(
+                "min_batch_size ({}) must not exceed max_batch_size ({})",
+                self.min_batch_size, self.max_batch_size
+            ));
+        }
+        if self.target_slot_ms == 0 {
+            return Err("target_slot_ms must be at least 1".to_string());
+        }
+        if self.max_queued_slots == 0 {
+            return Err("max_queued_slots must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Admission decision for a newly submitted transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    /// The transaction can be queued
+    Accept,
+    /// The queue is saturated; the client should retry after the hint
+    Backpressure { retry_after_ms: u64 },
+}
+
+/// Feedback-driven batch sizer
+///
+/// Shrinks the per-slot batch multiplicatively when a slot overruns its
+/// target duration and grows it additively when a full batch finishes
+/// comfortably inside the target.
+#[derive(Debug, Clone)]
+pub struct AdaptiveBatchSizer {
+    config: AdaptiveBatchConfig,
+    batch_size: usize,
+    last_slot_ms: u64,
+}
+
+impl AdaptiveBatchSizer {
+    /// Create a new sizer starting at the configured initial batch size
+    pub fn new(config: AdaptiveBatchConfig) -> Self {
+        let batch_size = config
+            .initial_batch_size
+            .clamp(config.min_batch_size, config.max_batch_size);
+        Self {
+            last_slot_ms: config.target_slot_ms,
+            config,
+            batch_size,
+        }
+    }
+
+    /// Number of transactions to admit into the next slot
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    /// Execution time of the most recently measured slot, in milliseconds
+    pub fn last_slot_ms(&self) -> u64 {
+        self.last_slot_ms
+    }
+
+    /// Record how long a slot took to execute `executed` transactions
+    pub fn record_slot(&mut self, executed: usize, elapsed: Duration) {
+        let elapsed_ms = elapsed.as_millis() as u64;
+        self.last_slot_ms = elapsed_ms;
+
+        let target = self.config.target_slot_ms;
+        if elapsed_ms > target {
+            // Overran the target: back off by a quarter
+            self.batch_size -= self.batch_size / 4;
+        } else if executed >= self.batch_size && elapsed_ms * 5 < target * 4 {
+            // Full batch with at least 20% headroom: probe upwards
+            self.batch_size += (self.batch_size / 8).max(1);
+        }
+
+        self.batch_size = self
+            .batch_size
+            .clamp(self.config.min_batch_size, self.config.max_batch_size);
+    }
+
+    /// Transactions that can queue before new ones see backpressure
+    pub fn queue_capacity(&self) -> usize {
+        self.batch_size.saturating_mul(self.config.max_queued_slots)
+    }
+
+    /// Decide whether a new transaction can join a queue of `queued` transactions
+    pub fn admit(&self, queued: usize) -> Admission {
+        let capacity = self.queue_capacity();
+        if queued < capacity {
+            return Admission::Accept;
+        }
+
+        // Estimate how many slots it takes to drain the excess
+        let slots_to_drain = (queued + 1 - capacity).div_ceil(self.batch_size.max(1)) as u64;
+        Admission::Backpressure {
+            retry_after_ms: slots_to_drain * self.last_slot_ms.max(self.config.target_slot_ms),
+        }
+    }
+}
+
+/// Number of recent slots the fee market keeps by default
+pub const DEFAULT_FEE_MARKET_WINDOW: usize = 64;
+
+/// Inclusion behaviour observed in one executed slot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlotFeeStats {
+    pub slot: SlotNumber,
+    /// Transactions waiting when the slot's batch was drawn
+    pub queue_depth: usize,
+    /// Priority fees of the transactions included in the slot
+    pub included_fees: Vec<u64>,
+    /// Transactions still waiting after the batch was drawn
+    pub left_pending: usize,
+}
+
+impl SlotFeeStats {
+    /// Whether the slot could not include everything that was waiting
+    pub fn is_congested(&self) -> bool {
+        self.left_pending > 0
+    }
+}
+
+/// Priority fees paid by included transactions over the sampled slots
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeDistribution {
+    pub min: u64,
+    pub median: u64,
+    pub p90: u64,
+    pub max: u64,
+}
+
+impl FeeDistribution {
+    fn from_fees(mut fees: Vec<u64>) -> Self {
+        if fees.is_empty() {
+            return Self::default();
+        }
+        fees.sort_unstable();
+        let percentile = |p: usize| fees[(fees.len() - 1) * p / 100];
+        Self {
+            min: fees[0],
+            median: percentile(50),
+            p90: percentile(90),
+            max: fees[fees.len() - 1],
+        }
+    }
+}
+
+/// Suggested priority fee for a target confirmation latency
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeEstimate {
+    /// Slots within which the transaction should be included
+    pub target_slots: u64,
+    /// Suggested priority fee
+    pub priority_fee: u64,
+    /// Transactions currently waiting for inclusion
+    pub queue_depth: usize,
+    /// Transactions admitted per slot
+    pub batch_size: usize,
+    /// Recent slots the estimate is based on
+    pub slots_sampled: usize,
+    /// Sampled slots that left transactions waiting
+    pub congested_slots: usize,
+    /// Fees paid by transactions included in the sampled slots
+    pub recent_fees: FeeDistribution,
+}
+
+/// Rolling record of recent slots' inclusion behaviour
+///
+/// Estimates assume the pending queue is ordered by priority fee, highest
+/// first, with ties in submission order.
+#[derive(Debug, Clone)]
+pub struct FeeMarket {
+    window: usize,
+    slots: VecDeque<SlotFeeStats>,
+}
+
+impl FeeMarket {
+    /// Create a fee market remembering the last `window` slots
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            slots: VecDeque::new(),
+        }
+    }
+
+    /// Record the outcome of an executed slot
+    pub fn record_slot(&mut self, stats: SlotFeeStats) {
+        self.slots.push_back(stats);
+        while self.slots.len() > self.window {
+            self.slots.pop_front();
+        }
+    }
+
+    /// Recorded slots, oldest first
+    pub fn recent_slots(&self) -> impl Iterator<Item = &SlotFeeStats> {
+        self.slots.iter()
+    }
+
+    /// Suggest a priority fee for inclusion within `target_slots` slots
+    ///
+    /// A new transaction is included in time if fewer than
+    /// `target_slots * batch_size` pending transactions pay at least as
+    /// much, so the queue sets a floor. When recent slots were congested,
+    /// the typical lowest fee those slots still included is used as well,
+    /// since the queue can refill before the transaction is drawn.
+    pub fn estimate(&self, pending_fees: &[u64], batch_size: usize, target_slots: u64) -> FeeEstimate {
+        let target_slots = target_slots.max(1);
+        let capacity = (target_slots as usize).saturating_mul(batch_size.max(1));
+
+        let mut queued = pending_fees.to_vec();
+        queued.sort_unstable_by(|a, b| b.cmp(a));
+        let queue_floor = queued
+            .get(capacity.saturating_sub(1))
+            .filter(|_| queued.len() >= capacity)
+            .map_or(0, |fee| fee.saturating_add(1));
+
+        let congested: Vec<&SlotFeeStats> = self.slots.iter().filter(|stats| stats.is_congested()).collect();
+        let clearing_fees: Vec<u64> = congested
+            .iter()
+            .filter_map(|stats| stats.included_fees.iter().min().copied())
+            .collect();
+        let recent_clearing = FeeDistribution::from_fees(clearing_fees).median;
+
+        FeeEstimate {
+            target_slots,
+            priority_fee: queue_floor.max(recent_clearing),
+            queue_depth: pending_fees.len(),
+            batch_size,
+            slots_sampled: self.slots.len(),
+            congested_slots: congested.len(),
+            recent_fees: FeeDistribution::from_fees(
+                self.slots.iter().flat_map(|stats| stats.included_fees.iter().copied()).collect(),
+            ),
+        }
+    }
+}
+
+impl Default for FeeMarket {
+    fn default() -> Self {
+        Self::new(DEFAULT_FEE_MARKET_WINDOW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AdaptiveBatchConfig {
+        AdaptiveBatchConfig {
+            target_slot_ms: 100,
+            min_batch_size: 4,
+            max_batch_size: 64,
+            initial_batch_size: 32,
+            max_queued_slots: 2,
+        }
+    }
+
+    #[test]
+    fn test_batch_shrinks_on_overrun_and_grows_with_headroom() {
+        let mut sizer = AdaptiveBatchSizer::new(config());
+
+        sizer.record_slot(32, Duration::from_millis(150));
+        assert_eq!(sizer.batch_size(), 24);
+
+        // Partial batches never grow the size
+        sizer.record_slot(10, Duration::from_millis(10));
+        assert_eq!(sizer.batch_size(), 24);
+
+        sizer.record_slot(24, Duration::from_millis(50));
+        assert_eq!(sizer.batch_size(), 27);
+
+        for _ in 0..50 {
+            sizer.record_slot(0, Duration::from_millis(1000));
+        }
+        assert_eq!(sizer.batch_size(), 4);
+    }
+
+    #[test]
+    fn test_validate_rejects_unusable_batch_ranges() {
+        assert!(AdaptiveBatchConfig::default().validate().is_ok());
+        assert!(config().validate().is_ok());
+
+        let mut inverted = config();
+        inverted.min_batch_size = 128;
+        assert!(inverted.validate().is_err());
+
+        let mut empty = config();
+        empty.min_batch_size = 0;
+        assert!(empty.validate().is_err());
+
+        let mut no_queue = config();
+        no_queue.max_queued_slots = 0;
+        assert!(no_queue.validate().is_err());
+    }
+
+    #[test]
+    fn test_backpressure_when_queue_saturated() {
+        let sizer = AdaptiveBatchSizer::new(config());
+
+        assert_eq!(sizer.admit(63), Admission::Accept);
+        assert_eq!(sizer.admit(64), Admission::Backpressure { retry_after_ms: 100 });
+        assert_eq!(sizer.admit(97), Admission::Backpressure { retry_after_ms: 200 });
+    }
+
+    #[test]
+    fn test_fee_estimate_from_queue_and_recent_slots() {
+        let mut market = FeeMarket::new(2);
+
+        // An uncongested market needs no priority fee
+        let estimate = market.estimate(&[5, 1], 4, 1);
+        assert_eq!(estimate.priority_fee, 0);
+        assert_eq!(estimate.queue_depth, 2);
+
+        // A full queue requires outbidding the last transaction that fits
+        assert_eq!(market.estimate(&[1, 9, 3, 7, 5], 2, 1).priority_fee, 8);
+        assert_eq!(market.estimate(&[1, 9, 3, 7, 5], 2, 2).priority_fee, 4);
+        assert_eq!(market.estimate(&[1, 9, 3, 7, 5], 2, 3).priority_fee, 0);
+
+        // Congested slots raise the estimate to their typical clearing fee
+        market.record_slot(SlotFeeStats { slot: 1, queue_depth: 2, included_fees: vec![2, 4], left_pending: 0 });
+        market.record_slot(SlotFeeStats { slot: 2, queue_depth: 6, included_fees: vec![6, 10], left_pending: 4 });
+        market.record_slot(SlotFeeStats { slot: 3, queue_depth: 5, included_fees: vec![8, 12], left_pending: 3 });
+        let estimate = market.estimate(&[], 2, 1);
+        assert_eq!(estimate.slots_sampled, 2);
+        assert_eq!(estimate.congested_slots, 2);
+        assert_eq!(estimate.priority_fee, 6);
+        assert_eq!(estimate.recent_fees, FeeDistribution { min: 6, median: 8, p90: 10, max: 12 });
+    }
+
+    fn transfer(hash: u8, controller: u8, targets: &[u8]) -> Transaction {
+        let targets = targets.iter().map(|&id| UnitsObjectId::new([id; 32])).collect();
+        let instruction = crate::transaction::Instruction::new(UnitsObjectId::new([controller; 32]), "transfer".to_string(), targets, vec![]);
+        Transaction::new(vec![instruction], [hash; 32])
+    }
+
+    #[test]
+    fn test_disjoint_transactions_run_in_parallel_and_conflicts_in_order() {
+        let checker = BasicConflictChecker::new();
+        let batch = [
+            transfer(1, 9, &[1, 2]),
+            transfer(2, 9, &[3]),
+            // Writes what the first transaction writes
+            transfer(3, 9, &[2, 4]),
+            // Disjoint from everything before it
+            transfer(4, 9, &[5]),
+            // Only conflicts with the deferred third transaction
+            transfer(5, 9, &[4]),
+            // Writes the controller every transaction reads
+            transfer(6, 8, &[9]),
+        ];
+        let access: Vec<AccessSet> = batch.iter().map(|transaction| checker.access_set(transaction)).collect();
+        assert!(access[0].reads.contains(&UnitsObjectId::new([9; 32])));
+
+        let scheduler = ParallelScheduler::new(ParallelSchedulerConfig { threads: 4 });
+        let plan = scheduler.plan(&access);
+        assert_eq!(plan.parallel, vec![0, 1, 3]);
+        assert_eq!(plan.serial, vec![2, 4, 5]);
+
+        // Results come back in batch order, whichever thread produced them
+        let hashes = scheduler.execute(batch.to_vec(), &access, |transaction| transaction.hash[0]);
+        assert_eq!(hashes, vec![1, 2, 3, 4, 5, 6]);
+
+        let serial = ParallelScheduler::new(ParallelSchedulerConfig { threads: 1 }).plan(&access);
+        assert!(serial.parallel.is_empty());
+        assert_eq!(serial.serial, (0..6).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_access_set_writes_subsume_reads() {
+        let mut access = AccessSet::default();
+        let id = UnitsObjectId::new([1; 32]);
+        access.insert(id, AccessIntent::Read);
+        access.insert(id, AccessIntent::Write);
+        access.insert(id, AccessIntent::Read);
+        assert!(access.reads.is_empty() && access.writes.contains(&id));
+
+        let mut reader = AccessSet::default();
+        reader.insert(id, AccessIntent::Read);
+        assert!(!reader.conflicts_with(&reader.clone()));
+        assert!(reader.conflicts_with(&access) && access.conflicts_with(&reader));
+    }
+}
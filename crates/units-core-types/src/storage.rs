@@ -10,20 +10,39 @@
 //! - `WriteAheadLog`: Optional durability logging
 //! - `LockManager`: Object-level locking
 //! - `ReceiptStorage`: Transaction receipt management
+//! - `StorageObserver`: Hooks backends call on every operation
 //! 
 //! Concrete implementations are provided by the `units-storage-impl` crate.
 
 use std::collections::HashMap;
+use std::ops::RangeBounds;
 use crate::error::StorageError;
 use crate::id::UnitsObjectId;
 use crate::objects::UnitsObject;
 use crate::{SlotNumber, StateProof, UnitsObjectProof};
-use crate::transaction::TransactionReceipt;
+use crate::transaction::{TransactionEvent, TransactionHash, TransactionReceipt};
 
 //==============================================================================
 // CORE STORAGE TRAIT
 //==============================================================================
 
+/// One write in a mixed batch applied by [`ObjectStorage::apply_batch`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchOp {
+    Set(UnitsObject),
+    Delete(UnitsObjectId),
+}
+
+impl BatchOp {
+    /// ID of the object the operation writes
+    pub fn object_id(&self) -> UnitsObjectId {
+        match self {
+            Self::Set(object) => *object.id(),
+            Self::Delete(id) => *id,
+        }
+    }
+}
+
 /// Core storage interface for UNITS objects
 /// 
 /// This trait focuses solely on object persistence and retrieval.
@@ -50,6 +69,29 @@ pub trait ObjectStorage: Send + Sync {
         transaction_hash: Option<[u8; 32]>,
     ) -> Result<UnitsObjectProof, StorageError>;
     
+    /// Write version of an object: the number of `set` and `delete` calls
+    /// applied to it, or 0 if it was never written
+    ///
+    /// Versions only increase, including across deletion and re-creation,
+    /// so clients can use them as compare-and-swap preconditions.
+    fn version(&self, id: &UnitsObjectId) -> Result<u64, StorageError>;
+    
+    /// Delete every ephemeral object that has expired by `slot`
+    ///
+    /// Returns the IDs of the deleted objects. Expired objects stay
+    /// readable until this runs.
+    fn purge_expired(&self, slot: SlotNumber) -> Result<Vec<UnitsObjectId>, StorageError> {
+        let expired = self
+            .iter()
+            .filter(|object| object.as_ref().map_or(true, |object| object.is_expired(slot)))
+            .map(|object| object.map(|object| object.id))
+            .collect::<Result<Vec<_>, _>>()?;
+        for id in &expired {
+            self.delete(id, None)?;
+        }
+        Ok(expired)
+    }
+    
     /// Check if an object exists
     fn exists(&self, id: &UnitsObjectId) -> Result<bool, StorageError> {
         Ok(self.get(id)?.is_some())
@@ -88,15 +130,91 @@ pub trait ObjectStorage: Send + Sync {
         }
         Ok(proofs)
     }
+
+    /// Apply a mixed sequence of sets and deletes, in order, as one write
+    /// under `transaction_hash`
+    ///
+    /// Either every operation is applied or none is: deleting an object
+    /// that neither exists nor is set earlier in the batch fails the whole
+    /// batch before anything is written. Returns the proof of the last
+    /// write to each object.
+    ///
+    /// The default checks the batch up front and then writes one operation
+    /// at a time, so concurrent readers may see it half applied; backends
+    /// able to write under a single lock should override it.
+    fn apply_batch(
+        &self,
+        ops: &[BatchOp],
+        transaction_hash: [u8; 32],
+    ) -> Result<HashMap<UnitsObjectId, UnitsObjectProof>, StorageError> {
+        let mut exists = HashMap::new();
+        for op in ops {
+            let id = op.object_id();
+            let present = match exists.get(&id) {
+                Some(present) => *present,
+                None => self.exists(&id)?,
+            };
+            if matches!(op, BatchOp::Delete(_)) && !present {
+                return Err(StorageError::NotFound(format!("Object not found: {:?}", id)));
+            }
+            exists.insert(id, matches!(op, BatchOp::Set(_)));
+        }
+
+        let mut proofs = HashMap::new();
+        for op in ops {
+            let proof = match op {
+                BatchOp::Set(object) => self.set(object, Some(transaction_hash))?,
+                BatchOp::Delete(id) => self.delete(id, Some(transaction_hash))?,
+            };
+            proofs.insert(op.object_id(), proof);
+        }
+        Ok(proofs)
+    }
     
     //--------------------------------------------------------------------------
     // ITERATION
     //--------------------------------------------------------------------------
     
-    /// Iterate over all objects in storage
+    /// Iterate over all objects in storage, in ascending ID order
     /// 
     /// Returns a standard iterator - no complex async adapters
     fn iter(&self) -> Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_>;
+
+    /// Iterate over the objects whose IDs fall in `range`, in ascending ID order
+    ///
+    /// The default walks `iter` up to the end of the range; backends with
+    /// ordered indexes should seek to its start instead.
+    fn iter_range<R>(&self, range: R) -> Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_>
+    where
+        R: RangeBounds<UnitsObjectId>,
+    {
+        let bounds = (range.start_bound().cloned(), range.end_bound().cloned());
+        Box::new(
+            self.iter()
+                .skip_while(move |result| matches!(result, Ok(object) if !bounds.contains(object.id())))
+                .take_while(move |result| result.as_ref().map_or(true, |object| bounds.contains(object.id()))),
+        )
+    }
+
+    /// Iterate over the objects whose IDs start with `prefix`, in ascending ID order
+    ///
+    /// Objects created at IDs sharing a prefix, such as a controller's
+    /// derived objects, can be listed without scanning the whole store.
+    fn iter_prefix(&self, prefix: &[u8]) -> Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_> {
+        match UnitsObjectId::prefix_range(prefix) {
+            Some(bounds) => self.iter_range(bounds),
+            None => Box::new(std::iter::empty()),
+        }
+    }
+    
+    /// Iterate over the objects managed by `controller_id`, in ascending ID order
+    ///
+    /// The default filters `iter`, visiting every object; backends keeping
+    /// a controller index should serve it from there.
+    fn iter_by_controller(&self, controller_id: &UnitsObjectId) -> Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_> {
+        let controller_id = *controller_id;
+        self.iter_filtered(move |object| object.controller_id == controller_id)
+    }
     
     /// Iterate over objects matching a filter
     fn iter_filtered<F>(&self, filter: F) -> Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_>
@@ -185,12 +303,52 @@ pub trait ProofStorage: Send + Sync {
         start_slot: SlotNumber,
         end_slot: SlotNumber,
     ) -> Result<Vec<StateProof>, StorageError>;
+
+    /// Iterate state proofs in `[start_slot, end_slot]` in slot order
+    ///
+    /// Proofs are loaded one at a time, so the range can be arbitrarily
+    /// large. The default probes every slot; stores that know which slots
+    /// hold proofs should override it.
+    fn iter_state_proofs(
+        &self,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+    ) -> Box<dyn Iterator<Item = Result<StateProof, StorageError>> + '_> {
+        Box::new(
+            (start_slot..=end_slot).filter_map(move |slot| self.get_state_proof(slot).transpose()),
+        )
+    }
+
+    /// Store an encoded node of the sparse Merkle tree over object proofs
+    ///
+    /// Nodes are keyed by their hash and never change, so storing one twice
+    /// is harmless. Stores that cannot hold nodes keep the default, which
+    /// refuses them.
+    fn store_merkle_node(&self, _hash: &[u8; 32], _node: &[u8]) -> Result<(), StorageError> {
+        Err(StorageError::Unimplemented("sparse Merkle tree nodes".to_string()))
+    }
+
+    /// Get a node stored by `store_merkle_node`
+    fn get_merkle_node(&self, _hash: &[u8; 32]) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(None)
+    }
 }
 
 //==============================================================================
 // WRITE-AHEAD LOG TRAIT
 //==============================================================================
 
+/// Condition of a write-ahead log's tail, as found by [`WriteAheadLog::check_tail`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WalTail {
+    /// Complete entries in the log
+    pub entries: usize,
+    /// Bytes of a final entry whose append never finished, 0 if none
+    pub torn_bytes: u64,
+    /// Whether those bytes were cut off
+    pub truncated: bool,
+}
+
 /// Optional write-ahead log for durability
 /// 
 /// This is a separate concern that can be composed with storage
@@ -213,12 +371,74 @@ pub trait WriteAheadLog: Send + Sync {
     fn replay<F>(&self, callback: F) -> Result<(), StorageError>
     where
         F: FnMut(&UnitsObject, &UnitsObjectProof) -> Result<(), StorageError>;
+
+    /// Number of entries currently in the log
+    fn entry_count(&self) -> Result<usize, StorageError>;
+
+    /// Drop every entry, returning how many were removed
+    ///
+    /// Only safe once the logged updates are durable elsewhere, since they
+    /// can no longer be replayed.
+    fn truncate(&self) -> Result<usize, StorageError>;
+
+    /// Check every entry decodes, allowing only a torn final entry
+    ///
+    /// A crash during an append can leave the last entry incomplete; with
+    /// `repair` it is cut off so appends continue after the last complete
+    /// one. Damage anywhere else is an error. Logs that cannot tear only
+    /// count their entries.
+    fn check_tail(&self, _repair: bool) -> Result<WalTail, StorageError> {
+        Ok(WalTail { entries: self.entry_count()?, ..WalTail::default() })
+    }
 }
 
 //==============================================================================
 // RECEIPT STORAGE TRAIT
 //==============================================================================
 
+/// Receipts of one slot at a time, as yielded by `iter_receipts_by_slot`
+pub type SlotReceiptsIter<'a> =
+    Box<dyn Iterator<Item = Result<(SlotNumber, Vec<TransactionReceipt>), StorageError>> + 'a>;
+
+/// One page of a paginated receipt query
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ReceiptPage {
+    /// Receipts in slot order, then by transaction hash
+    pub receipts: Vec<TransactionReceipt>,
+    /// Cursor for the next page: the hash of the last receipt returned,
+    /// absent once the query is exhausted
+    pub next: Option<TransactionHash>,
+}
+
+impl ReceiptPage {
+    /// Page of up to `limit` receipts following `after` in `receipts`
+    ///
+    /// `receipts` must already be in slot then hash order; reading stops
+    /// one receipt past the page, just far enough to know whether more remain.
+    pub fn after<I>(
+        receipts: I,
+        after: Option<(SlotNumber, TransactionHash)>,
+        limit: usize,
+    ) -> Result<Self, StorageError>
+    where
+        I: IntoIterator<Item = Result<TransactionReceipt, StorageError>>,
+    {
+        let mut page = Vec::new();
+        for receipt in receipts {
+            let receipt = receipt?;
+            if after.is_some_and(|after| (receipt.slot, receipt.transaction_hash) <= after) {
+                continue;
+            }
+            if page.len() == limit {
+                let next = page.last().map(|last: &TransactionReceipt| last.transaction_hash);
+                return Ok(Self { receipts: page, next });
+            }
+            page.push(receipt);
+        }
+        Ok(Self { receipts: page, next: None })
+    }
+}
+
 /// Storage for transaction receipts
 /// 
 /// This consolidates transaction receipt storage into a single, focused trait
@@ -247,6 +467,49 @@ pub trait ReceiptStorage: Send + Sync {
         start_slot: SlotNumber,
         end_slot: SlotNumber,
     ) -> Result<Vec<TransactionReceipt>, StorageError>;
+
+    /// Iterate the receipts in `[start_slot, end_slot]` one slot at a time
+    ///
+    /// Each item holds every receipt of one slot that has any, in slot
+    /// order, so only a single slot's receipts are in memory at once. The
+    /// default probes every slot; stores that know which slots hold
+    /// receipts should override it.
+    fn iter_receipts_by_slot(
+        &self,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+    ) -> SlotReceiptsIter<'_> {
+        Box::new((start_slot..=end_slot).filter_map(move |slot| match self.get_receipts_for_slot(slot) {
+            Ok(receipts) if receipts.is_empty() => None,
+            Ok(receipts) => Some(Ok((slot, receipts))),
+            Err(error) => Some(Err(error)),
+        }))
+    }
+    
+    /// Page of up to `limit` receipts in `[start_slot, end_slot]` following
+    /// the receipt `after`, or from the start of the range without it
+    ///
+    /// Receipts are ordered by slot, then transaction hash. The default
+    /// walks `iter_receipts_by_slot` from the cursor's slot, so it holds one
+    /// slot's receipts at a time.
+    fn get_receipts_range_page(
+        &self,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+        after: Option<TransactionHash>,
+        limit: usize,
+    ) -> Result<ReceiptPage, StorageError> {
+        let after = self.receipt_cursor(after)?;
+        let from = after.map_or(start_slot, |(slot, _)| slot.max(start_slot));
+        let receipts = self.iter_receipts_by_slot(from, end_slot).flat_map(|slot_receipts| match slot_receipts {
+            Ok((_, mut receipts)) => {
+                receipts.sort_by_key(|receipt| receipt.transaction_hash);
+                receipts.into_iter().map(Ok).collect()
+            }
+            Err(error) => vec![Err(error)],
+        });
+        ReceiptPage::after(receipts, after, limit)
+    }
     
     /// Get receipts affecting a specific object
     fn get_receipts_for_object(
@@ -255,6 +518,65 @@ pub trait ReceiptStorage: Send + Sync {
         start_slot: Option<SlotNumber>,
         end_slot: Option<SlotNumber>,
     ) -> Result<Vec<TransactionReceipt>, StorageError>;
+
+    /// Page of up to `limit` receipts affecting `object_id` following the
+    /// receipt `after`, ordered like [`ReceiptStorage::get_receipts_range_page`]
+    fn get_receipts_for_object_page(
+        &self,
+        object_id: &UnitsObjectId,
+        start_slot: Option<SlotNumber>,
+        end_slot: Option<SlotNumber>,
+        after: Option<TransactionHash>,
+        limit: usize,
+    ) -> Result<ReceiptPage, StorageError> {
+        let after = self.receipt_cursor(after)?;
+        let from = match (start_slot, after) {
+            (start, Some((slot, _))) => Some(start.map_or(slot, |start| start.max(slot))),
+            (start, None) => start,
+        };
+        let mut receipts = self.get_receipts_for_object(object_id, from, end_slot)?;
+        receipts.sort_by_key(|receipt| (receipt.slot, receipt.transaction_hash));
+        ReceiptPage::after(receipts.into_iter().map(Ok), after, limit)
+    }
+
+    /// Events emitted about `object_id`, optionally bounded to a slot range
+    ///
+    /// Events are ordered like [`ReceiptStorage::get_receipts_range_page`],
+    /// then in emission order within a receipt. An object need not have been
+    /// modified by a transaction for its events to be returned.
+    fn get_events_for_object(
+        &self,
+        object_id: &UnitsObjectId,
+        start_slot: Option<SlotNumber>,
+        end_slot: Option<SlotNumber>,
+    ) -> Result<Vec<TransactionEvent>, StorageError> {
+        let mut receipts = self.get_receipts_range(
+            start_slot.unwrap_or(SlotNumber::MIN),
+            end_slot.unwrap_or(SlotNumber::MAX),
+        )?;
+        receipts.sort_by_key(|receipt| (receipt.slot, receipt.transaction_hash));
+        Ok(receipts
+            .into_iter()
+            .flat_map(|receipt| receipt.events)
+            .filter(|event| event.object_id == *object_id)
+            .collect())
+    }
+
+    /// Slot and hash of the cursor receipt `after`
+    ///
+    /// A cursor whose receipt has been cleaned up is reported as not found
+    /// rather than silently restarting the query.
+    fn receipt_cursor(
+        &self,
+        after: Option<TransactionHash>,
+    ) -> Result<Option<(SlotNumber, TransactionHash)>, StorageError> {
+        after
+            .map(|hash| match self.get_receipt(&hash)? {
+                Some(receipt) => Ok((receipt.slot, hash)),
+                None => Err(StorageError::NotFound(format!("Receipt cursor not found: {}", hex::encode(hash)))),
+            })
+            .transpose()
+    }
     
     /// Delete old receipts before a slot (for cleanup)
     fn cleanup_receipts_before(
@@ -282,6 +604,36 @@ pub trait LockManager: Send + Sync {
     fn lock_many(&self, ids: &[UnitsObjectId]) -> Result<Vec<Self::Guard<'_>>, StorageError>;
 }
 
+//==============================================================================
+// STORAGE OBSERVER TRAIT
+//==============================================================================
+
+/// Proof recorded by a storage backend, as passed to [`StorageObserver::on_proof`]
+#[derive(Debug, Clone, Copy)]
+pub enum ObservedProof<'a> {
+    Object(&'a UnitsObjectProof),
+    State(&'a StateProof),
+}
+
+/// Hooks storage backends call after each successful operation
+///
+/// Observers add logging, statistics or invariant checks without changing
+/// backend code. They run inline on the storage path, so they should be
+/// cheap and must not call back into the storage that invokes them.
+pub trait StorageObserver: Send + Sync {
+    /// An object was looked up; `found` is whether it existed
+    fn on_get(&self, _id: &UnitsObjectId, _found: bool) {}
+
+    /// An object was written and received `proof`
+    fn on_set(&self, _object: &UnitsObject, _proof: &UnitsObjectProof) {}
+
+    /// An object was deleted and received the deletion `proof`
+    fn on_delete(&self, _id: &UnitsObjectId, _proof: &UnitsObjectProof) {}
+
+    /// A proof was stored
+    fn on_proof(&self, _proof: ObservedProof<'_>) {}
+}
+
 //==============================================================================
 // COMPOSED STORAGE TYPE
 //==============================================================================
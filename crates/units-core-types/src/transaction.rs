@@ -1,8 +1,19 @@
 use crate::id::UnitsObjectId;
+#[cfg(feature = "std")]
 use crate::locks::{ObjectLockGuard, PersistentLockManager};
 use crate::objects::UnitsObject;
 use crate::UnitsObjectProof;
+use alloc::string::String;
+use alloc::vec::Vec;
+use borsh::{BorshDeserialize, BorshSerialize};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap as HashMap;
+#[cfg(not(feature = "std"))]
+use alloc::format;
+#[cfg(feature = "std")]
 use std::collections::HashMap;
 
 /// Transaction hash type (32-byte array)
@@ -44,7 +55,7 @@ impl Default for CommitmentLevel {
 pub const STANDARD_ENTRYPOINT: &str = "main";
 
 /// Transaction instruction - call into controller entrypoint with target function
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
 pub struct Instruction {
     /// The controller kernel module to execute
     pub controller_id: UnitsObjectId,
@@ -97,6 +108,82 @@ impl Instruction {
     }
 }
 
+/// Precondition that an object is at a given write version
+///
+/// Versions come from `ObjectStorage::version`; an object that was never
+/// written is at version 0, so expecting 0 asserts the object is new.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpectedVersion {
+    pub object_id: UnitsObjectId,
+    pub version: u64,
+}
+
+/// Domain separator prefixed to every fee commitment a sponsor signs
+pub const FEE_SPONSORSHIP_DOMAIN: &[u8] = b"units-fee-sponsorship-v2";
+
+/// Fee payer other than the transaction's signers
+///
+/// The sponsor signs only a fee commitment: the transaction's content
+/// digest, its own next nonce and the most it agrees to pay. Its signature
+/// authorizes the fee and nothing the instructions do, yet cannot be moved
+/// to a transaction doing something else under the same hash, nor charged
+/// twice. The fee is charged to the sponsor's account in the fee ledger,
+/// keyed by its public key, which also holds the sponsor's nonce.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeSponsorship {
+    /// Sponsor's Ed25519 public key
+    pub payer_key: [u8; 32],
+    /// Most the sponsor pays; the priority fee must not exceed it
+    pub max_fee: u64,
+    /// Must be the sponsor's next nonce in the fee ledger when the fee is charged
+    pub nonce: u64,
+    /// Sponsor's 64-byte signature over [`FeeSponsorship::commitment`]
+    pub signature: Vec<u8>,
+}
+
+impl FeeSponsorship {
+    /// Message the sponsor signs to pay up to `max_fee` for `transaction`
+    /// with its nonce `nonce`
+    pub fn commitment(transaction: &Transaction, nonce: u64, max_fee: u64) -> Vec<u8> {
+        let mut message = Vec::with_capacity(FEE_SPONSORSHIP_DOMAIN.len() + 32 + 8 + 8);
+        message.extend_from_slice(FEE_SPONSORSHIP_DOMAIN);
+        message.extend_from_slice(&transaction.content_digest());
+        message.extend_from_slice(&nonce.to_le_bytes());
+        message.extend_from_slice(&max_fee.to_le_bytes());
+        message
+    }
+
+    /// Fee ledger account the sponsor pays from
+    pub fn payer_id(&self) -> UnitsObjectId {
+        UnitsObjectId::new(self.payer_key)
+    }
+
+    /// Check the sponsor's signature over the commitment for `transaction`
+    pub fn verify(&self, transaction: &Transaction) -> Result<(), String> {
+        let signature: &[u8; 64] = self.signature.as_slice().try_into().map_err(|_| {
+            alloc::format!("Fee sponsor signature must be 64 bytes, got {}", self.signature.len())
+        })?;
+        let message = Self::commitment(transaction, self.nonce, self.max_fee);
+        if !crate::ed25519::verify(&self.payer_key, &message, signature) {
+            return Err("Fee sponsor signature does not match the fee commitment".into());
+        }
+        Ok(())
+    }
+}
+
+/// Fee charged for a transaction, as recorded in its receipt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeePayment {
+    /// Fee ledger account charged
+    pub payer: UnitsObjectId,
+    pub amount: u64,
+    /// Whether the payer sponsored the transaction rather than signing it
+    pub sponsored: bool,
+}
+
+/// Longest memo a transaction may carry, in bytes
+pub const MAX_MEMO_LEN: usize = 256;
+
 /// Transaction that contains multiple instructions to be executed
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transaction {
@@ -108,6 +195,26 @@ pub struct Transaction {
 
     /// The commitment level of this transaction
     pub commitment_level: CommitmentLevel,
+
+    /// Fee offered for earlier inclusion; pending transactions with higher
+    /// fees are admitted to slots first
+    #[serde(default)]
+    pub priority_fee: u64,
+
+    /// Object versions the transaction was built against; it fails without
+    /// executing if any object has been written since (compare-and-swap)
+    #[serde(default)]
+    pub expected_versions: Vec<ExpectedVersion>,
+
+    /// Separate payer of the priority fee, checked apart from the
+    /// instructions' own authorization
+    #[serde(default)]
+    pub sponsorship: Option<FeeSponsorship>,
+
+    /// Caller's reference, such as an invoice number, copied into the
+    /// receipt; at most [`MAX_MEMO_LEN`] bytes and never interpreted
+    #[serde(default)]
+    pub memo: Option<Vec<u8>>,
 }
 
 impl Transaction {
@@ -117,7 +224,85 @@ impl Transaction {
             instructions,
             hash,
             commitment_level: CommitmentLevel::Processing,
+            priority_fee: 0,
+            expected_versions: Vec::new(),
+            sponsorship: None,
+            memo: None,
+        }
+    }
+
+    /// Set the priority fee offered for inclusion
+    pub fn with_priority_fee(mut self, priority_fee: u64) -> Self {
+        self.priority_fee = priority_fee;
+        self
+    }
+
+    /// Have the fee paid by the sponsor behind `sponsorship`
+    pub fn with_sponsorship(mut self, sponsorship: FeeSponsorship) -> Self {
+        self.sponsorship = Some(sponsorship);
+        self
+    }
+
+    /// Append `instruction`, to run after those already added
+    ///
+    /// All instructions of a transaction commit together or not at all,
+    /// so flows spanning several controllers belong in one transaction.
+    pub fn with_instruction(mut self, instruction: Instruction) -> Self {
+        self.instructions.push(instruction);
+        self
+    }
+
+    /// Attach `memo` to the transaction and its receipt
+    pub fn with_memo(mut self, memo: impl Into<Vec<u8>>) -> Self {
+        self.memo = Some(memo.into());
+        self
+    }
+
+    /// Digest of what the transaction does: its hash, instructions,
+    /// priority fee and expected versions
+    ///
+    /// Variable-length fields are length-prefixed, so bytes cannot move
+    /// between fields without changing the digest.
+    pub fn content_digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.hash);
+        hasher.update((self.instructions.len() as u64).to_le_bytes());
+        for instruction in &self.instructions {
+            hasher.update(instruction.controller_id.bytes());
+            hasher.update((instruction.target_function.len() as u64).to_le_bytes());
+            hasher.update(instruction.target_function.as_bytes());
+            hasher.update((instruction.target_objects.len() as u64).to_le_bytes());
+            for target in &instruction.target_objects {
+                hasher.update(target.bytes());
+            }
+            hasher.update((instruction.params.len() as u64).to_le_bytes());
+            hasher.update(&instruction.params);
         }
+        hasher.update(self.priority_fee.to_le_bytes());
+        hasher.update((self.expected_versions.len() as u64).to_le_bytes());
+        for expected in &self.expected_versions {
+            hasher.update(expected.object_id.bytes());
+            hasher.update(expected.version.to_le_bytes());
+        }
+        hasher.finalize().into()
+    }
+
+    /// Check the memo fits within [`MAX_MEMO_LEN`]
+    pub fn check_memo(&self) -> Result<(), String> {
+        match &self.memo {
+            Some(memo) if memo.len() > MAX_MEMO_LEN => Err(format!(
+                "Memo of {} bytes exceeds the maximum of {}",
+                memo.len(),
+                MAX_MEMO_LEN
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Require `object_id` to still be at `version` when the transaction executes
+    pub fn with_expected_version(mut self, object_id: UnitsObjectId, version: u64) -> Self {
+        self.expected_versions.push(ExpectedVersion { object_id, version });
+        self
     }
 
     /// Mark the transaction as committed
@@ -137,6 +322,7 @@ impl Transaction {
 
     /// Acquire all locks needed for this transaction
     /// TODO: Implement with new object model - requires access intent information
+    #[cfg(feature = "std")]
     pub fn acquire_locks<'a, M: PersistentLockManager>(
         &self,
         _lock_manager: &'a M,
@@ -160,6 +346,7 @@ impl Transaction {
     /// # Returns
     /// A result containing the result of the execution function if successful,
     /// or an error if any lock could not be acquired
+    #[cfg(feature = "std")]
     pub fn execute_with_locks<'a, M: PersistentLockManager, F, R>(
         &self,
         lock_manager: &'a M,
@@ -180,7 +367,7 @@ impl Transaction {
 
     /// Create in-memory locks for testing
     /// TODO: Implement with new object model
-    #[cfg(test)]
+    #[cfg(all(test, feature = "std"))]
     pub fn create_in_memory_locks<M: PersistentLockManager>(
         &self,
     ) -> Vec<ObjectLockGuard<'static, M>> {
@@ -189,6 +376,7 @@ impl Transaction {
 
     /// Check if all locks needed for this transaction can be acquired
     /// TODO: Implement with new object model
+    #[cfg(feature = "std")]
     pub fn can_acquire_all_locks<M: PersistentLockManager>(
         &self,
         _lock_manager: &M,
@@ -211,11 +399,32 @@ pub struct TransactionEffect {
     
     /// The state of the object after the transaction (None if object was deleted)
     pub after_image: Option<UnitsObject>,
+
+    /// State hash of `before_image`, letting verifiers check the effect
+    /// against object proofs without the full object
+    #[serde(default)]
+    pub before_hash: Option<[u8; 32]>,
+
+    /// State hash of `after_image`
+    #[serde(default)]
+    pub after_hash: Option<[u8; 32]>,
 }
 
 /// Alias for transaction effect to maintain API compatibility
 pub type ObjectEffect = TransactionEffect;
 
+/// Append `effect` to `effects`, or merge it into the effect on the same object
+pub(crate) fn merge_effect(effects: &mut Vec<TransactionEffect>, effect: TransactionEffect) {
+    match effects.iter().position(|earlier| earlier.object_id == effect.object_id) {
+        Some(index) => {
+            if !effects[index].merge(effect.after_image) {
+                effects.remove(index);
+            }
+        }
+        None => effects.push(effect),
+    }
+}
+
 impl TransactionEffect {
     /// Get the transaction hash for this effect
     pub fn transaction_hash(&self) -> &TransactionHash {
@@ -227,17 +436,63 @@ impl TransactionEffect {
         &self.object_id
     }
     
+    /// Fold in `after_image`, the object's state after a later change in
+    /// the same transaction
+    ///
+    /// The effect keeps its before image, so it describes the net change.
+    /// Returns false if nothing is left of it: the object was created and
+    /// deleted again.
+    pub fn merge(&mut self, after_image: Option<UnitsObject>) -> bool {
+        self.after_image = after_image;
+        self.compute_hashes();
+        self.before_image.is_some() || self.after_image.is_some()
+    }
+
+    /// Create an effect from its object images, computing both state hashes
+    pub fn from_images(
+        transaction_hash: TransactionHash,
+        object_id: UnitsObjectId,
+        before_image: Option<UnitsObject>,
+        after_image: Option<UnitsObject>,
+    ) -> Self {
+        let mut effect = Self {
+            transaction_hash,
+            object_id,
+            before_image,
+            after_image,
+            before_hash: None,
+            after_hash: None,
+        };
+        effect.compute_hashes();
+        effect
+    }
+
+    /// Recompute `before_hash` and `after_hash` from the current images
+    pub fn compute_hashes(&mut self) {
+        self.before_hash = self.before_image.as_ref().map(UnitsObject::state_hash);
+        self.after_hash = self.after_image.as_ref().map(UnitsObject::state_hash);
+    }
+
+    /// Whether the stored hashes match the images they describe
+    pub fn hashes_match_images(&self) -> bool {
+        self.before_hash == self.before_image.as_ref().map(UnitsObject::state_hash)
+            && self.after_hash == self.after_image.as_ref().map(UnitsObject::state_hash)
+    }
+
+    /// Whether `proof` commits to the state this effect produced
+    ///
+    /// Only needs the effect's hashes, so it works on receipts whose
+    /// images have been stripped.
+    pub fn matches_proof(&self, proof: &UnitsObjectProof) -> bool {
+        proof.object_id == self.object_id && self.after_hash == Some(proof.object_hash)
+    }
+
     /// Create a new effect for object creation
     pub fn new_creation(
         transaction_hash: TransactionHash,
         object: UnitsObject,
     ) -> Self {
-        Self {
-            transaction_hash,
-            object_id: *object.id(),
-            before_image: None,
-            after_image: Some(object),
-        }
+        Self::from_images(transaction_hash, *object.id(), None, Some(object))
     }
     
     /// Create a new effect for object deletion
@@ -245,12 +500,7 @@ impl TransactionEffect {
         transaction_hash: TransactionHash,
         object: UnitsObject,
     ) -> Self {
-        Self {
-            transaction_hash,
-            object_id: *object.id(),
-            before_image: Some(object),
-            after_image: None,
-        }
+        Self::from_images(transaction_hash, *object.id(), Some(object), None)
     }
     
     /// Create a new effect for object modification
@@ -259,12 +509,7 @@ impl TransactionEffect {
         before: UnitsObject,
         after: UnitsObject,
     ) -> Self {
-        Self {
-            transaction_hash,
-            object_id: *after.id(),
-            before_image: Some(before),
-            after_image: Some(after),
-        }
+        Self::from_images(transaction_hash, *after.id(), Some(before), Some(after))
     }
     
     /// Check if this effect represents an object creation
@@ -284,6 +529,92 @@ impl TransactionEffect {
 }
 
 
+/// Deterministic resource usage of a single instruction execution
+///
+/// Counters depend only on the program and its input, so they can be
+/// compared across nodes and used to calibrate gas tables.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionMetrics {
+    /// Number of VM instructions retired
+    pub instructions_executed: u64,
+    /// Largest amount of guest memory mapped during execution, in bytes
+    pub peak_memory_bytes: u64,
+    /// Number of syscalls made by the program
+    pub syscall_count: u64,
+    /// Compute units charged for the instructions and syscalls, per the executor's cost table
+    #[serde(default)]
+    pub compute_units: u64,
+}
+
+impl ExecutionMetrics {
+    /// Combine the usage of two executions
+    pub fn accumulate(&mut self, other: &ExecutionMetrics) {
+        self.instructions_executed = self.instructions_executed.saturating_add(other.instructions_executed);
+        self.peak_memory_bytes = self.peak_memory_bytes.max(other.peak_memory_bytes);
+        self.syscall_count = self.syscall_count.saturating_add(other.syscall_count);
+        self.compute_units = self.compute_units.saturating_add(other.compute_units);
+    }
+}
+
+/// Note an effect processor attached to a receipt
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiptAnnotation {
+    /// Name of the processor that made the note
+    pub processor: String,
+    pub key: String,
+    pub value: String,
+}
+
+/// Event a controller emitted while executing a transaction
+///
+/// The runtime stamps the emitting controller, so an event can only claim
+/// to come from the module that actually emitted it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionEvent {
+    pub transaction_hash: TransactionHash,
+    /// Controller whose module emitted the event
+    pub controller_id: UnitsObjectId,
+    /// Object the event concerns
+    pub object_id: UnitsObjectId,
+    pub name: String,
+    /// Payload, encoded as the emitting module defines
+    pub data: Vec<u8>,
+}
+
+/// Object a transaction targeted and read without writing it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectRead {
+    pub object_id: UnitsObjectId,
+    /// State hash of the image read, absent if the object did not exist
+    pub state_hash: Option<[u8; 32]>,
+}
+
+/// Instruction whose controller exited with an error code
+///
+/// The name and message come from the error table the controller registered
+/// with the module manager, when it has an entry for the code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionFailure {
+    /// Position of the failed instruction in the transaction
+    pub instruction_index: usize,
+    pub controller_id: UnitsObjectId,
+    /// Exit status of the controller program
+    pub code: u32,
+    /// Registered name of the code, such as `ERROR_INVALID_USERNAME`
+    pub name: Option<String>,
+    pub message: Option<String>,
+}
+
+impl core::fmt::Display for ExecutionFailure {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match (&self.name, &self.message) {
+            (Some(name), Some(message)) => write!(f, "{} ({}): {}", name, self.code, message),
+            (Some(name), None) => write!(f, "{} ({})", name, self.code),
+            _ => write!(f, "Program exited with code: {}", self.code),
+        }
+    }
+}
+
 /// A receipt of a processed transaction, containing all proofs of object modifications
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionReceipt {
@@ -310,6 +641,36 @@ pub struct TransactionReceipt {
 
     /// Effects of the transaction on objects
     pub effects: Vec<TransactionEffect>,
+
+    /// VM resource usage of each executed instruction, in execution order
+    #[serde(default)]
+    pub instruction_metrics: Vec<ExecutionMetrics>,
+
+    /// Notes attached by the runtime's effect processors, in the order made
+    #[serde(default)]
+    pub annotations: Vec<ReceiptAnnotation>,
+
+    /// Controller error code the transaction failed with, if it failed that way
+    #[serde(default)]
+    pub failure: Option<ExecutionFailure>,
+
+    /// Fee charged for the transaction, absent when none was
+    #[serde(default)]
+    pub fee: Option<FeePayment>,
+
+    /// Memo of the transaction, as submitted
+    #[serde(default)]
+    pub memo: Option<Vec<u8>>,
+
+    /// Objects the instructions targeted but did not write, in ID order;
+    /// with the effects, the transaction's access set
+    #[serde(default)]
+    pub reads: Vec<ObjectRead>,
+
+    /// Events the controllers emitted, in execution order; empty for a
+    /// failed transaction
+    #[serde(default)]
+    pub events: Vec<TransactionEvent>,
 }
 
 impl TransactionReceipt {
@@ -333,6 +694,13 @@ impl TransactionReceipt {
             },
             error_message: None,
             effects: Vec::new(),
+            instruction_metrics: Vec::new(),
+            annotations: Vec::new(),
+            failure: None,
+            fee: None,
+            memo: None,
+            reads: Vec::new(),
+            events: Vec::new(),
         }
     }
 
@@ -353,6 +721,13 @@ impl TransactionReceipt {
             commitment_level,
             error_message: None,
             effects: Vec::new(),
+            instruction_metrics: Vec::new(),
+            annotations: Vec::new(),
+            failure: None,
+            fee: None,
+            memo: None,
+            reads: Vec::new(),
+            events: Vec::new(),
         }
     }
 
@@ -362,6 +737,32 @@ impl TransactionReceipt {
     }
     
 
+    /// Record the objects `transaction` targeted that no effect wrote
+    ///
+    /// `current` resolves an object as the transaction left it, which for
+    /// an object it did not write is the image it read.
+    pub fn record_reads<E>(
+        &mut self,
+        transaction: &Transaction,
+        mut current: impl FnMut(&UnitsObjectId) -> Result<Option<UnitsObject>, E>,
+    ) -> Result<(), E> {
+        let mut targets: Vec<UnitsObjectId> = transaction
+            .instructions
+            .iter()
+            .flat_map(|instruction| instruction.target_objects.iter().copied())
+            .filter(|id| !self.effects.iter().any(|effect| effect.object_id == *id))
+            .collect();
+        targets.sort_unstable();
+        targets.dedup();
+
+        self.reads = Vec::with_capacity(targets.len());
+        for object_id in targets {
+            let state_hash = current(&object_id)?.as_ref().map(UnitsObject::state_hash);
+            self.reads.push(ObjectRead { object_id, state_hash });
+        }
+        Ok(())
+    }
+
     /// Add an effect to the receipt
     pub fn add_effect(&mut self, effect: TransactionEffect) {
         self.effects.push(effect);
@@ -375,17 +776,59 @@ impl TransactionReceipt {
         before_image: Option<UnitsObject>,
         after_image: Option<UnitsObject>,
     ) {
-        let effect = TransactionEffect {
+        let effect = TransactionEffect::from_images(
             transaction_hash,
             object_id,
             before_image,
             after_image,
-        };
+        );
         
         self.effects.push(effect);
     }
     
 
+    /// Record a change to `object_id`, merged into the receipt's effect on
+    /// that object if an earlier instruction already changed it
+    ///
+    /// Receipts built this way carry one net effect per object, whose
+    /// after hash matches the object's proof.
+    pub fn merge_object_effect(
+        &mut self,
+        transaction_hash: TransactionHash,
+        object_id: UnitsObjectId,
+        before_image: Option<UnitsObject>,
+        after_image: Option<UnitsObject>,
+    ) {
+        merge_effect(
+            &mut self.effects,
+            TransactionEffect::from_images(transaction_hash, object_id, before_image, after_image),
+        );
+    }
+
+    /// Events concerning `object_id`, in the order emitted
+    pub fn events_for_object<'a>(&'a self, object_id: &'a UnitsObjectId) -> impl Iterator<Item = &'a TransactionEvent> {
+        self.events.iter().filter(move |event| &event.object_id == object_id)
+    }
+
+    /// Record the resource usage of the next executed instruction
+    pub fn add_instruction_metrics(&mut self, metrics: ExecutionMetrics) {
+        self.instruction_metrics.push(metrics);
+    }
+
+    /// Resource usage summed across all instructions
+    pub fn total_metrics(&self) -> ExecutionMetrics {
+        let mut total = ExecutionMetrics::default();
+        for metrics in &self.instruction_metrics {
+            total.accumulate(metrics);
+        }
+        total
+    }
+
+    /// Compute units charged across all instructions
+    pub fn compute_units(&self) -> u64 {
+        self.total_metrics().compute_units
+    }
+
     /// Set an error message (used when transaction fails)
     pub fn set_error(&mut self, error: String) {
         self.success = false;
@@ -479,6 +922,58 @@ mod tests {
         assert!(!deletion_effect.is_modification());
     }
     
+    #[test]
+    fn test_effect_hashes() {
+        let id = UnitsObjectId::new([1; 32]);
+        let before = UnitsObject::new_data(id, UnitsObjectId::new([2; 32]), vec![1, 2, 3]);
+        let after = UnitsObject::new_data(id, UnitsObjectId::new([2; 32]), vec![4, 5, 6]);
+
+        let mut effect = TransactionEffect::new_modification([4; 32], before.clone(), after.clone());
+        assert_eq!(effect.before_hash, Some(before.state_hash()));
+        assert_eq!(effect.after_hash, Some(after.state_hash()));
+        assert_ne!(effect.before_hash, effect.after_hash);
+        assert!(effect.hashes_match_images());
+
+        let deletion = TransactionEffect::new_deletion([4; 32], before.clone());
+        assert_eq!(deletion.after_hash, None);
+
+        // A proof committing to the after state matches even without images
+        let proof = UnitsObjectProof::new(id, after.state_hash(), 1, Vec::new(), None, None);
+        effect.before_image = None;
+        effect.after_image = None;
+        assert!(effect.matches_proof(&proof));
+        assert!(!effect.hashes_match_images());
+        assert!(!deletion.matches_proof(&proof));
+    }
+
+    #[test]
+    fn test_receipt_instruction_metrics() {
+        let mut receipt = TransactionReceipt::new([4; 32], 1, true, 0);
+        receipt.add_instruction_metrics(ExecutionMetrics {
+            instructions_executed: 100,
+            peak_memory_bytes: 4096,
+            syscall_count: 2,
+            compute_units: 140,
+        });
+        receipt.add_instruction_metrics(ExecutionMetrics {
+            instructions_executed: 50,
+            peak_memory_bytes: 8192,
+            syscall_count: 1,
+            compute_units: 70,
+        });
+
+        let total = receipt.total_metrics();
+        assert_eq!(total.instructions_executed, 150);
+        assert_eq!(total.peak_memory_bytes, 8192);
+        assert_eq!(total.syscall_count, 3);
+        assert_eq!(total.compute_units, 210);
+        assert_eq!(receipt.compute_units(), 210);
+
+        // Metrics survive the receipt's storage encoding
+        let decoded: TransactionReceipt = bincode::deserialize(&bincode::serialize(&receipt).unwrap()).unwrap();
+        assert_eq!(decoded.instruction_metrics, receipt.instruction_metrics);
+    }
+
     #[test]
     fn test_transaction_receipt() {
         // Create an ID for testing
@@ -534,4 +1029,20 @@ mod tests {
         // Verify the second effect is a modification
         assert!(receipt.effects[1].is_modification());
     }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_memo_is_bounded_and_filterable() {
+        use crate::transaction_manager::TransactionFilter;
+
+        let transaction = Transaction::new(Vec::new(), [5; 32]).with_memo(b"INV-1001".to_vec());
+        assert!(transaction.check_memo().is_ok());
+        assert!(transaction.clone().with_memo(vec![0; MAX_MEMO_LEN + 1]).check_memo().is_err());
+
+        let mut receipt = TransactionReceipt::new(transaction.hash, 3, true, 0);
+        receipt.memo = transaction.memo.clone();
+        assert!(TransactionFilter::new().with_memo(b"INV-1001".to_vec()).matches(&receipt));
+        assert!(!TransactionFilter::new().with_memo(b"INV-1002".to_vec()).matches(&receipt));
+        assert!(!TransactionFilter::new().with_slot_range(4, 9).matches(&receipt));
+    }
 }
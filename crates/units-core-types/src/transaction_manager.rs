@@ -7,9 +7,10 @@ use std::collections::HashMap;
 use crate::error::{RuntimeError, StorageError};
 use crate::id::UnitsObjectId;
 use crate::objects::UnitsObject;
+use crate::vm_executor::{ExecutionMetrics, ObjectEffect, VMExecutionError};
 use crate::{SlotNumber, UnitsObjectProof};
 use crate::transaction::{
-    CommitmentLevel, ConflictResult, Transaction, TransactionEffect, 
+    merge_effect, CommitmentLevel, ConflictResult, Instruction, Transaction, TransactionEffect,
     TransactionHash, TransactionReceipt
 };
 
@@ -27,6 +28,11 @@ pub trait TransactionManager: Send + Sync {
     //--------------------------------------------------------------------------
     
     /// Execute a transaction and return a receipt
+    ///
+    /// The instructions run in order, each seeing the objects as the ones
+    /// before it left them, and commit together under a single receipt or
+    /// not at all. [`TransactionContext::execute_instructions`] provides
+    /// that working set.
     fn execute_transaction(
         &self,
         transaction: &Transaction,
@@ -178,6 +184,9 @@ pub struct TransactionFilter {
     
     /// Filter by success status
     pub success_only: bool,
+
+    /// Filter by memo, matched exactly
+    pub memo: Option<Vec<u8>>,
     
     /// Maximum number of results
     pub limit: Option<usize>,
@@ -214,6 +223,25 @@ impl TransactionFilter {
         self
     }
     
+    /// Filter by memo, such as an invoice number
+    pub fn with_memo(mut self, memo: impl Into<Vec<u8>>) -> Self {
+        self.memo = Some(memo.into());
+        self
+    }
+
+    /// Whether `receipt` meets every criterion but the limit
+    pub fn matches(&self, receipt: &TransactionReceipt) -> bool {
+        let touches = |id: &UnitsObjectId| {
+            receipt.object_proofs.contains_key(id) || receipt.effects.iter().any(|effect| &effect.object_id == id)
+        };
+        self.object_ids.as_ref().map_or(true, |ids| ids.iter().any(touches))
+            && self.start_slot.map_or(true, |start| receipt.slot >= start)
+            && self.end_slot.map_or(true, |end| receipt.slot <= end)
+            && self.commitment_level.map_or(true, |level| receipt.commitment_level == level)
+            && (!self.success_only || receipt.success)
+            && self.memo.as_ref().map_or(true, |memo| receipt.memo.as_ref() == Some(memo))
+    }
+
     /// Limit the number of results
     pub fn with_limit(mut self, limit: usize) -> Self {
         self.limit = Some(limit);
@@ -227,7 +255,10 @@ impl TransactionFilter {
 
 /// Context for executing a transaction
 /// 
-/// This encapsulates all the state needed during transaction execution
+/// This encapsulates all the state needed during transaction execution.
+/// `objects` is the transaction's working set: loaded with the objects its
+/// instructions may touch, it holds their latest images as instructions
+/// run, while `effects` holds one net effect per changed object.
 pub struct TransactionContext {
     /// The transaction being executed
     pub transaction: Transaction,
@@ -235,7 +266,7 @@ pub struct TransactionContext {
     /// Current slot number
     pub slot: SlotNumber,
     
-    /// Objects affected by this transaction
+    /// Objects affected by this transaction, as changed so far
     pub objects: HashMap<UnitsObjectId, UnitsObject>,
     
     /// Proofs generated during execution
@@ -244,6 +275,9 @@ pub struct TransactionContext {
     /// Effects of the transaction
     pub effects: Vec<TransactionEffect>,
     
+    /// VM resource usage of each executed instruction
+    pub metrics: Vec<ExecutionMetrics>,
+    
     /// Whether the transaction has been rolled back
     pub rolled_back: bool,
 }
@@ -257,6 +291,7 @@ impl TransactionContext {
             objects: HashMap::new(),
             proofs: HashMap::new(),
             effects: Vec::new(),
+            metrics: Vec::new(),
             rolled_back: false,
         }
     }
@@ -276,8 +311,89 @@ impl TransactionContext {
         self.effects.push(effect);
     }
     
-    /// Mark the transaction as rolled back
+    /// Record the resource usage of an executed instruction
+    pub fn add_metrics(&mut self, metrics: ExecutionMetrics) {
+        self.metrics.push(metrics);
+    }
+
+    /// Apply one instruction's effects to the working set
+    ///
+    /// Each effect must start from the object's current image in the
+    /// working set; if any does not, nothing is applied. Changes to an
+    /// object the transaction already changed are merged into its effect.
+    pub fn apply_effects(&mut self, effects: &[ObjectEffect]) -> Result<(), VMExecutionError> {
+        let mut touched: HashMap<UnitsObjectId, Option<&UnitsObject>> = HashMap::new();
+        for effect in effects {
+            let current = match touched.get(&effect.object_id) {
+                Some(object) => *object,
+                None => self.objects.get(&effect.object_id),
+            };
+            if current != effect.before_image.as_ref() {
+                return Err(VMExecutionError::ExecutionFailed(format!(
+                    "Effect on {} does not start from the object's current state",
+                    effect.object_id
+                )));
+            }
+            touched.insert(effect.object_id, effect.after_image.as_ref());
+        }
+
+        for effect in effects {
+            match &effect.after_image {
+                Some(object) => self.objects.insert(effect.object_id, object.clone()),
+                None => self.objects.remove(&effect.object_id),
+            };
+            merge_effect(
+                &mut self.effects,
+                TransactionEffect::from_images(
+                    self.transaction.hash,
+                    effect.object_id,
+                    effect.before_image.clone(),
+                    effect.after_image.clone(),
+                ),
+            );
+        }
+        Ok(())
+    }
+
+    /// Run the transaction's instructions in order against the working set
+    ///
+    /// `execute` runs one instruction on the objects as the instructions
+    /// before it left them. On the first failure the whole transaction is
+    /// rolled back and the index of the failed instruction returned with
+    /// its error.
+    pub fn execute_instructions<F>(&mut self, mut execute: F) -> Result<(), (usize, VMExecutionError)>
+    where
+        F: FnMut(
+            &Instruction,
+            &HashMap<UnitsObjectId, UnitsObject>,
+        ) -> Result<(Vec<ObjectEffect>, ExecutionMetrics), VMExecutionError>,
+    {
+        for index in 0..self.transaction.instructions.len() {
+            let executed = execute(&self.transaction.instructions[index], &self.objects)
+                .and_then(|(effects, metrics)| self.apply_effects(&effects).map(|()| metrics));
+            match executed {
+                Ok(metrics) => self.add_metrics(metrics),
+                Err(error) => {
+                    self.rollback();
+                    return Err((index, error));
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    /// Roll the transaction back, restoring the working set
+    ///
+    /// Effects and proofs are discarded, so a receipt made from the
+    /// context afterwards records no changes.
     pub fn rollback(&mut self) {
+        for effect in self.effects.drain(..) {
+            match effect.before_image {
+                Some(object) => self.objects.insert(effect.object_id, object),
+                None => self.objects.remove(&effect.object_id),
+            };
+        }
+        self.proofs.clear();
         self.rolled_back = true;
     }
     
@@ -289,14 +405,19 @@ impl TransactionContext {
             success,
             timestamp,
         );
+        receipt.memo = self.transaction.memo;
         
         // Add proofs to the receipt
         for (id, proof) in self.proofs {
             receipt.add_proof(id, proof);
         }
         
-        // Add effects
+        // Add effects, hashing their final images, and per-instruction resource usage
         receipt.effects = self.effects;
+        for effect in &mut receipt.effects {
+            effect.compute_hashes();
+        }
+        receipt.instruction_metrics = self.metrics;
         
         // Set commitment level
         receipt.commitment_level = if success {
@@ -307,4 +428,82 @@ impl TransactionContext {
         
         receipt
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(id: u8, data: Vec<u8>) -> UnitsObject {
+        UnitsObject::new_data(UnitsObjectId::new([id; 32]), UnitsObjectId::new([9; 32]), data)
+    }
+
+    /// Append a byte to every target, creating the scratch object `[7; 32]`
+    /// on the first call and deleting it on the second
+    fn append(
+        instruction: &Instruction,
+        objects: &HashMap<UnitsObjectId, UnitsObject>,
+    ) -> Result<(Vec<ObjectEffect>, ExecutionMetrics), VMExecutionError> {
+        let mut effects = Vec::new();
+        for id in &instruction.target_objects {
+            let before = objects.get(id).cloned().ok_or(VMExecutionError::ModuleError(1))?;
+            let mut after = before.clone();
+            after.data.push(1);
+            effects.push(ObjectEffect::modification(before, after));
+        }
+        let scratch = UnitsObjectId::new([7; 32]);
+        effects.push(match objects.get(&scratch) {
+            Some(object) => ObjectEffect::deletion(object.clone()),
+            None => ObjectEffect::creation(object(7, vec![])),
+        });
+        Ok((effects, ExecutionMetrics::default()))
+    }
+
+    fn working_set(targets: &[&[u8]]) -> TransactionContext {
+        let instructions = targets
+            .iter()
+            .map(|ids| {
+                let targets = ids.iter().map(|&id| UnitsObjectId::new([id; 32])).collect();
+                Instruction::new(UnitsObjectId::new([9; 32]), "append".to_string(), targets, vec![])
+            })
+            .collect();
+        let mut context = TransactionContext::new(Transaction::new(instructions, [1; 32]), 5);
+        context.add_object(object(1, vec![0]));
+        context.add_object(object(2, vec![0]));
+        context
+    }
+
+    #[test]
+    fn test_instructions_share_a_working_set_and_merge_effects() {
+        let mut context = working_set(&[&[1], &[1, 2]]);
+        context.execute_instructions(append).unwrap();
+        assert_eq!(context.metrics.len(), 2);
+
+        // One net effect per object; the scratch object came and went
+        let receipt = context.into_receipt(true, 0);
+        assert_eq!(receipt.effects.len(), 2);
+        let first = &receipt.effects[0];
+        assert_eq!(first.before_image, Some(object(1, vec![0])));
+        assert_eq!(first.after_image, Some(object(1, vec![0, 1, 1])));
+        assert_eq!(first.after_hash, Some(object(1, vec![0, 1, 1]).state_hash()));
+        assert_eq!(receipt.effects[1].after_image, Some(object(2, vec![0, 1])));
+    }
+
+    #[test]
+    fn test_failed_instruction_rolls_back_the_transaction() {
+        let mut context = working_set(&[&[1], &[2, 3]]);
+        let (index, error) = context.execute_instructions(append).unwrap_err();
+        assert_eq!(index, 1);
+        assert!(matches!(error, VMExecutionError::ModuleError(1)));
+        assert!(context.rolled_back);
+        assert_eq!(context.objects[&UnitsObjectId::new([1; 32])], object(1, vec![0]));
+        assert!(!context.objects.contains_key(&UnitsObjectId::new([7; 32])));
+        assert!(context.into_receipt(false, 0).effects.is_empty());
+
+        // Effects that do not start from the working set are refused whole
+        let mut context = working_set(&[]);
+        let stale = ObjectEffect::modification(object(1, vec![5]), object(1, vec![6]));
+        let fresh = ObjectEffect::modification(object(2, vec![0]), object(2, vec![6]));
+        assert!(context.apply_effects(&[fresh, stale]).is_err());
+        assert_eq!(context.objects[&UnitsObjectId::new([2; 32])], object(2, vec![0]));
+    }
+}
@@ -2,14 +2,16 @@
 //!
 //! This module provides the core VM execution interfaces and supporting data structures.
 
+use borsh::{BorshDeserialize, BorshSerialize};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use crate::id::UnitsObjectId;
 use crate::objects::{UnitsObject, VMType};
-use crate::transaction::Instruction;
+use crate::transaction::{Instruction, TransactionEvent, TransactionHash};
+pub use crate::transaction::ExecutionMetrics;
 
 /// Complete context provided to controller during execution
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
 pub struct ExecutionContext {
     /// The instruction being executed
     pub instruction: Instruction,
@@ -54,9 +56,119 @@ impl ExecutionContext {
     }
 }
 
+/// Size limits applied to an execution context before it reaches the VM
+///
+/// Guards against transactions that list so many (or such large) objects
+/// that serializing the context into VM memory becomes the bottleneck.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContextLimits {
+    /// Maximum number of objects loaded into the context
+    pub max_objects: usize,
+    /// Maximum size of the serialized context in bytes
+    pub max_context_bytes: usize,
+    /// Largest chunk returned by a single hosted read of the context
+    pub read_chunk_bytes: usize,
+}
+
+impl Default for ContextLimits {
+    fn default() -> Self {
+        Self {
+            max_objects: 256,
+            max_context_bytes: 1024 * 1024, // Matches the VM input buffer
+            read_chunk_bytes: 64 * 1024,
+        }
+    }
+}
+
+impl ContextLimits {
+    /// Check the number of objects a context would contain
+    ///
+    /// Cheap enough to run before any objects are loaded from storage.
+    pub fn check_object_count(&self, count: usize) -> Result<(), VMExecutionError> {
+        if count > self.max_objects {
+            return Err(VMExecutionError::TooManyObjects(format!(
+                "{} objects exceeds the limit of {}",
+                count, self.max_objects
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check a fully built context, returning its serialized size
+    pub fn check(&self, context: &ExecutionContext) -> Result<usize, VMExecutionError> {
+        self.check_object_count(context.objects.len())?;
+
+        let size = bincode::serialized_size(context)
+            .map_err(|e| VMExecutionError::SerializationError(format!("Context size: {}", e)))?
+            as usize;
+        if size > self.max_context_bytes {
+            return Err(VMExecutionError::ContextTooLarge(format!(
+                "{} bytes exceeds the limit of {}",
+                size, self.max_context_bytes
+            )));
+        }
+        Ok(size)
+    }
+}
+
+/// Serialized execution context served to a module in bounded chunks
+///
+/// The stream is the context's length as a little-endian `u32` followed by
+/// its serialized bytes, matching what the kernel SDK's `read_context`
+/// expects from successive reads of the input.
+#[derive(Debug, Clone)]
+pub struct ContextStream {
+    bytes: Vec<u8>,
+    position: usize,
+    chunk_size: usize,
+}
+
+impl ContextStream {
+    /// Serialize a context after checking it against `limits`
+    pub fn new(context: &ExecutionContext, limits: &ContextLimits) -> Result<Self, VMExecutionError> {
+        let size = limits.check(context)?;
+
+        let mut bytes = Vec::with_capacity(size + 4);
+        bytes.extend_from_slice(&(size as u32).to_le_bytes());
+        bincode::serialize_into(&mut bytes, context)
+            .map_err(|e| VMExecutionError::SerializationError(format!("Context serialization failed: {}", e)))?;
+
+        Ok(Self {
+            bytes,
+            position: 0,
+            chunk_size: limits.read_chunk_bytes.max(1),
+        })
+    }
+
+    /// Copy the next chunk into `buf`, returning the number of bytes copied
+    ///
+    /// Returns 0 once the whole context has been read.
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let len = buf.len().min(self.chunk_size).min(self.remaining());
+        buf[..len].copy_from_slice(&self.bytes[self.position..self.position + len]);
+        self.position += len;
+        len
+    }
+
+    /// Bytes not yet read
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+
+    /// Total length of the stream including the size prefix
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Whether the stream is empty (never true for a valid context)
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
 /// Effect of controller execution on a single object
 /// Represents before/after state for one object in an instruction
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
 pub struct ObjectEffect {
     /// The object that was modified
     pub object_id: UnitsObjectId,
@@ -97,6 +209,47 @@ impl ObjectEffect {
     }
 }
 
+/// Event a module emitted, as it crosses the VM boundary
+///
+/// Same Borsh layout as the wire type modules write; see
+/// [`TransactionEvent`] for the form recorded in receipts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+pub struct KernelEvent {
+    /// Object the event concerns
+    pub object_id: UnitsObjectId,
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+impl KernelEvent {
+    pub fn new(object_id: UnitsObjectId, name: impl Into<String>, data: Vec<u8>) -> Self {
+        Self {
+            object_id,
+            name: name.into(),
+            data,
+        }
+    }
+
+    /// The event as recorded in the receipt of `transaction_hash`, emitted by `controller_id`
+    pub fn into_transaction_event(self, transaction_hash: TransactionHash, controller_id: UnitsObjectId) -> TransactionEvent {
+        TransactionEvent {
+            transaction_hash,
+            controller_id,
+            object_id: self.object_id,
+            name: self.name,
+            data: self.data,
+        }
+    }
+}
+
+/// Everything one execution of a module produced
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionOutput {
+    pub effects: Vec<ObjectEffect>,
+    pub events: Vec<KernelEvent>,
+    pub metrics: ExecutionMetrics,
+}
+
 /// Execution error types
 #[derive(Debug, thiserror::Error)]
 pub enum VMExecutionError {
@@ -105,12 +258,20 @@ pub enum VMExecutionError {
     
     #[error("Invalid bytecode: {0}")]
     InvalidBytecode(String),
+
+    /// The program halted with a non-zero exit code
+    #[error("Program exited with code: {0}")]
+    ModuleError(u32),
     
     #[error("Memory limit exceeded")]
     MemoryLimitExceeded,
     
     #[error("Instruction limit exceeded")]
     InstructionLimitExceeded,
+
+    /// Metered compute units reached the budget, given here
+    #[error("Compute budget of {0} units exceeded")]
+    ComputeBudgetExceeded(u64),
     
     #[error("Timeout exceeded")]
     TimeoutExceeded,
@@ -123,6 +284,21 @@ pub enum VMExecutionError {
     
     #[error("Unsupported VM type: {0}")]
     UnsupportedVMType(String),
+    
+    #[error("Insufficient storage deposit: {0}")]
+    InsufficientDeposit(String),
+
+    #[error("Insufficient fee balance: {0}")]
+    InsufficientFeeBalance(String),
+
+    #[error("Sponsorship nonce is not the sponsor's next: {0}")]
+    InvalidSponsorshipNonce(String),
+    
+    #[error("Too many objects in execution context: {0}")]
+    TooManyObjects(String),
+    
+    #[error("Execution context too large: {0}")]
+    ContextTooLarge(String),
 }
 
 /// Abstract interface for different VM types
@@ -136,6 +312,30 @@ pub trait VMExecutor: Send + Sync {
         bytecode: &[u8],
         context: &ExecutionContext,
     ) -> Result<Vec<ObjectEffect>, VMExecutionError>;
+
+    /// Load bytecode and execute, also reporting resource usage
+    ///
+    /// Executors that do not track usage report zeroed metrics.
+    fn load_and_execute_with_metrics(
+        &self,
+        bytecode: &[u8],
+        context: &ExecutionContext,
+    ) -> Result<(Vec<ObjectEffect>, ExecutionMetrics), VMExecutionError> {
+        Ok((self.load_and_execute(bytecode, context)?, ExecutionMetrics::default()))
+    }
+
+    /// Load bytecode and execute, reporting the events the module emitted
+    /// along with its effects and resource usage
+    ///
+    /// Executors whose modules cannot emit events report none.
+    fn load_and_execute_with_events(
+        &self,
+        bytecode: &[u8],
+        context: &ExecutionContext,
+    ) -> Result<ExecutionOutput, VMExecutionError> {
+        let (effects, metrics) = self.load_and_execute_with_metrics(bytecode, context)?;
+        Ok(ExecutionOutput { effects, events: Vec::new(), metrics })
+    }
 }
 
 /// Validate that controller can only modify objects it controls
@@ -156,4 +356,67 @@ pub fn validate_object_effects(
         }
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context_with_objects(count: u8, data_len: usize) -> ExecutionContext {
+        let controller = UnitsObjectId::new([1; 32]);
+        let objects = (0..count)
+            .map(|i| {
+                let id = UnitsObjectId::new([i; 32]);
+                (id, UnitsObject::new_data(id, controller, vec![0; data_len]))
+            })
+            .collect();
+        let instruction = Instruction::new(controller, "test".to_string(), vec![], vec![]);
+        ExecutionContext::new(instruction, objects, 1, 2)
+    }
+
+    #[test]
+    fn test_context_limits() {
+        let limits = ContextLimits {
+            max_objects: 4,
+            max_context_bytes: 1024,
+            read_chunk_bytes: 64,
+        };
+
+        assert!(limits.check(&context_with_objects(4, 16)).is_ok());
+        assert!(matches!(
+            limits.check(&context_with_objects(5, 16)),
+            Err(VMExecutionError::TooManyObjects(_))
+        ));
+        assert!(matches!(
+            limits.check(&context_with_objects(2, 1024)),
+            Err(VMExecutionError::ContextTooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn test_context_stream_reads_in_chunks() {
+        let limits = ContextLimits {
+            read_chunk_bytes: 100,
+            ..ContextLimits::default()
+        };
+        let context = context_with_objects(3, 64);
+        let mut stream = ContextStream::new(&context, &limits).unwrap();
+
+        let mut size = [0u8; 4];
+        assert_eq!(stream.read(&mut size), 4);
+        let size = u32::from_le_bytes(size) as usize;
+        assert_eq!(size, stream.remaining());
+
+        let mut data = vec![0u8; size];
+        let mut read = 0;
+        while read < size {
+            let n = stream.read(&mut data[read..]);
+            assert!(n > 0 && n <= 100);
+            read += n;
+        }
+        assert_eq!(stream.read(&mut [0u8; 8]), 0);
+
+        let decoded: ExecutionContext = bincode::deserialize(&data).unwrap();
+        assert_eq!(decoded.objects.len(), 3);
+    }
+}
@@ -0,0 +1,256 @@
+//! Golden wire-format vectors
+//!
+//! Each test pins the exact serialized bytes of a type that crosses a
+//! compatibility boundary: Borsh for everything exchanged with kernel
+//! modules, bincode for proofs and receipts as they are persisted. A
+//! failure here means a refactor changed the wire layout. Only update a
+//! vector when the format change is intentional and versioned.
+
+use std::collections::HashMap;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{de::DeserializeOwned, Serialize};
+use units_core_types::{
+    CommitmentLevel, ExecutionContext, Instruction, ObjectRead, ObjectType, ReceiptAnnotation, StateProof, TransactionEffect,
+    TransactionEvent, TransactionReceipt, UnitsObject, UnitsObjectId, UnitsObjectProof, VMType,
+};
+use units_core_types::vm_executor::{ExecutionMetrics, ObjectEffect};
+
+fn assert_borsh<T: BorshSerialize + BorshDeserialize>(name: &str, value: &T, expected: &str) {
+    let bytes = borsh::to_vec(value).unwrap();
+    assert_eq!(hex::encode(&bytes), expected, "Borsh layout of {} changed", name);
+
+    let decoded = T::try_from_slice(&bytes).unwrap();
+    assert_eq!(borsh::to_vec(&decoded).unwrap(), bytes, "{} does not round-trip", name);
+}
+
+fn assert_bincode<T: Serialize + DeserializeOwned>(name: &str, value: &T, expected: &str) {
+    let bytes = bincode::serialize(value).unwrap();
+    assert_eq!(hex::encode(&bytes), expected, "bincode layout of {} changed", name);
+
+    let decoded: T = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(bincode::serialize(&decoded).unwrap(), bytes, "{} does not round-trip", name);
+}
+
+fn id(byte: u8) -> UnitsObjectId {
+    UnitsObjectId::new([byte; 32])
+}
+
+fn data_object() -> UnitsObject {
+    UnitsObject::new_data(id(1), id(2), vec![0xde, 0xad, 0xbe, 0xef])
+}
+
+fn executable_object() -> UnitsObject {
+    UnitsObject::new_executable(id(3), id(4), VMType::RiscV, vec![0x13, 0x00, 0x00, 0x00])
+}
+
+fn instruction() -> Instruction {
+    Instruction::new(id(5), "transfer".to_string(), vec![id(1), id(3)], vec![1, 2, 3])
+}
+
+fn object_proof() -> UnitsObjectProof {
+    UnitsObjectProof {
+        object_id: id(1),
+        slot: 7,
+        object_hash: [0xaa; 32],
+        prev_proof_hash: Some([0xbb; 32]),
+        transaction_hash: None,
+        proof_data: vec![9, 8, 7],
+    }
+}
+
+#[test]
+fn test_object_vectors() {
+    assert_borsh(
+        "UnitsObjectId",
+        &id(1),
+        "0101010101010101010101010101010101010101010101010101010101010101",
+    );
+    assert_borsh(
+        "UnitsObject (data)",
+        &data_object(),
+        concat!(
+            "0101010101010101010101010101010101010101010101010101010101010101",
+            "0202020202020202020202020202020202020202020202020202020202020202",
+            "0004000000deadbeef",
+        ),
+    );
+    assert_borsh(
+        "UnitsObject (executable)",
+        &executable_object(),
+        concat!(
+            "0303030303030303030303030303030303030303030303030303030303030303",
+            "0404040404040404040404040404040404040404040404040404040404040404",
+            "01000400000013000000",
+        ),
+    );
+    assert_borsh("ObjectType::Data", &ObjectType::Data, "00");
+}
+
+#[test]
+fn test_execution_vectors() {
+    assert_borsh(
+        "Instruction",
+        &instruction(),
+        concat!(
+            "0505050505050505050505050505050505050505050505050505050505050505",
+            "080000007472616e736665720200000001010101010101010101010101010101",
+            "0101010101010101010101010101010103030303030303030303030303030303",
+            "0303030303030303030303030303030303000000010203",
+        ),
+    );
+
+    let objects = HashMap::from([(id(3), executable_object()), (id(1), data_object())]);
+    let context = ExecutionContext::new(instruction(), objects, 42, 1_700_000_000);
+    assert_borsh(
+        "ExecutionContext",
+        &context,
+        concat!(
+            "0505050505050505050505050505050505050505050505050505050505050505",
+            "080000007472616e736665720200000001010101010101010101010101010101",
+            "0101010101010101010101010101010103030303030303030303030303030303",
+            "0303030303030303030303030303030303000000010203020000000101010101",
+            "0101010101010101010101010101010101010101010101010101010101010101",
+            "0101010101010101010101010101010101010101010101010101010202020202",
+            "0202020202020202020202020202020202020202020202020202020004000000",
+            "deadbeef03030303030303030303030303030303030303030303030303030303",
+            "0303030303030303030303030303030303030303030303030303030303030303",
+            "0303030304040404040404040404040404040404040404040404040404040404",
+            "04040404010004000000130000002a0000000000000000f1536500000000",
+        ),
+    );
+
+    let effects = vec![
+        ObjectEffect::creation(data_object()),
+        ObjectEffect::modification(data_object(), executable_object()),
+        ObjectEffect::deletion(executable_object()),
+    ];
+    assert_borsh(
+        "ObjectEffect",
+        &effects,
+        concat!(
+            "0300000001010101010101010101010101010101010101010101010101010101",
+            "0101010100010101010101010101010101010101010101010101010101010101",
+            "0101010101010202020202020202020202020202020202020202020202020202",
+            "0202020202020004000000deadbeef0303030303030303030303030303030303",
+            "0303030303030303030303030303030101010101010101010101010101010101",
+            "0101010101010101010101010101010102020202020202020202020202020202",
+            "020202020202020202020202020202020004000000deadbeef01030303030303",
+            "0303030303030303030303030303030303030303030303030303040404040404",
+            "0404040404040404040404040404040404040404040404040404010004000000",
+            "1300000003030303030303030303030303030303030303030303030303030303",
+            "0303030301030303030303030303030303030303030303030303030303030303",
+            "0303030303040404040404040404040404040404040404040404040404040404",
+            "04040404040100040000001300000000",
+        ),
+    );
+}
+
+#[test]
+fn test_proof_vectors() {
+    assert_bincode(
+        "UnitsObjectProof",
+        &object_proof(),
+        concat!(
+            "0101010101010101010101010101010101010101010101010101010101010101",
+            "0700000000000000aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            "aaaaaaaaaaaaaaaa01bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",
+            "bbbbbbbbbbbbbbbbbb000300000000000000090807",
+        ),
+    );
+
+    let state_proof = StateProof {
+        slot: 7,
+        prev_state_proof_hash: None,
+        object_ids: vec![id(1), id(3)],
+        proof_data: vec![0xcc; 4],
+    };
+    assert_bincode(
+        "StateProof",
+        &state_proof,
+        concat!(
+            "0700000000000000000200000000000000010101010101010101010101010101",
+            "0101010101010101010101010101010101030303030303030303030303030303",
+            "03030303030303030303030303030303030400000000000000cccccccc",
+        ),
+    );
+}
+
+#[test]
+fn test_receipt_vectors() {
+    let mut receipt = TransactionReceipt::new([0x11; 32], 7, true, 1_700_000_000);
+    receipt.commitment_level = CommitmentLevel::Committed;
+    receipt.object_proofs.insert(id(1), object_proof());
+    receipt.effects.push(TransactionEffect::new_creation([0x11; 32], data_object()));
+    receipt.instruction_metrics.push(ExecutionMetrics {
+        instructions_executed: 1000,
+        peak_memory_bytes: 65536,
+        syscall_count: 2,
+        compute_units: 1500,
+    });
+    receipt.annotations.push(ReceiptAnnotation {
+        processor: "p".to_string(),
+        key: "k".to_string(),
+        value: "v".to_string(),
+    });
+    receipt.memo = Some(b"inv".to_vec());
+    receipt.reads.push(ObjectRead { object_id: id(2), state_hash: None });
+    receipt.events.push(TransactionEvent {
+        transaction_hash: [0x11; 32],
+        controller_id: id(3),
+        object_id: id(2),
+        name: "t".to_string(),
+        data: vec![5],
+    });
+    assert_bincode(
+        "TransactionReceipt",
+        &receipt,
+        concat!(
+            "1111111111111111111111111111111111111111111111111111111111111111",
+            "0700000000000000010000000000000001010101010101010101010101010101",
+            "0101010101010101010101010101010101010101010101010101010101010101",
+            "010101010101010101010101010101010700000000000000aaaaaaaaaaaaaaaa",
+            "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa01bbbbbbbbbbbbbb",
+            "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb00030000000000",
+            "00000908070100f1536500000000010000000001000000000000001111111111",
+            "1111111111111111111111111111111111111111111111111111110101010101",
+            "0101010101010101010101010101010101010101010101010101010001010101",
+            "0101010101010101010101010101010101010101010101010101010101020202",
+            "0202020202020202020202020202020202020202020202020202020202000000",
+            "000400000000000000deadbeef0001570b48dfd5861a152c79444fa6fd4de04d",
+            "7b8b4672372e7b3b73f7a359939e180100000000000000e80300000000000000",
+            "000100000000000200000000000000dc0500000000000001000000000000000100",
+            "0000000000007001000000000000006b0100000000000000760000",
+            "010300000000000000696e76",
+            "0100000000000000",
+            "0202020202020202020202020202020202020202020202020202020202020202",
+            "00",
+            "0100000000000000",
+            "1111111111111111111111111111111111111111111111111111111111111111",
+            "0303030303030303030303030303030303030303030303030303030303030303",
+            "0202020202020202020202020202020202020202020202020202020202020202",
+            "010000000000000074",
+            "010000000000000005",
+        ),
+    );
+}
+
+#[test]
+fn test_external_id_vectors() {
+    // Integrators reproduce these IDs off-chain, so the derivation is as
+    // fixed as any wire layout
+    let registry = units_core_types::IdDerivationRegistry::with_defaults();
+    let derived = [
+        ("isin", "US0378331005"),
+        ("did", "did:example:123456789abcdefghi"),
+    ]
+    .map(|(namespace, identifier)| hex::encode(*registry.derive(namespace, identifier).unwrap()));
+    assert_eq!(
+        derived,
+        [
+            "e7f19cf2f79cbe99bde9edbea82fd476722074a97a24774939fad70207fd096e",
+            "5c33f9b025dd14d5bd60ada56a2968ab76ed4112805a99cf5d27438f779175e9",
+        ],
+        "Derivation of external IDs changed"
+    );
+}
@@ -31,6 +31,17 @@ impl AuthPolicy for StandardAccountPolicy {
                 vec![AuthRequirement::Factor(AuthFactor::Signature(SignatureType::Ed25519))]
             }
             
+            // Exceeding a token spend limit requires a second factor
+            "approve_spend" => {
+                vec![AuthRequirement::All(vec![
+                    AuthRequirement::Factor(AuthFactor::Signature(SignatureType::Ed25519)),
+                    AuthRequirement::Any(vec![
+                        AuthRequirement::Factor(AuthFactor::TimeBasedCode),
+                        AuthRequirement::Factor(AuthFactor::HardwareToken),
+                    ])
+                ])]
+            }
+            
             // Reactivation can use owner signature OR recovery key
             "reactivate_account" => {
                 vec![AuthRequirement::Any(vec![
@@ -154,6 +165,15 @@ impl AuthPolicy for HighSecurityPolicy {
                 ])]
             }
             
+            // Exceeding a token spend limit requires every second factor
+            "approve_spend" => {
+                vec![AuthRequirement::All(vec![
+                    AuthRequirement::Factor(AuthFactor::Signature(SignatureType::Ed25519)),
+                    AuthRequirement::Factor(AuthFactor::TimeBasedCode),
+                    AuthRequirement::Factor(AuthFactor::HardwareToken),
+                ])]
+            }
+            
             // Reactivation requires multiple recovery signatures
             "reactivate_account" => {
                 vec![AuthRequirement::Any(vec![
@@ -272,7 +292,7 @@ impl ConfigurablePolicy {
             ])
         ]);
         
-        for operation in ["update_account", "add_recovery_address", "remove_recovery_address", "deactivate_account"] {
+        for operation in ["update_account", "add_recovery_address", "remove_recovery_address", "deactivate_account", "approve_spend"] {
             policy.set_operation_requirement(operation.to_string(), mfa_requirement.clone());
         }
         
@@ -148,6 +148,32 @@ pub fn create_operation_message(
     message
 }
 
+/// Domain separating signed account operations from other signed messages
+pub const ACCOUNT_OPERATION_DOMAIN: &[u8] = b"units/account/operation/v1";
+
+/// Canonical message the owner signs to authorize an account operation
+///
+/// Binds the account, the module function, the account's current nonce,
+/// the signature's expiry and the operation's other parameters. Variable-length
+/// fields are length-prefixed, so no two operations share a message.
+pub fn account_operation_message(
+    account_id: &UnitsObjectId,
+    function: &str,
+    nonce: u64,
+    expires_at: u64,
+    params: &[u8],
+) -> Vec<u8> {
+    let mut message = Vec::from(ACCOUNT_OPERATION_DOMAIN);
+    message.extend_from_slice(account_id.bytes());
+    message.extend_from_slice(&(function.len() as u32).to_le_bytes());
+    message.extend_from_slice(function.as_bytes());
+    message.extend_from_slice(&nonce.to_le_bytes());
+    message.extend_from_slice(&expires_at.to_le_bytes());
+    message.extend_from_slice(&(params.len() as u32).to_le_bytes());
+    message.extend_from_slice(params);
+    message
+}
+
 // Use arrayref crate
 use arrayref::array_ref;
 
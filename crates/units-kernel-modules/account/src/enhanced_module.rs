@@ -16,8 +16,9 @@ units_kernel_sdk::use_default_allocator!();
 use crate::{
     EnhancedAccountData, FlexCreateAccountParams, FlexUpdateAccountParams, 
     FlexAddRecoveryAddressParams, FlexRemoveRecoveryAddressParams, 
-    FlexDeactivateAccountParams, FlexReactivateAccountParams, GetAccountParams,
+    FlexDeactivateAccountParams, FlexReactivateAccountParams, FlexApproveSpendParams, GetAccountParams,
     validate_username,
+    usernames::claim_username,
     auth::{
         AuthManager, AuthContext, AuthResult, AuthError,
         signature_schemes::{create_default_signature_authenticators},
@@ -134,6 +135,7 @@ impl KernelModule for EnhancedAccountModule {
             "flex_remove_recovery_address" => module.handle_flex_remove_recovery_address(ctx),
             "flex_deactivate_account" => module.handle_flex_deactivate_account(ctx),
             "flex_reactivate_account" => module.handle_flex_reactivate_account(ctx),
+            "flex_approve_spend" => module.handle_flex_approve_spend(ctx),
             "get_account" => module.handle_get_account(ctx),
             _ => Err(KernelError::InvalidFunction),
         }
@@ -195,6 +197,12 @@ impl EnhancedAccountModule {
             account_data.recovery_addresses = recovery_addresses;
         }
         
+        // Claim the username in the registry alongside creating the account
+        let registry_effect = match account_data.username {
+            Some(ref username) => claim_username(ctx, account_id, None, username)?,
+            None => None,
+        };
+        
         let account_object = UnitsObject {
             id: account_id,
             controller_id: ctx.instruction.controller_id,
@@ -203,7 +211,9 @@ impl EnhancedAccountModule {
                 .map_err(|_| KernelError::InvalidData)?,
         };
         
-        Ok(vec![ObjectEffect::creation(account_object)])
+        let mut effects = vec![ObjectEffect::creation(account_object)];
+        effects.extend(registry_effect);
+        Ok(effects)
     }
     
     fn handle_flex_update_account(&self, ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, KernelError> {
@@ -244,12 +254,14 @@ impl EnhancedAccountModule {
             return Err(KernelError::InvalidParams);
         }
         
-        // Validate username if provided
+        // Validate username if provided, moving the registry entry to it
+        let mut registry_effect = None;
         if let Some(ref username) = params.username {
             if !validate_username(&username) {
                 return Err(KernelError::InvalidParams);
             }
-            account_data.username = Some(username.clone());
+            let previous = account_data.username.replace(username.clone());
+            registry_effect = claim_username(ctx, account.id, previous.as_deref(), username)?;
         }
         
         if let Some(display_name) = params.display_name {
@@ -270,7 +282,9 @@ impl EnhancedAccountModule {
                 .map_err(|_| KernelError::InvalidData)?,
         };
         
-        Ok(vec![ObjectEffect::modification(account.clone(), updated_account)])
+        let mut effects = vec![ObjectEffect::modification(account.clone(), updated_account)];
+        effects.extend(registry_effect);
+        Ok(effects)
     }
     
     fn handle_flex_add_recovery_address(&self, ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, KernelError> {
@@ -487,6 +501,62 @@ impl EnhancedAccountModule {
         Ok(vec![ObjectEffect::modification(account.clone(), updated_account)])
     }
     
+    fn handle_flex_approve_spend(&self, ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, KernelError> {
+        let params: FlexApproveSpendParams = borsh::from_slice(&ctx.instruction.params)
+            .map_err(|_| KernelError::InvalidData)?;
+        
+        if ctx.instruction.target_objects.is_empty() {
+            return Err(KernelError::InvalidParams);
+        }
+        
+        let account = ctx.objects.get(&params.account_id)
+            .ok_or(KernelError::ObjectNotFound)?;
+        
+        // Check authorization
+        if account.controller_id != ctx.instruction.controller_id {
+            return Err(KernelError::Unauthorized);
+        }
+        
+        // Authenticate the operation
+        let operation_data = borsh::to_vec(&FlexApproveSpendParams {
+            credentials: vec![], // Exclude credentials from message
+            ..params.clone()
+        }).map_err(|_| KernelError::InvalidData)?;
+        
+        self.authenticate_operation(
+            "approve_spend",
+            params.account_id,
+            ctx.instruction.controller_id,
+            ctx.timestamp,
+            &operation_data,
+            &params.credentials,
+        )?;
+        
+        let account_data: EnhancedAccountData = borsh::from_slice(&account.data)
+            .map_err(|_| KernelError::InvalidData)?;
+        if !account_data.is_active {
+            return Err(KernelError::InvalidParams);
+        }
+        
+        // The token module honours approvals controlled by the limit's approver
+        let approval = token::SpendApproval {
+            token_id: params.token_id,
+            owner_id: params.account_id,
+            amount: params.amount,
+            sequence: params.sequence,
+            expires_at: params.expires_at,
+        };
+        let approval_object = UnitsObject {
+            id: ctx.instruction.target_objects[0],
+            controller_id: ctx.instruction.controller_id,
+            object_type: ObjectType::Data,
+            data: borsh::to_vec(&approval)
+                .map_err(|_| KernelError::InvalidData)?,
+        };
+        
+        Ok(vec![ObjectEffect::creation(approval_object)])
+    }
+    
     fn handle_get_account(&self, ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, KernelError> {
         let params: GetAccountParams = borsh::from_slice(&ctx.instruction.params)
             .map_err(|_| KernelError::InvalidData)?;
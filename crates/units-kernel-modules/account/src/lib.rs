@@ -12,7 +12,7 @@ use alloc::collections::BTreeMap as HashMap;
 use std::collections::HashMap;
 
 use borsh::{BorshDeserialize, BorshSerialize};
-use units_kernel_sdk::UnitsObjectId;
+use units_kernel_sdk::{KernelError, UnitsObjectId, Versioned, LEGACY_SCHEMA_VERSION};
 use crate::crypto::Signature;
 use crate::auth::AuthCredential;
 
@@ -24,6 +24,7 @@ pub mod module;
 pub mod crypto;
 pub mod auth;
 pub mod enhanced_module;
+pub mod usernames;
 
 // Re-export modules for testing
 #[cfg(feature = "std")]
@@ -31,6 +32,8 @@ pub use crate::module::AccountModule;
 #[cfg(feature = "std")]
 pub use crate::enhanced_module::EnhancedAccountModule;
 
+pub use crate::usernames::{resolve_username, username_hash, username_registry_id, UsernameRegistry};
+
 // Account data structure
 #[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
 pub struct AccountData {
@@ -42,6 +45,46 @@ pub struct AccountData {
     pub recovery_addresses: Vec<UnitsObjectId>,
     pub created_at: u64,
     pub updated_at: u64,
+    /// Operations the owner has signed so far; each signature covers the
+    /// current value, so it cannot be replayed
+    pub nonce: u64,
+}
+
+/// Version 1 account layout, from before signed operations carried a nonce
+#[derive(BorshDeserialize)]
+struct AccountDataV1 {
+    account_id: UnitsObjectId,
+    username: Option<String>,
+    display_name: Option<String>,
+    metadata: HashMap<String, String>,
+    is_active: bool,
+    recovery_addresses: Vec<UnitsObjectId>,
+    created_at: u64,
+    updated_at: u64,
+}
+
+/// Account state is versioned so later module versions can extend it;
+/// untagged data predates versioning and has the version 1 layout
+impl Versioned for AccountData {
+    const SCHEMA_VERSION: u8 = 2;
+
+    fn upgrade(version: u8, bytes: &[u8]) -> Result<Self, KernelError> {
+        let v1: AccountDataV1 = match version {
+            LEGACY_SCHEMA_VERSION | 1 => borsh::from_slice(bytes).map_err(|_| KernelError::InvalidData)?,
+            _ => return Err(KernelError::InvalidData),
+        };
+        Ok(Self {
+            account_id: v1.account_id,
+            username: v1.username,
+            display_name: v1.display_name,
+            metadata: v1.metadata,
+            is_active: v1.is_active,
+            recovery_addresses: v1.recovery_addresses,
+            created_at: v1.created_at,
+            updated_at: v1.updated_at,
+            nonce: 0,
+        })
+    }
 }
 
 // Account metadata helper
@@ -66,6 +109,11 @@ pub const FN_FLEX_ADD_RECOVERY_ADDRESS: &str = "flex_add_recovery_address";
 pub const FN_FLEX_REMOVE_RECOVERY_ADDRESS: &str = "flex_remove_recovery_address";
 pub const FN_FLEX_DEACTIVATE_ACCOUNT: &str = "flex_deactivate_account";
 pub const FN_FLEX_REACTIVATE_ACCOUNT: &str = "flex_reactivate_account";
+pub const FN_FLEX_APPROVE_SPEND: &str = "flex_approve_spend";
+
+/// Furthest past the execution timestamp, in seconds, a signed operation
+/// may set its expiry
+pub const MAX_SIGNATURE_LIFETIME: u64 = 300;
 
 // Error codes
 pub const ERROR_INVALID_USERNAME: u32 = 1001;
@@ -80,8 +128,153 @@ pub const ERROR_SERIALIZATION_FAILED: u32 = 1009;
 pub const ERROR_SIGNATURE_VERIFICATION_FAILED: u32 = 1010;
 pub const ERROR_INVALID_SIGNATURE: u32 = 1011;
 pub const ERROR_MISSING_SIGNATURE: u32 = 1012;
+pub const ERROR_INVALID_FUNCTION: u32 = 1013;
+pub const ERROR_INVALID_PARAMS: u32 = 1014;
+pub const ERROR_USERNAME_TAKEN: u32 = 1015;
+pub const ERROR_SIGNATURE_EXPIRED: u32 = 1016;
+
+/// Account module error, one variant per `ERROR_*` code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum AccountError {
+    InvalidUsername = ERROR_INVALID_USERNAME,
+    AccountNotFound = ERROR_ACCOUNT_NOT_FOUND,
+    Unauthorized = ERROR_UNAUTHORIZED,
+    AccountInactive = ERROR_ACCOUNT_INACTIVE,
+    RecoveryAddressExists = ERROR_RECOVERY_ADDRESS_EXISTS,
+    RecoveryAddressNotFound = ERROR_RECOVERY_ADDRESS_NOT_FOUND,
+    InvalidRecoveryAddress = ERROR_INVALID_RECOVERY_ADDRESS,
+    AccountAlreadyActive = ERROR_ACCOUNT_ALREADY_ACTIVE,
+    SerializationFailed = ERROR_SERIALIZATION_FAILED,
+    SignatureVerificationFailed = ERROR_SIGNATURE_VERIFICATION_FAILED,
+    InvalidSignature = ERROR_INVALID_SIGNATURE,
+    MissingSignature = ERROR_MISSING_SIGNATURE,
+    InvalidFunction = ERROR_INVALID_FUNCTION,
+    InvalidParams = ERROR_INVALID_PARAMS,
+    UsernameTaken = ERROR_USERNAME_TAKEN,
+    SignatureExpired = ERROR_SIGNATURE_EXPIRED,
+}
+
+impl AccountError {
+    /// Every error, in code order
+    pub const ALL: [AccountError; 16] = [
+        Self::InvalidUsername,
+        Self::AccountNotFound,
+        Self::Unauthorized,
+        Self::AccountInactive,
+        Self::RecoveryAddressExists,
+        Self::RecoveryAddressNotFound,
+        Self::InvalidRecoveryAddress,
+        Self::AccountAlreadyActive,
+        Self::SerializationFailed,
+        Self::SignatureVerificationFailed,
+        Self::InvalidSignature,
+        Self::MissingSignature,
+        Self::InvalidFunction,
+        Self::InvalidParams,
+        Self::UsernameTaken,
+        Self::SignatureExpired,
+    ];
+
+    /// Numeric error code reported to the runtime
+    pub fn code(self) -> u32 {
+        self as u32
+    }
+
+    /// Look up the error for a numeric code
+    pub fn from_code(code: u32) -> Option<Self> {
+        let error = match code {
+            ERROR_INVALID_USERNAME => Self::InvalidUsername,
+            ERROR_ACCOUNT_NOT_FOUND => Self::AccountNotFound,
+            ERROR_UNAUTHORIZED => Self::Unauthorized,
+            ERROR_ACCOUNT_INACTIVE => Self::AccountInactive,
+            ERROR_RECOVERY_ADDRESS_EXISTS => Self::RecoveryAddressExists,
+            ERROR_RECOVERY_ADDRESS_NOT_FOUND => Self::RecoveryAddressNotFound,
+            ERROR_INVALID_RECOVERY_ADDRESS => Self::InvalidRecoveryAddress,
+            ERROR_ACCOUNT_ALREADY_ACTIVE => Self::AccountAlreadyActive,
+            ERROR_SERIALIZATION_FAILED => Self::SerializationFailed,
+            ERROR_SIGNATURE_VERIFICATION_FAILED => Self::SignatureVerificationFailed,
+            ERROR_INVALID_SIGNATURE => Self::InvalidSignature,
+            ERROR_MISSING_SIGNATURE => Self::MissingSignature,
+            ERROR_INVALID_FUNCTION => Self::InvalidFunction,
+            ERROR_INVALID_PARAMS => Self::InvalidParams,
+            ERROR_USERNAME_TAKEN => Self::UsernameTaken,
+            ERROR_SIGNATURE_EXPIRED => Self::SignatureExpired,
+            _ => return None,
+        };
+        Some(error)
+    }
+
+    /// Name of the code's constant, as registered in the module's error table
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::InvalidUsername => "ERROR_INVALID_USERNAME",
+            Self::AccountNotFound => "ERROR_ACCOUNT_NOT_FOUND",
+            Self::Unauthorized => "ERROR_UNAUTHORIZED",
+            Self::AccountInactive => "ERROR_ACCOUNT_INACTIVE",
+            Self::RecoveryAddressExists => "ERROR_RECOVERY_ADDRESS_EXISTS",
+            Self::RecoveryAddressNotFound => "ERROR_RECOVERY_ADDRESS_NOT_FOUND",
+            Self::InvalidRecoveryAddress => "ERROR_INVALID_RECOVERY_ADDRESS",
+            Self::AccountAlreadyActive => "ERROR_ACCOUNT_ALREADY_ACTIVE",
+            Self::SerializationFailed => "ERROR_SERIALIZATION_FAILED",
+            Self::SignatureVerificationFailed => "ERROR_SIGNATURE_VERIFICATION_FAILED",
+            Self::InvalidSignature => "ERROR_INVALID_SIGNATURE",
+            Self::MissingSignature => "ERROR_MISSING_SIGNATURE",
+            Self::InvalidFunction => "ERROR_INVALID_FUNCTION",
+            Self::InvalidParams => "ERROR_INVALID_PARAMS",
+            Self::UsernameTaken => "ERROR_USERNAME_TAKEN",
+            Self::SignatureExpired => "ERROR_SIGNATURE_EXPIRED",
+        }
+    }
+
+    pub fn message(self) -> &'static str {
+        match self {
+            Self::InvalidUsername => "Invalid username",
+            Self::AccountNotFound => "Account not found",
+            Self::Unauthorized => "Unauthorized",
+            Self::AccountInactive => "Account is inactive",
+            Self::RecoveryAddressExists => "Recovery address already exists",
+            Self::RecoveryAddressNotFound => "Recovery address not found",
+            Self::InvalidRecoveryAddress => "Invalid recovery address",
+            Self::AccountAlreadyActive => "Account is already active",
+            Self::SerializationFailed => "Serialization failed",
+            Self::SignatureVerificationFailed => "Signature verification failed",
+            Self::InvalidSignature => "Invalid signature",
+            Self::MissingSignature => "Missing signature",
+            Self::InvalidFunction => "Invalid function",
+            Self::InvalidParams => "Invalid parameters",
+            Self::UsernameTaken => "Username is already taken",
+            Self::SignatureExpired => "Signature expired or expires too far ahead",
+        }
+    }
+}
+
+/// Collapse into the generic kernel error expected by `KernelModule`
+impl From<AccountError> for KernelError {
+    fn from(error: AccountError) -> Self {
+        match error {
+            AccountError::AccountNotFound => KernelError::ObjectNotFound,
+            AccountError::Unauthorized
+            | AccountError::SignatureVerificationFailed
+            | AccountError::SignatureExpired => KernelError::Unauthorized,
+            AccountError::SerializationFailed => KernelError::InvalidData,
+            AccountError::InvalidFunction => KernelError::InvalidFunction,
+            _ => KernelError::InvalidParams,
+        }
+    }
+}
+
+impl core::fmt::Display for AccountError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Account error {}: {}", self.code(), self.message())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AccountError {}
 
 // Parameter structures for each function
+/// Targets are the new account, then the username registry if a username is set
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct CreateAccountParams {
     pub username: Option<String>,
@@ -91,12 +284,15 @@ pub struct CreateAccountParams {
     pub signature: Option<Signature>, // Optional for account creation
 }
 
+/// Targets include the username registry if a username is set
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct UpdateAccountParams {
     pub account_id: UnitsObjectId,
     pub username: Option<String>,
     pub display_name: Option<String>,
     pub metadata: Option<HashMap<String, String>>,
+    /// Last execution timestamp at which the signature is accepted
+    pub expires_at: u64,
     pub signature: Signature, // Required for account updates
 }
 
@@ -104,6 +300,8 @@ pub struct UpdateAccountParams {
 pub struct AddRecoveryAddressParams {
     pub account_id: UnitsObjectId,
     pub recovery_address: UnitsObjectId,
+    /// Last execution timestamp at which the signature is accepted
+    pub expires_at: u64,
     pub signature: Signature, // Required for security operations
 }
 
@@ -111,18 +309,26 @@ pub struct AddRecoveryAddressParams {
 pub struct RemoveRecoveryAddressParams {
     pub account_id: UnitsObjectId,
     pub recovery_address: UnitsObjectId,
+    /// Last execution timestamp at which the signature is accepted
+    pub expires_at: u64,
     pub signature: Signature, // Required for security operations
 }
 
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct DeactivateAccountParams {
     pub account_id: UnitsObjectId,
+    /// Last execution timestamp at which the signature is accepted
+    pub expires_at: u64,
     pub signature: Signature, // Required for deactivation
 }
 
+/// Signed by the instruction's controller, which may be the account's
+/// controller or one of its recovery addresses
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct ReactivateAccountParams {
     pub account_id: UnitsObjectId,
+    /// Last execution timestamp at which the signature is accepted
+    pub expires_at: u64,
     pub signature: Signature, // Required for reactivation
 }
 
@@ -186,6 +392,22 @@ pub struct FlexReactivateAccountParams {
     pub credentials: Vec<AuthCredential>,
 }
 
+/// Issue a `token::SpendApproval` letting the account exceed a spend limit once
+///
+/// Authenticated as the `approve_spend` operation, which account policies
+/// hold to a stronger factor than ordinary account updates.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct FlexApproveSpendParams {
+    pub account_id: UnitsObjectId,
+    pub token_id: UnitsObjectId,
+    pub amount: u64,
+    /// The spend limit's next approval sequence
+    pub sequence: u64,
+    pub expires_at: u64,
+    /// Multiple authentication credentials (signatures, MFA codes, etc.)
+    pub credentials: Vec<AuthCredential>,
+}
+
 // Enhanced account data with authentication policy support
 #[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
 pub struct EnhancedAccountData {
@@ -273,6 +495,7 @@ impl AccountData {
             recovery_addresses: Vec::new(),
             created_at,
             updated_at: created_at,
+            nonce: 0,
         }
     }
     
@@ -313,6 +536,19 @@ mod tests {
         assert!(!validate_username("user name")); // Space not allowed
     }
     
+    #[test]
+    fn test_account_error_codes() {
+        for code in ERROR_INVALID_USERNAME..=ERROR_SIGNATURE_EXPIRED {
+            let error = AccountError::from_code(code).unwrap();
+            assert_eq!(error.code(), code);
+            assert_eq!(AccountError::ALL[(code - ERROR_INVALID_USERNAME) as usize], error);
+            assert!(error.name().starts_with("ERROR_"));
+        }
+        assert_eq!(AccountError::InvalidUsername.name(), "ERROR_INVALID_USERNAME");
+        assert_eq!(AccountError::from_code(1000), None);
+        assert_eq!(KernelError::from(AccountError::AccountNotFound) as i32, KernelError::ObjectNotFound as i32);
+    }
+
     #[test]
     fn test_account_data_builder() {
         let account_id = UnitsObjectId::new([1u8; 32]);
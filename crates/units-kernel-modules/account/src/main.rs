@@ -32,14 +32,14 @@ pub extern "C" fn _start() -> ! {
         Err(_) => units_kernel_sdk::exit(KernelError::InvalidParams as i32),
     };
     
-    // Execute the module
-    let effects = match AccountModule::execute(&ctx) {
+    // Execute the module, exiting with the account-specific error code
+    let effects = match AccountModule::process(&ctx) {
         Ok(effects) => effects,
-        Err(e) => units_kernel_sdk::exit(e as i32),
+        Err(e) => units_kernel_sdk::exit(e.code() as i32),
     };
     
     // Write effects to standard output
-    match write_effects(&effects) {
+    match write_effects(&effects, &[]) {
         Ok(_) => units_kernel_sdk::exit(0),
         Err(_) => units_kernel_sdk::exit(KernelError::IOError as i32),
     }
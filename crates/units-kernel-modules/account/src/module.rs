@@ -14,14 +14,17 @@ use std::{vec, vec::Vec, string::String, collections::HashMap};
 units_kernel_sdk::use_default_allocator!();
 
 use crate::{
-    AccountData, CreateAccountParams, UpdateAccountParams, AddRecoveryAddressParams,
+    AccountData, AccountError, CreateAccountParams, UpdateAccountParams, AddRecoveryAddressParams,
     RemoveRecoveryAddressParams, DeactivateAccountParams, ReactivateAccountParams,
-    GetAccountParams, validate_username, 
-    crypto::{verify_signature, create_operation_message, PublicKey, CryptoError},
+    GetAccountParams, validate_username, MAX_SIGNATURE_LIFETIME,
+    FN_CREATE_ACCOUNT, FN_UPDATE_ACCOUNT, FN_ADD_RECOVERY_ADDRESS, FN_REMOVE_RECOVERY_ADDRESS,
+    FN_DEACTIVATE_ACCOUNT, FN_REACTIVATE_ACCOUNT, FN_GET_ACCOUNT,
+    crypto::{verify_signature, account_operation_message, PublicKey, Signature, SIGNATURE_SIZE},
+    usernames::claim_username,
 };
 use units_kernel_sdk::{
     ExecutionContext, ObjectEffect, KernelModule, KernelError,
-    UnitsObject, ObjectType, UnitsObjectId,
+    UnitsObject, ObjectType, decode_versioned, encode_versioned,
 };
 
 #[cfg(not(feature = "std"))]
@@ -35,27 +38,34 @@ type MetadataMap = HashMap<String, String>;
 /// Account kernel module implementation
 pub struct AccountModule;
 
-impl KernelModule for AccountModule {
-    fn execute(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, KernelError> {
+impl AccountModule {
+    /// Execute an instruction, reporting failures with account error codes
+    pub fn process(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, AccountError> {
         match ctx.instruction.target_function.as_str() {
-            "create_account" => handle_create_account(ctx),
-            "update_account" => handle_update_account(ctx),
-            "add_recovery_address" => handle_add_recovery_address(ctx),
-            "remove_recovery_address" => handle_remove_recovery_address(ctx),
-            "deactivate_account" => handle_deactivate_account(ctx),
-            "reactivate_account" => handle_reactivate_account(ctx),
-            "get_account" => handle_get_account(ctx),
-            _ => Err(KernelError::InvalidFunction),
+            FN_CREATE_ACCOUNT => handle_create_account(ctx),
+            FN_UPDATE_ACCOUNT => handle_update_account(ctx),
+            FN_ADD_RECOVERY_ADDRESS => handle_add_recovery_address(ctx),
+            FN_REMOVE_RECOVERY_ADDRESS => handle_remove_recovery_address(ctx),
+            FN_DEACTIVATE_ACCOUNT => handle_deactivate_account(ctx),
+            FN_REACTIVATE_ACCOUNT => handle_reactivate_account(ctx),
+            FN_GET_ACCOUNT => handle_get_account(ctx),
+            _ => Err(AccountError::InvalidFunction),
         }
     }
 }
 
-fn handle_create_account(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, KernelError> {
+impl KernelModule for AccountModule {
+    fn execute(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, KernelError> {
+        Self::process(ctx).map_err(KernelError::from)
+    }
+}
+
+fn handle_create_account(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, AccountError> {
     let params: CreateAccountParams = borsh::from_slice(&ctx.instruction.params)
-        .map_err(|_| KernelError::InvalidData)?;
+        .map_err(|_| AccountError::SerializationFailed)?;
     
     if ctx.instruction.target_objects.is_empty() {
-        return Err(KernelError::InvalidParams);
+        return Err(AccountError::InvalidParams);
     }
     
     let account_id = ctx.instruction.target_objects[0];
@@ -63,7 +73,7 @@ fn handle_create_account(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, Ke
     // Validate username if provided
     if let Some(ref username) = params.username {
         if !validate_username(&username) {
-            return Err(KernelError::InvalidParams);
+            return Err(AccountError::InvalidUsername);
         }
     }
     
@@ -86,61 +96,56 @@ fn handle_create_account(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, Ke
         account_data.recovery_addresses = recovery_addresses;
     }
     
+    // Claim the username in the registry alongside creating the account
+    let registry_effect = match account_data.username {
+        Some(ref username) => claim_username(ctx, account_id, None, username)?,
+        None => None,
+    };
+    
     let account_object = UnitsObject {
         id: account_id,
         controller_id: ctx.instruction.controller_id,
         object_type: ObjectType::Data,
-        data: borsh::to_vec(&account_data)
-            .map_err(|_| KernelError::InvalidData)?,
+        data: encode_versioned(&account_data)
+            .map_err(|_| AccountError::SerializationFailed)?,
     };
     
-    Ok(vec![ObjectEffect::creation(account_object)])
+    let mut effects = vec![ObjectEffect::creation(account_object)];
+    effects.extend(registry_effect);
+    Ok(effects)
 }
 
-fn handle_update_account(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, KernelError> {
+fn handle_update_account(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, AccountError> {
     let params: UpdateAccountParams = borsh::from_slice(&ctx.instruction.params)
-        .map_err(|_| KernelError::InvalidData)?;
+        .map_err(|_| AccountError::SerializationFailed)?;
     
     let account = ctx.objects.get(&params.account_id)
-        .ok_or(KernelError::ObjectNotFound)?;
+        .ok_or(AccountError::AccountNotFound)?;
     
     // Check authorization
     if account.controller_id != ctx.instruction.controller_id {
-        return Err(KernelError::Unauthorized);
+        return Err(AccountError::Unauthorized);
     }
     
+    let mut account_data: AccountData = decode_versioned(&account.data)
+        .map_err(|_| AccountError::SerializationFailed)?;
+    
     // Verify signature - the signature should be from the account owner (controller)
-    let operation_params = borsh::to_vec(&UpdateAccountParams {
-        account_id: params.account_id,
-        username: params.username.clone(),
-        display_name: params.display_name.clone(),
-        metadata: params.metadata.clone(),
-        signature: crate::crypto::Signature::new([0u8; 64]), // Exclude signature from message
-    }).map_err(|_| KernelError::InvalidData)?;
-    
-    verify_account_signature(
-        &ctx.instruction.controller_id,
-        "update_account",
-        &params.account_id,
-        ctx.timestamp,
-        &operation_params,
-        &params.signature,
-    )?;
-    
-    let mut account_data: AccountData = borsh::from_slice(&account.data)
-        .map_err(|_| KernelError::InvalidData)?;
+    verify_operation(ctx, &mut account_data, params.expires_at, &params.signature)?;
     
     // Check if account is active
     if !account_data.is_active {
-        return Err(KernelError::InvalidParams);
+        return Err(AccountError::AccountInactive);
     }
     
-    // Validate username if provided
+    // Validate username if provided, moving the registry entry to it
+    let mut registry_effect = None;
     if let Some(ref username) = params.username {
         if !validate_username(&username) {
-            return Err(KernelError::InvalidParams);
+            return Err(AccountError::InvalidUsername);
         }
-        account_data.username = Some(username.clone());
+        let previous = account_data.username.replace(username.clone());
+        registry_effect = claim_username(ctx, account.id, previous.as_deref(), username)?;
     }
     
     if let Some(display_name) = params.display_name {
@@ -157,36 +162,40 @@ fn handle_update_account(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, Ke
         id: account.id,
         controller_id: account.controller_id,
         object_type: account.object_type.clone(),
-        data: borsh::to_vec(&account_data)
-            .map_err(|_| KernelError::InvalidData)?,
+        data: encode_versioned(&account_data)
+            .map_err(|_| AccountError::SerializationFailed)?,
     };
     
-    Ok(vec![ObjectEffect::modification(account.clone(), updated_account)])
+    let mut effects = vec![ObjectEffect::modification(account.clone(), updated_account)];
+    effects.extend(registry_effect);
+    Ok(effects)
 }
 
-fn handle_add_recovery_address(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, KernelError> {
+fn handle_add_recovery_address(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, AccountError> {
     let params: AddRecoveryAddressParams = borsh::from_slice(&ctx.instruction.params)
-        .map_err(|_| KernelError::InvalidData)?;
+        .map_err(|_| AccountError::SerializationFailed)?;
     
     let account = ctx.objects.get(&params.account_id)
-        .ok_or(KernelError::ObjectNotFound)?;
+        .ok_or(AccountError::AccountNotFound)?;
     
     // Check authorization
     if account.controller_id != ctx.instruction.controller_id {
-        return Err(KernelError::Unauthorized);
+        return Err(AccountError::Unauthorized);
     }
     
-    let mut account_data: AccountData = borsh::from_slice(&account.data)
-        .map_err(|_| KernelError::InvalidData)?;
+    let mut account_data: AccountData = decode_versioned(&account.data)
+        .map_err(|_| AccountError::SerializationFailed)?;
+    
+    verify_operation(ctx, &mut account_data, params.expires_at, &params.signature)?;
     
     // Check if account is active
     if !account_data.is_active {
-        return Err(KernelError::InvalidParams);
+        return Err(AccountError::AccountInactive);
     }
     
     // Check if recovery address already exists
     if account_data.recovery_addresses.contains(&params.recovery_address) {
-        return Err(KernelError::InvalidParams);
+        return Err(AccountError::RecoveryAddressExists);
     }
     
     account_data.recovery_addresses.push(params.recovery_address);
@@ -196,31 +205,33 @@ fn handle_add_recovery_address(ctx: &ExecutionContext) -> Result<Vec<ObjectEffec
         id: account.id,
         controller_id: account.controller_id,
         object_type: account.object_type.clone(),
-        data: borsh::to_vec(&account_data)
-            .map_err(|_| KernelError::InvalidData)?,
+        data: encode_versioned(&account_data)
+            .map_err(|_| AccountError::SerializationFailed)?,
     };
     
     Ok(vec![ObjectEffect::modification(account.clone(), updated_account)])
 }
 
-fn handle_remove_recovery_address(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, KernelError> {
+fn handle_remove_recovery_address(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, AccountError> {
     let params: RemoveRecoveryAddressParams = borsh::from_slice(&ctx.instruction.params)
-        .map_err(|_| KernelError::InvalidData)?;
+        .map_err(|_| AccountError::SerializationFailed)?;
     
     let account = ctx.objects.get(&params.account_id)
-        .ok_or(KernelError::ObjectNotFound)?;
+        .ok_or(AccountError::AccountNotFound)?;
     
     // Check authorization
     if account.controller_id != ctx.instruction.controller_id {
-        return Err(KernelError::Unauthorized);
+        return Err(AccountError::Unauthorized);
     }
     
-    let mut account_data: AccountData = borsh::from_slice(&account.data)
-        .map_err(|_| KernelError::InvalidData)?;
+    let mut account_data: AccountData = decode_versioned(&account.data)
+        .map_err(|_| AccountError::SerializationFailed)?;
+    
+    verify_operation(ctx, &mut account_data, params.expires_at, &params.signature)?;
     
     // Check if account is active
     if !account_data.is_active {
-        return Err(KernelError::InvalidParams);
+        return Err(AccountError::AccountInactive);
     }
     
     // Find and remove recovery address
@@ -228,7 +239,7 @@ fn handle_remove_recovery_address(ctx: &ExecutionContext) -> Result<Vec<ObjectEf
     account_data.recovery_addresses.retain(|&addr| addr != params.recovery_address);
     
     if account_data.recovery_addresses.len() == initial_len {
-        return Err(KernelError::InvalidParams);
+        return Err(AccountError::RecoveryAddressNotFound);
     }
     
     account_data.updated_at = ctx.timestamp;
@@ -237,31 +248,33 @@ fn handle_remove_recovery_address(ctx: &ExecutionContext) -> Result<Vec<ObjectEf
         id: account.id,
         controller_id: account.controller_id,
         object_type: account.object_type.clone(),
-        data: borsh::to_vec(&account_data)
-            .map_err(|_| KernelError::InvalidData)?,
+        data: encode_versioned(&account_data)
+            .map_err(|_| AccountError::SerializationFailed)?,
     };
     
     Ok(vec![ObjectEffect::modification(account.clone(), updated_account)])
 }
 
-fn handle_deactivate_account(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, KernelError> {
+fn handle_deactivate_account(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, AccountError> {
     let params: DeactivateAccountParams = borsh::from_slice(&ctx.instruction.params)
-        .map_err(|_| KernelError::InvalidData)?;
+        .map_err(|_| AccountError::SerializationFailed)?;
     
     let account = ctx.objects.get(&params.account_id)
-        .ok_or(KernelError::ObjectNotFound)?;
+        .ok_or(AccountError::AccountNotFound)?;
     
     // Check authorization
     if account.controller_id != ctx.instruction.controller_id {
-        return Err(KernelError::Unauthorized);
+        return Err(AccountError::Unauthorized);
     }
     
-    let mut account_data: AccountData = borsh::from_slice(&account.data)
-        .map_err(|_| KernelError::InvalidData)?;
+    let mut account_data: AccountData = decode_versioned(&account.data)
+        .map_err(|_| AccountError::SerializationFailed)?;
+    
+    verify_operation(ctx, &mut account_data, params.expires_at, &params.signature)?;
     
     // Check if already inactive
     if !account_data.is_active {
-        return Err(KernelError::InvalidParams);
+        return Err(AccountError::AccountInactive);
     }
     
     account_data.is_active = false;
@@ -271,58 +284,60 @@ fn handle_deactivate_account(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>
         id: account.id,
         controller_id: account.controller_id,
         object_type: account.object_type.clone(),
-        data: borsh::to_vec(&account_data)
-            .map_err(|_| KernelError::InvalidData)?,
+        data: encode_versioned(&account_data)
+            .map_err(|_| AccountError::SerializationFailed)?,
     };
     
     Ok(vec![ObjectEffect::modification(account.clone(), updated_account)])
 }
 
-fn handle_reactivate_account(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, KernelError> {
+fn handle_reactivate_account(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, AccountError> {
     let params: ReactivateAccountParams = borsh::from_slice(&ctx.instruction.params)
-        .map_err(|_| KernelError::InvalidData)?;
+        .map_err(|_| AccountError::SerializationFailed)?;
     
     let account = ctx.objects.get(&params.account_id)
-        .ok_or(KernelError::ObjectNotFound)?;
+        .ok_or(AccountError::AccountNotFound)?;
     
-    let account_data: AccountData = borsh::from_slice(&account.data)
-        .map_err(|_| KernelError::InvalidData)?;
+    let mut account_data: AccountData = decode_versioned(&account.data)
+        .map_err(|_| AccountError::SerializationFailed)?;
     
     // Check authorization (controller or recovery address)
     let is_controller = account.controller_id == ctx.instruction.controller_id;
     let is_recovery = account_data.recovery_addresses.contains(&ctx.instruction.controller_id);
     
     if !is_controller && !is_recovery {
-        return Err(KernelError::Unauthorized);
+        return Err(AccountError::Unauthorized);
     }
     
+    // Whichever of them reactivates signs with its own key
+    verify_operation(ctx, &mut account_data, params.expires_at, &params.signature)?;
+    
     // Check if already active
     if account_data.is_active {
-        return Err(KernelError::InvalidParams);
+        return Err(AccountError::AccountAlreadyActive);
     }
     
-    let mut updated_data = account_data;
-    updated_data.is_active = true;
-    updated_data.updated_at = ctx.timestamp;
+    account_data.is_active = true;
+    account_data.updated_at = ctx.timestamp;
     
     let updated_account = UnitsObject {
         id: account.id,
         controller_id: account.controller_id,
         object_type: account.object_type.clone(),
-        data: borsh::to_vec(&updated_data)
-            .map_err(|_| KernelError::InvalidData)?,
+        data: encode_versioned(&account_data)
+            .map_err(|_| AccountError::SerializationFailed)?,
     };
     
     Ok(vec![ObjectEffect::modification(account.clone(), updated_account)])
 }
 
-fn handle_get_account(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, KernelError> {
+fn handle_get_account(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, AccountError> {
     let params: GetAccountParams = borsh::from_slice(&ctx.instruction.params)
-        .map_err(|_| KernelError::InvalidData)?;
+        .map_err(|_| AccountError::SerializationFailed)?;
     
     // This is a read-only operation, just verify the account exists
     let _account = ctx.objects.get(&params.account_id)
-        .ok_or(KernelError::ObjectNotFound)?;
+        .ok_or(AccountError::AccountNotFound)?;
     
     // No effects for read-only operation
     Ok(vec![])
@@ -338,27 +353,41 @@ fn convert_metadata(metadata: HashMap<String, String>) -> HashMap<String, String
     metadata
 }
 
-/// Verify signature for account operations
-fn verify_account_signature(
-    signer_id: &UnitsObjectId,
-    operation: &str,
-    account_id: &UnitsObjectId,
-    timestamp: u64,
-    params: &[u8],
-    signature: &crate::crypto::Signature,
-) -> Result<(), KernelError> {
+/// Verify the signer's signature over the instruction, consuming the account's nonce
+///
+/// Every signed parameter struct ends with its signature; the message
+/// covers the parameters before it, so none of them can be altered. The
+/// client picks `expires_at`, which must not have passed and must lie
+/// within `MAX_SIGNATURE_LIFETIME` of the execution timestamp.
+fn verify_operation(
+    ctx: &ExecutionContext,
+    account_data: &mut AccountData,
+    expires_at: u64,
+    signature: &Signature,
+) -> Result<(), AccountError> {
+    if expires_at < ctx.timestamp || expires_at - ctx.timestamp > MAX_SIGNATURE_LIFETIME {
+        return Err(AccountError::SignatureExpired);
+    }
+    
     // Convert signer ID to public key
-    let public_key = PublicKey::from_units_object_id(signer_id)
-        .map_err(|_| KernelError::InvalidParams)?;
+    let public_key = PublicKey::from_units_object_id(&ctx.instruction.controller_id)
+        .map_err(|_| AccountError::InvalidSignature)?;
     
     // Create the message to verify
-    let message = create_operation_message(operation, account_id, timestamp, params);
-    
-    // Verify the signature
+    let params = ctx.instruction.params.len().checked_sub(SIGNATURE_SIZE)
+        .map(|len| &ctx.instruction.params[..len])
+        .ok_or(AccountError::MissingSignature)?;
+    let message = account_operation_message(
+        &account_data.account_id,
+        &ctx.instruction.target_function,
+        account_data.nonce,
+        expires_at,
+        params,
+    );
+    
+    // Verify the signature; a signature that fails to parse fails verification too
     verify_signature(&public_key, &message, signature)
-        .map_err(|err| match err {
-            CryptoError::SignatureVerificationFailed => KernelError::Unauthorized,
-            _ => KernelError::InvalidParams,
-        })
+        .map_err(|_| AccountError::SignatureVerificationFailed)?;
+    account_data.nonce += 1;
+    Ok(())
 }
-
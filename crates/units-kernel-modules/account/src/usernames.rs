@@ -0,0 +1,203 @@
+//! Username registry
+//!
+//! Usernames are unique across accounts. A single registry object at
+//! [`username_registry_id`], controlled by the account module itself
+//! (`ACCOUNT_CONTROLLER_ID` on a node), maps the hash of every claimed
+//! username to the account holding it. Creating or renaming an account
+//! updates the registry in the same instruction, so two accounts can never
+//! end up with the same username.
+
+use alloc::collections::btree_map::{BTreeMap, Entry};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use sha2::{Digest, Sha256};
+use units_kernel_sdk::{
+    decode_versioned, encode_versioned, ExecutionContext, KernelError, ObjectEffect, ObjectType, UnitsObject,
+    UnitsObjectId, Versioned,
+};
+
+use crate::AccountError;
+
+/// Domain of the registry's object ID
+pub const USERNAME_REGISTRY_DOMAIN: &[u8] = b"units/account/usernames/v1";
+
+/// Domain separating username hashes from other hashes
+pub const USERNAME_DOMAIN: &[u8] = b"units/account/username/v1";
+
+/// Account holding each claimed username
+#[derive(Debug, Clone, Default, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct UsernameRegistry {
+    /// Holder of each username, by [`username_hash`]
+    pub accounts: BTreeMap<[u8; 32], UnitsObjectId>,
+}
+
+impl Versioned for UsernameRegistry {
+    const SCHEMA_VERSION: u8 = 1;
+
+    fn upgrade(_version: u8, _bytes: &[u8]) -> Result<Self, KernelError> {
+        // The registry was introduced versioned
+        Err(KernelError::InvalidData)
+    }
+}
+
+impl UsernameRegistry {
+    /// Account holding `username`, if any
+    pub fn resolve(&self, username: &str) -> Option<UnitsObjectId> {
+        self.accounts.get(&username_hash(username)).copied()
+    }
+
+    /// Record `username` as held by `account_id`
+    ///
+    /// Returns whether the registry changed; fails if another account
+    /// holds the username.
+    pub fn claim(&mut self, username: &str, account_id: UnitsObjectId) -> Result<bool, AccountError> {
+        match self.accounts.entry(username_hash(username)) {
+            Entry::Vacant(entry) => {
+                entry.insert(account_id);
+                Ok(true)
+            }
+            Entry::Occupied(entry) if *entry.get() == account_id => Ok(false),
+            Entry::Occupied(_) => Err(AccountError::UsernameTaken),
+        }
+    }
+
+    /// Free `username` if `account_id` holds it, returning whether it did
+    pub fn release(&mut self, username: &str, account_id: &UnitsObjectId) -> bool {
+        let hash = username_hash(username);
+        if self.accounts.get(&hash) != Some(account_id) {
+            return false;
+        }
+        self.accounts.remove(&hash);
+        true
+    }
+}
+
+/// ID of the username registry object
+pub fn username_registry_id() -> UnitsObjectId {
+    UnitsObjectId::new(Sha256::digest(USERNAME_REGISTRY_DOMAIN).into())
+}
+
+/// Registry key of `username`
+///
+/// Usernames are compared case-insensitively, so `Alice` and `alice` are
+/// the same username.
+pub fn username_hash(username: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(USERNAME_DOMAIN);
+    hasher.update(username.to_lowercase().as_bytes());
+    hasher.finalize().into()
+}
+
+/// Account holding `username`, according to the stored registry object
+pub fn resolve_username(registry: &UnitsObject, username: &str) -> Result<Option<UnitsObjectId>, AccountError> {
+    let registry: UsernameRegistry = decode_versioned(&registry.data).map_err(|_| AccountError::SerializationFailed)?;
+    Ok(registry.resolve(username))
+}
+
+/// Claim `username` for `account_id`, freeing its `previous` username
+///
+/// The registry must be among the instruction's targets so the runtime
+/// passes it in; it is created by the first claim. Returns the effect on
+/// the registry, or `None` if it is unchanged.
+pub(crate) fn claim_username(
+    ctx: &ExecutionContext,
+    account_id: UnitsObjectId,
+    previous: Option<&str>,
+    username: &str,
+) -> Result<Option<ObjectEffect>, AccountError> {
+    let id = username_registry_id();
+    if !ctx.instruction.target_objects.contains(&id) || account_id == id {
+        return Err(AccountError::InvalidParams);
+    }
+
+    let before = ctx.objects.get(&id);
+    let mut registry: UsernameRegistry = match before {
+        Some(object) if object.controller_id != ctx.instruction.controller_id => return Err(AccountError::Unauthorized),
+        Some(object) => decode_versioned(&object.data).map_err(|_| AccountError::SerializationFailed)?,
+        None => UsernameRegistry::default(),
+    };
+
+    let claimed = registry.claim(username, account_id)?;
+    let released = match previous {
+        Some(previous) if username_hash(previous) != username_hash(username) => registry.release(previous, &account_id),
+        _ => false,
+    };
+    if !claimed && !released {
+        return Ok(None);
+    }
+
+    let after = UnitsObject {
+        id,
+        controller_id: ctx.instruction.controller_id,
+        object_type: ObjectType::Data,
+        data: encode_versioned(&registry).map_err(|_| AccountError::SerializationFailed)?,
+    };
+    Ok(Some(match before {
+        Some(before) => ObjectEffect::modification(before.clone(), after),
+        None => ObjectEffect::creation(after),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usernames_are_claimed_once() {
+        let (alice, bob) = (UnitsObjectId::new([1; 32]), UnitsObjectId::new([2; 32]));
+        let mut registry = UsernameRegistry::default();
+
+        assert_eq!(registry.claim("alice", alice), Ok(true));
+        assert_eq!(registry.claim("alice", alice), Ok(false));
+        assert_eq!(registry.claim("Alice", bob), Err(AccountError::UsernameTaken));
+        assert_eq!(registry.resolve("ALICE"), Some(alice));
+
+        // Only the holder can free a username
+        assert!(!registry.release("alice", &bob));
+        assert!(registry.release("alice", &alice));
+        assert_eq!(registry.claim("alice", bob), Ok(true));
+        assert_eq!(registry.resolve("alice"), Some(bob));
+
+        let decoded: UsernameRegistry = decode_versioned(&encode_versioned(&registry).unwrap()).unwrap();
+        assert_eq!(decoded, registry);
+        assert_ne!(username_registry_id(), UnitsObjectId::new(username_hash("alice")));
+    }
+
+    #[test]
+    fn test_renames_move_the_registry_entry() {
+        use units_kernel_sdk::Instruction;
+
+        let (controller, alice) = (UnitsObjectId::new([9; 32]), UnitsObjectId::new([1; 32]));
+        let mut registry = UsernameRegistry::default();
+        registry.claim("alice", alice).unwrap();
+        let object = UnitsObject {
+            id: username_registry_id(),
+            controller_id: controller,
+            object_type: ObjectType::Data,
+            data: encode_versioned(&registry).unwrap(),
+        };
+        let mut ctx = ExecutionContext {
+            instruction: Instruction {
+                controller_id: controller,
+                target_function: "update_account".into(),
+                target_objects: alloc::vec![alice, object.id],
+                params: alloc::vec![],
+            },
+            objects: [(object.id, object.clone())].into_iter().collect(),
+            slot: 1,
+            timestamp: 2,
+        };
+
+        let effect = claim_username(&ctx, alice, Some("alice"), "alicia").unwrap().unwrap();
+        let renamed = effect.after_image.unwrap();
+        assert_eq!(resolve_username(&renamed, "alicia").unwrap(), Some(alice));
+        assert_eq!(resolve_username(&renamed, "alice").unwrap(), None);
+
+        // Changing only the case keeps the entry, and leaves the registry alone
+        assert!(claim_username(&ctx, alice, Some("alice"), "Alice").unwrap().is_none());
+
+        // A registry controlled by anyone else is not trusted
+        ctx.instruction.controller_id = UnitsObjectId::new([8; 32]);
+        assert_eq!(claim_username(&ctx, alice, None, "alicia"), Err(AccountError::Unauthorized));
+    }
+}
@@ -231,6 +231,41 @@ fn test_configurable_policy() {
     }
 }
 
+#[test]
+fn test_spend_approval_requires_second_factor() {
+    let context = AuthContext {
+        operation: "approve_spend".to_string(),
+        target_account: UnitsObjectId::new([1u8; 32]),
+        requester: UnitsObjectId::new([2u8; 32]),
+        timestamp: 1234567890,
+        operation_data: b"approve".to_vec(),
+    };
+    let signature = AuthCredential::Signature {
+        signature_type: SignatureType::Ed25519,
+        signature_bytes: vec![0u8; 64],
+        public_key: vec![2u8; 32],
+    };
+    let totp = AuthCredential::TimeBasedCode {
+        code: "123456".to_string(),
+        timestamp: 1234567890,
+    };
+    let hardware = AuthCredential::HardwareToken {
+        token_id: "key".to_string(),
+        challenge_response: vec![1u8; 32],
+    };
+
+    // A signature alone is enough to update an account but not to exceed a spend limit
+    let signature_only = vec![signature.clone()];
+    assert!(matches!(StandardAccountPolicy.validate(&signature_only, &context), AuthResult::Failed(_)));
+    let with_totp = vec![signature.clone(), totp.clone()];
+    assert_eq!(StandardAccountPolicy.validate(&with_totp, &context), AuthResult::Success);
+
+    // The high-security policy wants every second factor
+    assert!(matches!(HighSecurityPolicy.validate(&with_totp, &context), AuthResult::Failed(_)));
+    let all_factors = vec![signature, totp, hardware];
+    assert_eq!(HighSecurityPolicy.validate(&all_factors, &context), AuthResult::Success);
+}
+
 #[test]
 fn test_enhanced_account_data() {
     let account_id = UnitsObjectId::new([1u8; 32]);
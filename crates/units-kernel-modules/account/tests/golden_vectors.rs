@@ -0,0 +1,312 @@
+//! Golden wire-format vectors
+//!
+//! Pins the exact Borsh bytes of every parameter and state type the module
+//! exchanges with clients and the host. A failure here means a refactor
+//! changed the wire layout; only update a vector when the format change is
+//! intentional and versioned.
+
+use std::collections::HashMap;
+
+use account::auth::{AuthCredential, AuthFactor, SignatureType};
+use account::crypto::Signature;
+use account::{
+    AccountData, AddRecoveryAddressParams, CreateAccountParams, DeactivateAccountParams,
+    EnhancedAccountData, FlexAddRecoveryAddressParams, FlexCreateAccountParams,
+    FlexDeactivateAccountParams, FlexReactivateAccountParams, FlexRemoveRecoveryAddressParams,
+    FlexUpdateAccountParams, GetAccountParams, ReactivateAccountParams, RemoveRecoveryAddressParams,
+    UpdateAccountParams, UsernameRegistry, username_registry_id,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use units_kernel_sdk::{decode_versioned, encode_versioned, schema_version, UnitsObjectId};
+
+fn assert_borsh<T: BorshSerialize + BorshDeserialize>(name: &str, value: &T, expected: &str) {
+    let bytes = borsh::to_vec(value).unwrap();
+    assert_eq!(hex::encode(&bytes), expected, "Borsh layout of {} changed", name);
+
+    let decoded = T::try_from_slice(&bytes).unwrap();
+    assert_eq!(borsh::to_vec(&decoded).unwrap(), bytes, "{} does not round-trip", name);
+}
+
+fn id(byte: u8) -> UnitsObjectId {
+    UnitsObjectId::new([byte; 32])
+}
+
+fn signature() -> Signature {
+    Signature::new([0x5a; 64])
+}
+
+fn metadata() -> HashMap<String, String> {
+    HashMap::from([("email".to_string(), "a@b.c".to_string())])
+}
+
+fn credentials() -> Vec<AuthCredential> {
+    vec![
+        AuthCredential::Signature {
+            signature_type: SignatureType::Ed25519,
+            signature_bytes: vec![0xab; 4],
+            public_key: vec![0xcd; 4],
+        },
+        AuthCredential::TimeBasedCode {
+            code: "123456".to_string(),
+            timestamp: 1_700_000_000,
+        },
+    ]
+}
+
+#[test]
+fn test_state_vectors() {
+    let account = AccountData::new(id(1), 1_700_000_000)
+        .with_username("alice".to_string())
+        .with_metadata(metadata())
+        .with_recovery_addresses(vec![id(2)]);
+    assert_borsh(
+        "AccountData",
+        &account,
+        concat!(
+            "0101010101010101010101010101010101010101010101010101010101010101",
+            "0105000000616c696365000100000005000000656d61696c050000006140622e",
+            "6301010000000202020202020202020202020202020202020202020202020202",
+            "02020202020200f153650000000000f15365000000000000000000000000",
+        ),
+    );
+
+    // Stored account state carries the schema prefix; version 1 and untagged
+    // state, which lack the nonce, still read
+    let stored = encode_versioned(&account).unwrap();
+    assert_eq!(hex::encode(&stored[..3]), "ff5e02");
+    assert_eq!(stored[3..], borsh::to_vec(&account).unwrap()[..]);
+    assert_eq!(schema_version(&stored), 2);
+    let v1_layout = &stored[3..stored.len() - 8];
+    let legacy: AccountData = decode_versioned(v1_layout).unwrap();
+    assert_eq!(legacy, account);
+    let v1: AccountData = decode_versioned(&[&[0xff, 0x5e, 1], v1_layout].concat()).unwrap();
+    assert_eq!(v1, account);
+
+    let enhanced = EnhancedAccountData::new(id(1), 1_700_000_000)
+        .with_username("alice".to_string())
+        .with_auth_policy(vec![1, 2, 3])
+        .with_supported_factors(vec![AuthFactor::Signature(SignatureType::Ed25519), AuthFactor::TimeBasedCode]);
+    assert_borsh(
+        "EnhancedAccountData",
+        &enhanced,
+        concat!(
+            "0101010101010101010101010101010101010101010101010101010101010101",
+            "0105000000616c6963650000000000010000000000f153650000000000f15365",
+            "00000000010300000001020302000000000001",
+        ),
+    );
+
+    // Registry keys and the registry's ID are derived by clients too
+    let mut registry = UsernameRegistry::default();
+    registry.claim("alice", id(1)).unwrap();
+    assert_borsh(
+        "UsernameRegistry",
+        &registry,
+        concat!(
+            "01000000f40666c753ecfc7a3de7c9070f7edb1bc0b2ec9eb0834680cca21d61",
+            "b6fc06d601010101010101010101010101010101010101010101010101010101",
+            "01010101",
+        ),
+    );
+    assert_eq!(hex::encode(username_registry_id().bytes()), "bde68db85989546e44763777a9503ba96724346f9f4655decd3c2ac7c592d55e");
+}
+
+#[test]
+fn test_param_vectors() {
+    let create = CreateAccountParams {
+        username: Some("alice".to_string()),
+        display_name: None,
+        metadata: Some(metadata()),
+        recovery_addresses: Some(vec![id(2)]),
+        signature: None,
+    };
+    assert_borsh(
+        "CreateAccountParams",
+        &create,
+        concat!(
+            "0105000000616c69636500010100000005000000656d61696c05000000614062",
+            "2e63010100000002020202020202020202020202020202020202020202020202",
+            "0202020202020200",
+        ),
+    );
+
+    let update = UpdateAccountParams {
+        account_id: id(1),
+        username: None,
+        display_name: Some("Alice".to_string()),
+        metadata: None,
+        expires_at: 1_700_000_060,
+        signature: signature(),
+    };
+    assert_borsh(
+        "UpdateAccountParams",
+        &update,
+        concat!(
+            "0101010101010101010101010101010101010101010101010101010101010101",
+            "000105000000416c696365003cf15365000000005a5a5a5a5a5a5a5a5a5a5a5a",
+            "5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a",
+            "5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a",
+        ),
+    );
+
+    let add_recovery = AddRecoveryAddressParams {
+        account_id: id(1),
+        recovery_address: id(2),
+        expires_at: 1_700_000_060,
+        signature: signature(),
+    };
+    assert_borsh(
+        "AddRecoveryAddressParams",
+        &add_recovery,
+        concat!(
+            "0101010101010101010101010101010101010101010101010101010101010101",
+            "0202020202020202020202020202020202020202020202020202020202020202",
+            "3cf15365000000005a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a",
+            "5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a",
+            "5a5a5a5a5a5a5a5a",
+        ),
+    );
+
+    let remove_recovery = RemoveRecoveryAddressParams {
+        account_id: id(1),
+        recovery_address: id(2),
+        expires_at: 1_700_000_060,
+        signature: signature(),
+    };
+    assert_borsh(
+        "RemoveRecoveryAddressParams",
+        &remove_recovery,
+        concat!(
+            "0101010101010101010101010101010101010101010101010101010101010101",
+            "0202020202020202020202020202020202020202020202020202020202020202",
+            "3cf15365000000005a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a",
+            "5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a",
+            "5a5a5a5a5a5a5a5a",
+        ),
+    );
+
+    let deactivate = DeactivateAccountParams { account_id: id(1), expires_at: 1_700_000_060, signature: signature() };
+    assert_borsh(
+        "DeactivateAccountParams",
+        &deactivate,
+        concat!(
+            "0101010101010101010101010101010101010101010101010101010101010101",
+            "3cf15365000000005a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a",
+            "5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a",
+            "5a5a5a5a5a5a5a5a",
+        ),
+    );
+
+    let reactivate = ReactivateAccountParams { account_id: id(1), expires_at: 1_700_000_060, signature: signature() };
+    assert_borsh(
+        "ReactivateAccountParams",
+        &reactivate,
+        concat!(
+            "0101010101010101010101010101010101010101010101010101010101010101",
+            "3cf15365000000005a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a",
+            "5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a",
+            "5a5a5a5a5a5a5a5a",
+        ),
+    );
+
+    assert_borsh(
+
+        "GetAccountParams",
+
+        &GetAccountParams { account_id: id(1) },
+
+        "0101010101010101010101010101010101010101010101010101010101010101",
+
+    );
+}
+
+#[test]
+fn test_flex_param_vectors() {
+    let create = FlexCreateAccountParams {
+        username: Some("alice".to_string()),
+        display_name: None,
+        metadata: None,
+        recovery_addresses: None,
+        credentials: credentials(),
+    };
+    assert_borsh(
+        "FlexCreateAccountParams",
+        &create,
+        concat!(
+            "0105000000616c69636500000002000000000004000000abababab04000000cd",
+            "cdcdcd010600000031323334353600f1536500000000",
+        ),
+    );
+
+    let update = FlexUpdateAccountParams {
+        account_id: id(1),
+        username: None,
+        display_name: Some("Alice".to_string()),
+        metadata: Some(metadata()),
+        credentials: credentials(),
+    };
+    assert_borsh(
+        "FlexUpdateAccountParams",
+        &update,
+        concat!(
+            "0101010101010101010101010101010101010101010101010101010101010101",
+            "000105000000416c696365010100000005000000656d61696c05000000614062",
+            "2e6302000000000004000000abababab04000000cdcdcdcd0106000000313233",
+            "34353600f1536500000000",
+        ),
+    );
+
+    let add_recovery = FlexAddRecoveryAddressParams {
+        account_id: id(1),
+        recovery_address: id(2),
+        credentials: credentials(),
+    };
+    assert_borsh(
+        "FlexAddRecoveryAddressParams",
+        &add_recovery,
+        concat!(
+            "0101010101010101010101010101010101010101010101010101010101010101",
+            "0202020202020202020202020202020202020202020202020202020202020202",
+            "02000000000004000000abababab04000000cdcdcdcd01060000003132333435",
+            "3600f1536500000000",
+        ),
+    );
+
+    let remove_recovery = FlexRemoveRecoveryAddressParams {
+        account_id: id(1),
+        recovery_address: id(2),
+        credentials: credentials(),
+    };
+    assert_borsh(
+        "FlexRemoveRecoveryAddressParams",
+        &remove_recovery,
+        concat!(
+            "0101010101010101010101010101010101010101010101010101010101010101",
+            "0202020202020202020202020202020202020202020202020202020202020202",
+            "02000000000004000000abababab04000000cdcdcdcd01060000003132333435",
+            "3600f1536500000000",
+        ),
+    );
+
+    let deactivate = FlexDeactivateAccountParams { account_id: id(1), credentials: credentials() };
+    assert_borsh(
+        "FlexDeactivateAccountParams",
+        &deactivate,
+        concat!(
+            "0101010101010101010101010101010101010101010101010101010101010101",
+            "02000000000004000000abababab04000000cdcdcdcd01060000003132333435",
+            "3600f1536500000000",
+        ),
+    );
+
+    let reactivate = FlexReactivateAccountParams { account_id: id(1), credentials: credentials() };
+    assert_borsh(
+        "FlexReactivateAccountParams",
+        &reactivate,
+        concat!(
+            "0101010101010101010101010101010101010101010101010101010101010101",
+            "02000000000004000000abababab04000000cdcdcdcd01060000003132333435",
+            "3600f1536500000000",
+        ),
+    );
+}
@@ -1,7 +1,53 @@
 use std::collections::HashMap;
+use account::crypto::{account_operation_message, Signature};
 use account::{
-    AccountData, validate_username,
+    AccountData, AccountError, AccountModule, AddRecoveryAddressParams, CreateAccountParams, DeactivateAccountParams,
+    ReactivateAccountParams, resolve_username, username_registry_id, validate_username, FN_ADD_RECOVERY_ADDRESS,
+    FN_CREATE_ACCOUNT, FN_DEACTIVATE_ACCOUNT, FN_REACTIVATE_ACCOUNT, ERROR_INVALID_USERNAME, ERROR_SIGNATURE_EXPIRED,
+    ERROR_USERNAME_TAKEN, MAX_SIGNATURE_LIFETIME,
 };
+use curve25519_dalek::{constants::ED25519_BASEPOINT_POINT, scalar::Scalar};
+use sha2::{Digest, Sha512};
+use units_kernel_sdk::{
+    decode_versioned, encode_versioned, ExecutionContext, Instruction, KernelError, KernelModule, ObjectType, UnitsObject, UnitsObjectId,
+};
+
+/// Ed25519 signature of `message` by the key with secret scalar `secret`
+fn sign(secret: &Scalar, message: &[u8]) -> Signature {
+    let hash_scalar = |parts: &[&[u8]]| {
+        let mut hasher = Sha512::new();
+        parts.iter().for_each(|part| hasher.update(part));
+        Scalar::from_bytes_mod_order_wide(&hasher.finalize().into())
+    };
+    let public_key = (secret * ED25519_BASEPOINT_POINT).compress();
+    let r = hash_scalar(&[secret.as_bytes(), message]);
+    let big_r = (r * ED25519_BASEPOINT_POINT).compress();
+    let k = hash_scalar(&[big_r.as_bytes(), public_key.as_bytes(), message]);
+
+    let mut bytes = [0u8; 64];
+    bytes[..32].copy_from_slice(big_r.as_bytes());
+    bytes[32..].copy_from_slice((r + k * secret).as_bytes());
+    Signature::new(bytes)
+}
+
+fn context(
+    function: &str,
+    target_objects: Vec<UnitsObjectId>,
+    params: Vec<u8>,
+    objects: Vec<UnitsObject>,
+) -> ExecutionContext {
+    ExecutionContext {
+        instruction: Instruction {
+            controller_id: UnitsObjectId::new([9u8; 32]),
+            target_function: function.to_string(),
+            target_objects,
+            params,
+        },
+        objects: objects.into_iter().map(|obj| (obj.id, obj)).collect(),
+        slot: 1,
+        timestamp: 1234567890,
+    }
+}
 
 #[test]
 fn test_username_validation() {
@@ -22,8 +68,6 @@ fn test_username_validation() {
 
 #[test]
 fn test_account_data_creation() {
-    use units_kernel_sdk::UnitsObjectId;
-    
     let account_id = UnitsObjectId::new([1u8; 32]);
     let timestamp = 1234567890;
     
@@ -41,8 +85,6 @@ fn test_account_data_creation() {
 
 #[test]
 fn test_account_data_serialization() {
-    use units_kernel_sdk::UnitsObjectId;
-    
     let account_id = UnitsObjectId::new([1u8; 32]);
     let recovery_id = UnitsObjectId::new([2u8; 32]);
     
@@ -67,4 +109,208 @@ fn test_account_data_serialization() {
     assert_eq!(account.recovery_addresses, deserialized.recovery_addresses);
     assert_eq!(account.created_at, deserialized.created_at);
     assert_eq!(account.updated_at, deserialized.updated_at);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_create_account_with_library_params() {
+    let account_id = UnitsObjectId::new([1u8; 32]);
+    let mut metadata = HashMap::new();
+    metadata.insert("email".to_string(), "test@example.com".to_string());
+
+    let params = CreateAccountParams {
+        username: Some("testuser".to_string()),
+        display_name: None,
+        metadata: Some(metadata.clone()),
+        recovery_addresses: None,
+        signature: None,
+    };
+    let ctx = context(FN_CREATE_ACCOUNT, vec![account_id, username_registry_id()], borsh::to_vec(&params).unwrap(), vec![]);
+
+    let effects = AccountModule::process(&ctx).unwrap();
+    assert_eq!(effects.len(), 2);
+
+    let created = effects[0].after_image.as_ref().unwrap();
+    let account: AccountData = decode_versioned(&created.data).unwrap();
+    assert_eq!(account.username, Some("testuser".to_string()));
+    assert_eq!(account.metadata, metadata);
+
+    // The first username creates the registry
+    let registry = effects[1].after_image.as_ref().unwrap();
+    assert!(effects[1].before_image.is_none());
+    assert_eq!(resolve_username(registry, "testuser").unwrap(), Some(account_id));
+}
+
+#[test]
+fn test_usernames_are_unique() {
+    let create = |account_id: UnitsObjectId, username: &str, registry: Option<&UnitsObject>| {
+        let params = CreateAccountParams {
+            username: Some(username.to_string()),
+            display_name: None,
+            metadata: None,
+            recovery_addresses: None,
+            signature: None,
+        };
+        let targets = vec![account_id, username_registry_id()];
+        context(FN_CREATE_ACCOUNT, targets, borsh::to_vec(&params).unwrap(), registry.into_iter().cloned().collect())
+    };
+    let (alice, bob) = (UnitsObjectId::new([1u8; 32]), UnitsObjectId::new([2u8; 32]));
+
+    let effects = AccountModule::process(&create(alice, "alice", None)).unwrap();
+    let registry = effects[1].after_image.clone().unwrap();
+
+    // Usernames are compared case-insensitively
+    let err = AccountModule::process(&create(bob, "Alice", Some(&registry))).unwrap_err();
+    assert_eq!(err, AccountError::UsernameTaken);
+    assert_eq!(err.code(), ERROR_USERNAME_TAKEN);
+
+    let effects = AccountModule::process(&create(bob, "bob", Some(&registry))).unwrap();
+    let registry = effects[1].after_image.clone().unwrap();
+    assert_eq!(effects[1].before_image.as_ref().map(|before| before.id), Some(username_registry_id()));
+    assert_eq!(resolve_username(&registry, "alice").unwrap(), Some(alice));
+    assert_eq!(resolve_username(&registry, "bob").unwrap(), Some(bob));
+
+    // Without the registry among the targets a username cannot be claimed
+    let mut ctx = create(UnitsObjectId::new([3u8; 32]), "carol", Some(&registry));
+    ctx.instruction.target_objects.truncate(1);
+    assert_eq!(AccountModule::process(&ctx).unwrap_err(), AccountError::InvalidParams);
+}
+
+#[test]
+fn test_account_errors() {
+    let account_id = UnitsObjectId::new([1u8; 32]);
+
+    // Invalid usernames surface the account-specific code
+    let params = CreateAccountParams {
+        username: Some("a".to_string()),
+        display_name: None,
+        metadata: None,
+        recovery_addresses: None,
+        signature: None,
+    };
+    let ctx = context(FN_CREATE_ACCOUNT, vec![account_id], borsh::to_vec(&params).unwrap(), vec![]);
+    let err = AccountModule::process(&ctx).unwrap_err();
+    assert_eq!(err, AccountError::InvalidUsername);
+    assert_eq!(err.code(), ERROR_INVALID_USERNAME);
+    assert!(matches!(AccountModule::execute(&ctx), Err(KernelError::InvalidParams)));
+
+    // Reactivating with a signature that has expired
+    let account = UnitsObject {
+        id: account_id,
+        controller_id: UnitsObjectId::new([9u8; 32]),
+        object_type: ObjectType::Data,
+        data: encode_versioned(&AccountData::new(account_id, 0)).unwrap(),
+    };
+    let params = borsh::to_vec(&ReactivateAccountParams {
+        account_id,
+        expires_at: 0,
+        signature: account::crypto::Signature::new([0u8; 64]),
+    })
+    .unwrap();
+    let ctx = context(FN_REACTIVATE_ACCOUNT, vec![account_id], params.clone(), vec![account]);
+    let err = AccountModule::process(&ctx).unwrap_err();
+    assert_eq!(err, AccountError::SignatureExpired);
+    assert_eq!(err.code(), ERROR_SIGNATURE_EXPIRED);
+    assert!(matches!(AccountModule::execute(&ctx), Err(KernelError::Unauthorized)));
+
+    // Missing account and unknown function
+    let ctx = context(FN_REACTIVATE_ACCOUNT, vec![account_id], params, vec![]);
+    assert_eq!(AccountModule::process(&ctx).unwrap_err(), AccountError::AccountNotFound);
+    assert!(matches!(AccountModule::execute(&ctx), Err(KernelError::ObjectNotFound)));
+
+    let ctx = context("transfer", vec![], vec![], vec![]);
+    assert_eq!(AccountModule::process(&ctx).unwrap_err(), AccountError::InvalidFunction);
+}
+
+#[test]
+fn test_operations_require_the_owners_signature() {
+    let secret = Scalar::from_bytes_mod_order([7u8; 32]);
+    let owner = UnitsObjectId::new((secret * ED25519_BASEPOINT_POINT).compress().to_bytes());
+    let account_id = UnitsObjectId::new([1u8; 32]);
+    let recovery_secret = Scalar::from_bytes_mod_order([3u8; 32]);
+    let recovery_address = UnitsObjectId::new((recovery_secret * ED25519_BASEPOINT_POINT).compress().to_bytes());
+    let account = UnitsObject {
+        id: account_id,
+        controller_id: owner,
+        object_type: ObjectType::Data,
+        data: encode_versioned(&AccountData::new(account_id, 0)).unwrap(),
+    };
+
+    // Parameters are signed without their trailing signature, until a minute
+    // past the execution timestamp
+    let expires_at = 1234567890 + 60;
+    let signed = |account: &UnitsObject, function: &str, params: Vec<u8>, signer: &Scalar, nonce: u64| {
+        let mut params = params[..params.len() - 64].to_vec();
+        let message = account_operation_message(&account_id, function, nonce, expires_at, &params);
+        params.extend(sign(signer, &message).to_bytes());
+        let mut ctx = context(function, vec![account_id], params, vec![account.clone()]);
+        ctx.instruction.controller_id = owner;
+        ctx
+    };
+    let add_expiring = |expires_at: u64| {
+        borsh::to_vec(&AddRecoveryAddressParams {
+            account_id,
+            recovery_address,
+            expires_at,
+            signature: Signature::new([0u8; 64]),
+        })
+        .unwrap()
+    };
+    let add = add_expiring(expires_at);
+
+    let ctx = signed(&account, FN_ADD_RECOVERY_ADDRESS, add.clone(), &secret, 0);
+    let updated = AccountModule::process(&ctx).unwrap()[0].after_image.clone().unwrap();
+    let data: AccountData = decode_versioned(&updated.data).unwrap();
+    assert_eq!((data.recovery_addresses, data.nonce), (vec![recovery_address], 1));
+
+    // The nonce moved on, so the same signature cannot be replayed
+    let mut replay = signed(&account, FN_ADD_RECOVERY_ADDRESS, add.clone(), &secret, 0);
+    replay.objects.insert(account_id, updated.clone());
+    assert_eq!(AccountModule::process(&replay).unwrap_err(), AccountError::SignatureVerificationFailed);
+
+    // Signatures by another key, or over other parameters, are refused
+    let stranger = Scalar::from_bytes_mod_order([8u8; 32]);
+    let ctx = signed(&account, FN_ADD_RECOVERY_ADDRESS, add.clone(), &stranger, 0);
+    assert_eq!(AccountModule::process(&ctx).unwrap_err(), AccountError::SignatureVerificationFailed);
+    let mut ctx = signed(&account, FN_ADD_RECOVERY_ADDRESS, add, &secret, 0);
+    ctx.instruction.params[32] ^= 1;
+    assert_eq!(AccountModule::process(&ctx).unwrap_err(), AccountError::SignatureVerificationFailed);
+
+    // Signatures must expire, and no later than the lifetime allows
+    for expires_at in [1234567890 - 1, 1234567890 + MAX_SIGNATURE_LIFETIME + 1] {
+        let ctx = signed(&account, FN_ADD_RECOVERY_ADDRESS, add_expiring(expires_at), &secret, 0);
+        assert_eq!(AccountModule::process(&ctx).unwrap_err(), AccountError::SignatureExpired);
+    }
+
+    let reactivate = borsh::to_vec(&ReactivateAccountParams {
+        account_id,
+        expires_at,
+        signature: Signature::new([0u8; 64]),
+    })
+    .unwrap();
+    let ctx = signed(&updated, FN_REACTIVATE_ACCOUNT, reactivate.clone(), &secret, 1);
+    assert_eq!(AccountModule::process(&ctx).unwrap_err(), AccountError::AccountAlreadyActive);
+
+    let deactivate = borsh::to_vec(&DeactivateAccountParams {
+        account_id,
+        expires_at,
+        signature: Signature::new([0u8; 64]),
+    })
+    .unwrap();
+    let ctx = signed(&updated, FN_DEACTIVATE_ACCOUNT, deactivate, &secret, 1);
+    let deactivated = AccountModule::process(&ctx).unwrap()[0].after_image.clone().unwrap();
+    let data: AccountData = decode_versioned(&deactivated.data).unwrap();
+    assert!(!data.is_active);
+    assert_eq!(data.nonce, 2);
+
+    // A recovery address reactivates with its own signature, and only with it
+    let mut unsigned = context(FN_REACTIVATE_ACCOUNT, vec![account_id], reactivate.clone(), vec![deactivated.clone()]);
+    unsigned.instruction.controller_id = recovery_address;
+    assert_eq!(AccountModule::process(&unsigned).unwrap_err(), AccountError::SignatureVerificationFailed);
+
+    let mut ctx = signed(&deactivated, FN_REACTIVATE_ACCOUNT, reactivate, &recovery_secret, 2);
+    ctx.instruction.controller_id = recovery_address;
+    let reactivated = AccountModule::process(&ctx).unwrap()[0].after_image.clone().unwrap();
+    let data: AccountData = decode_versioned(&reactivated.data).unwrap();
+    assert!(data.is_active);
+    assert_eq!(data.nonce, 3);
+}
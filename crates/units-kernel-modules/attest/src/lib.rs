@@ -0,0 +1,75 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use sha2::{Digest, Sha256};
+use units_kernel_sdk::{KernelError, UnitsObjectId, Versioned};
+
+pub const ATTEST_MODULE_NAME: &str = "attest";
+
+/// Domain separating attestation IDs from other hash-derived object IDs
+pub const ATTESTATION_DOMAIN: &[u8] = b"units/attest/v1";
+
+/// Record that a document with `document_hash` existed by `slot`
+///
+/// The record is an ordinary object, so its proof chains into the state
+/// proof of the slot it was written in. The first attestation of a
+/// document stands; later ones are rejected, so the record always carries
+/// the earliest time the document was seen.
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct AttestationData {
+    pub document_hash: [u8; 32],
+    /// Account that submitted the document hash
+    pub attester_id: UnitsObjectId,
+    pub slot: u64,
+    pub timestamp: u64,
+}
+
+impl Versioned for AttestationData {
+    const SCHEMA_VERSION: u8 = 1;
+
+    fn upgrade(_version: u8, _bytes: &[u8]) -> Result<Self, KernelError> {
+        // Attestations were introduced versioned
+        Err(KernelError::InvalidData)
+    }
+}
+
+/// Parameters of `attest`; targets are the attestation object and the attester
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct AttestParams {
+    pub document_hash: [u8; 32],
+}
+
+/// ID of the object attesting to `document_hash`
+///
+/// Anyone holding the document can recompute it and look the record up.
+pub fn attestation_id(document_hash: &[u8; 32]) -> UnitsObjectId {
+    let mut hasher = Sha256::new();
+    hasher.update(ATTESTATION_DOMAIN);
+    hasher.update(document_hash);
+    UnitsObjectId::new(hasher.finalize().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use units_kernel_sdk::{decode_versioned, encode_versioned};
+
+    #[test]
+    fn test_attestation_ids_are_domain_separated() {
+        let document_hash = [7; 32];
+        assert_eq!(attestation_id(&document_hash), attestation_id(&[7; 32]));
+        assert_ne!(attestation_id(&document_hash), attestation_id(&[8; 32]));
+        assert_ne!(attestation_id(&document_hash), UnitsObjectId::new(document_hash));
+
+        let data = AttestationData {
+            document_hash,
+            attester_id: UnitsObjectId::new([1; 32]),
+            slot: 4,
+            timestamp: 1_700_000_000,
+        };
+        let decoded: AttestationData = decode_versioned(&encode_versioned(&data).unwrap()).unwrap();
+        assert_eq!(decoded, data);
+    }
+}
@@ -0,0 +1,145 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(not(feature = "std"), no_main)]
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+
+#[cfg(feature = "std")]
+use std::{vec, vec::Vec};
+
+#[cfg(not(feature = "std"))]
+units_kernel_sdk::use_default_allocator!();
+
+#[cfg(not(feature = "std"))]
+use units_kernel_sdk::{read_context, write_effects};
+
+use attest::{attestation_id, AttestParams, AttestationData};
+use units_kernel_sdk::{
+    ExecutionContext, ObjectEffect, KernelModule, KernelError, UnitsObject, ObjectType, encode_versioned,
+};
+
+/// Proof-of-existence kernel module implementation
+#[allow(dead_code)]
+struct AttestModule;
+
+impl KernelModule for AttestModule {
+    fn execute(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, KernelError> {
+        match ctx.instruction.target_function.as_str() {
+            "attest" => handle_attest(ctx),
+            _ => Err(KernelError::InvalidFunction),
+        }
+    }
+}
+
+fn handle_attest(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, KernelError> {
+    let params: AttestParams = borsh::from_slice(&ctx.instruction.params)
+        .map_err(|_| KernelError::InvalidParams)?;
+
+    if ctx.instruction.target_objects.len() < 2 {
+        return Err(KernelError::InvalidParams);
+    }
+
+    // The record must live at the ID derived from the hash, so it can be found
+    let id = ctx.instruction.target_objects[0];
+    if id != attestation_id(&params.document_hash) {
+        return Err(KernelError::InvalidParams);
+    }
+    // The earliest attestation stands
+    if ctx.objects.contains_key(&id) {
+        return Err(KernelError::Unauthorized);
+    }
+
+    let attestation = AttestationData {
+        document_hash: params.document_hash,
+        attester_id: ctx.instruction.target_objects[1],
+        slot: ctx.slot,
+        timestamp: ctx.timestamp,
+    };
+
+    let attestation_object = UnitsObject {
+        id,
+        controller_id: ctx.instruction.controller_id,
+        object_type: ObjectType::Data,
+        data: encode_versioned(&attestation)?,
+    };
+
+    Ok(vec![ObjectEffect::creation(attestation_object)])
+}
+
+/// Entry point for the kernel module
+#[cfg(not(feature = "std"))]
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    let ctx = match read_context() {
+        Ok(ctx) => ctx,
+        Err(_) => units_kernel_sdk::exit(KernelError::InvalidParams as i32),
+    };
+
+    let effects = match AttestModule::execute(&ctx) {
+        Ok(effects) => effects,
+        Err(e) => units_kernel_sdk::exit(e as i32),
+    };
+
+    match write_effects(&effects, &[]) {
+        Ok(_) => units_kernel_sdk::exit(0),
+        Err(_) => units_kernel_sdk::exit(KernelError::IOError as i32),
+    }
+}
+
+/// Entry point for std builds (testing)
+#[cfg(feature = "std")]
+fn main() {
+    println!("Attest kernel module - std build for testing");
+}
+
+/// Panic handler for no_std environment
+#[cfg(not(feature = "std"))]
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    units_kernel_sdk::exit(KernelError::Panic as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use units_kernel_sdk::{decode_versioned, Instruction, UnitsObjectId};
+
+    fn attest_context(target: UnitsObjectId, document_hash: [u8; 32]) -> ExecutionContext {
+        ExecutionContext {
+            instruction: Instruction {
+                controller_id: UnitsObjectId::new([4; 32]),
+                target_function: "attest".to_string(),
+                target_objects: vec![target, UnitsObjectId::new([9; 32])],
+                params: borsh::to_vec(&AttestParams { document_hash }).unwrap(),
+            },
+            objects: HashMap::new(),
+            slot: 12,
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn test_attest_records_first_sighting() {
+        let document_hash = [5; 32];
+        let id = attestation_id(&document_hash);
+        let mut ctx = attest_context(id, document_hash);
+
+        let effects = AttestModule::execute(&ctx).unwrap();
+        let created = effects[0].after_image.clone().unwrap();
+        let data: AttestationData = decode_versioned(&created.data).unwrap();
+        assert_eq!((data.slot, data.timestamp), (12, 1_700_000_000));
+        assert_eq!(data.attester_id, UnitsObjectId::new([9; 32]));
+
+        // A second attestation of the same document is refused
+        ctx.objects.insert(id, created);
+        assert!(matches!(AttestModule::execute(&ctx), Err(KernelError::Unauthorized)));
+
+        // Records must sit at the derived ID
+        let ctx = attest_context(UnitsObjectId::new([1; 32]), document_hash);
+        assert!(matches!(AttestModule::execute(&ctx), Err(KernelError::InvalidParams)));
+    }
+}
@@ -3,8 +3,13 @@
 extern crate alloc;
 
 use alloc::string::String;
+use alloc::vec::Vec;
 use borsh::{BorshDeserialize, BorshSerialize};
-use units_kernel_sdk::UnitsObjectId;
+use curve25519_dalek::constants::ED25519_BASEPOINT_POINT;
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use curve25519_dalek::scalar::Scalar;
+use sha2::{Digest, Sha256, Sha512};
+use units_kernel_sdk::{KernelError, UnitsObjectId, Versioned, LEGACY_SCHEMA_VERSION};
 
 pub const TOKEN_MODULE_NAME: &str = "token";
 
@@ -17,6 +22,19 @@ pub struct TokenData {
     pub is_frozen: bool,
 }
 
+/// Token state is versioned so later module versions can extend it;
+/// untagged data predates versioning and has the version 1 layout
+impl Versioned for TokenData {
+    const SCHEMA_VERSION: u8 = 1;
+
+    fn upgrade(version: u8, bytes: &[u8]) -> Result<Self, KernelError> {
+        match version {
+            LEGACY_SCHEMA_VERSION => borsh::from_slice(bytes).map_err(|_| KernelError::InvalidData),
+            _ => Err(KernelError::InvalidData),
+        }
+    }
+}
+
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct BalanceData {
     pub token_id: UnitsObjectId,
@@ -31,6 +49,204 @@ pub struct TransferParams {
     pub amount: u64,
 }
 
+/// Rolling cap on what one owner may transfer of one token per period
+///
+/// The tracking object is controlled by the token controller. Transfers
+/// find it among the objects loaded for the instruction, so deployments
+/// register a `transfer_token` prefetch rule that derives its ID from the
+/// sender's balance object, and create the limit at that ID.
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct SpendLimitData {
+    pub token_id: UnitsObjectId,
+    pub owner_id: UnitsObjectId,
+    /// Most the owner may transfer per period without an approval
+    pub limit: u64,
+    pub period_secs: u64,
+    /// Start of the current period
+    pub window_start: u64,
+    /// Amount transferred in the current period
+    pub spent: u64,
+    /// Controller whose `SpendApproval` objects lift the limit, normally
+    /// the account module, which issues them under a stronger auth policy
+    pub approver_id: UnitsObjectId,
+    /// Approvals redeemed so far; the next approval must carry this sequence
+    pub approvals_used: u64,
+}
+
+impl Versioned for SpendLimitData {
+    const SCHEMA_VERSION: u8 = 1;
+
+    fn upgrade(_version: u8, _bytes: &[u8]) -> Result<Self, KernelError> {
+        // Spend limits were introduced versioned
+        Err(KernelError::InvalidData)
+    }
+}
+
+/// One-time permission to exceed a spend limit, issued by the limit's approver
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct SpendApproval {
+    pub token_id: UnitsObjectId,
+    pub owner_id: UnitsObjectId,
+    /// Largest transfer the approval covers
+    pub amount: u64,
+    /// Must equal the limit's `approvals_used` when redeemed
+    pub sequence: u64,
+    /// Timestamp after which the approval can no longer be redeemed
+    pub expires_at: u64,
+}
+
+impl SpendApproval {
+    /// Whether this approval can cover `amount` against `limit` at `now`
+    pub fn covers(&self, limit: &SpendLimitData, amount: u64, now: u64) -> bool {
+        self.token_id == limit.token_id
+            && self.owner_id == limit.owner_id
+            && self.sequence == limit.approvals_used
+            && self.amount >= amount
+            && now <= self.expires_at
+    }
+}
+
+impl SpendLimitData {
+    /// Start the period containing `now` if the current one has ended
+    fn roll_window(&mut self, now: u64) {
+        if self.period_secs == 0 || now < self.window_start {
+            return;
+        }
+        let elapsed_periods = (now - self.window_start) / self.period_secs;
+        if elapsed_periods > 0 {
+            self.window_start += elapsed_periods * self.period_secs;
+            self.spent = 0;
+        }
+    }
+
+    /// Record a transfer of `amount` at `now`
+    ///
+    /// A transfer that would take the period's spending over the limit
+    /// needs an approval covering it, which is then used up. Approved
+    /// transfers do not count against the limit.
+    pub fn record_spend(&mut self, amount: u64, now: u64, approval: Option<&SpendApproval>) -> Result<(), KernelError> {
+        self.roll_window(now);
+        let total = self.spent.checked_add(amount).ok_or(KernelError::Overflow)?;
+        if total <= self.limit {
+            self.spent = total;
+            return Ok(());
+        }
+        match approval {
+            Some(approval) if approval.covers(self, amount, now) => {
+                self.approvals_used += 1;
+                Ok(())
+            }
+            _ => Err(KernelError::Unauthorized),
+        }
+    }
+
+    /// Whether replacing this limit with `next` loosens it, which needs an approval
+    pub fn is_loosened_by(&self, next: &SetSpendLimitParams) -> bool {
+        next.limit > self.limit || next.period_secs < self.period_secs || next.approver_id != self.approver_id
+    }
+}
+
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct SetSpendLimitParams {
+    pub limit: u64,
+    pub period_secs: u64,
+    pub approver_id: UnitsObjectId,
+}
+
+/// Amount of an owner's token that a spender has been allowed to move
+///
+/// Allowances are set by `permit`, from a signature the owner made offline,
+/// so granting one takes no transaction from the owner. Each lives at
+/// `allowance_id`, so the nonce guarding its permits is the only one.
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct AllowanceData {
+    pub token_id: UnitsObjectId,
+    pub owner_id: UnitsObjectId,
+    pub spender_id: UnitsObjectId,
+    pub amount: u64,
+    /// Permits applied so far; the next permit must be signed over this nonce
+    pub nonce: u64,
+}
+
+impl Versioned for AllowanceData {
+    const SCHEMA_VERSION: u8 = 1;
+
+    fn upgrade(_version: u8, _bytes: &[u8]) -> Result<Self, KernelError> {
+        // Allowances were introduced versioned
+        Err(KernelError::InvalidData)
+    }
+}
+
+/// ID of the allowance `owner_id` grants `spender_id` on `token_id`
+///
+/// Permits only apply at this ID; were the allowance object the caller's
+/// choice, a fresh object would start at nonce 0 and replay old permits.
+pub fn allowance_id(token_id: &UnitsObjectId, owner_id: &UnitsObjectId, spender_id: &UnitsObjectId) -> UnitsObjectId {
+    UnitsObjectId::new(
+        Sha256::new()
+            .chain_update(b"units/token/allowance/v1")
+            .chain_update(token_id.bytes())
+            .chain_update(owner_id.bytes())
+            .chain_update(spender_id.bytes())
+            .finalize()
+            .into(),
+    )
+}
+
+/// An owner's signed grant of an allowance, submittable by anyone
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct PermitParams {
+    pub spender_id: UnitsObjectId,
+    pub amount: u64,
+    /// Must equal the allowance's current nonce, so a permit applies once
+    pub nonce: u64,
+    /// Timestamp after which the permit can no longer be applied
+    pub expires_at: u64,
+    /// Owner's Ed25519 signature over `permit_message`
+    pub signature: [u8; 64],
+}
+
+/// Bytes an owner signs to grant `params`' allowance on `token_id`
+pub fn permit_message(token_id: &UnitsObjectId, owner_id: &UnitsObjectId, params: &PermitParams) -> Vec<u8> {
+    let mut message = Vec::with_capacity(128);
+    message.extend_from_slice(b"units/token/permit/v1");
+    message.extend_from_slice(token_id.bytes());
+    message.extend_from_slice(owner_id.bytes());
+    message.extend_from_slice(params.spender_id.bytes());
+    message.extend_from_slice(&params.amount.to_le_bytes());
+    message.extend_from_slice(&params.nonce.to_le_bytes());
+    message.extend_from_slice(&params.expires_at.to_le_bytes());
+    message
+}
+
+/// Whether `signature` is a valid Ed25519 signature of `message` by `owner_id`
+///
+/// Accounts are identified by their Ed25519 public key, as the account
+/// module's signature authenticator requires, so the owner's ID is the key.
+pub fn verify_owner_signature(owner_id: &UnitsObjectId, message: &[u8], signature: &[u8; 64]) -> bool {
+    let Ok(key_bytes) = <[u8; 32]>::try_from(owner_id.bytes()) else {
+        return false;
+    };
+    let Some(key) = CompressedEdwardsY(key_bytes).decompress() else {
+        return false;
+    };
+    let mut r_bytes = [0u8; 32];
+    r_bytes.copy_from_slice(&signature[..32]);
+    let Some(r) = CompressedEdwardsY(r_bytes).decompress() else {
+        return false;
+    };
+    let mut s_bytes = [0u8; 32];
+    s_bytes.copy_from_slice(&signature[32..]);
+    let Some(s) = Option::<Scalar>::from(Scalar::from_canonical_bytes(s_bytes)) else {
+        return false;
+    };
+
+    let mut wide = [0u8; 64];
+    wide.copy_from_slice(&Sha512::new().chain_update(r_bytes).chain_update(key_bytes).chain_update(message).finalize());
+    let challenge = Scalar::from_bytes_mod_order_wide(&wide);
+    s * ED25519_BASEPOINT_POINT == r + challenge * key
+}
+
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct TokenizeParams {
     pub initial_supply: u64,
@@ -56,6 +272,8 @@ pub enum TokenFunction {
     BurnToken,
     FreezeToken,
     UnfreezeToken,
+    SetSpendLimit,
+    Permit,
 }
 
 impl TokenFunction {
@@ -67,6 +285,8 @@ impl TokenFunction {
             TokenFunction::BurnToken => "burn_token",
             TokenFunction::FreezeToken => "freeze_token",
             TokenFunction::UnfreezeToken => "unfreeze_token",
+            TokenFunction::SetSpendLimit => "set_spend_limit",
+            TokenFunction::Permit => "permit",
         }
     }
 }
@@ -189,6 +409,8 @@ mod tests {
         assert_eq!(TokenFunction::BurnToken.as_str(), "burn_token");
         assert_eq!(TokenFunction::FreezeToken.as_str(), "freeze_token");
         assert_eq!(TokenFunction::UnfreezeToken.as_str(), "unfreeze_token");
+        assert_eq!(TokenFunction::SetSpendLimit.as_str(), "set_spend_limit");
+        assert_eq!(TokenFunction::Permit.as_str(), "permit");
     }
 
     #[test]
@@ -201,4 +423,38 @@ mod tests {
         assert_eq!(err.code, TokenError::INSUFFICIENT_BALANCE);
         assert_eq!(err.message, "Insufficient balance");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_spend_limit_rolls_and_needs_approval() {
+        let token_id = UnitsObjectId::new([1; OBJECT_ID_SIZE]);
+        let owner_id = UnitsObjectId::new([2; OBJECT_ID_SIZE]);
+        let mut limit = SpendLimitData {
+            token_id,
+            owner_id,
+            limit: 100,
+            period_secs: 86_400,
+            window_start: 1_000,
+            spent: 0,
+            approver_id: UnitsObjectId::new([3; OBJECT_ID_SIZE]),
+            approvals_used: 0,
+        };
+
+        limit.record_spend(60, 1_000, None).unwrap();
+        limit.record_spend(40, 2_000, None).unwrap();
+        assert!(matches!(limit.record_spend(1, 3_000, None), Err(KernelError::Unauthorized)));
+
+        // An approval for enough, for this owner and in sequence, lifts the limit once
+        let approval = SpendApproval { token_id, owner_id, amount: 500, sequence: 0, expires_at: 5_000 };
+        assert!(limit.record_spend(500, 6_000, Some(&approval)).is_err());
+        limit.record_spend(500, 3_000, Some(&approval)).unwrap();
+        assert_eq!((limit.spent, limit.approvals_used), (100, 1));
+        assert!(limit.record_spend(500, 3_000, Some(&approval)).is_err());
+
+        // The next period starts from nothing
+        limit.record_spend(100, 1_000 + 2 * 86_400 + 5, None).unwrap();
+        assert_eq!(limit.window_start, 1_000 + 2 * 86_400);
+
+        let encoded = units_kernel_sdk::encode_versioned(&limit).unwrap();
+        assert_eq!(units_kernel_sdk::decode_versioned::<SpendLimitData>(&encoded).unwrap(), limit);
+    }
+}
@@ -15,10 +15,12 @@ units_kernel_sdk::use_default_allocator!();
 
 use token::{
     TokenData, BalanceData, TokenizeParams, TransferParams, MintParams, BurnParams,
+    SetSpendLimitParams, SpendApproval, SpendLimitData,
+    AllowanceData, PermitParams, permit_message, verify_owner_signature,
 };
 use units_kernel_sdk::{
     ExecutionContext, ObjectEffect, KernelModule, KernelError,
-    UnitsObject, ObjectType,
+    UnitsObject, UnitsObjectId, ObjectType, decode_versioned, encode_versioned,
 };
 
 /// Token kernel module implementation
@@ -34,6 +36,8 @@ impl KernelModule for TokenModule {
             "burn_token" => handle_burn_token(ctx),
             "freeze_token" => handle_freeze_token(ctx),
             "unfreeze_token" => handle_unfreeze_token(ctx),
+            "set_spend_limit" => handle_set_spend_limit(ctx),
+            "permit" => handle_permit(ctx),
             _ => Err(KernelError::InvalidFunction),
         }
     }
@@ -65,7 +69,7 @@ fn handle_create_token(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, Kern
         id: ctx.instruction.target_objects[0],
         controller_id: ctx.instruction.controller_id,
         object_type: ObjectType::Data,
-        data: borsh::to_vec(&token_data).map_err(|_| KernelError::InvalidData)?,
+        data: encode_versioned(&token_data)?,
     };
     
     let balance_object = UnitsObject {
@@ -98,8 +102,7 @@ fn handle_transfer_token(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, Ke
         .ok_or(KernelError::ObjectNotFound)?;
     
     // Parse data
-    let token_data: TokenData = borsh::from_slice(&token.data)
-        .map_err(|_| KernelError::InvalidData)?;
+    let token_data: TokenData = decode_versioned(&token.data)?;
     let mut from_data: BalanceData = borsh::from_slice(&from_balance.data)
         .map_err(|_| KernelError::InvalidData)?;
     let mut to_data: BalanceData = borsh::from_slice(&to_balance.data)
@@ -134,10 +137,183 @@ fn handle_transfer_token(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, Ke
         data: borsh::to_vec(&to_data).map_err(|_| KernelError::InvalidData)?,
     };
     
-    Ok(vec![
+    let mut effects = vec![
         ObjectEffect::modification(from_balance.clone(), updated_from),
         ObjectEffect::modification(to_balance.clone(), updated_to),
-    ])
+    ];
+    
+    // Every spend limit on the sender must allow the transfer
+    for (limit_object, mut limit) in spend_limits(ctx, &from_data) {
+        let approval = spend_approval(ctx, &limit, params.amount);
+        limit.record_spend(params.amount, ctx.timestamp, approval.as_ref())?;
+        let updated_limit = UnitsObject {
+            id: limit_object.id,
+            controller_id: limit_object.controller_id,
+            object_type: limit_object.object_type.clone(),
+            data: encode_versioned(&limit)?,
+        };
+        effects.push(ObjectEffect::modification(limit_object.clone(), updated_limit));
+    }
+    
+    Ok(effects)
+}
+
+/// Spend limits of this controller on the owner and token of `balance`
+fn spend_limits<'a>(ctx: &'a ExecutionContext, balance: &BalanceData) -> Vec<(&'a UnitsObject, SpendLimitData)> {
+    ctx.objects
+        .values()
+        .filter(|object| object.controller_id == ctx.instruction.controller_id)
+        .filter_map(|object| Some((object, decode_versioned::<SpendLimitData>(&object.data).ok()?)))
+        .filter(|(_, limit)| limit.owner_id == balance.owner_id && limit.token_id == balance.token_id)
+        .collect()
+}
+
+/// An approval from the limit's approver covering `amount`, if one was supplied
+fn spend_approval(ctx: &ExecutionContext, limit: &SpendLimitData, amount: u64) -> Option<SpendApproval> {
+    ctx.objects
+        .values()
+        .filter(|object| object.controller_id == limit.approver_id)
+        .filter_map(|object| borsh::from_slice::<SpendApproval>(&object.data).ok())
+        .find(|approval| approval.covers(limit, amount, ctx.timestamp))
+}
+
+/// Use up the next approval for `limit`, failing if none was supplied
+fn redeem_approval(ctx: &ExecutionContext, mut limit: SpendLimitData) -> Result<SpendLimitData, KernelError> {
+    spend_approval(ctx, &limit, 0).ok_or(KernelError::Unauthorized)?;
+    limit.approvals_used += 1;
+    Ok(limit)
+}
+
+fn handle_set_spend_limit(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, KernelError> {
+    let params: SetSpendLimitParams = borsh::from_slice(&ctx.instruction.params)
+        .map_err(|_| KernelError::InvalidParams)?;
+    
+    if ctx.instruction.target_objects.len() < 3 {
+        return Err(KernelError::InvalidParams);
+    }
+    
+    let token_id = ctx.instruction.target_objects[0];
+    let balance = ctx.objects.get(&ctx.instruction.target_objects[1])
+        .ok_or(KernelError::ObjectNotFound)?;
+    let balance_data: BalanceData = borsh::from_slice(&balance.data)
+        .map_err(|_| KernelError::InvalidData)?;
+    if balance_data.token_id != token_id {
+        return Err(KernelError::InvalidParams);
+    }
+    
+    let limit_id: UnitsObjectId = ctx.instruction.target_objects[2];
+    let existing = ctx.objects.get(&limit_id);
+    let mut limit = match existing {
+        Some(object) => {
+            if object.controller_id != ctx.instruction.controller_id {
+                return Err(KernelError::Unauthorized);
+            }
+            let limit: SpendLimitData = decode_versioned(&object.data)?;
+            // Tightening is always allowed; loosening uses up an approval
+            if !limit.is_loosened_by(&params) {
+                limit
+            } else {
+                redeem_approval(ctx, limit)?
+            }
+        }
+        // Creation needs the owner's approval too, or anyone could freeze a balance
+        None => redeem_approval(ctx, SpendLimitData {
+            token_id,
+            owner_id: balance_data.owner_id,
+            limit: 0,
+            period_secs: 0,
+            window_start: ctx.timestamp,
+            spent: 0,
+            approver_id: params.approver_id,
+            approvals_used: 0,
+        })?,
+    };
+    limit.limit = params.limit;
+    limit.period_secs = params.period_secs;
+    limit.approver_id = params.approver_id;
+    
+    let limit_object = UnitsObject {
+        id: limit_id,
+        controller_id: ctx.instruction.controller_id,
+        object_type: ObjectType::Data,
+        data: encode_versioned(&limit)?,
+    };
+    
+    Ok(vec![match existing {
+        Some(object) => ObjectEffect::modification(object.clone(), limit_object),
+        None => ObjectEffect::creation(limit_object),
+    }])
+}
+
+fn handle_permit(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, KernelError> {
+    let params: PermitParams = borsh::from_slice(&ctx.instruction.params)
+        .map_err(|_| KernelError::InvalidParams)?;
+    
+    if ctx.instruction.target_objects.len() < 3 {
+        return Err(KernelError::InvalidParams);
+    }
+    
+    let token_id = ctx.instruction.target_objects[0];
+    let balance = ctx.objects.get(&ctx.instruction.target_objects[1])
+        .ok_or(KernelError::ObjectNotFound)?;
+    let balance_data: BalanceData = borsh::from_slice(&balance.data)
+        .map_err(|_| KernelError::InvalidData)?;
+    if balance.controller_id != ctx.instruction.controller_id || balance_data.token_id != token_id {
+        return Err(KernelError::InvalidParams);
+    }
+    let owner_id = balance_data.owner_id;
+    
+    // The allowance sits at an ID fixed by the grant, so its nonce is the only one
+    let allowance_id: UnitsObjectId = ctx.instruction.target_objects[2];
+    if allowance_id != token::allowance_id(&token_id, &owner_id, &params.spender_id) {
+        return Err(KernelError::InvalidParams);
+    }
+    let existing = ctx.objects.get(&allowance_id);
+    let allowance = match existing {
+        Some(object) => {
+            let allowance: AllowanceData = decode_versioned(&object.data)?;
+            if object.controller_id != ctx.instruction.controller_id
+                || allowance.token_id != token_id
+                || allowance.owner_id != owner_id
+                || allowance.spender_id != params.spender_id
+            {
+                return Err(KernelError::Unauthorized);
+            }
+            allowance
+        }
+        None => AllowanceData {
+            token_id,
+            owner_id,
+            spender_id: params.spender_id,
+            amount: 0,
+            nonce: 0,
+        },
+    };
+    
+    // The signature must be fresh, in sequence and the owner's own
+    if ctx.timestamp > params.expires_at || params.nonce != allowance.nonce {
+        return Err(KernelError::Unauthorized);
+    }
+    if !verify_owner_signature(&owner_id, &permit_message(&token_id, &owner_id, &params), &params.signature) {
+        return Err(KernelError::Unauthorized);
+    }
+    
+    let updated = AllowanceData {
+        amount: params.amount,
+        nonce: allowance.nonce + 1,
+        ..allowance
+    };
+    let allowance_object = UnitsObject {
+        id: allowance_id,
+        controller_id: ctx.instruction.controller_id,
+        object_type: ObjectType::Data,
+        data: encode_versioned(&updated)?,
+    };
+    
+    Ok(vec![match existing {
+        Some(object) => ObjectEffect::modification(object.clone(), allowance_object),
+        None => ObjectEffect::creation(allowance_object),
+    }])
 }
 
 fn handle_mint_token(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, KernelError> {
@@ -153,8 +329,7 @@ fn handle_mint_token(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, Kernel
     let balance = ctx.objects.get(&ctx.instruction.target_objects[1])
         .ok_or(KernelError::ObjectNotFound)?;
     
-    let mut token_data: TokenData = borsh::from_slice(&token.data)
-        .map_err(|_| KernelError::InvalidData)?;
+    let mut token_data: TokenData = decode_versioned(&token.data)?;
     let mut balance_data: BalanceData = borsh::from_slice(&balance.data)
         .map_err(|_| KernelError::InvalidData)?;
     
@@ -168,7 +343,7 @@ fn handle_mint_token(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, Kernel
         id: token.id,
         controller_id: token.controller_id,
         object_type: token.object_type.clone(),
-        data: borsh::to_vec(&token_data).map_err(|_| KernelError::InvalidData)?,
+        data: encode_versioned(&token_data)?,
     };
     
     let updated_balance = UnitsObject {
@@ -197,8 +372,7 @@ fn handle_burn_token(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, Kernel
     let balance = ctx.objects.get(&ctx.instruction.target_objects[1])
         .ok_or(KernelError::ObjectNotFound)?;
     
-    let mut token_data: TokenData = borsh::from_slice(&token.data)
-        .map_err(|_| KernelError::InvalidData)?;
+    let mut token_data: TokenData = decode_versioned(&token.data)?;
     let mut balance_data: BalanceData = borsh::from_slice(&balance.data)
         .map_err(|_| KernelError::InvalidData)?;
     
@@ -217,7 +391,7 @@ fn handle_burn_token(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, Kernel
         id: token.id,
         controller_id: token.controller_id,
         object_type: token.object_type.clone(),
-        data: borsh::to_vec(&token_data).map_err(|_| KernelError::InvalidData)?,
+        data: encode_versioned(&token_data)?,
     };
     
     let updated_balance = UnitsObject {
@@ -241,8 +415,7 @@ fn handle_freeze_token(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, Kern
     let token = ctx.objects.get(&ctx.instruction.target_objects[0])
         .ok_or(KernelError::ObjectNotFound)?;
     
-    let mut token_data: TokenData = borsh::from_slice(&token.data)
-        .map_err(|_| KernelError::InvalidData)?;
+    let mut token_data: TokenData = decode_versioned(&token.data)?;
     
     token_data.is_frozen = true;
     
@@ -250,7 +423,7 @@ fn handle_freeze_token(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, Kern
         id: token.id,
         controller_id: token.controller_id,
         object_type: token.object_type.clone(),
-        data: borsh::to_vec(&token_data).map_err(|_| KernelError::InvalidData)?,
+        data: encode_versioned(&token_data)?,
     };
     
     Ok(vec![ObjectEffect::modification(token.clone(), updated_token)])
@@ -264,8 +437,7 @@ fn handle_unfreeze_token(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, Ke
     let token = ctx.objects.get(&ctx.instruction.target_objects[0])
         .ok_or(KernelError::ObjectNotFound)?;
     
-    let mut token_data: TokenData = borsh::from_slice(&token.data)
-        .map_err(|_| KernelError::InvalidData)?;
+    let mut token_data: TokenData = decode_versioned(&token.data)?;
     
     token_data.is_frozen = false;
     
@@ -273,12 +445,124 @@ fn handle_unfreeze_token(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, Ke
         id: token.id,
         controller_id: token.controller_id,
         object_type: token.object_type.clone(),
-        data: borsh::to_vec(&token_data).map_err(|_| KernelError::InvalidData)?,
+        data: encode_versioned(&token_data)?,
     };
     
     Ok(vec![ObjectEffect::modification(token.clone(), updated_token)])
 }
 
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use curve25519_dalek::constants::ED25519_BASEPOINT_POINT;
+    use curve25519_dalek::scalar::Scalar;
+    use sha2::{Digest, Sha512};
+    use std::collections::HashMap;
+    use units_kernel_sdk::{ExecutionContext, Instruction};
+
+    /// Ed25519 key from a seed, as an account ID, with a signer for it
+    fn owner_key(seed: [u8; 32]) -> (UnitsObjectId, impl Fn(&[u8]) -> [u8; 64]) {
+        let expanded = Sha512::digest(seed);
+        let mut scalar_bytes = [0u8; 32];
+        scalar_bytes.copy_from_slice(&expanded[..32]);
+        scalar_bytes[0] &= 248;
+        scalar_bytes[31] &= 127;
+        scalar_bytes[31] |= 64;
+        let secret = Scalar::from_bytes_mod_order(scalar_bytes);
+        let public = (secret * ED25519_BASEPOINT_POINT).compress().to_bytes();
+        let prefix = expanded[32..].to_vec();
+
+        let wide = |hash: &[u8]| {
+            let mut bytes = [0u8; 64];
+            bytes.copy_from_slice(hash);
+            Scalar::from_bytes_mod_order_wide(&bytes)
+        };
+        let sign = move |message: &[u8]| {
+            let nonce = wide(&Sha512::new().chain_update(&prefix).chain_update(message).finalize());
+            let r = (nonce * ED25519_BASEPOINT_POINT).compress().to_bytes();
+            let challenge = wide(&Sha512::new().chain_update(r).chain_update(public).chain_update(message).finalize());
+            let mut signature = [0u8; 64];
+            signature[..32].copy_from_slice(&r);
+            signature[32..].copy_from_slice((nonce + challenge * secret).as_bytes());
+            signature
+        };
+        (UnitsObjectId::new(public), sign)
+    }
+
+    fn permit_context(objects: &[UnitsObject], params: &PermitParams, allowance_id: UnitsObjectId) -> ExecutionContext {
+        ExecutionContext {
+            instruction: Instruction {
+                controller_id: UnitsObjectId::new([1; 32]),
+                target_function: "permit".to_string(),
+                target_objects: vec![UnitsObjectId::new([10; 32]), UnitsObjectId::new([11; 32]), allowance_id],
+                params: borsh::to_vec(params).unwrap(),
+            },
+            objects: objects.iter().map(|object| (object.id, object.clone())).collect::<HashMap<_, _>>(),
+            slot: 3,
+            timestamp: 1_000,
+        }
+    }
+
+    #[test]
+    fn test_permit_sets_allowance_from_owner_signature() {
+        let (owner_id, sign) = owner_key([7; 32]);
+        let token_id = UnitsObjectId::new([10; 32]);
+        let balance = UnitsObject {
+            id: UnitsObjectId::new([11; 32]),
+            controller_id: UnitsObjectId::new([1; 32]),
+            object_type: ObjectType::Data,
+            data: borsh::to_vec(&BalanceData { token_id, owner_id, amount: 500 }).unwrap(),
+        };
+        let mut params = PermitParams {
+            spender_id: UnitsObjectId::new([20; 32]),
+            amount: 250,
+            nonce: 0,
+            expires_at: 2_000,
+            signature: [0; 64],
+        };
+        params.signature = sign(&permit_message(&token_id, &owner_id, &params));
+        let allowance_id = token::allowance_id(&token_id, &owner_id, &params.spender_id);
+
+        // Only the allowance derived from the grant can be created
+        let elsewhere = permit_context(&[balance.clone()], &params, UnitsObjectId::new([12; 32]));
+        assert!(matches!(TokenModule::execute(&elsewhere), Err(KernelError::InvalidParams)));
+
+        let effects = TokenModule::execute(&permit_context(&[balance.clone()], &params, allowance_id)).unwrap();
+        let allowance_object = effects[0].after_image.clone().unwrap();
+        let allowance: AllowanceData = decode_versioned(&allowance_object.data).unwrap();
+        assert_eq!((allowance.owner_id, allowance.amount, allowance.nonce), (owner_id, 250, 1));
+
+        // The same permit cannot be replayed once applied
+        let applied = [balance.clone(), allowance_object.clone()];
+        assert!(matches!(TokenModule::execute(&permit_context(&applied, &params, allowance_id)), Err(KernelError::Unauthorized)));
+
+        // Nor replayed into a second allowance object, which would start at nonce 0
+        let second = permit_context(&applied, &params, UnitsObjectId::new([12; 32]));
+        assert!(matches!(TokenModule::execute(&second), Err(KernelError::InvalidParams)));
+
+        // The next nonce, signed by the owner, replaces the amount
+        params.nonce = 1;
+        params.amount = 0;
+        params.signature = sign(&permit_message(&token_id, &owner_id, &params));
+        let effects = TokenModule::execute(&permit_context(&applied, &params, allowance_id)).unwrap();
+        let revoked: AllowanceData = decode_versioned(&effects[0].after_image.as_ref().unwrap().data).unwrap();
+        assert_eq!((revoked.amount, revoked.nonce), (0, 2));
+
+        // Tampered or expired permits are refused
+        params.amount = 1_000;
+        assert!(TokenModule::execute(&permit_context(&applied, &params, allowance_id)).is_err());
+        params.amount = 0;
+        params.expires_at = 999;
+        params.signature = sign(&permit_message(&token_id, &owner_id, &params));
+        assert!(TokenModule::execute(&permit_context(&applied, &params, allowance_id)).is_err());
+        let (stranger, stranger_sign) = owner_key([8; 32]);
+        assert_ne!(stranger, owner_id);
+        params.expires_at = 2_000;
+        params.signature = stranger_sign(&permit_message(&token_id, &owner_id, &params));
+        assert!(TokenModule::execute(&permit_context(&applied, &params, allowance_id)).is_err());
+    }
+}
+
 /// Entry point for the kernel module  
 #[cfg(not(feature = "std"))]
 #[no_mangle]
@@ -296,7 +580,7 @@ pub extern "C" fn _start() -> ! {
     };
     
     // Write effects to standard output
-    match write_effects(&effects) {
+    match write_effects(&effects, &[]) {
         Ok(_) => units_kernel_sdk::exit(0),
         Err(_) => units_kernel_sdk::exit(KernelError::IOError as i32),
     }
@@ -0,0 +1,65 @@
+//! Golden wire-format vectors
+//!
+//! Pins the exact Borsh bytes of every parameter and state type the module
+//! exchanges with clients and the host. A failure here means a refactor
+//! changed the wire layout; only update a vector when the format change is
+//! intentional and versioned.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use token::{BalanceData, BurnParams, MintParams, TokenData, TokenizeParams, TransferParams};
+use units_kernel_sdk::{decode_versioned, encode_versioned, UnitsObjectId};
+
+fn assert_borsh<T: BorshSerialize + BorshDeserialize>(name: &str, value: &T, expected: &str) {
+    let bytes = borsh::to_vec(value).unwrap();
+    assert_eq!(hex::encode(&bytes), expected, "Borsh layout of {} changed", name);
+
+    let decoded = T::try_from_slice(&bytes).unwrap();
+    assert_eq!(borsh::to_vec(&decoded).unwrap(), bytes, "{} does not round-trip", name);
+}
+
+#[test]
+fn test_state_vectors() {
+    let token = TokenData {
+        total_supply: 1_000_000,
+        decimals: 9,
+        name: "Units".to_string(),
+        symbol: "UNT".to_string(),
+        is_frozen: false,
+    };
+    assert_borsh("TokenData", &token, "40420f00000000000905000000556e69747303000000554e5400");
+
+    // Stored token state carries the schema prefix; untagged state still reads
+    let stored = encode_versioned(&token).unwrap();
+    assert_eq!(hex::encode(&stored), "ff5e0140420f00000000000905000000556e69747303000000554e5400");
+    let legacy: TokenData = decode_versioned(&borsh::to_vec(&token).unwrap()).unwrap();
+    assert_eq!(borsh::to_vec(&legacy).unwrap(), stored[3..]);
+
+    let balance = BalanceData {
+        token_id: UnitsObjectId::new([1; 32]),
+        owner_id: UnitsObjectId::new([2; 32]),
+        amount: 500,
+    };
+    assert_borsh(
+        "BalanceData",
+        &balance,
+        concat!(
+            "0101010101010101010101010101010101010101010101010101010101010101",
+            "0202020202020202020202020202020202020202020202020202020202020202",
+            "f401000000000000",
+        ),
+    );
+}
+
+#[test]
+fn test_param_vectors() {
+    let tokenize = TokenizeParams {
+        initial_supply: 1_000_000,
+        decimals: 9,
+        name: "Units".to_string(),
+        symbol: "UNT".to_string(),
+    };
+    assert_borsh("TokenizeParams", &tokenize, "40420f00000000000905000000556e69747303000000554e54");
+    assert_borsh("TransferParams", &TransferParams { amount: 250 }, "fa00000000000000");
+    assert_borsh("MintParams", &MintParams { amount: 1_000 }, "e803000000000000");
+    assert_borsh("BurnParams", &BurnParams { amount: 10 }, "0a00000000000000");
+}
@@ -0,0 +1,270 @@
+//! Borrowed, allocation-free view of the execution context
+//!
+//! [`crate::read_context`] decodes the whole context into owned values: a
+//! `String`, a `Vec` per object's data and a map over every object. Small
+//! modules that only inspect their params and one or two objects can read
+//! the context into a caller-supplied buffer instead and walk the Borsh
+//! encoding in place with [`BorrowedContext`], allocating nothing.
+
+use borsh::BorshDeserialize;
+
+use crate::{KernelError, ObjectType, UnitsObject, UnitsObjectId, VMType, OBJECT_ID_SIZE};
+
+/// Zero-copy view of a Borsh-encoded [`crate::ExecutionContext`]
+#[derive(Debug, Clone, Copy)]
+pub struct BorrowedContext<'a> {
+    pub controller_id: UnitsObjectId,
+    pub target_function: &'a str,
+    /// Encoded params of the instruction
+    pub params: &'a [u8],
+    pub slot: u64,
+    pub timestamp: u64,
+    /// Concatenated IDs of the instruction's target objects
+    target_objects: &'a [u8],
+    /// Encoded entries of the object map
+    objects: &'a [u8],
+    object_count: usize,
+}
+
+/// Object borrowed from a [`BorrowedContext`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjectRef<'a> {
+    pub id: UnitsObjectId,
+    pub controller_id: UnitsObjectId,
+    pub object_type: ObjectKind,
+    pub data: &'a [u8],
+}
+
+/// Allocation-free mirror of [`ObjectType`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Data,
+    Executable(VMType),
+    Ephemeral { expires_at_slot: u64 },
+}
+
+impl<'a> BorrowedContext<'a> {
+    /// Borrow a context from its Borsh encoding
+    ///
+    /// The encoding is validated up front, so later lookups cannot fail on
+    /// malformed input.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, KernelError> {
+        let mut reader = Reader(bytes);
+
+        let controller_id = reader.id()?;
+        let target_function = core::str::from_utf8(reader.sized()?).map_err(|_| KernelError::InvalidData)?;
+        let target_count = reader.u32()? as usize;
+        let target_objects = reader.take(target_count.checked_mul(OBJECT_ID_SIZE).ok_or(KernelError::InvalidData)?)?;
+        let params = reader.sized()?;
+
+        let object_count = reader.u32()? as usize;
+        let objects_start = reader.0;
+        for _ in 0..object_count {
+            reader.id()?;
+            reader.object()?;
+        }
+        let objects = &objects_start[..objects_start.len() - reader.0.len()];
+
+        let slot = reader.u64()?;
+        let timestamp = reader.u64()?;
+        if !reader.0.is_empty() {
+            return Err(KernelError::InvalidData);
+        }
+
+        Ok(Self {
+            controller_id,
+            target_function,
+            params,
+            slot,
+            timestamp,
+            target_objects,
+            objects,
+            object_count,
+        })
+    }
+
+    /// Decode the instruction params
+    pub fn decode_params<T: BorshDeserialize>(&self) -> Result<T, KernelError> {
+        T::try_from_slice(self.params).map_err(|_| KernelError::InvalidParams)
+    }
+
+    /// ID of the target object at `index`
+    pub fn target_object(&self, index: usize) -> Option<UnitsObjectId> {
+        self.target_objects.chunks_exact(OBJECT_ID_SIZE).nth(index).map(id_from_slice)
+    }
+
+    /// IDs of the instruction's target objects, in order
+    pub fn target_objects(&self) -> impl Iterator<Item = UnitsObjectId> + 'a {
+        self.target_objects.chunks_exact(OBJECT_ID_SIZE).map(id_from_slice)
+    }
+
+    /// Number of objects supplied with the context
+    pub fn object_count(&self) -> usize {
+        self.object_count
+    }
+
+    /// Objects supplied with the context, in ID order
+    pub fn objects(&self) -> impl Iterator<Item = ObjectRef<'a>> + 'a {
+        let mut reader = Reader(self.objects);
+        // `parse` validated every entry, so reads cannot fail here
+        (0..self.object_count).filter_map(move |_| {
+            reader.id().ok()?;
+            reader.object().ok()
+        })
+    }
+
+    /// Object supplied with the context under `id`
+    pub fn object(&self, id: &UnitsObjectId) -> Option<ObjectRef<'a>> {
+        self.objects().find(|object| object.id == *id)
+    }
+}
+
+impl ObjectRef<'_> {
+    /// Copy into an owned object, e.g. as the before image of an effect
+    pub fn to_object(&self) -> UnitsObject {
+        UnitsObject {
+            id: self.id,
+            controller_id: self.controller_id,
+            object_type: match self.object_type {
+                ObjectKind::Data => ObjectType::Data,
+                ObjectKind::Executable(vm_type) => ObjectType::Executable(vm_type),
+                ObjectKind::Ephemeral { expires_at_slot } => ObjectType::Ephemeral { expires_at_slot },
+            },
+            data: self.data.into(),
+        }
+    }
+}
+
+fn id_from_slice(bytes: &[u8]) -> UnitsObjectId {
+    let mut id = [0u8; OBJECT_ID_SIZE];
+    id.copy_from_slice(bytes);
+    UnitsObjectId::new(id)
+}
+
+/// Cursor over Borsh-encoded bytes
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], KernelError> {
+        if self.0.len() < len {
+            return Err(KernelError::InvalidData);
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, KernelError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, KernelError> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn u64(&mut self) -> Result<u64, KernelError> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn id(&mut self) -> Result<UnitsObjectId, KernelError> {
+        self.take(OBJECT_ID_SIZE).map(id_from_slice)
+    }
+
+    /// Length-prefixed bytes, as Borsh encodes `String` and `Vec<u8>`
+    fn sized(&mut self) -> Result<&'a [u8], KernelError> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn object(&mut self) -> Result<ObjectRef<'a>, KernelError> {
+        let id = self.id()?;
+        let controller_id = self.id()?;
+        let object_type = match self.u8()? {
+            0 => ObjectKind::Data,
+            1 => match self.u8()? {
+                0 => ObjectKind::Executable(VMType::RiscV),
+                1 => ObjectKind::Executable(VMType::Wasm),
+                _ => return Err(KernelError::InvalidData),
+            },
+            2 => ObjectKind::Ephemeral { expires_at_slot: self.u64()? },
+            _ => return Err(KernelError::InvalidData),
+        };
+        let data = self.sized()?;
+        Ok(ObjectRef { id, controller_id, object_type, data })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExecutionContext, Instruction};
+    use alloc::vec;
+
+    fn context() -> ExecutionContext {
+        let object = |byte: u8, object_type: ObjectType| UnitsObject {
+            id: UnitsObjectId::new([byte; 32]),
+            controller_id: UnitsObjectId::new([9; 32]),
+            object_type,
+            data: vec![byte; byte as usize],
+        };
+        let objects = [
+            object(2, ObjectType::Data),
+            object(1, ObjectType::Executable(VMType::RiscV)),
+            object(3, ObjectType::Ephemeral { expires_at_slot: 12 }),
+        ];
+
+        ExecutionContext {
+            instruction: Instruction {
+                controller_id: UnitsObjectId::new([9; 32]),
+                target_function: "transfer_token".into(),
+                target_objects: vec![UnitsObjectId::new([2; 32]), UnitsObjectId::new([1; 32])],
+                params: borsh::to_vec(&42u64).unwrap(),
+            },
+            objects: objects.into_iter().map(|object| (object.id, object)).collect(),
+            slot: 7,
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn test_borrowed_matches_owned() {
+        let owned = context();
+        let bytes = borsh::to_vec(&owned).unwrap();
+        let borrowed = BorrowedContext::parse(&bytes).unwrap();
+
+        assert_eq!(borrowed.controller_id, owned.instruction.controller_id);
+        assert_eq!(borrowed.target_function, "transfer_token");
+        assert_eq!(borrowed.decode_params::<u64>().unwrap(), 42);
+        assert_eq!((borrowed.slot, borrowed.timestamp), (7, 1_700_000_000));
+        assert!(borrowed.target_objects().eq(owned.instruction.target_objects.iter().copied()));
+        assert_eq!(borrowed.target_object(1), Some(UnitsObjectId::new([1; 32])));
+        assert_eq!(borrowed.target_object(2), None);
+
+        assert_eq!(borrowed.object_count(), 3);
+        for (id, owned_object) in &owned.objects {
+            let object = borrowed.object(id).unwrap();
+            assert_eq!(&object.to_object(), owned_object);
+        }
+        assert!(borrowed.object(&UnitsObjectId::new([4; 32])).is_none());
+    }
+
+    #[test]
+    fn test_rejects_malformed_encoding() {
+        let bytes = borsh::to_vec(&context()).unwrap();
+        assert!(BorrowedContext::parse(&bytes[..bytes.len() - 1]).is_err());
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(BorrowedContext::parse(&trailing).is_err());
+
+        // An object count larger than the objects present
+        let count_at = 32 + 4 + "transfer_token".len() + 4 + 2 * 32 + 4 + 8;
+        let mut inflated = bytes;
+        inflated[count_at] = 4;
+        assert!(BorrowedContext::parse(&inflated).is_err());
+    }
+}
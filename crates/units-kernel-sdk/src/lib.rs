@@ -3,7 +3,8 @@
 //! UNITS Kernel SDK - Framework for building kernel modules in Rust
 //! 
 //! This SDK provides the necessary types and utilities for building
-//! kernel modules that run in the UNITS RISC-V VM environment.
+//! kernel modules that run in the UNITS RISC-V VM environment, or in the
+//! WebAssembly VM when built for `wasm32-unknown-unknown`.
 //!
 //! # Memory Management
 //! 
@@ -23,109 +24,19 @@
 extern crate alloc;
 
 pub mod allocator;
+pub mod borrowed;
+pub mod schema;
 
 use alloc::vec::Vec;
-use alloc::string::String;
-use borsh::{BorshDeserialize, BorshSerialize};
 
-#[cfg(not(feature = "std"))]
-use alloc::collections::BTreeMap as HashMap;
-#[cfg(feature = "std")]
-use std::collections::HashMap;
-
-/// Size of object IDs in bytes
-pub const OBJECT_ID_SIZE: usize = 32;
-
-/// Units object ID type
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, BorshSerialize, BorshDeserialize)]
-pub struct UnitsObjectId([u8; OBJECT_ID_SIZE]);
+// Wire types are defined once in `units-types-ffi` and shared with the host
+pub use units_types_ffi::{
+    ExecutionContext, Instruction, KernelEvent, MemoryLayout, ObjectEffect, ObjectType, UnitsObject, UnitsObjectId, VMType,
+    MEMORY_LAYOUT_ADDR, OBJECT_ID_SIZE,
+};
 
-impl UnitsObjectId {
-    pub const fn new(bytes: [u8; OBJECT_ID_SIZE]) -> Self {
-        Self(bytes)
-    }
-    
-    pub fn bytes(&self) -> &[u8] {
-        &self.0
-    }
-}
-
-/// Object type enum
-#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
-pub enum ObjectType {
-    Data,
-    Executable(VMType),
-}
-
-/// VM type enum
-#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
-pub enum VMType {
-    RiscV,
-}
-
-/// Units object structure
-#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
-pub struct UnitsObject {
-    pub id: UnitsObjectId,
-    pub controller_id: UnitsObjectId,
-    pub object_type: ObjectType,
-    pub data: Vec<u8>,
-}
-
-/// Instruction structure
-#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
-pub struct Instruction {
-    pub controller_id: UnitsObjectId,
-    pub target_function: String,
-    pub target_objects: Vec<UnitsObjectId>,
-    pub params: Vec<u8>,
-}
-
-/// Execution context provided to kernel modules
-#[derive(Debug, BorshSerialize, BorshDeserialize)]
-pub struct ExecutionContext {
-    pub instruction: Instruction,
-    pub objects: HashMap<UnitsObjectId, UnitsObject>,
-    pub slot: u64,
-    pub timestamp: u64,
-}
-
-/// Effect of kernel execution on a single object
-#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
-pub struct ObjectEffect {
-    pub object_id: UnitsObjectId,
-    pub before_image: Option<UnitsObject>,
-    pub after_image: Option<UnitsObject>,
-}
-
-impl ObjectEffect {
-    /// Create new object effect
-    pub fn creation(object: UnitsObject) -> Self {
-        Self {
-            object_id: object.id,
-            before_image: None,
-            after_image: Some(object),
-        }
-    }
-    
-    /// Modify existing object effect
-    pub fn modification(before: UnitsObject, after: UnitsObject) -> Self {
-        Self {
-            object_id: after.id,
-            before_image: Some(before),
-            after_image: Some(after),
-        }
-    }
-    
-    /// Delete object effect
-    pub fn deletion(object: UnitsObject) -> Self {
-        Self {
-            object_id: object.id,
-            before_image: Some(object),
-            after_image: None,
-        }
-    }
-}
+pub use borrowed::{BorrowedContext, ObjectKind, ObjectRef};
+pub use schema::{decode_versioned, encode_versioned, schema_version, Versioned, LEGACY_SCHEMA_VERSION, SCHEMA_MAGIC};
 
 /// Kernel error types
 #[repr(i32)]
@@ -149,17 +60,62 @@ pub trait KernelModule {
     fn execute(ctx: &ExecutionContext) -> Result<Vec<ObjectEffect>, KernelError>;
 }
 
-// System calls for no_std environment
+// System calls for no_std environment, serviced by the executor as
+// described in `units_types_ffi::syscall`
 #[cfg(not(feature = "std"))]
 mod syscalls {
-    extern "C" {
-        fn sys_read(fd: i32, buf: *mut u8, count: usize) -> isize;
-        fn sys_write(fd: i32, buf: *const u8, count: usize) -> isize;
-        fn sys_exit(status: i32) -> !;
+    use units_types_ffi::syscall::{SYS_EXIT, SYS_GET_SLOT, SYS_GET_TIMESTAMP, SYS_LOG, SYS_READ, SYS_WRITE};
+
+    /// Trap into the host with `number` in `a7`, returning `a0` and `a1`
+    #[cfg(target_arch = "riscv32")]
+    unsafe fn ecall(number: u32, a0: usize, a1: usize, a2: usize) -> (isize, usize) {
+        let (ret, high): (isize, usize);
+        core::arch::asm!(
+            "ecall",
+            in("a7") number,
+            inlateout("a0") a0 => ret,
+            inlateout("a1") a1 => high,
+            in("a2") a2,
+        );
+        (ret, high)
     }
-    
+
+    /// Host functions a WebAssembly module imports in place of `ecall`
+    #[cfg(target_arch = "wasm32")]
+    mod host {
+        #[link(wasm_import_module = "units")]
+        extern "C" {
+            pub fn read(fd: i32, buf: *mut u8, len: usize) -> i32;
+            pub fn write(fd: i32, buf: *const u8, len: usize) -> i32;
+            pub fn log(buf: *const u8, len: usize) -> i32;
+            pub fn get_slot() -> i64;
+            pub fn get_timestamp() -> i64;
+            pub fn exit(status: i32) -> !;
+        }
+    }
+
+    /// Call the host import for `number`, returning the result as `ecall` would
+    #[cfg(target_arch = "wasm32")]
+    unsafe fn ecall(number: u32, a0: usize, a1: usize, a2: usize) -> (isize, usize) {
+        let wide = |value: i64| (value as u32 as isize, (value as u64 >> 32) as usize);
+        match number {
+            SYS_READ => (host::read(a0 as i32, a1 as *mut u8, a2) as isize, 0),
+            SYS_WRITE => (host::write(a0 as i32, a1 as *const u8, a2) as isize, 0),
+            SYS_LOG => (host::log(a0 as *const u8, a1) as isize, 0),
+            SYS_GET_SLOT => wide(host::get_slot()),
+            SYS_GET_TIMESTAMP => wide(host::get_timestamp()),
+            SYS_EXIT => host::exit(a0 as i32),
+            _ => unreachable!("unknown syscall {}", number),
+        }
+    }
+
+    #[cfg(not(any(target_arch = "riscv32", target_arch = "wasm32")))]
+    unsafe fn ecall(_number: u32, _a0: usize, _a1: usize, _a2: usize) -> (isize, usize) {
+        unimplemented!("syscalls are only available inside the UNITS VM")
+    }
+
     pub unsafe fn read(fd: i32, buf: &mut [u8]) -> Result<usize, ()> {
-        let result = sys_read(fd, buf.as_mut_ptr(), buf.len());
+        let (result, _) = ecall(SYS_READ, fd as usize, buf.as_mut_ptr() as usize, buf.len());
         if result < 0 {
             Err(())
         } else {
@@ -168,16 +124,34 @@ mod syscalls {
     }
     
     pub unsafe fn write(fd: i32, buf: &[u8]) -> Result<usize, ()> {
-        let result = sys_write(fd, buf.as_ptr(), buf.len());
+        let (result, _) = ecall(SYS_WRITE, fd as usize, buf.as_ptr() as usize, buf.len());
         if result < 0 {
             Err(())
         } else {
             Ok(result as usize)
         }
     }
+
+    pub unsafe fn get_u64(number: u32) -> u64 {
+        let (low, high) = ecall(number, 0, 0, 0);
+        (low as u32 as u64) | ((high as u64) << 32)
+    }
+
+    pub unsafe fn slot() -> u64 {
+        get_u64(SYS_GET_SLOT)
+    }
+
+    pub unsafe fn timestamp() -> u64 {
+        get_u64(SYS_GET_TIMESTAMP)
+    }
+
+    pub unsafe fn log(message: &[u8]) {
+        ecall(SYS_LOG, message.as_ptr() as usize, message.len(), 0);
+    }
     
     pub unsafe fn exit(status: i32) -> ! {
-        sys_exit(status)
+        ecall(SYS_EXIT, status as usize, 0, 0);
+        unreachable!("the host halts the program on exit")
     }
 }
 
@@ -215,10 +189,53 @@ pub fn read_context() -> Result<ExecutionContext, KernelError> {
     }
 }
 
-/// Write effects to stdout
-pub fn write_effects(effects: &[ObjectEffect]) -> Result<(), KernelError> {
-    let data = borsh::to_vec(effects).map_err(|_| KernelError::InvalidData)?;
-    let _size = (data.len() as u32).to_le_bytes();
+/// Read execution context from stdin into `buf` and borrow it in place
+///
+/// Unlike [`read_context`] this allocates nothing, which suits small
+/// modules that only inspect their params and an object or two. `buf` must
+/// be large enough for the whole encoded context.
+pub fn read_context_borrowed(buf: &mut [u8]) -> Result<BorrowedContext<'_>, KernelError> {
+    #[cfg(not(feature = "std"))]
+    {
+        let mut size_buf = [0u8; 4];
+        unsafe {
+            syscalls::read(0, &mut size_buf).map_err(|_| KernelError::IOError)?;
+        }
+        let size = u32::from_le_bytes(size_buf) as usize;
+
+        let data = buf.get_mut(..size).ok_or(KernelError::InvalidData)?;
+        let mut read = 0;
+        while read < size {
+            let n = unsafe {
+                syscalls::read(0, &mut data[read..]).map_err(|_| KernelError::IOError)?
+            };
+            if n == 0 {
+                return Err(KernelError::IOError);
+            }
+            read += n;
+        }
+
+        BorrowedContext::parse(&buf[..size])
+    }
+
+    #[cfg(feature = "std")]
+    {
+        // Mirrors `read_context`; tests parse encoded contexts directly
+        let _ = buf;
+        unimplemented!("read_context_borrowed not implemented for std")
+    }
+}
+
+/// Write effects and any events to stdout
+///
+/// Events follow the effects in the same frame, and are left out entirely
+/// when there are none so the frame reads the same to older hosts.
+pub fn write_effects(effects: &[ObjectEffect], events: &[KernelEvent]) -> Result<(), KernelError> {
+    let mut data = borsh::to_vec(effects).map_err(|_| KernelError::InvalidData)?;
+    if !events.is_empty() {
+        data.extend(borsh::to_vec(events).map_err(|_| KernelError::InvalidData)?);
+    }
+    let size = (data.len() as u32).to_le_bytes();
     
     #[cfg(not(feature = "std"))]
     {
@@ -241,10 +258,117 @@ pub fn write_effects(effects: &[ObjectEffect]) -> Result<(), KernelError> {
     {
         // In std environment, this would write to stdout
         // This is mainly for testing
+        let _ = size;
         unimplemented!("write_effects not implemented for std")
     }
 }
 
+/// Memory layout the executor set up for this run
+///
+/// Read from the descriptor at [`MEMORY_LAYOUT_ADDR`], so modules keep
+/// working when the host moves or resizes the buffers.
+pub fn memory_layout() -> Result<MemoryLayout, KernelError> {
+    #[cfg(not(feature = "std"))]
+    {
+        // SAFETY: the executor maps the descriptor read-only at this fixed
+        // address before the module starts, and never unmaps it
+        let bytes = unsafe {
+            core::slice::from_raw_parts(MEMORY_LAYOUT_ADDR as usize as *const u8, MemoryLayout::ENCODED_SIZE)
+        };
+        MemoryLayout::from_bytes(bytes).ok_or(KernelError::InvalidData)
+    }
+
+    #[cfg(feature = "std")]
+    {
+        // Mirrors `read_context`; there is no guest memory to inspect
+        unimplemented!("memory_layout not implemented for std")
+    }
+}
+
+/// Encoded execution context in the input buffer
+///
+/// Lets modules decode the context straight from guest memory, e.g. with
+/// [`BorrowedContext::parse`], without copying it through a syscall.
+pub fn input_buffer() -> Result<&'static [u8], KernelError> {
+    let layout = memory_layout()?;
+
+    #[cfg(not(feature = "std"))]
+    {
+        // SAFETY: the executor maps the length prefix and the context it
+        // describes read-only for the whole run
+        unsafe {
+            let len = core::ptr::read_unaligned(layout.input_len_addr() as usize as *const u32);
+            if len > layout.input_capacity {
+                return Err(KernelError::InvalidData);
+            }
+            Ok(core::slice::from_raw_parts(layout.input_addr as usize as *const u8, len as usize))
+        }
+    }
+
+    #[cfg(feature = "std")]
+    {
+        let _ = layout;
+        unimplemented!("input_buffer not implemented for std")
+    }
+}
+
+/// Write encoded effects to the output buffer and set its length prefix
+pub fn write_output(data: &[u8]) -> Result<(), KernelError> {
+    let layout = memory_layout()?;
+    if data.len() > layout.output_capacity as usize {
+        return Err(KernelError::IOError);
+    }
+
+    #[cfg(not(feature = "std"))]
+    {
+        // SAFETY: the executor maps the output buffer and its length prefix
+        // read/write, and `data` fits within its capacity
+        unsafe {
+            core::ptr::copy_nonoverlapping(data.as_ptr(), layout.output_addr as usize as *mut u8, data.len());
+            core::ptr::write_unaligned(layout.output_len_addr() as usize as *mut u32, data.len() as u32);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    {
+        unimplemented!("write_output not implemented for std")
+    }
+}
+
+/// Slot the transaction executes in
+pub fn slot() -> u64 {
+    #[cfg(not(feature = "std"))]
+    unsafe {
+        syscalls::slot()
+    }
+
+    #[cfg(feature = "std")]
+    unimplemented!("slot not implemented for std")
+}
+
+/// Timestamp the transaction executes at, as in the execution context
+pub fn timestamp() -> u64 {
+    #[cfg(not(feature = "std"))]
+    unsafe {
+        syscalls::timestamp()
+    }
+
+    #[cfg(feature = "std")]
+    unimplemented!("timestamp not implemented for std")
+}
+
+/// Log a message through the host
+pub fn log(message: &str) {
+    #[cfg(not(feature = "std"))]
+    unsafe {
+        syscalls::log(message.as_bytes())
+    }
+
+    #[cfg(feature = "std")]
+    std::eprintln!("{}", message)
+}
+
 /// Exit the program with a status code
 pub fn exit(status: i32) -> ! {
     #[cfg(not(feature = "std"))]
@@ -0,0 +1,135 @@
+//! Versioned object data layouts
+//!
+//! Module state is stored as Borsh, which has no room for evolving a struct
+//! in place. Versioned data is prefixed with [`SCHEMA_MAGIC`] and a version
+//! byte so an upgraded module can still read objects written by an older
+//! one and migrate them on read. Data written before a type was versioned
+//! carries no prefix and is handed to [`Versioned::upgrade`] as
+//! [`LEGACY_SCHEMA_VERSION`].
+
+use alloc::vec::Vec;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::KernelError;
+
+/// Prefix marking data written with an explicit schema version
+pub const SCHEMA_MAGIC: [u8; 2] = [0xff, 0x5e];
+
+/// Version reported for untagged data from before versioning
+pub const LEGACY_SCHEMA_VERSION: u8 = 0;
+
+/// A module data type whose layout can change between module versions
+pub trait Versioned: BorshSerialize + BorshDeserialize {
+    /// Version of the current layout, starting at 1
+    const SCHEMA_VERSION: u8;
+
+    /// Decode `bytes` written with an older layout `version`
+    fn upgrade(version: u8, bytes: &[u8]) -> Result<Self, KernelError>;
+}
+
+/// Encode a value tagged with its current schema version
+pub fn encode_versioned<T: Versioned>(value: &T) -> Result<Vec<u8>, KernelError> {
+    let mut bytes = Vec::from(SCHEMA_MAGIC);
+    bytes.push(T::SCHEMA_VERSION);
+    value.serialize(&mut bytes).map_err(|_| KernelError::InvalidData)?;
+    Ok(bytes)
+}
+
+/// Decode a value written by any version of its layout
+///
+/// Current data decodes directly, older tagged data is migrated through
+/// [`Versioned::upgrade`], and data from a newer layout is rejected.
+pub fn decode_versioned<T: Versioned>(bytes: &[u8]) -> Result<T, KernelError> {
+    if let [m0, m1, version, body @ ..] = bytes {
+        if [*m0, *m1] == SCHEMA_MAGIC {
+            let decoded = match (*version).cmp(&T::SCHEMA_VERSION) {
+                core::cmp::Ordering::Equal => T::try_from_slice(body).map_err(|_| KernelError::InvalidData),
+                core::cmp::Ordering::Less => T::upgrade(*version, body),
+                core::cmp::Ordering::Greater => Err(KernelError::InvalidData),
+            };
+            // Untagged legacy data can start with the magic by chance
+            if decoded.is_ok() {
+                return decoded;
+            }
+        }
+    }
+
+    T::upgrade(LEGACY_SCHEMA_VERSION, bytes)
+}
+
+/// Version of the layout `bytes` were written with
+pub fn schema_version(bytes: &[u8]) -> u8 {
+    match bytes {
+        [m0, m1, version, ..] if [*m0, *m1] == SCHEMA_MAGIC => *version,
+        _ => LEGACY_SCHEMA_VERSION,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::{String, ToString};
+
+    #[derive(Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+    struct RecordV1 {
+        amount: u64,
+    }
+
+    impl Versioned for RecordV1 {
+        const SCHEMA_VERSION: u8 = 1;
+
+        fn upgrade(version: u8, bytes: &[u8]) -> Result<Self, KernelError> {
+            match version {
+                LEGACY_SCHEMA_VERSION => Self::try_from_slice(bytes).map_err(|_| KernelError::InvalidData),
+                _ => Err(KernelError::InvalidData),
+            }
+        }
+    }
+
+    /// Second layout adds a label, defaulted when migrating
+    #[derive(Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+    struct RecordV2 {
+        amount: u64,
+        label: String,
+    }
+
+    impl Versioned for RecordV2 {
+        const SCHEMA_VERSION: u8 = 2;
+
+        fn upgrade(version: u8, bytes: &[u8]) -> Result<Self, KernelError> {
+            let v1 = match version {
+                1 => RecordV1::try_from_slice(bytes).map_err(|_| KernelError::InvalidData)?,
+                _ => RecordV1::upgrade(version, bytes)?,
+            };
+            Ok(Self { amount: v1.amount, label: "unlabelled".to_string() })
+        }
+    }
+
+    #[test]
+    fn test_v1_data_migrates_to_v2() {
+        let v1 = encode_versioned(&RecordV1 { amount: 7 }).unwrap();
+        assert_eq!(v1, [0xff, 0x5e, 1, 7, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(schema_version(&v1), 1);
+
+        let migrated: RecordV2 = decode_versioned(&v1).unwrap();
+        assert_eq!(migrated, RecordV2 { amount: 7, label: "unlabelled".to_string() });
+
+        // Untagged data from before versioning migrates through every step
+        let legacy = borsh::to_vec(&RecordV1 { amount: 9 }).unwrap();
+        assert_eq!(schema_version(&legacy), LEGACY_SCHEMA_VERSION);
+        assert_eq!(decode_versioned::<RecordV2>(&legacy).unwrap().amount, 9);
+
+        // Current data round-trips; newer data is rejected by older readers
+        let v2 = encode_versioned(&migrated).unwrap();
+        assert_eq!(decode_versioned::<RecordV2>(&v2).unwrap(), migrated);
+        assert!(decode_versioned::<RecordV1>(&v2).is_err());
+    }
+
+    #[test]
+    fn test_legacy_data_starting_with_magic() {
+        // An untagged amount whose low bytes happen to match the prefix
+        let amount = u64::from_le_bytes([0xff, 0x5e, 1, 0, 0, 0, 0, 0]);
+        let legacy = borsh::to_vec(&RecordV1 { amount }).unwrap();
+        assert_eq!(decode_versioned::<RecordV1>(&legacy).unwrap().amount, amount);
+    }
+}
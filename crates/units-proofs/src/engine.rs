@@ -4,18 +4,90 @@
 //! 1. Cryptographically prove object state at any slot
 //! 2. Cryptographically prove transaction inclusion in a slot
 
-use units_core_types::{Proof, SlotNumber, StateProof, UnitsObjectProof, VerificationResult, MerkleNode, ProofStorageError, UnitsObjectId};
-use blake3::Hasher;
+use units_core_types::{HashAlgorithm, Proof, SlotNumber, StateProof, UnitsObjectProof, VerificationResult, MerkleNode, ProofStorageError, StorageError, UnitsObjectId};
+use crate::hasher::{Blake3Hasher, ProofHasher};
+use crate::sparse_merkle::{MemoryNodes, MerkleNodeStore, SparseMerkleTree};
+use crate::signing::{state_proof_message, ProofSigner, StateProofSignature};
+use crate::types::InclusionProof;
+use std::marker::PhantomData;
 use serde::{Deserialize, Serialize};
 
-/// Proof engine using Blake3 hashing
+/// How the slots of consecutive proofs in an object's chain must relate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SlotOrdering {
+    /// Slots are not checked
+    Off,
+    /// Each proof's slot is at least its predecessor's
+    #[default]
+    NonDecreasing,
+    /// Each proof's slot is greater than its predecessor's
+    Strict,
+}
+
+impl SlotOrdering {
+    /// Whether a proof at `slot` may follow one at `prev_slot`
+    pub fn allows(&self, prev_slot: SlotNumber, slot: SlotNumber) -> bool {
+        match self {
+            Self::Off => true,
+            Self::NonDecreasing => slot >= prev_slot,
+            Self::Strict => slot > prev_slot,
+        }
+    }
+
+    /// Earliest slot a proof following one at `prev_slot` may take
+    fn floor(&self, prev_slot: SlotNumber) -> SlotNumber {
+        match self {
+            Self::Off => 0,
+            Self::NonDecreasing => prev_slot,
+            Self::Strict => prev_slot.saturating_add(1),
+        }
+    }
+}
+
+/// Proof in an object's chain whose slot breaks the engine's `SlotOrdering`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlotRegression {
+    pub object_id: UnitsObjectId,
+    /// Position of the offending proof in the chain
+    pub index: usize,
+    pub prev_slot: SlotNumber,
+    pub slot: SlotNumber,
+}
+
+/// Proof engine hashing with `H`, Blake3 unless another hasher is chosen
 #[derive(Debug, Clone, Default)]
-pub struct ProofEngine;
+pub struct ProofEngine<H = Blake3Hasher> {
+    slot_ordering: SlotOrdering,
+    hasher: PhantomData<H>,
+}
 
 impl ProofEngine {
     /// Create a new proof engine
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+}
+
+impl<H: ProofHasher> ProofEngine<H> {
+    /// Create a proof engine hashing with `H`
+    pub fn with_hasher() -> Self {
+        Self::default()
+    }
+
+    /// Algorithm this engine hashes with, recorded in its state proofs
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        H::ALGORITHM
+    }
+
+    /// Enforce `slot_ordering` on generated and verified proof chains
+    pub fn with_slot_ordering(mut self, slot_ordering: SlotOrdering) -> Self {
+        self.slot_ordering = slot_ordering;
+        self
+    }
+
+    pub fn slot_ordering(&self) -> SlotOrdering {
+        self.slot_ordering
     }
 
     /// Generate a cryptographic proof for a UNITS object
@@ -25,8 +97,11 @@ impl ProofEngine {
         prev_proof: Option<&UnitsObjectProof>,
         transaction_hash: Option<[u8; 32]>,
     ) -> Result<UnitsObjectProof, ProofStorageError> {
-        // Get current slot
-        let current_slot = crate::current_slot();
+        // Get current slot, held at the previous proof's if the clock went back
+        let current_slot = match prev_proof {
+            Some(prev) => crate::current_slot().max(self.slot_ordering.floor(prev.slot)),
+            None => crate::current_slot(),
+        };
         
         // Compute object hash
         let object_hash = self.hash_object(object)?;
@@ -91,11 +166,64 @@ impl ProofEngine {
             .map(|(id, _)| *id)
             .collect();
         
-        // Create state proof data
+        let object_root = self.compute_object_root(object_proofs)?;
+        self.build_state_proof(ObjectTree::Merkle, object_root, object_ids, transaction_hashes, prev_state_proof, slot)
+    }
+
+    /// Generate a state proof whose object root is a sparse Merkle tree's root
+    ///
+    /// Only `changed_proofs` are applied to `tree`, `None` removing a
+    /// deleted object, so the hashing grows with the objects written since
+    /// the tree's last root rather than with every object. The state proof
+    /// lists `object_ids`, which should be every object the tree then holds;
+    /// paths to its root come from `SparseMerkleTree::path` on the tree at
+    /// that root.
+    pub fn generate_incremental_state_proof<S: MerkleNodeStore + ?Sized>(
+        &self,
+        tree: &mut SparseMerkleTree<'_, S, H>,
+        changed_proofs: &[(UnitsObjectId, Option<UnitsObjectProof>)],
+        object_ids: Vec<UnitsObjectId>,
+        transaction_hashes: &[[u8; 32]],
+        prev_state_proof: Option<&StateProof>,
+        slot: SlotNumber,
+    ) -> Result<StateProof, StorageError> {
+        for (id, proof) in changed_proofs {
+            match proof {
+                Some(proof) => tree.insert(id, Self::object_leaf(id, proof))?,
+                None => tree.remove(id)?,
+            }
+        }
+
+        Ok(self.build_state_proof(ObjectTree::Sparse, tree.root(), object_ids, transaction_hashes, prev_state_proof, slot)?)
+    }
+
+    /// Root of a sparse Merkle tree holding exactly `object_proofs`
+    pub fn sparse_object_root(&self, object_proofs: &[(UnitsObjectId, UnitsObjectProof)]) -> Result<[u8; 32], ProofStorageError> {
+        let nodes = MemoryNodes::default();
+        let mut tree = SparseMerkleTree::<_, H>::with_hasher(&nodes);
+        for (id, proof) in object_proofs {
+            tree.insert(id, Self::object_leaf(id, proof))
+                .map_err(|e| ProofStorageError::ProofMissingData(e.to_string()))?;
+        }
+        Ok(tree.root())
+    }
+
+    fn build_state_proof(
+        &self,
+        object_tree: ObjectTree,
+        object_root: [u8; 32],
+        object_ids: Vec<UnitsObjectId>,
+        transaction_hashes: &[[u8; 32]],
+        prev_state_proof: Option<&StateProof>,
+        slot: SlotNumber,
+    ) -> Result<StateProof, ProofStorageError> {
         let proof_data = StateProofData {
-            object_root: self.compute_object_root(object_proofs)?,
+            object_root,
             transaction_root: self.compute_transaction_root(transaction_hashes),
             slot,
+            transaction_count: transaction_hashes.len() as u64,
+            object_tree,
+            hash_algorithm: H::ALGORITHM,
         };
         
         let serialized = bincode::serialize(&proof_data)
@@ -116,14 +244,55 @@ impl ProofEngine {
         object_proofs: &[(UnitsObjectId, UnitsObjectProof)],
     ) -> Result<bool, ProofStorageError> {
         // Deserialize proof data
-        let proof_data: StateProofData = bincode::deserialize(&state_proof.proof_data)
-            .map_err(|e| ProofStorageError::Serialization(e.to_string()))?;
-        
-        // Compute expected object root
-        let expected_root = self.compute_object_root(object_proofs)?;
+        let proof_data = self.state_proof_data(state_proof)?;
         
-        // Verify the root matches
-        Ok(expected_root == proof_data.object_root && state_proof.slot == proof_data.slot)
+        if state_proof.slot != proof_data.slot || proof_data.hash_algorithm != H::ALGORITHM {
+            return Ok(false);
+        }
+
+        let object_root = match proof_data.object_tree {
+            ObjectTree::Merkle => self.compute_object_root(object_proofs)?,
+            ObjectTree::Sparse => self.sparse_object_root(object_proofs)?,
+        };
+        Ok(object_root == proof_data.object_root)
+    }
+
+    /// Sign a state proof's slot root with `signer`
+    pub fn sign_state_proof(&self, state_proof: &StateProof, signer: &dyn ProofSigner) -> StateProofSignature {
+        StateProofSignature {
+            signer: signer.public_key(),
+            signature: signer.sign(&state_proof_message(state_proof)).to_vec(),
+        }
+    }
+
+    /// Check that `signature` is a trusted signer's signature over `state_proof`
+    ///
+    /// `trusted_signers` are the public keys whose signatures are accepted,
+    /// such as a validator set's.
+    pub fn verify_state_proof_signature(
+        &self,
+        state_proof: &StateProof,
+        signature: &StateProofSignature,
+        trusted_signers: &[[u8; 32]],
+    ) -> VerificationResult {
+        if !trusted_signers.contains(&signature.signer) {
+            return VerificationResult::InvalidSignature(format!(
+                "Signer {} is not trusted",
+                hex::encode(signature.signer)
+            ));
+        }
+        let Ok(bytes) = <[u8; 64]>::try_from(signature.signature.as_slice()) else {
+            return VerificationResult::InvalidSignature(format!(
+                "Signature is {} bytes, expected 64",
+                signature.signature.len()
+            ));
+        };
+
+        if units_core_types::ed25519::verify(&signature.signer, &state_proof_message(state_proof), &bytes) {
+            VerificationResult::Valid
+        } else {
+            VerificationResult::InvalidSignature(format!("Bad signature over the state proof for slot {}", state_proof.slot))
+        }
     }
 
     /// Verify transaction inclusion in a state proof
@@ -135,8 +304,7 @@ impl ProofEngine {
         merkle_path: &[MerkleNode],
     ) -> Result<bool, ProofStorageError> {
         // Deserialize proof data
-        let proof_data: StateProofData = bincode::deserialize(&state_proof.proof_data)
-            .map_err(|e| ProofStorageError::Serialization(e.to_string()))?;
+        let proof_data = self.state_proof_data(state_proof)?;
         
         // Verify the merkle path
         let computed_root = self.verify_merkle_path(transaction_hash, merkle_path)?;
@@ -145,95 +313,264 @@ impl ProofEngine {
         Ok(computed_root == proof_data.transaction_root)
     }
 
+    /// Decode the roots a state proof commits to
+    ///
+    /// State proofs committed before the object tree and hash algorithm
+    /// were recorded lack their trailing tags, so they fail the current
+    /// layout and decode through the legacy one, as plain Merkle roots
+    /// hashed with Blake3.
+    pub fn state_proof_data(&self, state_proof: &StateProof) -> Result<StateProofData, ProofStorageError> {
+        bincode::deserialize(&state_proof.proof_data)
+            .or_else(|_| {
+                bincode::deserialize::<LegacyStateProofData>(&state_proof.proof_data).map(|legacy| StateProofData {
+                    object_root: legacy.object_root,
+                    transaction_root: legacy.transaction_root,
+                    slot: legacy.slot,
+                    transaction_count: legacy.transaction_count,
+                    object_tree: ObjectTree::Merkle,
+                    hash_algorithm: HashAlgorithm::Blake3,
+                })
+            })
+            .map_err(|e| ProofStorageError::Serialization(e.to_string()))
+    }
+
+    /// Merkle path from an object's leaf to the object root built from `object_proofs`
+    ///
+    /// Returns `None` if the object has no proof in the set.
+    pub fn object_path(
+        &self,
+        object_proofs: &[(UnitsObjectId, UnitsObjectProof)],
+        object_id: &UnitsObjectId,
+    ) -> Option<Vec<MerkleNode>> {
+        let sorted_proofs = Self::sorted_proofs(object_proofs);
+        let index = sorted_proofs.iter().position(|(id, _)| id == object_id)?;
+        let leaves = sorted_proofs.iter().map(|(id, proof)| Self::object_leaf(id, proof)).collect();
+        Some(Self::merkle_path(leaves, index))
+    }
+
+    /// Inclusion proof for one object in the state proof of `slot`
+    ///
+    /// `object_proofs` are the proofs that state proof commits to. Returns
+    /// `None` if the object is not among them.
+    pub fn generate_inclusion_proof(
+        &self,
+        object_proofs: &[(UnitsObjectId, UnitsObjectProof)],
+        object_id: &UnitsObjectId,
+        slot: SlotNumber,
+    ) -> Option<InclusionProof> {
+        let (_, object_proof) = object_proofs.iter().find(|(id, _)| id == object_id)?;
+        Some(InclusionProof {
+            object_id: *object_id,
+            slot,
+            object_proof: object_proof.clone(),
+            path: self.object_path(object_proofs, object_id)?,
+        })
+    }
+
+    /// Inclusion proof for one object against a sparse Merkle tree's current root
+    ///
+    /// For state proofs from `generate_incremental_state_proof`, with `tree`
+    /// opened at the state proof's object root. Returns `None` if
+    /// `object_proof` is not the proof the tree holds for its object.
+    pub fn generate_sparse_inclusion_proof<S: MerkleNodeStore + ?Sized>(
+        &self,
+        tree: &SparseMerkleTree<'_, S, H>,
+        object_proof: &UnitsObjectProof,
+        slot: SlotNumber,
+    ) -> Result<Option<InclusionProof>, StorageError> {
+        let object_id = object_proof.object_id;
+        if tree.get(&object_id)? != Some(Self::object_leaf(&object_id, object_proof)) {
+            return Ok(None);
+        }
+        Ok(tree.path(&object_id)?.map(|path| InclusionProof {
+            object_id,
+            slot,
+            object_proof: object_proof.clone(),
+            path,
+        }))
+    }
+
+    /// Verify that `object_proof` is the proof of `object_id` committed to by `state_proof`
+    ///
+    /// Needs only the state proof and the path, not the other objects'
+    /// proofs. Checking the object itself against `object_proof` is left to
+    /// `verify_object_proof`.
+    pub fn verify_inclusion(
+        &self,
+        state_proof: &StateProof,
+        object_id: &UnitsObjectId,
+        object_proof: &UnitsObjectProof,
+        path: &[MerkleNode],
+    ) -> Result<bool, ProofStorageError> {
+        if object_proof.object_id != *object_id {
+            return Ok(false);
+        }
+        let data = self.state_proof_data(state_proof)?;
+        if data.hash_algorithm != H::ALGORITHM {
+            return Ok(false);
+        }
+        self.verify_proof_against_root(object_proof, path, &data.object_root)
+    }
+
+    /// Verify an [`InclusionProof`] against the state proof of its slot
+    pub fn verify_inclusion_proof(
+        &self,
+        state_proof: &StateProof,
+        inclusion: &InclusionProof,
+    ) -> Result<bool, ProofStorageError> {
+        if inclusion.slot != state_proof.slot {
+            return Ok(false);
+        }
+        self.verify_inclusion(state_proof, &inclusion.object_id, &inclusion.object_proof, &inclusion.path)
+    }
+
+    /// Verify that an object is committed to by an object root
+    ///
+    /// Checks the object against its proof, then hashes the proof's leaf up
+    /// `path` and compares the result with `root`. The root can come from a
+    /// state proof or from anywhere it was anchored independently.
+    pub fn verify_object_against_root<T: Proof>(
+        &self,
+        object: &T,
+        proof: &UnitsObjectProof,
+        path: &[MerkleNode],
+        root: &[u8; 32],
+    ) -> Result<bool, ProofStorageError> {
+        if !self.verify_object_proof(object, proof)? {
+            return Ok(false);
+        }
+
+        self.verify_proof_against_root(proof, path, root)
+    }
+
+    /// Verify that an object proof's leaf hashes up `path` to `root`
+    ///
+    /// Unlike `verify_object_against_root` this needs only the proof, for
+    /// callers checking that a set of objects is committed without fetching
+    /// the objects themselves.
+    pub fn verify_proof_against_root(
+        &self,
+        proof: &UnitsObjectProof,
+        path: &[MerkleNode],
+        root: &[u8; 32],
+    ) -> Result<bool, ProofStorageError> {
+        let leaf = Self::object_leaf(&proof.object_id, proof);
+        Ok(self.verify_merkle_path(&leaf, path)? == *root)
+    }
+
+    /// Merkle root over the latest proofs of a group of objects
+    ///
+    /// Built from the same leaves as a state proof's object root, so a
+    /// member proven in the state root also hashes into the group root.
+    pub fn group_root(&self, member_proofs: &[(UnitsObjectId, UnitsObjectProof)]) -> [u8; 32] {
+        let leaves = Self::sorted_proofs(member_proofs)
+            .iter()
+            .map(|(id, proof)| Self::object_leaf(id, proof))
+            .collect();
+        Self::merkle_root(leaves)
+    }
+
     // Helper methods
 
     fn hash_object<T: Proof>(&self, object: &T) -> Result<[u8; 32], ProofStorageError> {
         let serialized = bincode::serialize(object)
             .map_err(|e| ProofStorageError::Serialization(e.to_string()))?;
         
-        let mut hasher = Hasher::new();
-        hasher.update(&serialized);
-        Ok(*hasher.finalize().as_bytes())
+        Ok(H::hash(&[&serialized]))
     }
 
-    fn create_proof_data(
+    pub(crate) fn create_proof_data(
         &self,
         object_hash: &[u8; 32],
         prev_proof_hash: Option<[u8; 32]>,
         slot: SlotNumber,
         transaction_hash: Option<[u8; 32]>,
     ) -> Vec<u8> {
-        let mut hasher = Hasher::new();
-        hasher.update(object_hash);
-        hasher.update(&slot.to_le_bytes());
+        let slot = slot.to_le_bytes();
+        let mut parts: Vec<&[u8]> = vec![object_hash, &slot];
         
-        if let Some(prev_hash) = prev_proof_hash {
-            hasher.update(&prev_hash);
+        if let Some(prev_hash) = &prev_proof_hash {
+            parts.push(prev_hash);
         }
         
-        if let Some(tx_hash) = transaction_hash {
-            hasher.update(&tx_hash);
+        if let Some(tx_hash) = &transaction_hash {
+            parts.push(tx_hash);
         }
         
-        hasher.finalize().as_bytes().to_vec()
+        H::hash(&parts).to_vec()
     }
 
     fn compute_object_root(&self, object_proofs: &[(UnitsObjectId, UnitsObjectProof)]) -> Result<[u8; 32], ProofStorageError> {
-        let mut hasher = Hasher::new();
-        
-        // Sort by object ID for deterministic ordering
+        Ok(self.group_root(object_proofs))
+    }
+
+    fn compute_transaction_root(&self, transaction_hashes: &[[u8; 32]]) -> [u8; 32] {
+        Self::merkle_root(transaction_hashes.to_vec())
+    }
+
+    /// Object proofs sorted by object ID for deterministic ordering
+    fn sorted_proofs(object_proofs: &[(UnitsObjectId, UnitsObjectProof)]) -> Vec<(UnitsObjectId, UnitsObjectProof)> {
         let mut sorted_proofs = object_proofs.to_vec();
         sorted_proofs.sort_by_key(|(id, _)| *id);
-        
-        for (id, proof) in sorted_proofs {
-            hasher.update(id.bytes());
-            hasher.update(&proof.hash());
-        }
-        
-        Ok(*hasher.finalize().as_bytes())
+        sorted_proofs
     }
 
-    fn compute_transaction_root(&self, transaction_hashes: &[[u8; 32]]) -> [u8; 32] {
-        if transaction_hashes.is_empty() {
+    /// Leaf committing to an object's latest proof
+    fn object_leaf(id: &UnitsObjectId, proof: &UnitsObjectProof) -> [u8; 32] {
+        H::hash(&[id.bytes(), &proof.hash()])
+    }
+
+    /// Hash a pair of nodes, duplicating the last one on odd levels
+    fn next_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+        level
+            .chunks(2)
+            .map(|chunk| H::hash(&[&chunk[0], chunk.get(1).unwrap_or(&chunk[0])]))
+            .collect()
+    }
+
+    /// Root of a simple binary Merkle tree, all zeros when empty
+    fn merkle_root(leaves: Vec<[u8; 32]>) -> [u8; 32] {
+        if leaves.is_empty() {
             return [0u8; 32];
         }
-        
-        // Build simple merkle tree
-        let mut current_level: Vec<[u8; 32]> = transaction_hashes.to_vec();
-        
+
+        let mut current_level = leaves;
         while current_level.len() > 1 {
-            let mut next_level = Vec::new();
-            
-            for chunk in current_level.chunks(2) {
-                let mut hasher = Hasher::new();
-                hasher.update(&chunk[0]);
-                if chunk.len() > 1 {
-                    hasher.update(&chunk[1]);
-                } else {
-                    hasher.update(&chunk[0]); // Duplicate if odd number
-                }
-                next_level.push(*hasher.finalize().as_bytes());
-            }
-            
-            current_level = next_level;
+            current_level = Self::next_level(&current_level);
         }
-        
+
         current_level[0]
     }
 
+    /// Sibling path for the leaf at `index`, ordered from the leaf upwards
+    fn merkle_path(leaves: Vec<[u8; 32]>, mut index: usize) -> Vec<MerkleNode> {
+        let mut path = Vec::new();
+        let mut current_level = leaves;
+
+        while current_level.len() > 1 {
+            let (sibling, is_left) = if index % 2 == 0 {
+                (*current_level.get(index + 1).unwrap_or(&current_level[index]), false)
+            } else {
+                (current_level[index - 1], true)
+            };
+            path.push(MerkleNode { hash: sibling, is_left });
+
+            current_level = Self::next_level(&current_level);
+            index /= 2;
+        }
+
+        path
+    }
+
     fn verify_merkle_path(&self, leaf: &[u8; 32], path: &[MerkleNode]) -> Result<[u8; 32], ProofStorageError> {
         let mut current_hash = *leaf;
         
         for node in path {
-            let mut hasher = Hasher::new();
-            if node.is_left {
-                hasher.update(&node.hash);
-                hasher.update(&current_hash);
+            current_hash = if node.is_left {
+                H::hash(&[&node.hash, &current_hash])
             } else {
-                hasher.update(&current_hash);
-                hasher.update(&node.hash);
-            }
-            current_hash = *hasher.finalize().as_bytes();
+                H::hash(&[&current_hash, &node.hash])
+            };
         }
         
         Ok(current_hash)
@@ -297,16 +634,106 @@ impl ProofEngine {
             }
         }
 
+        if let Some(regression) = self.slot_regressions(proofs.iter().map(|(_, proof)| proof)).first() {
+            return VerificationResult::Invalid(format!(
+                "Proof slot regresses from {} to {}",
+                regression.prev_slot, regression.slot
+            ));
+        }
+
         VerificationResult::Valid
     }
+
+    /// Proofs in a chain, oldest first, whose slot breaks the slot ordering
+    pub fn slot_regressions<'a>(
+        &self,
+        chain: impl IntoIterator<Item = &'a UnitsObjectProof>,
+    ) -> Vec<SlotRegression> {
+        let mut regressions = Vec::new();
+        let mut prev_slot = None;
+        for (index, proof) in chain.into_iter().enumerate() {
+            if let Some(prev_slot) = prev_slot {
+                if !self.slot_ordering.allows(prev_slot, proof.slot) {
+                    regressions.push(SlotRegression {
+                        object_id: proof.object_id,
+                        index,
+                        prev_slot,
+                        slot: proof.slot,
+                    });
+                }
+            }
+            prev_slot = Some(proof.slot);
+        }
+        regressions
+    }
+
+    /// Rebuild a chain so its slots follow the slot ordering
+    ///
+    /// Each offending proof is moved up to the earliest slot allowed after
+    /// its predecessor. Proof data and links are recomputed from the first
+    /// change on, so the repaired chain verifies but no longer matches state
+    /// proofs committed to the original one. Returns `None` when the chain
+    /// needs no repair.
+    pub fn repair_slot_order(&self, chain: &[UnitsObjectProof]) -> Option<Vec<UnitsObjectProof>> {
+        let first = self.slot_regressions(chain).first()?.index;
+
+        let mut repaired: Vec<UnitsObjectProof> = chain[..first].to_vec();
+        for proof in &chain[first..] {
+            let prev = repaired.last();
+            let slot = prev.map_or(proof.slot, |prev| proof.slot.max(self.slot_ordering.floor(prev.slot)));
+            let proof_data = self.create_proof_data(
+                &proof.object_hash,
+                prev.map(|prev| prev.hash()),
+                slot,
+                proof.transaction_hash,
+            );
+            let rebuilt = UnitsObjectProof::new(
+                proof.object_id,
+                proof.object_hash,
+                slot,
+                proof_data,
+                prev,
+                proof.transaction_hash,
+            );
+            repaired.push(rebuilt);
+        }
+        Some(repaired)
+    }
+}
+
+/// Tree a state proof's object root is the root of
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ObjectTree {
+    /// Merkle tree over the object proofs sorted by ID, rebuilt each slot
+    #[default]
+    Merkle,
+    /// Sparse Merkle tree keyed by object ID, updated with each slot's writes
+    Sparse,
 }
 
-/// State proof data structure
+/// Roots committed to by a state proof's `proof_data`
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct StateProofData {
+pub struct StateProofData {
+    /// Root over the latest proof of every object in the slot
+    pub object_root: [u8; 32],
+    /// Merkle root over the slot's transaction hashes
+    pub transaction_root: [u8; 32],
+    pub slot: SlotNumber,
+    /// Number of transactions executed in the slot
+    pub transaction_count: u64,
+    /// Tree `object_root` is the root of
+    pub object_tree: ObjectTree,
+    /// Hash function both roots were computed with
+    pub hash_algorithm: HashAlgorithm,
+}
+
+/// `StateProofData` as encoded before the object tree and hash algorithm were recorded
+#[derive(Deserialize)]
+struct LegacyStateProofData {
     object_root: [u8; 32],
     transaction_root: [u8; 32],
     slot: SlotNumber,
+    transaction_count: u64,
 }
 
 #[cfg(test)]
@@ -371,4 +798,241 @@ mod tests {
         // Verify chain
         assert_eq!(proof2.prev_proof_hash, Some(proof1.hash()));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_effect_hashes_match_object_proofs() {
+        use units_core_types::{TransactionEffect, UnitsObject};
+
+        let engine = ProofEngine::new();
+        let id = UnitsObjectId::from_bytes([1u8; 32]);
+        let before = UnitsObject::new_data(id, id, vec![1, 2, 3]);
+        let after = UnitsObject::new_data(id, id, vec![4, 5, 6]);
+
+        let proof = engine.generate_object_proof(&after, None, Some([9u8; 32])).unwrap();
+        let effect = TransactionEffect::new_modification([9u8; 32], before, after);
+
+        assert_eq!(effect.after_hash, Some(proof.object_hash));
+        assert!(effect.matches_proof(&proof));
+    }
+
+    #[test]
+    fn test_object_paths_verify_against_state_root() {
+        use units_core_types::UnitsObject;
+
+        let engine = ProofEngine::new();
+        let objects: Vec<UnitsObject> = (1..=5u8)
+            .map(|seed| {
+                let id = UnitsObjectId::from_bytes([seed; 32]);
+                UnitsObject::new_data(id, id, vec![seed; 4])
+            })
+            .collect();
+        let object_proofs: Vec<_> = objects
+            .iter()
+            .map(|object| (object.id, engine.generate_object_proof(object, None, None).unwrap()))
+            .collect();
+
+        let state_proof = engine.generate_state_proof(&object_proofs, &[[7u8; 32]], None, 3).unwrap();
+        let data = engine.state_proof_data(&state_proof).unwrap();
+        assert_eq!(data.transaction_count, 1);
+        assert!(engine.verify_state_proof(&state_proof, &object_proofs).unwrap());
+
+        // Every object, including the odd one out, verifies against the root
+        for (object, (id, proof)) in objects.iter().zip(&object_proofs) {
+            let path = engine.object_path(&object_proofs, id).unwrap();
+            assert!(engine.verify_object_against_root(object, proof, &path, &data.object_root).unwrap());
+        }
+
+        // A tampered object or a different root does not
+        let (id, proof) = &object_proofs[0];
+        let path = engine.object_path(&object_proofs, id).unwrap();
+        let mut tampered = objects[0].clone();
+        tampered.data.push(0);
+        assert!(!engine.verify_object_against_root(&tampered, proof, &path, &data.object_root).unwrap());
+        assert!(!engine.verify_object_against_root(&objects[0], proof, &path, &[0u8; 32]).unwrap());
+        assert!(engine.object_path(&object_proofs, &UnitsObjectId::from_bytes([9u8; 32])).is_none());
+
+        // A group's members verify by proof alone, and its root covers exactly them
+        let group = &object_proofs[1..3];
+        for (id, proof) in group {
+            let path = engine.object_path(&object_proofs, id).unwrap();
+            assert!(engine.verify_proof_against_root(proof, &path, &data.object_root).unwrap());
+        }
+        assert_eq!(engine.group_root(&object_proofs), data.object_root);
+        assert_ne!(engine.group_root(group), data.object_root);
+        assert_ne!(engine.group_root(group), engine.group_root(&object_proofs[1..2]));
+    }
+
+    #[test]
+    fn test_incremental_state_proofs_match_full_rebuild() {
+        use units_core_types::UnitsObject;
+
+        let engine = ProofEngine::new();
+        let objects: Vec<UnitsObject> = (1..=4u8)
+            .map(|seed| {
+                let id = UnitsObjectId::from_bytes([seed; 32]);
+                UnitsObject::new_data(id, id, vec![seed; 4])
+            })
+            .collect();
+        let mut proofs: Vec<_> = objects
+            .iter()
+            .map(|object| (object.id, engine.generate_object_proof(object, None, None).unwrap()))
+            .collect();
+
+        let nodes = MemoryNodes::default();
+        let mut tree = SparseMerkleTree::new(&nodes);
+        let changed: Vec<_> = proofs.iter().map(|(id, proof)| (*id, Some(proof.clone()))).collect();
+        let ids = |proofs: &[(UnitsObjectId, UnitsObjectProof)]| proofs.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        let first = engine.generate_incremental_state_proof(&mut tree, &changed, ids(&proofs), &[], None, 1).unwrap();
+        assert!(engine.verify_state_proof(&first, &proofs).unwrap());
+        assert_eq!(engine.state_proof_data(&first).unwrap().object_tree, ObjectTree::Sparse);
+
+        // The next slot rewrites one object and deletes another
+        let mut updated = objects[0].clone();
+        updated.data.push(0);
+        proofs[0].1 = engine.generate_object_proof(&updated, Some(&proofs[0].1), None).unwrap();
+        let deleted = proofs.remove(3).0;
+        let changed = [(proofs[0].0, Some(proofs[0].1.clone())), (deleted, None)];
+        let second = engine.generate_incremental_state_proof(&mut tree, &changed, ids(&proofs), &[], Some(&first), 2).unwrap();
+
+        let data = engine.state_proof_data(&second).unwrap();
+        assert_eq!(second.object_ids, ids(&proofs));
+        assert_eq!(data.object_root, engine.sparse_object_root(&proofs).unwrap());
+        assert!(engine.verify_state_proof(&second, &proofs).unwrap());
+
+        // The tag picks the root: a plain Merkle proof over the same objects differs
+        let plain = engine.generate_state_proof(&proofs, &[], None, 2).unwrap();
+        assert_eq!(engine.state_proof_data(&plain).unwrap().object_tree, ObjectTree::Merkle);
+        assert_ne!(engine.state_proof_data(&plain).unwrap().object_root, data.object_root);
+        assert!(engine.verify_state_proof(&plain, &proofs).unwrap());
+        for (id, proof) in &proofs {
+            let path = tree.path(id).unwrap().unwrap();
+            assert!(engine.verify_proof_against_root(proof, &path, &data.object_root).unwrap());
+        }
+        assert!(tree.path(&deleted).unwrap().is_none());
+
+        // Inclusion proofs come from the tree and verify against the state proof
+        let inclusion = engine.generate_sparse_inclusion_proof(&tree, &proofs[0].1, 2).unwrap().unwrap();
+        assert!(engine.verify_inclusion_proof(&second, &inclusion).unwrap());
+        assert!(!engine.verify_inclusion_proof(&first, &inclusion).unwrap());
+        let stale = engine.generate_object_proof(&objects[0], None, None).unwrap();
+        assert!(engine.generate_sparse_inclusion_proof(&tree, &stale, 2).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_state_proofs_without_recorded_tags_decode_as_blake3_merkle() {
+        #[derive(Serialize)]
+        struct Legacy {
+            object_root: [u8; 32],
+            transaction_root: [u8; 32],
+            slot: SlotNumber,
+            transaction_count: u64,
+        }
+
+        let engine = ProofEngine::new();
+        let id = UnitsObjectId::from_bytes([4u8; 32]);
+        let object = units_core_types::UnitsObject::new_data(id, id, vec![1]);
+        let object_proofs = vec![(id, engine.generate_object_proof(&object, None, None).unwrap())];
+        let mut state_proof = engine.generate_state_proof(&object_proofs, &[[7; 32]], None, 3).unwrap();
+        let data = engine.state_proof_data(&state_proof).unwrap();
+        state_proof.proof_data = bincode::serialize(&Legacy {
+            object_root: data.object_root,
+            transaction_root: data.transaction_root,
+            slot: data.slot,
+            transaction_count: data.transaction_count,
+        })
+        .unwrap();
+
+        let legacy = engine.state_proof_data(&state_proof).unwrap();
+        assert_eq!((legacy.object_tree, legacy.object_root, legacy.transaction_count), (ObjectTree::Merkle, data.object_root, 1));
+        assert_eq!(legacy.hash_algorithm, HashAlgorithm::Blake3);
+        assert!(engine.verify_state_proof(&state_proof, &object_proofs).unwrap());
+    }
+
+    #[test]
+    fn test_proofs_are_bound_to_their_hash_algorithm() {
+        use crate::hasher::Keccak256Hasher;
+        use units_core_types::{HashAlgorithm, UnitsObject};
+
+        let keccak = ProofEngine::<Keccak256Hasher>::with_hasher();
+        let blake3 = ProofEngine::new();
+        let id = UnitsObjectId::from_bytes([4u8; 32]);
+        let object = UnitsObject::new_data(id, id, vec![1, 2, 3]);
+
+        let proof = keccak.generate_object_proof(&object, None, None).unwrap();
+        assert!(keccak.verify_object_proof(&object, &proof).unwrap());
+        assert!(!blake3.verify_object_proof(&object, &proof).unwrap());
+        assert_ne!(proof.proof_data, blake3.generate_object_proof(&object, None, None).unwrap().proof_data);
+
+        // State proofs and their sparse roots follow the engine's hasher too
+        let object_proofs = vec![(id, proof)];
+        let state_proof = keccak.generate_state_proof(&object_proofs, &[], None, 1).unwrap();
+        assert_eq!(keccak.state_proof_data(&state_proof).unwrap().hash_algorithm, HashAlgorithm::Keccak256);
+        assert!(keccak.verify_state_proof(&state_proof, &object_proofs).unwrap());
+        assert!(!blake3.verify_state_proof(&state_proof, &object_proofs).unwrap());
+        assert_ne!(keccak.sparse_object_root(&object_proofs).unwrap(), blake3.sparse_object_root(&object_proofs).unwrap());
+    }
+
+    #[test]
+    fn test_signed_state_proofs() {
+        use crate::signing::Ed25519Signer;
+
+        let engine = ProofEngine::new();
+        let validator = Ed25519Signer::from_seed([1; 32]);
+        let stranger = Ed25519Signer::from_seed([2; 32]);
+        let state_proof = engine.generate_state_proof(&[], &[[7u8; 32]], None, 5).unwrap();
+
+        let signature = engine.sign_state_proof(&state_proof, &validator);
+        let trusted = [validator.public_key()];
+        assert_eq!(engine.verify_state_proof_signature(&state_proof, &signature, &trusted), VerificationResult::Valid);
+
+        // A different root, a key outside the trusted set or a damaged signature is rejected
+        let other = engine.generate_state_proof(&[], &[], None, 5).unwrap();
+        let rejected = |result| matches!(result, VerificationResult::InvalidSignature(_));
+        assert!(rejected(engine.verify_state_proof_signature(&other, &signature, &trusted)));
+        let untrusted = engine.sign_state_proof(&state_proof, &stranger);
+        assert!(rejected(engine.verify_state_proof_signature(&state_proof, &untrusted, &trusted)));
+        let mut damaged = signature.clone();
+        damaged.signature[0] ^= 1;
+        assert!(rejected(engine.verify_state_proof_signature(&state_proof, &damaged, &trusted)));
+        damaged.signature.pop();
+        assert!(rejected(engine.verify_state_proof_signature(&state_proof, &damaged, &trusted)));
+    }
+
+    #[test]
+    fn test_slot_regressions_are_reported_and_repaired() {
+        let engine = ProofEngine::new();
+        let id = UnitsObjectId::from_bytes([1u8; 32]);
+        let object = TestObject { id, data: vec![1] };
+        let object_hash = engine.hash_object(&object).unwrap();
+
+        let mut chain: Vec<UnitsObjectProof> = Vec::new();
+        for slot in [10, 20, 15, 30] {
+            let prev = chain.last();
+            let data = engine.create_proof_data(&object_hash, prev.map(|p| p.hash()), slot, None);
+            let proof = UnitsObjectProof::new(id, object_hash, slot, data, prev, None);
+            chain.push(proof);
+        }
+
+        let regressions = engine.slot_regressions(&chain);
+        assert_eq!(regressions, vec![SlotRegression { object_id: id, index: 2, prev_slot: 20, slot: 15 }]);
+        assert!(ProofEngine::new().with_slot_ordering(SlotOrdering::Off).slot_regressions(&chain).is_empty());
+        let states: Vec<_> = chain.iter().map(|p| (p.slot, object.clone())).collect();
+        let proofs: Vec<_> = chain.iter().map(|p| (p.slot, p.clone())).collect();
+        assert!(matches!(engine.verify_proof_history(&states, &proofs), VerificationResult::Invalid(_)));
+
+        // The regressive proof moves up to its predecessor's slot and later links are rebuilt
+        let repaired = engine.repair_slot_order(&chain).unwrap();
+        assert_eq!(repaired.iter().map(|p| p.slot).collect::<Vec<_>>(), vec![10, 20, 20, 30]);
+        assert_eq!(repaired[1].hash(), chain[1].hash());
+        let states: Vec<_> = repaired.iter().map(|p| (p.slot, object.clone())).collect();
+        let proofs: Vec<_> = repaired.iter().map(|p| (p.slot, p.clone())).collect();
+        assert_eq!(engine.verify_proof_history(&states, &proofs), VerificationResult::Valid);
+        assert!(engine.repair_slot_order(&repaired).is_none());
+
+        // Under strict ordering equal slots regress as well
+        let strict = ProofEngine::new().with_slot_ordering(SlotOrdering::Strict);
+        assert_eq!(strict.slot_regressions(&repaired).len(), 1);
+        let strict_repaired = strict.repair_slot_order(&repaired).unwrap();
+        assert_eq!(strict_repaired.iter().map(|p| p.slot).collect::<Vec<_>>(), vec![10, 20, 21, 30]);
+    }
+}
@@ -0,0 +1,119 @@
+//! Hash functions the proof engine can be built on
+//!
+//! Blake3 is the default for speed. Sha256 and Keccak-256 let deployments
+//! produce roots that other ecosystems can check natively, Keccak-256 in
+//! particular for EVM contracts. State proofs record the algorithm in their
+//! proof data, so a verifier built on another hasher rejects them rather
+//! than reporting a root mismatch. The [`with_proof_engine!`] macro picks
+//! the hasher for an algorithm chosen at runtime, such as from config.
+
+use sha2::Digest;
+use units_core_types::HashAlgorithm;
+
+/// Hash function behind a [`ProofEngine`](crate::ProofEngine)
+pub trait ProofHasher: std::fmt::Debug + Clone + Default + Send + Sync + 'static {
+    /// Algorithm recorded in the proofs this hasher produces
+    const ALGORITHM: HashAlgorithm;
+
+    /// Hash the concatenation of `parts`
+    fn hash(parts: &[&[u8]]) -> [u8; 32];
+}
+
+/// Blake3, the default hasher and the one every proof before the choice existed used
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Blake3Hasher;
+
+impl ProofHasher for Blake3Hasher {
+    const ALGORITHM: HashAlgorithm = HashAlgorithm::Blake3;
+
+    fn hash(parts: &[&[u8]]) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        for part in parts {
+            hasher.update(part);
+        }
+        *hasher.finalize().as_bytes()
+    }
+}
+
+/// SHA-256, for roots checked where only SHA-2 is available
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256Hasher;
+
+impl ProofHasher for Sha256Hasher {
+    const ALGORITHM: HashAlgorithm = HashAlgorithm::Sha256;
+
+    fn hash(parts: &[&[u8]]) -> [u8; 32] {
+        digest::<sha2::Sha256>(parts)
+    }
+}
+
+/// Keccak-256 as Ethereum uses it, without SHA-3's padding change, for
+/// roots checked by EVM contracts
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Keccak256Hasher;
+
+impl ProofHasher for Keccak256Hasher {
+    const ALGORITHM: HashAlgorithm = HashAlgorithm::Keccak256;
+
+    fn hash(parts: &[&[u8]]) -> [u8; 32] {
+        digest::<sha3::Keccak256>(parts)
+    }
+}
+
+/// Evaluate `$body` with `$engine` bound to a [`ProofEngine`](crate::ProofEngine)
+/// hashing with the [`HashAlgorithm`]'s hasher
+///
+/// The hasher is a type parameter of the engine, so `$body` is compiled
+/// once per algorithm and must have the same type in each.
+#[macro_export]
+macro_rules! with_proof_engine {
+    ($algorithm:expr, |$engine:ident| $body:expr) => {
+        match $algorithm {
+            $crate::HashAlgorithm::Blake3 => {
+                let $engine = $crate::ProofEngine::<$crate::Blake3Hasher>::with_hasher();
+                $body
+            }
+            $crate::HashAlgorithm::Sha256 => {
+                let $engine = $crate::ProofEngine::<$crate::Sha256Hasher>::with_hasher();
+                $body
+            }
+            $crate::HashAlgorithm::Keccak256 => {
+                let $engine = $crate::ProofEngine::<$crate::Keccak256Hasher>::with_hasher();
+                $body
+            }
+        }
+    };
+}
+
+fn digest<D: Digest>(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = D::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().as_slice().try_into().expect("32-byte digest")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_digests() {
+        assert_eq!(
+            hex::encode(Keccak256Hasher::hash(&[b""])),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(
+            hex::encode(Sha256Hasher::hash(&[b"a", b"bc"])),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(Blake3Hasher::hash(&[b"a", b"bc"]), *blake3::hash(b"abc").as_bytes());
+    }
+
+    #[test]
+    fn test_engine_follows_the_chosen_algorithm() {
+        for algorithm in [HashAlgorithm::Blake3, HashAlgorithm::Sha256, HashAlgorithm::Keccak256] {
+            assert_eq!(with_proof_engine!(algorithm, |engine| engine.hash_algorithm()), algorithm);
+        }
+    }
+}
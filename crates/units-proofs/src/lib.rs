@@ -1,9 +1,17 @@
 pub mod engine;
+pub mod hasher;
+pub mod migration;
+pub mod signing;
+pub mod sparse_merkle;
 pub mod types;
 
 // Re-export main types and functions for convenience
-pub use engine::ProofEngine;
-pub use types::{Proof, SlotNumber, StateProof, UnitsObjectProof, VerificationResult, MerkleNode};
+pub use engine::{ObjectTree, ProofEngine, SlotOrdering, SlotRegression, StateProofData};
+pub use hasher::{Blake3Hasher, Keccak256Hasher, ProofHasher, Sha256Hasher};
+pub use migration::{MigratedChain, ProofBridge, ProofFormat, ProofMigration};
+pub use signing::{state_proof_message, Ed25519Signer, ProofSigner, StateProofSignature};
+pub use sparse_merkle::{MerkleNodeStore, SparseMerkleTree};
+pub use types::{HashAlgorithm, InclusionProof, Proof, SlotNumber, StateProof, UnitsObjectProof, VerificationResult, MerkleNode};
 
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -0,0 +1,259 @@
+//! Regenerating proof chains under a new proof format
+//!
+//! When the hash algorithm or layout behind `proof_data` changes, stored
+//! chains must be reissued in the new format without losing what the old
+//! ones proved. A `ProofMigration` rebuilds each chain proof by proof under
+//! the target format and emits a `ProofBridge` for every pair, committing
+//! to both the old proof's hash and its replacement. Anything anchored to
+//! an old proof, such as an earlier state root, stays checkable through
+//! the bridge.
+//!
+//! Object hashes are carried over unchanged: old object states may have
+//! been compacted away, so the new chain vouches for them via the bridge
+//! rather than by rehashing.
+
+use blake3::Hasher;
+use serde::{Deserialize, Serialize};
+use units_core_types::{HashAlgorithm, ProofStorageError, SlotNumber, UnitsObjectId, UnitsObjectProof};
+
+use crate::engine::ProofEngine;
+use crate::hasher::ProofHasher;
+
+/// Domain separating bridge commitments from other hashes
+const BRIDGE_DOMAIN: &[u8] = b"units/proof-bridge/v1";
+
+/// A way of computing the `proof_data` that authenticates an object proof
+pub trait ProofFormat: Send + Sync {
+    /// Stable name of the format, committed to by bridges
+    fn format_id(&self) -> &str;
+
+    /// Proof data committing to `object_hash` at `slot` after `prev_proof_hash`
+    fn proof_data(
+        &self,
+        object_hash: &[u8; 32],
+        prev_proof_hash: Option<[u8; 32]>,
+        slot: SlotNumber,
+        transaction_hash: Option<[u8; 32]>,
+    ) -> Vec<u8>;
+}
+
+/// The engine's own hash chain, named after its hasher
+impl<H: ProofHasher> ProofFormat for ProofEngine<H> {
+    fn format_id(&self) -> &str {
+        match H::ALGORITHM {
+            HashAlgorithm::Blake3 => "blake3-chain/v1",
+            HashAlgorithm::Sha256 => "sha256-chain/v1",
+            HashAlgorithm::Keccak256 => "keccak256-chain/v1",
+        }
+    }
+
+    fn proof_data(
+        &self,
+        object_hash: &[u8; 32],
+        prev_proof_hash: Option<[u8; 32]>,
+        slot: SlotNumber,
+        transaction_hash: Option<[u8; 32]>,
+    ) -> Vec<u8> {
+        self.create_proof_data(object_hash, prev_proof_hash, slot, transaction_hash)
+    }
+}
+
+/// Commitment linking a proof to its replacement in a new format
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofBridge {
+    pub object_id: UnitsObjectId,
+    pub slot: SlotNumber,
+    pub old_proof_hash: [u8; 32],
+    pub new_proof_hash: [u8; 32],
+    /// Hash over both format IDs and both proof hashes
+    pub commitment: [u8; 32],
+}
+
+/// A chain reissued in the target format, with a bridge per proof
+#[derive(Debug, Clone)]
+pub struct MigratedChain {
+    pub proofs: Vec<UnitsObjectProof>,
+    pub bridges: Vec<ProofBridge>,
+}
+
+/// Moves proof chains from one format to another
+pub struct ProofMigration<'a> {
+    from: &'a dyn ProofFormat,
+    to: &'a dyn ProofFormat,
+}
+
+impl<'a> ProofMigration<'a> {
+    pub fn new(from: &'a dyn ProofFormat, to: &'a dyn ProofFormat) -> Self {
+        Self { from, to }
+    }
+
+    /// Reissue `chain` (oldest first) in the target format
+    ///
+    /// The old chain must verify under the source format first; a broken
+    /// chain is reported rather than laundered into a valid-looking one.
+    pub fn migrate_chain(&self, chain: &[UnitsObjectProof]) -> Result<MigratedChain, ProofStorageError> {
+        Self::check_chain(self.from, chain)?;
+
+        let mut proofs: Vec<UnitsObjectProof> = Vec::with_capacity(chain.len());
+        let mut bridges = Vec::with_capacity(chain.len());
+        for old in chain {
+            let prev = proofs.last();
+            let proof_data = self.to.proof_data(
+                &old.object_hash,
+                prev.map(|prev| prev.hash()),
+                old.slot,
+                old.transaction_hash,
+            );
+            let new = UnitsObjectProof::new(old.object_id, old.object_hash, old.slot, proof_data, prev, old.transaction_hash);
+            bridges.push(self.bridge(old, &new));
+            proofs.push(new);
+        }
+        Ok(MigratedChain { proofs, bridges })
+    }
+
+    /// Check that `migrated` is a faithful reissue of `old`
+    ///
+    /// The new chain must verify under the target format, pair up with the
+    /// old one proof for proof, and carry a correct bridge for every pair.
+    pub fn validate(&self, old: &[UnitsObjectProof], migrated: &MigratedChain) -> Result<(), ProofStorageError> {
+        Self::check_chain(self.to, &migrated.proofs)?;
+        if old.len() != migrated.proofs.len() || old.len() != migrated.bridges.len() {
+            return Err(ProofStorageError::ProofChainInvalid(format!(
+                "Migrated chain has {} proofs and {} bridges for {} original proofs",
+                migrated.proofs.len(),
+                migrated.bridges.len(),
+                old.len()
+            )));
+        }
+
+        for ((old, new), bridge) in old.iter().zip(&migrated.proofs).zip(&migrated.bridges) {
+            let same_state = old.object_id == new.object_id
+                && old.slot == new.slot
+                && old.object_hash == new.object_hash
+                && old.transaction_hash == new.transaction_hash;
+            if !same_state || *bridge != self.bridge(old, new) {
+                return Err(ProofStorageError::ProofChainInvalid(format!(
+                    "Proof of {} at slot {} is not bridged to its replacement",
+                    old.object_id, old.slot
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `bridge` links `old_proof_hash` to `new_proof_hash` under this migration
+    pub fn verify_bridge(&self, bridge: &ProofBridge) -> bool {
+        bridge.commitment == self.commitment(&bridge.old_proof_hash, &bridge.new_proof_hash)
+    }
+
+    fn bridge(&self, old: &UnitsObjectProof, new: &UnitsObjectProof) -> ProofBridge {
+        let (old_proof_hash, new_proof_hash) = (old.hash(), new.hash());
+        ProofBridge {
+            object_id: old.object_id,
+            slot: old.slot,
+            old_proof_hash,
+            new_proof_hash,
+            commitment: self.commitment(&old_proof_hash, &new_proof_hash),
+        }
+    }
+
+    fn commitment(&self, old_proof_hash: &[u8; 32], new_proof_hash: &[u8; 32]) -> [u8; 32] {
+        let mut hasher = Hasher::new();
+        hasher.update(BRIDGE_DOMAIN);
+        for format_id in [self.from.format_id(), self.to.format_id()] {
+            hasher.update(&(format_id.len() as u32).to_le_bytes());
+            hasher.update(format_id.as_bytes());
+        }
+        hasher.update(old_proof_hash);
+        hasher.update(new_proof_hash);
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Check every proof's data under `format` and every link to its predecessor
+    fn check_chain(format: &dyn ProofFormat, chain: &[UnitsObjectProof]) -> Result<(), ProofStorageError> {
+        let mut prev: Option<&UnitsObjectProof> = None;
+        for proof in chain {
+            let linked = proof.prev_proof_hash == prev.map(|prev| prev.hash())
+                && prev.is_none_or(|prev| prev.object_id == proof.object_id);
+            let expected = format.proof_data(&proof.object_hash, proof.prev_proof_hash, proof.slot, proof.transaction_hash);
+            if !linked || proof.proof_data != expected {
+                return Err(ProofStorageError::ProofChainInvalid(format!(
+                    "Proof of {} at slot {} does not verify as {}",
+                    proof.object_id,
+                    proof.slot,
+                    format.format_id()
+                )));
+            }
+            prev = Some(proof);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use units_core_types::UnitsObject;
+
+    /// Stand-in for a future format: SHA-256 instead of Blake3
+    struct Sha256Chain;
+
+    impl ProofFormat for Sha256Chain {
+        fn format_id(&self) -> &str {
+            "sha256-chain/v1"
+        }
+
+        fn proof_data(
+            &self,
+            object_hash: &[u8; 32],
+            prev_proof_hash: Option<[u8; 32]>,
+            slot: SlotNumber,
+            transaction_hash: Option<[u8; 32]>,
+        ) -> Vec<u8> {
+            use sha2::{Digest, Sha256};
+            let mut hasher = Sha256::new();
+            hasher.update(object_hash);
+            hasher.update(slot.to_le_bytes());
+            hasher.update(prev_proof_hash.unwrap_or_default());
+            hasher.update(transaction_hash.unwrap_or_default());
+            hasher.finalize().to_vec()
+        }
+    }
+
+    #[test]
+    fn test_chains_migrate_with_bridges() {
+        let engine = ProofEngine::new();
+        let id = UnitsObjectId::from_bytes([3u8; 32]);
+        let mut chain: Vec<UnitsObjectProof> = Vec::new();
+        for version in 0..3u8 {
+            let object = UnitsObject::new_data(id, id, vec![version]);
+            let proof = engine.generate_object_proof(&object, chain.last(), Some([version; 32])).unwrap();
+            chain.push(proof);
+        }
+
+        let migration = ProofMigration::new(&engine, &Sha256Chain);
+        let migrated = migration.migrate_chain(&chain).unwrap();
+        migration.validate(&chain, &migrated).unwrap();
+        assert!(migrated.bridges.iter().all(|bridge| migration.verify_bridge(bridge)));
+        assert_ne!(migrated.proofs[0].proof_data, chain[0].proof_data);
+        assert_eq!(migrated.bridges[2].old_proof_hash, chain[2].hash());
+        assert_eq!(migrated.bridges[2].new_proof_hash, migrated.proofs[2].hash());
+
+        // A bridge is only good for the formats it was made between
+        let reverse = ProofMigration::new(&Sha256Chain, &engine);
+        assert!(!reverse.verify_bridge(&migrated.bridges[0]));
+
+        // Tampering with either chain fails validation
+        let mut forged = migrated.clone();
+        forged.proofs[1].object_hash = [9u8; 32];
+        assert!(migration.validate(&chain, &forged).is_err());
+        let mut dropped = migrated.clone();
+        dropped.bridges.pop();
+        assert!(migration.validate(&chain, &dropped).is_err());
+
+        // A chain that is already broken is refused
+        let mut broken = chain.clone();
+        broken[1].proof_data = vec![0u8; 32];
+        assert!(migration.migrate_chain(&broken).is_err());
+    }
+}
@@ -0,0 +1,99 @@
+//! Signatures over state proofs
+//!
+//! A state proof's hash commits to its slot, object and transaction roots
+//! and the link to the previous state proof, so signing that hash
+//! authenticates the whole slot root. Consumers holding the signer's public
+//! key can then trust a root without trusting whoever relayed it.
+
+use curve25519_dalek::constants::ED25519_BASEPOINT_POINT;
+use curve25519_dalek::scalar::Scalar;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+use units_core_types::StateProof;
+
+/// Domain separator prefixed to every signed state proof message
+const STATE_PROOF_DOMAIN: &[u8] = b"units-state-proof-v1";
+
+/// Key that signs state proofs
+pub trait ProofSigner: Send + Sync {
+    /// Ed25519 public key signatures verify under
+    fn public_key(&self) -> [u8; 32];
+
+    /// Ed25519 signature over `message`
+    fn sign(&self, message: &[u8]) -> [u8; 64];
+}
+
+/// In-process Ed25519 key
+pub struct Ed25519Signer {
+    secret: Scalar,
+    /// Second half of the expanded seed, used to derive nonces
+    prefix: [u8; 32],
+    public_key: [u8; 32],
+}
+
+impl Ed25519Signer {
+    /// Derive the key from a 32-byte Ed25519 seed
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        let expanded = Sha512::digest(seed);
+        let mut scalar_bytes = [0u8; 32];
+        scalar_bytes.copy_from_slice(&expanded[..32]);
+        scalar_bytes[0] &= 248;
+        scalar_bytes[31] &= 127;
+        scalar_bytes[31] |= 64;
+
+        let secret = Scalar::from_bytes_mod_order(scalar_bytes);
+        let mut prefix = [0u8; 32];
+        prefix.copy_from_slice(&expanded[32..]);
+
+        Self {
+            secret,
+            prefix,
+            public_key: (secret * ED25519_BASEPOINT_POINT).compress().to_bytes(),
+        }
+    }
+}
+
+impl ProofSigner for Ed25519Signer {
+    fn public_key(&self) -> [u8; 32] {
+        self.public_key
+    }
+
+    fn sign(&self, message: &[u8]) -> [u8; 64] {
+        let nonce = wide_scalar(Sha512::new().chain_update(self.prefix).chain_update(message));
+        let r = (nonce * ED25519_BASEPOINT_POINT).compress().to_bytes();
+        let challenge = wide_scalar(
+            Sha512::new()
+                .chain_update(r)
+                .chain_update(self.public_key)
+                .chain_update(message),
+        );
+        let s = nonce + challenge * self.secret;
+
+        let mut signature = [0u8; 64];
+        signature[..32].copy_from_slice(&r);
+        signature[32..].copy_from_slice(s.as_bytes());
+        signature
+    }
+}
+
+fn wide_scalar(hasher: Sha512) -> Scalar {
+    Scalar::from_bytes_mod_order_wide(&hasher.finalize().into())
+}
+
+/// A signer's attestation to one state proof
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateProofSignature {
+    /// Ed25519 public key of the signer
+    pub signer: [u8; 32],
+    /// 64-byte Ed25519 signature over `state_proof_message`
+    pub signature: Vec<u8>,
+}
+
+/// Message a state proof's signature covers
+pub fn state_proof_message(state_proof: &StateProof) -> Vec<u8> {
+    let mut message = Vec::with_capacity(STATE_PROOF_DOMAIN.len() + 40);
+    message.extend_from_slice(STATE_PROOF_DOMAIN);
+    message.extend_from_slice(&state_proof.slot.to_le_bytes());
+    message.extend_from_slice(&state_proof.hash());
+    message
+}
@@ -0,0 +1,389 @@
+//! Persistent sparse Merkle tree over object proofs
+//!
+//! The tree has one leaf position for every possible `UnitsObjectId`, taken
+//! from the ID's bits most significant first. Empty subtrees hash to fixed
+//! defaults and are never stored, and a subtree holding a single object is
+//! stored as one shortcut node, so an update reads and writes one node per
+//! level of the occupied part of the tree: O(log n) for n objects.
+//!
+//! Nodes are content-addressed and never overwritten. Every root ever
+//! produced stays readable, so paths can still be built against the root
+//! of an older state proof.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::OnceLock;
+
+use units_core_types::{MerkleNode, ProofStorage, StorageError, UnitsObjectId};
+
+use crate::hasher::{Blake3Hasher, ProofHasher};
+
+/// Height of the tree: one level per bit of an object ID
+const DEPTH: usize = 256;
+
+const BRANCH: u8 = 0;
+const LEAF: u8 = 1;
+
+/// Where a [`SparseMerkleTree`] keeps its nodes
+pub trait MerkleNodeStore {
+    /// Get the encoded node stored under `hash`
+    fn get_node(&self, hash: &[u8; 32]) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Store an encoded node under its `hash`
+    fn put_node(&self, hash: &[u8; 32], node: &[u8]) -> Result<(), StorageError>;
+}
+
+impl<P: ProofStorage + ?Sized> MerkleNodeStore for P {
+    fn get_node(&self, hash: &[u8; 32]) -> Result<Option<Vec<u8>>, StorageError> {
+        self.get_merkle_node(hash)
+    }
+
+    fn put_node(&self, hash: &[u8; 32], node: &[u8]) -> Result<(), StorageError> {
+        self.store_merkle_node(hash, node)
+    }
+}
+
+/// Throwaway node store for computing a root from scratch
+#[derive(Default)]
+pub(crate) struct MemoryNodes(RefCell<HashMap<[u8; 32], Vec<u8>>>);
+
+impl MerkleNodeStore for MemoryNodes {
+    fn get_node(&self, hash: &[u8; 32]) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.0.borrow().get(hash).cloned())
+    }
+
+    fn put_node(&self, hash: &[u8; 32], node: &[u8]) -> Result<(), StorageError> {
+        self.0.borrow_mut().insert(*hash, node.to_vec());
+        Ok(())
+    }
+}
+
+enum Node {
+    /// Subtree with objects on both sides
+    Branch([u8; 32], [u8; 32]),
+    /// Subtree whose only object is `id`, with its leaf hash
+    Leaf(UnitsObjectId, [u8; 32]),
+}
+
+/// Sparse Merkle tree keyed by object ID, with nodes kept in a [`MerkleNodeStore`]
+///
+/// Leaves hash with the same pairing as [`ProofEngine`](crate::ProofEngine)
+/// paths, so a path from [`SparseMerkleTree::path`] verifies with
+/// `ProofEngine::verify_proof_against_root` when both use the same hasher `H`.
+pub struct SparseMerkleTree<'a, S: ?Sized, H = Blake3Hasher> {
+    store: &'a S,
+    root: [u8; 32],
+    hasher: PhantomData<H>,
+}
+
+impl<'a, S: MerkleNodeStore + ?Sized> SparseMerkleTree<'a, S> {
+    /// Empty tree writing its nodes to `store`
+    pub fn new(store: &'a S) -> Self {
+        Self::with_hasher(store)
+    }
+
+    /// Tree at `root`, whose nodes were written to `store` earlier
+    pub fn open(store: &'a S, root: [u8; 32]) -> Self {
+        Self::open_with_hasher(store, root)
+    }
+}
+
+impl<'a, S: MerkleNodeStore + ?Sized, H: ProofHasher> SparseMerkleTree<'a, S, H> {
+    /// Empty tree hashing with `H`, writing its nodes to `store`
+    pub fn with_hasher(store: &'a S) -> Self {
+        Self::open_with_hasher(store, Self::empty_root())
+    }
+
+    /// Tree at `root` hashed with `H`, whose nodes were written to `store` earlier
+    pub fn open_with_hasher(store: &'a S, root: [u8; 32]) -> Self {
+        Self { store, root, hasher: PhantomData }
+    }
+
+    /// Root of a tree without objects
+    pub fn empty_root() -> [u8; 32] {
+        default_hash::<H>(DEPTH)
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.root
+    }
+
+    /// Leaf hash stored for `id`, if the object is in the tree
+    pub fn get(&self, id: &UnitsObjectId) -> Result<Option<[u8; 32]>, StorageError> {
+        let mut hash = self.root;
+        for height in (1..=DEPTH).rev() {
+            if hash == default_hash::<H>(height) {
+                return Ok(None);
+            }
+            match self.load(&hash)? {
+                Node::Leaf(key, leaf) => return Ok((key == *id).then_some(leaf)),
+                Node::Branch(left, right) => hash = if bit(id, DEPTH - height) { right } else { left },
+            }
+        }
+        self.leaf_at_bottom(&hash, id)
+    }
+
+    /// Set the leaf hash of `id`, replacing any earlier one
+    pub fn insert(&mut self, id: &UnitsObjectId, leaf: [u8; 32]) -> Result<(), StorageError> {
+        self.root = self.update(self.root, DEPTH, id, Some(leaf))?;
+        Ok(())
+    }
+
+    /// Remove `id` from the tree; removing an absent object changes nothing
+    pub fn remove(&mut self, id: &UnitsObjectId) -> Result<(), StorageError> {
+        self.root = self.update(self.root, DEPTH, id, None)?;
+        Ok(())
+    }
+
+    /// Sibling path from the leaf of `id` to the root, ordered from the leaf upwards
+    ///
+    /// Returns `None` if the object is not in the tree.
+    pub fn path(&self, id: &UnitsObjectId) -> Result<Option<Vec<MerkleNode>>, StorageError> {
+        // Siblings from the root downwards, until the object's shortcut node
+        let mut above = Vec::new();
+        let mut hash = self.root;
+        let mut height = DEPTH;
+        loop {
+            if hash == default_hash::<H>(height) {
+                return Ok(None);
+            }
+            if height == 0 {
+                if self.leaf_at_bottom(&hash, id)?.is_none() {
+                    return Ok(None);
+                }
+                break;
+            }
+            match self.load(&hash)? {
+                Node::Leaf(key, _) if key == *id => break,
+                Node::Leaf(..) => return Ok(None),
+                Node::Branch(left, right) => {
+                    let right_side = bit(id, DEPTH - height);
+                    let (next, sibling) = if right_side { (right, left) } else { (left, right) };
+                    above.push(MerkleNode { hash: sibling, is_left: right_side });
+                    hash = next;
+                    height -= 1;
+                }
+            }
+        }
+
+        // Below the shortcut node every sibling is an empty subtree
+        let below = (0..height).map(|level| MerkleNode {
+            hash: default_hash::<H>(level),
+            is_left: bit(id, DEPTH - 1 - level),
+        });
+        Ok(Some(below.chain(above.into_iter().rev()).collect()))
+    }
+
+    /// Apply `value` for `id` to the subtree at `hash`, returning its new hash
+    fn update(
+        &self,
+        hash: [u8; 32],
+        height: usize,
+        id: &UnitsObjectId,
+        value: Option<[u8; 32]>,
+    ) -> Result<[u8; 32], StorageError> {
+        if hash == default_hash::<H>(height) {
+            return match value {
+                Some(leaf) => self.put_leaf(id, leaf, height),
+                None => Ok(hash),
+            };
+        }
+
+        let (left, right) = match self.load(&hash)? {
+            Node::Leaf(key, _) if key == *id => {
+                return match value {
+                    Some(leaf) => self.put_leaf(id, leaf, height),
+                    None => Ok(default_hash::<H>(height)),
+                };
+            }
+            Node::Leaf(..) if value.is_none() => return Ok(hash),
+            Node::Leaf(key, leaf) => {
+                // Push the other object one level down to make room; two IDs
+                // differ in some bit, so this stops above the bottom level
+                let child = self.put_leaf(&key, leaf, height - 1)?;
+                if bit(&key, DEPTH - height) {
+                    (default_hash::<H>(height - 1), child)
+                } else {
+                    (child, default_hash::<H>(height - 1))
+                }
+            }
+            Node::Branch(left, right) => (left, right),
+        };
+
+        if bit(id, DEPTH - height) {
+            let right = self.update(right, height - 1, id, value)?;
+            self.put_branch(left, right, height)
+        } else {
+            let left = self.update(left, height - 1, id, value)?;
+            self.put_branch(left, right, height)
+        }
+    }
+
+    /// Store a branch, collapsing it if a removal left at most one object below it
+    fn put_branch(&self, left: [u8; 32], right: [u8; 32], height: usize) -> Result<[u8; 32], StorageError> {
+        let empty = default_hash::<H>(height - 1);
+        let only_child = match (left == empty, right == empty) {
+            (true, true) => return Ok(default_hash::<H>(height)),
+            (true, false) => Some(right),
+            (false, true) => Some(left),
+            (false, false) => None,
+        };
+        if let Some(child) = only_child {
+            if let Node::Leaf(key, leaf) = self.load(&child)? {
+                return self.put_leaf(&key, leaf, height);
+            }
+        }
+
+        let hash = hash_pair::<H>(&left, &right);
+        let mut node = Vec::with_capacity(65);
+        node.push(BRANCH);
+        node.extend_from_slice(&left);
+        node.extend_from_slice(&right);
+        self.store.put_node(&hash, &node)?;
+        Ok(hash)
+    }
+
+    /// Store `id` as the only object of a subtree at `height`
+    fn put_leaf(&self, id: &UnitsObjectId, leaf: [u8; 32], height: usize) -> Result<[u8; 32], StorageError> {
+        let mut hash = leaf;
+        for level in 0..height {
+            hash = if bit(id, DEPTH - 1 - level) {
+                hash_pair::<H>(&default_hash::<H>(level), &hash)
+            } else {
+                hash_pair::<H>(&hash, &default_hash::<H>(level))
+            };
+        }
+
+        let mut node = Vec::with_capacity(65);
+        node.push(LEAF);
+        node.extend_from_slice(id.bytes());
+        node.extend_from_slice(&leaf);
+        self.store.put_node(&hash, &node)?;
+        Ok(hash)
+    }
+
+    /// Leaf hash of a non-empty bottom-level node, if it belongs to `id`
+    fn leaf_at_bottom(&self, hash: &[u8; 32], id: &UnitsObjectId) -> Result<Option<[u8; 32]>, StorageError> {
+        if *hash == default_hash::<H>(0) {
+            return Ok(None);
+        }
+        match self.load(hash)? {
+            Node::Leaf(key, leaf) if key == *id => Ok(Some(leaf)),
+            _ => Ok(None),
+        }
+    }
+
+    fn load(&self, hash: &[u8; 32]) -> Result<Node, StorageError> {
+        let node = self
+            .store
+            .get_node(hash)?
+            .ok_or_else(|| StorageError::NotFound(format!("Merkle node {}", hex::encode(hash))))?;
+        if node.len() != 65 {
+            return Err(StorageError::Serialization(format!("Merkle node {} is {} bytes", hex::encode(hash), node.len())));
+        }
+
+        let first: [u8; 32] = node[1..33].try_into().unwrap();
+        let second: [u8; 32] = node[33..].try_into().unwrap();
+        match node[0] {
+            BRANCH => Ok(Node::Branch(first, second)),
+            LEAF => Ok(Node::Leaf(UnitsObjectId::new(first), second)),
+            tag => Err(StorageError::Serialization(format!("Unknown Merkle node tag {}", tag))),
+        }
+    }
+}
+
+/// Bit `index` of an ID, counting from the most significant bit of the first byte
+fn bit(id: &UnitsObjectId, index: usize) -> bool {
+    id.bytes()[index / 8] & (0x80 >> (index % 8)) != 0
+}
+
+fn hash_pair<H: ProofHasher>(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    H::hash(&[left, right])
+}
+
+/// Hash of an empty subtree of `height` under `H`; an empty leaf is all zeros
+fn default_hash<H: ProofHasher>(height: usize) -> [u8; 32] {
+    // One table per algorithm, indexed by its tag
+    static DEFAULTS: [OnceLock<Vec<[u8; 32]>>; 3] = [OnceLock::new(), OnceLock::new(), OnceLock::new()];
+    DEFAULTS[H::ALGORITHM.tag() as usize].get_or_init(|| {
+        let mut defaults = vec![[0u8; 32]];
+        for height in 0..DEPTH {
+            defaults.push(hash_pair::<H>(&defaults[height], &defaults[height]));
+        }
+        defaults
+    })[height]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(first: u8, last: u8) -> UnitsObjectId {
+        let mut bytes = [0u8; 32];
+        bytes[0] = first;
+        bytes[31] = last;
+        UnitsObjectId::new(bytes)
+    }
+
+    fn verify(leaf: [u8; 32], path: &[MerkleNode]) -> [u8; 32] {
+        path.iter().fold(leaf, |hash, node| {
+            if node.is_left {
+                hash_pair::<Blake3Hasher>(&node.hash, &hash)
+            } else {
+                hash_pair::<Blake3Hasher>(&hash, &node.hash)
+            }
+        })
+    }
+
+    #[test]
+    fn test_root_depends_only_on_contents() {
+        let store = MemoryNodes::default();
+        let ids = [id(0x80, 0), id(0x80, 1), id(0, 0), id(0xff, 0xff)];
+
+        let mut forward = SparseMerkleTree::new(&store);
+        for (n, id) in ids.iter().enumerate() {
+            forward.insert(id, [n as u8 + 1; 32]).unwrap();
+        }
+        let mut backward = SparseMerkleTree::new(&store);
+        for (n, id) in ids.iter().enumerate().rev() {
+            backward.insert(id, [n as u8 + 1; 32]).unwrap();
+        }
+        assert_eq!(forward.root(), backward.root());
+
+        // Every object's path hashes up to the root, including the two that
+        // share all but their last bit
+        for (n, id) in ids.iter().enumerate() {
+            let path = forward.path(id).unwrap().unwrap();
+            assert_eq!(path.len(), DEPTH);
+            assert_eq!(verify([n as u8 + 1; 32], &path), forward.root());
+            assert_eq!(forward.get(id).unwrap(), Some([n as u8 + 1; 32]));
+        }
+        assert!(forward.path(&id(0x80, 2)).unwrap().is_none());
+
+        // Removing objects restores the root of the smaller tree, down to empty
+        let before = forward.root();
+        forward.insert(&id(0x40, 0), [9; 32]).unwrap();
+        forward.remove(&id(0x40, 0)).unwrap();
+        assert_eq!(forward.root(), before);
+        for id in &ids {
+            forward.remove(id).unwrap();
+        }
+        assert_eq!(forward.root(), SparseMerkleTree::<MemoryNodes>::empty_root());
+    }
+
+    #[test]
+    fn test_older_roots_stay_readable() {
+        let store = MemoryNodes::default();
+        let mut tree = SparseMerkleTree::new(&store);
+        tree.insert(&id(1, 0), [1; 32]).unwrap();
+        let old_root = tree.root();
+        tree.insert(&id(1, 0), [2; 32]).unwrap();
+        tree.insert(&id(2, 0), [3; 32]).unwrap();
+
+        let old = SparseMerkleTree::open(&store, old_root);
+        assert_eq!(old.get(&id(1, 0)).unwrap(), Some([1; 32]));
+        assert_eq!(old.get(&id(2, 0)).unwrap(), None);
+        assert_eq!(tree.get(&id(1, 0)).unwrap(), Some([2; 32]));
+    }
+}
@@ -1,5 +1,6 @@
 // Re-export types from units-core-types for backward compatibility
 pub use units_core_types::{
+    HashAlgorithm,
     Proof,
     SlotNumber,
     UnitsObjectId,
@@ -8,4 +9,21 @@ pub use units_core_types::{
     VerificationResult,
     MerkleNode,
     ProofStorageError,
-};
\ No newline at end of file
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Evidence that one object's proof is committed to by a slot's state proof
+///
+/// Carries everything a light client needs besides the state proof itself:
+/// the object's committed proof and the sibling path from its leaf to the
+/// state proof's object root.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub object_id: UnitsObjectId,
+    /// Slot of the state proof the path leads to
+    pub slot: SlotNumber,
+    pub object_proof: UnitsObjectProof,
+    /// Sibling path ordered from the leaf upwards
+    pub path: Vec<MerkleNode>,
+}
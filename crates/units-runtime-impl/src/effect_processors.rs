@@ -0,0 +1,64 @@
+//! Built-in effect processors
+//!
+//! `WriteQuota` is a compliance check vetoing transactions that write too
+//! much; `WriteStats` enriches receipts with what a transaction wrote, for
+//! analytics and fee accounting downstream. Deployments register them, or
+//! their own processors, with `MockRuntime::with_effect_processor`.
+
+use units_core_types::{EffectProcessor, EffectVerdict, Transaction, TransactionReceipt};
+
+/// Object data bytes a receipt's effects leave behind
+fn bytes_written(receipt: &TransactionReceipt) -> u64 {
+    receipt
+        .effects
+        .iter()
+        .filter_map(|effect| effect.after_image.as_ref())
+        .map(|object| object.data().len() as u64)
+        .sum()
+}
+
+/// Veto transactions touching too many objects or writing too many bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteQuota {
+    pub max_effects: Option<usize>,
+    pub max_bytes_written: Option<u64>,
+}
+
+impl EffectProcessor for WriteQuota {
+    fn name(&self) -> &str {
+        "write_quota"
+    }
+
+    fn process(&self, _transaction: &Transaction, receipt: &TransactionReceipt) -> EffectVerdict {
+        if let Some(max) = self.max_effects.filter(|max| receipt.effects.len() > *max) {
+            return EffectVerdict::Veto(format!("{} effects exceed the limit of {}", receipt.effects.len(), max));
+        }
+        let written = bytes_written(receipt);
+        if let Some(max) = self.max_bytes_written.filter(|max| written > *max) {
+            return EffectVerdict::Veto(format!("{} bytes written exceed the limit of {}", written, max));
+        }
+        EffectVerdict::Accept
+    }
+}
+
+/// Annotate receipts with counts of created, modified and deleted objects and bytes written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteStats;
+
+impl EffectProcessor for WriteStats {
+    fn name(&self) -> &str {
+        "write_stats"
+    }
+
+    fn process(&self, _transaction: &Transaction, receipt: &TransactionReceipt) -> EffectVerdict {
+        let count = |kind: fn(&units_core_types::TransactionEffect) -> bool| {
+            receipt.effects.iter().filter(|effect| kind(effect)).count().to_string()
+        };
+        EffectVerdict::Annotate(vec![
+            ("created".to_string(), count(|effect| effect.is_creation())),
+            ("modified".to_string(), count(|effect| effect.is_modification())),
+            ("deleted".to_string(), count(|effect| effect.is_deletion())),
+            ("bytes_written".to_string(), bytes_written(receipt).to_string()),
+        ])
+    }
+}
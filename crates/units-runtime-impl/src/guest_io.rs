@@ -0,0 +1,280 @@
+//! Stdin, stdout and logging for executing modules
+//!
+//! Modules exchange data with the host through the streams described in
+//! [`units_types_ffi::syscall`], whatever VM they run in: stdin yields the
+//! length-prefixed Borsh execution context, effects and any events come
+//! back framed the same way on stdout, and stderr and `log` calls are
+//! logged. Each VM passes the module's buffers through [`GuestMemory`],
+//! which only exposes memory the module could access itself.
+
+use borsh::BorshDeserialize;
+use units_core_types::{ExecutionContext, KernelEvent, ObjectEffect, VMExecutionError};
+use units_types_ffi::syscall::{EBADF, EFAULT, EFBIG, STDERR, STDIN, STDOUT};
+
+/// Longest message logged from one call; the rest is dropped
+const MAX_LOG_BYTES: usize = 1024;
+
+/// Log target for module output
+const LOG_TARGET: &str = "units::module";
+
+/// Effects and events a module wrote to stdout
+pub(crate) type GuestOutput = (Vec<ObjectEffect>, Vec<KernelEvent>);
+
+/// A module's memory, as it may access it
+pub(crate) trait GuestMemory {
+    /// Bytes the module may read
+    fn guest_bytes(&self, addr: u32, len: usize) -> Option<&[u8]>;
+    /// Bytes the module may write
+    fn guest_bytes_mut(&mut self, addr: u32, len: usize) -> Option<&mut [u8]>;
+}
+
+/// Flat memory where every in-bounds byte is readable and writable
+impl GuestMemory for [u8] {
+    fn guest_bytes(&self, addr: u32, len: usize) -> Option<&[u8]> {
+        self.get(addr as usize..(addr as usize).checked_add(len)?)
+    }
+
+    fn guest_bytes_mut(&mut self, addr: u32, len: usize) -> Option<&mut [u8]> {
+        self.get_mut(addr as usize..(addr as usize).checked_add(len)?)
+    }
+}
+
+/// Per-execution state behind a module's streams
+pub(crate) struct GuestIo<'a> {
+    context: &'a ExecutionContext,
+    /// Length-prefixed context, encoded on the first read
+    input: Option<Vec<u8>>,
+    read_offset: usize,
+    output: Vec<u8>,
+    output_limit: usize,
+    logs: Vec<String>,
+}
+
+impl<'a> GuestIo<'a> {
+    /// State for running with `context`, accepting up to `output_limit` bytes on stdout
+    pub fn new(context: &'a ExecutionContext, output_limit: usize) -> Self {
+        Self {
+            context,
+            input: None,
+            read_offset: 0,
+            output: Vec::new(),
+            output_limit,
+            logs: Vec::new(),
+        }
+    }
+
+    pub fn slot(&self) -> u64 {
+        self.context.slot
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        self.context.timestamp
+    }
+
+    /// Read up to `len` bytes from `fd` into the module's buffer at `buf`
+    ///
+    /// Returns the bytes read, 0 at the end of the stream, or a negative
+    /// error code for the module.
+    pub fn read<M: GuestMemory + ?Sized>(
+        &mut self,
+        fd: i32,
+        buf: u32,
+        len: usize,
+        memory: &mut M,
+    ) -> Result<i32, VMExecutionError> {
+        if fd != STDIN {
+            return Ok(EBADF);
+        }
+        let input = match &mut self.input {
+            Some(input) => input,
+            slot => {
+                let encoded = borsh::to_vec(self.context).map_err(|e| {
+                    VMExecutionError::SerializationError(format!("Context serialization failed: {}", e))
+                })?;
+                let mut input = (encoded.len() as u32).to_le_bytes().to_vec();
+                input.extend_from_slice(&encoded);
+                slot.insert(input)
+            }
+        };
+
+        let remaining = &input[self.read_offset..];
+        let count = len.min(remaining.len());
+        if count == 0 {
+            return Ok(0);
+        }
+        let Some(dest) = memory.guest_bytes_mut(buf, count) else {
+            return Ok(EFAULT);
+        };
+        dest.copy_from_slice(&remaining[..count]);
+        self.read_offset += count;
+        Ok(count as i32)
+    }
+
+    /// Write up to `len` bytes from the module's buffer at `buf` to `fd`
+    ///
+    /// Returns the bytes written or a negative error code for the module.
+    pub fn write<M: GuestMemory + ?Sized>(&mut self, fd: i32, buf: u32, len: usize, memory: &M) -> i32 {
+        match fd {
+            STDOUT => {
+                let space = self.output_limit - self.output.len();
+                if len > 0 && space == 0 {
+                    return EFBIG;
+                }
+                let count = len.min(space);
+                if count == 0 {
+                    return 0;
+                }
+                match memory.guest_bytes(buf, count) {
+                    Some(src) => {
+                        self.output.extend_from_slice(src);
+                        count as i32
+                    }
+                    None => EFAULT,
+                }
+            }
+            // Logged in full as far as kept, so the module never retries
+            STDERR => match self.log(buf, len, memory) {
+                0 => len as i32,
+                error => error,
+            },
+            _ => EBADF,
+        }
+    }
+
+    /// Log `len` bytes of UTF-8 from the module's buffer at `buf`
+    ///
+    /// Returns 0, or a negative error code for the module.
+    pub fn log<M: GuestMemory + ?Sized>(&mut self, buf: u32, len: usize, memory: &M) -> i32 {
+        let len = len.min(MAX_LOG_BYTES);
+        let bytes = if len == 0 {
+            &[][..]
+        } else {
+            match memory.guest_bytes(buf, len) {
+                Some(bytes) => bytes,
+                None => return EFAULT,
+            }
+        };
+        let message = String::from_utf8_lossy(bytes).into_owned();
+        log::info!(target: LOG_TARGET, "{}", message);
+        self.logs.push(message);
+        0
+    }
+
+    /// Effects and events written to stdout, if the module wrote any
+    ///
+    /// Events follow the effects inside the frame; a frame ending after
+    /// the effects has none.
+    pub fn effects(&self) -> Option<Result<GuestOutput, VMExecutionError>> {
+        if self.output.is_empty() {
+            return None;
+        }
+        let invalid = |reason: &str| VMExecutionError::SerializationError(format!("Invalid effects on stdout: {}", reason));
+        let decoded = match self.output.split_first_chunk::<4>() {
+            Some((len, body)) if u32::from_le_bytes(*len) as usize == body.len() => {
+                decode_output(body).map_err(|e| invalid(&e.to_string()))
+            }
+            Some((len, body)) => Err(invalid(&format!(
+                "{} bytes announced, {} written",
+                u32::from_le_bytes(*len),
+                body.len()
+            ))),
+            None => Err(invalid("truncated length prefix")),
+        };
+        Some(decoded)
+    }
+
+    /// Messages the module logged, in order
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn logs(&self) -> &[String] {
+        &self.logs
+    }
+}
+
+/// Effects, then the events that follow them, from the body of a stdout frame
+fn decode_output(mut body: &[u8]) -> borsh::io::Result<GuestOutput> {
+    let effects = Vec::<ObjectEffect>::deserialize(&mut body)?;
+    let events = if body.is_empty() { Vec::new() } else { borsh::from_slice(body)? };
+    Ok((effects, events))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use units_core_types::{Instruction, UnitsObjectId};
+
+    #[test]
+    fn test_context_streams_from_stdin_and_effects_from_stdout() {
+        let instruction = Instruction::new(UnitsObjectId::new([1; 32]), "run".to_string(), vec![], vec![7]);
+        let context = ExecutionContext::new(instruction, HashMap::new(), 2, 3);
+        let mut io = GuestIo::new(&context, 64);
+        let mut memory = vec![0u8; 1024];
+
+        // The stream is the length prefix and the Borsh context, read in any chunks
+        let encoded = borsh::to_vec(&context).unwrap();
+        let mut stream = Vec::new();
+        loop {
+            let read = io.read(STDIN, 0, 5, memory.as_mut_slice()).unwrap();
+            if read == 0 {
+                break;
+            }
+            stream.extend_from_slice(&memory[..read as usize]);
+        }
+        assert_eq!(stream[..4], (encoded.len() as u32).to_le_bytes());
+        assert_eq!(stream[4..], encoded[..]);
+
+        // Bad descriptors and buffers outside memory are refused
+        assert_eq!(io.read(STDOUT, 0, 4, memory.as_mut_slice()).unwrap(), EBADF);
+        let mut fresh = GuestIo::new(&context, 64);
+        assert_eq!(fresh.read(STDIN, 1022, 4, memory.as_mut_slice()).unwrap(), EFAULT);
+        assert_eq!(io.write(3, 0, 4, memory.as_slice()), EBADF);
+
+        // Logs go through stderr and the log call alike
+        memory[..5].copy_from_slice(b"hello");
+        assert_eq!(io.write(STDERR, 0, 5, memory.as_slice()), 5);
+        assert_eq!(io.log(0, 2, memory.as_slice()), 0);
+        assert_eq!(io.log(1020, 8, memory.as_slice()), EFAULT);
+        assert_eq!(io.logs(), ["hello", "he"]);
+
+        // Effects are framed like the context
+        assert!(io.effects().is_none());
+        let body = borsh::to_vec(&Vec::<ObjectEffect>::new()).unwrap();
+        let mut framed = (body.len() as u32).to_le_bytes().to_vec();
+        framed.extend(&body);
+        memory[..framed.len()].copy_from_slice(&framed);
+        assert_eq!(io.write(STDOUT, 0, framed.len(), memory.as_slice()) as usize, framed.len());
+        assert!(io.effects().unwrap().unwrap().0.is_empty());
+
+        // Output stops at the limit
+        assert_eq!(io.write(STDOUT, 0, 100, memory.as_slice()) as usize, 64 - framed.len());
+        assert_eq!(io.write(STDOUT, 0, 1, memory.as_slice()), EFBIG);
+        assert!(io.effects().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_events_follow_effects_in_the_frame() {
+        let instruction = Instruction::new(UnitsObjectId::new([1; 32]), "run".to_string(), vec![], vec![]);
+        let context = ExecutionContext::new(instruction, HashMap::new(), 2, 3);
+        let events = vec![KernelEvent::new(UnitsObjectId::new([4; 32]), "Transfer", vec![9])];
+        let frame = |trailer: &[u8]| {
+            let mut body = borsh::to_vec(&Vec::<ObjectEffect>::new()).unwrap();
+            body.extend(trailer);
+            let mut framed = (body.len() as u32).to_le_bytes().to_vec();
+            framed.extend(body);
+            framed
+        };
+
+        let mut io = GuestIo::new(&context, 256);
+        let framed = frame(&borsh::to_vec(&events).unwrap());
+        io.write(STDOUT, 0, framed.len(), framed.as_slice());
+        let (effects, decoded) = io.effects().unwrap().unwrap();
+        assert!(effects.is_empty());
+        assert_eq!(decoded, events);
+
+        // Bytes after the effects that are not events are refused
+        let mut io = GuestIo::new(&context, 256);
+        let framed = frame(&[0xff]);
+        io.write(STDOUT, 0, framed.len(), framed.as_slice());
+        assert!(io.effects().unwrap().is_err());
+    }
+}
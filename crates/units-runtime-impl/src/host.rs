@@ -0,0 +1,262 @@
+//! Host environment seen by executing programs
+//!
+//! Everything the runtime takes from the machine it runs on goes through a
+//! [`HostEnvironment`]: the clock behind execution timeouts, the entropy
+//! source, and handlers for `ecall`s beyond the executor's built-in
+//! syscalls. The default environment uses the system clock, seeds its
+//! entropy from the time and registers no handlers, so any `ecall` that is
+//! not built in halts the program.
+//!
+//! Unit tests build their own environment to drive time by hand, get
+//! repeatable random values and stub out syscalls:
+//!
+//! ```
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use units_runtime_impl::{FakeClock, HostEnvironment, MockRuntime};
+//!
+//! let clock = Arc::new(FakeClock::new(1_700_000_000_000));
+//! let host = HostEnvironment::builder()
+//!     .clock(clock.clone())
+//!     .seed(7)
+//!     .syscall(1000, |regs| regs[10] = 42)
+//!     .build();
+//! let runtime = MockRuntime::new().with_host(host);
+//! clock.advance(Duration::from_secs(1));
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use units_core_types::id::UnitsObjectId;
+
+/// Register holding the syscall number on `ecall`
+pub const REG_SYSCALL: usize = 17;
+
+/// Source of the current time
+pub trait HostClock: Send + Sync {
+    /// Milliseconds since the Unix epoch
+    fn now_millis(&self) -> u64;
+}
+
+/// Clock reading the machine's wall time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl HostClock for SystemClock {
+    fn now_millis(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or(0)
+    }
+}
+
+/// Clock that only moves when told to
+#[derive(Debug, Default)]
+pub struct FakeClock {
+    millis: AtomicU64,
+}
+
+impl FakeClock {
+    pub fn new(millis: u64) -> Self {
+        Self { millis: AtomicU64::new(millis) }
+    }
+
+    pub fn set(&self, millis: u64) {
+        self.millis.store(millis, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.millis.fetch_add(by.as_millis() as u64, Ordering::SeqCst);
+    }
+}
+
+impl HostClock for FakeClock {
+    fn now_millis(&self) -> u64 {
+        self.millis.load(Ordering::SeqCst)
+    }
+}
+
+/// Source of random bytes
+pub trait HostEntropy: Send + Sync {
+    fn fill_bytes(&self, dest: &mut [u8]);
+}
+
+/// Entropy stream fully determined by its seed
+///
+/// Not suitable for anything secret; it exists so tests drawing random
+/// values see the same ones on every run.
+#[derive(Debug)]
+pub struct SeededEntropy {
+    state: AtomicU64,
+}
+
+impl SeededEntropy {
+    pub fn new(seed: u64) -> Self {
+        Self { state: AtomicU64::new(seed) }
+    }
+
+    /// Seeded from the system time, for environments that do not care
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or(0);
+        Self::new(nanos)
+    }
+
+    fn next_u64(&self) -> u64 {
+        // splitmix64
+        let mut z = self
+            .state
+            .fetch_add(0x9e37_79b9_7f4a_7c15, Ordering::Relaxed)
+            .wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl HostEntropy for SeededEntropy {
+    fn fill_bytes(&self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next_u64().to_le_bytes()[..chunk.len()]);
+        }
+    }
+}
+
+/// Handler for one syscall number, given the program's registers
+///
+/// Results go back through the registers (conventionally `a0`); execution
+/// resumes after the `ecall` once the handler returns.
+pub type SyscallHandler = Arc<dyn Fn(&mut [u32; 32]) + Send + Sync>;
+
+/// Clock, entropy and syscalls available to executing programs
+#[derive(Clone)]
+pub struct HostEnvironment {
+    clock: Arc<dyn HostClock>,
+    entropy: Arc<dyn HostEntropy>,
+    syscalls: HashMap<u32, SyscallHandler>,
+}
+
+impl HostEnvironment {
+    /// The real machine: system clock, time-seeded entropy, no syscall handlers
+    pub fn system() -> Self {
+        Self::builder().build()
+    }
+
+    pub fn builder() -> HostEnvironmentBuilder {
+        HostEnvironmentBuilder::default()
+    }
+
+    pub fn now_millis(&self) -> u64 {
+        self.clock.now_millis()
+    }
+
+    #[cfg(feature = "vm")]
+    pub(crate) fn clock(&self) -> &dyn HostClock {
+        self.clock.as_ref()
+    }
+
+    pub fn random_bytes<const N: usize>(&self) -> [u8; N] {
+        let mut bytes = [0u8; N];
+        self.entropy.fill_bytes(&mut bytes);
+        bytes
+    }
+
+    pub fn random_id(&self) -> UnitsObjectId {
+        UnitsObjectId::new(self.random_bytes())
+    }
+
+    /// Handler registered for syscall `number`, if any
+    pub fn syscall(&self, number: u32) -> Option<&SyscallHandler> {
+        self.syscalls.get(&number)
+    }
+}
+
+impl Default for HostEnvironment {
+    fn default() -> Self {
+        Self::system()
+    }
+}
+
+impl fmt::Debug for HostEnvironment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut syscalls: Vec<_> = self.syscalls.keys().collect();
+        syscalls.sort();
+        f.debug_struct("HostEnvironment")
+            .field("now_millis", &self.now_millis())
+            .field("syscalls", &syscalls)
+            .finish()
+    }
+}
+
+/// Builder for a [`HostEnvironment`], defaulting to the system's
+#[derive(Default)]
+pub struct HostEnvironmentBuilder {
+    clock: Option<Arc<dyn HostClock>>,
+    entropy: Option<Arc<dyn HostEntropy>>,
+    syscalls: HashMap<u32, SyscallHandler>,
+}
+
+impl HostEnvironmentBuilder {
+    pub fn clock(mut self, clock: Arc<dyn HostClock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    pub fn entropy(mut self, entropy: Arc<dyn HostEntropy>) -> Self {
+        self.entropy = Some(entropy);
+        self
+    }
+
+    /// Deterministic entropy from `seed`
+    pub fn seed(self, seed: u64) -> Self {
+        self.entropy(Arc::new(SeededEntropy::new(seed)))
+    }
+
+    /// Handle `ecall`s with `number` in `a7` instead of halting
+    pub fn syscall(
+        mut self,
+        number: u32,
+        handler: impl Fn(&mut [u32; 32]) + Send + Sync + 'static,
+    ) -> Self {
+        self.syscalls.insert(number, Arc::new(handler));
+        self
+    }
+
+    pub fn build(self) -> HostEnvironment {
+        HostEnvironment {
+            clock: self.clock.unwrap_or_else(|| Arc::new(SystemClock)),
+            entropy: self.entropy.unwrap_or_else(|| Arc::new(SeededEntropy::from_time())),
+            syscalls: self.syscalls,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_clock_and_seeded_entropy() {
+        let clock = Arc::new(FakeClock::new(1_000));
+        let host = HostEnvironment::builder().clock(clock.clone()).seed(7).build();
+        assert_eq!(host.now_millis(), 1_000);
+        clock.advance(Duration::from_millis(250));
+        assert_eq!(host.now_millis(), 1_250);
+
+        // Same seed, same stream; different seed, different stream
+        let again = HostEnvironment::builder().seed(7).build();
+        assert_eq!(host.random_id(), again.random_id());
+        assert_eq!(host.random_bytes::<13>(), again.random_bytes::<13>());
+        let other = HostEnvironment::builder().seed(8).build();
+        assert_ne!(host.random_id(), other.random_id());
+
+        assert!(host.syscall(1000).is_none());
+    }
+}
@@ -1,11 +1,41 @@
+pub mod effect_processors;
+#[cfg(any(feature = "vm", feature = "wasm"))]
+mod guest_io;
+pub mod host;
 pub mod mock_runtime;
+#[cfg(feature = "vm")]
+pub mod riscv_debug;
+#[cfg(feature = "vm")]
 pub mod riscv_executor;
+#[cfg(feature = "vm")]
+mod riscv_memory;
+#[cfg(feature = "vm")]
+mod riscv_syscalls;
+pub mod rvbc;
 pub mod verification;
+#[cfg(feature = "wasm")]
+pub mod wasm_executor;
 
 // Re-export runtime implementations
+pub use effect_processors::{WriteQuota, WriteStats};
+pub use host::{
+    FakeClock, HostClock, HostEntropy, HostEnvironment, HostEnvironmentBuilder, SeededEntropy,
+    SyscallHandler, SystemClock,
+};
 pub use mock_runtime::MockRuntime;
-pub use riscv_executor::{RiscVExecutor, RiscVExecutorConfig};
+#[cfg(feature = "vm")]
+pub use riscv_debug::{
+    DebugAction, DebugCommand, DebugHook, Debugger, ExecutionTrace, TraceEntry, TracedFailure,
+};
+#[cfg(feature = "vm")]
+pub use riscv_executor::{ComputeCostTable, RiscVExecutor, RiscVExecutorConfig};
+/// Guest memory, exposed for the fuzz targets under `fuzz/`
+#[cfg(feature = "fuzzing")]
+pub use riscv_memory::{Permissions, RiscVMemory};
+pub use rvbc::{RvbcError, RvbcHeader, RvbcImage};
 pub use verification::{detect_double_spend, verify_transaction_included, ProofVerifier};
+#[cfg(feature = "wasm")]
+pub use wasm_executor::{WasmExecutor, WasmExecutorConfig};
 
 // Re-export storage implementations for convenience
 pub use units_storage_impl::InMemoryReceiptStorage;
\ No newline at end of file
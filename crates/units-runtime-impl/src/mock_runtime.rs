@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use units_core_types::error::RuntimeError;
 use units_core_types::id::UnitsObjectId;
@@ -6,11 +7,15 @@ use units_core_types::objects::{UnitsObject, VMType};
 use units_core_types::transaction::{
     ConflictResult, Transaction, TransactionHash, TransactionReceipt,
 };
-use units_core_types::SlotNumber;
+use units_core_types::{ContextLimits, ResourceClass, SlotNumber, StorageRentConfig};
 
-use units_core_types::{Runtime, VMExecutor, Verifier};
-use crate::riscv_executor::RiscVExecutor;
+use units_core_types::{EffectProcessor, Runtime, VMExecutor, Verifier};
+use crate::host::HostEnvironment;
+#[cfg(feature = "vm")]
+use crate::riscv_executor::{RiscVExecutor, RiscVExecutorConfig};
 use crate::verification::ProofVerifier;
+#[cfg(feature = "wasm")]
+use crate::wasm_executor::{WasmExecutor, WasmExecutorConfig};
 
 /// Mock implementation of the Runtime trait for testing purposes
 pub struct MockRuntime {
@@ -24,6 +29,14 @@ pub struct MockRuntime {
     objects: HashMap<UnitsObjectId, UnitsObject>,
     /// Verifier for proof and transaction verification
     verifier: ProofVerifier,
+    /// Storage rent pricing (None disables deposit accounting)
+    storage_rent: Option<StorageRentConfig>,
+    /// Limits on execution context size
+    context_limits: ContextLimits,
+    /// Plugins reviewing effects before commit, in registration order
+    effect_processors: Vec<Arc<dyn EffectProcessor>>,
+    /// Clock, entropy and syscalls handed to the VM executors
+    host: HostEnvironment,
 }
 
 impl MockRuntime {
@@ -35,9 +48,37 @@ impl MockRuntime {
             current_slot: 0,
             objects: HashMap::new(),
             verifier: ProofVerifier::new(),
+            storage_rent: None,
+            context_limits: ContextLimits::default(),
+            effect_processors: Vec::new(),
+            host: HostEnvironment::system(),
         }
     }
 
+    /// Enable storage deposit accounting with the given pricing
+    pub fn with_storage_rent(mut self, config: StorageRentConfig) -> Self {
+        self.storage_rent = Some(config);
+        self
+    }
+
+    /// Override the execution context size limits
+    pub fn with_context_limits(mut self, limits: ContextLimits) -> Self {
+        self.context_limits = limits;
+        self
+    }
+
+    /// Run `processor` over each transaction's effects, after those registered before it
+    pub fn with_effect_processor(mut self, processor: Arc<dyn EffectProcessor>) -> Self {
+        self.effect_processors.push(processor);
+        self
+    }
+
+    /// Execute programs against `host` instead of the real machine
+    pub fn with_host(mut self, host: HostEnvironment) -> Self {
+        self.host = host;
+        self
+    }
+
     /// Add a transaction to the mock runtime's transaction store
     pub fn add_transaction(&mut self, transaction: Transaction) {
         self.transactions.insert(transaction.hash, transaction);
@@ -71,9 +112,20 @@ impl MockRuntime {
 
 impl Runtime for MockRuntime {
     fn get_vm_executor(&self, vm_type: VMType) -> Option<Box<dyn VMExecutor>> {
+        self.get_vm_executor_for_class(vm_type, ResourceClass::Standard)
+    }
+
+    #[cfg_attr(not(any(feature = "vm", feature = "wasm")), allow(unused_variables))]
+    fn get_vm_executor_for_class(&self, vm_type: VMType, class: ResourceClass) -> Option<Box<dyn VMExecutor>> {
+        // VMs left out of the build have nothing to execute their programs
         match vm_type {
-            VMType::RiscV => Some(Box::new(RiscVExecutor::new())),
-            _ => Some(Box::new(RiscVExecutor::new())), // Future VM types default to RiscV
+            #[cfg(feature = "vm")]
+            VMType::RiscV => Some(Box::new(
+                RiscVExecutor::with_config(RiscVExecutorConfig::for_class(class)).with_host(self.host.clone()),
+            )),
+            #[cfg(feature = "wasm")]
+            VMType::Wasm => Some(Box::new(WasmExecutor::with_config(WasmExecutorConfig::for_class(class)))),
+            _ => None,
         }
     }
 
@@ -87,6 +139,18 @@ impl Runtime for MockRuntime {
         Ok(ConflictResult::NoConflict)
     }
 
+    fn storage_rent_config(&self) -> Option<StorageRentConfig> {
+        self.storage_rent
+    }
+
+    fn context_limits(&self) -> ContextLimits {
+        self.context_limits
+    }
+
+    fn effect_processors(&self) -> &[Arc<dyn EffectProcessor>] {
+        &self.effect_processors
+    }
+
     fn get_transaction(&self, hash: &TransactionHash) -> Option<Transaction> {
         self.transactions.get(hash).cloned()
     }
@@ -119,7 +183,312 @@ impl Clone for MockRuntime {
             current_slot: self.current_slot,
             objects: self.objects.clone(),
             verifier: ProofVerifier::new(), // Create new verifier instance
+            storage_rent: self.storage_rent,
+            context_limits: self.context_limits,
+            effect_processors: self.effect_processors.clone(),
+            host: self.host.clone(),
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use units_core_types::transaction::Instruction;
+    use units_core_types::VMExecutionError;
+
+    #[test]
+    fn test_execute_instruction_enforces_context_limits() {
+        let runtime = MockRuntime::new().with_context_limits(ContextLimits {
+            max_objects: 1,
+            ..ContextLimits::default()
+        });
+
+        let controller = UnitsObjectId::new([1; 32]);
+        let objects: HashMap<_, _> = (0..2u8)
+            .map(|i| {
+                let id = UnitsObjectId::new([i; 32]);
+                (id, UnitsObject::new_data(id, controller, vec![]))
+            })
+            .collect();
+        let instruction = Instruction::new(controller, "test".to_string(), vec![], vec![]);
+
+        let result = runtime.execute_instruction(&instruction, objects, 1, 2);
+        assert!(matches!(result, Err(VMExecutionError::TooManyObjects(_))));
+    }
+
+    #[test]
+    fn test_effect_processors_annotate_and_veto() {
+        use crate::effect_processors::{WriteQuota, WriteStats};
+        use units_core_types::TransactionView;
+
+        let load = |_: &UnitsObjectId| Ok(None);
+        let transaction = Transaction::new(vec![], [1; 32]);
+
+        let runtime = MockRuntime::new().with_effect_processor(Arc::new(WriteStats));
+        let mut view = TransactionView::new(&load);
+        let receipt = runtime.execute_transaction_atomic(&transaction, &mut view, 1, 2).unwrap();
+        assert!(receipt.success);
+        let annotated: Vec<_> = receipt.annotations.iter().map(|note| (note.key.as_str(), note.value.as_str())).collect();
+        assert_eq!(annotated, [("created", "0"), ("modified", "0"), ("deleted", "0"), ("bytes_written", "0")]);
+
+        // A veto fails the transaction, and later processors do not run
+        let runtime = MockRuntime::new()
+            .with_effect_processor(Arc::new(WriteQuota { max_effects: Some(0), max_bytes_written: None }))
+            .with_effect_processor(Arc::new(Vetoing))
+            .with_effect_processor(Arc::new(WriteStats));
+        let mut view = TransactionView::new(&load);
+        let receipt = runtime.execute_transaction_atomic(&transaction, &mut view, 1, 2).unwrap();
+        assert!(!receipt.success);
+        assert_eq!(receipt.error_message.as_deref(), Some("Vetoed by compliance: sanctioned"));
+        assert!(receipt.annotations.is_empty());
+    }
+
+    #[test]
+    fn test_account_authorizers_approve_or_deny_instructions() {
+        use units_core_types::{AuthorizerRegistry, TransactionView};
+
+        let wallet_controller = UnitsObjectId::new([1; 32]);
+        let authorizer = UnitsObjectId::new([2; 32]);
+        let wallet = UnitsObject::new_data(UnitsObjectId::new([3; 32]), wallet_controller, vec![]);
+        let vault = UnitsObject::new_data(UnitsObjectId::new([4; 32]), *wallet.id(), vec![]);
+        let mut registry = AuthorizerRegistry::default();
+        registry.set(*wallet.id(), Some(authorizer));
+        let registry = registry.to_object().unwrap();
+        let stored = [wallet.clone(), vault.clone(), registry];
+        let load = |id: &UnitsObjectId| Ok(stored.iter().find(|object| object.id() == id).cloned());
+
+        let runtime = Authorizing(MockRuntime::new());
+        let transfer = Instruction::new(wallet_controller, "transfer".to_string(), vec![*vault.id()], vec![]);
+        let mut view = TransactionView::new(&load);
+        let receipt = runtime
+            .execute_transaction_atomic(&Transaction::new(vec![transfer], [1; 32]), &mut view, 1, 2)
+            .unwrap();
+        assert!(receipt.success, "{:?}", receipt.error_message);
+        // The authorizer bumped the wallet's nonce before the transfer ran
+        assert_eq!(view.get(wallet.id()).unwrap().unwrap().data, vec![1]);
+        assert_eq!(view.get(vault.id()).unwrap().unwrap().data, vec![1]);
+        assert_eq!((receipt.effects.len(), receipt.instruction_metrics.len()), (2, 1));
+        // Events are attributed to the controller that ran, authorizer first
+        let events: Vec<_> = receipt.events.iter().map(|event| (event.controller_id, event.object_id)).collect();
+        assert_eq!(events, [(authorizer, *wallet.id()), (wallet_controller, *vault.id())]);
+        assert!(receipt.events.iter().all(|event| event.transaction_hash == receipt.transaction_hash));
+
+        let drain = Instruction::new(wallet_controller, "drain".to_string(), vec![*vault.id()], vec![]);
+        let mut view = TransactionView::new(&load);
+        let receipt = runtime
+            .execute_transaction_atomic(&Transaction::new(vec![drain], [2; 32]), &mut view, 1, 2)
+            .unwrap();
+        assert!(!receipt.success);
+        assert!(view.is_empty() && receipt.effects.is_empty() && receipt.events.is_empty());
+        assert_eq!(receipt.failure.as_ref().map(|failure| failure.controller_id), Some(authorizer));
+        assert!(receipt.error_message.unwrap().contains("Authorizer"));
+    }
+
+    #[test]
+    fn test_transactions_pay_storage_deposits() {
+        use units_core_types::{DepositLedger, TransactionView, DEPOSIT_LEDGER_ID};
+
+        let controller_id = UnitsObjectId::new([5; 32]);
+        let controller = UnitsObject::new_executable(controller_id, controller_id, VMType::RiscV, vec![]);
+        let created = UnitsObjectId::new([6; 32]);
+        let create = Transaction::new(
+            vec![Instruction::new(controller_id, "create".to_string(), vec![created], vec![0; 40])],
+            [1; 32],
+        );
+        let runtime = Creating(MockRuntime::new().with_storage_rent(StorageRentConfig::new(10, 1)));
+        let ledger = |balance| {
+            let mut ledger = DepositLedger::default();
+            ledger.fund(controller_id, 1000);
+            ledger.fund(CREATED_OWNER, balance);
+            ledger.to_object().unwrap()
+        };
+
+        // The new object's owner, not the controller creating it, pays the
+        // 50 its 40 bytes require
+        let stored = [controller.clone(), ledger(60)];
+        let load = |id: &UnitsObjectId| Ok(stored.iter().find(|object| object.id() == id).cloned());
+        let mut view = TransactionView::new(&load);
+        let receipt = runtime.execute_transaction_atomic(&create, &mut view, 1, 2).unwrap();
+        assert!(receipt.success, "{:?}", receipt.error_message);
+        assert!(receipt.effects.iter().any(|effect| effect.object_id == DEPOSIT_LEDGER_ID));
+        let charged = DepositLedger::from_object(&view.get(&DEPOSIT_LEDGER_ID).unwrap().unwrap()).unwrap();
+        assert_eq!((charged.balance_of(&CREATED_OWNER), charged.deposit_held(&created)), (10, 50));
+        assert_eq!(charged.balance_of(&controller_id), 1000);
+
+        // An underfunded owner fails the transaction and writes nothing
+        let stored = [controller, ledger(49)];
+        let load = |id: &UnitsObjectId| Ok(stored.iter().find(|object| object.id() == id).cloned());
+        let mut view = TransactionView::new(&load);
+        let receipt = runtime.execute_transaction_atomic(&create, &mut view, 1, 2).unwrap();
+        assert!(!receipt.success);
+        assert!(view.is_empty() && receipt.effects.is_empty());
+        assert!(receipt.error_message.unwrap().contains("Insufficient storage deposit"));
+    }
+
+    #[cfg(feature = "vm")]
+    #[test]
+    fn test_controllers_run_within_their_resource_class() {
+        use units_core_types::{ModuleRegistry, TransactionView};
+
+        // lui t0, 0x31; loop: addi t0, t0, -1; bnez t0, loop; ecall
+        let mut bytecode = b"RVBC".to_vec();
+        bytecode.extend_from_slice(&0u32.to_le_bytes());
+        for instruction in [0x0003_12b7u32, 0xfff2_8293, 0xfe02_9ee3, 0x0000_0073] {
+            bytecode.extend_from_slice(&instruction.to_le_bytes());
+        }
+        let controller_id = UnitsObjectId::new([5; 32]);
+        let controller = UnitsObject::new_executable(controller_id, controller_id, VMType::RiscV, bytecode);
+        let transaction = Transaction::new(vec![Instruction::new(controller_id, "crunch".to_string(), vec![], vec![])], [1; 32]);
+        let runtime = MockRuntime::new();
+
+        // About 400k instructions fit the standard budget but not the small one
+        for (class, fits) in [(ResourceClass::Standard, true), (ResourceClass::Small, false)] {
+            let mut registry = ModuleRegistry::default();
+            registry.record_deployment(&controller, 1);
+            assert!(registry.set_resource_class(&controller_id, class));
+            let stored = [controller.clone(), registry.to_object().unwrap()];
+            let load = |id: &UnitsObjectId| Ok(stored.iter().find(|object| object.id() == id).cloned());
+            let mut view = TransactionView::new(&load);
+            let receipt = runtime.execute_transaction_atomic(&transaction, &mut view, 1, 2).unwrap();
+            assert_eq!(receipt.success, fits, "{:?}: {:?}", class, receipt.error_message);
+        }
+    }
+
+    /// Appends a byte to every target, emitting an event for each; as an
+    /// authorizer, denies draining
+    struct Authorizing(MockRuntime);
+
+    impl Runtime for Authorizing {
+        fn get_vm_executor(&self, vm_type: VMType) -> Option<Box<dyn VMExecutor>> {
+            self.0.get_vm_executor(vm_type)
+        }
+
+        fn execute_transaction(&self, transaction: Transaction) -> TransactionReceipt {
+            self.0.execute_transaction(transaction)
+        }
+
+        fn execute_instruction_in_class(
+            &self,
+            instruction: &Instruction,
+            objects: HashMap<UnitsObjectId, UnitsObject>,
+            _class: ResourceClass,
+            _slot: u64,
+            _timestamp: u64,
+        ) -> Result<units_core_types::ExecutionOutput, VMExecutionError> {
+            if instruction.target_function == units_core_types::AUTHORIZE_FUNCTION {
+                let request = units_core_types::AuthorizationRequest::from_params(&instruction.params)
+                    .map_err(|err| VMExecutionError::SerializationError(err.to_string()))?;
+                if request.instruction.target_function == "drain" {
+                    return Err(VMExecutionError::ModuleError(7));
+                }
+            }
+            let effects: Vec<_> = instruction
+                .target_objects
+                .iter()
+                .filter_map(|id| objects.get(id))
+                .map(|before| {
+                    let mut after = before.clone();
+                    after.data.push(1);
+                    units_core_types::ObjectEffect::modification(before.clone(), after)
+                })
+                .collect();
+            let events = effects
+                .iter()
+                .map(|effect| units_core_types::KernelEvent::new(effect.object_id, "Appended", vec![1]))
+                .collect();
+            Ok(units_core_types::ExecutionOutput { effects, events, ..Default::default() })
+        }
+
+        fn get_transaction(&self, hash: &TransactionHash) -> Option<Transaction> {
+            self.0.get_transaction(hash)
+        }
+
+        fn get_transaction_receipt(&self, hash: &TransactionHash) -> Option<TransactionReceipt> {
+            self.0.get_transaction_receipt(hash)
+        }
+
+        fn rollback_transaction(&self, hash: &TransactionHash) -> Result<bool, RuntimeError> {
+            self.0.rollback_transaction(hash)
+        }
+
+        fn get_verifier(&self) -> &dyn Verifier {
+            self.0.get_verifier()
+        }
+    }
+
+    /// Runs every controller as [`CreateTargets`], leaving rent accounting
+    /// to the runtime
+    struct Creating(MockRuntime);
+
+    impl Runtime for Creating {
+        fn get_vm_executor(&self, _vm_type: VMType) -> Option<Box<dyn VMExecutor>> {
+            Some(Box::new(CreateTargets))
+        }
+
+        fn execute_transaction(&self, transaction: Transaction) -> TransactionReceipt {
+            self.0.execute_transaction(transaction)
+        }
+
+        fn storage_rent_config(&self) -> Option<StorageRentConfig> {
+            self.0.storage_rent_config()
+        }
+
+        fn get_transaction(&self, hash: &TransactionHash) -> Option<Transaction> {
+            self.0.get_transaction(hash)
+        }
+
+        fn get_transaction_receipt(&self, hash: &TransactionHash) -> Option<TransactionReceipt> {
+            self.0.get_transaction_receipt(hash)
+        }
+
+        fn rollback_transaction(&self, hash: &TransactionHash) -> Result<bool, RuntimeError> {
+            self.0.rollback_transaction(hash)
+        }
+
+        fn get_verifier(&self) -> &dyn Verifier {
+            self.0.get_verifier()
+        }
+    }
+
+    /// Owner of the objects [`CreateTargets`] creates
+    const CREATED_OWNER: UnitsObjectId = UnitsObjectId::new([7; 32]);
+
+    /// Creates every target, holding the instruction's params and owned by
+    /// [`CREATED_OWNER`]
+    struct CreateTargets;
+
+    impl VMExecutor for CreateTargets {
+        fn vm_type(&self) -> VMType {
+            VMType::RiscV
+        }
+
+        fn load_and_execute(
+            &self,
+            _bytecode: &[u8],
+            context: &units_core_types::ExecutionContext,
+        ) -> Result<Vec<units_core_types::ObjectEffect>, VMExecutionError> {
+            let instruction = &context.instruction;
+            Ok(instruction
+                .target_objects
+                .iter()
+                .map(|id| {
+                    let object = UnitsObject::new_data(*id, CREATED_OWNER, instruction.params.clone());
+                    units_core_types::ObjectEffect::creation(object)
+                })
+                .collect())
+        }
+    }
+
+    struct Vetoing;
+
+    impl EffectProcessor for Vetoing {
+        fn name(&self) -> &str {
+            "compliance"
+        }
+
+        fn process(&self, _transaction: &Transaction, _receipt: &TransactionReceipt) -> units_core_types::EffectVerdict {
+            units_core_types::EffectVerdict::Veto("sanctioned".to_string())
+        }
+    }
+}
@@ -0,0 +1,316 @@
+//! Instruction tracing and debugger hooks for the RISC-V executor
+//!
+//! When tracing is enabled the executor records every executed instruction,
+//! the registers it changed and any syscalls into a bounded ring buffer,
+//! which is handed back alongside the error when execution fails. A
+//! [`DebugHook`] can additionally inspect and stop execution before each
+//! instruction; [`Debugger`] implements breakpoints and single-stepping on
+//! top of it.
+
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
+
+use rvsim::{CpuError, Op};
+use units_core_types::VMExecutionError;
+
+/// Register used to pass the syscall number to `ecall`
+const REG_A7: usize = 17;
+
+/// ABI names of the integer registers, indexed by register number
+const REGISTER_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4", "a5",
+    "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4", "t5", "t6",
+];
+
+/// A single register changed by an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterWrite {
+    /// Register number (0-31)
+    pub register: u8,
+    /// Value before the instruction
+    pub old: u32,
+    /// Value after the instruction
+    pub new: u32,
+}
+
+/// Record of one executed instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// Address the instruction was fetched from
+    pub pc: u32,
+    /// Decoded instruction, if it could be fetched and decoded
+    pub op: Option<Op>,
+    /// Registers changed by the instruction
+    pub register_writes: Vec<RegisterWrite>,
+    /// Syscall number (`a7`) if the instruction was an `ecall`
+    pub syscall: Option<u32>,
+}
+
+impl TraceEntry {
+    pub(crate) fn new(
+        pc: u32,
+        op: Option<Op>,
+        stop: Option<CpuError>,
+        before: &[u32; 32],
+        after: &[u32; 32],
+    ) -> Self {
+        let register_writes = (0..32)
+            .filter(|&reg| before[reg] != after[reg])
+            .map(|reg| RegisterWrite {
+                register: reg as u8,
+                old: before[reg],
+                new: after[reg],
+            })
+            .collect();
+
+        Self {
+            pc,
+            op,
+            register_writes,
+            syscall: (stop == Some(CpuError::Ecall)).then_some(after[REG_A7]),
+        }
+    }
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#010x}  ", self.pc)?;
+        match &self.op {
+            Some(op) => write!(f, "{:?}", op)?,
+            None => write!(f, "<invalid>")?,
+        }
+        for write in &self.register_writes {
+            write!(
+                f,
+                "  {}: {:#x} -> {:#x}",
+                REGISTER_NAMES[write.register as usize], write.old, write.new
+            )?;
+        }
+        if let Some(number) = self.syscall {
+            write!(f, "  [syscall {}]", number)?;
+        }
+        Ok(())
+    }
+}
+
+/// Ring buffer of the most recently executed instructions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionTrace {
+    entries: VecDeque<TraceEntry>,
+    capacity: usize,
+    total_steps: u64,
+}
+
+impl ExecutionTrace {
+    /// Create a trace keeping the last `capacity` instructions (0 disables recording)
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity.min(4096)),
+            capacity,
+            total_steps: 0,
+        }
+    }
+
+    /// Whether instructions are being recorded
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Append an entry, evicting the oldest when full
+    pub(crate) fn record(&mut self, entry: TraceEntry) {
+        self.total_steps += 1;
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// Retained entries, oldest first
+    pub fn entries(&self) -> impl Iterator<Item = &TraceEntry> {
+        self.entries.iter()
+    }
+
+    /// Most recently executed instruction
+    pub fn last(&self) -> Option<&TraceEntry> {
+        self.entries.back()
+    }
+
+    /// Number of retained entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no entries are retained
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Total instructions executed, including evicted entries
+    pub fn total_steps(&self) -> u64 {
+        self.total_steps
+    }
+}
+
+impl fmt::Display for ExecutionTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dropped = self.total_steps - self.entries.len() as u64;
+        if dropped > 0 {
+            writeln!(f, "... {} earlier instructions omitted", dropped)?;
+        }
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+/// Failed execution together with the instructions leading up to it
+#[derive(Debug)]
+pub struct TracedFailure {
+    /// The execution error
+    pub error: VMExecutionError,
+    /// Trace recorded up to the failure (empty if tracing is disabled)
+    pub trace: ExecutionTrace,
+}
+
+impl fmt::Display for TracedFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.error)?;
+        write!(f, "{}", self.trace)
+    }
+}
+
+impl std::error::Error for TracedFailure {}
+
+/// What the executor should do after consulting a debug hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugAction {
+    /// Execute the next instruction
+    Continue,
+    /// Stop execution with an error
+    Abort,
+}
+
+/// Hook invoked by the executor before every instruction
+pub trait DebugHook {
+    /// Inspect the CPU before the instruction at `pc` executes
+    fn before_step(&mut self, pc: u32, registers: &[u32; 32], trace: &ExecutionTrace) -> DebugAction;
+}
+
+/// Command returned by a [`Debugger`] stop handler
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugCommand {
+    /// Run until the next breakpoint
+    Continue,
+    /// Execute one instruction and stop again
+    Step,
+    /// Stop execution
+    Abort,
+}
+
+/// Breakpoint and single-step debugger
+///
+/// Calls `on_stop` whenever execution reaches a breakpoint or, while
+/// single-stepping, before every instruction.
+pub struct Debugger<F> {
+    breakpoints: BTreeSet<u32>,
+    stepping: bool,
+    on_stop: F,
+}
+
+impl<F> Debugger<F>
+where
+    F: FnMut(u32, &[u32; 32], &ExecutionTrace) -> DebugCommand,
+{
+    /// Create a debugger with no breakpoints
+    pub fn new(on_stop: F) -> Self {
+        Self {
+            breakpoints: BTreeSet::new(),
+            stepping: false,
+            on_stop,
+        }
+    }
+
+    /// Stop before executing the instruction at `pc`
+    pub fn add_breakpoint(&mut self, pc: u32) {
+        self.breakpoints.insert(pc);
+    }
+
+    /// Remove a breakpoint, returning whether it was set
+    pub fn remove_breakpoint(&mut self, pc: u32) -> bool {
+        self.breakpoints.remove(&pc)
+    }
+
+    /// Stop before the very first instruction and every one after it
+    pub fn single_step(mut self) -> Self {
+        self.stepping = true;
+        self
+    }
+}
+
+impl<F> DebugHook for Debugger<F>
+where
+    F: FnMut(u32, &[u32; 32], &ExecutionTrace) -> DebugCommand,
+{
+    fn before_step(&mut self, pc: u32, registers: &[u32; 32], trace: &ExecutionTrace) -> DebugAction {
+        if !self.stepping && !self.breakpoints.contains(&pc) {
+            return DebugAction::Continue;
+        }
+
+        match (self.on_stop)(pc, registers, trace) {
+            DebugCommand::Continue => {
+                self.stepping = false;
+                DebugAction::Continue
+            }
+            DebugCommand::Step => {
+                self.stepping = true;
+                DebugAction::Continue
+            }
+            DebugCommand::Abort => DebugAction::Abort,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(pc: u32) -> TraceEntry {
+        TraceEntry::new(pc, None, None, &[0; 32], &[0; 32])
+    }
+
+    #[test]
+    fn test_ring_buffer_keeps_latest_entries() {
+        let mut trace = ExecutionTrace::new(2);
+        for pc in [0x1000, 0x1004, 0x1008] {
+            trace.record(entry(pc));
+        }
+
+        let pcs: Vec<u32> = trace.entries().map(|entry| entry.pc).collect();
+        assert_eq!(pcs, vec![0x1004, 0x1008]);
+        assert_eq!(trace.total_steps(), 3);
+        assert!(trace.to_string().starts_with("... 1 earlier instructions omitted"));
+
+        let mut disabled = ExecutionTrace::new(0);
+        disabled.record(entry(0x1000));
+        assert!(disabled.is_empty());
+        assert_eq!(disabled.total_steps(), 1);
+    }
+
+    #[test]
+    fn test_entry_records_register_diff_and_syscall() {
+        let before = [0u32; 32];
+        let mut after = before;
+        after[10] = 3;
+        after[REG_A7] = 93;
+
+        let entry = TraceEntry::new(0x1000, None, Some(CpuError::Ecall), &before, &after);
+        assert_eq!(entry.register_writes.len(), 2);
+        assert_eq!(entry.register_writes[0], RegisterWrite { register: 10, old: 0, new: 3 });
+        assert_eq!(entry.syscall, Some(93));
+        assert!(entry.to_string().contains("a0: 0x0 -> 0x3"));
+    }
+}
@@ -6,37 +6,128 @@
 //! ## Raw Bytecode Format
 //! 
 //! The raw bytecode format is a simplified alternative to ELF that allows
-//! direct loading of RISC-V instructions:
+//! direct loading of RISC-V instructions. Images are laid out and validated
+//! as described in [`crate::rvbc`]; the code is loaded at address 0x1000, and
+//! the entry point is calculated as 0x1000 + entry_offset.
 //!
-//! ```text
-//! [4 bytes] Magic: "RVBC" (0x52564243)
-//! [4 bytes] Entry offset: Little-endian u32, must be 4-byte aligned
-//! [N bytes] RISC-V instructions
-//! ```
+//! ## Execution
 //!
-//! The bytecode is loaded at address 0x1000, and the entry point is calculated
-//! as 0x1000 + entry_offset.
+//! Code is mapped read/execute, data and the stack read/write, and the input
+//! buffer read-only. ELF segments (`.text`, `.rodata`, `.data`/`.bss`) are
+//! loaded at their link addresses without relocation, so they must be linked
+//! as a static executable clear of the stack, the buffers and the layout
+//! descriptor. The program halts with `ecall` (or `ebreak`), returning its
+//! exit code in `a0`. Any other CPU fault aborts execution.
+//!
+//! ## Syscalls
+//!
+//! An `ecall` with a syscall number from [`units_types_ffi::syscall`] in
+//! `a7` is serviced by the executor: modules built with the kernel SDK read
+//! the Borsh execution context from stdin, write their effects to stdout,
+//! log, query the slot and timestamp, and exit. Effects written to stdout
+//! take the place of the output buffer. Any other number with a handler in
+//! the executor's [`HostEnvironment`] runs that handler and resumes; the
+//! rest halt the program. The timeout is measured against the
+//! environment's clock.
+//!
+//! ## Metering
+//!
+//! Each executed instruction is charged compute units from the configured
+//! [`ComputeCostTable`], and each serviced syscall a surcharge on top.
+//! Charges depend only on what the program executes, so every node meters
+//! a program identically. Execution stops with
+//! `VMExecutionError::ComputeBudgetExceeded` once the units reach the
+//! budget; the units consumed are reported in the execution metrics.
+//!
+//! ## Memory Layout
+//!
+//! Buffer and stack placement comes from the configured [`MemoryLayout`].
+//! Its descriptor is mapped read-only at the well-known [`MEMORY_LAYOUT_ADDR`],
+//! so modules built against the SDK find their buffers without hard-coding
+//! addresses. Registers other than `sp` start zeroed, as before.
 
-use units_core_types::{ExecutionContext, ObjectEffect, VMExecutionError, VMExecutor};
+use units_core_types::{ExecutionContext, ExecutionMetrics, ExecutionOutput, ObjectEffect, ResourceClass, VMExecutionError, VMExecutor};
 use rvsim::*;
-use std::time::Instant;
+use std::time::Duration;
 use units_core_types::objects::VMType;
+use units_types_ffi::layout::{MemoryLayout, MEMORY_LAYOUT_ADDR};
 
-/// RISC-V VM memory layout constants
-const INPUT_BUFFER_ADDR: u32 = 0x10000000;
-const OUTPUT_BUFFER_ADDR: u32 = 0x20000000;
-const MAX_BUFFER_SIZE: u32 = 1024 * 1024; // 1MB limit
-const CODE_BASE_ADDR: u32 = 0x1000; // Base address for loading bytecode
-
-/// Raw bytecode format magic bytes
-const BYTECODE_MAGIC: &[u8; 4] = b"RVBC";
+use crate::host::{HostClock, HostEnvironment, REG_SYSCALL};
+use crate::riscv_debug::{DebugAction, DebugHook, ExecutionTrace, TraceEntry, TracedFailure};
+use crate::riscv_memory::{Permissions, RiscVMemory};
+use crate::guest_io::GuestIo;
+use crate::riscv_syscalls::{self, SyscallOutcome};
+use crate::rvbc::{self, CODE_BASE_ADDR};
 
 /// ELF constants
 const ELF_MAGIC: &[u8; 4] = b"\x7fELF";
 const PT_LOAD: u32 = 1; // Loadable segment type
+const PT_DYNAMIC: u32 = 2; // Dynamic linking information
+const PT_INTERP: u32 = 3; // Program interpreter path
+const ET_DYN: u16 = 3; // Position-independent executable or shared object
 const ELF32_HEADER_SIZE: usize = 52;
 const ELF32_PHDR_SIZE: usize = 32; // Program header size
 
+/// Register holding the exit code when the program halts
+const REG_A0: usize = 10;
+/// Stack pointer register
+const REG_SP: usize = 2;
+/// Instructions executed between wall-clock timeout checks
+const TIMEOUT_CHECK_INTERVAL: u64 = 4096;
+
+/// Compute units charged per executed instruction, by instruction class
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComputeCostTable {
+    /// Arithmetic, logic, shifts, comparisons and immediates
+    pub alu: u64,
+    /// Loads and stores
+    pub memory: u64,
+    /// Branches and jumps
+    pub branch: u64,
+    /// Multiplication
+    pub multiply: u64,
+    /// Division and remainder
+    pub divide: u64,
+    /// Everything else: system instructions, fences, CSR access, atomics and floating point
+    pub other: u64,
+    /// Charged for each syscall a host handler services, on top of its `ecall`
+    pub syscall: u64,
+}
+
+impl Default for ComputeCostTable {
+    fn default() -> Self {
+        Self {
+            alu: 1,
+            memory: 2,
+            branch: 1,
+            multiply: 3,
+            divide: 10,
+            other: 2,
+            syscall: 100,
+        }
+    }
+}
+
+impl ComputeCostTable {
+    /// Units charged for executing `op`
+    pub fn cost(&self, op: &Op) -> u64 {
+        match op {
+            Op::Lui { .. } | Op::Auipc { .. }
+            | Op::Addi { .. } | Op::Slti { .. } | Op::Sltiu { .. } | Op::Xori { .. } | Op::Ori { .. } | Op::Andi { .. }
+            | Op::Slli { .. } | Op::Srli { .. } | Op::Srai { .. }
+            | Op::Add { .. } | Op::Sub { .. } | Op::Sll { .. } | Op::Slt { .. } | Op::Sltu { .. }
+            | Op::Xor { .. } | Op::Srl { .. } | Op::Sra { .. } | Op::Or { .. } | Op::And { .. } => self.alu,
+            Op::Lb { .. } | Op::Lh { .. } | Op::Lw { .. } | Op::Lbu { .. } | Op::Lhu { .. }
+            | Op::Sb { .. } | Op::Sh { .. } | Op::Sw { .. } => self.memory,
+            Op::Jal { .. } | Op::Jalr { .. }
+            | Op::Beq { .. } | Op::Bne { .. } | Op::Blt { .. } | Op::Bge { .. } | Op::Bltu { .. } | Op::Bgeu { .. } => self.branch,
+            Op::Mul { .. } | Op::Mulh { .. } | Op::Mulhsu { .. } | Op::Mulhu { .. } => self.multiply,
+            Op::Div { .. } | Op::Divu { .. } | Op::Rem { .. } | Op::Remu { .. } => self.divide,
+            _ => self.other,
+        }
+    }
+}
+
 /// RISC-V executor configuration
 #[derive(Debug, Clone)]
 pub struct RiscVExecutorConfig {
@@ -44,123 +135,190 @@ pub struct RiscVExecutorConfig {
     pub memory_limit: usize,
     /// Maximum number of instructions to execute
     pub instruction_limit: u64,
+    /// Maximum compute units a program may consume
+    pub compute_budget: u64,
+    /// Compute units charged per instruction and syscall
+    pub costs: ComputeCostTable,
     /// Maximum execution time in milliseconds
     pub timeout_ms: u64,
+    /// Number of executed instructions kept in the trace ring buffer (0 disables tracing)
+    pub trace_capacity: usize,
+    /// Placement of the stack and buffers, advertised to programs through the descriptor
+    pub layout: MemoryLayout,
 }
 
-/// Custom memory implementation for rvsim
-struct RiscVMemory {
-    data: Vec<u8>,
-    memory_limit: usize,
-}
-
-impl RiscVMemory {
-    fn new(memory_limit: usize) -> Self {
+impl Default for RiscVExecutorConfig {
+    fn default() -> Self {
         Self {
-            data: vec![0u8; memory_limit],
-            memory_limit,
+            memory_limit: 16 * 1024 * 1024, // 16MB
+            instruction_limit: 1_000_000,   // 1M instructions
+            compute_budget: 2_000_000,
+            costs: ComputeCostTable::default(),
+            timeout_ms: 5000,               // 5 seconds
+            trace_capacity: 0,              // tracing disabled
+            layout: MemoryLayout::DEFAULT,
         }
     }
-    
-    /// Write bytes to memory at the specified address
-    fn write_bytes(&mut self, addr: u32, bytes: &[u8]) -> Result<(), VMExecutionError> {
-        let addr = addr as usize;
-        if addr + bytes.len() > self.memory_limit {
-            return Err(VMExecutionError::ExecutionFailed("Memory write out of bounds".to_string()));
+}
+
+impl RiscVExecutorConfig {
+    /// Preset budgets of a controller resource class
+    ///
+    /// Standard matches the default configuration; small suits simple
+    /// state transitions and large is meant for analytics-style modules
+    /// an admin has vetted.
+    pub fn for_class(class: ResourceClass) -> Self {
+        match class {
+            ResourceClass::Small => Self {
+                memory_limit: 8 * 1024 * 1024, // 8MB
+                instruction_limit: 250_000,
+                compute_budget: 500_000,
+                timeout_ms: 1000,
+                ..Self::default()
+            },
+            ResourceClass::Standard => Self::default(),
+            ResourceClass::Large => Self {
+                memory_limit: 64 * 1024 * 1024, // 64MB
+                instruction_limit: 20_000_000,
+                compute_budget: 40_000_000,
+                timeout_ms: 30_000,
+                ..Self::default()
+            },
         }
-        self.data[addr..addr + bytes.len()].copy_from_slice(bytes);
-        Ok(())
     }
-    
-    /// Read bytes from memory at the specified address
-    fn read_bytes(&self, addr: u32, len: usize) -> Result<Vec<u8>, VMExecutionError> {
-        let addr = addr as usize;
-        if addr + len > self.memory_limit {
-            return Err(VMExecutionError::ExecutionFailed("Memory read out of bounds".to_string()));
+}
+
+/// Clock enforcing the executor's instruction, compute and wall-clock limits
+struct LimitedClock<'a> {
+    instret: u64,
+    instruction_limit: u64,
+    units: u64,
+    compute_budget: u64,
+    costs: ComputeCostTable,
+    host: &'a dyn HostClock,
+    deadline_millis: u64,
+    timed_out: bool,
+}
+
+impl<'a> LimitedClock<'a> {
+    fn new(config: &RiscVExecutorConfig, host: &'a dyn HostClock) -> Self {
+        let timeout = Duration::from_millis(config.timeout_ms);
+        Self {
+            instret: 0,
+            instruction_limit: config.instruction_limit,
+            units: 0,
+            compute_budget: config.compute_budget,
+            costs: config.costs,
+            host,
+            deadline_millis: host.now_millis().saturating_add(timeout.as_millis() as u64),
+            timed_out: false,
         }
-        Ok(self.data[addr..addr + len].to_vec())
+    }
+
+    /// Charge compute units beyond the executed instructions
+    fn charge(&mut self, units: u64) {
+        self.units = self.units.saturating_add(units);
+    }
+
+    fn budget_exhausted(&self) -> bool {
+        self.units >= self.compute_budget
     }
 }
 
-impl Memory for RiscVMemory {
-    fn access<T: Copy>(&mut self, addr: u32, _access: MemoryAccess<T>) -> bool {
-        let addr = addr as usize;
-        let size = std::mem::size_of::<T>();
-        
-        if addr + size > self.memory_limit {
-            return false;
+impl Clock for LimitedClock<'_> {
+    fn read_cycle(&self) -> u64 {
+        self.instret
+    }
+
+    fn read_time(&self) -> u64 {
+        self.instret
+    }
+
+    fn read_instret(&self) -> u64 {
+        self.instret
+    }
+
+    fn progress(&mut self, op: &Op) {
+        self.instret = self.instret.wrapping_add(1);
+        self.charge(self.costs.cost(op));
+        if self.instret % TIMEOUT_CHECK_INTERVAL == 0 && self.host.now_millis() >= self.deadline_millis {
+            self.timed_out = true;
         }
-        
-        // For now, simplified implementation - just allow all accesses within bounds
-        true
+    }
+
+    fn check_quota(&self) -> bool {
+        !self.timed_out && self.instret < self.instruction_limit && !self.budget_exhausted()
     }
 }
 
-impl Default for RiscVExecutorConfig {
-    fn default() -> Self {
-        Self {
-            memory_limit: 16 * 1024 * 1024, // 16MB
-            instruction_limit: 1_000_000,   // 1M instructions
-            timeout_ms: 5000,               // 5 seconds
-        }
+/// Section kind a segment holds, judged by its permissions
+fn segment_kind(perms: Permissions) -> &'static str {
+    if perms.contains(Permissions::EXEC) {
+        "code"
+    } else if perms.contains(Permissions::WRITE) {
+        "data"
+    } else {
+        "rodata"
     }
 }
 
+/// Read a little-endian `u16` field, failing if it runs past the end of `bytes`
+fn read_u16_le(bytes: &[u8], offset: usize) -> Result<u16, VMExecutionError> {
+    offset
+        .checked_add(2)
+        .and_then(|end| bytes.get(offset..end))
+        .and_then(|field| <[u8; 2]>::try_from(field).ok())
+        .map(u16::from_le_bytes)
+        .ok_or_else(|| VMExecutionError::InvalidBytecode(format!("Truncated field at offset {}", offset)))
+}
+
+/// Read a little-endian `u32` field, failing if it runs past the end of `bytes`
+fn read_u32_le(bytes: &[u8], offset: usize) -> Result<u32, VMExecutionError> {
+    offset
+        .checked_add(4)
+        .and_then(|end| bytes.get(offset..end))
+        .and_then(|field| <[u8; 4]>::try_from(field).ok())
+        .map(u32::from_le_bytes)
+        .ok_or_else(|| VMExecutionError::InvalidBytecode(format!("Truncated field at offset {}", offset)))
+}
+
 /// RISC-V VM executor implementation using rvsim
 pub struct RiscVExecutor {
     config: RiscVExecutorConfig,
+    host: HostEnvironment,
 }
 
 impl RiscVExecutor {
     /// Create a new RISC-V executor with default configuration
     pub fn new() -> Self {
-        Self {
-            config: RiscVExecutorConfig::default(),
-        }
+        Self::with_config(RiscVExecutorConfig::default())
     }
 
     /// Create a new RISC-V executor with custom configuration
     pub fn with_config(config: RiscVExecutorConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            host: HostEnvironment::system(),
+        }
+    }
+
+    /// Run programs against `host` instead of the real machine
+    pub fn with_host(mut self, host: HostEnvironment) -> Self {
+        self.host = host;
+        self
     }
 
 
-    /// Load raw bytecode into machine memory
-    /// Format: [4 bytes magic "RVBC"] [4 bytes entry offset] [N bytes RISC-V instructions]
+    /// Load an RVBC image, of any supported version, into machine memory
     fn load_raw_bytecode(&self, bytecode: &[u8], memory: &mut RiscVMemory) -> Result<u32, VMExecutionError> {
-        // Minimum size: magic (4) + entry offset (4) + at least one instruction (4)
-        if bytecode.len() < 12 {
-            return Err(VMExecutionError::InvalidBytecode("Bytecode too small".to_string()));
-        }
-        
-        // Check magic bytes
-        if &bytecode[0..4] != BYTECODE_MAGIC {
-            return Err(VMExecutionError::InvalidBytecode("Invalid bytecode magic".to_string()));
-        }
-        
-        // Read entry offset (little-endian)
-        let entry_offset = u32::from_le_bytes([
-            bytecode[4], bytecode[5], bytecode[6], bytecode[7]
-        ]);
-        
-        // Get actual code bytes
-        let code_bytes = &bytecode[8..];
-        
-        // Validate entry offset
-        if entry_offset as usize >= code_bytes.len() {
-            return Err(VMExecutionError::InvalidBytecode("Entry offset out of bounds".to_string()));
-        }
-        
-        // Check alignment (RISC-V instructions must be 4-byte aligned)
-        if entry_offset % 4 != 0 {
-            return Err(VMExecutionError::InvalidBytecode("Entry offset must be 4-byte aligned".to_string()));
-        }
+        let image = rvbc::validate(bytecode)?;
         
-        // Load code into memory at CODE_BASE_ADDR
-        memory.write_bytes(CODE_BASE_ADDR, code_bytes)?;
+        // Map code read/execute at CODE_BASE_ADDR and load it
+        memory.map(CODE_BASE_ADDR, image.code.len(), Permissions::RX)?;
+        memory.write_bytes(CODE_BASE_ADDR, image.code)?;
         
-        // Return absolute entry point address
-        Ok(CODE_BASE_ADDR + entry_offset)
+        // Return absolute entry point address (mapping succeeded, so this cannot wrap)
+        Ok(image.entry_point())
     }
 
     /// Load ELF binary into machine memory
@@ -185,17 +343,20 @@ impl RiscVExecutor {
             return Err(VMExecutionError::InvalidBytecode("Only little-endian ELF files are supported".to_string()));
         }
         
-        // Parse ELF header fields
-        let entry_point = u32::from_le_bytes([
-            elf_bytes[24], elf_bytes[25], elf_bytes[26], elf_bytes[27]
-        ]);
-        
-        let phoff = u32::from_le_bytes([
-            elf_bytes[28], elf_bytes[29], elf_bytes[30], elf_bytes[31]
-        ]) as usize;
+        // The loader maps segments at their link addresses and applies no
+        // relocations, so position-independent images cannot run
+        if read_u16_le(elf_bytes, 16)? == ET_DYN {
+            return Err(VMExecutionError::InvalidBytecode(
+                "Position-independent ELF (ET_DYN) needs relocation, which is not supported; \
+                 link a static executable at fixed addresses".to_string()
+            ));
+        }
         
-        let phentsize = u16::from_le_bytes([elf_bytes[42], elf_bytes[43]]) as usize;
-        let phnum = u16::from_le_bytes([elf_bytes[44], elf_bytes[45]]) as usize;
+        // Parse ELF header fields
+        let entry_point = read_u32_le(elf_bytes, 24)?;
+        let phoff = read_u32_le(elf_bytes, 28)? as usize;
+        let phentsize = read_u16_le(elf_bytes, 42)? as usize;
+        let phnum = read_u16_le(elf_bytes, 44)? as usize;
         
         // Validate entry point
         if entry_point == 0 {
@@ -218,83 +379,135 @@ impl RiscVExecutor {
         }
         
         // Check if we have enough data for program headers
-        let ph_end = phoff + (phnum * phentsize);
-        if ph_end > elf_bytes.len() {
+        let ph_end = phoff.checked_add(phnum * phentsize);
+        if ph_end.map_or(true, |end| end > elf_bytes.len()) {
             return Err(VMExecutionError::InvalidBytecode("Program headers extend beyond file".to_string()));
         }
         
-        // Load all PT_LOAD segments
-        let mut loaded_any = false;
+        // Load all PT_LOAD segments, remembering where each landed
+        let mut loaded: Vec<(usize, &str, u32, u64)> = Vec::new();
         for i in 0..phnum {
             let ph_offset = phoff + (i * phentsize);
             
-            // Parse program header
-            let p_type = u32::from_le_bytes([
-                elf_bytes[ph_offset], elf_bytes[ph_offset + 1], 
-                elf_bytes[ph_offset + 2], elf_bytes[ph_offset + 3]
-            ]);
+            // Dynamic linking implies relocations the loader cannot apply
+            let p_type = read_u32_le(elf_bytes, ph_offset)?;
+            if p_type == PT_DYNAMIC || p_type == PT_INTERP {
+                return Err(VMExecutionError::InvalidBytecode(format!(
+                    "Segment {} requests dynamic linking ({}), which is not supported; \
+                     link a static executable with all relocations resolved",
+                    i,
+                    if p_type == PT_DYNAMIC { "PT_DYNAMIC" } else { "PT_INTERP" }
+                )));
+            }
             
-            // Skip non-loadable segments
+            // Skip other non-loadable segments
             if p_type != PT_LOAD {
                 continue;
             }
             
-            let p_offset = u32::from_le_bytes([
-                elf_bytes[ph_offset + 4], elf_bytes[ph_offset + 5],
-                elf_bytes[ph_offset + 6], elf_bytes[ph_offset + 7]
-            ]) as usize;
-            
-            let p_vaddr = u32::from_le_bytes([
-                elf_bytes[ph_offset + 8], elf_bytes[ph_offset + 9],
-                elf_bytes[ph_offset + 10], elf_bytes[ph_offset + 11]
-            ]);
-            
-            let p_filesz = u32::from_le_bytes([
-                elf_bytes[ph_offset + 16], elf_bytes[ph_offset + 17],
-                elf_bytes[ph_offset + 18], elf_bytes[ph_offset + 19]
-            ]) as usize;
-            
-            let p_memsz = u32::from_le_bytes([
-                elf_bytes[ph_offset + 20], elf_bytes[ph_offset + 21],
-                elf_bytes[ph_offset + 22], elf_bytes[ph_offset + 23]
-            ]) as usize;
+            let p_offset = read_u32_le(elf_bytes, ph_offset + 4)? as usize;
+            let p_vaddr = read_u32_le(elf_bytes, ph_offset + 8)?;
+            let p_filesz = read_u32_le(elf_bytes, ph_offset + 16)? as usize;
+            let p_memsz = read_u32_le(elf_bytes, ph_offset + 20)? as usize;
+            let perms = Permissions::from_elf_flags(read_u32_le(elf_bytes, ph_offset + 24)?);
             
             // Validate segment
-            if p_offset + p_filesz > elf_bytes.len() {
-                return Err(VMExecutionError::InvalidBytecode(
+            let segment_data = p_offset
+                .checked_add(p_filesz)
+                .and_then(|end| elf_bytes.get(p_offset..end))
+                .ok_or_else(|| VMExecutionError::InvalidBytecode(
                     format!("Segment {} data extends beyond file", i)
+                ))?;
+            
+            if p_filesz > p_memsz {
+                return Err(VMExecutionError::InvalidBytecode(
+                    format!("Segment {} file size exceeds memory size", i)
                 ));
             }
             
-            // Check memory bounds
-            if p_vaddr as usize + p_memsz > self.config.memory_limit {
-                return Err(VMExecutionError::MemoryLimitExceeded);
+            // Enforce W^X: code segments may not be writable
+            if perms.contains(Permissions::WRITE | Permissions::EXEC) {
+                return Err(VMExecutionError::InvalidBytecode(
+                    format!("Segment {} is both writable and executable", i)
+                ));
             }
             
-            // Load segment data
-            if p_filesz > 0 {
-                let segment_data = &elf_bytes[p_offset..p_offset + p_filesz];
-                memory.write_bytes(p_vaddr, segment_data)?;
+            // Segments are placed where the linker put them, so check the
+            // layout against the runtime regions and earlier segments
+            let kind = segment_kind(perms);
+            let start = p_vaddr as u64;
+            let end = start + p_memsz as u64;
+            if end > 1 << 32 {
+                return Err(VMExecutionError::InvalidBytecode(format!(
+                    "Segment {} ({}) at {:#x} of {} bytes exceeds the address space",
+                    i, kind, p_vaddr, p_memsz
+                )));
             }
             
-            // Zero-fill remaining memory (BSS section)
-            if p_memsz > p_filesz {
-                let zero_start = p_vaddr + p_filesz as u32;
-                let zero_size = p_memsz - p_filesz;
-                let zeros = vec![0u8; zero_size];
-                memory.write_bytes(zero_start, &zeros)?;
+            if p_memsz > 0 {
+                for (region, region_start, region_end) in self.reserved_regions() {
+                    if start < region_end && region_start < end {
+                        return Err(VMExecutionError::InvalidBytecode(format!(
+                            "Segment {} ({}) at {:#x}..{:#x} overlaps the {} at {:#x}..{:#x}; \
+                             relink with a linker script placing it elsewhere",
+                            i, kind, start, end, region, region_start, region_end
+                        )));
+                    }
+                }
+                
+                for &(other, other_kind, other_start, other_end) in &loaded {
+                    if start < other_end && (other_start as u64) < end {
+                        return Err(VMExecutionError::InvalidBytecode(format!(
+                            "Segment {} ({}) at {:#x}..{:#x} overlaps segment {} ({}) at {:#x}..{:#x}",
+                            i, kind, start, end, other, other_kind, other_start, other_end
+                        )));
+                    }
+                }
             }
             
-            loaded_any = true;
+            // Map the segment (zero-filled, so BSS needs no extra work) and load its data
+            memory.map(p_vaddr, p_memsz, perms)?;
+            memory.write_bytes(p_vaddr, segment_data)?;
+            
+            loaded.push((i, kind, p_vaddr, end));
         }
         
-        if !loaded_any {
+        if loaded.is_empty() {
             return Err(VMExecutionError::InvalidBytecode("No loadable segments found".to_string()));
         }
         
         Ok(entry_point)
     }
 
+    /// Address ranges reserved for the runtime, which program segments may not overlap
+    fn reserved_regions(&self) -> [(&'static str, u64, u64); 4] {
+        let layout = &self.config.layout;
+        let buffer = |addr: u32, capacity: u32| (addr as u64 - 4, addr as u64 + capacity as u64);
+        let (input_start, input_end) = buffer(layout.input_addr, layout.input_capacity);
+        let (output_start, output_end) = buffer(layout.output_addr, layout.output_capacity);
+        [
+            ("layout descriptor", MEMORY_LAYOUT_ADDR as u64, MEMORY_LAYOUT_ADDR as u64 + MemoryLayout::ENCODED_SIZE as u64),
+            ("stack", layout.stack_top as u64 - layout.stack_size as u64, layout.stack_top as u64),
+            ("input buffer", input_start, input_end),
+            ("output buffer", output_start, output_end),
+        ]
+    }
+
+    /// Map the layout descriptor, stack and output buffer used by every program
+    fn setup_runtime_regions(&self, memory: &mut RiscVMemory) -> Result<(), VMExecutionError> {
+        let layout = &self.config.layout;
+        let invalid_layout = |region: &str| {
+            VMExecutionError::ExecutionFailed(format!("Invalid memory layout: {} does not fit the address space", region))
+        };
+        let stack_base = layout.stack_top.checked_sub(layout.stack_size).ok_or_else(|| invalid_layout("stack"))?;
+        let output_base = layout.output_addr.checked_sub(4).ok_or_else(|| invalid_layout("output buffer"))?;
+
+        memory.map(MEMORY_LAYOUT_ADDR, MemoryLayout::ENCODED_SIZE, Permissions::READ)?;
+        memory.write_bytes(MEMORY_LAYOUT_ADDR, &layout.to_bytes())?;
+        memory.map(stack_base, layout.stack_size as usize, Permissions::RW)?;
+        memory.map(output_base, layout.output_capacity as usize + 4, Permissions::RW)
+    }
+
     /// Setup input buffer with execution context
     fn setup_input_buffer(
         &self, 
@@ -306,34 +519,40 @@ impl RiscVExecutor {
             .map_err(|e| VMExecutionError::SerializationError(format!("Context serialization failed: {}", e)))?;
         
         // Check if serialized context fits in the buffer
-        if context_bytes.len() > MAX_BUFFER_SIZE as usize {
-            return Err(VMExecutionError::ExecutionFailed(
-                format!("Execution context too large: {} bytes", context_bytes.len())
+        let layout = &self.config.layout;
+        if context_bytes.len() > layout.input_capacity as usize {
+            return Err(VMExecutionError::ContextTooLarge(
+                format!("{} bytes exceeds the {} byte input buffer", context_bytes.len(), layout.input_capacity)
             ));
         }
         
+        // The input buffer is read-only to the program, prefixed by its size
+        let input_base = layout.input_addr.checked_sub(4).ok_or_else(|| {
+            VMExecutionError::ExecutionFailed("Invalid memory layout: input buffer does not fit the address space".to_string())
+        })?;
+        memory.map(input_base, context_bytes.len() + 4, Permissions::READ)?;
+        
         // Write context to input buffer location
-        memory.write_bytes(INPUT_BUFFER_ADDR, &context_bytes)?;
+        memory.write_bytes(layout.input_addr, &context_bytes)?;
         
         // Write buffer size at the beginning of the buffer (for the VM program to know)
         let size_bytes = (context_bytes.len() as u32).to_le_bytes();
-        memory.write_bytes(INPUT_BUFFER_ADDR - 4, &size_bytes)?;
+        memory.write_bytes(input_base, &size_bytes)?;
         
         Ok(())
     }
 
     /// Read output buffer and deserialize object effects
     fn read_output_buffer(&self, memory: &RiscVMemory) -> Result<Vec<ObjectEffect>, VMExecutionError> {
-        // Read the output buffer size (stored at OUTPUT_BUFFER_ADDR - 4)
-        let size_bytes = memory.read_bytes(OUTPUT_BUFFER_ADDR - 4, 4)
+        // Read the output buffer size (stored just before the buffer)
+        let layout = &self.config.layout;
+        let size_bytes = memory.read_bytes(layout.output_len_addr(), 4)
             .map_err(|e| VMExecutionError::ExecutionFailed(format!("Failed to read output size: {}", e)))?;
         
-        let output_size = u32::from_le_bytes([
-            size_bytes[0], size_bytes[1], size_bytes[2], size_bytes[3]
-        ]) as usize;
+        let output_size = read_u32_le(&size_bytes, 0)? as usize;
         
         // Validate output size
-        if output_size > MAX_BUFFER_SIZE as usize {
+        if output_size > layout.output_capacity as usize {
             return Err(VMExecutionError::ExecutionFailed(
                 format!("Output buffer size too large: {} bytes", output_size)
             ));
@@ -345,7 +564,7 @@ impl RiscVExecutor {
         }
         
         // Read the output buffer
-        let output_bytes = memory.read_bytes(OUTPUT_BUFFER_ADDR, output_size)
+        let output_bytes = memory.read_bytes(layout.output_addr, output_size)
             .map_err(|e| VMExecutionError::ExecutionFailed(format!("Failed to read output buffer: {}", e)))?;
         
         // Deserialize object effects
@@ -356,61 +575,109 @@ impl RiscVExecutor {
     }
 
     /// Execute RISC-V program using rvsim
+    ///
+    /// Runs until the program halts or faults, enforcing the configured
+    /// instruction and time limits. Returns the exit code from `a0` along
+    /// with the resources used. Executed instructions are recorded into
+    /// `trace`, and `hook` (if any) is consulted before each one. Built-in
+    /// syscalls are serviced through `io`, and others with a host handler
+    /// by the handler; either way execution continues.
     fn execute_program(
         &self,
         memory: &mut RiscVMemory,
-        entry_point: u32
-    ) -> Result<i32, VMExecutionError> {
-        // Create CPU state with the entry point
+        io: &mut GuestIo<'_>,
+        entry_point: u32,
+        trace: &mut ExecutionTrace,
+        mut hook: Option<&mut dyn DebugHook>,
+    ) -> Result<(i32, ExecutionMetrics), VMExecutionError> {
+        // Create CPU state with the entry point and an empty stack
         let mut cpu = CpuState::new(entry_point);
+        cpu.x[REG_SP] = self.config.layout.stack_top;
         
-        // Create a simple clock
-        let mut clock = SimpleClock::new();
+        let mut clock = LimitedClock::new(&self.config, self.host.clock());
         
-        // Create interpreter
+        let mut syscall_count = 0;
         let mut interp = Interp::new(&mut cpu, memory, &mut clock);
+        let stop = loop {
+            let stop = if !trace.is_enabled() && hook.is_none() {
+                interp.run().0
+            } else {
+                loop {
+                    let pc = interp.state.pc;
+                    let before = interp.state.x;
+                
+                    if let Some(hook) = hook.as_deref_mut() {
+                        if hook.before_step(pc, &before, trace) == DebugAction::Abort {
+                            return Err(VMExecutionError::ExecutionFailed(
+                                format!("Execution aborted by debugger at pc {:#x}", pc)
+                            ));
+                        }
+                    }
+                
+                    let (op, stop) = match interp.step() {
+                        Ok(op) => (Some(op), None),
+                        Err((err, op)) => (op, Some(err)),
+                    };
+                    trace.record(TraceEntry::new(pc, op, stop, &before, &interp.state.x));
+                
+                    if let Some(stop) = stop {
+                        break stop;
+                    }
+                }
+            };
+            if stop != CpuError::Ecall {
+                break stop;
+            }
+            syscall_count += 1;
+            // The pc is already past the ecall, so a serviced syscall just resumes
+            let number = interp.state.x[REG_SYSCALL];
+            match riscv_syscalls::service(io, number, &mut interp.state.x, interp.mem)? {
+                SyscallOutcome::Resume => {}
+                SyscallOutcome::Exit => break stop,
+                SyscallOutcome::Unhandled => match self.host.syscall(number) {
+                    Some(handler) => handler(&mut interp.state.x),
+                    None => break stop,
+                },
+            }
+            interp.clock.charge(interp.clock.costs.syscall);
+        };
         
-        // For this simplified implementation, we'll run the program once
-        // In a full implementation, we'd have a proper execution loop with timeout and instruction limits
-        let _start_time = Instant::now();
-        
-        // Try to run one step
-        let _result = interp.run();
-        
-        // For now, use a simplified approach - assume the program runs once and terminates
-        // In a full implementation, we'd check for different CpuError types
-        // and handle them appropriately (halt, breakpoint, system calls, etc.)
+        let metrics = ExecutionMetrics {
+            instructions_executed: clock.instret,
+            peak_memory_bytes: memory.mapped_bytes() as u64,
+            syscall_count,
+            compute_units: clock.units,
+        };
         
-        // If we reach here, consider the program executed (for basic testing)
-        // A proper implementation would loop until termination condition
-        Ok(0)
+        match stop {
+            CpuError::Ecall | CpuError::Ebreak => Ok((cpu.x[REG_A0] as i32, metrics)),
+            CpuError::QuotaExceeded if clock.timed_out => Err(VMExecutionError::TimeoutExceeded),
+            CpuError::QuotaExceeded if clock.instret >= clock.instruction_limit => {
+                Err(VMExecutionError::InstructionLimitExceeded)
+            }
+            CpuError::QuotaExceeded => Err(VMExecutionError::ComputeBudgetExceeded(clock.compute_budget)),
+            fault => Err(VMExecutionError::ExecutionFailed(
+                format!("CPU fault {:?} at pc {:#x}", fault, cpu.pc)
+            )),
+        }
     }
-}
 
-impl Default for RiscVExecutor {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl VMExecutor for RiscVExecutor {
-    fn vm_type(&self) -> VMType {
-        VMType::RiscV
-    }
-    
-    fn load_and_execute(
+    /// Load and run a program, recording a trace if enabled
+    fn execute(
         &self,
         bytecode: &[u8],
         context: &ExecutionContext,
-    ) -> Result<Vec<ObjectEffect>, VMExecutionError> {
+        trace: &mut ExecutionTrace,
+        hook: Option<&mut dyn DebugHook>,
+    ) -> Result<ExecutionOutput, VMExecutionError> {
         // 1. Create memory for the RISC-V VM
         let mut memory = RiscVMemory::new(self.config.memory_limit);
 
         // 2. Detect bytecode format and load appropriately
-        let entry_point = if bytecode.len() >= 4 && &bytecode[0..4] == BYTECODE_MAGIC {
+        let entry_point = if rvbc::is_rvbc(bytecode) {
             // Raw bytecode format
             self.load_raw_bytecode(bytecode, &mut memory)?
-        } else if bytecode.len() >= 4 && &bytecode[0..4] == b"\x7fELF" {
+        } else if bytecode.len() >= 4 && &bytecode[0..4] == ELF_MAGIC {
             // ELF format
             self.load_elf(bytecode, &mut memory)?
         } else {
@@ -419,24 +686,101 @@ impl VMExecutor for RiscVExecutor {
             ));
         };
 
-        // 3. Set up input buffer with serialized ExecutionContext
+        // 3. Map the stack and output buffer, then the input buffer with serialized ExecutionContext
+        self.setup_runtime_regions(&mut memory)?;
         self.setup_input_buffer(&mut memory, context)?;
 
-        // 4. Execute the program
-        let exit_code = self.execute_program(&mut memory, entry_point)?;
+        // 4. Execute the program, servicing its syscalls
+        let mut io = GuestIo::new(context, self.config.layout.output_capacity as usize + 4);
+        let (exit_code, metrics) = self.execute_program(&mut memory, &mut io, entry_point, trace, hook)?;
 
         // 5. Check exit code
         if exit_code != 0 {
-            return Err(VMExecutionError::ExecutionFailed(format!("Program exited with code: {}", exit_code)));
+            return Err(VMExecutionError::ModuleError(exit_code as u32));
         }
 
-        // 6. Read and deserialize ObjectEffects from output buffer
-        let effects = self.read_output_buffer(&memory)?;
+        // 6. Take ObjectEffects and events written to stdout, or else effects from the output buffer
+        let (effects, events) = match io.effects() {
+            Some(output) => output?,
+            None => (self.read_output_buffer(&memory)?, Vec::new()),
+        };
 
         // 7. Validate effects (controller can only modify objects it controls)
         units_core_types::validate_object_effects(&effects, context.instruction.controller_id)?;
 
-        Ok(effects)
+        Ok(ExecutionOutput { effects, events, metrics })
+    }
+
+    /// Execute a program, returning the instruction trace if it fails
+    ///
+    /// The trace holds the last `trace_capacity` instructions; it is empty
+    /// when tracing is disabled in the configuration.
+    pub fn load_and_execute_traced(
+        &self,
+        bytecode: &[u8],
+        context: &ExecutionContext,
+    ) -> Result<Vec<ObjectEffect>, TracedFailure> {
+        let mut trace = ExecutionTrace::new(self.config.trace_capacity);
+        self.execute(bytecode, context, &mut trace, None)
+            .map(|output| output.effects)
+            .map_err(|error| TracedFailure { error, trace })
+    }
+
+    /// Execute a program under a debug hook
+    ///
+    /// The hook is consulted before every instruction and may abort
+    /// execution, e.g. to implement breakpoints or single-stepping.
+    pub fn debug(
+        &self,
+        bytecode: &[u8],
+        context: &ExecutionContext,
+        hook: &mut dyn DebugHook,
+    ) -> Result<Vec<ObjectEffect>, TracedFailure> {
+        let mut trace = ExecutionTrace::new(self.config.trace_capacity);
+        self.execute(bytecode, context, &mut trace, Some(hook))
+            .map(|output| output.effects)
+            .map_err(|error| TracedFailure { error, trace })
+    }
+}
+
+impl Default for RiscVExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VMExecutor for RiscVExecutor {
+    fn vm_type(&self) -> VMType {
+        VMType::RiscV
+    }
+    
+    fn load_and_execute(
+        &self,
+        bytecode: &[u8],
+        context: &ExecutionContext,
+    ) -> Result<Vec<ObjectEffect>, VMExecutionError> {
+        self.load_and_execute_traced(bytecode, context).map_err(|failure| {
+            if !failure.trace.is_empty() {
+                log::debug!("RISC-V execution failed, trace:\n{}", failure.trace);
+            }
+            failure.error
+        })
+    }
+
+    fn load_and_execute_with_metrics(
+        &self,
+        bytecode: &[u8],
+        context: &ExecutionContext,
+    ) -> Result<(Vec<ObjectEffect>, ExecutionMetrics), VMExecutionError> {
+        self.load_and_execute_with_events(bytecode, context).map(|output| (output.effects, output.metrics))
+    }
+
+    fn load_and_execute_with_events(
+        &self,
+        bytecode: &[u8],
+        context: &ExecutionContext,
+    ) -> Result<ExecutionOutput, VMExecutionError> {
+        self.execute(bytecode, context, &mut ExecutionTrace::new(0), None)
     }
 }
 
@@ -447,6 +791,7 @@ mod tests {
     use std::collections::HashMap;
     use units_core_types::constants::TOKEN_CONTROLLER_ID;
     use units_core_types::transaction::Instruction;
+    use crate::riscv_debug::{DebugCommand, Debugger};
 
     #[test]
     fn test_riscv_executor_creation() {
@@ -456,7 +801,11 @@ mod tests {
         let custom_config = RiscVExecutorConfig {
             memory_limit: 8 * 1024 * 1024,
             instruction_limit: 500_000,
+            compute_budget: 1_000_000,
+            costs: ComputeCostTable::default(),
             timeout_ms: 1000,
+            trace_capacity: 0,
+            layout: MemoryLayout::DEFAULT,
         };
         
         let custom_executor = RiscVExecutor::with_config(custom_config.clone());
@@ -621,6 +970,117 @@ mod tests {
         assert!(bss_data.iter().all(|&b| b == 0));
     }
 
+    /// Program header of a test image: (p_type, p_vaddr, p_flags, data, p_memsz)
+    type TestSegment<'a> = (u32, u32, u32, &'a [u8], u32);
+    
+    /// Build a static ELF laid out like a linker script's output, with each
+    /// segment's data following the program headers
+    fn linked_elf(entry_point: u32, segments: &[TestSegment]) -> Vec<u8> {
+        let phoff = ELF32_HEADER_SIZE;
+        let mut elf = vec![0u8; phoff + segments.len() * ELF32_PHDR_SIZE];
+        elf[0..4].copy_from_slice(ELF_MAGIC);
+        elf[4] = 1; // 32-bit
+        elf[5] = 1; // Little-endian
+        elf[6] = 1; // ELF version
+        elf[16..18].copy_from_slice(&2u16.to_le_bytes()); // e_type = ET_EXEC
+        elf[18..20].copy_from_slice(&243u16.to_le_bytes()); // e_machine = EM_RISCV
+        elf[24..28].copy_from_slice(&entry_point.to_le_bytes());
+        elf[28..32].copy_from_slice(&(phoff as u32).to_le_bytes());
+        elf[42..44].copy_from_slice(&(ELF32_PHDR_SIZE as u16).to_le_bytes());
+        elf[44..46].copy_from_slice(&(segments.len() as u16).to_le_bytes());
+        
+        for (i, &(p_type, vaddr, flags, data, memsz)) in segments.iter().enumerate() {
+            let ph = phoff + i * ELF32_PHDR_SIZE;
+            let fields = [p_type, elf.len() as u32, vaddr, vaddr, data.len() as u32, memsz, flags, 4];
+            for (field, value) in fields.iter().enumerate() {
+                elf[ph + field * 4..ph + field * 4 + 4].copy_from_slice(&value.to_le_bytes());
+            }
+            elf.extend_from_slice(data);
+        }
+        elf
+    }
+    
+    fn words(instructions: &[u32]) -> Vec<u8> {
+        instructions.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+    
+    #[test]
+    fn test_elf_static_data_sections() {
+        let executor = RiscVExecutor::new();
+        let context = test_context();
+        let rodata = 7u32.to_le_bytes();
+        let data = 0xCAFEBABEu32.to_le_bytes();
+        
+        // lui t0, 0x2; lw a0, 0(t0); lui t1, 0x3; sw a0, 8(t1); lw a0, 8(t1); ecall
+        let text = words(&[0x0000_22b7, 0x0002_a503, 0x0000_3337, 0x00a3_2423, 0x0083_2503, 0x0000_0073]);
+        let elf = linked_elf(0x1000, &[
+            (PT_LOAD, 0x1000, 0x5, &text, text.len() as u32), // .text
+            (PT_LOAD, 0x2000, 0x4, &rodata, 4), // .rodata
+            (PT_LOAD, 0x3000, 0x6, &data, 64), // .data + .bss
+        ]);
+        
+        let mut memory = RiscVMemory::new(executor.config.memory_limit);
+        assert_eq!(executor.load_elf(&elf, &mut memory).unwrap(), 0x1000);
+        assert_eq!(memory.read_bytes(0x2000, 4).unwrap(), rodata);
+        assert_eq!(memory.read_bytes(0x3000, 4).unwrap(), data);
+        assert!(memory.read_bytes(0x3004, 60).unwrap().iter().all(|&b| b == 0));
+        
+        // The value read from .rodata round-trips through .bss into the exit code
+        match executor.load_and_execute(&elf, &context).unwrap_err() {
+            VMExecutionError::ModuleError(code) => assert_eq!(code, 7),
+            other => panic!("Expected exit code from .rodata, got: {:?}", other),
+        }
+        
+        // Stores into .rodata fault: lui t0, 0x2; sw zero, 0(t0)
+        let text = words(&[0x0000_22b7, 0x0002_a023, 0x0000_0073]);
+        let elf = linked_elf(0x1000, &[
+            (PT_LOAD, 0x1000, 0x5, &text, text.len() as u32),
+            (PT_LOAD, 0x2000, 0x4, &rodata, 4),
+        ]);
+        match executor.load_and_execute(&elf, &context).unwrap_err() {
+            VMExecutionError::ExecutionFailed(msg) => assert!(msg.contains("CPU fault"), "{}", msg),
+            other => panic!("Expected fault writing .rodata, got: {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_elf_segment_region_conflicts() {
+        let executor = RiscVExecutor::new();
+        let text = words(&[0x0000_0073]);
+        let load = |segments: &[TestSegment]| {
+            let mut memory = RiscVMemory::new(executor.config.memory_limit);
+            match executor.load_elf(&linked_elf(0x1000, segments), &mut memory) {
+                Err(VMExecutionError::InvalidBytecode(msg)) => msg,
+                other => panic!("Expected InvalidBytecode, got: {:?}", other),
+            }
+        };
+        
+        // .data linked into the input buffer, .rodata into the output buffer size prefix
+        let msg = load(&[(PT_LOAD, 0x1000, 0x5, &text, 4), (PT_LOAD, MemoryLayout::DEFAULT.input_addr + 0x100, 0x6, &[1], 16)]);
+        assert!(msg.contains("Segment 1 (data)") && msg.contains("input buffer"), "{}", msg);
+        let msg = load(&[(PT_LOAD, 0x1000, 0x5, &text, 4), (PT_LOAD, MemoryLayout::DEFAULT.output_addr - 8, 0x4, &[1], 8)]);
+        assert!(msg.contains("Segment 1 (rodata)") && msg.contains("output buffer"), "{}", msg);
+        
+        // A large .bss running into the stack
+        let msg = load(&[(PT_LOAD, 0x1000, 0x5, &text, 4), (PT_LOAD, 0x0700_0000, 0x6, &[], 0x0100_0000)]);
+        assert!(msg.contains("stack"), "{}", msg);
+        
+        // Code overlapping rodata from a mislinked section
+        let msg = load(&[(PT_LOAD, 0x1000, 0x5, &text, 0x100), (PT_LOAD, 0x1080, 0x4, &[1], 4)]);
+        assert!(msg.contains("Segment 1 (rodata)") && msg.contains("segment 0 (code)"), "{}", msg);
+        
+        // Images needing relocation
+        let msg = load(&[(PT_LOAD, 0x1000, 0x5, &text, 4), (PT_DYNAMIC, 0x2000, 0x6, &[], 0)]);
+        assert!(msg.contains("PT_DYNAMIC"), "{}", msg);
+        let mut pie = linked_elf(0x1000, &[(PT_LOAD, 0x1000, 0x5, &text, 4)]);
+        pie[16..18].copy_from_slice(&ET_DYN.to_le_bytes());
+        let mut memory = RiscVMemory::new(executor.config.memory_limit);
+        match executor.load_elf(&pie, &mut memory) {
+            Err(VMExecutionError::InvalidBytecode(msg)) => assert!(msg.contains("relocation"), "{}", msg),
+            other => panic!("Expected relocation error, got: {:?}", other),
+        }
+    }
+    
     #[test]
     fn test_raw_bytecode_loading() {
         let executor = RiscVExecutor::new();
@@ -628,7 +1088,7 @@ mod tests {
         
         // Create a valid raw bytecode with some RISC-V instructions
         let mut bytecode = Vec::new();
-        bytecode.extend_from_slice(BYTECODE_MAGIC); // Magic bytes
+        bytecode.extend_from_slice(rvbc::MAGIC); // Magic bytes
         bytecode.extend_from_slice(&8u32.to_le_bytes()); // Entry offset
         
         // Add some sample RISC-V instructions (NOP instructions)
@@ -643,6 +1103,12 @@ mod tests {
         // Verify code was loaded into memory
         let loaded_code = memory.read_bytes(CODE_BASE_ADDR, 12).unwrap();
         assert_eq!(&loaded_code[0..4], &[0x13, 0x00, 0x00, 0x00]);
+
+        // The versioned header wrapping the same code loads identically
+        let versioned = rvbc::encode(&bytecode[8..], 8).unwrap();
+        let mut memory = RiscVMemory::new(executor.config.memory_limit);
+        assert_eq!(executor.load_raw_bytecode(&versioned, &mut memory).unwrap(), CODE_BASE_ADDR + 8);
+        assert_eq!(memory.read_bytes(CODE_BASE_ADDR, 12).unwrap(), &bytecode[8..]);
     }
     
     #[test]
@@ -662,7 +1128,7 @@ mod tests {
         
         // Test invalid entry offset (out of bounds)
         let mut invalid_offset = Vec::new();
-        invalid_offset.extend_from_slice(BYTECODE_MAGIC);
+        invalid_offset.extend_from_slice(rvbc::MAGIC);
         invalid_offset.extend_from_slice(&100u32.to_le_bytes()); // Offset beyond code size
         invalid_offset.extend_from_slice(&[0x13, 0x00, 0x00, 0x00]); // One instruction
         let result = executor.load_raw_bytecode(&invalid_offset, &mut memory);
@@ -670,7 +1136,7 @@ mod tests {
         
         // Test unaligned entry offset
         let mut unaligned = Vec::new();
-        unaligned.extend_from_slice(BYTECODE_MAGIC);
+        unaligned.extend_from_slice(rvbc::MAGIC);
         unaligned.extend_from_slice(&3u32.to_le_bytes()); // Not 4-byte aligned
         unaligned.extend_from_slice(&[0x13, 0x00, 0x00, 0x00]);
         let result = executor.load_raw_bytecode(&unaligned, &mut memory);
@@ -692,7 +1158,7 @@ mod tests {
         
         // Test raw bytecode format detection
         let mut raw_bytecode = Vec::new();
-        raw_bytecode.extend_from_slice(BYTECODE_MAGIC);
+        raw_bytecode.extend_from_slice(rvbc::MAGIC);
         raw_bytecode.extend_from_slice(&0u32.to_le_bytes());
         raw_bytecode.extend_from_slice(&[0x13, 0x00, 0x00, 0x00]); // NOP
         
@@ -711,4 +1177,418 @@ mod tests {
             _ => panic!("Expected InvalidBytecode error for unknown format"),
         }
     }
-}
\ No newline at end of file
+
+    fn test_context() -> ExecutionContext {
+        let instruction = Instruction::new(TOKEN_CONTROLLER_ID, "test".to_string(), vec![], vec![]);
+        ExecutionContext::new(instruction, HashMap::new(), 1, 2)
+    }
+
+    fn raw_program(instructions: &[u32]) -> Vec<u8> {
+        let mut bytecode = Vec::new();
+        bytecode.extend_from_slice(rvbc::MAGIC);
+        bytecode.extend_from_slice(&0u32.to_le_bytes());
+        for instruction in instructions {
+            bytecode.extend_from_slice(&instruction.to_le_bytes());
+        }
+        bytecode
+    }
+
+    #[test]
+    fn test_program_halts_with_exit_code() {
+        let executor = RiscVExecutor::new();
+        let context = test_context();
+
+        // ecall with a0 = 0: clean exit, no effects
+        let result = executor.load_and_execute(&raw_program(&[0x0000_0073]), &context);
+        assert!(result.unwrap().is_empty());
+
+        // addi a0, x0, 3; ecall
+        let result = executor.load_and_execute(&raw_program(&[0x0030_0513, 0x0000_0073]), &context);
+        match result.unwrap_err() {
+            VMExecutionError::ModuleError(code) => assert_eq!(code, 3),
+            other => panic!("Expected non-zero exit code, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_custom_memory_layout() {
+        let layout = MemoryLayout {
+            input_addr: 0x3000_0000,
+            input_capacity: 64 * 1024,
+            output_addr: 0x3800_0000,
+            output_capacity: 64 * 1024,
+            stack_top: 0x0400_0000,
+            stack_size: 16 * 1024,
+        };
+        let executor = RiscVExecutor::with_config(RiscVExecutorConfig {
+            layout,
+            ..RiscVExecutorConfig::default()
+        });
+        let context = test_context();
+        
+        // Find the input buffer through the descriptor and exit with the context length:
+        // lui t0, 0x1; addi t0, t0, -0x800; lw t1, 12(t0); lw a0, -4(t1); ecall
+        let program = raw_program(&[0x0000_12b7, 0x8002_8293, 0x00c2_a303, 0xffc3_2503, 0x0000_0073]);
+        let input_len = bincode::serialize(&context).unwrap().len();
+        match executor.load_and_execute(&program, &context).unwrap_err() {
+            VMExecutionError::ModuleError(code) => assert_eq!(code as usize, input_len),
+            other => panic!("Expected exit code from the input length, got: {:?}", other),
+        }
+        
+        // Segments are checked against the configured regions, not the defaults
+        let text = words(&[0x0000_0073]);
+        let elf = linked_elf(0x1000, &[(PT_LOAD, 0x1000, 0x5, &text, 4), (PT_LOAD, 0x3000_0000, 0x6, &[1], 4)]);
+        let mut memory = RiscVMemory::new(executor.config.memory_limit);
+        match executor.load_elf(&elf, &mut memory) {
+            Err(VMExecutionError::InvalidBytecode(msg)) => assert!(msg.contains("input buffer"), "{}", msg),
+            other => panic!("Expected input buffer conflict, got: {:?}", other),
+        }
+        let elf = linked_elf(0x1000, &[(PT_LOAD, 0x1000, 0x5, &text, 4), (PT_LOAD, 0x1000_0000, 0x6, &[1], 4)]);
+        assert!(executor.load_and_execute(&elf, &context).is_ok());
+        
+        // Contexts beyond the configured capacity are rejected
+        let small = RiscVExecutor::with_config(RiscVExecutorConfig {
+            layout: MemoryLayout { input_capacity: 8, ..layout },
+            ..RiscVExecutorConfig::default()
+        });
+        assert!(matches!(
+            small.load_and_execute(&raw_program(&[0x0000_0073]), &context),
+            Err(VMExecutionError::ContextTooLarge(_))
+        ));
+    }
+    
+    #[test]
+    fn test_memory_layout_descriptor_encoding() {
+        let bytes = MemoryLayout::DEFAULT.to_bytes();
+        assert_eq!(MemoryLayout::from_bytes(&bytes), Some(MemoryLayout::DEFAULT));
+        
+        // Later versions append fields, which older readers skip
+        let mut newer = bytes.to_vec();
+        newer[4..8].copy_from_slice(&2u32.to_le_bytes());
+        newer[8..12].copy_from_slice(&40u32.to_le_bytes());
+        newer.extend_from_slice(&0xffff_ffffu32.to_le_bytes());
+        assert_eq!(MemoryLayout::from_bytes(&newer), Some(MemoryLayout::DEFAULT));
+        
+        let mut bad_magic = bytes;
+        bad_magic[0] ^= 1;
+        assert_eq!(MemoryLayout::from_bytes(&bad_magic), None);
+        assert_eq!(MemoryLayout::from_bytes(&bytes[..32]), None);
+    }
+    
+    #[test]
+    fn test_execution_limits_and_faults() {
+        let executor = RiscVExecutor::with_config(RiscVExecutorConfig {
+            instruction_limit: 1000,
+            ..RiscVExecutorConfig::default()
+        });
+        let context = test_context();
+
+        // j 0 loops forever
+        let result = executor.load_and_execute(&raw_program(&[0x0000_006f]), &context);
+        assert!(matches!(result, Err(VMExecutionError::InstructionLimitExceeded)));
+
+        // lui a1, 0x1; sw x0, 0(a1): code is not writable
+        let result = executor.load_and_execute(&raw_program(&[0x0000_15b7, 0x0005_a023, 0x0000_0073]), &context);
+        match result.unwrap_err() {
+            VMExecutionError::ExecutionFailed(msg) => assert!(msg.contains("IllegalAccess")),
+            other => panic!("Expected illegal access fault, got: {:?}", other),
+        }
+
+        // lui a1, 0x10000; sw x0, 0(a1): input buffer is read-only
+        let result = executor.load_and_execute(&raw_program(&[0x1000_05b7, 0x0005_a023, 0x0000_0073]), &context);
+        assert!(matches!(result, Err(VMExecutionError::ExecutionFailed(_))));
+
+        // sw x0, -4(sp); lw a0, -4(sp); ecall: the stack is usable
+        let result = executor.load_and_execute(
+            &raw_program(&[0xfe01_2e23, 0xffc1_2503, 0x0000_0073]),
+            &context,
+        );
+        assert!(result.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_execution_metrics() {
+        let executor = RiscVExecutor::new();
+        let context = test_context();
+
+        // nop; nop; ecall
+        let program = raw_program(&[0x0000_0013, 0x0000_0013, 0x0000_0073]);
+        let (effects, metrics) = executor.load_and_execute_with_metrics(&program, &context).unwrap();
+        assert!(effects.is_empty());
+        assert_eq!(metrics.instructions_executed, 3);
+        assert_eq!(metrics.syscall_count, 1);
+        // Two ALU instructions and the halting ecall, with no syscall surcharge
+        assert_eq!(metrics.compute_units, 4);
+
+        // Code, layout descriptor, stack, output and input regions are all accounted for
+        let layout = MemoryLayout::DEFAULT;
+        let input_len = bincode::serialize(&context).unwrap().len() as u64;
+        assert_eq!(
+            metrics.peak_memory_bytes,
+            12 + MemoryLayout::ENCODED_SIZE as u64
+                + layout.stack_size as u64
+                + layout.output_capacity as u64 + 4
+                + input_len + 4
+        );
+
+        // Identical inputs produce identical metrics
+        let (_, again) = executor.load_and_execute_with_metrics(&program, &context).unwrap();
+        assert_eq!(metrics, again);
+    }
+
+    #[test]
+    fn test_compute_budget() {
+        let context = test_context();
+        let executor = RiscVExecutor::with_config(RiscVExecutorConfig {
+            compute_budget: 1000,
+            ..RiscVExecutorConfig::default()
+        });
+
+        // j 0 runs out of compute long before the instruction limit
+        let result = executor.load_and_execute(&raw_program(&[0x0000_006f]), &context);
+        assert!(matches!(result, Err(VMExecutionError::ComputeBudgetExceeded(1000))));
+
+        // nop; nop; ecall under a custom cost table
+        let executor = RiscVExecutor::with_config(RiscVExecutorConfig {
+            compute_budget: 16,
+            costs: ComputeCostTable { alu: 7, ..ComputeCostTable::default() },
+            ..RiscVExecutorConfig::default()
+        });
+        let program = raw_program(&[0x0000_0013, 0x0000_0013, 0x0000_0073]);
+        let (_, metrics) = executor.load_and_execute_with_metrics(&program, &context).unwrap();
+        assert_eq!(metrics.compute_units, 16);
+
+        // Execution stops once the units reach the budget, here before the ecall
+        let executor = RiscVExecutor::with_config(RiscVExecutorConfig { compute_budget: 14, ..executor.config });
+        assert!(matches!(
+            executor.load_and_execute(&program, &context),
+            Err(VMExecutionError::ComputeBudgetExceeded(14))
+        ));
+    }
+
+    #[test]
+    fn test_host_syscalls_and_clock() {
+        use crate::host::FakeClock;
+        use std::sync::Arc;
+
+        let clock = Arc::new(FakeClock::new(0));
+        let ticking = clock.clone();
+        let host = HostEnvironment::builder()
+            .clock(clock.clone())
+            .syscall(1000, |regs| regs[REG_A0] = 42)
+            .syscall(1001, move |_| ticking.advance(Duration::from_secs(10)))
+            .build();
+        let executor = RiscVExecutor::new().with_host(host);
+        let context = test_context();
+
+        // li a7, 1000; ecall; addi a0, a0, -42; li a7, 93; ecall
+        let program = raw_program(&[0x3e80_0893, 0x0000_0073, 0xfd65_0513, 0x05d0_0893, 0x0000_0073]);
+        let (effects, metrics) = executor.load_and_execute_with_metrics(&program, &context).unwrap();
+        assert!(effects.is_empty());
+        assert_eq!(metrics.syscall_count, 2);
+        // Only the serviced syscall carries the surcharge
+        assert_eq!(metrics.compute_units, 3 + 2 * 2 + 100);
+
+        // Without the stub the first ecall halts with a0 = 0, as before
+        let (_, metrics) = RiscVExecutor::new().load_and_execute_with_metrics(&program, &context).unwrap();
+        assert_eq!(metrics.syscall_count, 1);
+
+        // li a7, 1001; loop: ecall; j loop -- each call moves the clock past the timeout
+        let program = raw_program(&[0x3e90_0893, 0x0000_0073, 0xffdf_f06f]);
+        assert!(matches!(
+            executor.load_and_execute(&program, &context),
+            Err(VMExecutionError::TimeoutExceeded)
+        ));
+        assert!(clock.now_millis() >= 5_000);
+    }
+
+    #[test]
+    fn test_sdk_syscalls_end_to_end() {
+        use units_core_types::{UnitsObject, UnitsObjectId};
+
+        let executor = RiscVExecutor::new();
+        let mut context = test_context();
+        context.slot = 7;
+
+        // li a0, 0; addi a1, sp, -4; li a2, 4; li a7, 63; ecall; lw a0, -4(sp); li a7, 93; ecall
+        // -- reads the context's length prefix from stdin and exits with it
+        let program = raw_program(&[
+            0x0000_0513, 0xffc1_0593, 0x0040_0613, 0x03f0_0893, 0x0000_0073,
+            0xffc1_2503, 0x05d0_0893, 0x0000_0073,
+        ]);
+        match executor.load_and_execute(&program, &context) {
+            Err(VMExecutionError::ModuleError(code)) => assert_eq!(code as usize, borsh::to_vec(&context).unwrap().len()),
+            other => panic!("Expected the context length as exit code, got: {:?}", other.map(|e| e.len())),
+        }
+
+        // lui a7, 1 (get_slot); ecall; li a7, 93; ecall -- exits with the slot
+        let program = raw_program(&[0x0000_18b7, 0x0000_0073, 0x05d0_0893, 0x0000_0073]);
+        assert!(matches!(executor.load_and_execute(&program, &context), Err(VMExecutionError::ModuleError(7))));
+
+        // Framed effects stored right after the code
+        let object = UnitsObject::new_data(UnitsObjectId::new([5; 32]), TOKEN_CONTROLLER_ID, vec![1, 2]);
+        let body = borsh::to_vec(&vec![ObjectEffect::creation(object.clone())]).unwrap();
+        let mut framed = (body.len() as u32).to_le_bytes().to_vec();
+        framed.extend(body);
+
+        // auipc a1, 0; addi a1, a1, 36; li a0, 1; li a2, <len>; li a7, 64; ecall; li a0, 0; li a7, 93; ecall
+        // -- writes them to stdout, where they replace the output buffer
+        let mut program = raw_program(&[
+            0x0000_0597, 0x0245_8593, 0x0010_0513, ((framed.len() as u32) << 20) | 0x0613, 0x0400_0893,
+            0x0000_0073, 0x0000_0513, 0x05d0_0893, 0x0000_0073,
+        ]);
+        program.extend(framed);
+        let (effects, metrics) = executor.load_and_execute_with_metrics(&program, &context).unwrap();
+        assert_eq!(effects.len(), 1);
+        assert_eq!(effects[0].after_image, Some(object));
+        assert_eq!(metrics.syscall_count, 2);
+    }
+
+    #[test]
+    fn test_traced_failure_returns_recent_instructions() {
+        let executor = RiscVExecutor::with_config(RiscVExecutorConfig {
+            trace_capacity: 2,
+            ..RiscVExecutorConfig::default()
+        });
+
+        // addi a0, x0, 3; lui a1, 0x1; sw x0, 0(a1) faults on read-only code
+        let program = raw_program(&[0x0030_0513, 0x0000_15b7, 0x0005_a023]);
+        let failure = executor.load_and_execute_traced(&program, &test_context()).unwrap_err();
+
+        assert!(matches!(failure.error, VMExecutionError::ExecutionFailed(_)));
+        assert_eq!(failure.trace.total_steps(), 3);
+        let pcs: Vec<u32> = failure.trace.entries().map(|entry| entry.pc).collect();
+        assert_eq!(pcs, vec![CODE_BASE_ADDR + 4, CODE_BASE_ADDR + 8]);
+        assert_eq!(failure.trace.entries().next().unwrap().register_writes[0].new, 0x1000);
+    }
+
+    #[test]
+    fn test_debugger_breakpoints_and_single_step() {
+        let executor = RiscVExecutor::new();
+        // nop; nop; addi a0, x0, 3; ecall
+        let program = raw_program(&[0x0000_0013, 0x0000_0013, 0x0030_0513, 0x0000_0073]);
+
+        let mut stops = Vec::new();
+        let mut debugger = Debugger::new(|pc, registers: &[u32; 32], _trace: &ExecutionTrace| {
+            stops.push((pc, registers[10]));
+            if registers[10] == 3 {
+                DebugCommand::Abort
+            } else {
+                DebugCommand::Step
+            }
+        });
+        debugger.add_breakpoint(CODE_BASE_ADDR + 4);
+
+        let failure = executor.debug(&program, &test_context(), &mut debugger).unwrap_err();
+        assert!(failure.error.to_string().contains("aborted by debugger"));
+        drop(debugger);
+        assert_eq!(stops, vec![
+            (CODE_BASE_ADDR + 4, 0),
+            (CODE_BASE_ADDR + 8, 0),
+            (CODE_BASE_ADDR + 12, 3),
+        ]);
+    }
+
+    /// Deterministic xorshift generator so smoke test failures are reproducible
+    struct FuzzRng(u64);
+
+    impl FuzzRng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: u64) -> u64 {
+            self.next() % n
+        }
+
+        /// Mostly small values, with a bias towards boundary cases
+        fn field(&mut self) -> u32 {
+            match self.below(8) {
+                0 => 0,
+                1 => u32::MAX,
+                2 => u32::MAX - self.below(64) as u32,
+                3 => 1 << self.below(32),
+                4 => self.next() as u32,
+                _ => self.below(0x4000) as u32,
+            }
+        }
+    }
+
+    /// Build a structurally plausible ELF with randomised header fields
+    fn fuzz_elf(rng: &mut FuzzRng) -> Vec<u8> {
+        let phnum = rng.below(4) as usize;
+        let mut elf = vec![0u8; ELF32_HEADER_SIZE + phnum * ELF32_PHDR_SIZE + rng.below(256) as usize];
+        elf[0..4].copy_from_slice(ELF_MAGIC);
+        elf[4] = if rng.below(16) == 0 { 2 } else { 1 };
+        elf[5] = 1;
+
+        let entry = if rng.below(2) == 0 { 0x1000 } else { rng.field() };
+        elf[24..28].copy_from_slice(&entry.to_le_bytes());
+        let phoff = if rng.below(8) == 0 { rng.field() } else { ELF32_HEADER_SIZE as u32 };
+        elf[28..32].copy_from_slice(&phoff.to_le_bytes());
+        elf[42..44].copy_from_slice(&(ELF32_PHDR_SIZE as u16).to_le_bytes());
+        elf[44..46].copy_from_slice(&(phnum as u16).to_le_bytes());
+
+        for i in 0..phnum {
+            let ph = ELF32_HEADER_SIZE + i * ELF32_PHDR_SIZE;
+            let p_type = if rng.below(4) == 0 { rng.field() } else { PT_LOAD };
+            elf[ph..ph + 4].copy_from_slice(&p_type.to_le_bytes());
+            for field in 1..7 {
+                let value = rng.field();
+                elf[ph + field * 4..ph + field * 4 + 4].copy_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        for byte in elf.iter_mut().skip(ELF32_HEADER_SIZE + phnum * ELF32_PHDR_SIZE) {
+            *byte = rng.next() as u8;
+        }
+        elf
+    }
+
+    /// Build an RVBC image of random instruction words
+    fn fuzz_rvbc(rng: &mut FuzzRng) -> Vec<u8> {
+        let words = rng.below(32) as usize + 1;
+        let mut bytecode = Vec::new();
+        bytecode.extend_from_slice(rvbc::MAGIC);
+        let entry = if rng.below(4) == 0 { rng.field() } else { 4 * rng.below(words as u64) as u32 };
+        bytecode.extend_from_slice(&entry.to_le_bytes());
+        for _ in 0..words {
+            bytecode.extend_from_slice(&(rng.next() as u32).to_le_bytes());
+        }
+        bytecode
+    }
+
+    /// Smoke test over random images; the coverage-guided fuzzing of guest
+    /// memory lives in `fuzz/fuzz_targets/riscv_memory.rs`
+    #[test]
+    fn test_random_images_smoke() {
+        let executor = RiscVExecutor::with_config(RiscVExecutorConfig {
+            memory_limit: 4 * 1024 * 1024,
+            instruction_limit: 10_000,
+            compute_budget: 20_000,
+            costs: ComputeCostTable::default(),
+            timeout_ms: 1000,
+            trace_capacity: 16,
+            layout: MemoryLayout::DEFAULT,
+        });
+        let context = test_context();
+        let mut rng = FuzzRng(0x5eed_1234_abcd_ef01);
+
+        for _ in 0..256 {
+            let mut image = if rng.below(2) == 0 { fuzz_elf(&mut rng) } else { fuzz_rvbc(&mut rng) };
+
+            // Flip a few bytes so the parsers also see malformed structure
+            for _ in 0..rng.below(4) {
+                let index = rng.below(image.len() as u64) as usize;
+                image[index] ^= rng.next() as u8;
+            }
+            image.truncate(image.len() - rng.below(image.len() as u64 / 4 + 1) as usize);
+
+            // Any result is acceptable; panics and out-of-bounds accesses are not
+            let _ = executor.load_and_execute(&image, &context);
+        }
+    }
+}
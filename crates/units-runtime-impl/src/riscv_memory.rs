@@ -0,0 +1,286 @@
+//! Guest memory for the RISC-V executor
+//!
+//! Memory is a sparse set of non-overlapping regions, each with its own
+//! permissions. Guest loads, stores and instruction fetches are only
+//! honoured when the whole access lies inside a single region that grants
+//! the matching permission; everything else faults. Loaders populate
+//! regions through the privileged `write_bytes`, which ignores permissions
+//! but still requires the target range to be mapped.
+
+use rvsim::{Memory, MemoryAccess};
+use std::mem::size_of;
+use std::ops::BitOr;
+use std::ptr;
+use units_core_types::VMExecutionError;
+
+use crate::guest_io::GuestMemory;
+
+/// Access permissions for a memory region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Permissions(u8);
+
+impl Permissions {
+    pub const NONE: Self = Self(0);
+    pub const READ: Self = Self(0b001);
+    pub const WRITE: Self = Self(0b010);
+    pub const EXEC: Self = Self(0b100);
+
+    /// Read + execute, used for code
+    pub const RX: Self = Self(Self::READ.0 | Self::EXEC.0);
+    /// Read + write, used for data, stack and output
+    pub const RW: Self = Self(Self::READ.0 | Self::WRITE.0);
+
+    /// Convert ELF program header flags (PF_X = 1, PF_W = 2, PF_R = 4)
+    pub fn from_elf_flags(p_flags: u32) -> Self {
+        let mut perms = Self::NONE;
+        if p_flags & 0x4 != 0 {
+            perms = perms | Self::READ;
+        }
+        if p_flags & 0x2 != 0 {
+            perms = perms | Self::WRITE;
+        }
+        if p_flags & 0x1 != 0 {
+            perms = perms | Self::EXEC;
+        }
+        perms
+    }
+
+    /// Whether every permission in `other` is granted
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for Permissions {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// A contiguous mapped range of guest memory
+#[derive(Debug)]
+struct MemoryRegion {
+    base: u32,
+    perms: Permissions,
+    data: Vec<u8>,
+}
+
+impl MemoryRegion {
+    /// One past the last guest address of the region
+    fn end(&self) -> u64 {
+        self.base as u64 + self.data.len() as u64
+    }
+}
+
+/// Region-based guest memory with permission checks
+#[derive(Debug)]
+pub struct RiscVMemory {
+    /// Mapped regions sorted by base address
+    regions: Vec<MemoryRegion>,
+    /// Total bytes currently mapped
+    mapped: usize,
+    memory_limit: usize,
+}
+
+impl RiscVMemory {
+    /// Create an empty address space that may map up to `memory_limit` bytes
+    pub fn new(memory_limit: usize) -> Self {
+        Self {
+            regions: Vec::new(),
+            mapped: 0,
+            memory_limit,
+        }
+    }
+
+    /// Map a zero-filled region of `size` bytes at `base`
+    ///
+    /// Fails if the region wraps the 32-bit address space, overlaps an
+    /// existing region, or would exceed the memory limit. Mapping zero
+    /// bytes is a no-op.
+    pub fn map(&mut self, base: u32, size: usize, perms: Permissions) -> Result<(), VMExecutionError> {
+        if size == 0 {
+            return Ok(());
+        }
+
+        let end = base as u64 + size as u64;
+        if end > 1 << 32 {
+            return Err(VMExecutionError::ExecutionFailed(format!(
+                "Region at {:#x} of {} bytes exceeds the address space",
+                base, size
+            )));
+        }
+
+        let mapped = self
+            .mapped
+            .checked_add(size)
+            .filter(|&mapped| mapped <= self.memory_limit)
+            .ok_or(VMExecutionError::MemoryLimitExceeded)?;
+
+        let index = self.regions.partition_point(|region| region.base < base);
+        let overlaps_prev = index > 0 && self.regions[index - 1].end() > base as u64;
+        let overlaps_next = self
+            .regions
+            .get(index)
+            .is_some_and(|next| (next.base as u64) < end);
+        if overlaps_prev || overlaps_next {
+            return Err(VMExecutionError::ExecutionFailed(format!(
+                "Region at {:#x} of {} bytes overlaps an existing mapping",
+                base, size
+            )));
+        }
+
+        self.regions.insert(index, MemoryRegion {
+            base,
+            perms,
+            data: vec![0u8; size],
+        });
+        self.mapped = mapped;
+        Ok(())
+    }
+
+    /// Total bytes currently mapped
+    pub fn mapped_bytes(&self) -> usize {
+        self.mapped
+    }
+
+    /// Locate the region fully containing `[addr, addr + len)`
+    fn locate(&self, addr: u32, len: usize) -> Option<(usize, usize)> {
+        let index = self.regions.partition_point(|region| region.base <= addr).checked_sub(1)?;
+        let region = &self.regions[index];
+        if addr as u64 + len as u64 > region.end() {
+            return None;
+        }
+        Some((index, (addr - region.base) as usize))
+    }
+
+    /// Guest-visible bytes for an access requiring `perms`
+    fn slice(&self, addr: u32, len: usize, perms: Permissions) -> Option<&[u8]> {
+        let (index, offset) = self.locate(addr, len)?;
+        let region = &self.regions[index];
+        if !region.perms.contains(perms) {
+            return None;
+        }
+        Some(&region.data[offset..offset + len])
+    }
+
+    /// Mutable guest-visible bytes for an access requiring `perms`
+    fn slice_mut(&mut self, addr: u32, len: usize, perms: Permissions) -> Option<&mut [u8]> {
+        let (index, offset) = self.locate(addr, len)?;
+        let region = &mut self.regions[index];
+        if !region.perms.contains(perms) {
+            return None;
+        }
+        Some(&mut region.data[offset..offset + len])
+    }
+
+    /// Write bytes to mapped memory, bypassing permissions (loader use only)
+    pub fn write_bytes(&mut self, addr: u32, bytes: &[u8]) -> Result<(), VMExecutionError> {
+        let dest = self
+            .slice_mut(addr, bytes.len(), Permissions::NONE)
+            .ok_or_else(|| VMExecutionError::ExecutionFailed("Memory write out of bounds".to_string()))?;
+        dest.copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Read bytes from mapped memory, bypassing permissions (host use only)
+    pub fn read_bytes(&self, addr: u32, len: usize) -> Result<Vec<u8>, VMExecutionError> {
+        self.slice(addr, len, Permissions::NONE)
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| VMExecutionError::ExecutionFailed("Memory read out of bounds".to_string()))
+    }
+}
+
+/// Syscall buffers are held to the permissions guest accesses get
+impl GuestMemory for RiscVMemory {
+    fn guest_bytes(&self, addr: u32, len: usize) -> Option<&[u8]> {
+        self.slice(addr, len, Permissions::READ)
+    }
+
+    fn guest_bytes_mut(&mut self, addr: u32, len: usize) -> Option<&mut [u8]> {
+        self.slice_mut(addr, len, Permissions::WRITE)
+    }
+}
+
+impl Memory for RiscVMemory {
+    fn access<T: Copy>(&mut self, addr: u32, access: MemoryAccess<T>) -> bool {
+        let size = size_of::<T>();
+
+        // SAFETY: each slice is exactly `size_of::<T>()` bytes long and the
+        // unaligned read/write never assumes alignment. rvsim only accesses
+        // memory as plain integer types, for which any bit pattern is valid.
+        match access {
+            MemoryAccess::Load(dest) => match self.slice(addr, size, Permissions::READ) {
+                Some(src) => {
+                    *dest = unsafe { ptr::read_unaligned(src.as_ptr() as *const T) };
+                    true
+                }
+                None => false,
+            },
+            MemoryAccess::Exec(dest) => match self.slice(addr, size, Permissions::EXEC) {
+                Some(src) => {
+                    *dest = unsafe { ptr::read_unaligned(src.as_ptr() as *const T) };
+                    true
+                }
+                None => false,
+            },
+            MemoryAccess::Store(value) => match self.slice_mut(addr, size, Permissions::WRITE) {
+                Some(dst) => {
+                    unsafe { ptr::write_unaligned(dst.as_mut_ptr() as *mut T, value) };
+                    true
+                }
+                None => false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typed_access_respects_permissions() {
+        let mut memory = RiscVMemory::new(1024);
+        memory.map(0x1000, 8, Permissions::RX).unwrap();
+        memory.map(0x2000, 8, Permissions::RW).unwrap();
+        memory.write_bytes(0x1000, &0x0000_0013u32.to_le_bytes()).unwrap();
+
+        let mut word = 0u32;
+        assert!(memory.access(0x1000, MemoryAccess::Exec(&mut word)));
+        assert_eq!(word, 0x13);
+
+        // Code is not writable, data is not executable
+        assert!(!memory.access(0x1000, MemoryAccess::Store(1u32)));
+        assert!(!memory.access(0x2000, MemoryAccess::Exec(&mut word)));
+
+        // Unaligned halfword store and load round-trip
+        assert!(memory.access(0x2003, MemoryAccess::Store(0xbeefu16)));
+        let mut half = 0u16;
+        assert!(memory.access(0x2003, MemoryAccess::Load(&mut half)));
+        assert_eq!(half, 0xbeef);
+
+        // Accesses straddling the end of a region or unmapped memory fault
+        assert!(!memory.access(0x2006, MemoryAccess::Load(&mut word)));
+        assert!(!memory.access(0xffff_fffe, MemoryAccess::Load(&mut word)));
+        assert!(!memory.access(0x3000, MemoryAccess::Store(0u8)));
+    }
+
+    #[test]
+    fn test_map_rejects_overlap_wrap_and_limit() {
+        let mut memory = RiscVMemory::new(64);
+        memory.map(0x100, 16, Permissions::RW).unwrap();
+
+        assert!(memory.map(0x10f, 4, Permissions::RW).is_err());
+        assert!(memory.map(0xf0, 17, Permissions::RW).is_err());
+        assert!(memory.map(0xffff_fff0, 32, Permissions::RW).is_err());
+        assert!(matches!(
+            memory.map(0x1000, 49, Permissions::RW),
+            Err(VMExecutionError::MemoryLimitExceeded)
+        ));
+
+        memory.map(0x110, 48, Permissions::RW).unwrap();
+        assert!(memory.write_bytes(0x100, &[1; 64]).is_err());
+    }
+}
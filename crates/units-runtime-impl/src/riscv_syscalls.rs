@@ -0,0 +1,93 @@
+//! Built-in syscalls serviced for RISC-V kernel modules
+//!
+//! Decodes the `ecall` convention of [`units_types_ffi::syscall`] from the
+//! program's registers: stream I/O and logging go to the execution's
+//! [`GuestIo`], the slot and timestamp come from its context, and exit
+//! halts the program.
+
+use units_core_types::VMExecutionError;
+use units_types_ffi::syscall::{SYS_EXIT, SYS_GET_SLOT, SYS_GET_TIMESTAMP, SYS_LOG, SYS_READ, SYS_WRITE};
+
+use crate::guest_io::GuestIo;
+use crate::riscv_memory::RiscVMemory;
+
+const REG_A0: usize = 10;
+const REG_A1: usize = 11;
+const REG_A2: usize = 12;
+
+/// What execution does after an `ecall`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SyscallOutcome {
+    /// The call was serviced; continue after the `ecall`
+    Resume,
+    /// The program asked to halt with the status in `a0`
+    Exit,
+    /// Not a built-in call
+    Unhandled,
+}
+
+/// Service syscall `number` with the program's registers and memory
+pub(crate) fn service(
+    io: &mut GuestIo<'_>,
+    number: u32,
+    regs: &mut [u32; 32],
+    memory: &mut RiscVMemory,
+) -> Result<SyscallOutcome, VMExecutionError> {
+    let (a0, a1, a2) = (regs[REG_A0], regs[REG_A1], regs[REG_A2]);
+    let result = match number {
+        SYS_EXIT => return Ok(SyscallOutcome::Exit),
+        SYS_READ => io.read(a0 as i32, a1, a2 as usize, memory)?,
+        SYS_WRITE => io.write(a0 as i32, a1, a2 as usize, memory),
+        SYS_LOG => io.log(a0, a1 as usize, memory),
+        SYS_GET_SLOT | SYS_GET_TIMESTAMP => {
+            let value = if number == SYS_GET_SLOT { io.slot() } else { io.timestamp() };
+            regs[REG_A0] = value as u32;
+            regs[REG_A1] = (value >> 32) as u32;
+            return Ok(SyscallOutcome::Resume);
+        }
+        _ => return Ok(SyscallOutcome::Unhandled),
+    };
+    regs[REG_A0] = result as u32;
+    Ok(SyscallOutcome::Resume)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::riscv_memory::Permissions;
+    use std::collections::HashMap;
+    use units_core_types::{ExecutionContext, Instruction, UnitsObjectId};
+    use units_types_ffi::syscall::{EFAULT, STDIN};
+
+    fn call(io: &mut GuestIo<'_>, memory: &mut RiscVMemory, number: u32, args: [u32; 3]) -> (SyscallOutcome, u32, u32) {
+        let mut regs = [0u32; 32];
+        regs[REG_A0..=REG_A2].copy_from_slice(&args);
+        let outcome = service(io, number, &mut regs, memory).unwrap();
+        (outcome, regs[REG_A0], regs[REG_A1])
+    }
+
+    #[test]
+    fn test_syscalls_decode_registers_and_respect_permissions() {
+        let instruction = Instruction::new(UnitsObjectId::new([1; 32]), "run".to_string(), vec![], vec![]);
+        let context = ExecutionContext::new(instruction, HashMap::new(), 0x1_0000_0002, 3);
+        let mut io = GuestIo::new(&context, 64);
+        let mut memory = RiscVMemory::new(4096);
+        memory.map(0x1000, 16, Permissions::READ).unwrap();
+        memory.map(0x2000, 16, Permissions::RW).unwrap();
+
+        // Reads land in writable memory only
+        assert_eq!(call(&mut io, &mut memory, SYS_READ, [STDIN as u32, 0x1000, 4]).1 as i32, EFAULT);
+        assert_eq!(call(&mut io, &mut memory, SYS_READ, [STDIN as u32, 0x2000, 4]).1, 4);
+
+        // Slot and timestamp come from the context, split across a0 and a1
+        assert_eq!(call(&mut io, &mut memory, SYS_GET_SLOT, [0; 3]), (SyscallOutcome::Resume, 2, 1));
+        assert_eq!(call(&mut io, &mut memory, SYS_GET_TIMESTAMP, [0; 3]), (SyscallOutcome::Resume, 3, 0));
+
+        // Read-only memory can still be logged
+        assert_eq!(call(&mut io, &mut memory, SYS_LOG, [0x1000, 4, 0]).1, 0);
+        assert_eq!(io.logs().len(), 1);
+
+        assert_eq!(call(&mut io, &mut memory, SYS_EXIT, [0; 3]).0, SyscallOutcome::Exit);
+        assert_eq!(call(&mut io, &mut memory, 1000, [0; 3]).0, SyscallOutcome::Unhandled);
+    }
+}
@@ -0,0 +1,242 @@
+//! RVBC, the raw RISC-V bytecode image format
+//!
+//! RVBC wraps a bare stream of RV32 instructions for the executor, which
+//! maps it read/execute at [`CODE_BASE_ADDR`] and jumps to the entry offset.
+//! Version 1 images carry a 16-byte header, all fields little-endian:
+//!
+//! ```text
+//! [4 bytes] Magic: "RVBC" (0x52564243)
+//! [2 bytes] Format version: 1
+//! [2 bytes] Flags: reserved, must be zero
+//! [4 bytes] Entry offset into the code, 4-byte aligned
+//! [4 bytes] Code length, the exact number of bytes that follow
+//! [N bytes] RISC-V instructions
+//! ```
+//!
+//! Images written before the header was versioned have the entry offset
+//! right after the magic and no length; they are read as version 0. Their
+//! entry offset is 4-byte aligned, so its low half is a multiple of four,
+//! and format versions are chosen never to be one.
+
+use thiserror::Error;
+use units_core_types::VMExecutionError;
+
+/// Address the code of an image is loaded at
+pub const CODE_BASE_ADDR: u32 = 0x1000;
+
+/// Magic bytes opening every image
+pub const MAGIC: &[u8; 4] = b"RVBC";
+
+/// Version written by [`encode`]
+pub const FORMAT_VERSION: u16 = 1;
+
+/// Length of a version 1 header
+pub const HEADER_LEN: usize = 16;
+
+/// Length of the unversioned header: magic and entry offset
+pub const LEGACY_HEADER_LEN: usize = 8;
+
+/// Smallest runnable code: a single instruction
+const MIN_CODE_LEN: usize = 4;
+
+/// Why bytes are not a valid RVBC image
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RvbcError {
+    #[error("Bytecode too small: {0} bytes")]
+    TooSmall(usize),
+    #[error("Invalid bytecode magic")]
+    BadMagic,
+    #[error("Unsupported RVBC format version {0}")]
+    UnsupportedVersion(u16),
+    #[error("Unsupported RVBC flags {0:#06x}")]
+    UnsupportedFlags(u16),
+    #[error("Code length {declared} does not match the {actual} bytes after the header")]
+    LengthMismatch { declared: u32, actual: usize },
+    #[error("Code of {0} bytes does not fit an RVBC image")]
+    CodeTooLarge(usize),
+    #[error("Entry offset {0} must be 4-byte aligned")]
+    MisalignedEntry(u32),
+    #[error("Entry offset {entry_offset} out of bounds for {code_len} bytes of code")]
+    EntryOutOfBounds { entry_offset: u32, code_len: usize },
+}
+
+impl From<RvbcError> for VMExecutionError {
+    fn from(error: RvbcError) -> Self {
+        VMExecutionError::InvalidBytecode(error.to_string())
+    }
+}
+
+/// Header fields of an image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RvbcHeader {
+    /// 0 for an unversioned image
+    pub version: u16,
+    pub flags: u16,
+    pub entry_offset: u32,
+}
+
+/// A validated image, borrowing its code from the encoded bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RvbcImage<'a> {
+    pub header: RvbcHeader,
+    pub code: &'a [u8],
+}
+
+impl RvbcImage<'_> {
+    /// Absolute address execution starts at once the code is loaded
+    pub fn entry_point(&self) -> u32 {
+        CODE_BASE_ADDR + self.header.entry_offset
+    }
+}
+
+/// Whether `bytes` claim to be an RVBC image, valid or not
+pub fn is_rvbc(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Wrap a raw instruction stream into a version 1 image
+pub fn encode(code: &[u8], entry_offset: u32) -> Result<Vec<u8>, RvbcError> {
+    let code_len = u32::try_from(code.len()).map_err(|_| RvbcError::CodeTooLarge(code.len()))?;
+    check_code(code, entry_offset)?;
+
+    let mut image = Vec::with_capacity(HEADER_LEN + code.len());
+    image.extend_from_slice(MAGIC);
+    image.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    image.extend_from_slice(&0u16.to_le_bytes());
+    image.extend_from_slice(&entry_offset.to_le_bytes());
+    image.extend_from_slice(&code_len.to_le_bytes());
+    image.extend_from_slice(code);
+    Ok(image)
+}
+
+/// Check an image the executor could load, of either version
+pub fn validate(bytes: &[u8]) -> Result<RvbcImage<'_>, RvbcError> {
+    if !is_rvbc(bytes) {
+        return Err(RvbcError::BadMagic);
+    }
+    if bytes.len() < LEGACY_HEADER_LEN + MIN_CODE_LEN {
+        return Err(RvbcError::TooSmall(bytes.len()));
+    }
+
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version % 4 == 0 {
+        // Unversioned: the two bytes read are the low half of the entry offset
+        let entry_offset = read_u32(bytes, 4);
+        let code = &bytes[LEGACY_HEADER_LEN..];
+        check_code(code, entry_offset)?;
+        let header = RvbcHeader { version: 0, flags: 0, entry_offset };
+        return Ok(RvbcImage { header, code });
+    }
+    if version != FORMAT_VERSION {
+        return Err(RvbcError::UnsupportedVersion(version));
+    }
+    if bytes.len() < HEADER_LEN + MIN_CODE_LEN {
+        return Err(RvbcError::TooSmall(bytes.len()));
+    }
+
+    let flags = u16::from_le_bytes([bytes[6], bytes[7]]);
+    if flags != 0 {
+        return Err(RvbcError::UnsupportedFlags(flags));
+    }
+    let entry_offset = read_u32(bytes, 8);
+    let declared = read_u32(bytes, 12);
+    let code = &bytes[HEADER_LEN..];
+    if declared as usize != code.len() {
+        return Err(RvbcError::LengthMismatch { declared, actual: code.len() });
+    }
+    check_code(code, entry_offset)?;
+    Ok(RvbcImage {
+        header: RvbcHeader { version, flags, entry_offset },
+        code,
+    })
+}
+
+/// Check the code and entry offset shared by both versions
+fn check_code(code: &[u8], entry_offset: u32) -> Result<(), RvbcError> {
+    if code.len() < MIN_CODE_LEN {
+        return Err(RvbcError::TooSmall(code.len()));
+    }
+    if entry_offset % 4 != 0 {
+        return Err(RvbcError::MisalignedEntry(entry_offset));
+    }
+    if entry_offset as usize >= code.len() {
+        return Err(RvbcError::EntryOutOfBounds { entry_offset, code_len: code.len() });
+    }
+    Ok(())
+}
+
+/// Little-endian u32 at `offset`, which the caller has bounds-checked
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `addi x0, x0, 0` then `ecall`
+    const CODE: [u8; 8] = [0x13, 0x00, 0x00, 0x00, 0x73, 0x00, 0x00, 0x00];
+
+    /// Golden version 1 image of `CODE` entered at its `ecall`
+    const GOLDEN_V1: [u8; 24] = [
+        b'R', b'V', b'B', b'C', // magic
+        0x01, 0x00, // version
+        0x00, 0x00, // flags
+        0x04, 0x00, 0x00, 0x00, // entry offset
+        0x08, 0x00, 0x00, 0x00, // code length
+        0x13, 0x00, 0x00, 0x00, 0x73, 0x00, 0x00, 0x00,
+    ];
+
+    /// Golden unversioned image of `CODE` entered at its `ecall`
+    const GOLDEN_LEGACY: [u8; 16] = [
+        b'R', b'V', b'B', b'C', // magic
+        0x04, 0x00, 0x00, 0x00, // entry offset
+        0x13, 0x00, 0x00, 0x00, 0x73, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_golden_vectors() {
+        assert_eq!(encode(&CODE, 4).unwrap(), GOLDEN_V1);
+
+        let image = validate(&GOLDEN_V1).unwrap();
+        assert_eq!(image.header, RvbcHeader { version: 1, flags: 0, entry_offset: 4 });
+        assert_eq!(image.code, CODE);
+        assert_eq!(image.entry_point(), 0x1004);
+
+        let legacy = validate(&GOLDEN_LEGACY).unwrap();
+        assert_eq!(legacy.header, RvbcHeader { version: 0, flags: 0, entry_offset: 4 });
+        assert_eq!(legacy.code, image.code);
+        assert_eq!(legacy.entry_point(), image.entry_point());
+    }
+
+    #[test]
+    fn test_rejects_malformed_images() {
+        let with = |index: usize, byte: u8| {
+            let mut image = GOLDEN_V1;
+            image[index] = byte;
+            image
+        };
+
+        assert_eq!(validate(&with(0, b'X')), Err(RvbcError::BadMagic));
+        assert_eq!(validate(&with(4, 2)), Err(RvbcError::UnsupportedVersion(2)));
+        assert_eq!(validate(&with(6, 1)), Err(RvbcError::UnsupportedFlags(1)));
+        assert_eq!(validate(&with(8, 2)), Err(RvbcError::MisalignedEntry(2)));
+        assert_eq!(
+            validate(&with(8, 8)),
+            Err(RvbcError::EntryOutOfBounds { entry_offset: 8, code_len: 8 })
+        );
+        assert_eq!(
+            validate(&with(12, 4)),
+            Err(RvbcError::LengthMismatch { declared: 4, actual: 8 })
+        );
+        assert_eq!(
+            validate(&GOLDEN_V1[..GOLDEN_V1.len() - 4]),
+            Err(RvbcError::LengthMismatch { declared: 8, actual: 4 })
+        );
+        assert_eq!(validate(&GOLDEN_V1[..10]), Err(RvbcError::TooSmall(10)));
+        assert_eq!(validate(b"RVBC"), Err(RvbcError::TooSmall(4)));
+
+        assert_eq!(encode(&CODE[..2], 0), Err(RvbcError::TooSmall(2)));
+        assert_eq!(encode(&CODE, 6), Err(RvbcError::MisalignedEntry(6)));
+    }
+}
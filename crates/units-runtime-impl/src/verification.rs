@@ -3,12 +3,13 @@
 //! This module provides adapter functions for verifying transaction receipts against
 //! the underlying proof engine.
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use units_core_types::id::UnitsObjectId;
 use units_core_types::objects::UnitsObject;
 
-use units_core_types::{UnitsObjectProof, SlotNumber, StateProof, VerificationResult, Verifier};
+use units_core_types::{ProofStorage, UnitsObjectProof, UnitsStorage, SlotNumber, StateProof, VerificationResult, Verifier};
 use units_proofs::ProofEngine;
+use units_storage_impl::ConsolidatedUnitsStorage;
 
 use units_core_types::transaction::TransactionReceipt;
 
@@ -99,9 +100,13 @@ impl ProofVerifier {
 
     /// Verify a state proof
     ///
+    /// The proofs must cover exactly the objects the state proof lists, each
+    /// filed under its own object's ID, and their object root must be the
+    /// one the state proof commits to for its slot.
+    ///
     /// # Parameters
     /// * `state_proof` - The state proof to verify
-    /// * `object_proofs` - Proofs for objects included in the state proof
+    /// * `object_proofs` - Latest proof of every object in the state proof
     ///
     /// # Returns
     /// A VerificationResult indicating whether the state proof is valid
@@ -110,34 +115,64 @@ impl ProofVerifier {
         state_proof: &StateProof,
         object_proofs: &HashMap<UnitsObjectId, UnitsObjectProof>,
     ) -> VerificationResult {
-        // Convert HashMap to the format expected by the proof engine
-        let object_proof_pairs: Vec<(units_proofs::types::UnitsObjectId, units_proofs::types::UnitsObjectProof)> = object_proofs
-            .iter()
-            .map(|(id, proof)| {
-                (
-                    (*id).into(),
-                    units_proofs::types::UnitsObjectProof::new(
-                        proof.object_id.into(),
-                        proof.object_hash,
-                        proof.slot,
-                        proof.proof_data.clone(),
-                        None,
-                        proof.transaction_hash,
-                    )
-                )
-            })
-            .collect();
+        let data = match self.engine.state_proof_data(state_proof) {
+            Ok(data) => data,
+            Err(e) => return VerificationResult::Invalid(format!("Malformed state proof: {}", e)),
+        };
+        if data.slot != state_proof.slot {
+            return VerificationResult::Invalid(format!(
+                "State proof for slot {} commits to slot {}",
+                state_proof.slot, data.slot
+            ));
+        }
+
+        if let Some((id, proof)) = object_proofs.iter().find(|(id, proof)| proof.object_id != **id) {
+            return VerificationResult::Invalid(format!("Proof of {} given for {}", proof.object_id, id));
+        }
+        let committed: BTreeSet<&UnitsObjectId> = state_proof.object_ids.iter().collect();
+        if let Some(id) = committed.iter().find(|id| !object_proofs.contains_key(**id)) {
+            return VerificationResult::MissingData(format!("No proof for committed object {}", id));
+        }
+        if let Some(id) = object_proofs.keys().find(|id| !committed.contains(id)) {
+            return VerificationResult::Invalid(format!("Object {} is not in the state proof", id));
+        }
 
-        // Delegate to the proof engine for state proof verification
-        match self
-            .engine
-            .verify_state_proof(state_proof, &object_proof_pairs)
-        {
+        let object_proofs: Vec<_> = object_proofs.iter().map(|(id, proof)| (*id, proof.clone())).collect();
+        match self.engine.verify_state_proof(state_proof, &object_proofs) {
             Ok(true) => VerificationResult::Valid,
-            Ok(false) => VerificationResult::Invalid("State proof verification failed".to_string()),
+            Ok(false) => VerificationResult::Invalid("Object root does not match the state proof".to_string()),
             Err(e) => VerificationResult::Invalid(format!("Verification error: {}", e)),
         }
     }
+
+    /// Verify the state proof stored for a slot end to end
+    ///
+    /// Pulls the object proofs the state proof commits to from `storage`
+    /// and checks them with [`ProofVerifier::verify_state_proof`]. The
+    /// state proof must also link to the latest earlier state proof still
+    /// stored; earlier ones may have been pruned, so a first stored proof
+    /// is not required to have no predecessor.
+    pub fn verify_stored_state_proof(&self, storage: &ConsolidatedUnitsStorage, slot: SlotNumber) -> VerificationResult {
+        let state_proof = match storage.proofs().get_state_proof(slot) {
+            Ok(Some(state_proof)) => state_proof,
+            Ok(None) => return VerificationResult::MissingData(format!("No state proof for slot {}", slot)),
+            Err(e) => return VerificationResult::MissingData(format!("Cannot read state proof for slot {}: {}", slot, e)),
+        };
+
+        match storage.previous_state_proof(slot) {
+            Ok(Some(previous)) if state_proof.prev_state_proof_hash != Some(previous.hash()) => {
+                return VerificationResult::Invalid(format!(
+                    "State proof for slot {} does not link to the one for slot {}",
+                    slot, previous.slot
+                ));
+            }
+            Ok(_) => {}
+            Err(e) => return VerificationResult::MissingData(format!("Cannot read state proofs before slot {}: {}", slot, e)),
+        }
+
+        let object_proofs = storage.committed_proofs(&state_proof).into_iter().collect();
+        self.verify_state_proof(&state_proof, &object_proofs)
+    }
 }
 
 /// Verify if a transaction is included in a collection of receipts
@@ -266,6 +301,7 @@ impl Verifier for ProofVerifier {
 mod tests {
     use super::*;
     use units_core_types::id::UnitsObjectId;
+    use units_core_types::ObjectStorage;
     use units_proofs::ProofEngine;
 
     fn create_test_object() -> UnitsObject {
@@ -303,6 +339,53 @@ mod tests {
         assert!(matches!(invalid_result, VerificationResult::Invalid(_)));
     }
 
+    #[test]
+    fn test_verify_state_proof_against_object_set() {
+        let storage = ConsolidatedUnitsStorage::new_in_memory();
+        let verifier = ProofVerifier::new();
+        let objects: Vec<UnitsObject> = (1..=3u8)
+            .map(|seed| UnitsObject::new_data(UnitsObjectId::new([seed; 32]), UnitsObjectId::new([9; 32]), vec![seed]))
+            .collect();
+        for object in &objects[..2] {
+            storage.objects().set(object, None).unwrap();
+            // A second write chains the proof, which must still verify
+            storage.objects().set(object, None).unwrap();
+        }
+        let state_proof = storage.commit_state_proof(1, &[]).unwrap();
+        let proofs: HashMap<_, _> = storage.committed_proofs(&state_proof).into_iter().collect();
+        assert_eq!(verifier.verify_state_proof(&state_proof, &proofs), VerificationResult::Valid);
+
+        // The set must match exactly, and every proof must be the committed one
+        let mut missing = proofs.clone();
+        missing.remove(objects[0].id());
+        assert!(matches!(verifier.verify_state_proof(&state_proof, &missing), VerificationResult::MissingData(_)));
+        let mut extra = proofs.clone();
+        let stray = ProofEngine::new().generate_object_proof(&objects[2], None, None).unwrap();
+        extra.insert(*objects[2].id(), stray.clone());
+        assert!(matches!(verifier.verify_state_proof(&state_proof, &extra), VerificationResult::Invalid(_)));
+        let mut stale = proofs.clone();
+        stale.insert(*objects[0].id(), storage.inner().get_proof_chain(objects[0].id())[0].clone());
+        assert!(matches!(verifier.verify_state_proof(&state_proof, &stale), VerificationResult::Invalid(_)));
+        let mut misfiled = proofs.clone();
+        misfiled.insert(*objects[0].id(), proofs[objects[1].id()].clone());
+        assert!(matches!(verifier.verify_state_proof(&state_proof, &misfiled), VerificationResult::Invalid(_)));
+
+        // Stored proofs verify end to end, and earlier ones are not disturbed
+        // by objects created after them
+        storage.objects().set(&objects[2], None).unwrap();
+        storage.commit_state_proof(2, &[]).unwrap();
+        assert_eq!(verifier.verify_stored_state_proof(&storage, 1), VerificationResult::Valid);
+        assert_eq!(verifier.verify_stored_state_proof(&storage, 2), VerificationResult::Valid);
+        assert!(matches!(verifier.verify_stored_state_proof(&storage, 3), VerificationResult::MissingData(_)));
+
+        // A state proof that does not link to its predecessor is rejected
+        let unlinked = ProofEngine::new()
+            .generate_state_proof(&storage.inner().latest_proofs(), &[], None, 3)
+            .unwrap();
+        storage.proofs().store_state_proof(&unlinked).unwrap();
+        assert!(matches!(verifier.verify_stored_state_proof(&storage, 3), VerificationResult::Invalid(_)));
+    }
+
     #[test]
     fn test_verify_transaction_receipt() {
         // Create test objects and proofs
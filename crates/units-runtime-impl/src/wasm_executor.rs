@@ -0,0 +1,449 @@
+//! WebAssembly VM executor for the UNITS system
+//!
+//! Runs controllers compiled for `wasm32-unknown-unknown` under wasmtime,
+//! for teams that would rather not maintain a RISC-V toolchain.
+//!
+//! ## Module Interface
+//!
+//! A module exports its linear memory as `memory` and an `execute`
+//! function taking no arguments and returning the exit status. It talks to
+//! the host through functions imported from the `units` module, mirroring
+//! the syscalls in [`units_types_ffi::syscall`]:
+//!
+//! - `read(fd: i32, buf: i32, len: i32) -> i32`
+//! - `write(fd: i32, buf: i32, len: i32) -> i32`
+//! - `log(buf: i32, len: i32) -> i32`
+//! - `get_slot() -> i64` and `get_timestamp() -> i64`
+//! - `exit(status: i32)`, which does not return
+//!
+//! The streams behave as they do for RISC-V modules: the length-prefixed
+//! Borsh execution context is read from stdin and effects are written to
+//! stdout in the same framing. Modules built with the kernel SDK for
+//! `wasm32` import these functions already.
+//!
+//! ## Metering
+//!
+//! Execution consumes wasmtime fuel, one unit per executed operator, and
+//! each host call a surcharge on top; fuel is reported as compute units.
+//! Execution stops with `VMExecutionError::ComputeBudgetExceeded` once the
+//! budget is spent, which also bounds how long a module can run. Floating
+//! point NaNs are canonicalized so every node computes identical results.
+
+use std::fmt;
+use std::sync::OnceLock;
+
+use units_core_types::objects::VMType;
+use units_core_types::{ExecutionContext, ExecutionMetrics, ExecutionOutput, ObjectEffect, ResourceClass, VMExecutionError, VMExecutor};
+use wasmtime::{Caller, Config, Engine, Extern, Linker, Module, ResourceLimiter, Store, Trap};
+
+use crate::guest_io::GuestIo;
+
+/// Module name the host functions are imported from
+const IMPORT_MODULE: &str = "units";
+/// Export a module runs from
+const ENTRY_POINT: &str = "execute";
+/// Export holding the module's linear memory
+const MEMORY_EXPORT: &str = "memory";
+
+/// WebAssembly executor configuration
+#[derive(Debug, Clone)]
+pub struct WasmExecutorConfig {
+    /// Maximum linear memory size in bytes
+    pub memory_limit: usize,
+    /// Maximum compute units a module may consume
+    pub compute_budget: u64,
+    /// Compute units charged per host call
+    pub host_call_cost: u64,
+    /// Maximum size of the effects a module writes to stdout, in bytes
+    pub output_capacity: usize,
+}
+
+impl Default for WasmExecutorConfig {
+    fn default() -> Self {
+        Self {
+            memory_limit: 16 * 1024 * 1024, // 16MB
+            compute_budget: 2_000_000,
+            host_call_cost: 100,
+            output_capacity: 1024 * 1024, // 1MB
+        }
+    }
+}
+
+impl WasmExecutorConfig {
+    /// Preset budgets of a controller resource class
+    ///
+    /// The budgets match those of the RISC-V executor for the same class.
+    pub fn for_class(class: ResourceClass) -> Self {
+        match class {
+            ResourceClass::Small => Self {
+                memory_limit: 8 * 1024 * 1024, // 8MB
+                compute_budget: 500_000,
+                ..Self::default()
+            },
+            ResourceClass::Standard => Self::default(),
+            ResourceClass::Large => Self {
+                memory_limit: 64 * 1024 * 1024, // 64MB
+                compute_budget: 40_000_000,
+                ..Self::default()
+            },
+        }
+    }
+}
+
+/// The module called `exit` with this status
+#[derive(Debug)]
+struct ModuleExit(i32);
+
+impl fmt::Display for ModuleExit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "module exited with status {}", self.0)
+    }
+}
+
+impl std::error::Error for ModuleExit {}
+
+/// Per-execution state held by the store
+struct WasmState<'a> {
+    io: GuestIo<'a>,
+    memory_limit: usize,
+    peak_memory: usize,
+    memory_denied: bool,
+    host_calls: u64,
+    host_call_cost: u64,
+}
+
+impl ResourceLimiter for WasmState<'_> {
+    fn memory_growing(&mut self, _current: usize, desired: usize, _maximum: Option<usize>) -> wasmtime::Result<bool> {
+        if desired > self.memory_limit {
+            self.memory_denied = true;
+            return Ok(false);
+        }
+        self.peak_memory = self.peak_memory.max(desired);
+        Ok(true)
+    }
+
+    fn table_growing(&mut self, _current: usize, desired: usize, maximum: Option<usize>) -> wasmtime::Result<bool> {
+        Ok(maximum.is_none_or(|maximum| desired <= maximum))
+    }
+}
+
+/// Engine shared by every executor, so compiled code is configured alike
+fn engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        config.cranelift_nan_canonicalization(true);
+        Engine::new(&config).expect("wasmtime engine configuration is valid")
+    })
+}
+
+/// Charge a host call and run `call` against the module's memory
+fn host_call<R>(
+    caller: &mut Caller<'_, WasmState<'_>>,
+    call: impl FnOnce(&mut GuestIo<'_>, &mut [u8]) -> Result<R, VMExecutionError>,
+) -> wasmtime::Result<R> {
+    let cost = caller.data().host_call_cost;
+    let fuel = caller.get_fuel()?;
+    if fuel < cost {
+        caller.set_fuel(0)?;
+        return Err(Trap::OutOfFuel.into());
+    }
+    caller.set_fuel(fuel - cost)?;
+    caller.data_mut().host_calls += 1;
+
+    let memory = match caller.get_export(MEMORY_EXPORT) {
+        Some(Extern::Memory(memory)) => memory,
+        _ => return Err(VMExecutionError::InvalidBytecode(format!("Module does not export `{}`", MEMORY_EXPORT)).into()),
+    };
+    let (bytes, state) = memory.data_and_store_mut(caller);
+    Ok(call(&mut state.io, bytes)?)
+}
+
+/// Host functions imported by modules, as described in the module docs
+fn linker<'a>() -> Result<Linker<WasmState<'a>>, VMExecutionError> {
+    let mut linker = Linker::new(engine());
+    let define = |error: wasmtime::Error| VMExecutionError::ExecutionFailed(format!("Cannot define host function: {}", error));
+    linker
+        .func_wrap(IMPORT_MODULE, "read", |mut caller: Caller<'_, WasmState<'a>>, fd: i32, buf: u32, len: u32| {
+            host_call(&mut caller, |io, memory| io.read(fd, buf, len as usize, memory))
+        })
+        .map_err(define)?
+        .func_wrap(IMPORT_MODULE, "write", |mut caller: Caller<'_, WasmState<'a>>, fd: i32, buf: u32, len: u32| {
+            host_call(&mut caller, |io, memory| Ok(io.write(fd, buf, len as usize, memory)))
+        })
+        .map_err(define)?
+        .func_wrap(IMPORT_MODULE, "log", |mut caller: Caller<'_, WasmState<'a>>, buf: u32, len: u32| {
+            host_call(&mut caller, |io, memory| Ok(io.log(buf, len as usize, memory)))
+        })
+        .map_err(define)?
+        .func_wrap(IMPORT_MODULE, "get_slot", |mut caller: Caller<'_, WasmState<'a>>| {
+            host_call(&mut caller, |io, _| Ok(io.slot() as i64))
+        })
+        .map_err(define)?
+        .func_wrap(IMPORT_MODULE, "get_timestamp", |mut caller: Caller<'_, WasmState<'a>>| {
+            host_call(&mut caller, |io, _| Ok(io.timestamp() as i64))
+        })
+        .map_err(define)?
+        .func_wrap(IMPORT_MODULE, "exit", |mut caller: Caller<'_, WasmState<'a>>, status: i32| -> wasmtime::Result<()> {
+            host_call(&mut caller, |_, _| Ok(()))?;
+            Err(ModuleExit(status).into())
+        })
+        .map_err(define)?;
+    Ok(linker)
+}
+
+/// WebAssembly VM executor implementation using wasmtime
+pub struct WasmExecutor {
+    config: WasmExecutorConfig,
+}
+
+impl WasmExecutor {
+    /// Create a new WebAssembly executor with default configuration
+    pub fn new() -> Self {
+        Self::with_config(WasmExecutorConfig::default())
+    }
+
+    /// Create a new WebAssembly executor with custom configuration
+    pub fn with_config(config: WasmExecutorConfig) -> Self {
+        Self { config }
+    }
+
+    /// Map a failed call to the error it stands for
+    fn execution_error(&self, state: &WasmState<'_>, error: wasmtime::Error) -> VMExecutionError {
+        if state.memory_denied {
+            return VMExecutionError::MemoryLimitExceeded;
+        }
+        if let Some(Trap::OutOfFuel) = error.downcast_ref::<Trap>() {
+            return VMExecutionError::ComputeBudgetExceeded(self.config.compute_budget);
+        }
+        match error.downcast::<VMExecutionError>() {
+            Ok(error) => error,
+            Err(error) => VMExecutionError::ExecutionFailed(format!("{:#}", error)),
+        }
+    }
+
+    /// Instantiate and run a module, returning the effects and events it wrote and its resource usage
+    fn execute_module(
+        &self,
+        bytecode: &[u8],
+        context: &ExecutionContext,
+    ) -> Result<ExecutionOutput, VMExecutionError> {
+        let module = Module::new(engine(), bytecode)
+            .map_err(|e| VMExecutionError::InvalidBytecode(format!("Invalid WebAssembly module: {}", e)))?;
+
+        let mut store = Store::new(
+            engine(),
+            WasmState {
+                io: GuestIo::new(context, self.config.output_capacity + 4),
+                memory_limit: self.config.memory_limit,
+                peak_memory: 0,
+                memory_denied: false,
+                host_calls: 0,
+                host_call_cost: self.config.host_call_cost,
+            },
+        );
+        store.limiter(|state| state as &mut dyn ResourceLimiter);
+        store
+            .set_fuel(self.config.compute_budget)
+            .map_err(|e| VMExecutionError::ExecutionFailed(e.to_string()))?;
+
+        let result = linker()?
+            .instantiate(&mut store, &module)
+            .and_then(|instance| instance.get_typed_func::<(), i32>(&mut store, ENTRY_POINT))
+            .and_then(|entry| entry.call(&mut store, ()));
+        let status = match result {
+            Ok(status) => status,
+            Err(error) => match error.downcast_ref::<ModuleExit>() {
+                Some(ModuleExit(status)) => *status,
+                None => return Err(self.execution_error(store.data(), error)),
+            },
+        };
+
+        let compute_units = self.config.compute_budget - store.get_fuel().unwrap_or(0);
+        let state = store.data();
+        let metrics = ExecutionMetrics {
+            instructions_executed: compute_units - state.host_calls * state.host_call_cost,
+            peak_memory_bytes: state.peak_memory as u64,
+            syscall_count: state.host_calls,
+            compute_units,
+        };
+        if status != 0 {
+            return Err(VMExecutionError::ModuleError(status as u32));
+        }
+
+        // Modules that write nothing to stdout have no effects
+        let (effects, events) = state.io.effects().transpose()?.unwrap_or_default();
+        Ok(ExecutionOutput { effects, events, metrics })
+    }
+}
+
+impl Default for WasmExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VMExecutor for WasmExecutor {
+    fn vm_type(&self) -> VMType {
+        VMType::Wasm
+    }
+
+    fn load_and_execute(
+        &self,
+        bytecode: &[u8],
+        context: &ExecutionContext,
+    ) -> Result<Vec<ObjectEffect>, VMExecutionError> {
+        self.load_and_execute_with_metrics(bytecode, context).map(|(effects, _)| effects)
+    }
+
+    fn load_and_execute_with_metrics(
+        &self,
+        bytecode: &[u8],
+        context: &ExecutionContext,
+    ) -> Result<(Vec<ObjectEffect>, ExecutionMetrics), VMExecutionError> {
+        self.load_and_execute_with_events(bytecode, context).map(|output| (output.effects, output.metrics))
+    }
+
+    fn load_and_execute_with_events(
+        &self,
+        bytecode: &[u8],
+        context: &ExecutionContext,
+    ) -> Result<ExecutionOutput, VMExecutionError> {
+        let output = self.execute_module(bytecode, context)?;
+        units_core_types::validate_object_effects(&output.effects, context.instruction.controller_id)?;
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use units_core_types::constants::TOKEN_CONTROLLER_ID;
+    use units_core_types::transaction::Instruction;
+
+    fn test_context() -> ExecutionContext {
+        let instruction = Instruction::new(TOKEN_CONTROLLER_ID, "test".to_string(), vec![], vec![]);
+        ExecutionContext::new(instruction, HashMap::new(), 42, 1_700_000_000)
+    }
+
+    fn module(wat: &str) -> Vec<u8> {
+        wat::parse_str(wat).unwrap()
+    }
+
+    #[test]
+    fn test_exit_status() {
+        let executor = WasmExecutor::new();
+        assert_eq!(executor.vm_type(), VMType::Wasm);
+
+        let returns = module(r#"(module (memory (export "memory") 1) (func (export "execute") (result i32) i32.const 0))"#);
+        let (effects, metrics) = executor.load_and_execute_with_metrics(&returns, &test_context()).unwrap();
+        assert!(effects.is_empty());
+        assert!(metrics.compute_units > 0);
+        assert_eq!(metrics.syscall_count, 0);
+
+        // A status from `exit` stops the module just like returning it
+        let exits = module(
+            r#"(module
+                (import "units" "exit" (func $exit (param i32)))
+                (memory (export "memory") 1)
+                (func (export "execute") (result i32) i32.const 3 call $exit unreachable))"#,
+        );
+        assert!(matches!(
+            executor.load_and_execute(&exits, &test_context()),
+            Err(VMExecutionError::ModuleError(3))
+        ));
+
+        assert!(matches!(
+            executor.load_and_execute(b"\x7fELF", &test_context()),
+            Err(VMExecutionError::InvalidBytecode(_))
+        ));
+    }
+
+    #[test]
+    fn test_streams_and_host_calls() {
+        // Echo the slot into the status, then write an empty effect list to stdout
+        let wat = r#"(module
+            (import "units" "get_slot" (func $slot (result i64)))
+            (import "units" "read" (func $read (param i32 i32 i32) (result i32)))
+            (import "units" "write" (func $write (param i32 i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 16) "\04\00\00\00\00\00\00\00")
+            (func (export "execute") (result i32)
+                (if (i64.ne (call $slot) (i64.const 42)) (then (return (i32.const 1))))
+                (if (i32.ne (call $read (i32.const 0) (i32.const 0) (i32.const 4)) (i32.const 4))
+                    (then (return (i32.const 2))))
+                (if (i32.ne (call $write (i32.const 1) (i32.const 16) (i32.const 8)) (i32.const 8))
+                    (then (return (i32.const 3))))
+                i32.const 0))"#;
+        let executor = WasmExecutor::new();
+        let (effects, metrics) = executor.load_and_execute_with_metrics(&module(wat), &test_context()).unwrap();
+        assert!(effects.is_empty());
+        assert_eq!(metrics.syscall_count, 3);
+        assert!(metrics.compute_units >= 3 * executor.config.host_call_cost);
+        assert_eq!(metrics.peak_memory_bytes, 64 * 1024);
+
+        // Garbage on stdout is rejected rather than read as no effects
+        let garbage = wat.replace(r#"\04\00\00\00\00\00\00\00"#, r#"\09\00\00\00\00\00\00\00"#);
+        assert!(matches!(
+            executor.load_and_execute(&module(&garbage), &test_context()),
+            Err(VMExecutionError::SerializationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_events_follow_effects_on_stdout() {
+        use std::fmt::Write;
+
+        let events = vec![units_core_types::KernelEvent::new(TOKEN_CONTROLLER_ID, "Mint", vec![1, 2])];
+        let mut body = borsh::to_vec(&Vec::<ObjectEffect>::new()).unwrap();
+        body.extend(borsh::to_vec(&events).unwrap());
+        let mut frame = (body.len() as u32).to_le_bytes().to_vec();
+        frame.extend(body);
+        let data = frame.iter().fold(String::new(), |mut data, byte| {
+            let _ = write!(data, "\\{:02x}", byte);
+            data
+        });
+        let wat = format!(
+            r#"(module
+                (import "units" "write" (func $write (param i32 i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 16) "{}")
+                (func (export "execute") (result i32)
+                    (drop (call $write (i32.const 1) (i32.const 16) (i32.const {})))
+                    i32.const 0))"#,
+            data,
+            frame.len()
+        );
+
+        let output = WasmExecutor::new().load_and_execute_with_events(&module(&wat), &test_context()).unwrap();
+        assert!(output.effects.is_empty());
+        assert_eq!(output.events, events);
+        assert_eq!(output.metrics.syscall_count, 1);
+    }
+
+    #[test]
+    fn test_compute_budget_and_memory_limit() {
+        let spins = module(r#"(module (memory (export "memory") 1) (func (export "execute") (result i32) (loop br 0) i32.const 0))"#);
+        let executor = WasmExecutor::with_config(WasmExecutorConfig { compute_budget: 10_000, ..WasmExecutorConfig::default() });
+        assert!(matches!(
+            executor.load_and_execute(&spins, &test_context()),
+            Err(VMExecutionError::ComputeBudgetExceeded(10_000))
+        ));
+
+        let grows = module(
+            r#"(module (memory (export "memory") 1)
+                (func (export "execute") (result i32)
+                    (if (i32.lt_s (memory.grow (i32.const 4)) (i32.const 0)) (then unreachable))
+                    i32.const 0))"#,
+        );
+        let executor = WasmExecutor::with_config(WasmExecutorConfig { memory_limit: 128 * 1024, ..WasmExecutorConfig::default() });
+        assert!(matches!(
+            executor.load_and_execute(&grows, &test_context()),
+            Err(VMExecutionError::MemoryLimitExceeded)
+        ));
+        let (_, metrics) = WasmExecutor::new().load_and_execute_with_metrics(&grows, &test_context()).unwrap();
+        assert_eq!(metrics.peak_memory_bytes, 5 * 64 * 1024);
+    }
+}
@@ -0,0 +1,63 @@
+//! Storage savings and CPU cost of the receipt codecs
+//!
+//! Run with `cargo bench -p units-storage-impl --bench codec`. The stored size
+//! of each codec is printed before its timings.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use units_core_types::objects::UnitsObject;
+use units_core_types::transaction::TransactionReceipt;
+use units_core_types::UnitsObjectId;
+use units_storage_impl::{Codec, CodecConfig, InMemoryReceiptStorage, ReceiptStorage};
+
+/// Receipt touching `effects` objects, each with a before and after image
+fn receipt(hash: u8, effects: usize) -> TransactionReceipt {
+    let mut receipt = TransactionReceipt::new([hash; 32], 1, true, 1_700_000_000);
+    for i in 0..effects {
+        let id = UnitsObjectId::new([i as u8; 32]);
+        let controller = UnitsObjectId::new([0xc0; 32]);
+        let before = UnitsObject::new_data(id, controller, vec![i as u8; 256]);
+        let after = UnitsObject::new_data(id, controller, vec![i as u8 ^ 0xff; 256]);
+        receipt.add_object_effect([hash; 32], id, Some(before), Some(after));
+    }
+    receipt
+}
+
+fn bench_codecs(c: &mut Criterion) {
+    let codecs = [Codec::None, Codec::Lz4, Codec::Zstd];
+
+    for effects in [4, 64] {
+        let raw = bincode::serialize(&receipt(1, effects)).unwrap();
+
+        let mut group = c.benchmark_group(format!("receipt_{}_effects", effects));
+        group.throughput(Throughput::Bytes(raw.len() as u64));
+        for codec in codecs {
+            let config = CodecConfig { threshold_bytes: 0, ..CodecConfig::new(codec) };
+
+            let storage = InMemoryReceiptStorage::with_codec(config.clone());
+            for hash in 0..32 {
+                storage.store_receipt(&receipt(hash, effects)).unwrap();
+            }
+            let stats = storage.stats();
+            println!(
+                "{:?} with {} effects: {} raw bytes stored in {} ({:.1}%)",
+                codec,
+                effects,
+                stats.raw_bytes,
+                stats.stored_bytes,
+                100.0 * stats.stored_bytes as f64 / stats.raw_bytes as f64,
+            );
+
+            let (used, stored) = config.encode(raw.clone()).unwrap();
+            group.bench_with_input(BenchmarkId::new("encode", format!("{:?}", codec)), &raw, |b, raw| {
+                b.iter(|| config.encode(black_box(raw.clone())).unwrap())
+            });
+            group.bench_with_input(BenchmarkId::new("decode", format!("{:?}", codec)), &stored, |b, stored| {
+                b.iter(|| used.decompress(black_box(stored)).unwrap())
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_codecs);
+criterion_main!(benches);
@@ -0,0 +1,64 @@
+//! Storage reads per instruction with and without the executable cache
+//!
+//! Run with `cargo bench -p units-storage-impl --bench executables`. The
+//! storage reads each instruction costs are printed before the timings.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use units_core_types::objects::{UnitsObject, VMType};
+use units_core_types::transaction::Instruction;
+use units_core_types::{ObjectStorage, TransactionView, UnitsObjectId};
+use units_storage_impl::{ConsolidatedUnitsStorage, UnitsStorage};
+
+/// Storage holding a controller with `code_len` bytes of code and the
+/// object its instructions target
+fn storage(code_len: usize) -> (ConsolidatedUnitsStorage, Instruction) {
+    let storage = ConsolidatedUnitsStorage::create();
+    let controller_id = UnitsObjectId::new([0xc0; 32]);
+    let controller = UnitsObject::new_executable(controller_id, controller_id, VMType::RiscV, vec![0x13; code_len]);
+    let target = UnitsObject::new_data(UnitsObjectId::new([1; 32]), controller_id, vec![0; 64]);
+    storage.objects().set(&controller, None).unwrap();
+    storage.objects().set(&target, None).unwrap();
+    let instruction = Instruction::new(controller_id, "transfer".to_string(), vec![*target.id()], vec![]);
+    (storage, instruction)
+}
+
+/// Load an instruction's objects through a fresh view, as each transaction does
+fn load_objects(storage: &ConsolidatedUnitsStorage, instruction: &Instruction, cached: bool) -> usize {
+    let objects = storage.objects();
+    let load = |id: &UnitsObjectId| objects.get(id);
+    let mut view = TransactionView::new(&load);
+    if cached {
+        view = view.with_executables(storage.executables());
+    }
+    view.objects_for(instruction, &[]).unwrap().len()
+}
+
+fn bench_executables(c: &mut Criterion) {
+    const INSTRUCTIONS: u64 = 1000;
+
+    for code_len in [4 * 1024, 256 * 1024] {
+        let mut group = c.benchmark_group(format!("controller_{}k", code_len / 1024));
+        for cached in [false, true] {
+            let (storage, instruction) = storage(code_len);
+            let before = storage.metrics().gets;
+            for _ in 0..INSTRUCTIONS {
+                load_objects(&storage, &instruction, cached);
+            }
+            println!(
+                "{} KiB controller, {}: {:.2} storage reads per instruction",
+                code_len / 1024,
+                if cached { "cached" } else { "uncached" },
+                (storage.metrics().gets - before) as f64 / INSTRUCTIONS as f64,
+            );
+
+            let name = if cached { "cached" } else { "uncached" };
+            group.bench_with_input(BenchmarkId::new("objects_for", name), &instruction, |b, instruction| {
+                b.iter(|| load_objects(&storage, black_box(instruction), cached))
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_executables);
+criterion_main!(benches);
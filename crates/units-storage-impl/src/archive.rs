@@ -0,0 +1,288 @@
+//! Object archival export/import
+//!
+//! An `ObjectArchive` bundles an object with its complete proof chain and the
+//! receipts that touched it, so it can be moved between nodes or namespaces.
+//! Imports verify the chain end to end and then link it to the local slot
+//! timeline with a bridging proof.
+//!
+//! `verify_objects` checks many objects' archives at once, spread over the
+//! rayon thread pool with the `parallel` feature, for scrubs and snapshot
+//! verification.
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use units_core_types::error::StorageError;
+use units_core_types::id::UnitsObjectId;
+use units_core_types::objects::UnitsObject;
+use units_core_types::transaction::TransactionReceipt;
+use units_core_types::{ObjectStorage, ReceiptStorage, UnitsObjectProof, UnitsStorage};
+use units_proofs::{ProofEngine, ProofHasher};
+
+use crate::consolidated_storage::ConsolidatedUnitsStorage;
+
+/// Portable bundle of an object, its proof chain and related receipts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectArchive {
+    /// Current state of the object
+    pub object: UnitsObject,
+    /// Every proof for the object, oldest first
+    pub proof_chain: Vec<UnitsObjectProof>,
+    /// Receipts of transactions that modified the object
+    pub receipts: Vec<TransactionReceipt>,
+}
+
+impl ObjectArchive {
+    /// Encode the archive for transport
+    pub fn to_bytes(&self) -> Result<Vec<u8>, StorageError> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    /// Decode an archive produced by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, StorageError> {
+        Ok(bincode::deserialize(bytes)?)
+    }
+
+    /// Verify the proof chain and receipts against the archived object
+    ///
+    /// Checks that every proof belongs to the object, that each proof links
+    /// to its predecessor in an order allowed by the engine's slot ordering,
+    /// that the tip commits to the archived state, and that every receipt is
+    /// referenced by a proof in the chain.
+    pub fn verify<H: ProofHasher>(&self, engine: &ProofEngine<H>) -> Result<(), StorageError> {
+        let id = *self.object.id();
+        let tip = self.proof_chain.last().ok_or_else(|| {
+            StorageError::ProofMissingData(id, "archive has an empty proof chain".to_string())
+        })?;
+
+        let mut prev_hash = None;
+        for (i, proof) in self.proof_chain.iter().enumerate() {
+            if proof.object_id != id {
+                return Err(StorageError::ProofChainInvalid(format!(
+                    "proof {} is for object {:?}, expected {:?}",
+                    i, proof.object_id, id
+                )));
+            }
+            if proof.prev_proof_hash != prev_hash {
+                return Err(StorageError::ProofChainInvalid(format!(
+                    "proof {} does not link to its predecessor",
+                    i
+                )));
+            }
+            prev_hash = Some(proof.hash());
+        }
+        if let Some(regression) = engine.slot_regressions(&self.proof_chain).first() {
+            return Err(StorageError::ProofChainInvalid(format!(
+                "proof {} at slot {} follows a proof at slot {}",
+                regression.index, regression.slot, regression.prev_slot
+            )));
+        }
+
+        if !engine.verify_object_proof(&self.object, tip)? {
+            return Err(StorageError::ProofVerification(
+                "latest proof does not commit to the archived object".to_string(),
+            ));
+        }
+
+        let chain_txs: HashSet<[u8; 32]> = self
+            .proof_chain
+            .iter()
+            .filter_map(|proof| proof.transaction_hash)
+            .collect();
+        for receipt in &self.receipts {
+            if !chain_txs.contains(&receipt.transaction_hash) {
+                return Err(StorageError::ProofChainInvalid(format!(
+                    "receipt {:?} is not referenced by the proof chain",
+                    receipt.transaction_hash
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Progress of a bulk verification, reported after each object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifyProgress {
+    /// Objects checked so far, including failures
+    pub verified: usize,
+    pub failed: usize,
+    pub total: usize,
+}
+
+impl ConsolidatedUnitsStorage {
+    /// Verify the proof chains of `ids` in parallel
+    ///
+    /// Each object is exported and checked as `ObjectArchive::verify` would.
+    /// `progress` is called from worker threads once per object, one call at
+    /// a time with counts that only grow. Returns the objects that failed, in
+    /// the order of `ids`.
+    pub fn verify_objects<H, F>(
+        &self,
+        ids: &[UnitsObjectId],
+        engine: &ProofEngine<H>,
+        progress: F,
+    ) -> Vec<(UnitsObjectId, StorageError)>
+    where
+        H: ProofHasher,
+        F: Fn(VerifyProgress) + Sync,
+    {
+        let state = Mutex::new(VerifyProgress { verified: 0, failed: 0, total: ids.len() });
+        #[cfg(feature = "parallel")]
+        let ids = ids.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let ids = ids.iter();
+        ids.filter_map(|id| {
+                let result = self.export_object(id).and_then(|archive| archive.verify(engine));
+                {
+                    let mut state = state.lock().unwrap();
+                    state.verified += 1;
+                    state.failed += result.is_err() as usize;
+                    progress(*state);
+                }
+                result.err().map(|error| (*id, error))
+            })
+            .collect()
+    }
+
+    /// Export an object with its full proof chain and receipts
+    pub fn export_object(&self, id: &UnitsObjectId) -> Result<ObjectArchive, StorageError> {
+        let objects = self.inner();
+        let object = objects
+            .get(id)?
+            .ok_or_else(|| StorageError::NotFound(format!("Object not found: {:?}", id)))?;
+
+        let proof_chain = objects.get_proof_chain(id);
+        if proof_chain.is_empty() {
+            return Err(StorageError::ProofNotFound(*id));
+        }
+
+        let chain_txs: HashSet<[u8; 32]> = proof_chain
+            .iter()
+            .filter_map(|proof| proof.transaction_hash)
+            .collect();
+        let receipts = self
+            .receipts()
+            .get_receipts_for_object(id, None, None)?
+            .into_iter()
+            .filter(|receipt| chain_txs.contains(&receipt.transaction_hash))
+            .collect();
+
+        Ok(ObjectArchive {
+            object,
+            proof_chain,
+            receipts,
+        })
+    }
+
+    /// Import an archived object, verifying its chain first
+    ///
+    /// Returns the bridging proof that links the imported chain to the local
+    /// slot timeline. Fails if the object already exists locally.
+    pub fn import_object(&self, archive: ObjectArchive) -> Result<UnitsObjectProof, StorageError> {
+        archive.verify(&ProofEngine::new())?;
+
+        let bridge = self
+            .inner()
+            .restore_with_proof_chain(&archive.object, archive.proof_chain)?;
+
+        let receipts = self.receipts();
+        for receipt in &archive.receipts {
+            if receipts.get_receipt(&receipt.transaction_hash)?.is_none() {
+                receipts.store_receipt(receipt)?;
+            }
+        }
+
+        Ok(bridge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn populated_storage(id: UnitsObjectId) -> ConsolidatedUnitsStorage {
+        let storage = ConsolidatedUnitsStorage::new_in_memory();
+        let controller = UnitsObjectId::new([9; 32]);
+        for (i, tx) in [[1u8; 32], [2u8; 32]].iter().enumerate() {
+            let object = UnitsObject::new_data(id, controller, vec![i as u8; 4]);
+            let proof = storage.objects().set(&object, Some(*tx)).unwrap();
+            let mut receipt = TransactionReceipt::new(*tx, proof.slot, true, 0);
+            receipt.object_proofs.insert(id, proof);
+            storage.receipts().store_receipt(&receipt).unwrap();
+        }
+        storage
+    }
+
+    #[test]
+    fn test_export_import_roundtrip() {
+        let id = UnitsObjectId::new([5; 32]);
+        let source = populated_storage(id);
+
+        let archive = source.export_object(&id).unwrap();
+        assert_eq!(archive.proof_chain.len(), 2);
+        assert_eq!(archive.receipts.len(), 2);
+
+        let bytes = archive.to_bytes().unwrap();
+        let target = ConsolidatedUnitsStorage::new_in_memory();
+        let bridge = target.import_object(ObjectArchive::from_bytes(&bytes).unwrap()).unwrap();
+
+        assert_eq!(bridge.prev_proof_hash, Some(archive.proof_chain[1].hash()));
+        assert_eq!(target.objects().get(&id).unwrap(), Some(archive.object.clone()));
+        assert_eq!(target.inner().get_proof_chain(&id).len(), 3);
+        assert!(target.receipts().get_receipt(&[1u8; 32]).unwrap().is_some());
+
+        // A second import of the same object is rejected
+        assert!(target.import_object(archive).is_err());
+    }
+
+    #[test]
+    fn test_import_rejects_tampered_archive() {
+        let id = UnitsObjectId::new([5; 32]);
+        let source = populated_storage(id);
+        let target = ConsolidatedUnitsStorage::new_in_memory();
+
+        let mut tampered = source.export_object(&id).unwrap();
+        tampered.object.data = vec![0xff];
+        assert!(matches!(
+            target.import_object(tampered),
+            Err(StorageError::ProofVerification(_))
+        ));
+
+        let mut broken = source.export_object(&id).unwrap();
+        broken.proof_chain.remove(0);
+        assert!(matches!(
+            target.import_object(broken),
+            Err(StorageError::ProofChainInvalid(_))
+        ));
+
+        assert!(!target.objects().exists(&id).unwrap());
+    }
+
+    #[test]
+    fn test_verify_objects_reports_failures_and_progress() {
+        let storage = ConsolidatedUnitsStorage::new_in_memory();
+        let controller = UnitsObjectId::new([9; 32]);
+        let ids: Vec<UnitsObjectId> = (0..64u8).map(|i| UnitsObjectId::new([i; 32])).collect();
+        for id in &ids {
+            storage.objects().set(&UnitsObject::new_data(*id, controller, vec![1]), None).unwrap();
+        }
+        let missing = UnitsObjectId::new([0xee; 32]);
+        let mut checked = ids.clone();
+        checked.insert(10, missing);
+
+        let reports = std::sync::Mutex::new(Vec::new());
+        let failures = storage.verify_objects(&checked, &ProofEngine::new(), |p| reports.lock().unwrap().push(p));
+
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, missing);
+        let reports = reports.into_inner().unwrap();
+        assert_eq!(reports.len(), checked.len());
+        assert!(reports.windows(2).all(|w| w[0].verified < w[1].verified));
+        assert_eq!(reports.last(), Some(&VerifyProgress { verified: 65, failed: 1, total: 65 }));
+    }
+}
@@ -0,0 +1,416 @@
+//! Fault injection for testing storage composition
+//!
+//! [`ChaosStorage`] wraps any object, proof or receipt storage and, on a
+//! seeded schedule, slows operations down, fails them before they reach
+//! the wrapped storage, or applies only part of a batch before failing.
+//! The same seed replays the same faults for the same sequence of calls,
+//! so a failing run can be reproduced.
+
+use std::collections::HashMap;
+use std::ops::RangeBounds;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+use units_core_types::error::StorageError;
+use units_core_types::id::UnitsObjectId;
+use units_core_types::objects::UnitsObject;
+use units_core_types::transaction::{TransactionEvent, TransactionHash, TransactionReceipt};
+use units_core_types::{BatchOp, HistoricalStorage, ObjectStorage, ProofStorage, ReceiptPage, ReceiptStorage, SlotReceiptsIter};
+use units_core_types::{SlotNumber, StateProof, UnitsObjectProof};
+
+/// Faults a [`ChaosStorage`] injects
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ChaosConfig {
+    /// Delay added to every operation
+    pub latency: Duration,
+    /// Chance, from 0 to 1, that an operation fails without running
+    pub error_rate: f64,
+    /// Chance, from 0 to 1, that a batch write applies only some of its
+    /// operations and then fails
+    pub partial_write_rate: f64,
+    /// Seed of the fault schedule
+    pub seed: u64,
+}
+
+impl ChaosConfig {
+    /// No faults yet, scheduled from `seed`
+    pub fn new(seed: u64) -> Self {
+        Self { seed, ..Self::default() }
+    }
+
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    pub fn with_error_rate(mut self, error_rate: f64) -> Self {
+        self.error_rate = error_rate;
+        self
+    }
+
+    pub fn with_partial_write_rate(mut self, partial_write_rate: f64) -> Self {
+        self.partial_write_rate = partial_write_rate;
+        self
+    }
+}
+
+/// Counts of the operations seen and faults injected
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChaosStats {
+    pub operations: u64,
+    pub injected_errors: u64,
+    pub partial_writes: u64,
+}
+
+/// Storage decorator injecting latency, errors and partial writes
+///
+/// Faults are only injected while enabled, so tests can set up state and
+/// check the outcome through the same wrapper.
+pub struct ChaosStorage<S> {
+    inner: S,
+    config: ChaosConfig,
+    enabled: AtomicBool,
+    /// State of the splitmix64 fault schedule
+    schedule: AtomicU64,
+    operations: AtomicU64,
+    injected_errors: AtomicU64,
+    partial_writes: AtomicU64,
+}
+
+impl<S> ChaosStorage<S> {
+    /// Wrap `inner`, injecting the faults of `config`
+    pub fn new(inner: S, config: ChaosConfig) -> Self {
+        Self {
+            inner,
+            config,
+            enabled: AtomicBool::new(true),
+            schedule: AtomicU64::new(config.seed),
+            operations: AtomicU64::new(0),
+            injected_errors: AtomicU64::new(0),
+            partial_writes: AtomicU64::new(0),
+        }
+    }
+
+    /// Storage the faults are injected in front of
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Turn fault injection on or off
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn stats(&self) -> ChaosStats {
+        ChaosStats {
+            operations: self.operations.load(Ordering::Relaxed),
+            injected_errors: self.injected_errors.load(Ordering::Relaxed),
+            partial_writes: self.partial_writes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Next value of the fault schedule
+    fn next(&self) -> u64 {
+        let mut z = self.schedule.fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed).wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Whether an event with probability `rate` happens this time
+    fn roll(&self, rate: f64) -> bool {
+        rate > 0.0 && ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < rate
+    }
+
+    /// Delay `operation` and maybe fail it before it runs
+    fn disturb(&self, operation: &str) -> Result<(), StorageError> {
+        if !self.enabled.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        self.operations.fetch_add(1, Ordering::Relaxed);
+        if !self.config.latency.is_zero() {
+            std::thread::sleep(self.config.latency);
+        }
+        if self.roll(self.config.error_rate) {
+            self.injected_errors.fetch_add(1, Ordering::Relaxed);
+            return Err(StorageError::Database(format!("Injected fault in {}", operation)));
+        }
+        Ok(())
+    }
+}
+
+impl<S: ObjectStorage> ChaosStorage<S> {
+    /// Run a batch write, or apply a prefix of `ops` one at a time and fail
+    fn batch<F>(&self, ops: &[BatchOp], transaction_hash: [u8; 32], write: F) -> Result<HashMap<UnitsObjectId, UnitsObjectProof>, StorageError>
+    where
+        F: FnOnce() -> Result<HashMap<UnitsObjectId, UnitsObjectProof>, StorageError>,
+    {
+        self.disturb("batch write")?;
+        if ops.is_empty() || !self.enabled.load(Ordering::SeqCst) || !self.roll(self.config.partial_write_rate) {
+            return write();
+        }
+
+        self.partial_writes.fetch_add(1, Ordering::Relaxed);
+        let applied = (self.next() % ops.len() as u64) as usize;
+        for op in &ops[..applied] {
+            match op {
+                BatchOp::Set(object) => self.inner.set(object, Some(transaction_hash))?,
+                BatchOp::Delete(id) => self.inner.delete(id, Some(transaction_hash))?,
+            };
+        }
+        Err(StorageError::Database(format!(
+            "Injected partial write: {} of {} operations applied",
+            applied,
+            ops.len()
+        )))
+    }
+}
+
+impl<S: ObjectStorage> ObjectStorage for ChaosStorage<S> {
+    fn get(&self, id: &UnitsObjectId) -> Result<Option<UnitsObject>, StorageError> {
+        self.disturb("get")?;
+        self.inner.get(id)
+    }
+
+    fn set(&self, object: &UnitsObject, transaction_hash: Option<[u8; 32]>) -> Result<UnitsObjectProof, StorageError> {
+        self.disturb("set")?;
+        self.inner.set(object, transaction_hash)
+    }
+
+    fn delete(&self, id: &UnitsObjectId, transaction_hash: Option<[u8; 32]>) -> Result<UnitsObjectProof, StorageError> {
+        self.disturb("delete")?;
+        self.inner.delete(id, transaction_hash)
+    }
+
+    fn version(&self, id: &UnitsObjectId) -> Result<u64, StorageError> {
+        self.disturb("version")?;
+        self.inner.version(id)
+    }
+
+    fn set_batch(
+        &self,
+        objects: &[UnitsObject],
+        transaction_hash: [u8; 32],
+    ) -> Result<HashMap<UnitsObjectId, UnitsObjectProof>, StorageError> {
+        let ops: Vec<_> = objects.iter().cloned().map(BatchOp::Set).collect();
+        self.batch(&ops, transaction_hash, || self.inner.set_batch(objects, transaction_hash))
+    }
+
+    fn delete_batch(
+        &self,
+        ids: &[UnitsObjectId],
+        transaction_hash: [u8; 32],
+    ) -> Result<HashMap<UnitsObjectId, UnitsObjectProof>, StorageError> {
+        let ops: Vec<_> = ids.iter().copied().map(BatchOp::Delete).collect();
+        self.batch(&ops, transaction_hash, || self.inner.delete_batch(ids, transaction_hash))
+    }
+
+    fn apply_batch(
+        &self,
+        ops: &[BatchOp],
+        transaction_hash: [u8; 32],
+    ) -> Result<HashMap<UnitsObjectId, UnitsObjectProof>, StorageError> {
+        self.batch(ops, transaction_hash, || self.inner.apply_batch(ops, transaction_hash))
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_> {
+        match self.disturb("iter") {
+            Ok(()) => self.inner.iter(),
+            Err(error) => Box::new(std::iter::once(Err(error))),
+        }
+    }
+
+    fn iter_range<R>(&self, range: R) -> Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_>
+    where
+        R: RangeBounds<UnitsObjectId>,
+    {
+        match self.disturb("iter_range") {
+            Ok(()) => self.inner.iter_range(range),
+            Err(error) => Box::new(std::iter::once(Err(error))),
+        }
+    }
+
+    fn iter_by_controller(&self, controller_id: &UnitsObjectId) -> Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_> {
+        match self.disturb("iter_by_controller") {
+            Ok(()) => self.inner.iter_by_controller(controller_id),
+            Err(error) => Box::new(std::iter::once(Err(error))),
+        }
+    }
+}
+
+impl<S: HistoricalStorage> HistoricalStorage for ChaosStorage<S> {
+    fn get_at_slot(&self, id: &UnitsObjectId, slot: SlotNumber) -> Result<Option<UnitsObject>, StorageError> {
+        self.disturb("get_at_slot")?;
+        self.inner.get_at_slot(id, slot)
+    }
+
+    fn get_history(
+        &self,
+        id: &UnitsObjectId,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+    ) -> Result<Vec<(SlotNumber, UnitsObject)>, StorageError> {
+        self.disturb("get_history")?;
+        self.inner.get_history(id, start_slot, end_slot)
+    }
+
+    fn compact_history(&self, before_slot: SlotNumber) -> Result<usize, StorageError> {
+        self.disturb("compact_history")?;
+        self.inner.compact_history(before_slot)
+    }
+}
+
+impl<S: ProofStorage> ProofStorage for ChaosStorage<S> {
+    fn store_object_proof(&self, proof: &UnitsObjectProof) -> Result<(), StorageError> {
+        self.disturb("store_object_proof")?;
+        self.inner.store_object_proof(proof)
+    }
+
+    fn get_latest_proof(&self, id: &UnitsObjectId) -> Result<Option<UnitsObjectProof>, StorageError> {
+        self.disturb("get_latest_proof")?;
+        self.inner.get_latest_proof(id)
+    }
+
+    fn get_proof_history(
+        &self,
+        id: &UnitsObjectId,
+        start_slot: Option<SlotNumber>,
+        end_slot: Option<SlotNumber>,
+    ) -> Result<Vec<(SlotNumber, UnitsObjectProof)>, StorageError> {
+        self.disturb("get_proof_history")?;
+        self.inner.get_proof_history(id, start_slot, end_slot)
+    }
+
+    fn store_state_proof(&self, proof: &StateProof) -> Result<(), StorageError> {
+        self.disturb("store_state_proof")?;
+        self.inner.store_state_proof(proof)
+    }
+
+    fn get_state_proof(&self, slot: SlotNumber) -> Result<Option<StateProof>, StorageError> {
+        self.disturb("get_state_proof")?;
+        self.inner.get_state_proof(slot)
+    }
+
+    fn get_state_proof_history(&self, start_slot: SlotNumber, end_slot: SlotNumber) -> Result<Vec<StateProof>, StorageError> {
+        self.disturb("get_state_proof_history")?;
+        self.inner.get_state_proof_history(start_slot, end_slot)
+    }
+}
+
+impl<S: ReceiptStorage> ReceiptStorage for ChaosStorage<S> {
+    fn store_receipt(&self, receipt: &TransactionReceipt) -> Result<(), StorageError> {
+        self.disturb("store_receipt")?;
+        self.inner.store_receipt(receipt)
+    }
+
+    fn get_receipt(&self, tx_hash: &[u8; 32]) -> Result<Option<TransactionReceipt>, StorageError> {
+        self.disturb("get_receipt")?;
+        self.inner.get_receipt(tx_hash)
+    }
+
+    fn get_receipts_for_slot(&self, slot: SlotNumber) -> Result<Vec<TransactionReceipt>, StorageError> {
+        self.disturb("get_receipts_for_slot")?;
+        self.inner.get_receipts_for_slot(slot)
+    }
+
+    fn get_receipts_range(&self, start_slot: SlotNumber, end_slot: SlotNumber) -> Result<Vec<TransactionReceipt>, StorageError> {
+        self.disturb("get_receipts_range")?;
+        self.inner.get_receipts_range(start_slot, end_slot)
+    }
+
+    fn get_receipts_range_page(
+        &self,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+        after: Option<TransactionHash>,
+        limit: usize,
+    ) -> Result<ReceiptPage, StorageError> {
+        self.disturb("get_receipts_range_page")?;
+        self.inner.get_receipts_range_page(start_slot, end_slot, after, limit)
+    }
+
+    fn iter_receipts_by_slot(&self, start_slot: SlotNumber, end_slot: SlotNumber) -> SlotReceiptsIter<'_> {
+        match self.disturb("iter_receipts_by_slot") {
+            Ok(()) => self.inner.iter_receipts_by_slot(start_slot, end_slot),
+            Err(error) => Box::new(std::iter::once(Err(error))),
+        }
+    }
+
+    fn get_receipts_for_object(
+        &self,
+        object_id: &UnitsObjectId,
+        start_slot: Option<SlotNumber>,
+        end_slot: Option<SlotNumber>,
+    ) -> Result<Vec<TransactionReceipt>, StorageError> {
+        self.disturb("get_receipts_for_object")?;
+        self.inner.get_receipts_for_object(object_id, start_slot, end_slot)
+    }
+
+    fn get_receipts_for_object_page(
+        &self,
+        object_id: &UnitsObjectId,
+        start_slot: Option<SlotNumber>,
+        end_slot: Option<SlotNumber>,
+        after: Option<TransactionHash>,
+        limit: usize,
+    ) -> Result<ReceiptPage, StorageError> {
+        self.disturb("get_receipts_for_object_page")?;
+        self.inner.get_receipts_for_object_page(object_id, start_slot, end_slot, after, limit)
+    }
+
+    fn get_events_for_object(
+        &self,
+        object_id: &UnitsObjectId,
+        start_slot: Option<SlotNumber>,
+        end_slot: Option<SlotNumber>,
+    ) -> Result<Vec<TransactionEvent>, StorageError> {
+        self.disturb("get_events_for_object")?;
+        self.inner.get_events_for_object(object_id, start_slot, end_slot)
+    }
+
+    fn cleanup_receipts_before(&self, slot: SlotNumber) -> Result<usize, StorageError> {
+        self.disturb("cleanup_receipts_before")?;
+        self.inner.cleanup_receipts_before(slot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryObjectStorage;
+
+    fn object(byte: u8) -> UnitsObject {
+        UnitsObject::new_data(UnitsObjectId::new([byte; 32]), UnitsObjectId::default(), vec![byte])
+    }
+
+    #[test]
+    fn test_faults_follow_the_seed() {
+        let outcomes = |seed| {
+            let storage = ChaosStorage::new(InMemoryObjectStorage::new(), ChaosConfig::new(seed).with_error_rate(0.5));
+            (0..32).map(|_| storage.get(&object(1).id).is_ok()).collect::<Vec<_>>()
+        };
+        assert_eq!(outcomes(7), outcomes(7));
+        assert!(outcomes(7).contains(&false) && outcomes(7).contains(&true));
+
+        // Disabled storage passes every call through
+        let storage = ChaosStorage::new(InMemoryObjectStorage::new(), ChaosConfig::new(7).with_error_rate(1.0));
+        assert!(storage.get(&object(1).id).is_err());
+        storage.set_enabled(false);
+        assert!(storage.get(&object(1).id).is_ok());
+        assert_eq!(storage.stats(), ChaosStats { operations: 1, injected_errors: 1, partial_writes: 0 });
+    }
+
+    #[test]
+    fn test_partial_writes_apply_a_prefix() {
+        let storage = ChaosStorage::new(InMemoryObjectStorage::new(), ChaosConfig::new(3).with_partial_write_rate(1.0));
+        let ops: Vec<_> = (1..=8).map(|byte| BatchOp::Set(object(byte))).collect();
+        assert!(storage.apply_batch(&ops, [1; 32]).is_err());
+
+        // Whatever landed is a prefix of the batch
+        storage.set_enabled(false);
+        let written: Vec<bool> = (1..=8).map(|byte| storage.exists(&object(byte).id).unwrap()).collect();
+        let applied = written.iter().take_while(|written| **written).count();
+        assert!(written[applied..].iter().all(|written| !written));
+        assert_eq!(storage.stats().partial_writes, 1);
+    }
+}
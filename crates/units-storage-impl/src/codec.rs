@@ -0,0 +1,177 @@
+//! Record compression for receipts and the write-ahead log
+//!
+//! Every stored record carries the id of the codec that produced it, so the
+//! configured codec or threshold can change without rewriting old records.
+//! Codecs are compiled in through the `lz4` and `zstd` features.
+
+use serde::{Deserialize, Serialize};
+use units_core_types::error::StorageError;
+
+/// Compression applied to a stored record
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+    /// Store records as serialized
+    #[default]
+    None,
+    /// Fast compression with a modest ratio
+    Lz4,
+    /// Slower compression with a better ratio
+    Zstd,
+}
+
+impl Codec {
+    /// Id stored with each record
+    pub fn id(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Lz4 => 1,
+            Self::Zstd => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Result<Self, StorageError> {
+        match id {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Lz4),
+            2 => Ok(Self::Zstd),
+            _ => Err(StorageError::Serialization(format!("Unknown codec id {}", id))),
+        }
+    }
+
+    /// Compress `bytes`, using `level` where the codec has levels
+    pub fn compress(self, bytes: &[u8], level: i32) -> Result<Vec<u8>, StorageError> {
+        #[cfg(not(feature = "zstd"))]
+        let _ = level;
+        match self {
+            Self::None => Ok(bytes.to_vec()),
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => Ok(lz4_flex::compress_prepend_size(bytes)),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => zstd::stream::encode_all(bytes, level).map_err(StorageError::from),
+            #[allow(unreachable_patterns)]
+            codec => Err(codec.not_compiled_in()),
+        }
+    }
+
+    pub fn decompress(self, bytes: &[u8]) -> Result<Vec<u8>, StorageError> {
+        match self {
+            Self::None => Ok(bytes.to_vec()),
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => lz4_flex::decompress_size_prepended(bytes)
+                .map_err(|e| StorageError::Serialization(format!("Corrupt lz4 record: {}", e))),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => zstd::stream::decode_all(bytes).map_err(StorageError::from),
+            #[allow(unreachable_patterns)]
+            codec => Err(codec.not_compiled_in()),
+        }
+    }
+
+    #[allow(dead_code)]
+    fn not_compiled_in(self) -> StorageError {
+        StorageError::Unimplemented(format!("{:?} codec is not compiled in", self))
+    }
+}
+
+/// When and how records are compressed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CodecConfig {
+    /// Codec for records at or above the threshold
+    pub codec: Codec,
+    /// Records smaller than this many bytes are stored uncompressed
+    pub threshold_bytes: usize,
+    /// Zstd compression level
+    pub zstd_level: i32,
+}
+
+impl Default for CodecConfig {
+    fn default() -> Self {
+        Self {
+            codec: Codec::None,
+            threshold_bytes: 512,
+            zstd_level: 3,
+        }
+    }
+}
+
+impl CodecConfig {
+    /// Compress records of at least the default threshold with `codec`
+    pub fn new(codec: Codec) -> Self {
+        Self {
+            codec,
+            ..Self::default()
+        }
+    }
+
+    /// Compress a serialized record, returning the codec actually used
+    ///
+    /// Records under the threshold, or that would not shrink, stay as they are.
+    pub fn encode(&self, bytes: Vec<u8>) -> Result<(Codec, Vec<u8>), StorageError> {
+        if self.codec == Codec::None || bytes.len() < self.threshold_bytes {
+            return Ok((Codec::None, bytes));
+        }
+
+        let compressed = self.codec.compress(&bytes, self.zstd_level)?;
+        if compressed.len() < bytes.len() {
+            Ok((self.codec, compressed))
+        } else {
+            Ok((Codec::None, bytes))
+        }
+    }
+}
+
+/// Space used by the records of a store
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodecStats {
+    pub records: usize,
+    /// Serialized size before compression
+    pub raw_bytes: usize,
+    /// Size as stored
+    pub stored_bytes: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compressible() -> Vec<u8> {
+        (0..4096u32).flat_map(|i| (i % 16).to_le_bytes()).collect()
+    }
+
+    #[test]
+    #[cfg(all(feature = "lz4", feature = "zstd"))]
+    fn test_codecs_round_trip() {
+        for codec in [Codec::None, Codec::Lz4, Codec::Zstd] {
+            let config = CodecConfig::new(codec);
+            let (used, stored) = config.encode(compressible()).unwrap();
+            assert_eq!(used, codec);
+            assert_eq!(Codec::from_id(used.id()).unwrap(), codec);
+            assert_eq!(used.decompress(&stored).unwrap(), compressible());
+            if codec != Codec::None {
+                assert!(stored.len() < compressible().len());
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "lz4", feature = "zstd"))]
+    fn test_small_and_incompressible_records_stay_raw() {
+        let config = CodecConfig::new(Codec::Zstd);
+        assert_eq!(config.encode(vec![7; 16]).unwrap(), (Codec::None, vec![7; 16]));
+
+        // Xorshift output has no repetition to exploit, so it grows
+        let mut state = 0x9e3779b97f4a7c15u64;
+        let noise: Vec<u8> = (0..1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        let config = CodecConfig { threshold_bytes: 0, ..CodecConfig::new(Codec::Lz4) };
+        assert_eq!(config.encode(noise.clone()).unwrap().0, Codec::None);
+        assert!(Codec::from_id(9).is_err());
+    }
+}
@@ -4,29 +4,396 @@
 //! architecture with in-memory implementations for development and testing.
 
 use units_core_types::{ObjectStorage, HistoricalStorage, ProofStorage, WriteAheadLog, UnitsStorage as UnitsStorageTrait, ReceiptStorage, LockManager};
-use std::collections::HashMap;
-use std::sync::RwLock;
+use units_core_types::{BatchOp, ExecutableCache, ObservedProof, StorageObserver};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ops::{Bound, RangeBounds};
+use std::sync::{Arc, Mutex, RwLock};
 use units_core_types::error::StorageError;
 use units_core_types::id::UnitsObjectId;
 use units_core_types::objects::UnitsObject;
-use units_core_types::{SlotNumber, StateProof, UnitsObjectProof};
-use units_proofs::ProofEngine;
+use units_core_types::{HashAlgorithm, Proof, ProofStorageError, SlotNumber, StateProof, UnitsObjectProof};
+use units_proofs::{
+    with_proof_engine, InclusionProof, MerkleNode, ObjectTree, ProofBridge, ProofEngine, ProofMigration, SlotOrdering,
+    SlotRegression, SparseMerkleTree,
+};
+
+/// Number of versions retained per object by default
+pub const DEFAULT_HISTORY_DEPTH: usize = 64;
+
+/// Versions of one object keyed by the slot they were written in
+///
+/// `None` marks a deletion, so lookups after it resolve to "not found".
+type VersionHistory = BTreeMap<SlotNumber, Option<UnitsObject>>;
 
 /// Simple in-memory object storage implementation with integrated proof generation
 pub struct InMemoryObjectStorage {
-    objects: RwLock<HashMap<UnitsObjectId, UnitsObject>>,
-    history: RwLock<HashMap<(UnitsObjectId, SlotNumber), UnitsObject>>,
+    /// Live objects, ordered by ID for range scans
+    objects: RwLock<BTreeMap<UnitsObjectId, UnitsObject>>,
+    /// IDs of the live objects of each controller, locked after `objects`
+    by_controller: RwLock<HashMap<UnitsObjectId, BTreeSet<UnitsObjectId>>>,
+    history: RwLock<HashMap<UnitsObjectId, VersionHistory>>,
+    history_depth: usize,
     proof_history: RwLock<HashMap<UnitsObjectId, Vec<UnitsObjectProof>>>,
-    proof_engine: ProofEngine,
+    /// Links from proofs replaced by a backfill to their replacements
+    proof_bridges: RwLock<HashMap<UnitsObjectId, Vec<ProofBridge>>>,
+    slot_ordering: SlotOrdering,
+    /// Hash function new object proofs are computed with
+    hash_algorithm: HashAlgorithm,
+    observer: Option<Arc<dyn StorageObserver>>,
+    /// Controllers whose objects are kept, hidden, when deleted
+    legal_holds: RwLock<HashSet<UnitsObjectId>>,
+    /// Objects deleted under a legal hold, in deletion order
+    retained: RwLock<Vec<RetainedObject>>,
+    /// Last write of each object, written under `objects`' write lock
+    changes: RwLock<ChangeLog>,
+}
+
+/// Position of each object's last write in the storage's write sequence
+#[derive(Debug, Default)]
+struct ChangeLog {
+    /// Writes made so far
+    sequence: u64,
+    by_sequence: BTreeMap<u64, UnitsObjectId>,
+    by_object: HashMap<UnitsObjectId, u64>,
+}
+
+impl ChangeLog {
+    fn record(&mut self, id: UnitsObjectId) {
+        self.sequence += 1;
+        if let Some(previous) = self.by_object.insert(id, self.sequence) {
+            self.by_sequence.remove(&previous);
+        }
+        self.by_sequence.insert(self.sequence, id);
+    }
+}
+
+/// Objects written after a point in a storage's write sequence
+#[derive(Debug, Clone, Default)]
+pub struct ObjectChanges {
+    /// Position in the write sequence the changes run up to
+    pub sequence: u64,
+    /// Current state and write version of each changed object, `None`
+    /// once deleted
+    pub objects: Vec<(UnitsObjectId, Option<(UnitsObject, u64)>)>,
+}
+
+/// Object proofs changed after a point in the write sequence
+struct ProofChanges {
+    sequence: u64,
+    proofs: Vec<(UnitsObjectId, Option<UnitsObjectProof>)>,
+    /// Every object committed to, as `latest_proofs` lists them
+    object_ids: Vec<UnitsObjectId>,
+}
+
+/// Sparse Merkle tree over the latest object proofs, as of a point in the
+/// write sequence
+#[derive(Debug, Clone, Copy, Default)]
+struct ObjectTreeCursor {
+    /// `None` until the first commit, for the empty tree
+    root: Option<[u8; 32]>,
+    sequence: u64,
+}
+
+/// Object deleted while its controller was under a legal hold
+///
+/// The deletion happens as usual, so reads no longer see the object, but
+/// its last state is kept with the proof of the deletion until the hold
+/// is released. That proof chains to the object's earlier proofs, so what
+/// was deleted, and when, stays provable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetainedObject {
+    /// Object as it was when deleted
+    pub object: UnitsObject,
+    /// Proof recording the deletion
+    pub deletion_proof: UnitsObjectProof,
+}
+
+/// Outcome of reissuing every stored proof chain in a new format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProofBackfill {
+    pub chains: usize,
+    pub proofs: usize,
 }
 
 impl InMemoryObjectStorage {
     pub fn new() -> Self {
+        Self::with_history_depth(DEFAULT_HISTORY_DEPTH)
+    }
+
+    /// Create storage retaining at most `history_depth` versions per object
+    ///
+    /// A depth of zero keeps only the current state, disabling time travel.
+    pub fn with_history_depth(history_depth: usize) -> Self {
         Self {
-            objects: RwLock::new(HashMap::new()),
+            objects: RwLock::new(BTreeMap::new()),
+            by_controller: RwLock::new(HashMap::new()),
             history: RwLock::new(HashMap::new()),
+            history_depth,
             proof_history: RwLock::new(HashMap::new()),
-            proof_engine: ProofEngine::new(),
+            proof_bridges: RwLock::new(HashMap::new()),
+            slot_ordering: SlotOrdering::default(),
+            hash_algorithm: HashAlgorithm::default(),
+            observer: None,
+            legal_holds: RwLock::new(HashSet::new()),
+            retained: RwLock::new(Vec::new()),
+            changes: RwLock::new(ChangeLog::default()),
+        }
+    }
+
+    /// Objects written after `sequence`, as one consistent view
+    ///
+    /// Finding them costs time in the number of changed objects, not the
+    /// size of the store, so a copy of the store can be kept current cheaply:
+    /// start from sequence 0 and pass back the returned `sequence` each time.
+    pub fn changes_since(&self, sequence: u64) -> ObjectChanges {
+        let objects = self.objects.read().unwrap();
+        let proof_history = self.proof_history.read().unwrap();
+        let changes = self.changes.read().unwrap();
+        // Every write appends one proof, as in `version`
+        let current = |id: &UnitsObjectId| {
+            let object = objects.get(id)?.clone();
+            Some((object, proof_history.get(id).map_or(0, |chain| chain.len() as u64)))
+        };
+        ObjectChanges {
+            sequence: changes.sequence,
+            objects: changes
+                .by_sequence
+                .range(sequence.saturating_add(1)..)
+                .map(|(_, id)| (*id, current(id)))
+                .collect(),
+        }
+    }
+
+    /// Latest proofs of the objects written after `sequence`, as one
+    /// consistent view with the IDs of every object committed to
+    ///
+    /// A changed object no longer committed to, being deleted or
+    /// ephemeral, comes with `None`. Applying the changes to a tree over
+    /// `latest_proofs` as of `sequence` brings it up to date.
+    fn proof_changes_since(&self, sequence: u64) -> ProofChanges {
+        let objects = self.objects.read().unwrap();
+        let proof_history = self.proof_history.read().unwrap();
+        let changes = self.changes.read().unwrap();
+        let latest = |id: &UnitsObjectId| {
+            let object = objects.get(id).filter(|object| !object.is_ephemeral())?;
+            proof_history.get(&object.id)?.last().cloned()
+        };
+        ProofChanges {
+            sequence: changes.sequence,
+            proofs: changes
+                .by_sequence
+                .range(sequence.saturating_add(1)..)
+                .map(|(_, id)| (*id, latest(id)))
+                .collect(),
+            object_ids: objects
+                .values()
+                .filter(|object| !object.is_ephemeral() && proof_history.contains_key(&object.id))
+                .map(|object| object.id)
+                .collect(),
+        }
+    }
+
+    /// Keep the objects of `controller_id` deleted from now on, returning
+    /// whether it was not already held
+    pub fn place_legal_hold(&self, controller_id: UnitsObjectId) -> bool {
+        self.legal_holds.write().unwrap().insert(controller_id)
+    }
+
+    /// Lift the hold on `controller_id`, discarding the objects it retained
+    ///
+    /// Returns how many retained objects were discarded.
+    pub fn release_legal_hold(&self, controller_id: &UnitsObjectId) -> usize {
+        self.legal_holds.write().unwrap().remove(controller_id);
+        let mut retained = self.retained.write().unwrap();
+        let before = retained.len();
+        retained.retain(|kept| kept.object.controller_id != *controller_id);
+        before - retained.len()
+    }
+
+    /// Controllers under a legal hold
+    pub fn legal_holds(&self) -> Vec<UnitsObjectId> {
+        let mut holds: Vec<_> = self.legal_holds.read().unwrap().iter().copied().collect();
+        holds.sort_unstable();
+        holds
+    }
+
+    pub fn is_held(&self, controller_id: &UnitsObjectId) -> bool {
+        self.legal_holds.read().unwrap().contains(controller_id)
+    }
+
+    /// Objects retained by legal holds, only those of `controller_id` if given
+    pub fn retained_objects(&self, controller_id: Option<&UnitsObjectId>) -> Vec<RetainedObject> {
+        let retained = self.retained.read().unwrap();
+        retained
+            .iter()
+            .filter(|kept| controller_id.is_none_or(|id| kept.object.controller_id == *id))
+            .cloned()
+            .collect()
+    }
+
+    /// Keep a deleted object if its controller is held
+    fn retain_if_held(&self, object: UnitsObject, deletion_proof: &UnitsObjectProof) {
+        if self.is_held(&object.controller_id) {
+            self.retained.write().unwrap().push(RetainedObject {
+                object,
+                deletion_proof: deletion_proof.clone(),
+            });
+        }
+    }
+
+    /// Hold new proofs to `slot_ordering` within each object's chain
+    pub fn set_slot_ordering(&mut self, slot_ordering: SlotOrdering) {
+        self.slot_ordering = slot_ordering;
+    }
+
+    /// Compute new object proofs with `hash_algorithm`
+    pub fn set_hash_algorithm(&mut self, hash_algorithm: HashAlgorithm) {
+        self.hash_algorithm = hash_algorithm;
+    }
+
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    fn generate_object_proof<T: Proof>(
+        &self,
+        object: &T,
+        prev_proof: Option<&UnitsObjectProof>,
+        transaction_hash: Option<[u8; 32]>,
+    ) -> Result<UnitsObjectProof, ProofStorageError> {
+        with_proof_engine!(self.hash_algorithm, |engine| engine
+            .with_slot_ordering(self.slot_ordering)
+            .generate_object_proof(object, prev_proof, transaction_hash))
+    }
+
+    /// Proofs breaking the slot ordering, across every object's chain
+    pub fn slot_regressions(&self) -> Vec<SlotRegression> {
+        let proof_history = self.proof_history.read().unwrap();
+        proof_history
+            .values()
+            .flat_map(|chain| {
+                with_proof_engine!(self.hash_algorithm, |engine| engine
+                    .with_slot_ordering(self.slot_ordering)
+                    .slot_regressions(chain))
+            })
+            .collect()
+    }
+
+    /// Rewrite every chain breaking the slot ordering, returning how many were rewritten
+    ///
+    /// See `ProofEngine::repair_slot_order`: state proofs committed before
+    /// the repair no longer match the rewritten chains.
+    pub fn repair_slot_order(&self) -> usize {
+        let _objects = self.objects.write().unwrap();
+        let mut proof_history = self.proof_history.write().unwrap();
+        let mut changes = self.changes.write().unwrap();
+        let mut repaired = 0;
+        for (id, chain) in proof_history.iter_mut() {
+            let fixed = with_proof_engine!(self.hash_algorithm, |engine| engine
+                .with_slot_ordering(self.slot_ordering)
+                .repair_slot_order(chain));
+            if let Some(fixed) = fixed {
+                *chain = fixed;
+                changes.record(*id);
+                repaired += 1;
+            }
+        }
+        repaired
+    }
+
+    /// Reissue every proof chain under `migration`'s target format
+    ///
+    /// Each chain is migrated and validated before any is replaced, so a
+    /// chain that fails leaves storage untouched. The bridges tying old
+    /// proofs to new ones are kept, after those of earlier backfills.
+    /// Committed state proofs still refer to the old proofs and are checked
+    /// through the bridges. New writes extend chains with the storage's own
+    /// engine, which must be switched to the target format as well.
+    pub fn backfill_proofs(&self, migration: &ProofMigration) -> Result<ProofBackfill, StorageError> {
+        let _objects = self.objects.write().unwrap();
+        let mut proof_history = self.proof_history.write().unwrap();
+        let mut migrated = Vec::with_capacity(proof_history.len());
+        for (id, chain) in proof_history.iter() {
+            let reissued = migration.migrate_chain(chain)?;
+            migration.validate(chain, &reissued)?;
+            migrated.push((*id, reissued));
+        }
+
+        let mut report = ProofBackfill::default();
+        let mut proof_bridges = self.proof_bridges.write().unwrap();
+        let mut changes = self.changes.write().unwrap();
+        for (id, reissued) in migrated {
+            changes.record(id);
+            report.chains += 1;
+            report.proofs += reissued.proofs.len();
+            proof_history.insert(id, reissued.proofs);
+            proof_bridges.entry(id).or_default().extend(reissued.bridges);
+        }
+        Ok(report)
+    }
+
+    /// Bridges recorded for an object by backfills, oldest first
+    pub fn proof_bridges(&self, id: &UnitsObjectId) -> Vec<ProofBridge> {
+        let proof_bridges = self.proof_bridges.read().unwrap();
+        proof_bridges.get(id).cloned().unwrap_or_default()
+    }
+
+    /// Report every operation to `observer`, replacing any previous one
+    pub fn set_observer(&mut self, observer: Arc<dyn StorageObserver>) {
+        self.observer = Some(observer);
+    }
+
+    /// Report a new object proof, as stored by a set or delete
+    fn observe_write(&self, object: Option<&UnitsObject>, proof: &UnitsObjectProof) {
+        if let Some(observer) = &self.observer {
+            match object {
+                Some(object) => observer.on_set(object, proof),
+                None => observer.on_delete(&proof.object_id, proof),
+            }
+            observer.on_proof(ObservedProof::Object(proof));
+        }
+    }
+
+    /// Move an object between controllers in the index as it is replaced
+    /// by `after`, or removed if `after` is `None`
+    ///
+    /// Called with the object map's write lock held.
+    fn reindex(&self, before: Option<&UnitsObject>, after: Option<&UnitsObject>) {
+        let mut by_controller = self.by_controller.write().unwrap();
+        if let Some(before) = before {
+            if after.is_some_and(|after| after.controller_id == before.controller_id) {
+                return;
+            }
+            if let Some(ids) = by_controller.get_mut(&before.controller_id) {
+                ids.remove(&before.id);
+                if ids.is_empty() {
+                    by_controller.remove(&before.controller_id);
+                }
+            }
+        }
+        if let Some(after) = after {
+            by_controller.entry(after.controller_id).or_default().insert(after.id);
+        }
+    }
+
+    /// Number of versions retained per object
+    pub fn history_depth(&self) -> usize {
+        self.history_depth
+    }
+
+    /// Record the state of an object at a slot, evicting the oldest versions
+    /// beyond the configured depth
+    fn record_version(&self, id: UnitsObjectId, slot: SlotNumber, state: Option<UnitsObject>) {
+        if self.history_depth == 0 {
+            return;
+        }
+
+        let mut history = self.history.write().unwrap();
+        let versions = history.entry(id).or_default();
+        versions.insert(slot, state);
+        while versions.len() > self.history_depth {
+            versions.pop_first();
         }
     }
     
@@ -35,6 +402,113 @@ impl InMemoryObjectStorage {
         let proof_history = self.proof_history.read().unwrap();
         proof_history.get(id)?.last().cloned()
     }
+
+    /// Latest proof of every live object, as committed to by a state proof
+    ///
+    /// Ephemeral objects are left out.
+    pub fn latest_proofs(&self) -> Vec<(UnitsObjectId, UnitsObjectProof)> {
+        let objects = self.objects.read().unwrap();
+        let proof_history = self.proof_history.read().unwrap();
+        objects
+            .values()
+            .filter(|object| !object.is_ephemeral())
+            .filter_map(|object| Some((object.id, proof_history.get(&object.id)?.last()?.clone())))
+            .collect()
+    }
+
+    /// Latest proof of every object live once the proofs `written` accepts
+    /// had been written
+    ///
+    /// Each object contributes the last accepted proof of its chain. An
+    /// accepted deletion leaves the object out, as do ephemeral objects.
+    pub fn proofs_as_of<F>(&self, written: F) -> Vec<(UnitsObjectId, UnitsObjectProof)>
+    where
+        F: Fn(&UnitsObjectProof) -> bool,
+    {
+        let objects = self.objects.read().unwrap();
+        let history = self.history.read().unwrap();
+        let proof_history = self.proof_history.read().unwrap();
+        // Ordered by ID like `latest_proofs`, so the object root matches
+        let mut proofs: Vec<_> = proof_history
+            .iter()
+            .filter_map(|(id, chain)| {
+                let index = chain.iter().rposition(&written)?;
+                let proof = &chain[index];
+                // Judged by the version written with the proof; once that is
+                // evicted, by the current state, and only the last proof of a
+                // removed object's chain is taken to be its deletion
+                let committed = match history.get(id).and_then(|versions| versions.get(&proof.slot)) {
+                    Some(state) => state.as_ref().is_some_and(|object| !object.is_ephemeral()),
+                    None => match objects.get(id) {
+                        Some(object) => !object.is_ephemeral(),
+                        None => index + 1 < chain.len(),
+                    },
+                };
+                committed.then(|| (*id, proof.clone()))
+            })
+            .collect();
+        proofs.sort_unstable_by_key(|(id, _)| *id);
+        proofs
+    }
+
+    /// IDs of every live object
+    pub fn object_ids(&self) -> Vec<UnitsObjectId> {
+        self.objects.read().unwrap().keys().copied().collect()
+    }
+
+    /// Number of versions `compact_history(before_slot)` would remove
+    pub fn compactable_versions(&self, before_slot: SlotNumber) -> usize {
+        let history = self.history.read().unwrap();
+        history
+            .values()
+            .map(|versions| compact_versions(&mut versions.clone(), before_slot))
+            .sum()
+    }
+
+    /// Get the full proof chain for an object, oldest first
+    pub fn get_proof_chain(&self, id: &UnitsObjectId) -> Vec<UnitsObjectProof> {
+        let proof_history = self.proof_history.read().unwrap();
+        proof_history.get(id).cloned().unwrap_or_default()
+    }
+
+    /// Install an object together with a proof chain produced elsewhere
+    ///
+    /// The chain is adopted as-is and extended with a bridging proof at the
+    /// local slot, which is returned. Callers are responsible for verifying
+    /// the chain first.
+    pub fn restore_with_proof_chain(
+        &self,
+        object: &UnitsObject,
+        proof_chain: Vec<UnitsObjectProof>,
+    ) -> Result<UnitsObjectProof, StorageError> {
+        if self.exists(object.id())? {
+            return Err(StorageError::InvalidOperation(format!(
+                "Object already exists: {:?}", object.id()
+            )));
+        }
+
+        let bridge = self.generate_object_proof(
+            object,
+            proof_chain.last(),
+            None,
+        )?;
+
+        self.record_version(*object.id(), bridge.slot, Some(object.clone()));
+
+        // The proof lands with the object, so a recorded change always has it
+        {
+            let mut objects = self.objects.write().unwrap();
+            objects.insert(*object.id(), object.clone());
+            self.reindex(None, Some(object));
+            let mut chain = proof_chain;
+            chain.push(bridge.clone());
+            self.proof_history.write().unwrap().insert(*object.id(), chain);
+            self.changes.write().unwrap().record(*object.id());
+        }
+
+        self.observe_write(Some(object), &bridge);
+        Ok(bridge)
+    }
 }
 
 impl Default for InMemoryObjectStorage {
@@ -45,8 +519,11 @@ impl Default for InMemoryObjectStorage {
 
 impl ObjectStorage for InMemoryObjectStorage {
     fn get(&self, id: &UnitsObjectId) -> Result<Option<UnitsObject>, StorageError> {
-        let objects = self.objects.read().unwrap();
-        Ok(objects.get(id).cloned())
+        let object = self.objects.read().unwrap().get(id).cloned();
+        if let Some(observer) = &self.observer {
+            observer.on_get(id, object.is_some());
+        }
+        Ok(object)
     }
     
     fn set(
@@ -58,32 +535,25 @@ impl ObjectStorage for InMemoryObjectStorage {
         let prev_proof = self.get_latest_proof(object.id());
         
         // Generate cryptographic proof using the proof engine
-        let proof = self.proof_engine.generate_object_proof(
+        let proof = self.generate_object_proof(
             object,
             prev_proof.as_ref(),
             transaction_hash,
         )?;
         
         // Store the object with current slot in history
-        {
-            let mut history = self.history.write().unwrap();
-            history.insert((*object.id(), proof.slot), object.clone());
-        }
+        self.record_version(*object.id(), proof.slot, Some(object.clone()));
         
-        // Update current object state
+        // Update current object state and store the proof in history
         {
             let mut objects = self.objects.write().unwrap();
-            objects.insert(*object.id(), object.clone());
-        }
-        
-        // Store the proof in history
-        {
-            let mut proof_history = self.proof_history.write().unwrap();
-            proof_history.entry(*object.id())
-                .or_insert_with(Vec::new)
-                .push(proof.clone());
+            let before = objects.insert(*object.id(), object.clone());
+            self.reindex(before.as_ref(), Some(object));
+            self.proof_history.write().unwrap().entry(*object.id()).or_default().push(proof.clone());
+            self.changes.write().unwrap().record(*object.id());
         }
         
+        self.observe_write(Some(object), &proof);
         Ok(proof)
     }
     
@@ -103,40 +573,130 @@ impl ObjectStorage for InMemoryObjectStorage {
         let prev_proof = self.get_latest_proof(id);
         
         // Generate cryptographic proof for deletion
-        let proof = self.proof_engine.generate_object_proof(
+        let proof = self.generate_object_proof(
             &object,
             prev_proof.as_ref(),
             transaction_hash,
         )?;
         
         // Store the deletion in history with current slot
-        {
-            let mut history = self.history.write().unwrap();
-            history.insert((*id, proof.slot), object.clone());
-        }
+        self.record_version(*id, proof.slot, None);
         
-        // Remove from current object state
+        // Remove from current object state and store the deletion proof in history
         {
             let mut objects = self.objects.write().unwrap();
-            objects.remove(id);
-        }
-        
-        // Store the deletion proof in history
-        {
-            let mut proof_history = self.proof_history.write().unwrap();
-            proof_history.entry(*id)
-                .or_insert_with(Vec::new)
-                .push(proof.clone());
+            let before = objects.remove(id);
+            self.reindex(before.as_ref(), None);
+            self.proof_history.write().unwrap().entry(*id).or_default().push(proof.clone());
+            self.changes.write().unwrap().record(*id);
         }
         
+        self.retain_if_held(object, &proof);
+        self.observe_write(None, &proof);
         Ok(proof)
     }
+
+    /// Applied under the object map's write lock, so readers see the batch
+    /// either not at all or in full
+    fn apply_batch(
+        &self,
+        ops: &[BatchOp],
+        transaction_hash: [u8; 32],
+    ) -> Result<HashMap<UnitsObjectId, UnitsObjectProof>, StorageError> {
+        let mut objects = self.objects.write().unwrap();
+
+        // Prove every operation against the batch's running state before
+        // touching anything, so a failure leaves storage as it was
+        let mut current: HashMap<UnitsObjectId, Option<UnitsObject>> = HashMap::new();
+        let mut latest: HashMap<UnitsObjectId, UnitsObjectProof> = HashMap::new();
+        let mut writes = Vec::with_capacity(ops.len());
+        for op in ops {
+            let id = op.object_id();
+            let before = current.entry(id).or_insert_with(|| objects.get(&id).cloned());
+            let (proved, after) = match op {
+                BatchOp::Set(object) => (object, Some(object.clone())),
+                BatchOp::Delete(_) => match before {
+                    Some(object) => (&*object, None),
+                    None => return Err(StorageError::NotFound(format!("Object not found: {:?}", id))),
+                },
+            };
+            let prev_proof = match latest.get(&id) {
+                Some(proof) => Some(proof.clone()),
+                None => self.get_latest_proof(&id),
+            };
+            let proof = self.generate_object_proof(proved, prev_proof.as_ref(), Some(transaction_hash))?;
+            let deleted = after.is_none().then(|| proved.clone());
+            *before = after.clone();
+            latest.insert(id, proof.clone());
+            writes.push((id, after, proof, deleted));
+        }
+
+        for (id, after, proof, _) in &writes {
+            self.record_version(*id, proof.slot, after.clone());
+            let before = match after {
+                Some(object) => objects.insert(*id, object.clone()),
+                None => objects.remove(id),
+            };
+            self.reindex(before.as_ref(), after.as_ref());
+            self.proof_history.write().unwrap().entry(*id).or_default().push(proof.clone());
+            self.changes.write().unwrap().record(*id);
+        }
+        drop(objects);
+
+        for (_, after, proof, deleted) in writes.iter_mut() {
+            if let Some(object) = deleted.take() {
+                self.retain_if_held(object, proof);
+            }
+            self.observe_write(after.as_ref(), proof);
+        }
+        Ok(latest)
+    }
+    
+    fn version(&self, id: &UnitsObjectId) -> Result<u64, StorageError> {
+        // Every write appends one proof; a restored object keeps its source
+        // chain, so its version continues from the source's
+        let proof_history = self.proof_history.read().unwrap();
+        Ok(proof_history.get(id).map_or(0, |chain| chain.len() as u64))
+    }
     
     fn iter(&self) -> Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_> {
         let objects = self.objects.read().unwrap();
         let objects_vec: Vec<_> = objects.values().cloned().collect();
         Box::new(objects_vec.into_iter().map(Ok))
     }
+
+    fn iter_range<R>(&self, range: R) -> Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_>
+    where
+        R: RangeBounds<UnitsObjectId>,
+    {
+        // `BTreeMap::range` panics on inverted bounds, which hold no IDs anyway
+        let inverted = match (range.start_bound(), range.end_bound()) {
+            (Bound::Included(start), Bound::Included(end)) => start > end,
+            (Bound::Included(start) | Bound::Excluded(start), Bound::Excluded(end))
+            | (Bound::Excluded(start), Bound::Included(end)) => start >= end,
+            _ => false,
+        };
+        if inverted {
+            return Box::new(std::iter::empty());
+        }
+
+        let objects = self.objects.read().unwrap();
+        let objects_vec: Vec<_> = objects.range(range).map(|(_, object)| object.clone()).collect();
+        Box::new(objects_vec.into_iter().map(Ok))
+    }
+
+    /// Served from the controller index, visiting only the controller's objects
+    fn iter_by_controller(&self, controller_id: &UnitsObjectId) -> Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_> {
+        let objects = self.objects.read().unwrap();
+        let by_controller = self.by_controller.read().unwrap();
+        let objects_vec: Vec<_> = by_controller
+            .get(controller_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| objects.get(id).cloned())
+            .collect();
+        Box::new(objects_vec.into_iter().map(Ok))
+    }
 }
 
 impl HistoricalStorage for InMemoryObjectStorage {
@@ -145,8 +705,12 @@ impl HistoricalStorage for InMemoryObjectStorage {
         id: &UnitsObjectId,
         slot: SlotNumber,
     ) -> Result<Option<UnitsObject>, StorageError> {
+        // The state at a slot is the latest version written at or before it
         let history = self.history.read().unwrap();
-        Ok(history.get(&(*id, slot)).cloned())
+        Ok(history
+            .get(id)
+            .and_then(|versions| versions.range(..=slot).next_back())
+            .and_then(|(_, state)| state.clone()))
     }
     
     fn get_history(
@@ -155,26 +719,63 @@ impl HistoricalStorage for InMemoryObjectStorage {
         start_slot: SlotNumber,
         end_slot: SlotNumber,
     ) -> Result<Vec<(SlotNumber, UnitsObject)>, StorageError> {
+        if start_slot > end_slot {
+            return Ok(Vec::new());
+        }
+
         let history = self.history.read().unwrap();
         Ok(history
-            .iter()
-            .filter(|((obj_id, slot), _)| {
-                *obj_id == *id && *slot >= start_slot && *slot <= end_slot
+            .get(id)
+            .map(|versions| {
+                versions
+                    .range(start_slot..=end_slot)
+                    .filter_map(|(slot, state)| state.clone().map(|obj| (*slot, obj)))
+                    .collect()
             })
-            .map(|((_, slot), obj)| (*slot, obj.clone()))
-            .collect())
+            .unwrap_or_default())
     }
     
-    fn compact_history(&self, _before_slot: SlotNumber) -> Result<usize, StorageError> {
-        // Simple implementation - could compact history here
-        Ok(0)
+    fn compact_history(&self, before_slot: SlotNumber) -> Result<usize, StorageError> {
+        let mut history = self.history.write().unwrap();
+        let mut removed = 0;
+
+        history.retain(|_, versions| {
+            removed += compact_versions(versions, before_slot);
+            !versions.is_empty()
+        });
+
+        Ok(removed)
     }
 }
 
+/// Drop the versions of one object made redundant by a cutoff at `before_slot`,
+/// returning how many were removed
+fn compact_versions(versions: &mut VersionHistory, before_slot: SlotNumber) -> usize {
+    // Keep the newest version before the cutoff so lookups at or after
+    // `before_slot` still resolve to the state in effect at that time
+    let mut newer = versions.split_off(&before_slot);
+    let base = versions.pop_last();
+    let mut removed = versions.len();
+
+    match base {
+        // A deletion with nothing after it carries no information
+        Some((_, None)) if newer.is_empty() => removed += 1,
+        Some((slot, state)) => {
+            newer.insert(slot, state);
+        }
+        None => {}
+    }
+
+    *versions = newer;
+    removed
+}
+
 /// Simple in-memory proof storage
 pub struct InMemoryProofStorage {
     object_proofs: RwLock<HashMap<UnitsObjectId, Vec<(SlotNumber, UnitsObjectProof)>>>,
     state_proofs: RwLock<HashMap<SlotNumber, StateProof>>,
+    merkle_nodes: RwLock<HashMap<[u8; 32], Vec<u8>>>,
+    observer: Option<Arc<dyn StorageObserver>>,
 }
 
 impl InMemoryProofStorage {
@@ -182,10 +783,36 @@ impl InMemoryProofStorage {
         Self {
             object_proofs: RwLock::new(HashMap::new()),
             state_proofs: RwLock::new(HashMap::new()),
+            merkle_nodes: RwLock::new(HashMap::new()),
+            observer: None,
+        }
+    }
+
+    /// Report every stored proof to `observer`, replacing any previous one
+    pub fn set_observer(&mut self, observer: Arc<dyn StorageObserver>) {
+        self.observer = Some(observer);
+    }
+
+    fn observe_proof(&self, proof: ObservedProof<'_>) {
+        if let Some(observer) = &self.observer {
+            observer.on_proof(proof);
         }
     }
 }
 
+impl InMemoryProofStorage {
+    /// Drop state proofs committed before `before_slot`, returning how many were removed
+    ///
+    /// Object proofs are kept, so object proof chains stay verifiable; only
+    /// the slot-level commitments older than the cutoff are forgotten.
+    pub fn prune_state_proofs(&self, before_slot: SlotNumber) -> usize {
+        let mut proofs = self.state_proofs.write().unwrap();
+        let before = proofs.len();
+        proofs.retain(|slot, _| *slot >= before_slot);
+        before - proofs.len()
+    }
+}
+
 impl Default for InMemoryProofStorage {
     fn default() -> Self {
         Self::new()
@@ -199,6 +826,8 @@ impl ProofStorage for InMemoryProofStorage {
             .entry(proof.object_id.into())
             .or_insert_with(Vec::new)
             .push((proof.slot, proof.clone()));
+        drop(proofs);
+        self.observe_proof(ObservedProof::Object(proof));
         Ok(())
     }
     
@@ -237,6 +866,8 @@ impl ProofStorage for InMemoryProofStorage {
     fn store_state_proof(&self, proof: &StateProof) -> Result<(), StorageError> {
         let mut proofs = self.state_proofs.write().unwrap();
         proofs.insert(proof.slot, proof.clone());
+        drop(proofs);
+        self.observe_proof(ObservedProof::State(proof));
         Ok(())
     }
     
@@ -257,6 +888,33 @@ impl ProofStorage for InMemoryProofStorage {
             .cloned()
             .collect())
     }
+
+    fn iter_state_proofs(
+        &self,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+    ) -> Box<dyn Iterator<Item = Result<StateProof, StorageError>> + '_> {
+        // Only the slot numbers are collected up front
+        let mut slots: Vec<SlotNumber> = self
+            .state_proofs
+            .read()
+            .unwrap()
+            .keys()
+            .copied()
+            .filter(|slot| (start_slot..=end_slot).contains(slot))
+            .collect();
+        slots.sort_unstable();
+        Box::new(slots.into_iter().filter_map(move |slot| self.get_state_proof(slot).transpose()))
+    }
+
+    fn store_merkle_node(&self, hash: &[u8; 32], node: &[u8]) -> Result<(), StorageError> {
+        self.merkle_nodes.write().unwrap().entry(*hash).or_insert_with(|| node.to_vec());
+        Ok(())
+    }
+
+    fn get_merkle_node(&self, hash: &[u8; 32]) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.merkle_nodes.read().unwrap().get(hash).cloned())
+    }
 }
 
 // Re-export from lock_manager module
@@ -285,6 +943,14 @@ impl WriteAheadLog for NoOpWriteAheadLog {
     {
         Ok(())
     }
+
+    fn entry_count(&self) -> Result<usize, StorageError> {
+        Ok(0)
+    }
+
+    fn truncate(&self) -> Result<usize, StorageError> {
+        Ok(0)
+    }
 }
 
 /// Complete consolidated storage implementation using composition
@@ -294,19 +960,127 @@ pub struct ConsolidatedUnitsStorage {
     wal: Option<NoOpWriteAheadLog>,
     receipts: InMemoryReceiptStorage,
     locks: InMemoryLockManager,
+    metrics: Arc<MetricsObserver>,
+    metadata: Arc<MetadataIndex>,
+    indexes: Arc<IndexAdvisor>,
+    executables: Arc<ExecutableCache>,
+    observer: Arc<dyn StorageObserver>,
+    /// Object tree of the last committed state proof, with its nodes in `proofs`
+    object_tree: Mutex<ObjectTreeCursor>,
 }
 
 impl ConsolidatedUnitsStorage {
     pub fn create() -> Self {
-        Self {
-            objects: InMemoryObjectStorage::new(),
+        Self::with_history_depth(DEFAULT_HISTORY_DEPTH)
+    }
+
+    /// Create storage retaining at most `history_depth` versions per object
+    pub fn with_history_depth(history_depth: usize) -> Self {
+        let metrics = Arc::new(MetricsObserver::new());
+        let metadata = Arc::new(MetadataIndex::new());
+        let indexes = Arc::new(IndexAdvisor::default());
+        let executables = Arc::new(ExecutableCache::new());
+        let storage = Self {
+            objects: InMemoryObjectStorage::with_history_depth(history_depth),
             proofs: InMemoryProofStorage::new(),
             wal: Some(NoOpWriteAheadLog),
             receipts: InMemoryReceiptStorage::new(),
             locks: InMemoryLockManager::new(),
+            metrics: metrics.clone(),
+            metadata: metadata.clone(),
+            indexes: indexes.clone(),
+            executables: executables.clone(),
+            observer: Arc::new(CompositeObserver::new(vec![metrics, metadata, indexes, executables])),
+            object_tree: Mutex::new(ObjectTreeCursor::default()),
+        };
+        storage.install_observer()
+    }
+
+    /// Report every storage operation to `observer`, after the built-in
+    /// metrics and any observers added before it
+    pub fn with_observer(mut self, observer: Arc<dyn StorageObserver>) -> Self {
+        self.observer = Arc::new(CompositeObserver::new(vec![self.observer.clone(), observer]));
+        self.install_observer()
+    }
+
+    fn install_observer(mut self) -> Self {
+        self.objects.set_observer(self.observer.clone());
+        self.proofs.set_observer(self.observer.clone());
+        self
+    }
+
+    /// Counts of the storage operations performed so far
+    pub fn metrics(&self) -> StorageMetrics {
+        self.metrics.snapshot()
+    }
+
+    /// Key/value annotations on objects, dropped as objects are deleted
+    pub fn metadata(&self) -> &MetadataIndex {
+        &self.metadata
+    }
+
+    /// Query pattern statistics and the controller indexes built from them
+    pub fn index_advisor(&self) -> &IndexAdvisor {
+        &self.indexes
+    }
+
+    /// Recommend indexes by the thresholds of `config`, building them
+    /// unprompted if it enables auto-creation
+    pub fn with_index_advisor(self, config: IndexAdvisorConfig) -> Self {
+        self.indexes.set_config(config);
+        self
+    }
+
+    /// Index the objects of `controller_id`, returning how many it holds
+    pub fn create_controller_index(&self, controller_id: UnitsObjectId) -> Result<usize, StorageError> {
+        self.indexes.create_controller_index(controller_id, self.objects.iter())
+    }
+
+    /// Report a query matching `pattern` that visited `scanned` objects,
+    /// building its controller index once it qualifies if auto-creation is on
+    pub fn record_query(&self, pattern: &QueryPattern, scanned: usize) {
+        if !self.indexes.record(pattern, scanned) {
+            return;
+        }
+        if let QueryPattern::Controller { controller_id } = pattern {
+            match self.create_controller_index(*controller_id) {
+                Ok(indexed) => log::info!("Indexed {} objects of controller {}", indexed, controller_id),
+                Err(err) => log::warn!("Indexing controller {} failed: {}", controller_id, err),
+            }
         }
     }
+
+    /// Controller executables read by transactions, dropped as they are
+    /// upgraded or deleted
+    pub fn executables(&self) -> &ExecutableCache {
+        &self.executables
+    }
     
+    /// Hold object proof chains to `slot_ordering`
+    pub fn with_slot_ordering(mut self, slot_ordering: SlotOrdering) -> Self {
+        self.objects.set_slot_ordering(slot_ordering);
+        self
+    }
+
+    /// Compute object and state proofs with `hash_algorithm`
+    ///
+    /// Chosen when the storage is created: proofs already written stay
+    /// under the algorithm they were computed with.
+    pub fn with_hash_algorithm(mut self, hash_algorithm: HashAlgorithm) -> Self {
+        self.objects.set_hash_algorithm(hash_algorithm);
+        self
+    }
+
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.objects.hash_algorithm()
+    }
+
+    /// Compress stored receipts according to `codec`
+    pub fn with_receipt_codec(mut self, codec: CodecConfig) -> Self {
+        self.receipts = InMemoryReceiptStorage::with_codec(codec);
+        self
+    }
+
     /// Get access to object storage
     pub fn inner(&self) -> &InMemoryObjectStorage {
         &self.objects
@@ -316,6 +1090,236 @@ impl ConsolidatedUnitsStorage {
     pub fn new_in_memory() -> Self {
         Self::create()
     }
+
+    /// Commit the latest proof of every live object into the state proof for `slot`
+    ///
+    /// The state proof chains to the most recent earlier one and is stored
+    /// so it can be looked up by slot. Its object root is a sparse Merkle
+    /// tree kept across commits, so only the objects written since the last
+    /// commit are hashed into it.
+    pub fn commit_state_proof(
+        &self,
+        slot: SlotNumber,
+        transaction_hashes: &[[u8; 32]],
+    ) -> Result<StateProof, StorageError> {
+        let prev_state_proof = self.previous_state_proof(slot)?;
+
+        let mut cursor = self.object_tree.lock().unwrap();
+        let changes = self.objects.proof_changes_since(cursor.sequence);
+        let (state_proof, root) = with_proof_engine!(self.hash_algorithm(), |engine| {
+            let mut tree = match cursor.root {
+                Some(root) => SparseMerkleTree::open_with_hasher(&self.proofs, root),
+                None => SparseMerkleTree::with_hasher(&self.proofs),
+            };
+            let state_proof = engine.generate_incremental_state_proof(
+                &mut tree,
+                &changes.proofs,
+                changes.object_ids,
+                transaction_hashes,
+                prev_state_proof.as_ref(),
+                slot,
+            )?;
+            (state_proof, tree.root())
+        });
+        self.proofs.store_state_proof(&state_proof)?;
+        *cursor = ObjectTreeCursor { root: Some(root), sequence: changes.sequence };
+
+        Ok(state_proof)
+    }
+
+    /// Most recent state proof stored for a slot before `slot`
+    pub fn previous_state_proof(&self, slot: SlotNumber) -> Result<Option<StateProof>, StorageError> {
+        match slot.checked_sub(1) {
+            Some(prev_slot) => Ok(self
+                .proofs
+                .get_state_proof_history(0, prev_slot)?
+                .into_iter()
+                .max_by_key(|proof| proof.slot)),
+            None => Ok(None),
+        }
+    }
+
+    /// Object proofs a stored state proof commits to
+    ///
+    /// Each object the state proof lists contributes the last proof of its
+    /// chain written by a transaction executed at or before the state
+    /// proof's slot, as in `regenerate_state_proof`. Writes made outside a
+    /// transaction carry no slot, so the latest of those is taken. Objects
+    /// without any such proof are left out.
+    pub fn committed_proofs(&self, state_proof: &StateProof) -> Vec<(UnitsObjectId, UnitsObjectProof)> {
+        state_proof
+            .object_ids
+            .iter()
+            .filter_map(|id| {
+                let proof = self.objects.get_proof_chain(id).into_iter().rev().find(|proof| {
+                    proof.transaction_hash.is_none()
+                        || self.written_at(proof).is_some_and(|written| written <= state_proof.slot)
+                })?;
+                Some((*id, proof))
+            })
+            .collect()
+    }
+
+    /// Path from `proof`'s leaf to the object root of a stored state proof
+    ///
+    /// Returns `None` if `proof` is not the proof the state proof commits
+    /// to for its object, or if the committed proofs no longer rebuild a
+    /// Merkle object root.
+    pub fn committed_path(
+        &self,
+        state_proof: &StateProof,
+        proof: &UnitsObjectProof,
+    ) -> Result<Option<Vec<MerkleNode>>, StorageError> {
+        let data = ProofEngine::new().state_proof_data(state_proof)?;
+        with_proof_engine!(data.hash_algorithm, |engine| match data.object_tree {
+            ObjectTree::Sparse => {
+                let tree = SparseMerkleTree::open_with_hasher(&self.proofs, data.object_root);
+                Ok(engine
+                    .generate_sparse_inclusion_proof(&tree, proof, state_proof.slot)?
+                    .map(|inclusion| inclusion.path))
+            }
+            ObjectTree::Merkle => {
+                let committed = self.committed_proofs(state_proof);
+                if engine.group_root(&committed) != data.object_root
+                    || !committed.iter().any(|(id, committed)| *id == proof.object_id && committed.hash() == proof.hash())
+                {
+                    return Ok(None);
+                }
+                Ok(engine.object_path(&committed, &proof.object_id))
+            }
+        })
+    }
+
+    /// Inclusion proof for one object in the state proof stored for `slot`
+    ///
+    /// Returns `None` if there is no state proof for the slot, the object is
+    /// not committed to by it, or its committed proof can no longer be
+    /// found.
+    pub fn inclusion_proof(
+        &self,
+        object_id: &UnitsObjectId,
+        slot: SlotNumber,
+    ) -> Result<Option<InclusionProof>, StorageError> {
+        let Some(state_proof) = self.proofs.get_state_proof(slot)? else {
+            return Ok(None);
+        };
+        if !state_proof.object_ids.contains(object_id) {
+            return Ok(None);
+        }
+        let Some((_, object_proof)) = self
+            .committed_proofs(&state_proof)
+            .into_iter()
+            .find(|(id, _)| id == object_id)
+        else {
+            return Ok(None);
+        };
+        Ok(self.committed_path(&state_proof, &object_proof)?.map(|path| InclusionProof {
+            object_id: *object_id,
+            slot,
+            object_proof,
+            path,
+        }))
+    }
+
+    /// Slot of the transaction that wrote `proof`, if its receipt is stored
+    pub fn written_at(&self, proof: &UnitsObjectProof) -> Option<SlotNumber> {
+        let transaction_hash = proof.transaction_hash?;
+        self.receipts
+            .get_receipt(&transaction_hash)
+            .ok()
+            .flatten()
+            .map(|receipt| receipt.slot)
+    }
+
+    /// Slots in `[start_slot, end_slot]` without a state proof
+    pub fn missing_state_proofs(
+        &self,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+    ) -> Result<Vec<SlotNumber>, StorageError> {
+        if start_slot > end_slot {
+            return Ok(Vec::new());
+        }
+
+        let committed: BTreeSet<SlotNumber> = self
+            .proofs
+            .get_state_proof_history(start_slot, end_slot)?
+            .into_iter()
+            .map(|proof| proof.slot)
+            .collect();
+        Ok((start_slot..=end_slot).filter(|slot| !committed.contains(slot)).collect())
+    }
+
+    /// Slots missing a state proof between the earliest and latest committed ones
+    pub fn state_proof_gaps(&self) -> Result<Vec<SlotNumber>, StorageError> {
+        let slots: Vec<SlotNumber> = self
+            .proofs
+            .get_state_proof_history(0, SlotNumber::MAX)?
+            .into_iter()
+            .map(|proof| proof.slot)
+            .collect();
+        match (slots.iter().min(), slots.iter().max()) {
+            (Some(&earliest), Some(&latest)) => self.missing_state_proofs(earliest, latest),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Rebuild the state proof of a past slot from the stored object proofs
+    ///
+    /// The proof commits to each object's last proof written by a
+    /// transaction executed at or before `slot`, and to the transactions
+    /// whose receipts were stored for the slot. Writes made outside a
+    /// transaction carry no slot, so their latest proof counts toward every
+    /// slot. It chains to the most recent earlier state proof; later state
+    /// proofs keep the links they were committed with.
+    pub fn regenerate_state_proof(&self, slot: SlotNumber) -> Result<StateProof, StorageError> {
+        let prev_state_proof = self.previous_state_proof(slot)?;
+        let transaction_hashes: Vec<[u8; 32]> = self
+            .receipts
+            .get_receipts_for_slot(slot)?
+            .iter()
+            .map(|receipt| receipt.transaction_hash)
+            .collect();
+
+        // Rebuilt as a fresh tree in the same node store, so its paths come
+        // from `committed_path` like those of committed state proofs
+        let proofs: Vec<(UnitsObjectId, Option<UnitsObjectProof>)> = self
+            .objects
+            .proofs_as_of(|proof| {
+                proof.transaction_hash.is_none() || self.written_at(proof).is_some_and(|written| written <= slot)
+            })
+            .into_iter()
+            .map(|(id, proof)| (id, Some(proof)))
+            .collect();
+        let object_ids = proofs.iter().map(|(id, _)| *id).collect();
+        let state_proof = with_proof_engine!(self.hash_algorithm(), |engine| engine.generate_incremental_state_proof(
+            &mut SparseMerkleTree::with_hasher(&self.proofs),
+            &proofs,
+            object_ids,
+            &transaction_hashes,
+            prev_state_proof.as_ref(),
+            slot,
+        ))?;
+        self.proofs.store_state_proof(&state_proof)?;
+
+        Ok(state_proof)
+    }
+
+    /// Regenerate every missing state proof in `[start_slot, end_slot]`
+    ///
+    /// Slots are filled in ascending order, so each regenerated proof chains
+    /// to the one before it. Returns the slots filled.
+    pub fn regenerate_state_proofs(
+        &self,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+    ) -> Result<Vec<SlotNumber>, StorageError> {
+        let missing = self.missing_state_proofs(start_slot, end_slot)?;
+        for &slot in &missing {
+            self.regenerate_state_proof(slot)?;
+        }
+        Ok(missing)
+    }
 }
 
 impl Default for ConsolidatedUnitsStorage {
@@ -325,6 +1329,10 @@ impl Default for ConsolidatedUnitsStorage {
 }
 
 // Import additional types needed for trait implementation
+use crate::codec::CodecConfig;
+use crate::index_advisor::{IndexAdvisor, IndexAdvisorConfig, QueryPattern};
+use crate::metadata_index::MetadataIndex;
+use crate::observer::{CompositeObserver, MetricsObserver, StorageMetrics};
 use crate::receipt_storage::InMemoryReceiptStorage;
 
 /// Wrapper to implement UnitsStorage trait
@@ -413,4 +1421,399 @@ impl UnitsStorageTrait for ConsolidatedUnitsStorage {
     fn locks(&self) -> &Self::Locks {
         &self.locks
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(id: UnitsObjectId, byte: u8) -> UnitsObject {
+        UnitsObject::new_data(id, UnitsObjectId::default(), vec![byte])
+    }
+
+    #[test]
+    fn test_time_travel_and_depth() {
+        let storage = InMemoryObjectStorage::with_history_depth(3);
+        let id = UnitsObjectId::new([1; 32]);
+
+        storage.record_version(id, 10, Some(version(id, 1)));
+        storage.record_version(id, 20, Some(version(id, 2)));
+        storage.record_version(id, 30, None);
+
+        // Lookups resolve to the latest version at or before the slot
+        assert_eq!(storage.get_at_slot(&id, 9).unwrap(), None);
+        assert_eq!(storage.get_at_slot(&id, 15).unwrap(), Some(version(id, 1)));
+        assert_eq!(storage.get_at_slot(&id, 20).unwrap(), Some(version(id, 2)));
+        assert_eq!(storage.get_at_slot(&id, 35).unwrap(), None);
+
+        let history = storage.get_history(&id, 0, 100).unwrap();
+        assert_eq!(history, vec![(10, version(id, 1)), (20, version(id, 2))]);
+
+        // A fourth version evicts the oldest
+        storage.record_version(id, 40, Some(version(id, 4)));
+        assert_eq!(storage.get_at_slot(&id, 15).unwrap(), None);
+        assert_eq!(storage.get_history(&id, 0, 100).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_compact_history_keeps_state_at_cutoff() {
+        let storage = InMemoryObjectStorage::new();
+        let live = UnitsObjectId::new([1; 32]);
+        let deleted = UnitsObjectId::new([2; 32]);
+
+        for slot in [10, 20, 30] {
+            storage.record_version(live, slot, Some(version(live, slot as u8)));
+        }
+        storage.record_version(deleted, 10, Some(version(deleted, 1)));
+        storage.record_version(deleted, 15, None);
+
+        // Slot 10 goes for `live`; both versions of `deleted` go
+        assert_eq!(storage.compactable_versions(25), 3);
+        assert_eq!(storage.compact_history(25).unwrap(), 3);
+        assert_eq!(storage.compactable_versions(25), 0);
+        assert_eq!(storage.get_at_slot(&live, 25).unwrap(), Some(version(live, 20)));
+        assert_eq!(storage.get_at_slot(&live, 15).unwrap(), None);
+        assert!(storage.get_history(&deleted, 0, 100).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_set_and_delete_record_history() {
+        let storage = InMemoryObjectStorage::new();
+        let id = UnitsObjectId::new([3; 32]);
+
+        let proof = storage.set(&version(id, 1), None).unwrap();
+        assert_eq!(storage.get_at_slot(&id, proof.slot).unwrap(), Some(version(id, 1)));
+
+        let proof = storage.delete(&id, None).unwrap();
+        assert_eq!(storage.get_at_slot(&id, proof.slot).unwrap(), None);
+
+        // Zero depth disables history entirely
+        let storage = InMemoryObjectStorage::with_history_depth(0);
+        let proof = storage.set(&version(id, 1), None).unwrap();
+        assert_eq!(storage.get_at_slot(&id, proof.slot).unwrap(), None);
+    }
+
+    #[test]
+    fn test_changes_since_lists_each_changed_object_once() {
+        let storage = InMemoryObjectStorage::new();
+        let (a, b, c) = (UnitsObjectId::new([1; 32]), UnitsObjectId::new([2; 32]), UnitsObjectId::new([3; 32]));
+
+        storage.set(&version(a, 1), None).unwrap();
+        storage.set(&version(b, 1), None).unwrap();
+        let start = storage.changes_since(0);
+        assert_eq!(start.sequence, 2);
+        assert_eq!(start.objects, vec![(a, Some((version(a, 1), 1))), (b, Some((version(b, 1), 1)))]);
+
+        // Only later writes are reported, each object with its current state and version
+        storage.set(&version(a, 2), None).unwrap();
+        storage.set(&version(a, 3), None).unwrap();
+        storage.delete(&b, None).unwrap();
+        storage.apply_batch(&[BatchOp::Set(version(c, 1))], [7; 32]).unwrap();
+        let later = storage.changes_since(start.sequence);
+        assert_eq!(later.sequence, 6);
+        assert_eq!(later.objects, vec![(a, Some((version(a, 3), 3))), (b, None), (c, Some((version(c, 1), 1)))]);
+        assert!(storage.changes_since(later.sequence).objects.is_empty());
+    }
+
+    #[test]
+    fn test_legal_hold_retains_deleted_objects() {
+        let storage = InMemoryObjectStorage::new();
+        let held = UnitsObjectId::new([1; 32]);
+        let (kept, other) = (UnitsObjectId::new([2; 32]), UnitsObjectId::new([3; 32]));
+        storage.set(&UnitsObject::new_data(kept, held, vec![1]), None).unwrap();
+        storage.set(&UnitsObject::new_data(other, UnitsObjectId::default(), vec![1]), None).unwrap();
+
+        assert!(storage.place_legal_hold(held));
+        assert!(!storage.place_legal_hold(held));
+        storage.delete(&other, None).unwrap();
+        storage.apply_batch(&[BatchOp::Delete(kept)], [7; 32]).unwrap();
+
+        // Hidden from reads, but kept with a deletion proof ending its chain
+        assert_eq!(storage.get(&kept).unwrap(), None);
+        let retained = storage.retained_objects(Some(&held));
+        assert_eq!(retained.len(), 1);
+        assert_eq!(retained[0].object, UnitsObject::new_data(kept, held, vec![1]));
+        assert_eq!(storage.get_proof_chain(&kept).last().unwrap().proof_data, retained[0].deletion_proof.proof_data);
+        assert_eq!(retained[0].deletion_proof.transaction_hash, Some([7; 32]));
+        assert_eq!(storage.retained_objects(None).len(), 1);
+
+        assert_eq!(storage.release_legal_hold(&held), 1);
+        assert!(storage.retained_objects(None).is_empty() && storage.legal_holds().is_empty());
+    }
+
+    #[test]
+    fn test_apply_batch_mixes_sets_and_deletes_atomically() {
+        let storage = InMemoryObjectStorage::new();
+        let (kept, dropped, created) = (UnitsObjectId::new([1; 32]), UnitsObjectId::new([2; 32]), UnitsObjectId::new([3; 32]));
+        storage.set(&version(kept, 1), None).unwrap();
+        storage.set(&version(dropped, 1), None).unwrap();
+
+        // A delete of a missing object fails the batch before any write
+        let missing = UnitsObjectId::new([9; 32]);
+        let failing = [BatchOp::Set(version(kept, 2)), BatchOp::Delete(missing)];
+        assert!(storage.apply_batch(&failing, [7; 32]).is_err());
+        assert_eq!(storage.get(&kept).unwrap(), Some(version(kept, 1)));
+        assert_eq!(storage.version(&kept).unwrap(), 1);
+
+        // Later operations see earlier ones, and proofs chain through them
+        let ops = [
+            BatchOp::Set(version(kept, 2)),
+            BatchOp::Delete(dropped),
+            BatchOp::Set(version(created, 1)),
+            BatchOp::Delete(created),
+            BatchOp::Set(version(created, 2)),
+        ];
+        let proofs = storage.apply_batch(&ops, [7; 32]).unwrap();
+        assert_eq!(proofs.len(), 3);
+        assert_eq!(storage.get(&kept).unwrap(), Some(version(kept, 2)));
+        assert_eq!(storage.get(&dropped).unwrap(), None);
+        assert_eq!(storage.get(&created).unwrap(), Some(version(created, 2)));
+        assert_eq!(storage.version(&created).unwrap(), 3);
+
+        let chain = storage.get_proof_chain(&created);
+        assert_eq!(chain.last().map(|proof| proof.hash()), Some(proofs[&created].hash()));
+        assert!(chain.windows(2).all(|pair| pair[1].prev_proof_hash == Some(pair[0].hash())));
+        assert!(chain.iter().all(|proof| proof.transaction_hash == Some([7; 32])));
+    }
+
+    #[test]
+    fn test_backfill_reissues_chains_atomically() {
+        use units_proofs::ProofFormat;
+
+        /// The engine's proof data, reversed, standing in for a new format
+        struct Reversed;
+
+        impl ProofFormat for Reversed {
+            fn format_id(&self) -> &str {
+                "reversed/v1"
+            }
+
+            fn proof_data(&self, object_hash: &[u8; 32], prev: Option<[u8; 32]>, slot: SlotNumber, tx: Option<[u8; 32]>) -> Vec<u8> {
+                let mut data = ProofEngine::new().proof_data(object_hash, prev, slot, tx);
+                data.reverse();
+                data
+            }
+        }
+
+        let storage = InMemoryObjectStorage::new();
+        let (a, b) = (UnitsObjectId::new([1; 32]), UnitsObjectId::new([2; 32]));
+        for seed in 1..=2u8 {
+            storage.set(&version(a, seed), None).unwrap();
+        }
+        storage.set(&version(b, 1), None).unwrap();
+        let old_chain = storage.get_proof_chain(&a);
+
+        let engine = ProofEngine::new();
+        let migration = ProofMigration::new(&engine, &Reversed);
+        let report = storage.backfill_proofs(&migration).unwrap();
+        assert_eq!(report, ProofBackfill { chains: 2, proofs: 3 });
+
+        let new_chain = storage.get_proof_chain(&a);
+        let bridges = storage.proof_bridges(&a);
+        let reissued = units_proofs::MigratedChain { proofs: new_chain.clone(), bridges: bridges.clone() };
+        migration.validate(&old_chain, &reissued).unwrap();
+        assert_eq!(bridges.len(), 2);
+        assert_eq!(bridges[1].old_proof_hash, old_chain[1].hash());
+
+        // A chain that does not verify in the source format stops the whole backfill
+        storage.proof_history.write().unwrap().get_mut(&b).unwrap()[0].proof_data.clear();
+        let again = ProofMigration::new(&Reversed, &engine);
+        assert!(storage.backfill_proofs(&again).is_err());
+        let hashes = |chain: Vec<UnitsObjectProof>| chain.iter().map(UnitsObjectProof::hash).collect::<Vec<_>>();
+        assert_eq!(hashes(storage.get_proof_chain(&a)), hashes(new_chain));
+        assert_eq!(storage.proof_bridges(&a), bridges);
+    }
+
+    #[test]
+    fn test_commit_state_proof() {
+        let storage = ConsolidatedUnitsStorage::new_in_memory();
+        let engine = ProofEngine::new();
+        let objects: Vec<UnitsObject> = (1..=3u8)
+            .map(|seed| {
+                let id = UnitsObjectId::new([seed; 32]);
+                UnitsObject::new_data(id, id, vec![seed])
+            })
+            .collect();
+        for object in &objects {
+            storage.objects().set(object, None).unwrap();
+        }
+        storage.objects().delete(objects[2].id(), None).unwrap();
+
+        let first = storage.commit_state_proof(1, &[[7; 32]]).unwrap();
+        assert_eq!(first.prev_state_proof_hash, None);
+        assert_eq!(storage.proofs().get_state_proof(1).unwrap().unwrap().hash(), first.hash());
+
+        // Only live objects are committed, and their paths verify against the root
+        let latest = storage.inner().latest_proofs();
+        assert_eq!(latest.len(), 2);
+        assert!(engine.verify_state_proof(&first, &latest).unwrap());
+        let root = engine.state_proof_data(&first).unwrap().object_root;
+        let proof = storage.inner().get_latest_proof(objects[0].id()).unwrap();
+        let path = storage.committed_path(&first, &proof).unwrap().unwrap();
+        assert!(engine.verify_object_against_root(&objects[0], &proof, &path, &root).unwrap());
+
+        // A light client checks one object with the state proof and its inclusion proof alone
+        let inclusion = storage.inclusion_proof(objects[0].id(), 1).unwrap().unwrap();
+        assert!(engine.verify_inclusion_proof(&first, &inclusion).unwrap());
+        assert!(engine.verify_object_proof(&objects[0], &inclusion.object_proof).unwrap());
+        assert!(!engine.verify_inclusion(&first, objects[1].id(), &inclusion.object_proof, &inclusion.path).unwrap());
+        assert!(storage.inclusion_proof(objects[2].id(), 1).unwrap().is_none());
+        assert!(storage.inclusion_proof(objects[0].id(), 2).unwrap().is_none());
+
+        // Later slots chain to the most recent earlier state proof
+        let third = storage.commit_state_proof(3, &[]).unwrap();
+        assert_eq!(third.prev_state_proof_hash, Some(first.hash()));
+        assert!(!engine.verify_inclusion_proof(&third, &inclusion).unwrap());
+
+        // The kept tree picks up only the writes since, ending where a full rebuild would
+        storage.objects().set(&UnitsObject::new_data(*objects[1].id(), *objects[1].id(), vec![9]), None).unwrap();
+        storage.objects().delete(objects[0].id(), None).unwrap();
+        let fourth = storage.commit_state_proof(4, &[]).unwrap();
+        assert!(engine.verify_state_proof(&fourth, &storage.inner().latest_proofs()).unwrap());
+        assert_eq!(fourth.object_ids, vec![*objects[1].id()]);
+        assert!(storage.inclusion_proof(objects[1].id(), 4).unwrap().is_some());
+        assert!(storage.inclusion_proof(objects[0].id(), 4).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_proofs_follow_the_configured_hash_algorithm() {
+        use units_proofs::Keccak256Hasher;
+
+        let storage = ConsolidatedUnitsStorage::new_in_memory().with_hash_algorithm(HashAlgorithm::Keccak256);
+        let keccak = ProofEngine::<Keccak256Hasher>::with_hasher();
+        let id = UnitsObjectId::new([1; 32]);
+        let object = UnitsObject::new_data(id, id, vec![1]);
+        storage.objects().set(&object, None).unwrap();
+
+        let proof = storage.inner().get_latest_proof(&id).unwrap();
+        assert!(keccak.verify_object_proof(&object, &proof).unwrap());
+        assert!(!ProofEngine::new().verify_object_proof(&object, &proof).unwrap());
+
+        let state_proof = storage.commit_state_proof(1, &[]).unwrap();
+        assert_eq!(keccak.state_proof_data(&state_proof).unwrap().hash_algorithm, HashAlgorithm::Keccak256);
+        assert!(keccak.verify_state_proof(&state_proof, &storage.inner().latest_proofs()).unwrap());
+        let inclusion = storage.inclusion_proof(&id, 1).unwrap().unwrap();
+        assert!(keccak.verify_inclusion_proof(&state_proof, &inclusion).unwrap());
+        assert!(!ProofEngine::new().verify_inclusion_proof(&state_proof, &inclusion).unwrap());
+    }
+
+    #[test]
+    fn test_regenerate_missing_state_proofs() {
+        let storage = ConsolidatedUnitsStorage::new_in_memory();
+        let engine = ProofEngine::new();
+        let controller = UnitsObjectId::new([9; 32]);
+        let write = |seed: u8, slot: SlotNumber| {
+            let object = UnitsObject::new_data(UnitsObjectId::new([seed; 32]), controller, vec![seed]);
+            storage.objects().set(&object, Some([seed; 32])).unwrap();
+            storage.receipts().store_receipt(&units_core_types::TransactionReceipt::new([seed; 32], slot, true, 0)).unwrap();
+            object.id
+        };
+
+        let first_id = write(1, 1);
+        let first = storage.commit_state_proof(1, &[[1; 32]]).unwrap();
+        let second_id = write(2, 2);
+        let second_root = engine.state_proof_data(&storage.commit_state_proof(2, &[[2; 32]]).unwrap()).unwrap().object_root;
+        write(3, 3);
+        storage.commit_state_proof(4, &[]).unwrap();
+        assert_eq!(storage.state_proof_gaps().unwrap(), vec![3]);
+
+        // Slot 2 is rebuilt as it was committed, without slot 3's write
+        storage.proofs.prune_state_proofs(3);
+        storage.proofs().store_state_proof(&first).unwrap();
+        assert_eq!(storage.missing_state_proofs(1, 4).unwrap(), vec![2, 3]);
+        assert_eq!(storage.regenerate_state_proofs(1, 4).unwrap(), vec![2, 3]);
+        let regenerated = storage.proofs().get_state_proof(2).unwrap().unwrap();
+        assert_eq!(regenerated.object_ids, vec![first_id, second_id]);
+        assert_eq!(engine.state_proof_data(&regenerated).unwrap().object_root, second_root);
+        assert_eq!(engine.state_proof_data(&regenerated).unwrap().transaction_count, 1);
+
+        let third = storage.proofs().get_state_proof(3).unwrap().unwrap();
+        assert_eq!(third.prev_state_proof_hash, Some(regenerated.hash()));
+        assert_eq!(third.object_ids.len(), 3);
+        assert!(storage.state_proof_gaps().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_ephemeral_objects_skip_state_proofs_and_expire() {
+        let storage = ConsolidatedUnitsStorage::new_in_memory();
+        let controller = UnitsObjectId::new([9; 32]);
+        let data = UnitsObject::new_data(UnitsObjectId::new([1; 32]), controller, vec![1]);
+        let scratch = UnitsObject::new_ephemeral(UnitsObjectId::new([2; 32]), controller, vec![2], 5);
+        storage.objects().set(&data, None).unwrap();
+        let proof = storage.objects().set(&scratch, None).unwrap();
+        assert_eq!(proof.object_id, scratch.id);
+
+        let state_proof = storage.commit_state_proof(1, &[]).unwrap();
+        assert_eq!(state_proof.object_ids, vec![data.id]);
+
+        assert!(storage.objects().purge_expired(4).unwrap().is_empty());
+        assert_eq!(storage.objects().purge_expired(5).unwrap(), vec![scratch.id]);
+        assert!(!storage.objects().exists(&scratch.id).unwrap());
+        assert!(storage.objects().exists(&data.id).unwrap());
+    }
+
+    #[test]
+    fn test_iteration_is_ordered_with_range_and_prefix_scans() {
+        let storage = ConsolidatedUnitsStorage::new_in_memory();
+        let controller = UnitsObjectId::new([9; 32]);
+        let id = |prefix: [u8; 2]| {
+            let mut bytes = [0x55; 32];
+            bytes[..2].copy_from_slice(&prefix);
+            UnitsObjectId::new(bytes)
+        };
+        let ids = [id([3, 0]), id([1, 0xff]), id([1, 2]), id([2, 0]), id([0xff, 0xff])];
+        for object_id in &ids {
+            storage.objects().set(&UnitsObject::new_data(*object_id, controller, vec![]), None).unwrap();
+        }
+        let scanned = |iter: Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_>| {
+            iter.map(|object| *object.unwrap().id()).collect::<Vec<_>>()
+        };
+
+        let mut sorted = ids.to_vec();
+        sorted.sort();
+        assert_eq!(scanned(storage.objects().iter()), sorted);
+
+        assert_eq!(scanned(storage.objects().iter_prefix(&[1])), vec![id([1, 2]), id([1, 0xff])]);
+        assert_eq!(scanned(storage.objects().iter_prefix(&[0xff])), vec![id([0xff, 0xff])]);
+        assert!(scanned(storage.objects().iter_prefix(&[4])).is_empty());
+        assert_eq!(scanned(storage.objects().iter_range(id([1, 0xff])..id([3, 0]))), vec![id([1, 0xff]), id([2, 0])]);
+        assert!(scanned(storage.objects().iter_range(id([3, 0])..id([1, 0]))).is_empty());
+
+        // The default implementation agrees with the indexed one
+        let overlay = crate::OverlayObjectStorage::new(std::sync::Arc::new(storage));
+        overlay.set(&UnitsObject::new_data(id([1, 5]), controller, vec![]), None).unwrap();
+        assert_eq!(scanned(overlay.iter_prefix(&[1])), vec![id([1, 2]), id([1, 5]), id([1, 0xff])]);
+        assert_eq!(scanned(overlay.iter_range(id([2, 0])..)), vec![id([2, 0]), id([3, 0]), id([0xff, 0xff])]);
+    }
+
+    #[test]
+    fn test_controller_index_follows_writes() {
+        let storage = InMemoryObjectStorage::new();
+        let token = UnitsObjectId::new([7; 32]);
+        let other = UnitsObjectId::new([8; 32]);
+        let ids: Vec<_> = (1..=4).map(|byte| UnitsObjectId::new([byte; 32])).collect();
+        let scanned = |iter: Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_>| {
+            iter.map(|object| *object.unwrap().id()).collect::<Vec<_>>()
+        };
+
+        for id in ids.iter().rev() {
+            storage.set(&UnitsObject::new_data(*id, token, vec![]), None).unwrap();
+        }
+        storage.set(&UnitsObject::new_data(ids[1], other, vec![]), None).unwrap();
+        storage.delete(&ids[2], None).unwrap();
+        assert_eq!(scanned(storage.iter_by_controller(&token)), vec![ids[0], ids[3]]);
+        assert_eq!(scanned(storage.iter_by_controller(&other)), vec![ids[1]]);
+
+        storage
+            .apply_batch(&[BatchOp::Delete(ids[0]), BatchOp::Set(UnitsObject::new_data(ids[2], other, vec![]))], [1; 32])
+            .unwrap();
+        assert_eq!(scanned(storage.iter_by_controller(&token)), vec![ids[3]]);
+        assert_eq!(scanned(storage.iter_by_controller(&other)), vec![ids[1], ids[2]]);
+        assert!(scanned(storage.iter_by_controller(&UnitsObjectId::default())).is_empty());
+
+        // The default implementation agrees with the indexed one
+        let expected = scanned(storage.iter_by_controller(&other));
+        let chaos = crate::ChaosStorage::new(storage, crate::ChaosConfig::new(0));
+        assert_eq!(scanned(chaos.iter_filtered(move |object| object.controller_id == other)), expected);
+    }
+}
@@ -0,0 +1,310 @@
+//! Workload-aware secondary index recommendations
+//!
+//! A query for the objects of one controller walks the whole store unless
+//! an index narrows it down. The service layer reports each such query,
+//! and each lookup by metadata key, together with the number of objects it
+//! visited. Patterns that recur at a high cost per query are recommended
+//! for an index; with `auto_create` the advisor builds controller indexes
+//! on its own.
+//!
+//! A controller index is backfilled from storage once and then kept in
+//! step with writes as a storage observer. Metadata keys are always served
+//! by the [`MetadataIndex`](crate::MetadataIndex), so their patterns are
+//! reported as indexed.
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::Bound;
+use std::sync::{Mutex, RwLock};
+
+use serde::{Deserialize, Serialize};
+use units_core_types::error::StorageError;
+use units_core_types::id::UnitsObjectId;
+use units_core_types::objects::UnitsObject;
+use units_core_types::{StorageObserver, UnitsObjectProof};
+
+/// Filter a query applied to the object store
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum QueryPattern {
+    /// Objects of one controller
+    Controller { controller_id: UnitsObjectId },
+    /// Objects of one controller carrying a metadata key
+    MetadataKey { controller_id: UnitsObjectId, key: String },
+}
+
+/// When patterns are recommended for an index, and whether indexes are
+/// created without an admin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexAdvisorConfig {
+    /// Queries a pattern must receive before it is recommended
+    pub min_queries: u64,
+    /// Objects visited per query, on average, from which a pattern is recommended
+    pub min_average_scanned: u64,
+    /// Create recommended controller indexes as soon as they qualify
+    pub auto_create: bool,
+}
+
+impl Default for IndexAdvisorConfig {
+    fn default() -> Self {
+        Self {
+            min_queries: 100,
+            min_average_scanned: 1_000,
+            auto_create: false,
+        }
+    }
+}
+
+/// A query pattern hot and costly enough to deserve an index
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexRecommendation {
+    pub pattern: QueryPattern,
+    pub queries: u64,
+    /// Objects visited across all of those queries
+    pub scanned: u64,
+    /// Whether an index already serves the pattern
+    pub indexed: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct PatternStats {
+    queries: u64,
+    scanned: u64,
+}
+
+/// Objects of one controller, in ID order
+#[derive(Debug, Default)]
+struct ControllerIndex {
+    ids: BTreeSet<UnitsObjectId>,
+    /// Set while the index is backfilled: objects that left the controller
+    /// since the backfill began, which it must not add back
+    departed: Option<HashSet<UnitsObjectId>>,
+}
+
+/// Query pattern statistics and the controller indexes built from them
+#[derive(Debug, Default)]
+pub struct IndexAdvisor {
+    config: RwLock<IndexAdvisorConfig>,
+    patterns: Mutex<HashMap<QueryPattern, PatternStats>>,
+    controllers: RwLock<HashMap<UnitsObjectId, ControllerIndex>>,
+}
+
+impl IndexAdvisor {
+    pub fn new(config: IndexAdvisorConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            ..Self::default()
+        }
+    }
+
+    pub fn config(&self) -> IndexAdvisorConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Replace the thresholds and auto-creation setting
+    pub fn set_config(&self, config: IndexAdvisorConfig) {
+        *self.config.write().unwrap() = config;
+    }
+
+    /// Record a query matching `pattern` that visited `scanned` objects
+    ///
+    /// Returns whether the pattern now qualifies for an index it lacks and
+    /// auto-creation is enabled, so the caller should build it.
+    pub fn record(&self, pattern: &QueryPattern, scanned: usize) -> bool {
+        let stats = {
+            let mut patterns = self.patterns.lock().unwrap();
+            let stats = patterns.entry(pattern.clone()).or_default();
+            stats.queries += 1;
+            stats.scanned = stats.scanned.saturating_add(scanned as u64);
+            *stats
+        };
+        let config = self.config();
+        config.auto_create && Self::qualifies(&config, stats) && !self.is_indexed(pattern)
+    }
+
+    /// Patterns past the recommendation thresholds, most objects visited first
+    pub fn recommendations(&self) -> Vec<IndexRecommendation> {
+        let config = self.config();
+        let patterns = self.patterns.lock().unwrap();
+        let mut recommendations: Vec<_> = patterns
+            .iter()
+            .filter(|(_, stats)| Self::qualifies(&config, **stats))
+            .map(|(pattern, stats)| IndexRecommendation {
+                pattern: pattern.clone(),
+                queries: stats.queries,
+                scanned: stats.scanned,
+                indexed: self.is_indexed(pattern),
+            })
+            .collect();
+        recommendations.sort_by(|a, b| b.scanned.cmp(&a.scanned).then_with(|| a.pattern.cmp(&b.pattern)));
+        recommendations
+    }
+
+    fn qualifies(config: &IndexAdvisorConfig, stats: PatternStats) -> bool {
+        stats.queries >= config.min_queries.max(1) && stats.scanned / stats.queries >= config.min_average_scanned
+    }
+
+    /// Whether an index serves `pattern`, counting one still being backfilled
+    pub fn is_indexed(&self, pattern: &QueryPattern) -> bool {
+        match pattern {
+            QueryPattern::Controller { controller_id } => self.controllers.read().unwrap().contains_key(controller_id),
+            QueryPattern::MetadataKey { .. } => true,
+        }
+    }
+
+    /// Controllers with an index, in ID order
+    pub fn indexed_controllers(&self) -> Vec<UnitsObjectId> {
+        let mut controllers: Vec<_> = self.controllers.read().unwrap().keys().copied().collect();
+        controllers.sort();
+        controllers
+    }
+
+    /// Index the objects of `controller_id`, backfilling from `objects`
+    ///
+    /// Writes made during the backfill are tracked as they happen, so the
+    /// index is exact once this returns. Returns the number of objects
+    /// indexed; an existing index is left as it is.
+    pub fn create_controller_index<I>(&self, controller_id: UnitsObjectId, objects: I) -> Result<usize, StorageError>
+    where
+        I: Iterator<Item = Result<UnitsObject, StorageError>>,
+    {
+        match self.controllers.write().unwrap().entry(controller_id) {
+            Entry::Occupied(index) => return Ok(index.get().ids.len()),
+            Entry::Vacant(entry) => {
+                entry.insert(ControllerIndex {
+                    ids: BTreeSet::new(),
+                    departed: Some(HashSet::new()),
+                });
+            }
+        }
+
+        let found = objects
+            .filter(|object| object.as_ref().map_or(true, |object| object.controller_id() == &controller_id))
+            .map(|object| object.map(|object| *object.id()))
+            .collect::<Result<Vec<_>, _>>();
+        let mut controllers = self.controllers.write().unwrap();
+        let found = match found {
+            Ok(found) => found,
+            Err(err) => {
+                controllers.remove(&controller_id);
+                return Err(err);
+            }
+        };
+
+        let index = controllers.get_mut(&controller_id).expect("only the backfill removes a building index");
+        let departed = index.departed.take().unwrap_or_default();
+        index.ids.extend(found.into_iter().filter(|id| !departed.contains(id)));
+        Ok(index.ids.len())
+    }
+
+    /// Objects of `controller_id` from `start` on, in ID order, or `None`
+    /// if the controller has no index ready
+    pub fn controller_objects(&self, controller_id: &UnitsObjectId, start: Bound<UnitsObjectId>) -> Option<Vec<UnitsObjectId>> {
+        let controllers = self.controllers.read().unwrap();
+        let index = controllers.get(controller_id).filter(|index| index.departed.is_none())?;
+        Some(index.ids.range((start, Bound::Unbounded)).copied().collect())
+    }
+
+    /// Drop `id` from every index but its controller's
+    fn depart(controllers: &mut HashMap<UnitsObjectId, ControllerIndex>, id: &UnitsObjectId, keep: Option<&UnitsObjectId>) {
+        for (controller_id, index) in controllers.iter_mut() {
+            if Some(controller_id) == keep {
+                index.ids.insert(*id);
+                if let Some(departed) = &mut index.departed {
+                    departed.remove(id);
+                }
+            } else {
+                index.ids.remove(id);
+                if let Some(departed) = &mut index.departed {
+                    departed.insert(*id);
+                }
+            }
+        }
+    }
+}
+
+impl StorageObserver for IndexAdvisor {
+    fn on_set(&self, object: &UnitsObject, _proof: &UnitsObjectProof) {
+        if self.controllers.read().unwrap().is_empty() {
+            return;
+        }
+        Self::depart(&mut self.controllers.write().unwrap(), object.id(), Some(object.controller_id()));
+    }
+
+    fn on_delete(&self, id: &UnitsObjectId, _proof: &UnitsObjectProof) {
+        if self.controllers.read().unwrap().is_empty() {
+            return;
+        }
+        Self::depart(&mut self.controllers.write().unwrap(), id, None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use units_proofs::ProofEngine;
+
+    fn object(byte: u8, controller: u8) -> UnitsObject {
+        UnitsObject::new_data(UnitsObjectId::new([byte; 32]), UnitsObjectId::new([controller; 32]), vec![byte])
+    }
+
+    #[test]
+    fn test_hot_costly_patterns_are_recommended_and_indexed() {
+        let advisor = IndexAdvisor::new(IndexAdvisorConfig {
+            min_queries: 2,
+            min_average_scanned: 10,
+            auto_create: true,
+        });
+        let controller_id = UnitsObjectId::new([0xC0; 32]);
+        let pattern = QueryPattern::Controller { controller_id };
+        let cheap = QueryPattern::Controller { controller_id: UnitsObjectId::new([0xC1; 32]) };
+
+        // One query is not a workload, and cheap patterns never qualify
+        assert!(!advisor.record(&pattern, 50));
+        assert!(!advisor.record(&cheap, 1));
+        assert!(!advisor.record(&cheap, 1));
+        assert!(advisor.record(&pattern, 50));
+        assert_eq!(advisor.recommendations(), vec![IndexRecommendation {
+            pattern: pattern.clone(),
+            queries: 2,
+            scanned: 100,
+            indexed: false,
+        }]);
+
+        // The backfill finds the controller's objects, and the index then
+        // follows writes, controller changes and deletions
+        let objects = vec![object(1, 0xC0), object(2, 0xC1), object(3, 0xC0)];
+        assert_eq!(advisor.create_controller_index(controller_id, objects.into_iter().map(Ok)).unwrap(), 2);
+        assert!(!advisor.record(&pattern, 50));
+        assert!(advisor.recommendations()[0].indexed);
+
+        let proof = ProofEngine::new().generate_object_proof(&object(0, 0), None, None).unwrap();
+        advisor.on_set(&object(2, 0xC0), &proof);
+        advisor.on_set(&object(3, 0xC1), &proof);
+        advisor.on_delete(object(1, 0).id(), &proof);
+        assert_eq!(advisor.controller_objects(&controller_id, Bound::Unbounded), Some(vec![*object(2, 0).id()]));
+        assert_eq!(advisor.controller_objects(&controller_id, Bound::Excluded(*object(2, 0).id())), Some(vec![]));
+        assert_eq!(advisor.controller_objects(&UnitsObjectId::new([0xC1; 32]), Bound::Unbounded), None);
+    }
+
+    #[test]
+    fn test_backfill_does_not_resurrect_objects_that_moved() {
+        let advisor = IndexAdvisor::default();
+        let controller_id = UnitsObjectId::new([0xC0; 32]);
+        let proof = ProofEngine::new().generate_object_proof(&object(0, 0), None, None).unwrap();
+
+        // The backfill reads a stale copy of object 1 while a write moves it
+        // to another controller
+        let moved = object(1, 0xC1);
+        let stale = vec![object(1, 0xC0), object(2, 0xC0)];
+        let objects = stale.into_iter().map(|stale| {
+            if stale.id() == moved.id() {
+                advisor.on_set(&moved, &proof);
+            }
+            Ok(stale)
+        });
+        assert_eq!(advisor.create_controller_index(controller_id, objects).unwrap(), 1);
+        assert_eq!(advisor.controller_objects(&controller_id, Bound::Unbounded), Some(vec![*object(2, 0).id()]));
+    }
+}
@@ -7,13 +7,43 @@
 //! - `InMemoryObjectStorage`: In-memory object storage for testing/development
 //! - `InMemoryProofStorage`: In-memory proof storage
 //! - `InMemoryReceiptStorage`: In-memory transaction receipt storage
-//! - `InMemoryLockManager`: Simple lock manager for development
-//! - `FileWriteAheadLog`: File-based write-ahead logging
+//! - `InMemoryLockManager`: Simple lock manager for development, with deadlock detection
+//! - `FileWriteAheadLog`: File-based write-ahead logging with configurable fsync durability
 //! - `ConsolidatedUnitsStorage`: Complete storage solution using composition
+//! - `ObjectArchive`: Portable object bundle for export/import with proofs intact
+//! - `OverlayObjectStorage`: Copy-on-write fork of another storage's objects
+//! - `PostgresStorage`: Object, proof and receipt storage shared by several nodes (`postgres` feature)
+//! - `RocksDbStorage`: Column-family storage for high-throughput batch writes (`rocksdb` feature)
+//! - `SqliteStorage`: Single-file object, proof and receipt storage (`sqlite` feature)
+//! - `SqliteLockManager`: Crash-safe persistent lock table with leased RAII guards (`sqlite` feature)
+//! - `CodecConfig`: lz4/zstd compression of receipts and WAL records
+//! - `MetricsObserver` / `CompositeObserver`: Storage operation counters and observer fan-out
+//! - `MetadataIndex`: Key/value annotations on objects, queryable per controller
+//! - `IndexAdvisor`: Query-pattern tracking that recommends, and can build, controller indexes
+//! - `ChaosStorage`: Fault-injecting wrapper for testing how storage is composed
+//!
+//! With `default-features = false, features = ["minimal"]` the crate builds
+//! without rayon, SQLite or the compression codecs, for embedded and WASM
+//! consumers that only need storage and proofs.
 
+pub mod archive;
+pub mod chaos;
+pub mod codec;
 pub mod consolidated_storage;
+pub mod index_advisor;
 pub mod receipt_storage;
+#[cfg(feature = "rocksdb")]
+pub mod rocksdb_storage;
 pub mod lock_manager;
+pub mod metadata_index;
+pub mod observer;
+pub mod overlay;
+#[cfg(feature = "postgres")]
+pub mod postgres_storage;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_lock_manager;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_storage;
 pub mod wal;
 
 // Re-export the main storage traits for convenience
@@ -25,9 +55,24 @@ pub use units_core_types::{
 // Export concrete implementations
 pub use consolidated_storage::{
     InMemoryObjectStorage, InMemoryProofStorage, NoOpWriteAheadLog, 
-    ConsolidatedUnitsStorage,
+    ConsolidatedUnitsStorage, ObjectChanges, ProofBackfill, RetainedObject, DEFAULT_HISTORY_DEPTH,
 };
 
+pub use archive::{ObjectArchive, VerifyProgress};
+pub use chaos::{ChaosConfig, ChaosStats, ChaosStorage};
+pub use codec::{Codec, CodecConfig, CodecStats};
+pub use observer::{CompositeObserver, MetricsObserver, StorageMetrics};
+pub use overlay::OverlayObjectStorage;
 pub use receipt_storage::InMemoryReceiptStorage;
-pub use lock_manager::{InMemoryLockManager, SimpleLockGuard};
-pub use wal::{FileWriteAheadLog, WALEntry, WALEntryType};
\ No newline at end of file
+#[cfg(feature = "rocksdb")]
+pub use rocksdb_storage::RocksDbStorage;
+pub use lock_manager::{InMemoryLockManager, SimpleLockGuard, TransactionLocks, WaitForGraph, DEFAULT_LOCK_TIMEOUT};
+pub use metadata_index::MetadataIndex;
+pub use index_advisor::{IndexAdvisor, IndexAdvisorConfig, IndexRecommendation, QueryPattern};
+#[cfg(feature = "postgres")]
+pub use postgres_storage::PostgresStorage;
+#[cfg(feature = "sqlite")]
+pub use sqlite_lock_manager::{SqliteLockManager, SqliteLockGuard, LockRecovery, DEFAULT_LOCK_LEASE};
+#[cfg(feature = "sqlite")]
+pub use sqlite_storage::SqliteStorage;
+pub use wal::{FileWriteAheadLog, WalDurability, WALEntry, WALEntryType};
\ No newline at end of file
@@ -1,33 +1,235 @@
 //! Lock Manager Implementation
-//! 
+//!
 //! Provides concrete implementations of the LockManager trait for object-level locking.
 
 use units_core_types::LockManager;
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
 use units_core_types::error::StorageError;
 use units_core_types::id::UnitsObjectId;
 
-/// Simple lock guard implementation
-pub struct SimpleLockGuard {
-    _object_id: UnitsObjectId,
+/// How long `lock` and `lock_many` wait for a held lock by default
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Guard for an object lock, released when dropped
+pub struct SimpleLockGuard<'a> {
+    object_id: UnitsObjectId,
+    manager: &'a InMemoryLockManager,
+}
+
+impl SimpleLockGuard<'_> {
+    /// The locked object
+    pub fn object_id(&self) -> &UnitsObjectId {
+        &self.object_id
+    }
+}
+
+impl Drop for SimpleLockGuard<'_> {
+    fn drop(&mut self) {
+        self.manager.release(&self.object_id);
+    }
+}
+
+/// Which transaction waits for which object lock
+///
+/// A waiter is blocked by whoever currently holds the object it waits for,
+/// so the edges are derived from the lock table at the time of the check
+/// and never go stale when a lock changes hands. Transaction IDs grow with
+/// age: a larger ID is a younger transaction.
+#[derive(Debug, Default)]
+pub struct WaitForGraph {
+    waiting_on: HashMap<u64, UnitsObjectId>,
+}
+
+impl WaitForGraph {
+    /// Record that `waiter` is blocked on `object`
+    pub fn wait(&mut self, waiter: u64, object: UnitsObjectId) {
+        self.waiting_on.insert(waiter, object);
+    }
+
+    /// Record that `waiter` is no longer blocked
+    pub fn stop_waiting(&mut self, waiter: u64) {
+        self.waiting_on.remove(&waiter);
+    }
+
+    /// The transactions of the wait cycle `waiter` is part of, if any
+    ///
+    /// Follows the chain from `waiter` to the holder of the object it waits
+    /// for, then to the holder of the object that holder waits for, and so
+    /// on until the chain ends or returns to `waiter`.
+    pub fn find_cycle(&self, waiter: u64, holders: &HashMap<UnitsObjectId, u64>) -> Option<Vec<u64>> {
+        let mut cycle = vec![waiter];
+        let mut current = waiter;
+        loop {
+            let holder = *holders.get(self.waiting_on.get(&current)?)?;
+            if holder == waiter {
+                return Some(cycle);
+            }
+            // A cycle that does not pass through `waiter` is found by its own members
+            if cycle.contains(&holder) {
+                return None;
+            }
+            cycle.push(holder);
+            current = holder;
+        }
+    }
 }
 
-unsafe impl Send for SimpleLockGuard {}
-unsafe impl Sync for SimpleLockGuard {}
+/// Lock table shared by every guard of one manager
+#[derive(Default)]
+struct LockTable {
+    /// Object to the transaction holding it
+    held: HashMap<UnitsObjectId, u64>,
+    waits: WaitForGraph,
+    /// Waiting transactions chosen to break a deadlock, with the object
+    /// each was waiting for
+    victims: HashMap<u64, UnitsObjectId>,
+}
 
-/// Simple in-memory lock manager for testing and development
+/// In-memory exclusive lock manager for testing and development
+///
+/// Each object can be held by one guard at a time. Blocking acquisition
+/// gives up with `StorageError::LockTimeout` after the configured timeout.
+///
+/// Locks taken through [`InMemoryLockManager::transaction`] are owned by
+/// that transaction. When a transaction that already holds locks would wait
+/// in a cycle, the youngest transaction in the cycle is aborted with
+/// `StorageError::DeadlockDetected` instead of everyone waiting out the
+/// timeout.
 pub struct InMemoryLockManager {
-    #[allow(dead_code)]
-    locks: Arc<Mutex<HashMap<UnitsObjectId, Arc<Mutex<()>>>>>,
+    table: Mutex<LockTable>,
+    released: Condvar,
+    timeout: Duration,
+    next_transaction: AtomicU64,
 }
 
 impl InMemoryLockManager {
     pub fn new() -> Self {
+        Self::with_timeout(DEFAULT_LOCK_TIMEOUT)
+    }
+
+    /// Create a lock manager that waits at most `timeout` for a held lock
+    pub fn with_timeout(timeout: Duration) -> Self {
         Self {
-            locks: Arc::new(Mutex::new(HashMap::new())),
+            table: Mutex::new(LockTable::default()),
+            released: Condvar::new(),
+            timeout,
+            next_transaction: AtomicU64::new(0),
+        }
+    }
+
+    /// Start a transaction whose locks take part in deadlock detection
+    ///
+    /// Transactions started later are younger and lose deadlocks.
+    pub fn transaction(&self) -> TransactionLocks<'_> {
+        TransactionLocks {
+            manager: self,
+            id: self.next_transaction.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// Whether an object is currently locked
+    pub fn is_locked(&self, id: &UnitsObjectId) -> bool {
+        self.table.lock().unwrap().held.contains_key(id)
+    }
+
+    /// Objects currently locked, in ID order
+    pub fn held(&self) -> Vec<UnitsObjectId> {
+        let mut held: Vec<_> = self.table.lock().unwrap().held.keys().copied().collect();
+        held.sort_unstable();
+        held
+    }
+
+    /// Release every lock, returning how many were held
+    ///
+    /// Only for locks no guard owns any more, such as those found before
+    /// the node has executed anything; a live guard would lose its lock.
+    pub fn release_all(&self) -> usize {
+        let released = std::mem::take(&mut self.table.lock().unwrap().held).len();
+        self.released.notify_all();
+        released
+    }
+
+    fn release(&self, id: &UnitsObjectId) {
+        self.table.lock().unwrap().held.remove(id);
+        self.released.notify_all();
+    }
+
+    /// Wait until every object in `ids` is free, then take them all at once for `owner`
+    fn acquire_all(&self, ids: &[UnitsObjectId], owner: u64) -> Result<(), StorageError> {
+        let deadline = Instant::now() + self.timeout;
+        let mut table = self.table.lock().unwrap();
+
+        let result = loop {
+            if let Some(object) = table.victims.remove(&owner) {
+                break Err(StorageError::DeadlockDetected(object));
+            }
+            let Some(busy) = ids.iter().copied().find(|id| table.held.contains_key(id)) else {
+                break Ok(());
+            };
+
+            table.waits.wait(owner, busy);
+            if let Some(cycle) = table.waits.find_cycle(owner, &table.held) {
+                let youngest = cycle.into_iter().max().unwrap_or(owner);
+                if youngest == owner {
+                    break Err(StorageError::DeadlockDetected(busy));
+                }
+                let object = table.waits.waiting_on[&youngest];
+                table.victims.insert(youngest, object);
+                self.released.notify_all();
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break Err(StorageError::LockTimeout(busy));
+            }
+            table = self.released.wait_timeout(table, remaining).unwrap().0;
+        };
+
+        table.waits.stop_waiting(owner);
+        if result.is_ok() {
+            table.held.extend(ids.iter().map(|id| (*id, owner)));
+        }
+        result
+    }
+
+    fn try_acquire(&self, id: &UnitsObjectId, owner: u64) -> bool {
+        let mut table = self.table.lock().unwrap();
+        if table.held.contains_key(id) {
+            return false;
+        }
+        table.held.insert(*id, owner);
+        true
+    }
+
+    fn guard(&self, id: UnitsObjectId) -> SimpleLockGuard<'_> {
+        SimpleLockGuard {
+            object_id: id,
+            manager: self,
         }
     }
+
+    fn lock_as(&self, id: &UnitsObjectId, owner: u64) -> Result<SimpleLockGuard<'_>, StorageError> {
+        self.acquire_all(std::slice::from_ref(id), owner)?;
+        Ok(self.guard(*id))
+    }
+
+    fn try_lock_as(&self, id: &UnitsObjectId, owner: u64) -> Option<SimpleLockGuard<'_>> {
+        self.try_acquire(id, owner).then(|| self.guard(*id))
+    }
+
+    fn lock_many_as(&self, ids: &[UnitsObjectId], owner: u64) -> Result<Vec<SimpleLockGuard<'_>>, StorageError> {
+        // Sorting gives callers a consistent order; taking every lock in one
+        // step means a partial set is never held while waiting
+        let mut ids = ids.to_vec();
+        ids.sort();
+        ids.dedup();
+
+        self.acquire_all(&ids, owner)?;
+        Ok(ids.into_iter().map(|id| self.guard(id)).collect())
+    }
 }
 
 impl Default for InMemoryLockManager {
@@ -37,25 +239,51 @@ impl Default for InMemoryLockManager {
 }
 
 impl LockManager for InMemoryLockManager {
-    type Guard<'a> = SimpleLockGuard where Self: 'a;
-    
+    type Guard<'a> = SimpleLockGuard<'a> where Self: 'a;
+
+    // Each call outside a transaction is its own transaction
     fn lock(&self, id: &UnitsObjectId) -> Result<Self::Guard<'_>, StorageError> {
-        // For now, return a simple guard without actual locking
-        // A full implementation would manage actual mutex locks
-        Ok(SimpleLockGuard { _object_id: *id })
+        self.lock_as(id, self.transaction().id)
     }
-    
+
     fn try_lock(&self, id: &UnitsObjectId) -> Result<Option<Self::Guard<'_>>, StorageError> {
-        // For now, always succeed
-        Ok(Some(SimpleLockGuard { _object_id: *id }))
+        Ok(self.try_lock_as(id, self.transaction().id))
     }
-    
+
     fn lock_many(&self, ids: &[UnitsObjectId]) -> Result<Vec<Self::Guard<'_>>, StorageError> {
-        // For now, return guards for all requested IDs
-        let guards = ids.iter()
-            .map(|id| SimpleLockGuard { _object_id: *id })
-            .collect();
-        Ok(guards)
+        self.lock_many_as(ids, self.transaction().id)
+    }
+}
+
+/// Locks taken incrementally by one transaction
+///
+/// Relocking an object the transaction already holds is reported as a
+/// deadlock, since the transaction would wait for itself.
+pub struct TransactionLocks<'a> {
+    manager: &'a InMemoryLockManager,
+    id: u64,
+}
+
+impl TransactionLocks<'_> {
+    /// The transaction's age; larger IDs are younger
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl LockManager for TransactionLocks<'_> {
+    type Guard<'b> = SimpleLockGuard<'b> where Self: 'b;
+
+    fn lock(&self, id: &UnitsObjectId) -> Result<Self::Guard<'_>, StorageError> {
+        self.manager.lock_as(id, self.id)
+    }
+
+    fn try_lock(&self, id: &UnitsObjectId) -> Result<Option<Self::Guard<'_>>, StorageError> {
+        Ok(self.manager.try_lock_as(id, self.id))
+    }
+
+    fn lock_many(&self, ids: &[UnitsObjectId]) -> Result<Vec<Self::Guard<'_>>, StorageError> {
+        self.manager.lock_many_as(ids, self.id)
     }
 }
 
@@ -69,15 +297,79 @@ mod tests {
         let object_id = UnitsObjectId::random();
 
         // Test basic locking
-        let _guard = lock_manager.lock(&object_id).unwrap();
-        
-        // Test try_lock
-        let _try_guard = lock_manager.try_lock(&object_id).unwrap();
-        
-        // Test multiple locks
-        let ids = [object_id, UnitsObjectId::random()];
+        let guard = lock_manager.lock(&object_id).unwrap();
+        assert!(lock_manager.is_locked(&object_id));
+
+        // A held lock cannot be taken again until released
+        assert!(lock_manager.try_lock(&object_id).unwrap().is_none());
+        drop(guard);
+        assert!(!lock_manager.is_locked(&object_id));
+        let _try_guard = lock_manager.try_lock(&object_id).unwrap().unwrap();
+        drop(_try_guard);
+
+        // Test multiple locks, duplicates collapse into one guard
+        let ids = [object_id, UnitsObjectId::random(), object_id];
         let _guards = lock_manager.lock_many(&ids).unwrap();
-        
+
         assert_eq!(_guards.len(), 2);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_lock_timeout_and_handoff() {
+        let lock_manager = InMemoryLockManager::with_timeout(Duration::from_millis(20));
+        let a = UnitsObjectId::new([1; 32]);
+        let b = UnitsObjectId::new([2; 32]);
+
+        let guard = lock_manager.lock(&b).unwrap();
+        match lock_manager.lock_many(&[a, b]) {
+            Err(StorageError::LockTimeout(id)) => assert_eq!(id, b),
+            other => panic!("expected lock timeout, got {:?}", other.map(|g| g.len())),
+        }
+        // The failed attempt must not leave `a` locked
+        assert!(!lock_manager.is_locked(&a));
+
+        // A waiter acquires the lock once the holder releases it
+        let lock_manager = InMemoryLockManager::with_timeout(Duration::from_secs(5));
+        let held = lock_manager.lock(&a).unwrap();
+        std::thread::scope(|scope| {
+            let waiter = scope.spawn(|| lock_manager.lock(&a).map(|_| ()));
+            std::thread::sleep(Duration::from_millis(10));
+            drop(held);
+            waiter.join().unwrap().unwrap();
+        });
+        drop(guard);
+    }
+
+    #[test]
+    fn test_deadlock_aborts_younger_transaction() {
+        let lock_manager = InMemoryLockManager::with_timeout(Duration::from_secs(5));
+        let a = UnitsObjectId::new([1; 32]);
+        let b = UnitsObjectId::new([2; 32]);
+
+        let older = lock_manager.transaction();
+        let younger = lock_manager.transaction();
+        assert!(older.id() < younger.id());
+
+        let held_a = older.lock(&a).unwrap();
+        let held_b = younger.lock(&b).unwrap();
+        std::thread::scope(|scope| {
+            // The older transaction waits for `b`, then the younger one closes the cycle
+            let waiter = scope.spawn(|| older.lock(&b).map(|_| ()));
+            std::thread::sleep(Duration::from_millis(10));
+            match younger.lock(&a) {
+                Err(StorageError::DeadlockDetected(id)) => assert_eq!(id, a),
+                other => panic!("expected deadlock, got {:?}", other.map(|_| ())),
+            }
+            // Aborting the younger transaction lets the older one finish
+            drop(held_b);
+            waiter.join().unwrap().unwrap();
+        });
+        drop(held_a);
+
+        // Waiting for a lock the transaction itself holds can never succeed
+        let transaction = lock_manager.transaction();
+        let _guard = transaction.lock(&a).unwrap();
+        assert!(matches!(transaction.lock_many(&[a, b]), Err(StorageError::DeadlockDetected(id)) if id == a));
+        assert!(!lock_manager.is_locked(&b));
+    }
+}
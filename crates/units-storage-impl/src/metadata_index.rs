@@ -0,0 +1,187 @@
+//! Secondary index of key/value annotations on objects
+//!
+//! Controllers attach small string annotations to the objects they control,
+//! such as `symbol=TEST` on a token, and find objects by them later without
+//! decoding every object's data. Entries are indexed under the annotated
+//! object's controller, so one controller's annotations never answer
+//! another's queries.
+//!
+//! The index observes the storage it belongs to and drops an object's
+//! annotations when the object is deleted.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::RwLock;
+
+use units_core_types::error::StorageError;
+use units_core_types::id::UnitsObjectId;
+use units_core_types::objects::UnitsObject;
+use units_core_types::{StorageObserver, UnitsObjectProof};
+
+/// Most annotations one object may carry
+pub const MAX_METADATA_ENTRIES: usize = 16;
+
+/// Longest annotation key, in bytes
+pub const MAX_METADATA_KEY_LEN: usize = 64;
+
+/// Longest annotation value, in bytes
+pub const MAX_METADATA_VALUE_LEN: usize = 256;
+
+/// Lookup key of the reverse index: controller, key and value
+type IndexKey = (UnitsObjectId, String, String);
+
+#[derive(Debug, Default)]
+struct Entries {
+    /// Controller and annotations of each annotated object
+    by_object: HashMap<UnitsObjectId, (UnitsObjectId, BTreeMap<String, String>)>,
+    by_value: BTreeMap<IndexKey, BTreeSet<UnitsObjectId>>,
+}
+
+/// In-memory metadata index, kept in step with object deletions
+#[derive(Debug, Default)]
+pub struct MetadataIndex {
+    entries: RwLock<Entries>,
+}
+
+impl MetadataIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set annotation `key` of `object` to `value`, or remove it with `None`
+    pub fn annotate(&self, object: &UnitsObject, key: &str, value: Option<&str>) -> Result<(), StorageError> {
+        if key.is_empty() || key.len() > MAX_METADATA_KEY_LEN {
+            return Err(StorageError::Other(format!(
+                "Metadata keys must be 1 to {} bytes",
+                MAX_METADATA_KEY_LEN
+            )));
+        }
+        if value.is_some_and(|value| value.len() > MAX_METADATA_VALUE_LEN) {
+            return Err(StorageError::Other(format!(
+                "Metadata values must be at most {} bytes",
+                MAX_METADATA_VALUE_LEN
+            )));
+        }
+
+        let id = *object.id();
+        let controller_id = *object.controller_id();
+        let mut entries = self.entries.write().unwrap();
+        // Annotations made under an earlier incarnation's controller lapse
+        if entries.by_object.get(&id).is_some_and(|(indexed, _)| *indexed != controller_id) {
+            entries.remove(&id);
+        }
+        let Entries { by_object, by_value } = &mut *entries;
+        let (_, annotations) = by_object.entry(id).or_insert_with(|| (controller_id, BTreeMap::new()));
+        if value.is_some() && !annotations.contains_key(key) && annotations.len() >= MAX_METADATA_ENTRIES {
+            return Err(StorageError::Other(format!(
+                "Objects carry at most {} metadata entries",
+                MAX_METADATA_ENTRIES
+            )));
+        }
+
+        let previous = match value {
+            Some(value) => annotations.insert(key.to_string(), value.to_string()),
+            None => annotations.remove(key),
+        };
+        if let Some(previous) = previous {
+            Self::unlink(by_value, (controller_id, key.to_string(), previous), &id);
+        }
+        if let Some(value) = value {
+            by_value
+                .entry((controller_id, key.to_string(), value.to_string()))
+                .or_default()
+                .insert(id);
+        }
+        if annotations.is_empty() {
+            by_object.remove(&id);
+        }
+        Ok(())
+    }
+
+    /// Annotations of `id`, empty if it has none
+    pub fn metadata(&self, id: &UnitsObjectId) -> BTreeMap<String, String> {
+        let entries = self.entries.read().unwrap();
+        entries
+            .by_object
+            .get(id)
+            .map(|(_, annotations)| annotations.clone())
+            .unwrap_or_default()
+    }
+
+    /// Objects of `controller_id` annotated with `key=value`, in ID order
+    pub fn find(&self, controller_id: &UnitsObjectId, key: &str, value: &str) -> Vec<UnitsObjectId> {
+        let entries = self.entries.read().unwrap();
+        entries
+            .by_value
+            .get(&(*controller_id, key.to_string(), value.to_string()))
+            .map(|ids| ids.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Drop every annotation of `id`
+    pub fn forget(&self, id: &UnitsObjectId) {
+        self.entries.write().unwrap().remove(id);
+    }
+
+    fn unlink(by_value: &mut BTreeMap<IndexKey, BTreeSet<UnitsObjectId>>, index_key: IndexKey, id: &UnitsObjectId) {
+        if let Some(ids) = by_value.get_mut(&index_key) {
+            ids.remove(id);
+            if ids.is_empty() {
+                by_value.remove(&index_key);
+            }
+        }
+    }
+}
+
+impl Entries {
+    fn remove(&mut self, id: &UnitsObjectId) {
+        if let Some((controller_id, annotations)) = self.by_object.remove(id) {
+            for (key, value) in annotations {
+                MetadataIndex::unlink(&mut self.by_value, (controller_id, key, value), id);
+            }
+        }
+    }
+}
+
+impl StorageObserver for MetadataIndex {
+    fn on_delete(&self, id: &UnitsObjectId, _proof: &UnitsObjectProof) {
+        self.forget(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_by_metadata_is_scoped_to_controller() {
+        let index = MetadataIndex::new();
+        let (tokens, other) = (UnitsObjectId::new([1; 32]), UnitsObjectId::new([2; 32]));
+        let test = UnitsObject::new_data(UnitsObjectId::new([10; 32]), tokens, vec![]);
+        let gold = UnitsObject::new_data(UnitsObjectId::new([11; 32]), tokens, vec![]);
+        let foreign = UnitsObject::new_data(UnitsObjectId::new([12; 32]), other, vec![]);
+
+        index.annotate(&test, "symbol", Some("TEST")).unwrap();
+        index.annotate(&gold, "symbol", Some("GOLD")).unwrap();
+        index.annotate(&foreign, "symbol", Some("TEST")).unwrap();
+        assert_eq!(index.find(&tokens, "symbol", "TEST"), vec![test.id]);
+        assert_eq!(index.find(&other, "symbol", "TEST"), vec![foreign.id]);
+
+        // Re-annotating moves the object between values
+        index.annotate(&test, "symbol", Some("GOLD")).unwrap();
+        assert!(index.find(&tokens, "symbol", "TEST").is_empty());
+        assert_eq!(index.find(&tokens, "symbol", "GOLD"), vec![test.id, gold.id]);
+
+        index.annotate(&gold, "symbol", None).unwrap();
+        index.forget(&test.id);
+        assert!(index.find(&tokens, "symbol", "GOLD").is_empty());
+        assert!(index.metadata(&test.id).is_empty());
+
+        assert!(index.annotate(&test, "", Some("x")).is_err());
+        assert!(index.annotate(&test, "k", Some(&"v".repeat(MAX_METADATA_VALUE_LEN + 1))).is_err());
+        for n in 0..MAX_METADATA_ENTRIES {
+            index.annotate(&test, &format!("k{}", n), Some("v")).unwrap();
+        }
+        assert!(index.annotate(&test, "one-more", Some("v")).is_err());
+        assert_eq!(index.metadata(&test.id).len(), MAX_METADATA_ENTRIES);
+    }
+}
@@ -0,0 +1,178 @@
+//! Storage observers: operation counters and fan-out to several observers
+//!
+//! `MetricsObserver` is installed in every `ConsolidatedUnitsStorage`, so
+//! operation counts are always available. Further observers are added with
+//! `ConsolidatedUnitsStorage::with_observer` and run after it through a
+//! `CompositeObserver`.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use units_core_types::id::UnitsObjectId;
+use units_core_types::objects::UnitsObject;
+use units_core_types::{ObservedProof, StorageObserver, UnitsObjectProof};
+
+/// Counts of the storage operations seen by a `MetricsObserver`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageMetrics {
+    pub gets: u64,
+    /// Gets that found the object
+    pub hits: u64,
+    pub sets: u64,
+    pub deletes: u64,
+    /// Object data bytes written by sets
+    pub bytes_written: u64,
+    pub object_proofs: u64,
+    pub state_proofs: u64,
+}
+
+/// Observer counting storage operations
+#[derive(Debug, Default)]
+pub struct MetricsObserver {
+    gets: AtomicU64,
+    hits: AtomicU64,
+    sets: AtomicU64,
+    deletes: AtomicU64,
+    bytes_written: AtomicU64,
+    object_proofs: AtomicU64,
+    state_proofs: AtomicU64,
+}
+
+impl MetricsObserver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts as of now
+    pub fn snapshot(&self) -> StorageMetrics {
+        StorageMetrics {
+            gets: self.gets.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
+            sets: self.sets.load(Ordering::Relaxed),
+            deletes: self.deletes.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            object_proofs: self.object_proofs.load(Ordering::Relaxed),
+            state_proofs: self.state_proofs.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl StorageObserver for MetricsObserver {
+    fn on_get(&self, _id: &UnitsObjectId, found: bool) {
+        self.gets.fetch_add(1, Ordering::Relaxed);
+        if found {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn on_set(&self, object: &UnitsObject, _proof: &UnitsObjectProof) {
+        self.sets.fetch_add(1, Ordering::Relaxed);
+        self.bytes_written.fetch_add(object.data().len() as u64, Ordering::Relaxed);
+    }
+
+    fn on_delete(&self, _id: &UnitsObjectId, _proof: &UnitsObjectProof) {
+        self.deletes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_proof(&self, proof: ObservedProof<'_>) {
+        let counter = match proof {
+            ObservedProof::Object(_) => &self.object_proofs,
+            ObservedProof::State(_) => &self.state_proofs,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Observer forwarding every event to each of its observers in order
+#[derive(Default)]
+pub struct CompositeObserver {
+    observers: Vec<Arc<dyn StorageObserver>>,
+}
+
+impl CompositeObserver {
+    pub fn new(observers: Vec<Arc<dyn StorageObserver>>) -> Self {
+        Self { observers }
+    }
+
+    /// Add an observer after the existing ones
+    pub fn push(&mut self, observer: Arc<dyn StorageObserver>) {
+        self.observers.push(observer);
+    }
+}
+
+impl StorageObserver for CompositeObserver {
+    fn on_get(&self, id: &UnitsObjectId, found: bool) {
+        self.observers.iter().for_each(|observer| observer.on_get(id, found));
+    }
+
+    fn on_set(&self, object: &UnitsObject, proof: &UnitsObjectProof) {
+        self.observers.iter().for_each(|observer| observer.on_set(object, proof));
+    }
+
+    fn on_delete(&self, id: &UnitsObjectId, proof: &UnitsObjectProof) {
+        self.observers.iter().for_each(|observer| observer.on_delete(id, proof));
+    }
+
+    fn on_proof(&self, proof: ObservedProof<'_>) {
+        self.observers.iter().for_each(|observer| observer.on_proof(proof));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ConsolidatedUnitsStorage;
+    use std::sync::Mutex;
+    use units_core_types::{ObjectStorage, UnitsStorage};
+
+    /// Checks that every proof chains to the previous proof of its object
+    #[derive(Default)]
+    struct ChainChecker {
+        last: Mutex<std::collections::HashMap<UnitsObjectId, UnitsObjectProof>>,
+        broken: AtomicU64,
+    }
+
+    impl StorageObserver for ChainChecker {
+        fn on_proof(&self, proof: ObservedProof<'_>) {
+            if let ObservedProof::Object(proof) = proof {
+                let mut last = self.last.lock().unwrap();
+                let expected = last.get(&proof.object_id).map(|prev| prev.hash());
+                if proof.prev_proof_hash != expected {
+                    self.broken.fetch_add(1, Ordering::Relaxed);
+                }
+                last.insert(proof.object_id, proof.clone());
+            }
+        }
+    }
+
+    #[test]
+    fn test_observers_see_every_operation() {
+        let checker = Arc::new(ChainChecker::default());
+        let storage = ConsolidatedUnitsStorage::create().with_observer(checker.clone());
+
+        let id = UnitsObjectId::new([1; 32]);
+        let object = UnitsObject::new_data(id, UnitsObjectId::new([2; 32]), vec![0; 10]);
+        storage.objects().set(&object, None).unwrap();
+        storage.objects().set(&object, None).unwrap();
+        assert!(storage.objects().get(&id).unwrap().is_some());
+        assert!(storage.objects().get(&UnitsObjectId::new([3; 32])).unwrap().is_none());
+        storage.objects().delete(&id, None).unwrap();
+        storage.commit_state_proof(1, &[]).unwrap();
+
+        assert_eq!(
+            storage.metrics(),
+            StorageMetrics {
+                gets: 2,
+                hits: 1,
+                sets: 2,
+                deletes: 1,
+                bytes_written: 20,
+                object_proofs: 3,
+                state_proofs: 1,
+            }
+        );
+        assert_eq!(checker.broken.load(Ordering::Relaxed), 0);
+        assert_eq!(checker.last.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,157 @@
+//! Copy-on-write object storage overlay
+//!
+//! An overlay forks a base storage: reads fall through to the base until an
+//! object is written or deleted in the overlay, and writes stay in the
+//! overlay. Dropping the overlay discards every staged change without the
+//! base ever seeing it.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+
+use units_core_types::error::StorageError;
+use units_core_types::id::UnitsObjectId;
+use units_core_types::objects::UnitsObject;
+use units_core_types::{ObjectStorage, UnitsObjectProof, UnitsStorage};
+use units_proofs::ProofEngine;
+
+/// Copy-on-write view over another storage's objects
+///
+/// Proofs returned by writes are generated locally and are not chained to
+/// the base's proof history.
+pub struct OverlayObjectStorage<S: UnitsStorage> {
+    base: Arc<S>,
+    /// Objects changed in the overlay; `None` marks a deletion
+    changes: RwLock<HashMap<UnitsObjectId, Option<UnitsObject>>>,
+    /// Writes made in the overlay per object, added to the base's version
+    writes: RwLock<HashMap<UnitsObjectId, u64>>,
+    proof_engine: ProofEngine,
+}
+
+impl<S: UnitsStorage> OverlayObjectStorage<S> {
+    /// Fork `base`, starting with no changes
+    pub fn new(base: Arc<S>) -> Self {
+        Self {
+            base,
+            changes: RwLock::new(HashMap::new()),
+            writes: RwLock::new(HashMap::new()),
+            proof_engine: ProofEngine::new(),
+        }
+    }
+
+    /// Storage this overlay reads through to
+    pub fn base(&self) -> &Arc<S> {
+        &self.base
+    }
+
+    /// Objects written or deleted in the overlay
+    ///
+    /// Deleted objects map to `None`.
+    pub fn changes(&self) -> HashMap<UnitsObjectId, Option<UnitsObject>> {
+        self.changes.read().unwrap().clone()
+    }
+}
+
+impl<S: UnitsStorage> ObjectStorage for OverlayObjectStorage<S> {
+    fn get(&self, id: &UnitsObjectId) -> Result<Option<UnitsObject>, StorageError> {
+        if let Some(change) = self.changes.read().unwrap().get(id) {
+            return Ok(change.clone());
+        }
+        self.base.objects().get(id)
+    }
+
+    fn set(
+        &self,
+        object: &UnitsObject,
+        transaction_hash: Option<[u8; 32]>,
+    ) -> Result<UnitsObjectProof, StorageError> {
+        let proof = self.proof_engine.generate_object_proof(object, None, transaction_hash)?;
+        self.changes.write().unwrap().insert(*object.id(), Some(object.clone()));
+        *self.writes.write().unwrap().entry(*object.id()).or_default() += 1;
+        Ok(proof)
+    }
+
+    fn delete(
+        &self,
+        id: &UnitsObjectId,
+        transaction_hash: Option<[u8; 32]>,
+    ) -> Result<UnitsObjectProof, StorageError> {
+        let object = self
+            .get(id)?
+            .ok_or_else(|| StorageError::NotFound(format!("Object not found: {:?}", id)))?;
+
+        let proof = self.proof_engine.generate_object_proof(&object, None, transaction_hash)?;
+        self.changes.write().unwrap().insert(*id, None);
+        *self.writes.write().unwrap().entry(*id).or_default() += 1;
+        Ok(proof)
+    }
+
+    fn version(&self, id: &UnitsObjectId) -> Result<u64, StorageError> {
+        let local = self.writes.read().unwrap().get(id).copied().unwrap_or_default();
+        Ok(self.base.objects().version(id)? + local)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_> {
+        // Merge the changes into the base's ID order
+        let mut merged: BTreeMap<UnitsObjectId, UnitsObject> = BTreeMap::new();
+        for result in self.base.objects().iter() {
+            let object = match result {
+                Ok(object) => object,
+                Err(error) => return Box::new(std::iter::once(Err(error))),
+            };
+            merged.insert(*object.id(), object);
+        }
+        for (id, change) in self.changes() {
+            match change {
+                Some(object) => merged.insert(id, object),
+                None => merged.remove(&id),
+            };
+        }
+        Box::new(merged.into_values().map(Ok))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ConsolidatedUnitsStorage;
+
+    fn object(seed: u8, data: u8) -> UnitsObject {
+        let id = UnitsObjectId::new([seed; 32]);
+        UnitsObject::new_data(id, id, vec![data])
+    }
+
+    #[test]
+    fn test_overlay_is_copy_on_write() {
+        let base = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+        base.objects().set(&object(1, 1), None).unwrap();
+        base.objects().set(&object(2, 1), None).unwrap();
+
+        let overlay = OverlayObjectStorage::new(base.clone());
+        assert_eq!(overlay.get(object(1, 0).id()).unwrap(), Some(object(1, 1)));
+
+        overlay.set(&object(1, 9), None).unwrap();
+        overlay.delete(object(2, 0).id(), None).unwrap();
+        overlay.set(&object(3, 9), None).unwrap();
+
+        // The overlay sees its own changes
+        assert_eq!(overlay.get(object(1, 0).id()).unwrap(), Some(object(1, 9)));
+        assert!(!overlay.exists(object(2, 0).id()).unwrap());
+        let mut ids: Vec<UnitsObjectId> = overlay.iter().map(|o| *o.unwrap().id()).collect();
+        ids.sort();
+        assert_eq!(ids, vec![*object(1, 0).id(), *object(3, 0).id()]);
+        assert_eq!(overlay.changes().len(), 3);
+        assert!(overlay.delete(object(2, 0).id(), None).is_err());
+
+        // The base is untouched
+        assert_eq!(base.objects().get(object(1, 0).id()).unwrap(), Some(object(1, 1)));
+        assert!(base.objects().exists(object(2, 0).id()).unwrap());
+        assert!(!base.objects().exists(object(3, 0).id()).unwrap());
+
+        // Versions continue from the base's without changing them
+        assert_eq!(base.objects().version(object(1, 0).id()).unwrap(), 1);
+        assert_eq!(overlay.version(object(1, 0).id()).unwrap(), 2);
+        assert_eq!(overlay.version(object(2, 0).id()).unwrap(), 2);
+        assert_eq!(overlay.version(object(3, 0).id()).unwrap(), 1);
+        assert_eq!(overlay.version(object(4, 0).id()).unwrap(), 0);
+    }
+}
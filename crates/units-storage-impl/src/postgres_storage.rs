@@ -0,0 +1,715 @@
+//! PostgreSQL-backed object, proof and receipt storage
+//!
+//! Several nodes can share one database, which a single SQLite file cannot
+//! offer. Connections come from an r2d2 pool, and every write runs in its
+//! own database transaction, so a batch is applied in full or not at all
+//! and readers on other connections never see it half written.
+//!
+//! Concurrent writes to one object are serialized by the primary key of
+//! its proof chain: the second writer's transaction fails instead of
+//! forking the chain.
+
+use std::collections::{HashMap, VecDeque};
+use std::ops::{Bound, RangeBounds};
+
+use postgres::types::ToSql;
+use postgres::{GenericClient, NoTls, Transaction};
+use r2d2::{Pool, PooledConnection};
+use r2d2_postgres::PostgresConnectionManager;
+use units_core_types::error::StorageError;
+use units_core_types::id::UnitsObjectId;
+use units_core_types::objects::UnitsObject;
+use units_core_types::transaction::TransactionReceipt;
+use units_core_types::{BatchOp, HistoricalStorage, ObjectStorage, ProofStorage, ReceiptStorage, SlotReceiptsIter};
+use units_core_types::{SlotNumber, StateProof, UnitsObjectProof};
+use units_proofs::ProofEngine;
+
+use crate::consolidated_storage::DEFAULT_HISTORY_DEPTH;
+
+/// Objects fetched per query while iterating
+const PAGE_SIZE: i64 = 512;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS units_objects (
+        id BYTEA PRIMARY KEY,
+        data BYTEA NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS units_object_versions (
+        id BYTEA NOT NULL,
+        slot BIGINT NOT NULL,
+        data BYTEA,
+        PRIMARY KEY (id, slot)
+    );
+    CREATE TABLE IF NOT EXISTS units_proof_chain (
+        id BYTEA NOT NULL,
+        seq BIGINT NOT NULL,
+        proof BYTEA NOT NULL,
+        PRIMARY KEY (id, seq)
+    );
+    CREATE TABLE IF NOT EXISTS units_object_proofs (
+        seq BIGSERIAL PRIMARY KEY,
+        id BYTEA NOT NULL,
+        slot BIGINT NOT NULL,
+        proof BYTEA NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS units_object_proofs_by_id ON units_object_proofs (id, slot);
+    CREATE TABLE IF NOT EXISTS units_state_proofs (
+        slot BIGINT PRIMARY KEY,
+        proof BYTEA NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS units_merkle_nodes (
+        hash BYTEA PRIMARY KEY,
+        node BYTEA NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS units_receipts (
+        tx_hash BYTEA PRIMARY KEY,
+        slot BIGINT NOT NULL,
+        receipt BYTEA NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS units_receipts_by_slot ON units_receipts (slot);
+    CREATE TABLE IF NOT EXISTS units_receipt_objects (
+        tx_hash BYTEA NOT NULL REFERENCES units_receipts (tx_hash) ON DELETE CASCADE,
+        object_id BYTEA NOT NULL,
+        PRIMARY KEY (tx_hash, object_id)
+    );
+    CREATE INDEX IF NOT EXISTS units_receipt_objects_by_object ON units_receipt_objects (object_id);
+";
+
+/// Storage keeping objects, their history, proofs and receipts in PostgreSQL
+///
+/// Like [`InMemoryObjectStorage`](crate::InMemoryObjectStorage), writes
+/// through [`ObjectStorage`] keep their own proof chain, while
+/// [`ProofStorage`] holds the proofs handed to it separately.
+pub struct PostgresStorage {
+    pool: Pool<PostgresConnectionManager<NoTls>>,
+    history_depth: usize,
+    proof_engine: ProofEngine,
+}
+
+impl PostgresStorage {
+    /// Connect to the database at `url` with up to `pool_size` connections,
+    /// creating the tables if they do not exist yet
+    ///
+    /// `url` is a libpq connection string, such as
+    /// `postgresql://units@localhost/units` or `host=db user=units`.
+    pub fn connect(url: &str, pool_size: u32) -> Result<Self, StorageError> {
+        Self::connect_with_history_depth(url, pool_size, DEFAULT_HISTORY_DEPTH)
+    }
+
+    /// Connect retaining at most `history_depth` versions per object
+    ///
+    /// A depth of zero keeps only the current state, disabling time travel.
+    pub fn connect_with_history_depth(url: &str, pool_size: u32, history_depth: usize) -> Result<Self, StorageError> {
+        let config = url.parse().map_err(postgres_error)?;
+        let pool = Pool::builder()
+            .max_size(pool_size)
+            .build(PostgresConnectionManager::new(config, NoTls))
+            .map_err(pool_error)?;
+
+        let storage = Self { pool, history_depth, proof_engine: ProofEngine::new() };
+        storage.connection()?.batch_execute(SCHEMA).map_err(postgres_error)?;
+        Ok(storage)
+    }
+
+    /// Number of versions retained per object
+    pub fn history_depth(&self) -> usize {
+        self.history_depth
+    }
+
+    fn connection(&self) -> Result<PooledConnection<PostgresConnectionManager<NoTls>>, StorageError> {
+        self.pool.get().map_err(pool_error)
+    }
+
+    /// Apply one write inside `tx`, chaining its proof to the object's last
+    fn write(
+        &self,
+        tx: &mut Transaction<'_>,
+        op: &BatchOp,
+        transaction_hash: Option<[u8; 32]>,
+    ) -> Result<UnitsObjectProof, StorageError> {
+        let id = op.object_id();
+        let before = tx
+            .query_opt("SELECT data FROM units_objects WHERE id = $1 FOR UPDATE", &[&id.bytes()])
+            .map_err(postgres_error)?
+            .map(|row| decode::<UnitsObject>(row.get(0)))
+            .transpose()?;
+        let proved = match (op, &before) {
+            (BatchOp::Set(object), _) => object,
+            (BatchOp::Delete(_), Some(object)) => object,
+            (BatchOp::Delete(_), None) => {
+                return Err(StorageError::NotFound(format!("Object not found: {:?}", id)));
+            }
+        };
+
+        let (seq, prev_proof) = match latest_chain_proof(tx, &id)? {
+            Some((seq, proof)) => (seq + 1, Some(proof)),
+            None => (1, None),
+        };
+        let proof = self.proof_engine.generate_object_proof(proved, prev_proof.as_ref(), transaction_hash)?;
+
+        match op {
+            BatchOp::Set(object) => tx.execute(
+                "INSERT INTO units_objects (id, data) VALUES ($1, $2)
+                 ON CONFLICT (id) DO UPDATE SET data = EXCLUDED.data",
+                &[&id.bytes(), &encode(object)?],
+            ),
+            BatchOp::Delete(_) => tx.execute("DELETE FROM units_objects WHERE id = $1", &[&id.bytes()]),
+        }
+        .map_err(postgres_error)?;
+
+        let state = match op {
+            BatchOp::Set(object) => Some(encode(object)?),
+            BatchOp::Delete(_) => None,
+        };
+        self.record_version(tx, &id, proof.slot, state)?;
+        tx.execute(
+            "INSERT INTO units_proof_chain (id, seq, proof) VALUES ($1, $2, $3)",
+            &[&id.bytes(), &seq, &encode(&proof)?],
+        )
+        .map_err(postgres_error)?;
+        Ok(proof)
+    }
+
+    /// Record the state of an object at a slot, evicting the oldest versions
+    /// beyond the configured depth
+    fn record_version(
+        &self,
+        tx: &mut Transaction<'_>,
+        id: &UnitsObjectId,
+        slot: SlotNumber,
+        state: Option<Vec<u8>>,
+    ) -> Result<(), StorageError> {
+        if self.history_depth == 0 {
+            return Ok(());
+        }
+
+        tx.execute(
+            "INSERT INTO units_object_versions (id, slot, data) VALUES ($1, $2, $3)
+             ON CONFLICT (id, slot) DO UPDATE SET data = EXCLUDED.data",
+            &[&id.bytes(), &to_sql_slot(slot), &state],
+        )
+        .map_err(postgres_error)?;
+        tx.execute(
+            "DELETE FROM units_object_versions WHERE id = $1 AND slot < (
+                 SELECT MIN(slot) FROM (
+                     SELECT slot FROM units_object_versions WHERE id = $1 ORDER BY slot DESC LIMIT $2
+                 ) AS kept
+             )",
+            &[&id.bytes(), &(self.history_depth as i64)],
+        )
+        .map_err(postgres_error)?;
+        Ok(())
+    }
+
+    /// Apply `ops` in one database transaction, returning the proof of the
+    /// last write to each object
+    fn apply(&self, ops: &[BatchOp], transaction_hash: Option<[u8; 32]>) -> Result<HashMap<UnitsObjectId, UnitsObjectProof>, StorageError> {
+        let mut connection = self.connection()?;
+        let mut tx = connection.transaction().map_err(postgres_error)?;
+        let mut proofs = HashMap::new();
+        for op in ops {
+            let proof = self.write(&mut tx, op, transaction_hash)?;
+            proofs.insert(op.object_id(), proof);
+        }
+        tx.commit().map_err(postgres_error)?;
+        Ok(proofs)
+    }
+
+    /// Decode the receipts returned by `query`
+    fn select_receipts(&self, query: &str, params: &[&(dyn ToSql + Sync)]) -> Result<Vec<TransactionReceipt>, StorageError> {
+        let rows = self.connection()?.query(query, params).map_err(postgres_error)?;
+        rows.iter().map(|row| decode(row.get(0))).collect()
+    }
+
+    /// Slots in `[start_slot, end_slot]` with a row in `table`, ascending
+    fn slots_in(&self, table: &str, start_slot: SlotNumber, end_slot: SlotNumber) -> Result<Vec<SlotNumber>, StorageError> {
+        let rows = self
+            .connection()?
+            .query(
+                &format!("SELECT DISTINCT slot FROM {table} WHERE slot BETWEEN $1 AND $2 ORDER BY slot"),
+                &[&to_sql_slot(start_slot), &to_sql_slot(end_slot)],
+            )
+            .map_err(postgres_error)?;
+        Ok(rows.iter().map(|row| from_sql_slot(row.get(0))).collect())
+    }
+}
+
+/// Last proof in the chain of `id` with its sequence number
+fn latest_chain_proof(client: &mut impl GenericClient, id: &UnitsObjectId) -> Result<Option<(i64, UnitsObjectProof)>, StorageError> {
+    client
+        .query_opt(
+            "SELECT seq, proof FROM units_proof_chain WHERE id = $1 ORDER BY seq DESC LIMIT 1",
+            &[&id.bytes()],
+        )
+        .map_err(postgres_error)?
+        .map(|row| Ok((row.get(0), decode(row.get(1))?)))
+        .transpose()
+}
+
+impl ObjectStorage for PostgresStorage {
+    fn get(&self, id: &UnitsObjectId) -> Result<Option<UnitsObject>, StorageError> {
+        self.connection()?
+            .query_opt("SELECT data FROM units_objects WHERE id = $1", &[&id.bytes()])
+            .map_err(postgres_error)?
+            .map(|row| decode(row.get(0)))
+            .transpose()
+    }
+
+    fn set(&self, object: &UnitsObject, transaction_hash: Option<[u8; 32]>) -> Result<UnitsObjectProof, StorageError> {
+        let op = BatchOp::Set(object.clone());
+        Ok(self.apply(std::slice::from_ref(&op), transaction_hash)?.remove(object.id()).expect("write was proved"))
+    }
+
+    fn delete(&self, id: &UnitsObjectId, transaction_hash: Option<[u8; 32]>) -> Result<UnitsObjectProof, StorageError> {
+        Ok(self.apply(&[BatchOp::Delete(*id)], transaction_hash)?.remove(id).expect("write was proved"))
+    }
+
+    /// Applied in one database transaction, so readers see the batch
+    /// either not at all or in full
+    fn apply_batch(
+        &self,
+        ops: &[BatchOp],
+        transaction_hash: [u8; 32],
+    ) -> Result<HashMap<UnitsObjectId, UnitsObjectProof>, StorageError> {
+        self.apply(ops, Some(transaction_hash))
+    }
+
+    fn version(&self, id: &UnitsObjectId) -> Result<u64, StorageError> {
+        let row = self
+            .connection()?
+            .query_one("SELECT COALESCE(MAX(seq), 0) FROM units_proof_chain WHERE id = $1", &[&id.bytes()])
+            .map_err(postgres_error)?;
+        Ok(row.get::<_, i64>(0) as u64)
+    }
+
+    fn exists(&self, id: &UnitsObjectId) -> Result<bool, StorageError> {
+        let row = self
+            .connection()?
+            .query_one("SELECT EXISTS (SELECT 1 FROM units_objects WHERE id = $1)", &[&id.bytes()])
+            .map_err(postgres_error)?;
+        Ok(row.get(0))
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_> {
+        self.iter_range(..)
+    }
+
+    /// Objects are fetched a page at a time, seeking past the last ID seen
+    fn iter_range<R>(&self, range: R) -> Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_>
+    where
+        R: RangeBounds<UnitsObjectId>,
+    {
+        Box::new(ObjectPages {
+            storage: self,
+            start: range.start_bound().cloned(),
+            end: range.end_bound().cloned(),
+            page: VecDeque::new(),
+            done: false,
+        })
+    }
+}
+
+/// Objects in an ID range, fetched [`PAGE_SIZE`] at a time
+struct ObjectPages<'a> {
+    storage: &'a PostgresStorage,
+    start: Bound<UnitsObjectId>,
+    end: Bound<UnitsObjectId>,
+    page: VecDeque<UnitsObject>,
+    done: bool,
+}
+
+impl ObjectPages<'_> {
+    fn fetch(&mut self) -> Result<(), StorageError> {
+        let mut query = String::from("SELECT data FROM units_objects WHERE TRUE");
+        let mut bounds = Vec::new();
+        for (bound, inclusive, exclusive) in [(&self.start, ">=", ">"), (&self.end, "<=", "<")] {
+            let (op, id) = match bound {
+                Bound::Included(id) => (inclusive, id),
+                Bound::Excluded(id) => (exclusive, id),
+                Bound::Unbounded => continue,
+            };
+            bounds.push(id.bytes().to_vec());
+            query.push_str(&format!(" AND id {op} ${}", bounds.len()));
+        }
+        query.push_str(&format!(" ORDER BY id LIMIT {PAGE_SIZE}"));
+
+        let params: Vec<&(dyn ToSql + Sync)> = bounds.iter().map(|bound| bound as &(dyn ToSql + Sync)).collect();
+        let rows = self.storage.connection()?.query(&query, &params).map_err(postgres_error)?;
+        self.done = (rows.len() as i64) < PAGE_SIZE;
+        for row in rows {
+            self.page.push_back(decode(row.get(0))?);
+        }
+        if let Some(last) = self.page.back() {
+            self.start = Bound::Excluded(last.id);
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for ObjectPages<'_> {
+    type Item = Result<UnitsObject, StorageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.page.is_empty() && !self.done {
+            if let Err(error) = self.fetch() {
+                self.done = true;
+                return Some(Err(error));
+            }
+        }
+        self.page.pop_front().map(Ok)
+    }
+}
+
+impl HistoricalStorage for PostgresStorage {
+    fn get_at_slot(&self, id: &UnitsObjectId, slot: SlotNumber) -> Result<Option<UnitsObject>, StorageError> {
+        // The state at a slot is the latest version written at or before it
+        let row = self
+            .connection()?
+            .query_opt(
+                "SELECT data FROM units_object_versions WHERE id = $1 AND slot <= $2 ORDER BY slot DESC LIMIT 1",
+                &[&id.bytes(), &to_sql_slot(slot)],
+            )
+            .map_err(postgres_error)?;
+        row.and_then(|row| row.get::<_, Option<Vec<u8>>>(0)).map(|data| decode(&data)).transpose()
+    }
+
+    fn get_history(
+        &self,
+        id: &UnitsObjectId,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+    ) -> Result<Vec<(SlotNumber, UnitsObject)>, StorageError> {
+        let rows = self
+            .connection()?
+            .query(
+                "SELECT slot, data FROM units_object_versions
+                 WHERE id = $1 AND slot BETWEEN $2 AND $3 AND data IS NOT NULL ORDER BY slot",
+                &[&id.bytes(), &to_sql_slot(start_slot), &to_sql_slot(end_slot)],
+            )
+            .map_err(postgres_error)?;
+        rows.iter().map(|row| Ok((from_sql_slot(row.get(0)), decode(row.get(1))?))).collect()
+    }
+
+    fn compact_history(&self, before_slot: SlotNumber) -> Result<usize, StorageError> {
+        // Keep the newest version before the cutoff so lookups at or after
+        // `before_slot` still resolve to the state in effect at that time,
+        // unless it is a deletion with nothing after it
+        let removed = self
+            .connection()?
+            .execute(
+                "WITH base AS (
+                     SELECT id, MAX(slot) AS slot FROM units_object_versions WHERE slot < $1 GROUP BY id
+                 )
+                 DELETE FROM units_object_versions AS version USING base
+                 WHERE version.id = base.id AND (
+                     version.slot < base.slot
+                     OR (version.slot = base.slot AND version.data IS NULL AND NOT EXISTS (
+                         SELECT 1 FROM units_object_versions AS newer WHERE newer.id = version.id AND newer.slot >= $1
+                     ))
+                 )",
+                &[&to_sql_slot(before_slot)],
+            )
+            .map_err(postgres_error)?;
+        Ok(removed as usize)
+    }
+}
+
+impl ProofStorage for PostgresStorage {
+    fn store_object_proof(&self, proof: &UnitsObjectProof) -> Result<(), StorageError> {
+        self.connection()?
+            .execute(
+                "INSERT INTO units_object_proofs (id, slot, proof) VALUES ($1, $2, $3)",
+                &[&proof.object_id.bytes(), &to_sql_slot(proof.slot), &encode(proof)?],
+            )
+            .map_err(postgres_error)?;
+        Ok(())
+    }
+
+    fn get_latest_proof(&self, id: &UnitsObjectId) -> Result<Option<UnitsObjectProof>, StorageError> {
+        self.connection()?
+            .query_opt(
+                "SELECT proof FROM units_object_proofs WHERE id = $1 ORDER BY slot DESC, seq DESC LIMIT 1",
+                &[&id.bytes()],
+            )
+            .map_err(postgres_error)?
+            .map(|row| decode(row.get(0)))
+            .transpose()
+    }
+
+    fn get_proof_history(
+        &self,
+        id: &UnitsObjectId,
+        start_slot: Option<SlotNumber>,
+        end_slot: Option<SlotNumber>,
+    ) -> Result<Vec<(SlotNumber, UnitsObjectProof)>, StorageError> {
+        let rows = self
+            .connection()?
+            .query(
+                "SELECT slot, proof FROM units_object_proofs
+                 WHERE id = $1 AND slot >= $2 AND slot <= $3 ORDER BY seq",
+                &[
+                    &id.bytes(),
+                    &start_slot.map_or(i64::MIN, to_sql_slot),
+                    &end_slot.map_or(i64::MAX, to_sql_slot),
+                ],
+            )
+            .map_err(postgres_error)?;
+        rows.iter().map(|row| Ok((from_sql_slot(row.get(0)), decode(row.get(1))?))).collect()
+    }
+
+    fn store_state_proof(&self, proof: &StateProof) -> Result<(), StorageError> {
+        self.connection()?
+            .execute(
+                "INSERT INTO units_state_proofs (slot, proof) VALUES ($1, $2)
+                 ON CONFLICT (slot) DO UPDATE SET proof = EXCLUDED.proof",
+                &[&to_sql_slot(proof.slot), &encode(proof)?],
+            )
+            .map_err(postgres_error)?;
+        Ok(())
+    }
+
+    fn get_state_proof(&self, slot: SlotNumber) -> Result<Option<StateProof>, StorageError> {
+        self.connection()?
+            .query_opt("SELECT proof FROM units_state_proofs WHERE slot = $1", &[&to_sql_slot(slot)])
+            .map_err(postgres_error)?
+            .map(|row| decode(row.get(0)))
+            .transpose()
+    }
+
+    fn get_state_proof_history(&self, start_slot: SlotNumber, end_slot: SlotNumber) -> Result<Vec<StateProof>, StorageError> {
+        let rows = self
+            .connection()?
+            .query(
+                "SELECT proof FROM units_state_proofs WHERE slot BETWEEN $1 AND $2 ORDER BY slot",
+                &[&to_sql_slot(start_slot), &to_sql_slot(end_slot)],
+            )
+            .map_err(postgres_error)?;
+        rows.iter().map(|row| decode(row.get(0))).collect()
+    }
+
+    fn iter_state_proofs(
+        &self,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+    ) -> Box<dyn Iterator<Item = Result<StateProof, StorageError>> + '_> {
+        // Only the slot numbers are collected up front; proofs are loaded per slot
+        match self.slots_in("units_state_proofs", start_slot, end_slot) {
+            Ok(slots) => Box::new(slots.into_iter().filter_map(move |slot| self.get_state_proof(slot).transpose())),
+            Err(error) => Box::new(std::iter::once(Err(error))),
+        }
+    }
+
+    fn store_merkle_node(&self, hash: &[u8; 32], node: &[u8]) -> Result<(), StorageError> {
+        self.connection()?
+            .execute(
+                "INSERT INTO units_merkle_nodes (hash, node) VALUES ($1, $2) ON CONFLICT (hash) DO NOTHING",
+                &[&hash.as_slice(), &node],
+            )
+            .map_err(postgres_error)?;
+        Ok(())
+    }
+
+    fn get_merkle_node(&self, hash: &[u8; 32]) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self
+            .connection()?
+            .query_opt("SELECT node FROM units_merkle_nodes WHERE hash = $1", &[&hash.as_slice()])
+            .map_err(postgres_error)?
+            .map(|row| row.get(0)))
+    }
+}
+
+impl ReceiptStorage for PostgresStorage {
+    fn store_receipt(&self, receipt: &TransactionReceipt) -> Result<(), StorageError> {
+        let mut connection = self.connection()?;
+        let mut tx = connection.transaction().map_err(postgres_error)?;
+        let tx_hash = receipt.transaction_hash.as_slice();
+        tx.execute(
+            "INSERT INTO units_receipts (tx_hash, slot, receipt) VALUES ($1, $2, $3)
+             ON CONFLICT (tx_hash) DO UPDATE SET slot = EXCLUDED.slot, receipt = EXCLUDED.receipt",
+            &[&tx_hash, &to_sql_slot(receipt.slot), &encode(receipt)?],
+        )
+        .map_err(postgres_error)?;
+
+        // The receipt affects the objects it proves or changes
+        tx.execute("DELETE FROM units_receipt_objects WHERE tx_hash = $1", &[&tx_hash])
+            .map_err(postgres_error)?;
+        let affected = receipt.object_proofs.keys().chain(receipt.effects.iter().map(|effect| &effect.object_id));
+        for object_id in affected {
+            tx.execute(
+                "INSERT INTO units_receipt_objects (tx_hash, object_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+                &[&tx_hash, &object_id.bytes()],
+            )
+            .map_err(postgres_error)?;
+        }
+        tx.commit().map_err(postgres_error)
+    }
+
+    fn get_receipt(&self, tx_hash: &[u8; 32]) -> Result<Option<TransactionReceipt>, StorageError> {
+        self.connection()?
+            .query_opt("SELECT receipt FROM units_receipts WHERE tx_hash = $1", &[&tx_hash.as_slice()])
+            .map_err(postgres_error)?
+            .map(|row| decode(row.get(0)))
+            .transpose()
+    }
+
+    fn get_receipts_for_slot(&self, slot: SlotNumber) -> Result<Vec<TransactionReceipt>, StorageError> {
+        self.select_receipts(
+            "SELECT receipt FROM units_receipts WHERE slot = $1 ORDER BY tx_hash",
+            &[&to_sql_slot(slot)],
+        )
+    }
+
+    fn get_receipts_range(&self, start_slot: SlotNumber, end_slot: SlotNumber) -> Result<Vec<TransactionReceipt>, StorageError> {
+        self.select_receipts(
+            "SELECT receipt FROM units_receipts WHERE slot BETWEEN $1 AND $2 ORDER BY slot, tx_hash",
+            &[&to_sql_slot(start_slot), &to_sql_slot(end_slot)],
+        )
+    }
+
+    fn iter_receipts_by_slot(&self, start_slot: SlotNumber, end_slot: SlotNumber) -> SlotReceiptsIter<'_> {
+        // Only the slot numbers are collected up front; receipts are decoded per slot
+        let slots = match self.slots_in("units_receipts", start_slot, end_slot) {
+            Ok(slots) => slots,
+            Err(error) => return Box::new(std::iter::once(Err(error))),
+        };
+        Box::new(slots.into_iter().filter_map(move |slot| match self.get_receipts_for_slot(slot) {
+            Ok(receipts) if receipts.is_empty() => None,
+            Ok(receipts) => Some(Ok((slot, receipts))),
+            Err(error) => Some(Err(error)),
+        }))
+    }
+
+    fn get_receipts_for_object(
+        &self,
+        object_id: &UnitsObjectId,
+        start_slot: Option<SlotNumber>,
+        end_slot: Option<SlotNumber>,
+    ) -> Result<Vec<TransactionReceipt>, StorageError> {
+        self.select_receipts(
+            "SELECT receipt.receipt FROM units_receipts AS receipt
+             JOIN units_receipt_objects AS affected ON affected.tx_hash = receipt.tx_hash
+             WHERE affected.object_id = $1 AND receipt.slot >= $2 AND receipt.slot <= $3
+             ORDER BY receipt.slot, receipt.tx_hash",
+            &[
+                &object_id.bytes(),
+                &start_slot.map_or(i64::MIN, to_sql_slot),
+                &end_slot.map_or(i64::MAX, to_sql_slot),
+            ],
+        )
+    }
+
+    fn cleanup_receipts_before(&self, slot: SlotNumber) -> Result<usize, StorageError> {
+        let removed = self
+            .connection()?
+            .execute("DELETE FROM units_receipts WHERE slot < $1", &[&to_sql_slot(slot)])
+            .map_err(postgres_error)?;
+        Ok(removed as usize)
+    }
+}
+
+fn encode<T: serde::Serialize>(value: &T) -> Result<Vec<u8>, StorageError> {
+    Ok(bincode::serialize(value)?)
+}
+
+fn decode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<T, StorageError> {
+    Ok(bincode::deserialize(bytes)?)
+}
+
+/// Slots are stored as BIGINT, clamped to its range
+fn to_sql_slot(slot: SlotNumber) -> i64 {
+    i64::try_from(slot).unwrap_or(i64::MAX)
+}
+
+fn from_sql_slot(slot: i64) -> SlotNumber {
+    slot.max(0) as SlotNumber
+}
+
+fn postgres_error(err: postgres::Error) -> StorageError {
+    StorageError::Database(err.to_string())
+}
+
+fn pool_error(err: r2d2::Error) -> StorageError {
+    StorageError::Database(err.to_string())
+}
+
+// These tests need a live database, so they are ignored by default. Run them
+// with `UNITS_TEST_POSTGRES_URL=postgres://... cargo test -p units-storage-impl
+// --features postgres -- --ignored`.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use units_core_types::transaction::TransactionEffect;
+
+    /// Storage on the database named by `UNITS_TEST_POSTGRES_URL`, emptied first
+    fn test_storage() -> PostgresStorage {
+        let url = std::env::var("UNITS_TEST_POSTGRES_URL").expect("UNITS_TEST_POSTGRES_URL names the test database");
+        let storage = PostgresStorage::connect(&url, 4).expect("connect to test database");
+        storage
+            .connection()
+            .unwrap()
+            .batch_execute(
+                "TRUNCATE units_objects, units_object_versions, units_proof_chain, units_object_proofs,
+                          units_state_proofs, units_merkle_nodes, units_receipts, units_receipt_objects",
+            )
+            .unwrap();
+        storage
+    }
+
+    fn object(byte: u8, data: u8) -> UnitsObject {
+        UnitsObject::new_data(UnitsObjectId::new([byte; 32]), UnitsObjectId::new([0xC0; 32]), vec![data])
+    }
+
+    #[test]
+    #[ignore = "needs a PostgreSQL database at UNITS_TEST_POSTGRES_URL"]
+    fn test_postgres_storage_round_trip() {
+        let storage = test_storage();
+
+        // Writes chain their proofs and bump the version
+        let first = storage.set(&object(1, 1), None).unwrap();
+        let second = storage.set(&object(1, 2), None).unwrap();
+        assert_eq!(second.prev_proof_hash, Some(first.hash()));
+        assert_eq!(storage.get(&object(1, 0).id).unwrap(), Some(object(1, 2)));
+        assert_eq!(storage.version(&object(1, 0).id).unwrap(), 2);
+        assert_eq!(storage.get_at_slot(&object(1, 0).id, second.slot).unwrap(), Some(object(1, 2)));
+
+        // A failing batch leaves storage as it was
+        let batch = [BatchOp::Set(object(2, 1)), BatchOp::Delete(object(3, 0).id)];
+        assert!(matches!(storage.apply_batch(&batch, [7; 32]), Err(StorageError::NotFound(_))));
+        assert!(!storage.exists(&object(2, 0).id).unwrap());
+        let batch = [BatchOp::Set(object(2, 1)), BatchOp::Set(object(3, 1)), BatchOp::Delete(object(1, 0).id)];
+        let proofs = storage.apply_batch(&batch, [7; 32]).unwrap();
+        assert_eq!(proofs.len(), 3);
+        assert!(storage.get(&object(1, 0).id).unwrap().is_none());
+        assert_eq!(storage.version(&object(1, 0).id).unwrap(), 3);
+
+        let ids: Vec<_> = storage.iter().map(|object| object.unwrap().id).collect();
+        assert_eq!(ids, vec![object(2, 0).id, object(3, 0).id]);
+        assert_eq!(storage.iter_range(object(3, 0).id..).count(), 1);
+
+        // Compaction drops the trailing deletion and keeps live versions
+        assert_eq!(storage.compact_history(SlotNumber::MAX).unwrap(), 1);
+        assert_eq!(storage.get_history(&object(2, 0).id, 0, SlotNumber::MAX).unwrap().len(), 1);
+
+        // Proofs and receipts round-trip
+        storage.store_object_proof(&proofs[&object(2, 0).id]).unwrap();
+        let latest = storage.get_latest_proof(&object(2, 0).id).unwrap().unwrap();
+        assert_eq!(latest.hash(), proofs[&object(2, 0).id].hash());
+        let mut receipt = TransactionReceipt::new([7; 32], 5, true, 1_700_000_000);
+        receipt.add_effect(TransactionEffect::new_creation([7; 32], object(2, 1)));
+        storage.store_receipt(&receipt).unwrap();
+        let stored = storage.get_receipt(&[7; 32]).unwrap().unwrap();
+        assert_eq!(bincode::serialize(&stored).unwrap(), bincode::serialize(&receipt).unwrap());
+        assert_eq!(storage.get_receipts_for_object(&object(2, 0).id, None, Some(5)).unwrap().len(), 1);
+        assert!(storage.get_receipts_for_object(&object(3, 0).id, None, None).unwrap().is_empty());
+        assert_eq!(storage.cleanup_receipts_before(6).unwrap(), 1);
+
+        // Sparse Merkle tree nodes are kept, reachable from the root
+        let root = {
+            let mut tree = units_proofs::SparseMerkleTree::new(&storage);
+            tree.insert(&object(2, 0).id, [9; 32]).unwrap();
+            tree.root()
+        };
+        let tree = units_proofs::SparseMerkleTree::open(&storage, root);
+        assert_eq!(tree.get(&object(2, 0).id).unwrap(), Some([9; 32]));
+    }
+}
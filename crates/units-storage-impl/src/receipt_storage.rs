@@ -5,21 +5,104 @@
 use std::collections::HashMap;
 use units_core_types::error::StorageError;
 use units_core_types::id::UnitsObjectId;
-use units_core_types::transaction::TransactionReceipt;
+use units_core_types::transaction::{TransactionHash, TransactionReceipt};
 use units_core_types::SlotNumber;
-use units_core_types::ReceiptStorage;
+use units_core_types::{ReceiptPage, ReceiptStorage, SlotReceiptsIter};
+
+use crate::codec::{Codec, CodecConfig, CodecStats};
+
+/// Serialized receipt, compressed when the codec config calls for it
+struct StoredReceipt {
+    slot: SlotNumber,
+    codec: Codec,
+    raw_len: usize,
+    bytes: Vec<u8>,
+}
+
+impl StoredReceipt {
+    fn decode(&self) -> Result<TransactionReceipt, StorageError> {
+        Ok(bincode::deserialize(&self.codec.decompress(&self.bytes)?)?)
+    }
+}
 
 /// Simple in-memory receipt storage for testing
 pub struct InMemoryReceiptStorage {
-    receipts: std::sync::RwLock<HashMap<[u8; 32], TransactionReceipt>>,
+    receipts: std::sync::RwLock<HashMap<[u8; 32], StoredReceipt>>,
+    codec: CodecConfig,
 }
 
 impl InMemoryReceiptStorage {
     pub fn new() -> Self {
+        Self::with_codec(CodecConfig::default())
+    }
+
+    /// Create storage compressing receipts according to `codec`
+    pub fn with_codec(codec: CodecConfig) -> Self {
         Self {
             receipts: std::sync::RwLock::new(HashMap::new()),
+            codec,
         }
     }
+
+    /// Raw and stored size of the receipts held
+    pub fn stats(&self) -> CodecStats {
+        let receipts = self.receipts.read().unwrap();
+        receipts.values().fold(CodecStats::default(), |stats, stored| CodecStats {
+            records: stats.records + 1,
+            raw_bytes: stats.raw_bytes + stored.raw_len,
+            stored_bytes: stats.stored_bytes + stored.bytes.len(),
+        })
+    }
+
+    /// Decode the receipts matching `filter`
+    fn collect<F>(&self, filter: F) -> Result<Vec<TransactionReceipt>, StorageError>
+    where
+        F: Fn(&StoredReceipt) -> bool,
+    {
+        let receipts = self.receipts.read().unwrap();
+        receipts
+            .values()
+            .filter(|stored| filter(stored))
+            .map(StoredReceipt::decode)
+            .collect()
+    }
+
+    /// Page of the receipts in `[start_slot, end_slot]` matching `filter`
+    ///
+    /// Receipts are ordered by their slot and hash without decoding them,
+    /// and only decoded until the page is full.
+    fn page<F>(
+        &self,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+        after: Option<TransactionHash>,
+        limit: usize,
+        filter: F,
+    ) -> Result<ReceiptPage, StorageError>
+    where
+        F: Fn(&TransactionReceipt) -> bool,
+    {
+        let after = self.receipt_cursor(after)?;
+        let receipts = self.receipts.read().unwrap();
+        let mut keys: Vec<(SlotNumber, TransactionHash)> = receipts
+            .iter()
+            .map(|(hash, stored)| (stored.slot, *hash))
+            .filter(|key| (start_slot..=end_slot).contains(&key.0) && after.map_or(true, |after| *key > after))
+            .collect();
+        keys.sort_unstable();
+
+        let matching = keys
+            .into_iter()
+            .map(|(_, hash)| receipts[&hash].decode())
+            .filter(|receipt| receipt.as_ref().map_or(true, &filter));
+        ReceiptPage::after(matching, None, limit)
+    }
+}
+
+/// Whether `receipt` proves or changes `object_id`
+fn affects(receipt: &TransactionReceipt, object_id: &UnitsObjectId) -> bool {
+    receipt.object_proofs.contains_key(object_id)
+        || receipt.effects.iter().any(|effect| &effect.object_id == object_id)
 }
 
 impl Default for InMemoryReceiptStorage {
@@ -30,23 +113,27 @@ impl Default for InMemoryReceiptStorage {
 
 impl ReceiptStorage for InMemoryReceiptStorage {
     fn store_receipt(&self, receipt: &TransactionReceipt) -> Result<(), StorageError> {
+        let raw = bincode::serialize(receipt)?;
+        let raw_len = raw.len();
+        let (codec, bytes) = self.codec.encode(raw)?;
+
         let mut receipts = self.receipts.write().unwrap();
-        receipts.insert(receipt.transaction_hash, receipt.clone());
+        receipts.insert(receipt.transaction_hash, StoredReceipt {
+            slot: receipt.slot,
+            codec,
+            raw_len,
+            bytes,
+        });
         Ok(())
     }
     
     fn get_receipt(&self, tx_hash: &[u8; 32]) -> Result<Option<TransactionReceipt>, StorageError> {
         let receipts = self.receipts.read().unwrap();
-        Ok(receipts.get(tx_hash).cloned())
+        receipts.get(tx_hash).map(StoredReceipt::decode).transpose()
     }
     
     fn get_receipts_for_slot(&self, slot: SlotNumber) -> Result<Vec<TransactionReceipt>, StorageError> {
-        let receipts = self.receipts.read().unwrap();
-        Ok(receipts
-            .values()
-            .filter(|r| r.slot == slot)
-            .cloned()
-            .collect())
+        self.collect(|r| r.slot == slot)
     }
     
     fn get_receipts_range(
@@ -54,12 +141,44 @@ impl ReceiptStorage for InMemoryReceiptStorage {
         start_slot: SlotNumber,
         end_slot: SlotNumber,
     ) -> Result<Vec<TransactionReceipt>, StorageError> {
-        let receipts = self.receipts.read().unwrap();
-        Ok(receipts
+        self.collect(|r| r.slot >= start_slot && r.slot <= end_slot)
+    }
+
+    fn get_receipts_range_page(
+        &self,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+        after: Option<TransactionHash>,
+        limit: usize,
+    ) -> Result<ReceiptPage, StorageError> {
+        self.page(start_slot, end_slot, after, limit, |_| true)
+    }
+
+    fn iter_receipts_by_slot(
+        &self,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+    ) -> SlotReceiptsIter<'_> {
+        // Only the slot numbers are collected up front; receipts are decoded per slot
+        let mut slots: Vec<SlotNumber> = self
+            .receipts
+            .read()
+            .unwrap()
             .values()
-            .filter(|r| r.slot >= start_slot && r.slot <= end_slot)
-            .cloned()
-            .collect())
+            .map(|stored| stored.slot)
+            .filter(|slot| (start_slot..=end_slot).contains(slot))
+            .collect();
+        slots.sort_unstable();
+        slots.dedup();
+
+        Box::new(slots.into_iter().filter_map(move |slot| match self.get_receipts_for_slot(slot) {
+            Ok(receipts) if receipts.is_empty() => None,
+            Ok(mut receipts) => {
+                receipts.sort_by_key(|receipt| receipt.transaction_hash);
+                Some(Ok((slot, receipts)))
+            }
+            Err(error) => Some(Err(error)),
+        }))
     }
     
     fn get_receipts_for_object(
@@ -68,24 +187,24 @@ impl ReceiptStorage for InMemoryReceiptStorage {
         start_slot: Option<SlotNumber>,
         end_slot: Option<SlotNumber>,
     ) -> Result<Vec<TransactionReceipt>, StorageError> {
-        let receipts = self.receipts.read().unwrap();
-        Ok(receipts
-            .values()
-            .filter(|r| {
-                // Check slot range
-                if let Some(start) = start_slot {
-                    if r.slot < start { return false; }
-                }
-                if let Some(end) = end_slot {
-                    if r.slot > end { return false; }
-                }
-                
-                // Check if this receipt affects the object
-                // Note: This is a simplified check - would need proper transaction effect parsing
-                r.object_proofs.contains_key(object_id)
-            })
-            .cloned()
-            .collect())
+        let receipts = self.collect(|r| {
+            // Check slot range
+            start_slot.map_or(true, |start| r.slot >= start) && end_slot.map_or(true, |end| r.slot <= end)
+        })?;
+
+        Ok(receipts.into_iter().filter(|r| affects(r, object_id)).collect())
+    }
+
+    fn get_receipts_for_object_page(
+        &self,
+        object_id: &UnitsObjectId,
+        start_slot: Option<SlotNumber>,
+        end_slot: Option<SlotNumber>,
+        after: Option<TransactionHash>,
+        limit: usize,
+    ) -> Result<ReceiptPage, StorageError> {
+        let (start_slot, end_slot) = (start_slot.unwrap_or(SlotNumber::MIN), end_slot.unwrap_or(SlotNumber::MAX));
+        self.page(start_slot, end_slot, after, limit, |r| affects(r, object_id))
     }
     
     fn cleanup_receipts_before(&self, slot: SlotNumber) -> Result<usize, StorageError> {
@@ -94,4 +213,117 @@ impl ReceiptStorage for InMemoryReceiptStorage {
         receipts.retain(|_, receipt| receipt.slot >= slot);
         Ok(initial_len - receipts.len())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use units_core_types::objects::UnitsObject;
+    use units_core_types::transaction::{TransactionEffect, TransactionEvent};
+
+    fn receipt_with_effects(hash: u8, slot: SlotNumber, effects: usize) -> TransactionReceipt {
+        let mut receipt = TransactionReceipt::new([hash; 32], slot, true, 1_700_000_000);
+        for i in 0..effects {
+            let object = UnitsObject::new_data(UnitsObjectId::new([i as u8; 32]), UnitsObjectId::new([9; 32]), vec![0xab; 64]);
+            receipt.add_effect(TransactionEffect::new_creation([hash; 32], object));
+        }
+        receipt
+    }
+
+    #[test]
+    #[cfg(feature = "zstd")]
+    fn test_compressed_receipts_round_trip() {
+        let storage = InMemoryReceiptStorage::with_codec(CodecConfig::new(Codec::Zstd));
+        let large = receipt_with_effects(1, 5, 32);
+        let small = receipt_with_effects(2, 5, 0);
+        storage.store_receipt(&large).unwrap();
+        storage.store_receipt(&small).unwrap();
+
+        let stored = storage.get_receipt(&[1; 32]).unwrap().unwrap();
+        assert_eq!(stored.effects.len(), 32);
+        assert_eq!(bincode::serialize(&stored).unwrap(), bincode::serialize(&large).unwrap());
+        assert_eq!(storage.get_receipts_for_slot(5).unwrap().len(), 2);
+
+        let stats = storage.stats();
+        assert_eq!(stats.records, 2);
+        assert!(stats.stored_bytes < stats.raw_bytes);
+    }
+
+    #[test]
+    fn test_iter_receipts_by_slot_groups_in_slot_order() {
+        let storage = InMemoryReceiptStorage::new();
+        for (hash, slot) in [(3, 7), (1, 2), (2, 7), (4, 9), (5, 12)] {
+            storage.store_receipt(&receipt_with_effects(hash, slot, 0)).unwrap();
+        }
+
+        let slots: Vec<(SlotNumber, Vec<u8>)> = storage
+            .iter_receipts_by_slot(2, 9)
+            .map(|item| {
+                let (slot, receipts) = item.unwrap();
+                (slot, receipts.iter().map(|r| r.transaction_hash[0]).collect())
+            })
+            .collect();
+        assert_eq!(slots, vec![(2, vec![1]), (7, vec![2, 3]), (9, vec![4])]);
+        assert_eq!(storage.iter_receipts_by_slot(13, 100).count(), 0);
+    }
+
+    #[test]
+    fn test_receipt_pages_follow_cursors() {
+        let storage = InMemoryReceiptStorage::new();
+        for (hash, slot) in [(3, 7), (1, 2), (2, 7), (4, 9), (5, 12)] {
+            storage.store_receipt(&receipt_with_effects(hash, slot, hash as usize % 2)).unwrap();
+        }
+
+        // Walking the range two at a time visits every receipt once, in order
+        let mut hashes = Vec::new();
+        let mut after = None;
+        loop {
+            let page = storage.get_receipts_range_page(2, 9, after, 2).unwrap();
+            assert!(page.receipts.len() <= 2);
+            hashes.extend(page.receipts.iter().map(|r| r.transaction_hash[0]));
+            after = page.next;
+            if after.is_none() {
+                break;
+            }
+        }
+        assert_eq!(hashes, vec![1, 2, 3, 4]);
+
+        // Only receipts 1, 3 and 5 have an effect on object zero
+        let object_id = UnitsObjectId::new([0; 32]);
+        let first = storage.get_receipts_for_object_page(&object_id, None, None, None, 2).unwrap();
+        assert_eq!(first.receipts.iter().map(|r| r.transaction_hash[0]).collect::<Vec<_>>(), vec![1, 3]);
+        let rest = storage.get_receipts_for_object_page(&object_id, None, None, first.next, 2).unwrap();
+        assert_eq!(rest.receipts.iter().map(|r| r.transaction_hash[0]).collect::<Vec<_>>(), vec![5]);
+        assert_eq!(rest.next, None);
+
+        // A cursor that was cleaned up is an error rather than a restart
+        storage.cleanup_receipts_before(8).unwrap();
+        assert!(matches!(
+            storage.get_receipts_range_page(0, 20, Some([3; 32]), 2),
+            Err(StorageError::NotFound(_))
+        ));
+    }
+    #[test]
+    fn test_events_for_object_in_slot_order() {
+        let storage = InMemoryReceiptStorage::new();
+        let (token, alice, bob) = (UnitsObjectId::new([7; 32]), UnitsObjectId::new([1; 32]), UnitsObjectId::new([2; 32]));
+        for (hash, slot, object_id) in [(3, 9, alice), (1, 4, alice), (2, 4, bob), (4, 12, alice)] {
+            let mut receipt = receipt_with_effects(hash, slot, 0);
+            receipt.events.push(TransactionEvent {
+                transaction_hash: [hash; 32],
+                controller_id: token,
+                object_id,
+                name: "Transfer".to_string(),
+                data: vec![hash],
+            });
+            storage.store_receipt(&receipt).unwrap();
+        }
+
+        let events = storage.get_events_for_object(&alice, None, None).unwrap();
+        assert_eq!(events.iter().map(|e| e.data[0]).collect::<Vec<_>>(), vec![1, 3, 4]);
+        assert!(events.iter().all(|e| e.controller_id == token && e.name == "Transfer"));
+        let events = storage.get_events_for_object(&alice, Some(5), Some(10)).unwrap();
+        assert_eq!(events.iter().map(|e| e.data[0]).collect::<Vec<_>>(), vec![3]);
+        assert!(storage.get_events_for_object(&token, None, None).unwrap().is_empty());
+    }
+}
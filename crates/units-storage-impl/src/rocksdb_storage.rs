@@ -0,0 +1,737 @@
+//! RocksDB-backed object, proof and receipt storage
+//!
+//! Each kind of record lives in its own column family, keyed so that the
+//! queries the storage traits need are prefix or range scans. Writes are
+//! collected into a single `WriteBatch`, so a batch of any size costs one
+//! write to the log-structured store and is applied in full or not at all.
+//!
+//! Writers are serialized by a lock held while a batch is assembled, since
+//! proofs chain to the state the previous write left behind. Readers never
+//! take it.
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::{Bound, RangeBounds};
+use std::path::Path;
+use std::sync::Mutex;
+
+use rocksdb::{ColumnFamily, ColumnFamilyDescriptor, Direction, IteratorMode, Options, WriteBatch, DB};
+use units_core_types::error::StorageError;
+use units_core_types::id::UnitsObjectId;
+use units_core_types::objects::UnitsObject;
+use units_core_types::transaction::TransactionReceipt;
+use units_core_types::{BatchOp, HistoricalStorage, ObjectStorage, ProofStorage, ReceiptStorage, SlotReceiptsIter, UnitsStorage};
+use units_core_types::{SlotNumber, StateProof, UnitsObjectProof};
+use units_proofs::ProofEngine;
+
+use crate::consolidated_storage::{NoOpWriteAheadLog, DEFAULT_HISTORY_DEPTH};
+use crate::lock_manager::InMemoryLockManager;
+
+/// Current object state, keyed by object ID
+const OBJECTS: &str = "objects";
+/// State of an object after each slot it changed in, keyed by ID then slot;
+/// `None` marks a deletion
+const OBJECT_VERSIONS: &str = "object_versions";
+/// Proofs of writes through [`ObjectStorage`], keyed by ID then sequence
+const PROOF_CHAIN: &str = "proof_chain";
+/// Proofs handed to [`ProofStorage`], keyed by ID, slot, then arrival
+const OBJECT_PROOFS: &str = "object_proofs";
+/// State proofs, keyed by slot
+const STATE_PROOFS: &str = "state_proofs";
+/// Encoded sparse Merkle tree nodes, keyed by hash
+const MERKLE_NODES: &str = "merkle_nodes";
+/// Receipts, keyed by transaction hash
+const RECEIPTS: &str = "receipts";
+/// Empty markers keyed by slot then transaction hash
+const RECEIPT_SLOTS: &str = "receipt_slots";
+/// Empty markers keyed by object ID, slot, then transaction hash
+const RECEIPT_OBJECTS: &str = "receipt_objects";
+
+const COLUMN_FAMILIES: [&str; 9] = [
+    OBJECTS,
+    OBJECT_VERSIONS,
+    PROOF_CHAIN,
+    OBJECT_PROOFS,
+    STATE_PROOFS,
+    MERKLE_NODES,
+    RECEIPTS,
+    RECEIPT_SLOTS,
+    RECEIPT_OBJECTS,
+];
+
+/// Length of an object ID at the front of a key
+const ID_LEN: usize = 32;
+
+/// A key and value read from a column family
+type KeyValue = (Box<[u8]>, Box<[u8]>);
+
+/// Storage keeping objects, their history, proofs and receipts in RocksDB
+///
+/// Like [`InMemoryObjectStorage`](crate::InMemoryObjectStorage), writes
+/// through [`ObjectStorage`] keep their own proof chain, while
+/// [`ProofStorage`] holds the proofs handed to it separately.
+pub struct RocksDbStorage {
+    db: DB,
+    history_depth: usize,
+    proof_engine: ProofEngine,
+    write_lock: Mutex<()>,
+    locks: InMemoryLockManager,
+}
+
+impl RocksDbStorage {
+    /// Open (or create) storage in the database directory at `path`
+    pub fn open(path: &Path) -> Result<Self, StorageError> {
+        Self::open_with_history_depth(path, DEFAULT_HISTORY_DEPTH)
+    }
+
+    /// Open storage at `path` retaining at most `history_depth` versions per object
+    ///
+    /// A depth of zero keeps only the current state, disabling time travel.
+    pub fn open_with_history_depth(path: &Path, history_depth: usize) -> Result<Self, StorageError> {
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+        let families = COLUMN_FAMILIES
+            .iter()
+            .map(|name| ColumnFamilyDescriptor::new(*name, Options::default()));
+        let db = DB::open_cf_descriptors(&options, path, families).map_err(rocksdb_error)?;
+
+        Ok(Self {
+            db,
+            history_depth,
+            proof_engine: ProofEngine::new(),
+            write_lock: Mutex::new(()),
+            locks: InMemoryLockManager::new(),
+        })
+    }
+
+    /// Number of versions retained per object
+    pub fn history_depth(&self) -> usize {
+        self.history_depth
+    }
+
+    fn cf(&self, name: &str) -> &ColumnFamily {
+        self.db.cf_handle(name).expect("column families are created on open")
+    }
+
+    fn get_decoded<T: serde::de::DeserializeOwned>(&self, cf: &str, key: &[u8]) -> Result<Option<T>, StorageError> {
+        self.db
+            .get_cf(self.cf(cf), key)
+            .map_err(rocksdb_error)?
+            .map(|bytes| decode(&bytes))
+            .transpose()
+    }
+
+    /// Entries of `cf` from `start` onwards, in key order
+    fn scan<'a>(&'a self, cf: &str, start: &[u8]) -> impl Iterator<Item = Result<KeyValue, StorageError>> + 'a {
+        self.db
+            .iterator_cf(self.cf(cf), IteratorMode::From(start, Direction::Forward))
+            .map(|entry| entry.map_err(rocksdb_error))
+    }
+
+    /// Entries of `cf` whose keys start with `prefix`, in key order
+    fn scan_prefix<'a>(&'a self, cf: &str, prefix: &'a [u8]) -> impl Iterator<Item = Result<KeyValue, StorageError>> + 'a {
+        self.scan(cf, prefix).take_while(move |entry| entry.as_ref().map_or(true, |(key, _)| key.starts_with(prefix)))
+    }
+
+    /// Last entry of `cf` whose key starts with `prefix`
+    fn last_with_prefix(&self, cf: &str, prefix: &[u8]) -> Result<Option<KeyValue>, StorageError> {
+        let upper = [prefix, &[0xFF; 16]].concat();
+        match self.db.iterator_cf(self.cf(cf), IteratorMode::From(&upper, Direction::Reverse)).next() {
+            Some(Ok((key, value))) if key.starts_with(prefix) => Ok(Some((key, value))),
+            Some(Err(err)) => Err(rocksdb_error(err)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Apply `ops` in one write batch, returning the proof of the last
+    /// write to each object
+    fn apply(&self, ops: &[BatchOp], transaction_hash: Option<[u8; 32]>) -> Result<HashMap<UnitsObjectId, UnitsObjectProof>, StorageError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let mut batch = WriteBatch::default();
+
+        // Reads within the batch must see its earlier writes, which the
+        // write batch itself does not expose
+        let mut states: HashMap<UnitsObjectId, Option<UnitsObject>> = HashMap::new();
+        let mut tips: HashMap<UnitsObjectId, (u64, UnitsObjectProof)> = HashMap::new();
+        let mut versions: HashMap<UnitsObjectId, BTreeMap<SlotNumber, Option<UnitsObject>>> = HashMap::new();
+        let mut proofs = HashMap::new();
+
+        for op in ops {
+            let id = op.object_id();
+            if let Entry::Vacant(entry) = states.entry(id) {
+                entry.insert(self.get(&id)?);
+            }
+            let proved = match (op, &states[&id]) {
+                (BatchOp::Set(object), _) => object,
+                (BatchOp::Delete(_), Some(object)) => object,
+                (BatchOp::Delete(_), None) => {
+                    return Err(StorageError::NotFound(format!("Object not found: {:?}", id)));
+                }
+            };
+
+            let tip = match tips.get(&id) {
+                Some(tip) => Some(tip.clone()),
+                None => self.latest_chain_proof(&id)?,
+            };
+            let (seq, prev_proof) = match tip {
+                Some((seq, proof)) => (seq + 1, Some(proof)),
+                None => (1, None),
+            };
+            let proof = self.proof_engine.generate_object_proof(proved, prev_proof.as_ref(), transaction_hash)?;
+
+            let state = match op {
+                BatchOp::Set(object) => Some(object.clone()),
+                BatchOp::Delete(_) => None,
+            };
+            match &state {
+                Some(object) => batch.put_cf(self.cf(OBJECTS), id.bytes(), encode(object)?),
+                None => batch.delete_cf(self.cf(OBJECTS), id.bytes()),
+            }
+            batch.put_cf(self.cf(PROOF_CHAIN), key(id.bytes(), seq), encode(&proof)?);
+            if self.history_depth > 0 {
+                versions.entry(id).or_default().insert(proof.slot, state.clone());
+            }
+
+            states.insert(id, state);
+            tips.insert(id, (seq, proof.clone()));
+            proofs.insert(id, proof);
+        }
+
+        for (id, written) in versions {
+            self.record_versions(&mut batch, &id, written)?;
+        }
+        self.db.write(batch).map_err(rocksdb_error)?;
+        Ok(proofs)
+    }
+
+    /// Record the versions an object gained in a batch, evicting the oldest
+    /// beyond the configured depth
+    fn record_versions(
+        &self,
+        batch: &mut WriteBatch,
+        id: &UnitsObjectId,
+        written: BTreeMap<SlotNumber, Option<UnitsObject>>,
+    ) -> Result<(), StorageError> {
+        let mut slots: BTreeSet<SlotNumber> = self
+            .scan_prefix(OBJECT_VERSIONS, id.bytes())
+            .map(|entry| entry.map(|(key, _)| key_number(&key)))
+            .collect::<Result<_, _>>()?;
+        slots.extend(written.keys().copied());
+
+        let evicted = slots.len().saturating_sub(self.history_depth);
+        for slot in slots.iter().take(evicted) {
+            batch.delete_cf(self.cf(OBJECT_VERSIONS), key(id.bytes(), *slot));
+        }
+        let oldest_kept = slots.iter().nth(evicted).copied().unwrap_or(SlotNumber::MAX);
+        for (slot, state) in written.range(oldest_kept..) {
+            batch.put_cf(self.cf(OBJECT_VERSIONS), key(id.bytes(), *slot), encode(state)?);
+        }
+        Ok(())
+    }
+
+    /// Last proof in the chain of `id` with its sequence number
+    fn latest_chain_proof(&self, id: &UnitsObjectId) -> Result<Option<(u64, UnitsObjectProof)>, StorageError> {
+        self.last_with_prefix(PROOF_CHAIN, id.bytes())?
+            .map(|(key, value)| Ok((key_number(&key), decode(&value)?)))
+            .transpose()
+    }
+
+    /// Receipts named by the keys of `index` from `start` onwards, which
+    /// end in a transaction hash, up to the first key `keep` rejects
+    fn indexed_receipts(
+        &self,
+        index: &str,
+        start: &[u8],
+        keep: impl Fn(&[u8]) -> bool,
+    ) -> Result<Vec<TransactionReceipt>, StorageError> {
+        let mut receipts = Vec::new();
+        for entry in self.scan(index, start) {
+            let (key, _) = entry?;
+            if !keep(&key) {
+                break;
+            }
+            if let Some(receipt) = self.get_receipt(&key_tx_hash(&key))? {
+                receipts.push(receipt);
+            }
+        }
+        Ok(receipts)
+    }
+
+    /// Slots in `[start_slot, end_slot]` with a receipt, ascending
+    fn receipt_slots(&self, start_slot: SlotNumber, end_slot: SlotNumber) -> Result<Vec<SlotNumber>, StorageError> {
+        let mut slots = Vec::new();
+        for entry in self.scan(RECEIPT_SLOTS, &start_slot.to_be_bytes()) {
+            let slot = slot_prefix(&entry?.0);
+            if slot > end_slot {
+                break;
+            }
+            if slots.last() != Some(&slot) {
+                slots.push(slot);
+            }
+        }
+        Ok(slots)
+    }
+}
+
+impl ObjectStorage for RocksDbStorage {
+    fn get(&self, id: &UnitsObjectId) -> Result<Option<UnitsObject>, StorageError> {
+        self.get_decoded(OBJECTS, id.bytes())
+    }
+
+    fn set(&self, object: &UnitsObject, transaction_hash: Option<[u8; 32]>) -> Result<UnitsObjectProof, StorageError> {
+        let op = BatchOp::Set(object.clone());
+        Ok(self.apply(std::slice::from_ref(&op), transaction_hash)?.remove(object.id()).expect("write was proved"))
+    }
+
+    fn delete(&self, id: &UnitsObjectId, transaction_hash: Option<[u8; 32]>) -> Result<UnitsObjectProof, StorageError> {
+        Ok(self.apply(&[BatchOp::Delete(*id)], transaction_hash)?.remove(id).expect("write was proved"))
+    }
+
+    fn set_batch(
+        &self,
+        objects: &[UnitsObject],
+        transaction_hash: [u8; 32],
+    ) -> Result<HashMap<UnitsObjectId, UnitsObjectProof>, StorageError> {
+        let ops: Vec<_> = objects.iter().cloned().map(BatchOp::Set).collect();
+        self.apply(&ops, Some(transaction_hash))
+    }
+
+    fn delete_batch(
+        &self,
+        ids: &[UnitsObjectId],
+        transaction_hash: [u8; 32],
+    ) -> Result<HashMap<UnitsObjectId, UnitsObjectProof>, StorageError> {
+        let ops: Vec<_> = ids.iter().copied().map(BatchOp::Delete).collect();
+        self.apply(&ops, Some(transaction_hash))
+    }
+
+    /// Applied in one write batch, so readers see the batch either not at
+    /// all or in full
+    fn apply_batch(
+        &self,
+        ops: &[BatchOp],
+        transaction_hash: [u8; 32],
+    ) -> Result<HashMap<UnitsObjectId, UnitsObjectProof>, StorageError> {
+        self.apply(ops, Some(transaction_hash))
+    }
+
+    fn version(&self, id: &UnitsObjectId) -> Result<u64, StorageError> {
+        Ok(self.latest_chain_proof(id)?.map_or(0, |(seq, _)| seq))
+    }
+
+    fn exists(&self, id: &UnitsObjectId) -> Result<bool, StorageError> {
+        Ok(self.db.get_pinned_cf(self.cf(OBJECTS), id.bytes()).map_err(rocksdb_error)?.is_some())
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_> {
+        self.iter_range(..)
+    }
+
+    /// Objects are read lazily from a RocksDB iterator seeked to the start
+    /// of the range
+    fn iter_range<R>(&self, range: R) -> Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_>
+    where
+        R: RangeBounds<UnitsObjectId>,
+    {
+        let start = range.start_bound().cloned();
+        let end = range.end_bound().cloned();
+        let from = match &start {
+            Bound::Included(id) | Bound::Excluded(id) => id.bytes().to_vec(),
+            Bound::Unbounded => Vec::new(),
+        };
+        let entries = self
+            .db
+            .iterator_cf(self.cf(OBJECTS), IteratorMode::From(&from, Direction::Forward))
+            .skip_while(move |entry| match (&start, entry) {
+                (Bound::Excluded(id), Ok((key, _))) => &key[..] == id.bytes(),
+                _ => false,
+            })
+            .take_while(move |entry| match (&end, entry) {
+                (Bound::Included(id), Ok((key, _))) => &key[..] <= id.bytes(),
+                (Bound::Excluded(id), Ok((key, _))) => &key[..] < id.bytes(),
+                _ => true,
+            });
+        Box::new(entries.map(|entry| decode(&entry.map_err(rocksdb_error)?.1)))
+    }
+}
+
+impl HistoricalStorage for RocksDbStorage {
+    fn get_at_slot(&self, id: &UnitsObjectId, slot: SlotNumber) -> Result<Option<UnitsObject>, StorageError> {
+        // The state at a slot is the latest version written at or before it
+        let at = key(id.bytes(), slot);
+        match self.db.iterator_cf(self.cf(OBJECT_VERSIONS), IteratorMode::From(&at, Direction::Reverse)).next() {
+            Some(Ok((key, value))) if key.starts_with(id.bytes()) => decode(&value),
+            Some(Err(err)) => Err(rocksdb_error(err)),
+            _ => Ok(None),
+        }
+    }
+
+    fn get_history(
+        &self,
+        id: &UnitsObjectId,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+    ) -> Result<Vec<(SlotNumber, UnitsObject)>, StorageError> {
+        let mut history = Vec::new();
+        for entry in self.scan_prefix(OBJECT_VERSIONS, id.bytes()).skip_while(|entry| {
+            entry.as_ref().is_ok_and(|(key, _)| key_number(key) < start_slot)
+        }) {
+            let (key, value) = entry?;
+            let slot = key_number(&key);
+            if slot > end_slot {
+                break;
+            }
+            if let Some(object) = decode::<Option<UnitsObject>>(&value)? {
+                history.push((slot, object));
+            }
+        }
+        Ok(history)
+    }
+
+    fn compact_history(&self, before_slot: SlotNumber) -> Result<usize, StorageError> {
+        let _guard = self.write_lock.lock().unwrap();
+
+        // Versions grouped per object, in slot order, with whether each is a deletion
+        let mut per_object: BTreeMap<Vec<u8>, Vec<(SlotNumber, bool)>> = BTreeMap::new();
+        for entry in self.scan(OBJECT_VERSIONS, &[]) {
+            let (key, value) = entry?;
+            let deleted = decode::<Option<UnitsObject>>(&value)?.is_none();
+            per_object.entry(key[..ID_LEN].to_vec()).or_default().push((key_number(&key), deleted));
+        }
+
+        // Keep the newest version before the cutoff so lookups at or after
+        // `before_slot` still resolve to the state in effect at that time,
+        // unless it is a deletion with nothing after it
+        let mut batch = WriteBatch::default();
+        let mut removed = 0;
+        for (id, versions) in per_object {
+            let older = versions.iter().take_while(|(slot, _)| *slot < before_slot).count();
+            if older == 0 {
+                continue;
+            }
+            let (_, base_deleted) = versions[older - 1];
+            let drop_base = base_deleted && older == versions.len();
+            let dropped = if drop_base { older } else { older - 1 };
+            for (slot, _) in &versions[..dropped] {
+                batch.delete_cf(self.cf(OBJECT_VERSIONS), key(&id, *slot));
+            }
+            removed += dropped;
+        }
+        self.db.write(batch).map_err(rocksdb_error)?;
+        Ok(removed)
+    }
+}
+
+impl ProofStorage for RocksDbStorage {
+    fn store_object_proof(&self, proof: &UnitsObjectProof) -> Result<(), StorageError> {
+        let _guard = self.write_lock.lock().unwrap();
+
+        // Proofs stored for the same slot keep their arrival order
+        let prefix = key(proof.object_id.bytes(), proof.slot);
+        let arrival = match self.last_with_prefix(OBJECT_PROOFS, &prefix)? {
+            Some((key, _)) => u64::from_be_bytes(key[key.len() - 8..].try_into().unwrap()) + 1,
+            None => 0,
+        };
+        self.db
+            .put_cf(self.cf(OBJECT_PROOFS), [&prefix[..], &arrival.to_be_bytes()].concat(), encode(proof)?)
+            .map_err(rocksdb_error)
+    }
+
+    fn get_latest_proof(&self, id: &UnitsObjectId) -> Result<Option<UnitsObjectProof>, StorageError> {
+        self.last_with_prefix(OBJECT_PROOFS, id.bytes())?
+            .map(|(_, value)| decode(&value))
+            .transpose()
+    }
+
+    fn get_proof_history(
+        &self,
+        id: &UnitsObjectId,
+        start_slot: Option<SlotNumber>,
+        end_slot: Option<SlotNumber>,
+    ) -> Result<Vec<(SlotNumber, UnitsObjectProof)>, StorageError> {
+        let start = key(id.bytes(), start_slot.unwrap_or(0));
+        let end_slot = end_slot.unwrap_or(SlotNumber::MAX);
+        let mut history = Vec::new();
+        for entry in self.scan(OBJECT_PROOFS, &start) {
+            let (key, value) = entry?;
+            if !key.starts_with(id.bytes()) || key_number(&key) > end_slot {
+                break;
+            }
+            history.push((key_number(&key), decode(&value)?));
+        }
+        Ok(history)
+    }
+
+    fn store_state_proof(&self, proof: &StateProof) -> Result<(), StorageError> {
+        self.db
+            .put_cf(self.cf(STATE_PROOFS), proof.slot.to_be_bytes(), encode(proof)?)
+            .map_err(rocksdb_error)
+    }
+
+    fn get_state_proof(&self, slot: SlotNumber) -> Result<Option<StateProof>, StorageError> {
+        self.get_decoded(STATE_PROOFS, &slot.to_be_bytes())
+    }
+
+    fn get_state_proof_history(&self, start_slot: SlotNumber, end_slot: SlotNumber) -> Result<Vec<StateProof>, StorageError> {
+        self.iter_state_proofs(start_slot, end_slot).collect()
+    }
+
+    fn iter_state_proofs(
+        &self,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+    ) -> Box<dyn Iterator<Item = Result<StateProof, StorageError>> + '_> {
+        let entries = self
+            .scan(STATE_PROOFS, &start_slot.to_be_bytes())
+            .take_while(move |entry| entry.as_ref().map_or(true, |(key, _)| slot_prefix(key) <= end_slot));
+        Box::new(entries.map(|entry| decode(&entry?.1)))
+    }
+
+    fn store_merkle_node(&self, hash: &[u8; 32], node: &[u8]) -> Result<(), StorageError> {
+        // Nodes never change under their hash, so rewriting one is harmless
+        self.db.put_cf(self.cf(MERKLE_NODES), hash, node).map_err(rocksdb_error)
+    }
+
+    fn get_merkle_node(&self, hash: &[u8; 32]) -> Result<Option<Vec<u8>>, StorageError> {
+        self.db.get_cf(self.cf(MERKLE_NODES), hash).map_err(rocksdb_error)
+    }
+}
+
+impl ReceiptStorage for RocksDbStorage {
+    fn store_receipt(&self, receipt: &TransactionReceipt) -> Result<(), StorageError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let mut batch = WriteBatch::default();
+
+        // A receipt stored again replaces the index entries of the old one
+        if let Some(previous) = self.get_receipt(&receipt.transaction_hash)? {
+            for (index, key) in receipt_index_keys(&previous) {
+                batch.delete_cf(self.cf(index), key);
+            }
+        }
+        batch.put_cf(self.cf(RECEIPTS), receipt.transaction_hash, encode(receipt)?);
+        for (index, key) in receipt_index_keys(receipt) {
+            batch.put_cf(self.cf(index), key, b"");
+        }
+        self.db.write(batch).map_err(rocksdb_error)
+    }
+
+    fn get_receipt(&self, tx_hash: &[u8; 32]) -> Result<Option<TransactionReceipt>, StorageError> {
+        self.get_decoded(RECEIPTS, tx_hash)
+    }
+
+    fn get_receipts_for_slot(&self, slot: SlotNumber) -> Result<Vec<TransactionReceipt>, StorageError> {
+        self.get_receipts_range(slot, slot)
+    }
+
+    fn get_receipts_range(&self, start_slot: SlotNumber, end_slot: SlotNumber) -> Result<Vec<TransactionReceipt>, StorageError> {
+        self.indexed_receipts(RECEIPT_SLOTS, &start_slot.to_be_bytes(), |key| slot_prefix(key) <= end_slot)
+    }
+
+    fn iter_receipts_by_slot(&self, start_slot: SlotNumber, end_slot: SlotNumber) -> SlotReceiptsIter<'_> {
+        // Only the slot numbers are collected up front; receipts are decoded per slot
+        let slots = match self.receipt_slots(start_slot, end_slot) {
+            Ok(slots) => slots,
+            Err(error) => return Box::new(std::iter::once(Err(error))),
+        };
+        Box::new(slots.into_iter().filter_map(move |slot| match self.get_receipts_for_slot(slot) {
+            Ok(receipts) if receipts.is_empty() => None,
+            Ok(receipts) => Some(Ok((slot, receipts))),
+            Err(error) => Some(Err(error)),
+        }))
+    }
+
+    fn get_receipts_for_object(
+        &self,
+        object_id: &UnitsObjectId,
+        start_slot: Option<SlotNumber>,
+        end_slot: Option<SlotNumber>,
+    ) -> Result<Vec<TransactionReceipt>, StorageError> {
+        let start = key(object_id.bytes(), start_slot.unwrap_or(0));
+        let end_slot = end_slot.unwrap_or(SlotNumber::MAX);
+        self.indexed_receipts(RECEIPT_OBJECTS, &start, |key| {
+            key.starts_with(object_id.bytes()) && key_number(key) <= end_slot
+        })
+    }
+
+    fn cleanup_receipts_before(&self, slot: SlotNumber) -> Result<usize, StorageError> {
+        let _guard = self.write_lock.lock().unwrap();
+        let mut batch = WriteBatch::default();
+        let mut removed = 0;
+        for receipt in self.indexed_receipts(RECEIPT_SLOTS, &[], |key| slot_prefix(key) < slot)? {
+            batch.delete_cf(self.cf(RECEIPTS), receipt.transaction_hash);
+            for (index, key) in receipt_index_keys(&receipt) {
+                batch.delete_cf(self.cf(index), key);
+            }
+            removed += 1;
+        }
+        self.db.write(batch).map_err(rocksdb_error)?;
+        Ok(removed)
+    }
+}
+
+impl UnitsStorage for RocksDbStorage {
+    type Objects = Self;
+    type Historical = Self;
+    type Proofs = Self;
+    type WAL = NoOpWriteAheadLog;
+    type Receipts = Self;
+    type Locks = InMemoryLockManager;
+
+    fn objects(&self) -> &Self::Objects {
+        self
+    }
+
+    fn historical(&self) -> &Self::Historical {
+        self
+    }
+
+    fn proofs(&self) -> &Self::Proofs {
+        self
+    }
+
+    /// RocksDB keeps its own write-ahead log, so no second one is kept
+    fn wal(&self) -> Option<&Self::WAL> {
+        None
+    }
+
+    fn receipts(&self) -> &Self::Receipts {
+        self
+    }
+
+    fn locks(&self) -> &Self::Locks {
+        &self.locks
+    }
+}
+
+/// Index entries of a receipt, so they can be removed with it
+fn receipt_index_keys(receipt: &TransactionReceipt) -> Vec<(&'static str, Vec<u8>)> {
+    let tx_hash = receipt.transaction_hash.as_slice();
+    let mut keys = vec![(RECEIPT_SLOTS, [&receipt.slot.to_be_bytes()[..], tx_hash].concat())];
+    // The receipt affects the objects it proves or changes
+    let affected = receipt.object_proofs.keys().chain(receipt.effects.iter().map(|effect| &effect.object_id));
+    for object_id in affected {
+        keys.push((RECEIPT_OBJECTS, [&key(object_id.bytes(), receipt.slot)[..], tx_hash].concat()));
+    }
+    keys
+}
+
+/// Key of an object ID followed by a big-endian number, so entries of one
+/// object sort by that number
+fn key(id: &[u8], number: u64) -> Vec<u8> {
+    [id, &number.to_be_bytes()].concat()
+}
+
+/// Number following the object ID in a [`key`]
+fn key_number(key: &[u8]) -> u64 {
+    u64::from_be_bytes(key[ID_LEN..ID_LEN + 8].try_into().unwrap())
+}
+
+/// Slot at the front of a key
+fn slot_prefix(key: &[u8]) -> SlotNumber {
+    u64::from_be_bytes(key[..8].try_into().unwrap())
+}
+
+/// Transaction hash at the end of a receipt index key
+fn key_tx_hash(key: &[u8]) -> [u8; 32] {
+    key[key.len() - 32..].try_into().unwrap()
+}
+
+fn encode<T: serde::Serialize>(value: &T) -> Result<Vec<u8>, StorageError> {
+    Ok(bincode::serialize(value)?)
+}
+
+fn decode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<T, StorageError> {
+    Ok(bincode::deserialize(bytes)?)
+}
+
+fn rocksdb_error(err: rocksdb::Error) -> StorageError {
+    StorageError::Database(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use units_core_types::transaction::TransactionEffect;
+
+    fn object(byte: u8, data: u8) -> UnitsObject {
+        UnitsObject::new_data(UnitsObjectId::new([byte; 32]), UnitsObjectId::new([0xC0; 32]), vec![data])
+    }
+
+    #[test]
+    fn test_rocksdb_storage_round_trip() {
+        let dir = tempdir().unwrap();
+        let storage = RocksDbStorage::open(dir.path()).unwrap();
+
+        // Writes chain their proofs and bump the version
+        let first = storage.set(&object(1, 1), None).unwrap();
+        let second = storage.set(&object(1, 2), None).unwrap();
+        assert_eq!(second.prev_proof_hash, Some(first.hash()));
+        assert_eq!(storage.get(&object(1, 0).id).unwrap(), Some(object(1, 2)));
+        assert_eq!(storage.version(&object(1, 0).id).unwrap(), 2);
+        assert_eq!(storage.get_at_slot(&object(1, 0).id, second.slot).unwrap(), Some(object(1, 2)));
+
+        // A failing batch leaves storage as it was
+        let batch = [BatchOp::Set(object(2, 1)), BatchOp::Delete(object(3, 0).id)];
+        assert!(matches!(storage.apply_batch(&batch, [7; 32]), Err(StorageError::NotFound(_))));
+        assert!(!storage.exists(&object(2, 0).id).unwrap());
+        let batch = [BatchOp::Set(object(2, 1)), BatchOp::Set(object(3, 1)), BatchOp::Delete(object(1, 0).id)];
+        let proofs = storage.apply_batch(&batch, [7; 32]).unwrap();
+        assert_eq!(proofs.len(), 3);
+        assert!(storage.get(&object(1, 0).id).unwrap().is_none());
+        assert_eq!(storage.version(&object(1, 0).id).unwrap(), 3);
+
+        let ids: Vec<_> = storage.iter().map(|object| object.unwrap().id).collect();
+        assert_eq!(ids, vec![object(2, 0).id, object(3, 0).id]);
+        assert_eq!(storage.iter_range(object(3, 0).id..).count(), 1);
+        assert_eq!(storage.iter_range(..object(3, 0).id).count(), 1);
+
+        // Compaction drops the trailing deletion and keeps live versions
+        assert_eq!(storage.compact_history(SlotNumber::MAX).unwrap(), 1);
+        assert_eq!(storage.get_history(&object(2, 0).id, 0, SlotNumber::MAX).unwrap().len(), 1);
+
+        // Proofs and receipts round-trip
+        storage.store_object_proof(&proofs[&object(2, 0).id]).unwrap();
+        let latest = storage.get_latest_proof(&object(2, 0).id).unwrap().unwrap();
+        assert_eq!(latest.hash(), proofs[&object(2, 0).id].hash());
+        let mut receipt = TransactionReceipt::new([7; 32], 5, true, 1_700_000_000);
+        receipt.add_effect(TransactionEffect::new_creation([7; 32], object(2, 1)));
+        storage.store_receipt(&receipt).unwrap();
+        let stored = storage.get_receipt(&[7; 32]).unwrap().unwrap();
+        assert_eq!(bincode::serialize(&stored).unwrap(), bincode::serialize(&receipt).unwrap());
+        assert_eq!(storage.get_receipts_for_slot(5).unwrap().len(), 1);
+        assert_eq!(storage.get_receipts_for_object(&object(2, 0).id, None, Some(5)).unwrap().len(), 1);
+        assert!(storage.get_receipts_for_object(&object(3, 0).id, None, None).unwrap().is_empty());
+        assert_eq!(storage.cleanup_receipts_before(6).unwrap(), 1);
+        assert!(storage.get_receipts_for_object(&object(2, 0).id, None, None).unwrap().is_empty());
+
+        // Sparse Merkle tree nodes survive reopening, reachable from the root
+        let root = {
+            let mut tree = units_proofs::SparseMerkleTree::new(&storage);
+            tree.insert(&object(2, 0).id, [9; 32]).unwrap();
+            tree.root()
+        };
+        drop(storage);
+        let storage = RocksDbStorage::open(dir.path()).unwrap();
+        let tree = units_proofs::SparseMerkleTree::open(&storage, root);
+        assert_eq!(tree.get(&object(2, 0).id).unwrap(), Some([9; 32]));
+    }
+
+    #[test]
+    fn test_rocksdb_set_batch_is_one_write() {
+        let dir = tempdir().unwrap();
+        let storage = RocksDbStorage::open_with_history_depth(dir.path(), 2).unwrap();
+
+        // Several writes to one object in a batch chain onto each other
+        let objects = [object(1, 1), object(1, 2), object(1, 3), object(2, 1)];
+        let proofs = storage.set_batch(&objects, [9; 32]).unwrap();
+        assert_eq!(proofs.len(), 2);
+        assert_eq!(storage.version(&object(1, 0).id).unwrap(), 3);
+        assert_eq!(storage.get(&object(1, 0).id).unwrap(), Some(object(1, 3)));
+        assert!(storage.get_history(&object(1, 0).id, 0, SlotNumber::MAX).unwrap().len() <= 2);
+
+        let next = storage.set(&object(1, 4), None).unwrap();
+        assert_eq!(next.prev_proof_hash, Some(proofs[&object(1, 0).id].hash()));
+    }
+}
@@ -0,0 +1,651 @@
+//! SQLite-backed persistent lock table
+//!
+//! Locks survive restarts: every acquisition and release is committed to a
+//! SQLite table before it returns. On startup, [`SqliteLockManager::recover`]
+//! keeps the locks of transactions that are still in flight and releases
+//! everything else, so a crash never leaves objects locked forever.
+//!
+//! The manager also implements [`LockManager`]: each guard holds an
+//! exclusive lock under its own owner hash with a lease, so the locks of a
+//! process that dies without dropping its guards expire on their own.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection};
+use units_core_types::error::StorageError;
+use units_core_types::id::UnitsObjectId;
+use units_core_types::LockManager;
+use units_core_types::locks::{
+    AccessIntent, LockInfo, LockType, PersistentLockManager, UnitsLockIterator,
+};
+
+use crate::lock_manager::DEFAULT_LOCK_TIMEOUT;
+
+/// How long a guard's lock lasts without being renewed, by default
+///
+/// Leases are not renewed in the background: work held under a guard for
+/// longer must call [`SqliteLockGuard::renew`] or
+/// [`SqliteLockGuard::ensure_held`] in time.
+pub const DEFAULT_LOCK_LEASE: Duration = Duration::from_secs(30);
+
+/// How often a blocked `lock` or `lock_many` retries
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Outcome of startup recovery
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LockRecovery {
+    /// Locks kept because their transaction is still in flight
+    pub retained: usize,
+    /// Locks released because they expired or their transaction is gone
+    pub released: usize,
+}
+
+/// Persistent lock manager storing its lock table in SQLite
+///
+/// Read locks are shared and write locks are exclusive. A transaction that
+/// holds the only lock on an object may upgrade it from read to write.
+#[derive(Debug)]
+pub struct SqliteLockManager {
+    connection: Mutex<Connection>,
+    lease: Option<Duration>,
+    timeout: Duration,
+    /// Prefix of the owner hashes given to guards, unique to this manager
+    session: [u8; 24],
+    next_owner: AtomicU64,
+}
+
+/// Guard for an exclusive lock taken through [`LockManager`], released when dropped
+///
+/// If the guard is never dropped, because the process crashed, the lock
+/// expires once its lease runs out. The same happens to a live guard held
+/// past its lease without renewal: another owner may then take the object
+/// while the guard still exists. Call [`SqliteLockGuard::ensure_held`]
+/// before committing work done under the guard.
+pub struct SqliteLockGuard<'a> {
+    object_id: UnitsObjectId,
+    owner: [u8; 32],
+    manager: &'a SqliteLockManager,
+}
+
+impl SqliteLockGuard<'_> {
+    /// The locked object
+    pub fn object_id(&self) -> &UnitsObjectId {
+        &self.object_id
+    }
+
+    /// Transaction hash the lock is recorded under in the lock table
+    ///
+    /// Guards from one `lock_many` call share an owner, so
+    /// `release_transaction_locks` frees them all at once.
+    pub fn owner(&self) -> &[u8; 32] {
+        &self.owner
+    }
+
+    /// Restart the lease, returning false if the lock has already been lost
+    pub fn renew(&self) -> Result<bool, StorageError> {
+        let connection = self.manager.connection.lock().unwrap();
+        let renewed = connection
+            .execute(
+                "UPDATE locks SET acquired_at = ?1 WHERE object_id = ?2 AND transaction_hash = ?3",
+                params![now_ms() as i64, self.object_id.as_ref(), self.owner.as_slice()],
+            )
+            .map_err(sqlite_error)?;
+        Ok(renewed > 0)
+    }
+
+    /// Restart the lease, failing if the lock has already been lost
+    ///
+    /// Once the lease has run out, another owner may have changed the object,
+    /// so work done under the guard must not be committed.
+    pub fn ensure_held(&self) -> Result<(), StorageError> {
+        if self.renew()? {
+            return Ok(());
+        }
+        Err(StorageError::LockError(format!(
+            "Lease on {} expired while its guard was held",
+            self.object_id
+        )))
+    }
+}
+
+impl Drop for SqliteLockGuard<'_> {
+    fn drop(&mut self) {
+        match self.manager.release_lock(&self.object_id, &self.owner) {
+            Ok(true) => {}
+            Ok(false) => log::warn!("Lock on {} was lost before its guard was dropped", self.object_id),
+            Err(e) => log::warn!("Failed to release lock on {}: {}", self.object_id, e),
+        }
+    }
+}
+
+impl SqliteLockManager {
+    /// Open (or create) the lock table at `path`
+    pub fn open(path: &Path) -> Result<Self, StorageError> {
+        Self::with_connection(Connection::open(path).map_err(sqlite_error)?)
+    }
+
+    /// Lock table that lives only as long as the manager, for tests
+    pub fn open_in_memory() -> Result<Self, StorageError> {
+        Self::with_connection(Connection::open_in_memory().map_err(sqlite_error)?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, StorageError> {
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS locks (
+                    object_id BLOB NOT NULL,
+                    transaction_hash BLOB NOT NULL,
+                    write INTEGER NOT NULL,
+                    acquired_at INTEGER NOT NULL,
+                    timeout_ms INTEGER,
+                    PRIMARY KEY (object_id, transaction_hash)
+                );
+                CREATE INDEX IF NOT EXISTS locks_by_transaction ON locks (transaction_hash);",
+            )
+            .map_err(sqlite_error)?;
+
+        // Owners must not collide with those of an earlier process whose
+        // locks are still in the table
+        let started = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let mut session = [0u8; 24];
+        session[..16].copy_from_slice(&started.as_nanos().to_le_bytes());
+        session[16..20].copy_from_slice(&std::process::id().to_le_bytes());
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+            lease: Some(DEFAULT_LOCK_LEASE),
+            timeout: DEFAULT_LOCK_TIMEOUT,
+            session,
+            next_owner: AtomicU64::new(0),
+        })
+    }
+
+    /// Set how long guard locks last without renewal; `None` never expires them
+    pub fn with_lease(mut self, lease: Option<Duration>) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    /// Set how long `lock` and `lock_many` wait for a held lock
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Reconcile the lock table after a restart
+    ///
+    /// Locks whose transaction `is_in_flight` reports as still running stay
+    /// with that transaction unless they have expired; all others are
+    /// released.
+    pub fn recover<F>(&self, mut is_in_flight: F) -> Result<LockRecovery, StorageError>
+    where
+        F: FnMut(&[u8; 32]) -> bool,
+    {
+        let mut connection = self.connection.lock().unwrap();
+        let tx = connection.transaction().map_err(sqlite_error)?;
+        let now = now_ms();
+
+        let locks = select_locks(&tx, "SELECT * FROM locks", [])?;
+        let mut recovery = LockRecovery::default();
+        for lock in locks {
+            if is_in_flight(&lock.transaction_hash) && !is_expired(&lock, now) {
+                recovery.retained += 1;
+            } else {
+                tx.execute(
+                    "DELETE FROM locks WHERE object_id = ?1 AND transaction_hash = ?2",
+                    params![lock.object_id.as_ref(), lock.transaction_hash.as_slice()],
+                )
+                .map_err(sqlite_error)?;
+                recovery.released += 1;
+            }
+        }
+
+        tx.commit().map_err(sqlite_error)?;
+        Ok(recovery)
+    }
+
+    /// Every lock in the table
+    pub fn all_locks(&self) -> Result<Vec<LockInfo>, StorageError> {
+        let connection = self.connection.lock().unwrap();
+        select_locks(&connection, "SELECT * FROM locks", [])
+    }
+
+    fn new_owner(&self) -> [u8; 32] {
+        let mut owner = [0u8; 32];
+        owner[..24].copy_from_slice(&self.session);
+        owner[24..].copy_from_slice(&self.next_owner.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+        owner
+    }
+
+    /// Write-lock every object in `ids` for `owner` in one step
+    ///
+    /// Returns the first object some other owner holds, taking nothing.
+    fn try_acquire_exclusive(
+        &self,
+        ids: &[UnitsObjectId],
+        owner: &[u8; 32],
+    ) -> Result<Option<UnitsObjectId>, StorageError> {
+        let mut connection = self.connection.lock().unwrap();
+        let tx = connection.transaction().map_err(sqlite_error)?;
+        let now = now_ms();
+
+        for id in ids {
+            delete_expired(&tx, Some(id), now)?;
+            let busy: bool = tx
+                .query_row(
+                    "SELECT EXISTS (SELECT 1 FROM locks WHERE object_id = ?1 AND transaction_hash != ?2)",
+                    params![id.as_ref(), owner.as_slice()],
+                    |row| row.get(0),
+                )
+                .map_err(sqlite_error)?;
+            if busy {
+                return Ok(Some(*id));
+            }
+        }
+
+        let lease_ms = self.lease.map(|lease| lease.as_millis() as i64);
+        for id in ids {
+            tx.execute(
+                "INSERT OR REPLACE INTO locks (object_id, transaction_hash, write, acquired_at, timeout_ms)
+                 VALUES (?1, ?2, 1, ?3, ?4)",
+                params![id.as_ref(), owner.as_slice(), now as i64, lease_ms],
+            )
+            .map_err(sqlite_error)?;
+        }
+
+        tx.commit().map_err(sqlite_error)?;
+        Ok(None)
+    }
+
+    /// Wait until every object in `ids` is free, then take them all for one new owner
+    fn acquire_exclusive(&self, ids: &[UnitsObjectId]) -> Result<Vec<SqliteLockGuard<'_>>, StorageError> {
+        let owner = self.new_owner();
+        let deadline = Instant::now() + self.timeout;
+
+        while let Some(busy) = self.try_acquire_exclusive(ids, &owner)? {
+            if Instant::now() >= deadline {
+                return Err(StorageError::LockTimeout(busy));
+            }
+            std::thread::sleep(LOCK_POLL_INTERVAL);
+        }
+
+        Ok(ids
+            .iter()
+            .map(|id| SqliteLockGuard {
+                object_id: *id,
+                owner,
+                manager: self,
+            })
+            .collect())
+    }
+}
+
+impl LockManager for SqliteLockManager {
+    type Guard<'a> = SqliteLockGuard<'a> where Self: 'a;
+
+    /// The lock lasts for the manager's lease, not for as long as the guard
+    /// lives; see [`SqliteLockGuard::ensure_held`].
+    fn lock(&self, id: &UnitsObjectId) -> Result<Self::Guard<'_>, StorageError> {
+        Ok(self.acquire_exclusive(std::slice::from_ref(id))?.remove(0))
+    }
+
+    fn try_lock(&self, id: &UnitsObjectId) -> Result<Option<Self::Guard<'_>>, StorageError> {
+        let owner = self.new_owner();
+        if self.try_acquire_exclusive(std::slice::from_ref(id), &owner)?.is_some() {
+            return Ok(None);
+        }
+        Ok(Some(SqliteLockGuard {
+            object_id: *id,
+            owner,
+            manager: self,
+        }))
+    }
+
+    /// Each lock lasts for the manager's lease, not for as long as its guard
+    /// lives; see [`SqliteLockGuard::ensure_held`].
+    fn lock_many(&self, ids: &[UnitsObjectId]) -> Result<Vec<Self::Guard<'_>>, StorageError> {
+        let mut ids = ids.to_vec();
+        ids.sort();
+        ids.dedup();
+        self.acquire_exclusive(&ids)
+    }
+}
+
+impl PersistentLockManager for SqliteLockManager {
+    type Error = StorageError;
+
+    fn acquire_lock(
+        &self,
+        object_id: &UnitsObjectId,
+        lock_type: LockType,
+        transaction_hash: &[u8; 32],
+        timeout_ms: Option<u64>,
+    ) -> Result<bool, Self::Error> {
+        let mut connection = self.connection.lock().unwrap();
+        let tx = connection.transaction().map_err(sqlite_error)?;
+        let now = now_ms();
+
+        delete_expired(&tx, Some(object_id), now)?;
+        let held = select_locks(
+            &tx,
+            "SELECT * FROM locks WHERE object_id = ?1",
+            params![object_id.as_ref()],
+        )?;
+
+        let others: Vec<&LockInfo> = held
+            .iter()
+            .filter(|lock| lock.transaction_hash != *transaction_hash)
+            .collect();
+        let conflict = match lock_type {
+            LockType::Read => others.iter().any(|lock| lock.lock_type == LockType::Write),
+            LockType::Write => !others.is_empty(),
+        };
+        if conflict {
+            return Err(StorageError::LockError(format!(
+                "Object {} is locked by transaction {}",
+                object_id,
+                hex_prefix(&others[0].transaction_hash)
+            )));
+        }
+
+        // Re-acquiring keeps the stronger of the held and requested lock
+        let own_write = held
+            .iter()
+            .any(|lock| lock.transaction_hash == *transaction_hash && lock.lock_type == LockType::Write);
+        let write = own_write || lock_type == LockType::Write;
+        tx.execute(
+            "INSERT INTO locks (object_id, transaction_hash, write, acquired_at, timeout_ms)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT (object_id, transaction_hash)
+             DO UPDATE SET write = excluded.write, acquired_at = excluded.acquired_at, timeout_ms = excluded.timeout_ms",
+            params![object_id.as_ref(), transaction_hash.as_slice(), write, now as i64, timeout_ms.map(|ms| ms as i64)],
+        )
+        .map_err(sqlite_error)?;
+
+        tx.commit().map_err(sqlite_error)?;
+        Ok(true)
+    }
+
+    fn release_lock(
+        &self,
+        object_id: &UnitsObjectId,
+        transaction_hash: &[u8; 32],
+    ) -> Result<bool, Self::Error> {
+        let connection = self.connection.lock().unwrap();
+        let removed = connection
+            .execute(
+                "DELETE FROM locks WHERE object_id = ?1 AND transaction_hash = ?2",
+                params![object_id.as_ref(), transaction_hash.as_slice()],
+            )
+            .map_err(sqlite_error)?;
+        Ok(removed > 0)
+    }
+
+    fn get_lock_info(&self, object_id: &UnitsObjectId) -> Result<Option<LockInfo>, Self::Error> {
+        let connection = self.connection.lock().unwrap();
+        let now = now_ms();
+
+        // Prefer reporting the exclusive holder when there is one
+        let locks = select_locks(
+            &connection,
+            "SELECT * FROM locks WHERE object_id = ?1 ORDER BY write DESC, acquired_at",
+            params![object_id.as_ref()],
+        )?;
+        Ok(locks.into_iter().find(|lock| !is_expired(lock, now)))
+    }
+
+    fn can_acquire_lock(
+        &self,
+        object_id: &UnitsObjectId,
+        intent: AccessIntent,
+        transaction_hash: &[u8; 32],
+    ) -> Result<bool, Self::Error> {
+        let now = now_ms();
+        let others: Vec<LockInfo> = self
+            .get_object_locks(object_id)
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|lock| lock.transaction_hash != *transaction_hash && !is_expired(lock, now))
+            .collect();
+
+        Ok(match intent {
+            AccessIntent::Read => others.iter().all(|lock| lock.lock_type == LockType::Read),
+            AccessIntent::Write => others.is_empty(),
+        })
+    }
+
+    fn release_transaction_locks(&self, transaction_hash: &[u8; 32]) -> Result<usize, Self::Error> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                "DELETE FROM locks WHERE transaction_hash = ?1",
+                params![transaction_hash.as_slice()],
+            )
+            .map_err(sqlite_error)
+    }
+
+    fn get_transaction_locks(
+        &self,
+        transaction_hash: &[u8; 32],
+    ) -> Box<dyn UnitsLockIterator<Self::Error> + '_> {
+        let connection = self.connection.lock().unwrap();
+        Box::new(LockIter::new(select_locks(
+            &connection,
+            "SELECT * FROM locks WHERE transaction_hash = ?1",
+            params![transaction_hash.as_slice()],
+        )))
+    }
+
+    fn get_object_locks(&self, object_id: &UnitsObjectId) -> Box<dyn UnitsLockIterator<Self::Error> + '_> {
+        let connection = self.connection.lock().unwrap();
+        Box::new(LockIter::new(select_locks(
+            &connection,
+            "SELECT * FROM locks WHERE object_id = ?1",
+            params![object_id.as_ref()],
+        )))
+    }
+
+    fn cleanup_expired_locks(&self) -> Result<usize, Self::Error> {
+        let connection = self.connection.lock().unwrap();
+        delete_expired(&connection, None, now_ms())
+    }
+}
+
+/// Locks read from the table
+struct LockIter(std::vec::IntoIter<Result<LockInfo, StorageError>>);
+
+impl LockIter {
+    fn new(locks: Result<Vec<LockInfo>, StorageError>) -> Self {
+        let items = match locks {
+            Ok(locks) => locks.into_iter().map(Ok).collect::<Vec<_>>(),
+            Err(e) => vec![Err(e)],
+        };
+        Self(items.into_iter())
+    }
+}
+
+impl Iterator for LockIter {
+    type Item = Result<LockInfo, StorageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+impl UnitsLockIterator<StorageError> for LockIter {}
+
+fn select_locks<P: rusqlite::Params>(
+    connection: &Connection,
+    sql: &str,
+    params: P,
+) -> Result<Vec<LockInfo>, StorageError> {
+    let mut statement = connection.prepare(sql).map_err(sqlite_error)?;
+    let rows = statement.query_map(params, row_to_lock).map_err(sqlite_error)?;
+    rows.collect::<Result<Vec<_>, _>>().map_err(sqlite_error)
+}
+
+fn row_to_lock(row: &rusqlite::Row<'_>) -> rusqlite::Result<LockInfo> {
+    let blob = |index: usize| -> rusqlite::Result<[u8; 32]> {
+        let bytes: Vec<u8> = row.get(index)?;
+        bytes.try_into().map_err(|_| {
+            rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Blob, "expected 32 bytes".into())
+        })
+    };
+
+    Ok(LockInfo {
+        object_id: UnitsObjectId::new(blob(0)?),
+        transaction_hash: blob(1)?,
+        lock_type: if row.get::<_, bool>(2)? { LockType::Write } else { LockType::Read },
+        acquired_at: row.get::<_, i64>(3)? as u64,
+        timeout_ms: row.get::<_, Option<i64>>(4)?.map(|ms| ms as u64),
+    })
+}
+
+/// Delete expired locks, optionally only those on one object
+fn delete_expired(
+    connection: &Connection,
+    object_id: Option<&UnitsObjectId>,
+    now: u64,
+) -> Result<usize, StorageError> {
+    let expired = "timeout_ms IS NOT NULL AND acquired_at + timeout_ms <= ?1";
+    match object_id {
+        Some(id) => connection.execute(
+            &format!("DELETE FROM locks WHERE object_id = ?2 AND {}", expired),
+            params![now as i64, id.as_ref()],
+        ),
+        None => connection.execute(&format!("DELETE FROM locks WHERE {}", expired), params![now as i64]),
+    }
+    .map_err(sqlite_error)
+}
+
+fn is_expired(lock: &LockInfo, now: u64) -> bool {
+    lock.timeout_ms
+        .is_some_and(|timeout| lock.acquired_at.saturating_add(timeout) <= now)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+fn hex_prefix(hash: &[u8; 32]) -> String {
+    format!("{:08x}", u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]]))
+}
+
+fn sqlite_error(error: rusqlite::Error) -> StorageError {
+    StorageError::Database(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const TX_A: [u8; 32] = [0xa; 32];
+    const TX_B: [u8; 32] = [0xb; 32];
+
+    #[test]
+    fn test_shared_and_exclusive_locks() {
+        let locks = SqliteLockManager::open_in_memory().unwrap();
+        let object = UnitsObjectId::new([1; 32]);
+
+        locks.acquire_lock(&object, LockType::Read, &TX_A, None).unwrap();
+        locks.acquire_lock(&object, LockType::Read, &TX_B, None).unwrap();
+        assert!(!locks.can_acquire_lock(&object, AccessIntent::Write, &TX_A).unwrap());
+        assert!(locks.acquire_lock(&object, LockType::Write, &TX_A, None).is_err());
+
+        // Once the other reader leaves, the read lock upgrades in place
+        assert!(locks.release_lock(&object, &TX_B).unwrap());
+        locks.acquire_lock(&object, LockType::Write, &TX_A, None).unwrap();
+        assert_eq!(locks.get_lock_info(&object).unwrap().unwrap().lock_type, LockType::Write);
+        assert!(locks.acquire_lock(&object, LockType::Read, &TX_B, None).is_err());
+
+        // Re-acquiring a read lock does not downgrade the write lock
+        locks.acquire_lock(&object, LockType::Read, &TX_A, None).unwrap();
+        assert_eq!(locks.get_object_locks(&object).count(), 1);
+        assert_eq!(locks.get_lock_info(&object).unwrap().unwrap().lock_type, LockType::Write);
+
+        assert_eq!(locks.release_transaction_locks(&TX_A).unwrap(), 1);
+        assert!(locks.get_lock_info(&object).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_expired_locks_do_not_block() {
+        let locks = SqliteLockManager::open_in_memory().unwrap();
+        let object = UnitsObjectId::new([1; 32]);
+
+        locks.acquire_lock(&object, LockType::Write, &TX_A, Some(0)).unwrap();
+        assert!(locks.get_lock_info(&object).unwrap().is_none());
+        locks.acquire_lock(&object, LockType::Write, &TX_B, Some(60_000)).unwrap();
+        assert_eq!(locks.get_lock_info(&object).unwrap().unwrap().transaction_hash, TX_B);
+
+        locks.acquire_lock(&UnitsObjectId::new([2; 32]), LockType::Read, &TX_A, Some(0)).unwrap();
+        assert_eq!(locks.cleanup_expired_locks().unwrap(), 1);
+        assert_eq!(locks.all_locks().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_locks_survive_restart_and_recover() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("locks.db");
+        let (a, b, c) = (UnitsObjectId::new([1; 32]), UnitsObjectId::new([2; 32]), UnitsObjectId::new([3; 32]));
+
+        {
+            let locks = SqliteLockManager::open(&path).unwrap();
+            locks.acquire_lock(&a, LockType::Write, &TX_A, None).unwrap();
+            locks.acquire_lock(&b, LockType::Read, &TX_A, None).unwrap();
+            locks.acquire_lock(&c, LockType::Write, &TX_B, None).unwrap();
+        }
+
+        let locks = SqliteLockManager::open(&path).unwrap();
+        assert_eq!(locks.get_transaction_locks(&TX_A).count(), 2);
+
+        // Only TX_A was still running when the node came back
+        let recovery = locks.recover(|hash| *hash == TX_A).unwrap();
+        assert_eq!(recovery, LockRecovery { retained: 2, released: 1 });
+        assert_eq!(locks.get_lock_info(&a).unwrap().unwrap().transaction_hash, TX_A);
+        assert!(locks.get_lock_info(&c).unwrap().is_none());
+        locks.acquire_lock(&c, LockType::Write, &TX_A, None).unwrap();
+    }
+
+    #[test]
+    fn test_guards_are_exclusive_and_leases_expire() {
+        let locks = SqliteLockManager::open_in_memory()
+            .unwrap()
+            .with_timeout(Duration::from_millis(20));
+        let (a, b) = (UnitsObjectId::new([1; 32]), UnitsObjectId::new([2; 32]));
+
+        let guard = locks.lock(&a).unwrap();
+        assert!(locks.try_lock(&a).unwrap().is_none());
+        assert!(matches!(locks.lock_many(&[b, a]), Err(StorageError::LockTimeout(id)) if id == a));
+        // The failed attempt must not leave `b` locked
+        assert!(locks.get_lock_info(&b).unwrap().is_none());
+        assert!(guard.renew().unwrap());
+        drop(guard);
+
+        let guards = locks.lock_many(&[b, a, b]).unwrap();
+        assert_eq!(guards.len(), 2);
+        assert_eq!(locks.release_transaction_locks(guards[0].owner()).unwrap(), 2);
+        assert!(!guards[0].renew().unwrap());
+        drop(guards);
+
+        // A guard that is never dropped stops blocking once its lease runs out
+        let locks = locks.with_lease(Some(Duration::ZERO));
+        std::mem::forget(locks.lock(&a).unwrap());
+        let guard = locks.try_lock(&a).unwrap().unwrap();
+        assert_eq!(locks.get_transaction_locks(guard.owner()).count(), 1);
+
+        // So does one held past its lease, which must notice before committing
+        let lapsed = locks.lock(&b).unwrap();
+        let taken = locks.try_lock(&b).unwrap().unwrap();
+        assert!(matches!(lapsed.ensure_held(), Err(StorageError::LockError(_))));
+        // Dropping it leaves the new owner's lock alone
+        drop(lapsed);
+        assert_eq!(locks.get_transaction_locks(taken.owner()).count(), 1);
+    }
+}
@@ -0,0 +1,762 @@
+//! SQLite-backed object, proof and receipt storage
+//!
+//! Everything the in-memory storage keeps in maps lives in one SQLite
+//! file instead, so objects, their history, proofs and receipts survive
+//! restarts. Each write or batch runs in one SQLite transaction, so a
+//! batch is applied in full or not at all.
+//!
+//! [`SqliteStorage`] also implements [`UnitsStorage`], with an in-memory
+//! lock manager, so code generic over storage can run on SQLite.
+
+use std::collections::{HashMap, VecDeque};
+use std::ops::{Bound, RangeBounds};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Params, Transaction};
+use units_core_types::error::StorageError;
+use units_core_types::id::UnitsObjectId;
+use units_core_types::objects::UnitsObject;
+use units_core_types::transaction::TransactionReceipt;
+use units_core_types::{BatchOp, HistoricalStorage, ObjectStorage, ProofStorage, ReceiptStorage, SlotReceiptsIter, UnitsStorage};
+use units_core_types::{SlotNumber, StateProof, UnitsObjectProof};
+use units_proofs::ProofEngine;
+
+use crate::consolidated_storage::{NoOpWriteAheadLog, DEFAULT_HISTORY_DEPTH};
+use crate::lock_manager::InMemoryLockManager;
+
+/// Objects fetched per query while iterating
+const PAGE_SIZE: i64 = 512;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS objects (
+        id BLOB PRIMARY KEY,
+        data BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS object_versions (
+        id BLOB NOT NULL,
+        slot INTEGER NOT NULL,
+        data BLOB,
+        PRIMARY KEY (id, slot)
+    );
+    CREATE TABLE IF NOT EXISTS proof_chain (
+        id BLOB NOT NULL,
+        seq INTEGER NOT NULL,
+        proof BLOB NOT NULL,
+        PRIMARY KEY (id, seq)
+    );
+    CREATE TABLE IF NOT EXISTS object_proofs (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        id BLOB NOT NULL,
+        slot INTEGER NOT NULL,
+        proof BLOB NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS object_proofs_by_id ON object_proofs (id, slot);
+    CREATE TABLE IF NOT EXISTS state_proofs (
+        slot INTEGER PRIMARY KEY,
+        proof BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS merkle_nodes (
+        hash BLOB PRIMARY KEY,
+        node BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS receipts (
+        tx_hash BLOB PRIMARY KEY,
+        slot INTEGER NOT NULL,
+        receipt BLOB NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS receipts_by_slot ON receipts (slot);
+    CREATE TABLE IF NOT EXISTS receipt_objects (
+        tx_hash BLOB NOT NULL REFERENCES receipts (tx_hash) ON DELETE CASCADE,
+        object_id BLOB NOT NULL,
+        PRIMARY KEY (tx_hash, object_id)
+    );
+    CREATE INDEX IF NOT EXISTS receipt_objects_by_object ON receipt_objects (object_id);
+";
+
+/// Storage keeping objects, their history, proofs and receipts in SQLite
+///
+/// Like [`InMemoryObjectStorage`](crate::InMemoryObjectStorage), writes
+/// through [`ObjectStorage`] keep their own proof chain, while
+/// [`ProofStorage`] holds the proofs handed to it separately.
+pub struct SqliteStorage {
+    connection: Mutex<Connection>,
+    history_depth: usize,
+    proof_engine: ProofEngine,
+    locks: InMemoryLockManager,
+}
+
+impl SqliteStorage {
+    /// Open (or create) storage in the database file at `path`
+    pub fn open(path: &Path) -> Result<Self, StorageError> {
+        Self::with_connection(Connection::open(path).map_err(sqlite_error)?, DEFAULT_HISTORY_DEPTH)
+    }
+
+    /// Storage that lives only as long as it is open, for tests
+    pub fn open_in_memory() -> Result<Self, StorageError> {
+        Self::with_connection(Connection::open_in_memory().map_err(sqlite_error)?, DEFAULT_HISTORY_DEPTH)
+    }
+
+    /// Open storage at `path` retaining at most `history_depth` versions per object
+    ///
+    /// A depth of zero keeps only the current state, disabling time travel.
+    pub fn open_with_history_depth(path: &Path, history_depth: usize) -> Result<Self, StorageError> {
+        Self::with_connection(Connection::open(path).map_err(sqlite_error)?, history_depth)
+    }
+
+    fn with_connection(connection: Connection, history_depth: usize) -> Result<Self, StorageError> {
+        connection
+            .execute_batch(&format!("PRAGMA foreign_keys = ON; {}", SCHEMA))
+            .map_err(sqlite_error)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+            history_depth,
+            proof_engine: ProofEngine::new(),
+            locks: InMemoryLockManager::new(),
+        })
+    }
+
+    /// Number of versions retained per object
+    pub fn history_depth(&self) -> usize {
+        self.history_depth
+    }
+
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap()
+    }
+
+    /// Apply one write inside `tx`, chaining its proof to the object's last
+    fn write(
+        &self,
+        tx: &Transaction<'_>,
+        op: &BatchOp,
+        transaction_hash: Option<[u8; 32]>,
+    ) -> Result<UnitsObjectProof, StorageError> {
+        let id = op.object_id();
+        let before: Option<UnitsObject> = select_one(tx, "SELECT data FROM objects WHERE id = ?1", params![id.bytes()])?;
+        let proved = match (op, &before) {
+            (BatchOp::Set(object), _) => object,
+            (BatchOp::Delete(_), Some(object)) => object,
+            (BatchOp::Delete(_), None) => {
+                return Err(StorageError::NotFound(format!("Object not found: {:?}", id)));
+            }
+        };
+
+        let (seq, prev_proof) = match latest_chain_proof(tx, &id)? {
+            Some((seq, proof)) => (seq + 1, Some(proof)),
+            None => (1, None),
+        };
+        let proof = self.proof_engine.generate_object_proof(proved, prev_proof.as_ref(), transaction_hash)?;
+
+        let state = match op {
+            BatchOp::Set(object) => Some(encode(object)?),
+            BatchOp::Delete(_) => None,
+        };
+        match &state {
+            Some(data) => tx.execute(
+                "INSERT INTO objects (id, data) VALUES (?1, ?2)
+                 ON CONFLICT (id) DO UPDATE SET data = excluded.data",
+                params![id.bytes(), data],
+            ),
+            None => tx.execute("DELETE FROM objects WHERE id = ?1", params![id.bytes()]),
+        }
+        .map_err(sqlite_error)?;
+
+        self.record_version(tx, &id, proof.slot, state)?;
+        tx.execute(
+            "INSERT INTO proof_chain (id, seq, proof) VALUES (?1, ?2, ?3)",
+            params![id.bytes(), seq, encode(&proof)?],
+        )
+        .map_err(sqlite_error)?;
+        Ok(proof)
+    }
+
+    /// Record the state of an object at a slot, evicting the oldest versions
+    /// beyond the configured depth
+    fn record_version(
+        &self,
+        tx: &Transaction<'_>,
+        id: &UnitsObjectId,
+        slot: SlotNumber,
+        state: Option<Vec<u8>>,
+    ) -> Result<(), StorageError> {
+        if self.history_depth == 0 {
+            return Ok(());
+        }
+
+        tx.execute(
+            "INSERT INTO object_versions (id, slot, data) VALUES (?1, ?2, ?3)
+             ON CONFLICT (id, slot) DO UPDATE SET data = excluded.data",
+            params![id.bytes(), to_sql_slot(slot), state],
+        )
+        .map_err(sqlite_error)?;
+        tx.execute(
+            "DELETE FROM object_versions WHERE id = ?1 AND slot < (
+                 SELECT MIN(slot) FROM (
+                     SELECT slot FROM object_versions WHERE id = ?1 ORDER BY slot DESC LIMIT ?2
+                 )
+             )",
+            params![id.bytes(), self.history_depth as i64],
+        )
+        .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    /// Apply `ops` in one SQLite transaction, returning the proof of the
+    /// last write to each object
+    fn apply(&self, ops: &[BatchOp], transaction_hash: Option<[u8; 32]>) -> Result<HashMap<UnitsObjectId, UnitsObjectProof>, StorageError> {
+        let mut connection = self.connection();
+        let tx = connection.transaction().map_err(sqlite_error)?;
+        let mut proofs = HashMap::new();
+        for op in ops {
+            let proof = self.write(&tx, op, transaction_hash)?;
+            proofs.insert(op.object_id(), proof);
+        }
+        tx.commit().map_err(sqlite_error)?;
+        Ok(proofs)
+    }
+
+    /// Slots in `[start_slot, end_slot]` with a row in `table`, ascending
+    fn slots_in(&self, table: &str, start_slot: SlotNumber, end_slot: SlotNumber) -> Result<Vec<SlotNumber>, StorageError> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare(&format!("SELECT DISTINCT slot FROM {table} WHERE slot BETWEEN ?1 AND ?2 ORDER BY slot"))
+            .map_err(sqlite_error)?;
+        let slots = statement
+            .query_map(params![to_sql_slot(start_slot), to_sql_slot(end_slot)], |row| row.get::<_, i64>(0))
+            .map_err(sqlite_error)?
+            .map(|slot| slot.map(from_sql_slot).map_err(sqlite_error))
+            .collect::<Result<_, _>>()?;
+        Ok(slots)
+    }
+}
+
+/// Decode the single value `query` returns, if any
+fn select_one<T: serde::de::DeserializeOwned>(
+    connection: &Connection,
+    query: &str,
+    params: impl Params,
+) -> Result<Option<T>, StorageError> {
+    connection
+        .query_row(query, params, |row| row.get::<_, Vec<u8>>(0))
+        .optional()
+        .map_err(sqlite_error)?
+        .map(|bytes| decode(&bytes))
+        .transpose()
+}
+
+/// Decode every value `query` returns, in order
+fn select_all<T: serde::de::DeserializeOwned>(
+    connection: &Connection,
+    query: &str,
+    params: impl Params,
+) -> Result<Vec<T>, StorageError> {
+    let mut statement = connection.prepare(query).map_err(sqlite_error)?;
+    let rows = statement
+        .query_map(params, |row| row.get::<_, Vec<u8>>(0))
+        .map_err(sqlite_error)?;
+    rows.map(|bytes| decode(&bytes.map_err(sqlite_error)?)).collect()
+}
+
+/// Decode the `(slot, value)` pairs `query` returns, in order
+fn select_slotted<T: serde::de::DeserializeOwned>(
+    connection: &Connection,
+    query: &str,
+    params: impl Params,
+) -> Result<Vec<(SlotNumber, T)>, StorageError> {
+    let mut statement = connection.prepare(query).map_err(sqlite_error)?;
+    let rows = statement
+        .query_map(params, |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?)))
+        .map_err(sqlite_error)?;
+    rows.map(|row| {
+        let (slot, bytes) = row.map_err(sqlite_error)?;
+        Ok((from_sql_slot(slot), decode(&bytes)?))
+    })
+    .collect()
+}
+
+/// Last proof in the chain of `id` with its sequence number
+fn latest_chain_proof(connection: &Connection, id: &UnitsObjectId) -> Result<Option<(i64, UnitsObjectProof)>, StorageError> {
+    connection
+        .query_row(
+            "SELECT seq, proof FROM proof_chain WHERE id = ?1 ORDER BY seq DESC LIMIT 1",
+            params![id.bytes()],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?)),
+        )
+        .optional()
+        .map_err(sqlite_error)?
+        .map(|(seq, bytes)| Ok((seq, decode(&bytes)?)))
+        .transpose()
+}
+
+impl ObjectStorage for SqliteStorage {
+    fn get(&self, id: &UnitsObjectId) -> Result<Option<UnitsObject>, StorageError> {
+        select_one(&self.connection(), "SELECT data FROM objects WHERE id = ?1", params![id.bytes()])
+    }
+
+    fn set(&self, object: &UnitsObject, transaction_hash: Option<[u8; 32]>) -> Result<UnitsObjectProof, StorageError> {
+        let op = BatchOp::Set(object.clone());
+        Ok(self.apply(std::slice::from_ref(&op), transaction_hash)?.remove(object.id()).expect("write was proved"))
+    }
+
+    fn delete(&self, id: &UnitsObjectId, transaction_hash: Option<[u8; 32]>) -> Result<UnitsObjectProof, StorageError> {
+        Ok(self.apply(&[BatchOp::Delete(*id)], transaction_hash)?.remove(id).expect("write was proved"))
+    }
+
+    /// Applied in one SQLite transaction, so readers see the batch either
+    /// not at all or in full
+    fn apply_batch(
+        &self,
+        ops: &[BatchOp],
+        transaction_hash: [u8; 32],
+    ) -> Result<HashMap<UnitsObjectId, UnitsObjectProof>, StorageError> {
+        self.apply(ops, Some(transaction_hash))
+    }
+
+    fn version(&self, id: &UnitsObjectId) -> Result<u64, StorageError> {
+        let version: i64 = self
+            .connection()
+            .query_row("SELECT COALESCE(MAX(seq), 0) FROM proof_chain WHERE id = ?1", params![id.bytes()], |row| row.get(0))
+            .map_err(sqlite_error)?;
+        Ok(version as u64)
+    }
+
+    fn exists(&self, id: &UnitsObjectId) -> Result<bool, StorageError> {
+        self.connection()
+            .query_row("SELECT EXISTS (SELECT 1 FROM objects WHERE id = ?1)", params![id.bytes()], |row| row.get(0))
+            .map_err(sqlite_error)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_> {
+        self.iter_range(..)
+    }
+
+    /// Objects are fetched a page at a time, seeking past the last ID seen
+    fn iter_range<R>(&self, range: R) -> Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_>
+    where
+        R: RangeBounds<UnitsObjectId>,
+    {
+        Box::new(ObjectPages {
+            storage: self,
+            start: range.start_bound().cloned(),
+            end: range.end_bound().cloned(),
+            page: VecDeque::new(),
+            done: false,
+        })
+    }
+}
+
+/// Objects in an ID range, fetched [`PAGE_SIZE`] at a time
+struct ObjectPages<'a> {
+    storage: &'a SqliteStorage,
+    start: Bound<UnitsObjectId>,
+    end: Bound<UnitsObjectId>,
+    page: VecDeque<UnitsObject>,
+    done: bool,
+}
+
+impl ObjectPages<'_> {
+    fn fetch(&mut self) -> Result<(), StorageError> {
+        let mut query = String::from("SELECT data FROM objects WHERE 1");
+        let mut bounds = Vec::new();
+        for (bound, inclusive, exclusive) in [(&self.start, ">=", ">"), (&self.end, "<=", "<")] {
+            let (op, id) = match bound {
+                Bound::Included(id) => (inclusive, id),
+                Bound::Excluded(id) => (exclusive, id),
+                Bound::Unbounded => continue,
+            };
+            bounds.push(Value::Blob(id.bytes().to_vec()));
+            query.push_str(&format!(" AND id {op} ?{}", bounds.len()));
+        }
+        query.push_str(&format!(" ORDER BY id LIMIT {PAGE_SIZE}"));
+
+        let objects: Vec<UnitsObject> = select_all(&self.storage.connection(), &query, params_from_iter(bounds))?;
+        self.done = (objects.len() as i64) < PAGE_SIZE;
+        if let Some(last) = objects.last() {
+            self.start = Bound::Excluded(last.id);
+        }
+        self.page.extend(objects);
+        Ok(())
+    }
+}
+
+impl Iterator for ObjectPages<'_> {
+    type Item = Result<UnitsObject, StorageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.page.is_empty() && !self.done {
+            if let Err(error) = self.fetch() {
+                self.done = true;
+                return Some(Err(error));
+            }
+        }
+        self.page.pop_front().map(Ok)
+    }
+}
+
+impl HistoricalStorage for SqliteStorage {
+    fn get_at_slot(&self, id: &UnitsObjectId, slot: SlotNumber) -> Result<Option<UnitsObject>, StorageError> {
+        // The state at a slot is the latest version written at or before it
+        let data: Option<Option<Vec<u8>>> = self
+            .connection()
+            .query_row(
+                "SELECT data FROM object_versions WHERE id = ?1 AND slot <= ?2 ORDER BY slot DESC LIMIT 1",
+                params![id.bytes(), to_sql_slot(slot)],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+        data.flatten().map(|data| decode(&data)).transpose()
+    }
+
+    fn get_history(
+        &self,
+        id: &UnitsObjectId,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+    ) -> Result<Vec<(SlotNumber, UnitsObject)>, StorageError> {
+        select_slotted(
+            &self.connection(),
+            "SELECT slot, data FROM object_versions
+             WHERE id = ?1 AND slot BETWEEN ?2 AND ?3 AND data IS NOT NULL ORDER BY slot",
+            params![id.bytes(), to_sql_slot(start_slot), to_sql_slot(end_slot)],
+        )
+    }
+
+    fn compact_history(&self, before_slot: SlotNumber) -> Result<usize, StorageError> {
+        // Keep the newest version before the cutoff so lookups at or after
+        // `before_slot` still resolve to the state in effect at that time,
+        // unless it is a deletion with nothing after it
+        self.connection()
+            .execute(
+                "WITH base AS (
+                     SELECT id, MAX(slot) AS slot FROM object_versions WHERE slot < ?1 GROUP BY id
+                 )
+                 DELETE FROM object_versions WHERE EXISTS (
+                     SELECT 1 FROM base WHERE base.id = object_versions.id AND (
+                         object_versions.slot < base.slot
+                         OR (object_versions.slot = base.slot AND object_versions.data IS NULL AND NOT EXISTS (
+                             SELECT 1 FROM object_versions AS newer
+                             WHERE newer.id = object_versions.id AND newer.slot >= ?1
+                         ))
+                     )
+                 )",
+                params![to_sql_slot(before_slot)],
+            )
+            .map_err(sqlite_error)
+    }
+}
+
+impl ProofStorage for SqliteStorage {
+    fn store_object_proof(&self, proof: &UnitsObjectProof) -> Result<(), StorageError> {
+        self.connection()
+            .execute(
+                "INSERT INTO object_proofs (id, slot, proof) VALUES (?1, ?2, ?3)",
+                params![proof.object_id.bytes(), to_sql_slot(proof.slot), encode(proof)?],
+            )
+            .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    fn get_latest_proof(&self, id: &UnitsObjectId) -> Result<Option<UnitsObjectProof>, StorageError> {
+        select_one(
+            &self.connection(),
+            "SELECT proof FROM object_proofs WHERE id = ?1 ORDER BY slot DESC, seq DESC LIMIT 1",
+            params![id.bytes()],
+        )
+    }
+
+    fn get_proof_history(
+        &self,
+        id: &UnitsObjectId,
+        start_slot: Option<SlotNumber>,
+        end_slot: Option<SlotNumber>,
+    ) -> Result<Vec<(SlotNumber, UnitsObjectProof)>, StorageError> {
+        select_slotted(
+            &self.connection(),
+            "SELECT slot, proof FROM object_proofs WHERE id = ?1 AND slot >= ?2 AND slot <= ?3 ORDER BY seq",
+            params![
+                id.bytes(),
+                start_slot.map_or(i64::MIN, to_sql_slot),
+                end_slot.map_or(i64::MAX, to_sql_slot),
+            ],
+        )
+    }
+
+    fn store_state_proof(&self, proof: &StateProof) -> Result<(), StorageError> {
+        self.connection()
+            .execute(
+                "INSERT INTO state_proofs (slot, proof) VALUES (?1, ?2)
+                 ON CONFLICT (slot) DO UPDATE SET proof = excluded.proof",
+                params![to_sql_slot(proof.slot), encode(proof)?],
+            )
+            .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    fn get_state_proof(&self, slot: SlotNumber) -> Result<Option<StateProof>, StorageError> {
+        select_one(&self.connection(), "SELECT proof FROM state_proofs WHERE slot = ?1", params![to_sql_slot(slot)])
+    }
+
+    fn get_state_proof_history(&self, start_slot: SlotNumber, end_slot: SlotNumber) -> Result<Vec<StateProof>, StorageError> {
+        select_all(
+            &self.connection(),
+            "SELECT proof FROM state_proofs WHERE slot BETWEEN ?1 AND ?2 ORDER BY slot",
+            params![to_sql_slot(start_slot), to_sql_slot(end_slot)],
+        )
+    }
+
+    fn iter_state_proofs(
+        &self,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+    ) -> Box<dyn Iterator<Item = Result<StateProof, StorageError>> + '_> {
+        // Only the slot numbers are collected up front; proofs are loaded per slot
+        match self.slots_in("state_proofs", start_slot, end_slot) {
+            Ok(slots) => Box::new(slots.into_iter().filter_map(move |slot| self.get_state_proof(slot).transpose())),
+            Err(error) => Box::new(std::iter::once(Err(error))),
+        }
+    }
+
+    fn store_merkle_node(&self, hash: &[u8; 32], node: &[u8]) -> Result<(), StorageError> {
+        self.connection()
+            .execute(
+                "INSERT OR IGNORE INTO merkle_nodes (hash, node) VALUES (?1, ?2)",
+                params![hash.as_slice(), node],
+            )
+            .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    fn get_merkle_node(&self, hash: &[u8; 32]) -> Result<Option<Vec<u8>>, StorageError> {
+        self.connection()
+            .query_row("SELECT node FROM merkle_nodes WHERE hash = ?1", params![hash.as_slice()], |row| row.get(0))
+            .optional()
+            .map_err(sqlite_error)
+    }
+}
+
+impl ReceiptStorage for SqliteStorage {
+    fn store_receipt(&self, receipt: &TransactionReceipt) -> Result<(), StorageError> {
+        let mut connection = self.connection();
+        let tx = connection.transaction().map_err(sqlite_error)?;
+        let tx_hash = receipt.transaction_hash.as_slice();
+        tx.execute(
+            "INSERT INTO receipts (tx_hash, slot, receipt) VALUES (?1, ?2, ?3)
+             ON CONFLICT (tx_hash) DO UPDATE SET slot = excluded.slot, receipt = excluded.receipt",
+            params![tx_hash, to_sql_slot(receipt.slot), encode(receipt)?],
+        )
+        .map_err(sqlite_error)?;
+
+        // The receipt affects the objects it proves or changes
+        tx.execute("DELETE FROM receipt_objects WHERE tx_hash = ?1", params![tx_hash])
+            .map_err(sqlite_error)?;
+        let affected = receipt.object_proofs.keys().chain(receipt.effects.iter().map(|effect| &effect.object_id));
+        for object_id in affected {
+            tx.execute(
+                "INSERT OR IGNORE INTO receipt_objects (tx_hash, object_id) VALUES (?1, ?2)",
+                params![tx_hash, object_id.bytes()],
+            )
+            .map_err(sqlite_error)?;
+        }
+        tx.commit().map_err(sqlite_error)
+    }
+
+    fn get_receipt(&self, tx_hash: &[u8; 32]) -> Result<Option<TransactionReceipt>, StorageError> {
+        select_one(&self.connection(), "SELECT receipt FROM receipts WHERE tx_hash = ?1", params![tx_hash.as_slice()])
+    }
+
+    fn get_receipts_for_slot(&self, slot: SlotNumber) -> Result<Vec<TransactionReceipt>, StorageError> {
+        select_all(
+            &self.connection(),
+            "SELECT receipt FROM receipts WHERE slot = ?1 ORDER BY tx_hash",
+            params![to_sql_slot(slot)],
+        )
+    }
+
+    fn get_receipts_range(&self, start_slot: SlotNumber, end_slot: SlotNumber) -> Result<Vec<TransactionReceipt>, StorageError> {
+        select_all(
+            &self.connection(),
+            "SELECT receipt FROM receipts WHERE slot BETWEEN ?1 AND ?2 ORDER BY slot, tx_hash",
+            params![to_sql_slot(start_slot), to_sql_slot(end_slot)],
+        )
+    }
+
+    fn iter_receipts_by_slot(&self, start_slot: SlotNumber, end_slot: SlotNumber) -> SlotReceiptsIter<'_> {
+        // Only the slot numbers are collected up front; receipts are decoded per slot
+        let slots = match self.slots_in("receipts", start_slot, end_slot) {
+            Ok(slots) => slots,
+            Err(error) => return Box::new(std::iter::once(Err(error))),
+        };
+        Box::new(slots.into_iter().filter_map(move |slot| match self.get_receipts_for_slot(slot) {
+            Ok(receipts) if receipts.is_empty() => None,
+            Ok(receipts) => Some(Ok((slot, receipts))),
+            Err(error) => Some(Err(error)),
+        }))
+    }
+
+    fn get_receipts_for_object(
+        &self,
+        object_id: &UnitsObjectId,
+        start_slot: Option<SlotNumber>,
+        end_slot: Option<SlotNumber>,
+    ) -> Result<Vec<TransactionReceipt>, StorageError> {
+        select_all(
+            &self.connection(),
+            "SELECT receipts.receipt FROM receipts
+             JOIN receipt_objects ON receipt_objects.tx_hash = receipts.tx_hash
+             WHERE receipt_objects.object_id = ?1 AND receipts.slot >= ?2 AND receipts.slot <= ?3
+             ORDER BY receipts.slot, receipts.tx_hash",
+            params![
+                object_id.bytes(),
+                start_slot.map_or(i64::MIN, to_sql_slot),
+                end_slot.map_or(i64::MAX, to_sql_slot),
+            ],
+        )
+    }
+
+    fn cleanup_receipts_before(&self, slot: SlotNumber) -> Result<usize, StorageError> {
+        self.connection()
+            .execute("DELETE FROM receipts WHERE slot < ?1", params![to_sql_slot(slot)])
+            .map_err(sqlite_error)
+    }
+}
+
+impl UnitsStorage for SqliteStorage {
+    type Objects = Self;
+    type Historical = Self;
+    type Proofs = Self;
+    type WAL = NoOpWriteAheadLog;
+    type Receipts = Self;
+    type Locks = InMemoryLockManager;
+
+    fn objects(&self) -> &Self::Objects {
+        self
+    }
+
+    fn historical(&self) -> &Self::Historical {
+        self
+    }
+
+    fn proofs(&self) -> &Self::Proofs {
+        self
+    }
+
+    /// Writes are durable once committed, so no log is kept
+    fn wal(&self) -> Option<&Self::WAL> {
+        None
+    }
+
+    fn receipts(&self) -> &Self::Receipts {
+        self
+    }
+
+    fn locks(&self) -> &Self::Locks {
+        &self.locks
+    }
+}
+
+fn encode<T: serde::Serialize>(value: &T) -> Result<Vec<u8>, StorageError> {
+    Ok(bincode::serialize(value)?)
+}
+
+fn decode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> Result<T, StorageError> {
+    Ok(bincode::deserialize(bytes)?)
+}
+
+/// Slots are stored as INTEGER, clamped to its range
+fn to_sql_slot(slot: SlotNumber) -> i64 {
+    i64::try_from(slot).unwrap_or(i64::MAX)
+}
+
+fn from_sql_slot(slot: i64) -> SlotNumber {
+    slot.max(0) as SlotNumber
+}
+
+fn sqlite_error(error: rusqlite::Error) -> StorageError {
+    StorageError::Database(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use units_core_types::transaction::TransactionEffect;
+
+    fn object(byte: u8, data: u8) -> UnitsObject {
+        UnitsObject::new_data(UnitsObjectId::new([byte; 32]), UnitsObjectId::new([0xC0; 32]), vec![data])
+    }
+
+    #[test]
+    fn test_sqlite_storage_round_trip() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+
+        // Writes chain their proofs and bump the version
+        let first = storage.set(&object(1, 1), None).unwrap();
+        let second = storage.set(&object(1, 2), None).unwrap();
+        assert_eq!(second.prev_proof_hash, Some(first.hash()));
+        assert_eq!(storage.get(&object(1, 0).id).unwrap(), Some(object(1, 2)));
+        assert_eq!(storage.version(&object(1, 0).id).unwrap(), 2);
+        assert_eq!(storage.get_at_slot(&object(1, 0).id, second.slot).unwrap(), Some(object(1, 2)));
+
+        // A failing batch leaves storage as it was
+        let batch = [BatchOp::Set(object(2, 1)), BatchOp::Delete(object(3, 0).id)];
+        assert!(matches!(storage.apply_batch(&batch, [7; 32]), Err(StorageError::NotFound(_))));
+        assert!(!storage.exists(&object(2, 0).id).unwrap());
+        let batch = [BatchOp::Set(object(2, 1)), BatchOp::Set(object(3, 1)), BatchOp::Delete(object(1, 0).id)];
+        let proofs = storage.apply_batch(&batch, [7; 32]).unwrap();
+        assert_eq!(proofs.len(), 3);
+        assert!(storage.get(&object(1, 0).id).unwrap().is_none());
+        assert_eq!(storage.version(&object(1, 0).id).unwrap(), 3);
+
+        let ids: Vec<_> = storage.iter().map(|object| object.unwrap().id).collect();
+        assert_eq!(ids, vec![object(2, 0).id, object(3, 0).id]);
+        assert_eq!(storage.iter_range(object(3, 0).id..).count(), 1);
+
+        // Compaction drops the trailing deletion and keeps live versions
+        assert_eq!(storage.compact_history(SlotNumber::MAX).unwrap(), 1);
+        assert_eq!(storage.get_history(&object(2, 0).id, 0, SlotNumber::MAX).unwrap().len(), 1);
+
+        // Proofs and receipts round-trip
+        storage.store_object_proof(&proofs[&object(2, 0).id]).unwrap();
+        let latest = storage.get_latest_proof(&object(2, 0).id).unwrap().unwrap();
+        assert_eq!(latest.hash(), proofs[&object(2, 0).id].hash());
+        let mut receipt = TransactionReceipt::new([7; 32], 5, true, 1_700_000_000);
+        receipt.add_effect(TransactionEffect::new_creation([7; 32], object(2, 1)));
+        storage.store_receipt(&receipt).unwrap();
+        let stored = storage.get_receipt(&[7; 32]).unwrap().unwrap();
+        assert_eq!(bincode::serialize(&stored).unwrap(), bincode::serialize(&receipt).unwrap());
+        assert_eq!(storage.get_receipts_for_object(&object(2, 0).id, None, Some(5)).unwrap().len(), 1);
+        assert!(storage.get_receipts_for_object(&object(3, 0).id, None, None).unwrap().is_empty());
+        let page = storage.get_receipts_for_object_page(&object(2, 0).id, None, None, None, 1).unwrap();
+        assert_eq!((page.receipts.len(), page.next), (1, None));
+        let page = storage.get_receipts_range_page(0, 10, Some([7; 32]), 1).unwrap();
+        assert!(page.receipts.is_empty());
+        assert_eq!(storage.cleanup_receipts_before(6).unwrap(), 1);
+        assert!(matches!(storage.get_receipts_range_page(0, 10, Some([7; 32]), 1), Err(StorageError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_sqlite_storage_survives_reopening() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("units.db");
+        let (proof, root) = {
+            let storage = SqliteStorage::open(&path).unwrap();
+            let mut tree = units_proofs::SparseMerkleTree::new(&storage);
+            tree.insert(&object(1, 0).id, [9; 32]).unwrap();
+            (storage.set(&object(1, 1), Some([1; 32])).unwrap(), tree.root())
+        };
+
+        // Objects and their proof chain are still there after a restart
+        let storage = SqliteStorage::open(&path).unwrap();
+        assert_eq!(storage.objects().get(&object(1, 0).id).unwrap(), Some(object(1, 1)));
+        let next = storage.set(&object(1, 2), None).unwrap();
+        assert_eq!(next.prev_proof_hash, Some(proof.hash()));
+
+        // So is the sparse Merkle tree, reachable from its root
+        let tree = units_proofs::SparseMerkleTree::open(&storage, root);
+        assert_eq!(tree.get(&object(1, 0).id).unwrap(), Some([9; 32]));
+    }
+}
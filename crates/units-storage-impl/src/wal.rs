@@ -1,19 +1,29 @@
 //! Write-Ahead Log Implementation
 //! 
 //! Provides concrete implementations of the WriteAheadLog trait for durability.
+//! How soon appended entries reach stable storage is set by `WalDurability`.
 
-use units_core_types::WriteAheadLog;
+use units_core_types::{WalTail, WriteAheadLog};
 use bincode;
 use serde::{Deserialize, Serialize};
 use std::fs::{File, OpenOptions};
 use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use units_core_types::error::StorageError;
 use units_core_types::objects::UnitsObject;
 use units_core_types::{StateProof, UnitsObjectProof, SlotNumber};
 
+use crate::codec::{Codec, CodecConfig};
+
+/// Bits of the length word holding the record's codec id
+///
+/// Records written before compression existed have a zero top byte and so
+/// read back as uncompressed.
+const CODEC_SHIFT: u32 = 56;
+
 /// WAL entry for object updates
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WALEntry {
@@ -33,12 +43,33 @@ pub enum WALEntryType {
     StateProof(StateProof),
 }
 
+/// When appended entries are forced to stable storage
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum WalDurability {
+    /// Fsync every entry before its write returns
+    #[default]
+    Always,
+    /// Fsync the entries appended since the last sync every `interval_ms`
+    ///
+    /// A crash loses at most the last interval's entries.
+    Group { interval_ms: u64 },
+    /// Hand entries to the OS and leave flushing them to disk to it
+    Async,
+}
+
 /// A basic file-based write-ahead log implementation
 pub struct FileWriteAheadLog {
     /// Path to the WAL file
     path: Arc<Mutex<PathBuf>>,
     /// File handle for writing
     file: Arc<Mutex<Option<BufWriter<File>>>>,
+    /// Compression of appended entries
+    codec: CodecConfig,
+    /// When appended entries are fsynced
+    durability: WalDurability,
+    /// Whether entries were appended since the last fsync
+    unsynced: Arc<AtomicBool>,
 }
 
 impl FileWriteAheadLog {
@@ -47,7 +78,66 @@ impl FileWriteAheadLog {
         Self {
             path: Arc::new(Mutex::new(PathBuf::new())),
             file: Arc::new(Mutex::new(None)),
+            codec: CodecConfig::default(),
+            durability: WalDurability::default(),
+            unsynced: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Fsync appended entries according to `durability`
+    ///
+    /// Group commit runs on a background thread that ends with the log.
+    pub fn with_durability(mut self, durability: WalDurability) -> Self {
+        self.durability = durability;
+        if let WalDurability::Group { interval_ms } = durability {
+            self.spawn_group_sync(Duration::from_millis(interval_ms.max(1)));
+        }
+        self
+    }
+
+    /// When appended entries are fsynced
+    pub fn durability(&self) -> WalDurability {
+        self.durability
+    }
+
+    /// Force every appended entry to stable storage now
+    pub fn sync(&self) -> Result<(), StorageError> {
+        let mut file_guard = self
+            .file
+            .lock()
+            .map_err(|e| StorageError::WAL(format!("Failed to acquire lock: {}", e)))?;
+        if let Some(file) = file_guard.as_mut() {
+            file.flush()?;
+            file.get_ref().sync_data()?;
+            self.unsynced.store(false, Ordering::Release);
         }
+        Ok(())
+    }
+
+    /// Fsync entries appended since the last pass every `interval`
+    fn spawn_group_sync(&self, interval: Duration) {
+        let file = Arc::downgrade(&self.file);
+        let unsynced = self.unsynced.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+            let Some(file) = file.upgrade() else {
+                break;
+            };
+            if !unsynced.swap(false, Ordering::AcqRel) {
+                continue;
+            }
+            let file_guard = file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if let Some(Err(e)) = file_guard.as_ref().map(|file| file.get_ref().sync_data()) {
+                log::warn!("WAL group sync failed: {}", e);
+                unsynced.store(true, Ordering::Release);
+            }
+        });
+    }
+
+    /// Compress entries appended from now on according to `codec`
+    pub fn with_codec(mut self, codec: CodecConfig) -> Self {
+        self.codec = codec;
+        self
     }
     
     /// Initialize the WAL with a file path
@@ -91,18 +181,62 @@ impl FileWriteAheadLog {
             .as_mut()
             .ok_or_else(|| StorageError::WAL("WAL has not been initialized".to_string()))?;
 
-        // Serialize the entry
-        let serialized = bincode::serialize(entry)?;
+        // Serialize and compress the entry
+        let (codec, serialized) = self.codec.encode(bincode::serialize(entry)?)?;
 
-        // Write the entry length and data
-        let entry_len = serialized.len() as u64;
+        // Write the codec-tagged entry length and data
+        let entry_len = serialized.len() as u64 | (codec.id() as u64) << CODEC_SHIFT;
         file.write_all(&entry_len.to_le_bytes())?;
         file.write_all(&serialized)?;
         file.flush()?;
 
+        match self.durability {
+            WalDurability::Always => file.get_ref().sync_data()?,
+            WalDurability::Group { .. } => self.unsynced.store(true, Ordering::Release),
+            WalDurability::Async => {}
+        }
+
         Ok(())
     }
     
+    /// Visit every entry in the log, oldest first
+    fn read_entries<F>(&self, mut visit: F) -> Result<(), StorageError>
+    where
+        F: FnMut(WALEntryType) -> Result<(), StorageError>,
+    {
+        let path_guard = self.path.lock()
+            .map_err(|e| StorageError::WAL(format!("Failed to acquire path lock: {}", e)))?;
+        let path = path_guard.clone();
+        drop(path_guard);
+
+        let file = File::open(&path)
+            .map_err(|e| StorageError::WAL(format!("Failed to open WAL file: {}", e)))?;
+        let mut reader = BufReader::new(file);
+
+        loop {
+            // Read the entry length
+            let mut len_buf = [0u8; 8];
+            match reader.read_exact(&mut len_buf) {
+                Ok(_) => {},
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(StorageError::from(e)),
+            }
+
+            let len_word = u64::from_le_bytes(len_buf);
+            let codec = Codec::from_id((len_word >> CODEC_SHIFT) as u8)?;
+            let entry_len = len_word & ((1 << CODEC_SHIFT) - 1);
+
+            // Read the entry data
+            let mut entry_data = vec![0u8; entry_len as usize];
+            reader.read_exact(&mut entry_data)?;
+
+            // Decompress and deserialize the entry
+            visit(bincode::deserialize(&codec.decompress(&entry_data)?)?)?;
+        }
+
+        Ok(())
+    }
+
     /// Get the current timestamp in milliseconds
     fn current_timestamp() -> u64 {
         SystemTime::now()
@@ -118,6 +252,17 @@ impl Default for FileWriteAheadLog {
     }
 }
 
+impl Drop for FileWriteAheadLog {
+    fn drop(&mut self) {
+        // Group commit may still hold entries from its last interval
+        if self.unsynced.load(Ordering::Acquire) {
+            if let Err(e) = self.sync() {
+                log::warn!("WAL sync on close failed: {}", e);
+            }
+        }
+    }
+}
+
 impl WriteAheadLog for FileWriteAheadLog {
     fn record_update(
         &self,
@@ -144,40 +289,85 @@ impl WriteAheadLog for FileWriteAheadLog {
     where
         F: FnMut(&UnitsObject, &UnitsObjectProof) -> Result<(), StorageError>,
     {
-        let path_guard = self.path.lock()
-            .map_err(|e| StorageError::WAL(format!("Failed to acquire path lock: {}", e)))?;
-        let path = path_guard.clone();
-        drop(path_guard);
-
-        let file = File::open(&path)
-            .map_err(|e| StorageError::WAL(format!("Failed to open WAL file: {}", e)))?;
-        let mut reader = BufReader::new(file);
-
-        loop {
-            // Read the entry length
-            let mut len_buf = [0u8; 8];
-            match reader.read_exact(&mut len_buf) {
-                Ok(_) => {},
-                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
-                Err(e) => return Err(StorageError::from(e)),
+        self.read_entries(|entry_type| {
+            // Only replay object updates
+            match entry_type {
+                WALEntryType::ObjectUpdate(entry) => callback(&entry.object, &entry.proof),
+                WALEntryType::StateProof(_) => Ok(()),
             }
+        })
+    }
 
-            let entry_len = u64::from_le_bytes(len_buf);
+    fn entry_count(&self) -> Result<usize, StorageError> {
+        let mut count = 0;
+        self.read_entries(|_| {
+            count += 1;
+            Ok(())
+        })?;
+        Ok(count)
+    }
 
-            // Read the entry data
-            let mut entry_data = vec![0u8; entry_len as usize];
-            reader.read_exact(&mut entry_data)?;
+    fn truncate(&self) -> Result<usize, StorageError> {
+        // Hold the writer so no entry lands between counting and truncating
+        let mut file_guard = self
+            .file
+            .lock()
+            .map_err(|e| StorageError::WAL(format!("Failed to acquire lock: {}", e)))?;
+        let file = file_guard
+            .as_mut()
+            .ok_or_else(|| StorageError::WAL("WAL has not been initialized".to_string()))?;
+        file.flush()?;
 
-            // Deserialize the entry
-            let entry_type: WALEntryType = bincode::deserialize(&entry_data)?;
+        let removed = self.entry_count()?;
+        file.get_ref().set_len(0)?;
+        Ok(removed)
+    }
 
-            // Only replay object updates
-            if let WALEntryType::ObjectUpdate(entry) = entry_type {
-                callback(&entry.object, &entry.proof)?;
+    fn check_tail(&self, repair: bool) -> Result<WalTail, StorageError> {
+        // Hold the writer so the tail cannot move while it is checked
+        let mut file_guard = self
+            .file
+            .lock()
+            .map_err(|e| StorageError::WAL(format!("Failed to acquire lock: {}", e)))?;
+        let file = file_guard
+            .as_mut()
+            .ok_or_else(|| StorageError::WAL("WAL has not been initialized".to_string()))?;
+        file.flush()?;
+
+        let path = self.path.lock()
+            .map_err(|e| StorageError::WAL(format!("Failed to acquire path lock: {}", e)))?
+            .clone();
+        let bytes = std::fs::read(&path)
+            .map_err(|e| StorageError::WAL(format!("Failed to read WAL file: {}", e)))?;
+
+        let mut tail = WalTail::default();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let Some(len_buf) = bytes.get(offset..offset + 8) else {
+                break;
+            };
+            let len_word = u64::from_le_bytes(len_buf.try_into().expect("eight bytes"));
+            let entry_len = (len_word & ((1 << CODEC_SHIFT) - 1)) as usize;
+            let Some(entry_data) = bytes.get(offset + 8..).and_then(|rest| rest.get(..entry_len)) else {
+                break;
+            };
+
+            let decoded = Codec::from_id((len_word >> CODEC_SHIFT) as u8)
+                .and_then(|codec| codec.decompress(entry_data))
+                .and_then(|data| Ok(bincode::deserialize::<WALEntryType>(&data)?));
+            if let Err(e) = decoded {
+                return Err(StorageError::WAL(format!("Corrupt WAL entry at byte {}: {}", offset, e)));
             }
+            tail.entries += 1;
+            offset += 8 + entry_len;
         }
 
-        Ok(())
+        tail.torn_bytes = (bytes.len() - offset) as u64;
+        if repair && tail.torn_bytes > 0 {
+            file.get_ref().set_len(offset as u64)?;
+            tail.truncated = true;
+        }
+        Ok(tail)
     }
 }
 
@@ -240,4 +430,111 @@ mod tests {
         assert_eq!(entries[0].0.id(), obj1.id());
         assert_eq!(entries[1].0.id(), obj2.id());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_wal_truncate() {
+        let temp_dir = tempdir().unwrap();
+        let wal_path = temp_dir.path().join("test.wal");
+
+        let wal = FileWriteAheadLog::new();
+        wal.init(&wal_path).unwrap();
+        wal.record_update(&create_test_object(), &create_test_proof(), None).unwrap();
+        wal.record_update(&create_test_object(), &create_test_proof(), None).unwrap();
+        assert_eq!(wal.entry_count().unwrap(), 2);
+
+        assert_eq!(wal.truncate().unwrap(), 2);
+        assert_eq!(wal.entry_count().unwrap(), 0);
+
+        // Appends continue into the emptied log
+        let obj = create_test_object();
+        wal.record_update(&obj, &create_test_proof(), None).unwrap();
+        let mut replayed = Vec::new();
+        wal.replay(|obj, _| {
+            replayed.push(*obj.id());
+            Ok(())
+        }).unwrap();
+        assert_eq!(replayed, vec![*obj.id()]);
+    }
+
+    #[test]
+    fn test_wal_torn_tail_is_cut_off() {
+        let temp_dir = tempdir().unwrap();
+        let wal_path = temp_dir.path().join("test.wal");
+
+        let wal = FileWriteAheadLog::new();
+        wal.init(&wal_path).unwrap();
+        wal.record_update(&create_test_object(), &create_test_proof(), None).unwrap();
+        wal.record_update(&create_test_object(), &create_test_proof(), None).unwrap();
+        let intact = std::fs::metadata(&wal_path).unwrap().len();
+
+        // An append interrupted half way through its entry
+        std::fs::OpenOptions::new().append(true).open(&wal_path).unwrap().write_all(&[64, 0, 0, 0, 0, 0, 0, 0, 1, 2]).unwrap();
+        assert_eq!(wal.check_tail(false).unwrap(), WalTail { entries: 2, torn_bytes: 10, truncated: false });
+        assert_eq!(wal.check_tail(true).unwrap(), WalTail { entries: 2, torn_bytes: 10, truncated: true });
+        assert_eq!(std::fs::metadata(&wal_path).unwrap().len(), intact);
+        wal.record_update(&create_test_object(), &create_test_proof(), None).unwrap();
+        assert_eq!(wal.check_tail(false).unwrap(), WalTail { entries: 3, ..WalTail::default() });
+
+        // A complete entry that does not decode is not a torn tail
+        std::fs::OpenOptions::new().append(true).open(&wal_path).unwrap().write_all(&[2, 0, 0, 0, 0, 0, 0, 0, 9, 9]).unwrap();
+        assert!(wal.check_tail(true).is_err());
+    }
+
+    #[test]
+    fn test_wal_durability_modes() {
+        let temp_dir = tempdir().unwrap();
+        let modes = [
+            WalDurability::Always,
+            WalDurability::Group { interval_ms: 5 },
+            WalDurability::Async,
+        ];
+        for (i, durability) in modes.into_iter().enumerate() {
+            let wal_path = temp_dir.path().join(format!("{}.wal", i));
+            let wal = FileWriteAheadLog::new().with_durability(durability);
+            wal.init(&wal_path).unwrap();
+            assert_eq!(wal.durability(), durability);
+            wal.record_update(&create_test_object(), &create_test_proof(), None).unwrap();
+            assert_eq!(wal.entry_count().unwrap(), 1);
+        }
+
+        // Group commit catches up within a few intervals
+        let wal = FileWriteAheadLog::new().with_durability(WalDurability::Group { interval_ms: 5 });
+        wal.init(&temp_dir.path().join("group.wal")).unwrap();
+        wal.record_update(&create_test_object(), &create_test_proof(), None).unwrap();
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while wal.unsynced.load(Ordering::Acquire) && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert!(!wal.unsynced.load(Ordering::Acquire));
+    }
+
+    #[test]
+    #[cfg(feature = "lz4")]
+    fn test_wal_mixes_compressed_and_raw_entries() {
+        let temp_dir = tempdir().unwrap();
+        let wal_path = temp_dir.path().join("test.wal");
+
+        // Entries from before compression was turned on stay readable
+        let raw = create_test_object();
+        let wal = FileWriteAheadLog::new();
+        wal.init(&wal_path).unwrap();
+        wal.record_update(&raw, &create_test_proof(), None).unwrap();
+        drop(wal);
+
+        let config = CodecConfig { threshold_bytes: 0, ..CodecConfig::new(Codec::Lz4) };
+        let wal = FileWriteAheadLog::new().with_codec(config);
+        wal.init(&wal_path).unwrap();
+        let large = UnitsObject::new_data(UnitsObjectId::random(), UnitsObjectId::random(), vec![0x5a; 4096]);
+        wal.record_update(&large, &create_test_proof(), None).unwrap();
+        assert!(std::fs::metadata(&wal_path).unwrap().len() < 4096);
+
+        let mut replayed = Vec::new();
+        wal.replay(|obj, _| {
+            replayed.push(obj.clone());
+            Ok(())
+        }).unwrap();
+        assert_eq!(replayed.len(), 2);
+        assert_eq!(replayed[0].id(), raw.id());
+        assert_eq!(replayed[1].data(), large.data());
+    }
+}
@@ -0,0 +1,117 @@
+//! Memory layout negotiated between the executor and kernel modules
+//!
+//! Before a module starts, the executor writes a [`MemoryLayout`] descriptor
+//! to read-only memory at the well-known [`MEMORY_LAYOUT_ADDR`]. Modules
+//! look up their buffers through the descriptor instead of compiling
+//! addresses in, so the host can move or resize the buffers without
+//! breaking deployed modules. Only the descriptor address is fixed.
+//!
+//! The descriptor is a sequence of little-endian `u32` fields. Later
+//! versions only append fields, so a module accepts any descriptor whose
+//! version and size are at least the ones it was built against.
+
+/// Fixed guest address of the layout descriptor
+pub const MEMORY_LAYOUT_ADDR: u32 = 0x0000_0800;
+
+/// Magic at the start of the descriptor ("UNML")
+pub const MEMORY_LAYOUT_MAGIC: u32 = u32::from_le_bytes(*b"UNML");
+
+/// Current descriptor version
+pub const MEMORY_LAYOUT_VERSION: u32 = 1;
+
+/// Buffer and stack placement for one execution
+///
+/// Each buffer is preceded by a little-endian `u32` holding the number of
+/// bytes in use, stored at `addr - 4`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryLayout {
+    /// Address of the execution context the module reads
+    pub input_addr: u32,
+    /// Largest context the input buffer can hold
+    pub input_capacity: u32,
+    /// Address the module writes its effects to
+    pub output_addr: u32,
+    /// Largest effect encoding the output buffer accepts
+    pub output_capacity: u32,
+    /// Initial stack pointer; the stack grows down from here
+    pub stack_top: u32,
+    pub stack_size: u32,
+}
+
+impl MemoryLayout {
+    /// Size of the encoded version 1 descriptor
+    pub const ENCODED_SIZE: usize = 36;
+
+    /// Layout used before layouts were negotiated, which existing modules assume
+    pub const DEFAULT: Self = Self {
+        input_addr: 0x1000_0000,
+        input_capacity: 1024 * 1024,
+        output_addr: 0x2000_0000,
+        output_capacity: 1024 * 1024,
+        stack_top: 0x0800_0000,
+        stack_size: 64 * 1024,
+    };
+
+    /// Address of the input buffer's length prefix
+    pub fn input_len_addr(&self) -> u32 {
+        self.input_addr.wrapping_sub(4)
+    }
+
+    /// Address of the output buffer's length prefix
+    pub fn output_len_addr(&self) -> u32 {
+        self.output_addr.wrapping_sub(4)
+    }
+
+    /// Encode the descriptor as written to guest memory
+    pub fn to_bytes(&self) -> [u8; Self::ENCODED_SIZE] {
+        let fields = [
+            MEMORY_LAYOUT_MAGIC,
+            MEMORY_LAYOUT_VERSION,
+            Self::ENCODED_SIZE as u32,
+            self.input_addr,
+            self.input_capacity,
+            self.output_addr,
+            self.output_capacity,
+            self.stack_top,
+            self.stack_size,
+        ];
+        let mut bytes = [0u8; Self::ENCODED_SIZE];
+        for (chunk, field) in bytes.chunks_exact_mut(4).zip(fields) {
+            chunk.copy_from_slice(&field.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Decode a descriptor, ignoring fields appended by later versions
+    ///
+    /// Returns `None` if the magic is wrong or the descriptor is older or
+    /// shorter than version 1.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let field = |index: usize| {
+            let chunk = bytes.get(index * 4..index * 4 + 4)?;
+            Some(u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        };
+
+        if field(0)? != MEMORY_LAYOUT_MAGIC
+            || field(1)? < MEMORY_LAYOUT_VERSION
+            || (field(2)? as usize) < Self::ENCODED_SIZE
+        {
+            return None;
+        }
+
+        Some(Self {
+            input_addr: field(3)?,
+            input_capacity: field(4)?,
+            output_addr: field(5)?,
+            output_capacity: field(6)?,
+            stack_top: field(7)?,
+            stack_size: field(8)?,
+        })
+    }
+}
+
+impl Default for MemoryLayout {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
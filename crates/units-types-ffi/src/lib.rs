@@ -0,0 +1,150 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Wire types shared between the UNITS host and kernel modules
+//!
+//! These are the Borsh-encoded structures that cross the VM boundary: the
+//! execution context handed to a module and the object effects and events
+//! it returns.
+//! The kernel SDK re-exports them directly, and `units-core-types` derives
+//! the same Borsh layout for its richer host-side equivalents, so both sides
+//! of the boundary agree on a single definition. The [`layout`] module
+//! describes where those encodings live in guest memory, and [`syscall`]
+//! how a module asks the host for them.
+
+extern crate alloc;
+
+pub mod layout;
+pub mod syscall;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap as HashMap;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+pub use layout::{MemoryLayout, MEMORY_LAYOUT_ADDR};
+
+/// Size of object IDs in bytes
+pub const OBJECT_ID_SIZE: usize = 32;
+
+/// Units object ID type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, BorshSerialize, BorshDeserialize)]
+pub struct UnitsObjectId([u8; OBJECT_ID_SIZE]);
+
+impl UnitsObjectId {
+    pub const fn new(bytes: [u8; OBJECT_ID_SIZE]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Object type enum
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum ObjectType {
+    Data,
+    Executable(VMType),
+    /// Scratch data the host removes at `expires_at_slot`
+    Ephemeral { expires_at_slot: u64 },
+}
+
+/// VM type enum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum VMType {
+    RiscV,
+    Wasm,
+}
+
+/// Units object structure
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct UnitsObject {
+    pub id: UnitsObjectId,
+    pub controller_id: UnitsObjectId,
+    pub object_type: ObjectType,
+    pub data: Vec<u8>,
+}
+
+/// Instruction structure
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct Instruction {
+    pub controller_id: UnitsObjectId,
+    pub target_function: String,
+    pub target_objects: Vec<UnitsObjectId>,
+    pub params: Vec<u8>,
+}
+
+/// Execution context provided to kernel modules
+///
+/// Borsh encodes maps in key order, so the `std` (`HashMap`) and `no_std`
+/// (`BTreeMap`) representations produce identical bytes.
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub struct ExecutionContext {
+    pub instruction: Instruction,
+    pub objects: HashMap<UnitsObjectId, UnitsObject>,
+    pub slot: u64,
+    pub timestamp: u64,
+}
+
+/// Effect of kernel execution on a single object
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct ObjectEffect {
+    pub object_id: UnitsObjectId,
+    pub before_image: Option<UnitsObject>,
+    pub after_image: Option<UnitsObject>,
+}
+
+impl ObjectEffect {
+    /// Create new object effect
+    pub fn creation(object: UnitsObject) -> Self {
+        Self {
+            object_id: object.id,
+            before_image: None,
+            after_image: Some(object),
+        }
+    }
+
+    /// Modify existing object effect
+    pub fn modification(before: UnitsObject, after: UnitsObject) -> Self {
+        Self {
+            object_id: after.id,
+            before_image: Some(before),
+            after_image: Some(after),
+        }
+    }
+
+    /// Delete object effect
+    pub fn deletion(object: UnitsObject) -> Self {
+        Self {
+            object_id: object.id,
+            before_image: Some(object),
+            after_image: None,
+        }
+    }
+}
+
+/// Structured event a module emits alongside its effects
+///
+/// The host records events in the transaction receipt under the object
+/// they concern. `name` and `data` mean whatever the module defines, e.g.
+/// `"Transfer"` with a Borsh-encoded `{ from, to, amount }`.
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct KernelEvent {
+    pub object_id: UnitsObjectId,
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+impl KernelEvent {
+    pub fn new(object_id: UnitsObjectId, name: impl Into<String>, data: Vec<u8>) -> Self {
+        Self {
+            object_id,
+            name: name.into(),
+            data,
+        }
+    }
+}
@@ -0,0 +1,42 @@
+//! Syscalls kernel modules make to the host
+//!
+//! A module places the syscall number in `a7` and up to three arguments in
+//! `a0`-`a2`, then executes `ecall`. The result comes back in `a0`, with
+//! negative values reporting an error; 64-bit results are split across
+//! `a0` (low word) and `a1` (high word).
+//!
+//! The I/O calls use the Linux RISC-V numbers. Reading [`STDIN`] yields the
+//! execution context as a little-endian `u32` length followed by its Borsh
+//! encoding, and a module returns its effects by writing the same framing
+//! of a Borsh `Vec<ObjectEffect>` to [`STDOUT`]. A module that emits events
+//! follows the effects with a Borsh `Vec<KernelEvent>` inside the same
+//! frame; a frame holding only effects has none. Bytes written to
+//! [`STDERR`] are logged by the host. Host calls are numbered above the
+//! Linux range.
+
+/// Read from a file descriptor: `a0` = fd, `a1` = buffer, `a2` = length
+pub const SYS_READ: u32 = 63;
+/// Write to a file descriptor: `a0` = fd, `a1` = buffer, `a2` = length
+pub const SYS_WRITE: u32 = 64;
+/// Halt with the exit status in `a0`
+pub const SYS_EXIT: u32 = 93;
+/// Slot the transaction executes in
+pub const SYS_GET_SLOT: u32 = 0x1000;
+/// Timestamp the transaction executes at, as given in the execution context
+pub const SYS_GET_TIMESTAMP: u32 = 0x1001;
+/// Log a UTF-8 message: `a0` = buffer, `a1` = length
+pub const SYS_LOG: u32 = 0x1002;
+
+/// Source of the execution context
+pub const STDIN: i32 = 0;
+/// Sink for the module's effects and events
+pub const STDOUT: i32 = 1;
+/// Sink for log output
+pub const STDERR: i32 = 2;
+
+/// Returned for a file descriptor the call does not accept
+pub const EBADF: i32 = -9;
+/// Returned for a buffer the module may not access
+pub const EFAULT: i32 = -14;
+/// Returned for a write past the space the host accepts
+pub const EFBIG: i32 = -27;
@@ -0,0 +1,197 @@
+//! Fuzz `RiscVMemory` mapping, typed guest accesses and loader copies
+//!
+//! Every operation is replayed against a naive model of the address space,
+//! and the memory must agree with it: an access succeeds exactly when it
+//! lies inside one region granting the needed permission, and loads read
+//! back the bytes last written.
+//!
+//! Run with `cargo fuzz run riscv_memory` from the repository root.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use rvsim::{Memory, MemoryAccess};
+use units_runtime_impl::{Permissions, RiscVMemory};
+
+const MEMORY_LIMIT: usize = 1 << 20;
+
+/// ELF segment flags, as accepted by `Permissions::from_elf_flags`
+const PF_X: u8 = 0x1;
+const PF_W: u8 = 0x2;
+const PF_R: u8 = 0x4;
+
+#[derive(Arbitrary, Debug, Clone, Copy)]
+enum Address {
+    Absolute(u32),
+    /// Near the base of a mapped region, so accesses hit and straddle edges
+    Relative { region: u8, offset: i16 },
+}
+
+#[derive(Arbitrary, Debug, Clone, Copy)]
+enum Width {
+    Byte,
+    Half,
+    Word,
+    Double,
+}
+
+impl Width {
+    fn len(self) -> usize {
+        match self {
+            Self::Byte => 1,
+            Self::Half => 2,
+            Self::Word => 4,
+            Self::Double => 8,
+        }
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Map { base: Address, size: u16, flags: u8 },
+    Load { addr: Address, width: Width },
+    Exec { addr: Address, width: Width },
+    Store { addr: Address, width: Width, value: u64 },
+    WriteBytes { addr: Address, bytes: Vec<u8> },
+    ReadBytes { addr: Address, len: u16 },
+}
+
+/// Plain integer the guest loads and stores
+trait Word: Copy + Default {
+    fn to_bytes(self) -> Vec<u8>;
+    fn from_bytes(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_word {
+    ($($ty:ty),*) => {$(
+        impl Word for $ty {
+            fn to_bytes(self) -> Vec<u8> {
+                self.to_le_bytes().to_vec()
+            }
+
+            fn from_bytes(bytes: &[u8]) -> Self {
+                Self::from_le_bytes(bytes.try_into().unwrap())
+            }
+        }
+    )*};
+}
+
+impl_word!(u8, u16, u32, u64);
+
+fn load<T: Word>(memory: &mut RiscVMemory, addr: u32, exec: bool) -> Option<Vec<u8>> {
+    let mut value = T::default();
+    let access = if exec { MemoryAccess::Exec(&mut value) } else { MemoryAccess::Load(&mut value) };
+    memory.access(addr, access).then(|| value.to_bytes())
+}
+
+fn store<T: Word>(memory: &mut RiscVMemory, addr: u32, bytes: &[u8]) -> bool {
+    memory.access(addr, MemoryAccess::Store(T::from_bytes(bytes)))
+}
+
+fn typed_load(memory: &mut RiscVMemory, addr: u32, width: Width, exec: bool) -> Option<Vec<u8>> {
+    match width {
+        Width::Byte => load::<u8>(memory, addr, exec),
+        Width::Half => load::<u16>(memory, addr, exec),
+        Width::Word => load::<u32>(memory, addr, exec),
+        Width::Double => load::<u64>(memory, addr, exec),
+    }
+}
+
+fn typed_store(memory: &mut RiscVMemory, addr: u32, width: Width, bytes: &[u8]) -> bool {
+    match width {
+        Width::Byte => store::<u8>(memory, addr, bytes),
+        Width::Half => store::<u16>(memory, addr, bytes),
+        Width::Word => store::<u32>(memory, addr, bytes),
+        Width::Double => store::<u64>(memory, addr, bytes),
+    }
+}
+
+/// Reference address space: a flat list of regions, searched linearly
+#[derive(Default)]
+struct Model {
+    /// Base, ELF permission flags and contents of each region
+    regions: Vec<(u64, u8, Vec<u8>)>,
+    mapped: usize,
+}
+
+impl Model {
+    fn resolve(&self, addr: Address) -> u32 {
+        match addr {
+            Address::Relative { region, offset } if !self.regions.is_empty() => {
+                let (base, _, _) = self.regions[region as usize % self.regions.len()];
+                (base as u32).wrapping_add(offset as i32 as u32)
+            }
+            Address::Relative { offset, .. } => offset as i32 as u32,
+            Address::Absolute(addr) => addr,
+        }
+    }
+
+    fn map(&mut self, base: u32, size: usize, flags: u8) -> bool {
+        if size == 0 {
+            return true;
+        }
+        let (start, end) = (base as u64, base as u64 + size as u64);
+        let overlaps = self
+            .regions
+            .iter()
+            .any(|(other, _, data)| start < other + data.len() as u64 && *other < end);
+        if end > 1 << 32 || self.mapped + size > MEMORY_LIMIT || overlaps {
+            return false;
+        }
+        self.regions.push((start, flags, vec![0; size]));
+        self.mapped += size;
+        true
+    }
+
+    /// Bytes of `[addr, addr + len)` if one region holds them all and grants `needed`
+    fn bytes_mut(&mut self, addr: u32, len: usize, needed: u8) -> Option<&mut [u8]> {
+        let (start, end) = (addr as u64, addr as u64 + len as u64);
+        self.regions
+            .iter_mut()
+            .find(|(base, _, data)| *base <= start && end <= base + data.len() as u64)
+            .filter(|(_, flags, _)| flags & needed == needed)
+            .map(|(base, _, data)| &mut data[(start - *base) as usize..(end - *base) as usize])
+    }
+}
+
+fuzz_target!(|ops: Vec<Op>| {
+    let mut memory = RiscVMemory::new(MEMORY_LIMIT);
+    let mut model = Model::default();
+
+    for op in ops {
+        match op {
+            Op::Map { base, size, flags } => {
+                let (base, flags) = (model.resolve(base), flags & (PF_R | PF_W | PF_X));
+                let mapped = memory.map(base, size as usize, Permissions::from_elf_flags(flags as u32)).is_ok();
+                assert_eq!(mapped, model.map(base, size as usize, flags), "map {:#x}+{}", base, size);
+                assert_eq!(memory.mapped_bytes(), model.mapped);
+            }
+            Op::Load { addr, width } | Op::Exec { addr, width } => {
+                let exec = matches!(op, Op::Exec { .. });
+                let addr = model.resolve(addr);
+                let expected = model
+                    .bytes_mut(addr, width.len(), if exec { PF_X } else { PF_R })
+                    .map(|bytes| bytes.to_vec());
+                assert_eq!(typed_load(&mut memory, addr, width, exec), expected, "load {:#x}", addr);
+            }
+            Op::Store { addr, width, value } => {
+                let addr = model.resolve(addr);
+                let bytes = &value.to_le_bytes()[..width.len()];
+                let expected = model.bytes_mut(addr, width.len(), PF_W).map(|dest| dest.copy_from_slice(bytes));
+                assert_eq!(typed_store(&mut memory, addr, width, bytes), expected.is_some(), "store {:#x}", addr);
+            }
+            Op::WriteBytes { addr, bytes } => {
+                // The loader's copy only needs the range to be mapped
+                let addr = model.resolve(addr);
+                let expected = model.bytes_mut(addr, bytes.len(), 0).map(|dest| dest.copy_from_slice(&bytes));
+                assert_eq!(memory.write_bytes(addr, &bytes).is_ok(), expected.is_some(), "write {:#x}", addr);
+            }
+            Op::ReadBytes { addr, len } => {
+                let addr = model.resolve(addr);
+                let expected = model.bytes_mut(addr, len as usize, 0).map(|bytes| bytes.to_vec());
+                assert_eq!(memory.read_bytes(addr, len as usize).ok(), expected, "read {:#x}", addr);
+            }
+        }
+    }
+});
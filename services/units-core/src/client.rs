@@ -0,0 +1,170 @@
+//! Client-side detection of stale reads across a fleet of nodes
+//!
+//! Every JSON-RPC response names the slot and state root its node had
+//! committed when it answered (see [`SLOT_HEADER`]). Behind a load
+//! balancer, consecutive requests can reach nodes at different slots; a
+//! response from a lower slot than one already seen predates state the
+//! client has observed. [`FreshnessTracker`] flags such regressions, and
+//! [`FleetClient`] sends requests through one, optionally retrying a stale
+//! answer against the next endpoint.
+
+use anyhow::{anyhow, Context, Result};
+use hyper::client::HttpConnector;
+use serde::de::DeserializeOwned;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use thiserror::Error;
+
+use crate::json_rpc::{SLOT_HEADER, STATE_ROOT_HEADER};
+use crate::service::ResponseContext;
+
+/// A response served from older state than the client already saw
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("Stale read: served at slot {}, but slot {} was already seen", served.slot, latest.slot)]
+pub struct StaleRead {
+    pub served: ResponseContext,
+    pub latest: ResponseContext,
+}
+
+/// Latest state seen in responses, which later responses must not precede
+#[derive(Debug, Default)]
+pub struct FreshnessTracker {
+    latest: Mutex<Option<ResponseContext>>,
+}
+
+impl FreshnessTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Latest state seen so far
+    pub fn latest(&self) -> Option<ResponseContext> {
+        *self.latest.lock().unwrap()
+    }
+
+    /// Record the state a response was served from
+    ///
+    /// Fails, keeping the latest state, if it is older.
+    pub fn observe(&self, served: ResponseContext) -> Result<(), StaleRead> {
+        let mut latest = self.latest.lock().unwrap();
+        match *latest {
+            Some(seen) if served.slot < seen.slot => Err(StaleRead { served, latest: seen }),
+            Some(seen) if served.slot == seen.slot => Ok(()),
+            _ => {
+                *latest = Some(served);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// JSON-RPC client spreading requests over a fleet, refusing stale answers
+///
+/// Requests go to the endpoints in turn. A response from older state than
+/// an earlier one fails with [`StaleRead`] or, with
+/// [`FleetClient::with_stale_retry`], is sent again to the following
+/// endpoints until one is fresh enough.
+pub struct FleetClient {
+    endpoints: Vec<String>,
+    next: AtomicUsize,
+    retry_stale: bool,
+    request_ids: AtomicU64,
+    tracker: FreshnessTracker,
+    http: hyper::Client<HttpConnector>,
+}
+
+impl FleetClient {
+    pub fn new(endpoints: Vec<String>) -> Self {
+        Self {
+            endpoints,
+            next: AtomicUsize::new(0),
+            retry_stale: false,
+            request_ids: AtomicU64::new(0),
+            tracker: FreshnessTracker::new(),
+            http: hyper::Client::new(),
+        }
+    }
+
+    /// Retry stale responses against the other endpoints, each once
+    pub fn with_stale_retry(mut self, retry: bool) -> Self {
+        self.retry_stale = retry;
+        self
+    }
+
+    pub fn tracker(&self) -> &FreshnessTracker {
+        &self.tracker
+    }
+
+    /// Call `method` with `params`, returning its result and the state it
+    /// was served from
+    ///
+    /// A stale response that is not retried fails with a [`StaleRead`]
+    /// error.
+    pub async fn request<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<(T, ResponseContext)> {
+        if self.endpoints.is_empty() {
+            return Err(anyhow!("No endpoints to send {} to", method));
+        }
+        let attempts = if self.retry_stale { self.endpoints.len() } else { 1 };
+        let mut stale = None;
+        for _ in 0..attempts {
+            let endpoint = &self.endpoints[self.next.fetch_add(1, Ordering::Relaxed) % self.endpoints.len()];
+            let (result, served) = self.send(endpoint, method, &params).await?;
+            match self.tracker.observe(served) {
+                Ok(()) => {
+                    let result = serde_json::from_value(result)
+                        .with_context(|| format!("Unexpected result from {}", method))?;
+                    return Ok((result, served));
+                }
+                Err(error) => {
+                    log::debug!("{} answered {} from older state: {}", endpoint, method, error);
+                    stale = Some(error);
+                }
+            }
+        }
+        Err(stale.expect("every attempt was stale").into())
+    }
+
+    async fn send(
+        &self,
+        endpoint: &str,
+        method: &str,
+        params: &serde_json::Value,
+    ) -> Result<(serde_json::Value, ResponseContext)> {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": self.request_ids.fetch_add(1, Ordering::Relaxed),
+            "method": method,
+            "params": params,
+        });
+        let request = hyper::Request::post(endpoint)
+            .header(hyper::header::CONTENT_TYPE, "application/json")
+            .body(hyper::Body::from(serde_json::to_vec(&body)?))
+            .with_context(|| format!("Invalid RPC URL {}", endpoint))?;
+        let response = self.http.request(request).await?;
+
+        let header = |name: &str| response.headers().get(name).and_then(|value| value.to_str().ok());
+        let slot = header(SLOT_HEADER)
+            .and_then(|slot| slot.parse().ok())
+            .ok_or_else(|| anyhow!("{} did not echo its slot", endpoint))?;
+        let state_root = header(STATE_ROOT_HEADER)
+            .map(|root| {
+                hex::decode(root)
+                    .ok()
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or_else(|| anyhow!("{} echoed an invalid state root", endpoint))
+            })
+            .transpose()?;
+        let served = ResponseContext { slot, state_root };
+
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+        let mut envelope: serde_json::Value = serde_json::from_slice(&body)?;
+        if let Some(error) = envelope.get("error") {
+            return Err(anyhow!("{} failed: {}", method, error));
+        }
+        Ok((envelope["result"].take(), served))
+    }
+}
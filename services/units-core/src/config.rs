@@ -1,12 +1,57 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
+use units_core_types::{AdaptiveBatchConfig, NamespacedScheme, ParallelSchedulerConfig, StorageRentConfig, UnitsObjectId};
+use units_proofs::{HashAlgorithm, SlotOrdering};
+use units_storage_impl::{CodecConfig, IndexAdvisorConfig, WalDurability, DEFAULT_HISTORY_DEPTH};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub storage: StorageConfig,
     pub runtime: RuntimeConfig,
     pub server: ServerConfig,
+    #[serde(default)]
+    pub replica: ReplicaConfig,
+    #[serde(default)]
+    pub pipeline: AdaptiveBatchConfig,
+    #[serde(default)]
+    pub scheduler: ParallelSchedulerConfig,
+    #[serde(default)]
+    pub sandbox: SandboxConfig,
+    #[serde(default)]
+    pub signing: SigningConfig,
+    #[serde(default)]
+    pub admin: AdminConfig,
+    #[serde(default)]
+    pub controllers: ControllerPolicy,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    #[serde(default)]
+    pub webhooks: WebhookConfig,
+    #[serde(default)]
+    pub shadow: ShadowConfig,
+    #[serde(default)]
+    pub effect_processors: EffectProcessorConfig,
+    #[serde(default)]
+    pub rent: RentConfig,
+    #[serde(default)]
+    pub finality: FinalityConfig,
+    #[serde(default)]
+    pub slot_timer: SlotTimerConfig,
+    #[serde(default)]
+    pub scan: ScanConfig,
+    #[serde(default)]
+    pub maintenance: MaintenanceConfig,
+    #[serde(default)]
+    pub watches: WatchConfig,
+    #[serde(default)]
+    pub response_cache: ResponseCacheConfig,
+    #[serde(default)]
+    pub preflight: PreflightConfig,
+    /// Namespaces of external identifiers mapped into object IDs, beside
+    /// the built-in ISIN and DID schemes
+    #[serde(default)]
+    pub id_schemes: Vec<NamespacedScheme>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +62,38 @@ pub struct StorageConfig {
     pub data_dir: Option<String>,
     /// Maximum object size in bytes
     pub max_object_size: usize,
+    /// Versions retained per object for time-travel queries (0 disables history)
+    #[serde(default = "default_history_depth")]
+    pub history_depth: usize,
+    /// Compression of stored receipts
+    #[serde(default)]
+    pub receipt_codec: CodecConfig,
+    /// Compression of write-ahead log entries
+    #[serde(default)]
+    pub wal_codec: CodecConfig,
+    /// When write-ahead log entries are fsynced: always, in groups, or by the OS
+    #[serde(default)]
+    pub wal_durability: WalDurability,
+    /// Required order of slots along each object's proof chain
+    #[serde(default)]
+    pub slot_ordering: SlotOrdering,
+    /// Hash function of object and state proofs: blake3, sha256, or
+    /// keccak256 for roots checked by EVM contracts. Fixed for a deployment,
+    /// as proofs already written are not rehashed.
+    #[serde(default)]
+    pub hash_algorithm: HashAlgorithm,
+    /// When hot queries are recommended an index, and whether one is built unprompted
+    #[serde(default)]
+    pub indexes: IndexAdvisorConfig,
+    /// Record the locks of executing transactions in a lock table under
+    /// `data_dir`, so locks left by a crash are released on startup; needs
+    /// the `sqlite` feature
+    #[serde(default)]
+    pub persistent_locks: bool,
+}
+
+fn default_history_depth() -> usize {
+    DEFAULT_HISTORY_DEPTH
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,6 +116,308 @@ pub struct ServerConfig {
     pub enable_cors: bool,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicaConfig {
+    /// Serve read RPCs from a periodically refreshed snapshot
+    pub enabled: bool,
+    /// Interval between snapshot refreshes in milliseconds
+    pub refresh_interval_ms: u64,
+    /// Reads fall back to the primary store when the snapshot is older than this
+    pub max_staleness_ms: u64,
+}
+
+impl Default for ReplicaConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_interval_ms: 500,
+            max_staleness_ms: 2000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxConfig {
+    /// Maximum number of simulation sandboxes open at once
+    pub max_namespaces: usize,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self { max_namespaces: 16 }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SigningConfig {
+    /// Sign read responses with the node key
+    pub enabled: bool,
+    /// File holding the hex-encoded Ed25519 seed of the node key
+    pub key_file: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminConfig {
+    /// Serve the `admin_*` RPC namespace
+    pub enabled: bool,
+    /// Key callers present to act with the admin role
+    pub api_key: Option<String>,
+    /// Seconds a dry-run confirmation token stays valid
+    pub confirmation_ttl_secs: u64,
+}
+
+impl Default for AdminConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            api_key: None,
+            confirmation_ttl_secs: 300,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    /// URLs that receive a POST for every executed transaction receipt
+    pub endpoints: Vec<String>,
+    /// Give up on a delivery after this many milliseconds
+    pub timeout_ms: u64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            endpoints: Vec::new(),
+            timeout_ms: 5000,
+        }
+    }
+}
+
+/// Limits on client-registered object watches
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchConfig {
+    /// Most watches the node holds at once
+    pub max_watches: usize,
+    /// WebSocket notifications kept per watch for reconnecting subscribers
+    pub backlog_per_watch: usize,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            max_watches: 1024,
+            backlog_per_watch: 256,
+        }
+    }
+}
+
+/// Caching of receipt and proof query answers until the next slot closes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResponseCacheConfig {
+    pub enabled: bool,
+    /// Most answers cached at once, oldest dropped first
+    pub max_entries: usize,
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_entries: 4096,
+        }
+    }
+}
+
+/// Storage checks run before the node serves traffic
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreflightConfig {
+    pub enabled: bool,
+    /// Fix what can be fixed safely, such as missing state proofs, a torn
+    /// write-ahead log entry or stale locks; otherwise any finding stops startup
+    pub repair: bool,
+}
+
+impl Default for PreflightConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            repair: true,
+        }
+    }
+}
+
+/// Re-execution of sampled transactions on a shadow runtime
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShadowConfig {
+    pub enabled: bool,
+    /// Fraction of transactions re-executed, from 0 to 1
+    pub sample_rate: f64,
+    /// Divergences kept for inspection, oldest dropped first
+    pub max_divergences: usize,
+}
+
+impl Default for ShadowConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sample_rate: 0.01,
+            max_divergences: 100,
+        }
+    }
+}
+
+impl ShadowConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(0.0..=1.0).contains(&self.sample_rate) {
+            anyhow::bail!("shadow.sample_rate must be between 0 and 1, got {}", self.sample_rate);
+        }
+        Ok(())
+    }
+}
+
+/// Built-in effect processors the runtime runs before each commit
+///
+/// Quota processors veto transactions over a limit; unset limits are not
+/// enforced. All nodes of a deployment must agree on these settings, since
+/// a veto changes a transaction's outcome.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EffectProcessorConfig {
+    /// Annotate receipts with object and byte counts of what was written
+    #[serde(default)]
+    pub write_stats: bool,
+    /// Most objects one transaction may create, modify or delete
+    #[serde(default)]
+    pub max_effects: Option<usize>,
+    /// Most object data bytes one transaction may write
+    #[serde(default)]
+    pub max_bytes_written: Option<u64>,
+}
+
+/// Storage deposits every live object must be backed by
+///
+/// Deposits are paid from balances in the deposit ledger, which the
+/// `FundDepositAccount` admin operation credits. All nodes of a deployment
+/// must agree on these settings, since an unpaid deposit fails a transaction.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RentConfig {
+    /// Charge deposits for objects transactions create or grow
+    #[serde(default)]
+    pub enabled: bool,
+    /// Flat deposit charged for every object regardless of size
+    #[serde(default)]
+    pub base_deposit: u64,
+    /// Additional deposit charged per byte of object data
+    #[serde(default)]
+    pub deposit_per_byte: u64,
+}
+
+impl RentConfig {
+    /// Pricing the runtime charges deposits at, when enabled
+    pub fn storage_rent(&self) -> Option<StorageRentConfig> {
+        self.enabled.then(|| StorageRentConfig::new(self.base_deposit, self.deposit_per_byte))
+    }
+}
+
+/// Controllers this node admits transactions for
+///
+/// The default admits every controller. Permissioned deployments list the
+/// controllers they serve in `allowed`; `denied` wins over `allowed`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControllerPolicy {
+    /// Only admit these controllers, or any controller when unset
+    #[serde(default)]
+    pub allowed: Option<Vec<UnitsObjectId>>,
+    /// Never admit these controllers
+    #[serde(default)]
+    pub denied: Vec<UnitsObjectId>,
+}
+
+impl ControllerPolicy {
+    /// Whether transactions invoking `controller_id` may be admitted
+    pub fn permits(&self, controller_id: &UnitsObjectId) -> bool {
+        !self.denied.contains(controller_id)
+            && self.allowed.as_ref().map_or(true, |allowed| allowed.contains(controller_id))
+    }
+}
+
+/// How many recent slots of each kind of data to keep
+///
+/// Unset windows keep data forever. Each store is pruned on its own, so a
+/// node can keep every proof while dropping old object versions. Object
+/// proof chains are never pruned.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    /// Slots of superseded object versions kept for time-travel reads
+    #[serde(default)]
+    pub object_history_slots: Option<u64>,
+    /// Slots of state proofs kept
+    #[serde(default)]
+    pub state_proof_slots: Option<u64>,
+    /// Slots of transaction receipts kept
+    #[serde(default)]
+    pub receipt_slots: Option<u64>,
+}
+
+impl RetentionConfig {
+    /// Reject windows that would prune a state proof still needed to verify retained data
+    ///
+    /// The newest state proof anchors the chain, so every window keeps at
+    /// least one slot, and receipts may not outlive the state proofs whose
+    /// transaction roots commit them.
+    pub fn validate(&self) -> Result<()> {
+        let windows = [
+            ("object_history_slots", self.object_history_slots),
+            ("state_proof_slots", self.state_proof_slots),
+            ("receipt_slots", self.receipt_slots),
+        ];
+        for (name, window) in windows {
+            if window == Some(0) {
+                anyhow::bail!("retention.{} must keep at least one slot", name);
+            }
+        }
+
+        if let Some(state_proof_slots) = self.state_proof_slots {
+            if self.receipt_slots.map_or(true, |receipt_slots| receipt_slots > state_proof_slots) {
+                anyhow::bail!(
+                    "retention.receipt_slots must not exceed retention.state_proof_slots ({}), \
+                     or receipts would outlive the state proofs committing them",
+                    state_proof_slots
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// When slots move from confirmed to finalized
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FinalityConfig {
+    /// Confirmed slots that must follow a slot before it is finalized
+    pub depth: u64,
+}
+
+impl Default for FinalityConfig {
+    fn default() -> Self {
+        Self { depth: crate::services::minimal_services::DEFAULT_FINALITY_DEPTH }
+    }
+}
+
+/// How often the node closes a slot on its own
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlotTimerConfig {
+    /// Interval between slots in milliseconds; 0 leaves slots to be advanced manually
+    pub interval_ms: u64,
+}
+
+impl Default for SlotTimerConfig {
+    fn default() -> Self {
+        Self { interval_ms: 400 }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -46,6 +425,14 @@ impl Default for Config {
                 storage_type: "memory".to_string(),
                 data_dir: None,
                 max_object_size: 10 * 1024 * 1024, // 10MB
+                history_depth: DEFAULT_HISTORY_DEPTH,
+                receipt_codec: CodecConfig::default(),
+                wal_codec: CodecConfig::default(),
+                wal_durability: WalDurability::default(),
+                slot_ordering: SlotOrdering::default(),
+                hash_algorithm: HashAlgorithm::default(),
+                indexes: IndexAdvisorConfig::default(),
+                persistent_locks: false,
             },
             runtime: RuntimeConfig {
                 max_execution_time_ms: 5000, // 5 seconds
@@ -57,6 +444,26 @@ impl Default for Config {
                 request_timeout_secs: 30,
                 enable_cors: true,
             },
+            replica: ReplicaConfig::default(),
+            pipeline: AdaptiveBatchConfig::default(),
+            scheduler: ParallelSchedulerConfig::default(),
+            sandbox: SandboxConfig::default(),
+            signing: SigningConfig::default(),
+            admin: AdminConfig::default(),
+            controllers: ControllerPolicy::default(),
+            retention: RetentionConfig::default(),
+            webhooks: WebhookConfig::default(),
+            shadow: ShadowConfig::default(),
+            effect_processors: EffectProcessorConfig::default(),
+            rent: RentConfig::default(),
+            finality: FinalityConfig::default(),
+            slot_timer: SlotTimerConfig::default(),
+            scan: ScanConfig::default(),
+            maintenance: MaintenanceConfig::default(),
+            watches: WatchConfig::default(),
+            response_cache: ResponseCacheConfig::default(),
+            preflight: PreflightConfig::default(),
+            id_schemes: Vec::new(),
         }
     }
 }
@@ -83,4 +490,64 @@ impl Config {
         std::fs::write(path, content)?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+/// Bounds on store scans made while serving a request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanConfig {
+    /// Most objects one scan visits before returning a partial result
+    pub max_rows: usize,
+    /// Longest a scan runs before returning a partial result, in milliseconds
+    pub max_scan_ms: u64,
+    /// Objects visited between yields of the scanning thread
+    pub yield_every: usize,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self {
+            max_rows: 100_000,
+            max_scan_ms: 500,
+            yield_every: 1_024,
+        }
+    }
+}
+
+/// Background compaction, pruning and scrubbing, held back under load
+///
+/// When enabled, retention windows are enforced by the scheduler instead of
+/// at every slot. A job runs once its interval has passed and every load
+/// threshold is met; otherwise it waits for the next check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    pub enabled: bool,
+    /// Time between load checks, in milliseconds
+    pub check_interval_ms: u64,
+    /// Jobs wait while more RPC requests than this are in flight
+    pub max_in_flight_requests: usize,
+    /// Jobs wait while RPC requests arrive faster than this per second
+    pub max_requests_per_sec: u64,
+    /// Jobs wait while the last slot took longer than this to close, in milliseconds
+    pub max_slot_duration_ms: u64,
+    /// Time between compactions of object history, in milliseconds (unset disables)
+    pub compact_interval_ms: Option<u64>,
+    /// Time between prunes of receipts and state proofs, in milliseconds (unset disables)
+    pub prune_interval_ms: Option<u64>,
+    /// Time between scrubs of every proof chain, in milliseconds (unset disables)
+    pub scrub_interval_ms: Option<u64>,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            check_interval_ms: 1_000,
+            max_in_flight_requests: 8,
+            max_requests_per_sec: 200,
+            max_slot_duration_ms: 500,
+            compact_interval_ms: Some(60_000),
+            prune_interval_ms: Some(60_000),
+            scrub_interval_ms: Some(3_600_000),
+        }
+    }
+}
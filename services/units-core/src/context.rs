@@ -0,0 +1,100 @@
+//! Per-request context carried from the RPC layer into the services
+//!
+//! A `RequestContext` names the request for logs, records who made it and
+//! carries the point after which the client has stopped waiting. Services
+//! doing work proportional to stored data call `check` as they go, so a
+//! scan whose client has timed out stops instead of running to completion.
+//! It also carries the bounds on how much of the store one request may scan.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::config::ScanConfig;
+use crate::error::{ServiceError, ServiceResult};
+
+static NEXT_TRACE: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    /// Identifier tying together the log lines of one request
+    pub trace_id: String,
+    /// Who made the request, when known
+    pub principal: Option<String>,
+    /// When the client stops waiting for a response
+    pub deadline: Option<Instant>,
+    /// Bounds on store scans made for the request
+    pub scan: ScanConfig,
+}
+
+impl Default for RequestContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestContext {
+    /// Anonymous context with a fresh trace id and no deadline
+    pub fn new() -> Self {
+        Self {
+            trace_id: format!("{:016x}", NEXT_TRACE.fetch_add(1, Ordering::Relaxed)),
+            principal: None,
+            deadline: None,
+            scan: ScanConfig::default(),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn with_principal(mut self, principal: impl Into<String>) -> Self {
+        self.principal = Some(principal.into());
+        self
+    }
+
+    /// Give up `timeout` from now
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.deadline = Some(Instant::now() + timeout);
+        self
+    }
+
+    pub fn with_scan_limits(mut self, scan: ScanConfig) -> Self {
+        self.scan = scan;
+        self
+    }
+
+    /// Time left before the deadline, or `None` without one
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining() == Some(Duration::ZERO)
+    }
+
+    /// Fail once the deadline has passed
+    pub fn check(&self) -> ServiceResult<()> {
+        if self.is_expired() {
+            log::debug!(
+                "Request {} from {} cancelled at its deadline",
+                self.trace_id,
+                self.principal.as_deref().unwrap_or("anonymous")
+            );
+            return Err(ServiceError::deadline_exceeded(&self.trace_id));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deadline_expires() {
+        let open = RequestContext::new();
+        assert!(open.check().is_ok());
+        assert_eq!(open.remaining(), None);
+
+        let expired = RequestContext::new().with_principal("alice").with_timeout(Duration::ZERO);
+        assert_ne!(expired.trace_id, open.trace_id);
+        assert!(matches!(expired.check(), Err(ServiceError::DeadlineExceeded { .. })));
+    }
+}
@@ -0,0 +1,120 @@
+//! Interactive VM debugger for controller modules
+//!
+//! Runs a module's bytecode in the RISC-V executor with an empty object set,
+//! stopping at breakpoints or single-stepping, and prints the instruction
+//! trace when execution fails.
+
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use units_core_types::transaction::Instruction;
+use units_core_types::{ExecutionContext, UnitsObjectId};
+use units_runtime_impl::{DebugCommand, Debugger, ExecutionTrace, RiscVExecutor, RiscVExecutorConfig};
+
+/// Arguments for the `debug-module` subcommand
+#[derive(Args)]
+pub struct DebugArgs {
+    /// Module bytecode (ELF or RVBC)
+    bytecode: PathBuf,
+
+    /// Hex-encoded controller ID the module runs as
+    #[arg(long, default_value = "0000000000000000000000000000000000000000000000000000000000000000")]
+    controller: String,
+
+    /// Function name passed to the module
+    #[arg(long, default_value = "debug")]
+    function: String,
+
+    /// Hex-encoded instruction parameters
+    #[arg(long, default_value = "")]
+    params: String,
+
+    /// Stop before the instruction at this address (hex), may be repeated
+    #[arg(long = "break", value_parser = parse_address)]
+    breakpoints: Vec<u32>,
+
+    /// Stop before the first instruction
+    #[arg(long)]
+    step: bool,
+
+    /// Number of executed instructions kept in the trace
+    #[arg(long, default_value_t = 64)]
+    trace: usize,
+}
+
+fn parse_address(s: &str) -> Result<u32, String> {
+    u32::from_str_radix(s.trim_start_matches("0x"), 16).map_err(|e| format!("Invalid address {}: {}", s, e))
+}
+
+/// Prompt on stdin whenever the debugger stops
+fn prompt(pc: u32, registers: &[u32; 32], trace: &ExecutionTrace) -> DebugCommand {
+    let stdin = io::stdin();
+    println!("stopped at {:#010x}", pc);
+
+    loop {
+        print!("(c)ontinue, (s)tep, (r)egisters, (t)race, (q)uit> ");
+        let _ = io::stdout().flush();
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+            return DebugCommand::Abort;
+        }
+
+        match line.trim() {
+            "c" => return DebugCommand::Continue,
+            "s" | "" => return DebugCommand::Step,
+            "q" => return DebugCommand::Abort,
+            "r" => {
+                for (reg, value) in registers.iter().enumerate() {
+                    println!("x{:<2} = {:#010x}", reg, value);
+                }
+            }
+            "t" => print!("{}", trace),
+            other => println!("unknown command: {}", other),
+        }
+    }
+}
+
+/// Run a module under the debugger
+pub fn run(args: DebugArgs) -> Result<()> {
+    let bytecode = std::fs::read(&args.bytecode)
+        .with_context(|| format!("Failed to read {}", args.bytecode.display()))?;
+
+    let controller: [u8; 32] = hex::decode(&args.controller)?
+        .try_into()
+        .map_err(|_| anyhow!("Controller ID must be 32 bytes"))?;
+    let instruction = Instruction::new(
+        UnitsObjectId::new(controller),
+        args.function,
+        vec![],
+        hex::decode(&args.params)?,
+    );
+    let context = ExecutionContext::new(instruction, HashMap::new(), 0, 0);
+
+    let executor = RiscVExecutor::with_config(RiscVExecutorConfig {
+        trace_capacity: args.trace,
+        ..RiscVExecutorConfig::default()
+    });
+
+    let mut debugger = Debugger::new(prompt);
+    for pc in args.breakpoints {
+        debugger.add_breakpoint(pc);
+    }
+    if args.step {
+        debugger = debugger.single_step();
+    }
+
+    match executor.debug(&bytecode, &context, &mut debugger) {
+        Ok(effects) => {
+            println!("execution succeeded with {} effect(s)", effects.len());
+            Ok(())
+        }
+        Err(failure) => {
+            print!("{}", failure.trace);
+            Err(failure.error.into())
+        }
+    }
+}
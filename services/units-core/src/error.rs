@@ -1,4 +1,6 @@
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use units_core_types::error::StorageError;
 
 #[derive(Error, Debug)]
 pub enum ServiceError {
@@ -22,9 +24,23 @@ pub enum ServiceError {
     TransactionFailed { reason: String },
 
     #[error("Service unavailable: {message}")]
-    #[allow(dead_code)]
     ServiceUnavailable { message: String },
 
+    #[error("Unauthorized: {message}")]
+    Unauthorized { message: String },
+
+    #[error("Backpressure: retry after {retry_after_ms}ms")]
+    Backpressure { retry_after_ms: u64 },
+
+    #[error("Controller not allowed: {controller_id}")]
+    ControllerNotAllowed { controller_id: units_core_types::UnitsObjectId },
+
+    #[error("Deadline exceeded for request {trace_id}")]
+    DeadlineExceeded { trace_id: String },
+
+    #[error("Storage failed preflight checks\n{report}")]
+    PreflightFailed { report: Box<crate::services::PreflightReport> },
+
     #[error("Internal error: {0}")]
     Internal(#[from] anyhow::Error),
 }
@@ -49,12 +65,87 @@ impl ServiceError {
         }
     }
 
-    #[allow(dead_code)]
     pub fn service_unavailable(message: impl Into<String>) -> Self {
         Self::ServiceUnavailable {
             message: message.into(),
         }
     }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self::Unauthorized {
+            message: message.into(),
+        }
+    }
+
+    pub fn backpressure(retry_after_ms: u64) -> Self {
+        Self::Backpressure { retry_after_ms }
+    }
+
+    pub fn deadline_exceeded(trace_id: impl Into<String>) -> Self {
+        Self::DeadlineExceeded {
+            trace_id: trace_id.into(),
+        }
+    }
+
+    /// Whether sending the same request again may succeed
+    ///
+    /// Overload, unavailability and contended or failing storage are
+    /// transient; rejected input and authorization failures are not.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Backpressure { .. } | Self::ServiceUnavailable { .. } => true,
+            Self::Storage(error) => matches!(
+                error,
+                StorageError::Io(_)
+                    | StorageError::LockError(_)
+                    | StorageError::LockTimeout(_)
+                    | StorageError::DeadlockDetected(_)
+            ),
+            _ => false,
+        }
+    }
+
+    /// Wait the error itself asks for before a retry
+    pub fn retry_after_ms(&self) -> Option<u64> {
+        match self {
+            Self::Backpressure { retry_after_ms } => Some(*retry_after_ms),
+            _ => None,
+        }
+    }
+}
+
+/// How loaded the node's transaction queue is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeLoad {
+    /// Queue under half its capacity
+    Normal,
+    /// Queue at least half full
+    Busy,
+    /// Queue full; new transactions see backpressure
+    Saturated,
+}
+
+impl NodeLoad {
+    /// Classify a queue holding `queued` of `capacity` transactions
+    pub fn from_queue(queued: usize, capacity: usize) -> Self {
+        if queued >= capacity {
+            Self::Saturated
+        } else if queued.saturating_mul(2) >= capacity {
+            Self::Busy
+        } else {
+            Self::Normal
+        }
+    }
+
+    /// Backoff to suggest for a retryable error that gave no wait of its own
+    pub fn suggested_backoff_ms(self) -> u64 {
+        match self {
+            Self::Normal => 100,
+            Self::Busy => 500,
+            Self::Saturated => 2_000,
+        }
+    }
 }
 
 pub type ServiceResult<T> = Result<T, ServiceError>;
\ No newline at end of file
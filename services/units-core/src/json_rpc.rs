@@ -1,24 +1,69 @@
 use anyhow::Result;
-use jsonrpsee::core::async_trait;
+use jsonrpsee::core::{async_trait, SubscriptionResult};
 use jsonrpsee::proc_macros::rpc;
-use jsonrpsee::server::ServerBuilder;
+use jsonrpsee::server::middleware::rpc::{RpcServiceBuilder, RpcServiceT};
+use jsonrpsee::server::{MethodResponse, PendingSubscriptionSink, ServerBuilder, SubscriptionMessage};
+use jsonrpsee::types::Request;
 use jsonrpsee::types::error::{ErrorCode, ErrorObject};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
 use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
 
 use units_core_types::id::UnitsObjectId;
-use units_core_types::objects::UnitsObject;
+use units_core_types::objects::{UnitsObject, VersionedObject};
 use units_core_types::transaction::{Transaction, TransactionReceipt};
+use units_core_types::{FeeEstimate, ModuleEntry, ModuleErrorCode, PrefetchRule, ReceiptPage, ResourceClass};
+use units_storage_impl::IndexRecommendation;
 
-use crate::error::ServiceError;
-use crate::service::{UnitsService, HealthStatus};
+use crate::config::ControllerPolicy;
+use crate::error::{NodeLoad, ServiceError};
+use crate::service::{UnitsService, HealthStatus, NodeIdentity, StateRoot, ObjectRootPath, ObjectRootVerification};
+use crate::service::{DerivedObjectId, LightSync, ObjectInclusion, ReceiptChunk, StateProofChunk, MAX_RANGE_CHUNK};
+use crate::signing::ResponseSignature;
+use crate::services::{ReadMetadata, SandboxInfo, SandboxChange, AdminAuth, AdminOperation, AdminReport, LegalHoldAudit};
+use crate::services::{LoadMonitor, MaintenanceStatus};
+use crate::services::{TokenBalance, TokenHolders, ActivityPage, ShadowReport, Attestation};
+use crate::services::{Collection, CollectionMembers, SlotStatus, TransactionGraph};
+use crate::services::{Watch, WatchDelivery, WatchFilter, WatchTarget};
+use crate::verify::{CollectionProof, ExistenceReceipt, SlotSummaryReceipt};
+
+/// Error code returned when the transaction pipeline applies backpressure
+pub const BACKPRESSURE_ERROR_CODE: i32 = -32005;
+
+/// HTTP response header carrying the slot of the latest state the node had
+/// committed when it answered
+pub const SLOT_HEADER: &str = "x-units-slot";
+
+/// HTTP response header carrying the hex-encoded object root of that slot
+pub const STATE_ROOT_HEADER: &str = "x-units-state-root";
+
+/// Error code returned when an admin call lacks the admin role
+pub const UNAUTHORIZED_ERROR_CODE: i32 = -32006;
+
+/// Error code returned when the node's controller policy refuses a transaction
+pub const CONTROLLER_NOT_ALLOWED_ERROR_CODE: i32 = -32007;
+
+/// Error code returned when a request runs past the server's request timeout
+pub const DEADLINE_EXCEEDED_ERROR_CODE: i32 = -32008;
 
 /// JSON-RPC API trait definition
 #[rpc(server)]
 pub trait UnitsJsonRpcApi {
-    /// Get object by ID
+    /// Get object by ID with its write version
+    ///
+    /// The version can be passed back as an expected version to make a
+    /// transaction fail if the object changed in between.
     #[method(name = "getObject")]
-    async fn get_object(&self, object_id: String) -> Result<UnitsObject, ErrorObject<'static>>;
+    async fn get_object(&self, object_id: String) -> Result<VersionedObject, ErrorObject<'static>>;
+
+    /// Get object by ID with read staleness metadata
+    ///
+    /// When response signing is enabled the object is signed with the node key.
+    #[method(name = "getObjectWithMetadata")]
+    async fn get_object_with_metadata(&self, object_id: String) -> Result<ObjectReadResponse, ErrorObject<'static>>;
 
     /// Submit transaction
     #[method(name = "submitTransaction")]
@@ -36,6 +81,163 @@ pub trait UnitsJsonRpcApi {
     #[method(name = "getCurrentSlot")]
     async fn get_current_slot(&self) -> Result<u64, ErrorObject<'static>>;
 
+    /// Get the latest processed, confirmed and finalized slots
+    #[method(name = "getSlotStatus")]
+    async fn get_slot_status(&self) -> Result<SlotStatus, ErrorObject<'static>>;
+
+    /// Get the state root committed for a slot
+    #[method(name = "getStateRoot")]
+    async fn get_state_root(&self, slot: u64) -> Result<StateRoot, ErrorObject<'static>>;
+
+    /// Receipts of a slot range in chunks of up to `limit` (default and max 1000)
+    ///
+    /// Call again from `next_slot` until it is absent to walk the whole range.
+    #[method(name = "getReceiptsChunk")]
+    async fn get_receipts_chunk(&self, start_slot: u64, end_slot: u64, limit: Option<usize>) -> Result<ReceiptChunk, ErrorObject<'static>>;
+
+    /// Receipts of a slot range in pages of up to `limit` (default and max 1000)
+    ///
+    /// Pages may split a slot. Hex-encode `next` and pass it back as `after`
+    /// until it is absent to walk the whole range.
+    #[method(name = "getReceiptsPage")]
+    async fn get_receipts_page(&self, start_slot: u64, end_slot: u64, after: Option<String>, limit: Option<usize>) -> Result<ReceiptPage, ErrorObject<'static>>;
+
+    /// Receipts affecting an object, oldest first, paged like `getReceiptsPage`
+    #[method(name = "getObjectReceipts")]
+    async fn get_object_receipts(&self, object_id: String, after: Option<String>, limit: Option<usize>) -> Result<ReceiptPage, ErrorObject<'static>>;
+
+    /// Dependencies among the transactions of a slot range, from the
+    /// states each one read or overwrote; continue from `next_slot`
+    #[method(name = "getTransactionGraph")]
+    async fn get_transaction_graph(&self, start_slot: u64, end_slot: u64) -> Result<TransactionGraph, ErrorObject<'static>>;
+
+    /// State proofs of a slot range in chunks of up to `limit` (default and max 1000)
+    #[method(name = "getStateProofsChunk")]
+    async fn get_state_proofs_chunk(&self, start_slot: u64, end_slot: u64, limit: Option<usize>) -> Result<StateProofChunk, ErrorObject<'static>>;
+
+    /// Merkle path of an object under the latest committed state root
+    #[method(name = "getObjectInclusion")]
+    async fn get_object_inclusion(&self, object_id: String) -> Result<ObjectInclusion, ErrorObject<'static>>;
+
+    /// Watched objects changed since `since_slot`, with inclusion proofs against the latest root
+    #[method(name = "lightSync")]
+    async fn light_sync(&self, since_slot: u64, object_ids: Vec<String>) -> Result<LightSyncResponse, ErrorObject<'static>>;
+
+    /// Verify an object against an object root using its Merkle path
+    #[method(name = "verifyObjectAgainstRoot")]
+    async fn verify_object_against_root(&self, object: UnitsObject, path: ObjectRootPath) -> Result<ObjectRootVerification, ErrorObject<'static>>;
+
+    /// Suggest a priority fee for inclusion within `target_slots` slots (default 1)
+    #[method(name = "getFeeEstimate")]
+    async fn get_fee_estimate(&self, target_slots: Option<u64>) -> Result<FeeEstimate, ErrorObject<'static>>;
+
+    /// Fee ledger balance of an account, such as a sponsor's public key
+    #[method(name = "getFeeBalance")]
+    async fn get_fee_balance(&self, account_id: String) -> Result<u64, ErrorObject<'static>>;
+
+    /// Object ID an external identifier such as an ISIN or DID maps to
+    ///
+    /// Uses the namespace's latest scheme version unless `version` is given.
+    #[method(name = "deriveObjectId")]
+    async fn derive_object_id(&self, namespace: String, identifier: String, version: Option<u16>) -> Result<DerivedObjectId, ErrorObject<'static>>;
+
+    /// Attest that a document with this hex-encoded hash exists
+    #[method(name = "attestDocument")]
+    async fn attest_document(&self, document_hash: String, attester_id: String) -> Result<Attestation, ErrorObject<'static>>;
+
+    /// Proof that an attested document existed by its attested slot
+    #[method(name = "getExistenceReceipt")]
+    async fn get_existence_receipt(&self, document_hash: String) -> Result<ExistenceReceipt, ErrorObject<'static>>;
+
+    /// Resource usage of a closed slot, with evidence against its state root
+    #[method(name = "getSlotSummary")]
+    async fn get_slot_summary(&self, slot: u64) -> Result<SlotSummaryReceipt, ErrorObject<'static>>;
+
+    /// Shadow re-execution checks run so far and their recent divergences
+    #[method(name = "getShadowReport")]
+    async fn get_shadow_report(&self) -> Result<ShadowReport, ErrorObject<'static>>;
+
+    /// List deployed controllers with their code hashes, versions and ABI locations
+    #[method(name = "listControllers")]
+    async fn list_controllers(&self) -> Result<Vec<ModuleEntry>, ErrorObject<'static>>;
+
+    /// Deploy a hex-encoded module artifact built by `units-build` as a controller
+    #[method(name = "deployModule")]
+    async fn deploy_module(&self, controller_id: UnitsObjectId, artifact: String) -> Result<ModuleEntry, ErrorObject<'static>>;
+
+    /// Register the names and messages of a deployed controller's exit codes
+    ///
+    /// Failed receipts then name the code, e.g. `ERROR_INVALID_USERNAME`,
+    /// instead of reporting a bare exit status.
+    #[method(name = "setControllerErrorCodes")]
+    async fn set_controller_error_codes(&self, controller_id: UnitsObjectId, error_codes: BTreeMap<u32, ModuleErrorCode>) -> Result<ModuleEntry, ErrorObject<'static>>;
+
+    /// Publish (or clear) where a deployed controller's ABI can be fetched
+    #[method(name = "setControllerAbi")]
+    async fn set_controller_abi(&self, controller_id: UnitsObjectId, location: Option<String>) -> Result<ModuleEntry, ErrorObject<'static>>;
+
+    /// Declare the objects a controller function reads beyond its targets
+    ///
+    /// Execution derives and loads them automatically; an empty list clears
+    /// the function's rules.
+    #[method(name = "setPrefetchRules")]
+    async fn set_prefetch_rules(&self, controller_id: UnitsObjectId, function: String, rules: Vec<PrefetchRule>) -> Result<ModuleEntry, ErrorObject<'static>>;
+
+    /// Set or, with a null value, remove a metadata entry of an object the controller controls
+    #[method(name = "setObjectMetadata")]
+    async fn set_object_metadata(&self, controller_id: UnitsObjectId, object_id: UnitsObjectId, key: String, value: Option<String>) -> Result<BTreeMap<String, String>, ErrorObject<'static>>;
+
+    /// Designate (or, with null, remove) the controller that must approve
+    /// instructions touching an account the controller controls
+    ///
+    /// Returns the previous authorizer.
+    #[method(name = "setAccountAuthorizer")]
+    async fn set_account_authorizer(&self, controller_id: UnitsObjectId, account_id: UnitsObjectId, authorizer: Option<UnitsObjectId>) -> Result<Option<UnitsObjectId>, ErrorObject<'static>>;
+
+    /// Controller approving instructions that touch an account, if any
+    #[method(name = "getAccountAuthorizer")]
+    async fn get_account_authorizer(&self, account_id: UnitsObjectId) -> Result<Option<UnitsObjectId>, ErrorObject<'static>>;
+
+    /// Metadata entries of an object
+    #[method(name = "getObjectMetadata")]
+    async fn get_object_metadata(&self, object_id: UnitsObjectId) -> Result<BTreeMap<String, String>, ErrorObject<'static>>;
+
+    /// Objects of a controller whose metadata maps `key` to `value`
+    #[method(name = "findByMetadata")]
+    async fn find_by_metadata(&self, controller_id: UnitsObjectId, key: String, value: String) -> Result<Vec<UnitsObjectId>, ErrorObject<'static>>;
+
+    /// Fork current state into a new simulation sandbox
+    #[method(name = "createSandbox")]
+    async fn create_sandbox(&self) -> Result<SandboxInfo, ErrorObject<'static>>;
+
+    /// List open simulation sandboxes
+    #[method(name = "listSandboxes")]
+    async fn list_sandboxes(&self) -> Result<Vec<SandboxInfo>, ErrorObject<'static>>;
+
+    /// Execute a transaction in a sandbox without touching the primary store
+    #[method(name = "sandboxExecuteTransaction")]
+    async fn sandbox_execute_transaction(&self, namespace: String, transaction: Transaction) -> Result<TransactionReceipt, ErrorObject<'static>>;
+
+    /// Get an object as a sandbox sees it
+    #[method(name = "sandboxGetObject")]
+    async fn sandbox_get_object(&self, namespace: String, object_id: UnitsObjectId) -> Result<VersionedObject, ErrorObject<'static>>;
+
+    /// Objects a sandbox has changed relative to the primary store
+    #[method(name = "sandboxGetChanges")]
+    async fn sandbox_get_changes(&self, namespace: String) -> Result<Vec<SandboxChange>, ErrorObject<'static>>;
+
+    /// Receipts of the transactions executed in a sandbox
+    #[method(name = "sandboxGetReceipts")]
+    async fn sandbox_get_receipts(&self, namespace: String) -> Result<Vec<TransactionReceipt>, ErrorObject<'static>>;
+
+    /// Discard a sandbox, returning whether it existed
+    #[method(name = "discardSandbox")]
+    async fn discard_sandbox(&self, namespace: String) -> Result<bool, ErrorObject<'static>>;
+
+    /// Public key this node signs responses with
+    #[method(name = "getNodeIdentity")]
+    async fn get_node_identity(&self) -> Result<NodeIdentity, ErrorObject<'static>>;
+
     /// Health check
     #[method(name = "health")]
     async fn health(&self) -> Result<HealthStatus, ErrorObject<'static>>;
@@ -45,6 +247,204 @@ pub trait UnitsJsonRpcApi {
     async fn version(&self) -> Result<VersionInfo, ErrorObject<'static>>;
 }
 
+/// Operational RPCs, served as `admin_*`
+///
+/// Every call carries an `AdminAuth` with the admin key. Setting `dry_run`
+/// reports what the call would change; destructive calls (compact, prune,
+/// truncateWal) only run when given the confirmation token from a dry run.
+#[rpc(server, namespace = "admin")]
+pub trait UnitsAdminRpcApi {
+    /// Drop object versions made redundant by a history cutoff at `before_slot`
+    #[method(name = "compact")]
+    async fn compact(&self, auth: AdminAuth, before_slot: u64) -> Result<AdminReport, ErrorObject<'static>>;
+
+    /// Drop state proofs committed before `before_slot`
+    #[method(name = "prune")]
+    async fn prune(&self, auth: AdminAuth, before_slot: u64) -> Result<AdminReport, ErrorObject<'static>>;
+
+    /// Write every object with its proof chain to `name` in the data directory
+    #[method(name = "snapshot")]
+    async fn snapshot(&self, auth: AdminAuth, name: String) -> Result<AdminReport, ErrorObject<'static>>;
+
+    /// Verify every object's proof chain, listing the objects that fail
+    #[method(name = "scrub")]
+    async fn scrub(&self, auth: AdminAuth) -> Result<AdminReport, ErrorObject<'static>>;
+
+    /// Rewrite proof chains whose slots regress, listing the offending proofs
+    #[method(name = "repairSlotOrder")]
+    async fn repair_slot_order(&self, auth: AdminAuth) -> Result<AdminReport, ErrorObject<'static>>;
+
+    /// Reject new transactions that invoke a controller
+    #[method(name = "pauseController")]
+    async fn pause_controller(&self, auth: AdminAuth, controller_id: UnitsObjectId) -> Result<AdminReport, ErrorObject<'static>>;
+
+    /// Accept transactions for a paused controller again
+    #[method(name = "resumeController")]
+    async fn resume_controller(&self, auth: AdminAuth, controller_id: UnitsObjectId) -> Result<AdminReport, ErrorObject<'static>>;
+
+    /// Replace the controller allow and deny lists enforced at admission
+    #[method(name = "setControllerPolicy")]
+    async fn set_controller_policy(&self, auth: AdminAuth, policy: ControllerPolicy) -> Result<AdminReport, ErrorObject<'static>>;
+
+    /// Drop every entry of the write-ahead log
+    #[method(name = "truncateWal")]
+    async fn truncate_wal(&self, auth: AdminAuth) -> Result<AdminReport, ErrorObject<'static>>;
+
+    /// Rebuild the state proofs missing in `[start_slot, end_slot]`, listing their slots
+    #[method(name = "regenerateStateProofs")]
+    async fn regenerate_state_proofs(&self, auth: AdminAuth, start_slot: u64, end_slot: u64) -> Result<AdminReport, ErrorObject<'static>>;
+
+    /// Hold back background compaction, pruning and scrubbing
+    #[method(name = "pauseMaintenance")]
+    async fn pause_maintenance(&self, auth: AdminAuth) -> Result<AdminReport, ErrorObject<'static>>;
+
+    /// Let background maintenance run again
+    #[method(name = "resumeMaintenance")]
+    async fn resume_maintenance(&self, auth: AdminAuth) -> Result<AdminReport, ErrorObject<'static>>;
+
+    /// Whether background maintenance is running and the load it last saw
+    #[method(name = "maintenanceStatus")]
+    async fn maintenance_status(&self, auth: AdminAuth) -> Result<MaintenanceStatus, ErrorObject<'static>>;
+
+    /// Keep a controller's deleted objects, hidden from reads, until the hold is released
+    #[method(name = "placeLegalHold")]
+    async fn place_legal_hold(&self, auth: AdminAuth, controller_id: UnitsObjectId) -> Result<AdminReport, ErrorObject<'static>>;
+
+    /// Lift a legal hold, discarding the objects it retained
+    #[method(name = "releaseLegalHold")]
+    async fn release_legal_hold(&self, auth: AdminAuth, controller_id: UnitsObjectId) -> Result<AdminReport, ErrorObject<'static>>;
+
+    /// Controllers under legal hold and the deleted objects they retain
+    #[method(name = "legalHoldAudit")]
+    async fn legal_hold_audit(&self, auth: AdminAuth, controller_id: Option<UnitsObjectId>) -> Result<LegalHoldAudit, ErrorObject<'static>>;
+
+    /// Query patterns that would benefit from an index, most objects visited first
+    #[method(name = "indexRecommendations")]
+    async fn index_recommendations(&self, auth: AdminAuth) -> Result<Vec<IndexRecommendation>, ErrorObject<'static>>;
+
+    /// Index a controller's objects, reporting how many the index holds
+    #[method(name = "createIndex")]
+    async fn create_index(&self, auth: AdminAuth, controller_id: UnitsObjectId) -> Result<AdminReport, ErrorObject<'static>>;
+
+    /// Assign the execution budgets a deployed controller runs with
+    #[method(name = "setResourceClass")]
+    async fn set_resource_class(&self, auth: AdminAuth, controller_id: UnitsObjectId, class: ResourceClass) -> Result<AdminReport, ErrorObject<'static>>;
+}
+
+/// Token balance queries, served as `token_*`
+///
+/// Object IDs are hex-encoded. Wallets pass the token and owner and never
+/// need to know which object holds a balance. Results built from a scan
+/// cut short by the node's scan limits carry a `scan_cursor` to pass back.
+#[rpc(server, namespace = "token")]
+pub trait UnitsTokenRpcApi {
+    /// Amount of `token_id` held by `owner_id`, zero if none
+    #[method(name = "getBalance")]
+    async fn get_balance(
+        &self,
+        owner_id: String,
+        token_id: String,
+        scan_cursor: Option<String>,
+    ) -> Result<TokenBalance, ErrorObject<'static>>;
+
+    /// Holders of `token_id` by descending balance, 100 per page
+    #[method(name = "getHolders")]
+    async fn get_holders(
+        &self,
+        token_id: String,
+        page: u32,
+        scan_cursor: Option<String>,
+    ) -> Result<TokenHolders, ErrorObject<'static>>;
+}
+
+/// Account-centric queries, served as `account_*`
+#[rpc(server, namespace = "account")]
+pub trait UnitsAccountRpcApi {
+    /// Receipts and object changes involving the account and the objects it
+    /// controls, newest first; pass `next_cursor` back for older entries and
+    /// `scan_cursor` back for the objects a cut-short scan did not reach
+    #[method(name = "getActivity")]
+    async fn get_activity(
+        &self,
+        account_id: String,
+        cursor: Option<String>,
+        scan_cursor: Option<String>,
+    ) -> Result<ActivityPage, ErrorObject<'static>>;
+}
+
+/// Object collections, served as `collection_*`
+///
+/// A collection is named by its owner and lists member object IDs; its
+/// proof lets clients check the whole set against one state root.
+#[rpc(server, namespace = "collection")]
+pub trait UnitsCollectionRpcApi {
+    /// Create an empty collection named `name` owned by `owner_id`
+    #[method(name = "create")]
+    async fn create(&self, owner_id: String, name: String) -> Result<Collection, ErrorObject<'static>>;
+
+    /// Add and then remove members; only the owner may edit
+    #[method(name = "update")]
+    async fn update(&self, collection_id: String, owner_id: String, add: Vec<String>, remove: Vec<String>) -> Result<Collection, ErrorObject<'static>>;
+
+    /// Member objects in ID order, 100 per page
+    #[method(name = "getMembers")]
+    async fn get_members(&self, collection_id: String, page: u32) -> Result<CollectionMembers, ErrorObject<'static>>;
+
+    /// The collection and every member's latest proof under one state root
+    #[method(name = "getProof")]
+    async fn get_proof(&self, collection_id: String) -> Result<CollectionProof, ErrorObject<'static>>;
+}
+
+/// Durable watches on objects and controllers, served as `watch_*`
+///
+/// A watch's ID is its only handle: whoever holds it may subscribe to or
+/// remove the watch.
+#[rpc(server, namespace = "watch")]
+pub trait UnitsWatchRpcApi {
+    /// Watch one object or every object of a controller; pass exactly one
+    ///
+    /// Notifications are POSTed to `webhook_url` when given, and otherwise
+    /// delivered to `watch_subscribe` subscribers.
+    #[method(name = "register")]
+    async fn register(
+        &self,
+        object_id: Option<String>,
+        controller_id: Option<String>,
+        filter: Option<WatchFilter>,
+        webhook_url: Option<String>,
+    ) -> Result<Watch, ErrorObject<'static>>;
+
+    /// Remove a watch, returning whether it existed
+    #[method(name = "remove")]
+    async fn remove(&self, watch_id: String) -> Result<bool, ErrorObject<'static>>;
+
+    /// A watch and the sequence of its latest notification
+    #[method(name = "get")]
+    async fn get(&self, watch_id: String) -> Result<Watch, ErrorObject<'static>>;
+
+    /// Notifications of a WebSocket watch, starting with those buffered
+    /// after `since_sequence` so a reconnecting client misses nothing
+    #[subscription(name = "subscribe" => "notification", unsubscribe = "unsubscribe", item = WatchNotification)]
+    async fn subscribe(&self, watch_id: String, since_sequence: Option<u64>) -> SubscriptionResult;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ObjectReadResponse {
+    pub object: UnitsObject,
+    pub metadata: ReadMetadata,
+    /// Node attestation over `object`, present when response signing is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<ResponseSignature>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LightSyncResponse {
+    pub sync: LightSync,
+    /// Node attestation over `sync`, present when response signing is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<ResponseSignature>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct VersionInfo {
     pub version: String,
@@ -52,6 +452,48 @@ pub struct VersionInfo {
     pub build_time: String,
 }
 
+/// Retry guidance sent as the `data` of every service error
+///
+/// Clients decide whether and when to retry from these fields instead of
+/// matching on error messages.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RetryMetadata {
+    /// Whether sending the same request again may succeed
+    pub retryable: bool,
+    /// Suggested wait before retrying, present when `retryable`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_ms: Option<u64>,
+    /// Load of the node that answered; another node may do better
+    pub node_load: NodeLoad,
+    /// Error-specific fields, such as the controller a policy refused
+    #[serde(flatten)]
+    pub details: serde_json::Map<String, serde_json::Value>,
+}
+
+impl RetryMetadata {
+    /// Metadata for `error` raised while the node was under `node_load`
+    pub fn for_error(error: &ServiceError, node_load: NodeLoad) -> Self {
+        let retryable = error.is_retryable();
+        Self {
+            retryable,
+            retry_after_ms: retryable
+                .then(|| error.retry_after_ms().unwrap_or_else(|| node_load.suggested_backoff_ms())),
+            node_load,
+            details: serde_json::Map::new(),
+        }
+    }
+}
+
+/// Read the metadata from an RPC error received by a client
+impl TryFrom<&ErrorObject<'_>> for RetryMetadata {
+    type Error = serde_json::Error;
+
+    fn try_from(error: &ErrorObject<'_>) -> Result<Self, Self::Error> {
+        let data = error.data().map_or("null", |data| data.get());
+        serde_json::from_str(data)
+    }
+}
+
 /// JSON-RPC server implementation
 #[derive(Clone)]
 pub struct JsonRpcServerImpl {
@@ -64,11 +506,23 @@ impl JsonRpcServerImpl {
     }
 
     pub async fn start(&self, addr: SocketAddr) -> Result<impl std::future::Future<Output = ()>> {
+        let load = self.service.load_monitor();
         let server = ServerBuilder::default()
+            .set_http_middleware(tower::ServiceBuilder::new().layer(StateEchoLayer { service: self.service.clone() }))
+            .set_rpc_middleware(RpcServiceBuilder::new().layer_fn(move |service| LoadTracking {
+                service,
+                load: load.clone(),
+            }))
             .build(addr)
             .await?;
 
-        let handle = server.start(self.clone().into_rpc());
+        let mut module = UnitsJsonRpcApiServer::into_rpc(self.clone());
+        module.merge(UnitsAdminRpcApiServer::into_rpc(self.clone()))?;
+        module.merge(UnitsTokenRpcApiServer::into_rpc(self.clone()))?;
+        module.merge(UnitsAccountRpcApiServer::into_rpc(self.clone()))?;
+        module.merge(UnitsCollectionRpcApiServer::into_rpc(self.clone()))?;
+        module.merge(UnitsWatchRpcApiServer::into_rpc(self.clone()))?;
+        let handle = server.start(module);
         
         Ok(async move {
             handle.stopped().await
@@ -101,55 +555,147 @@ impl JsonRpcServerImpl {
         Ok(array)
     }
 
-    fn map_service_error(err: ServiceError) -> ErrorObject<'static> {
-        match err {
+    /// Map a service error to its RPC error, with retry metadata as `data`
+    fn map_service_error(&self, err: ServiceError) -> ErrorObject<'static> {
+        let mut metadata = RetryMetadata::for_error(&err, self.service.node_load());
+        let (code, message) = match err {
             ServiceError::ObjectNotFound { object_id } => {
-                ErrorObject::owned(
-                    ErrorCode::InvalidParams.code(),
-                    format!("Object not found: {}", object_id),
-                    None::<()>,
-                )
-            }
-            ServiceError::InvalidRequest { message } => {
-                ErrorObject::owned(
-                    ErrorCode::InvalidParams.code(),
-                    message,
-                    None::<()>,
-                )
+                (ErrorCode::InvalidParams.code(), format!("Object not found: {}", object_id))
             }
+            ServiceError::InvalidRequest { message } => (ErrorCode::InvalidParams.code(), message),
             ServiceError::TransactionFailed { reason } => {
-                ErrorObject::owned(
-                    ErrorCode::InternalError.code(),
-                    format!("Transaction failed: {}", reason),
-                    None::<()>,
-                )
+                (ErrorCode::InternalError.code(), format!("Transaction failed: {}", reason))
             }
-            _ => {
-                ErrorObject::owned(
-                    ErrorCode::InternalError.code(),
-                    err.to_string(),
-                    None::<()>,
+            ServiceError::Unauthorized { message } => (UNAUTHORIZED_ERROR_CODE, message),
+            ServiceError::Backpressure { retry_after_ms } => {
+                (BACKPRESSURE_ERROR_CODE, format!("Server busy, retry after {}ms", retry_after_ms))
+            }
+            ServiceError::ControllerNotAllowed { controller_id } => {
+                metadata.details.insert("controller_id".to_string(), controller_id.to_string().into());
+                (
+                    CONTROLLER_NOT_ALLOWED_ERROR_CODE,
+                    format!("Controller {} is not allowed on this node", controller_id),
                 )
             }
-        }
+            ServiceError::DeadlineExceeded { trace_id } => {
+                metadata.details.insert("trace_id".to_string(), trace_id.clone().into());
+                (DEADLINE_EXCEEDED_ERROR_CODE, format!("Request {} exceeded its deadline", trace_id))
+            }
+            _ => (ErrorCode::InternalError.code(), err.to_string()),
+        };
+        ErrorObject::owned(code, message, Some(metadata))
+    }
+}
+
+/// Counts every call as in flight for the maintenance scheduler's load checks
+#[derive(Clone)]
+struct LoadTracking<S> {
+    service: S,
+    load: Arc<LoadMonitor>,
+}
+
+impl<'a, S> RpcServiceT<'a> for LoadTracking<S>
+where
+    S: RpcServiceT<'a> + Send + Sync + Clone + 'static,
+{
+    type Future = Pin<Box<dyn Future<Output = MethodResponse> + Send + 'a>>;
+
+    fn call(&self, request: Request<'a>) -> Self::Future {
+        let request_guard = self.load.begin_request();
+        let response = self.service.call(request);
+        Box::pin(async move {
+            let response = response.await;
+            drop(request_guard);
+            response
+        })
+    }
+}
+
+/// Echoes the node's latest committed slot and state root with every response
+///
+/// JSON-RPC clients refuse response members other than `jsonrpc`, `result`,
+/// `error` and `id`, so the echo travels in the HTTP envelope as
+/// [`SLOT_HEADER`] and [`STATE_ROOT_HEADER`]. It is read before the request
+/// runs: the response reflects at least the state it names.
+#[derive(Clone)]
+struct StateEchoLayer {
+    service: UnitsService,
+}
+
+impl<S> tower::Layer<S> for StateEchoLayer {
+    type Service = StateEcho<S>;
+
+    fn layer(&self, inner: S) -> StateEcho<S> {
+        StateEcho { inner, service: self.service.clone() }
+    }
+}
+
+#[derive(Clone)]
+struct StateEcho<S> {
+    inner: S,
+    service: UnitsService,
+}
+
+impl<S, B> tower::Service<hyper::Request<B>> for StateEcho<S>
+where
+    S: tower::Service<hyper::Request<B>, Response = hyper::Response<hyper::Body>>,
+    S::Future: Send + 'static,
+{
+    type Response = hyper::Response<hyper::Body>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: hyper::Request<B>) -> Self::Future {
+        let context = self.service.response_context();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let mut response = response.await?;
+            let headers = response.headers_mut();
+            headers.insert(SLOT_HEADER, hyper::header::HeaderValue::from(context.slot));
+            if let Some(root) = context.state_root {
+                let root = hyper::header::HeaderValue::from_str(&hex::encode(root)).expect("hex is a valid header value");
+                headers.insert(STATE_ROOT_HEADER, root);
+            }
+            Ok(response)
+        })
     }
 }
 
 #[async_trait]
 impl UnitsJsonRpcApiServer for JsonRpcServerImpl {
-    async fn get_object(&self, object_id: String) -> Result<UnitsObject, ErrorObject<'static>> {
+    async fn get_object(&self, object_id: String) -> Result<VersionedObject, ErrorObject<'static>> {
         let parsed_id = Self::parse_object_id(&object_id)?;
-        self.service
-            .get_object(&parsed_id)
+        let (object, metadata) = self.service
+            .get_object_with_metadata(&parsed_id)
             .await
-            .map_err(Self::map_service_error)
+            .map_err(|err| self.map_service_error(err))?;
+        Ok(VersionedObject { object, version: metadata.version })
+    }
+
+    async fn get_object_with_metadata(&self, object_id: String) -> Result<ObjectReadResponse, ErrorObject<'static>> {
+        let parsed_id = Self::parse_object_id(&object_id)?;
+        let (object, metadata) = self.service
+            .get_object_with_metadata(&parsed_id)
+            .await
+            .map_err(|err| self.map_service_error(err))?;
+
+        let signature = self.service
+            .sign_response(&object)
+            .await
+            .map_err(|err| self.map_service_error(err))?;
+
+        Ok(ObjectReadResponse { object, metadata, signature })
     }
 
     async fn submit_transaction(&self, transaction: Transaction) -> Result<String, ErrorObject<'static>> {
         let tx_hash = self.service
             .submit_transaction(transaction)
             .await
-            .map_err(Self::map_service_error)?;
+            .map_err(|err| self.map_service_error(err))?;
         
         Ok(hex::encode(tx_hash))
     }
@@ -159,7 +705,7 @@ impl UnitsJsonRpcApiServer for JsonRpcServerImpl {
         self.service
             .get_transaction(&parsed_hash)
             .await
-            .map_err(Self::map_service_error)
+            .map_err(|err| self.map_service_error(err))
     }
 
     async fn execute_transaction(&self, tx_hash: String) -> Result<TransactionReceipt, ErrorObject<'static>> {
@@ -167,21 +713,287 @@ impl UnitsJsonRpcApiServer for JsonRpcServerImpl {
         self.service
             .get_transaction_receipt(&parsed_hash)
             .await
-            .map_err(Self::map_service_error)
+            .map_err(|err| self.map_service_error(err))
     }
 
     async fn get_current_slot(&self) -> Result<u64, ErrorObject<'static>> {
         self.service
             .get_current_slot()
             .await
-            .map_err(Self::map_service_error)
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn get_slot_status(&self) -> Result<SlotStatus, ErrorObject<'static>> {
+        self.service
+            .get_slot_status()
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn get_state_root(&self, slot: u64) -> Result<StateRoot, ErrorObject<'static>> {
+        self.service
+            .get_state_root(slot)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn get_receipts_chunk(&self, start_slot: u64, end_slot: u64, limit: Option<usize>) -> Result<ReceiptChunk, ErrorObject<'static>> {
+        self.service
+            .get_receipts_chunk(&self.service.request_context(), start_slot, end_slot, limit.unwrap_or(MAX_RANGE_CHUNK))
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn get_receipts_page(&self, start_slot: u64, end_slot: u64, after: Option<String>, limit: Option<usize>) -> Result<ReceiptPage, ErrorObject<'static>> {
+        let after = after.as_deref().map(Self::parse_tx_hash).transpose()?;
+        self.service
+            .get_receipts_page(&self.service.request_context(), start_slot, end_slot, after, limit.unwrap_or(MAX_RANGE_CHUNK))
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn get_object_receipts(&self, object_id: String, after: Option<String>, limit: Option<usize>) -> Result<ReceiptPage, ErrorObject<'static>> {
+        let object_id = Self::parse_object_id(&object_id)?;
+        let after = after.as_deref().map(Self::parse_tx_hash).transpose()?;
+        self.service
+            .get_object_receipts(&self.service.request_context(), &object_id, after, limit.unwrap_or(MAX_RANGE_CHUNK))
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn get_transaction_graph(&self, start_slot: u64, end_slot: u64) -> Result<TransactionGraph, ErrorObject<'static>> {
+        self.service
+            .get_transaction_graph(&self.service.request_context(), start_slot, end_slot)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn get_state_proofs_chunk(&self, start_slot: u64, end_slot: u64, limit: Option<usize>) -> Result<StateProofChunk, ErrorObject<'static>> {
+        self.service
+            .get_state_proofs_chunk(&self.service.request_context(), start_slot, end_slot, limit.unwrap_or(MAX_RANGE_CHUNK))
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn get_object_inclusion(&self, object_id: String) -> Result<ObjectInclusion, ErrorObject<'static>> {
+        let object_id = Self::parse_object_id(&object_id)?;
+        self.service
+            .get_object_inclusion(&object_id)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn light_sync(&self, since_slot: u64, object_ids: Vec<String>) -> Result<LightSyncResponse, ErrorObject<'static>> {
+        let object_ids = object_ids
+            .iter()
+            .map(|id| Self::parse_object_id(id))
+            .collect::<Result<Vec<_>, _>>()?;
+        let sync = self.service
+            .light_sync(since_slot, &object_ids)
+            .await
+            .map_err(|err| self.map_service_error(err))?;
+
+        let signature = self.service
+            .sign_response(&sync)
+            .await
+            .map_err(|err| self.map_service_error(err))?;
+
+        Ok(LightSyncResponse { sync, signature })
+    }
+
+    async fn verify_object_against_root(&self, object: UnitsObject, path: ObjectRootPath) -> Result<ObjectRootVerification, ErrorObject<'static>> {
+        self.service
+            .verify_object_against_root(&object, &path)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn get_fee_estimate(&self, target_slots: Option<u64>) -> Result<FeeEstimate, ErrorObject<'static>> {
+        self.service
+            .get_fee_estimate(target_slots.unwrap_or(1))
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn get_fee_balance(&self, account_id: String) -> Result<u64, ErrorObject<'static>> {
+        let account_id = Self::parse_object_id(&account_id)?;
+        self.service
+            .get_fee_balance(&account_id)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn derive_object_id(&self, namespace: String, identifier: String, version: Option<u16>) -> Result<DerivedObjectId, ErrorObject<'static>> {
+        self.service
+            .derive_object_id(&namespace, &identifier, version)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn attest_document(&self, document_hash: String, attester_id: String) -> Result<Attestation, ErrorObject<'static>> {
+        let document_hash = Self::parse_tx_hash(&document_hash)?;
+        let attester_id = Self::parse_object_id(&attester_id)?;
+        self.service
+            .attest_document(document_hash, attester_id)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn get_existence_receipt(&self, document_hash: String) -> Result<ExistenceReceipt, ErrorObject<'static>> {
+        let document_hash = Self::parse_tx_hash(&document_hash)?;
+        self.service
+            .get_existence_receipt(&document_hash)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn get_slot_summary(&self, slot: u64) -> Result<SlotSummaryReceipt, ErrorObject<'static>> {
+        self.service
+            .get_slot_summary(slot)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn get_shadow_report(&self) -> Result<ShadowReport, ErrorObject<'static>> {
+        self.service
+            .shadow_report()
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn list_controllers(&self) -> Result<Vec<ModuleEntry>, ErrorObject<'static>> {
+        self.service
+            .list_controllers()
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn deploy_module(&self, controller_id: UnitsObjectId, artifact: String) -> Result<ModuleEntry, ErrorObject<'static>> {
+        let artifact = hex::decode(&artifact)
+            .map_err(|e| ErrorObject::owned(ErrorCode::InvalidParams.code(), format!("Invalid hex: {}", e), None::<()>))?;
+        self.service
+            .deploy_module_artifact(controller_id, &artifact)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn set_controller_error_codes(&self, controller_id: UnitsObjectId, error_codes: BTreeMap<u32, ModuleErrorCode>) -> Result<ModuleEntry, ErrorObject<'static>> {
+        self.service
+            .set_controller_error_codes(&controller_id, error_codes)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn set_controller_abi(&self, controller_id: UnitsObjectId, location: Option<String>) -> Result<ModuleEntry, ErrorObject<'static>> {
+        self.service
+            .set_controller_abi(&controller_id, location)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn set_prefetch_rules(&self, controller_id: UnitsObjectId, function: String, rules: Vec<PrefetchRule>) -> Result<ModuleEntry, ErrorObject<'static>> {
+        self.service
+            .set_prefetch_rules(&controller_id, &function, rules)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn set_object_metadata(&self, controller_id: UnitsObjectId, object_id: UnitsObjectId, key: String, value: Option<String>) -> Result<BTreeMap<String, String>, ErrorObject<'static>> {
+        self.service
+            .set_object_metadata(&controller_id, &object_id, &key, value.as_deref())
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn set_account_authorizer(&self, controller_id: UnitsObjectId, account_id: UnitsObjectId, authorizer: Option<UnitsObjectId>) -> Result<Option<UnitsObjectId>, ErrorObject<'static>> {
+        self.service
+            .set_account_authorizer(&controller_id, &account_id, authorizer)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn get_account_authorizer(&self, account_id: UnitsObjectId) -> Result<Option<UnitsObjectId>, ErrorObject<'static>> {
+        self.service
+            .get_account_authorizer(&account_id)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn get_object_metadata(&self, object_id: UnitsObjectId) -> Result<BTreeMap<String, String>, ErrorObject<'static>> {
+        self.service
+            .get_object_metadata(&object_id)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn find_by_metadata(&self, controller_id: UnitsObjectId, key: String, value: String) -> Result<Vec<UnitsObjectId>, ErrorObject<'static>> {
+        self.service
+            .find_by_metadata(&controller_id, &key, &value)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn create_sandbox(&self) -> Result<SandboxInfo, ErrorObject<'static>> {
+        self.service
+            .create_sandbox()
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn list_sandboxes(&self) -> Result<Vec<SandboxInfo>, ErrorObject<'static>> {
+        self.service
+            .list_sandboxes()
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn sandbox_execute_transaction(&self, namespace: String, transaction: Transaction) -> Result<TransactionReceipt, ErrorObject<'static>> {
+        self.service
+            .sandbox_execute_transaction(&self.service.request_context(), &namespace, transaction)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn sandbox_get_object(&self, namespace: String, object_id: UnitsObjectId) -> Result<VersionedObject, ErrorObject<'static>> {
+        self.service
+            .sandbox_get_object(&namespace, &object_id)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn sandbox_get_changes(&self, namespace: String) -> Result<Vec<SandboxChange>, ErrorObject<'static>> {
+        self.service
+            .sandbox_get_changes(&namespace)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn sandbox_get_receipts(&self, namespace: String) -> Result<Vec<TransactionReceipt>, ErrorObject<'static>> {
+        self.service
+            .sandbox_get_receipts(&namespace)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn discard_sandbox(&self, namespace: String) -> Result<bool, ErrorObject<'static>> {
+        self.service
+            .discard_sandbox(&namespace)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn get_node_identity(&self) -> Result<NodeIdentity, ErrorObject<'static>> {
+        self.service
+            .node_identity()
+            .await
+            .map_err(|err| self.map_service_error(err))
     }
 
     async fn health(&self) -> Result<HealthStatus, ErrorObject<'static>> {
         self.service
             .health_check()
             .await
-            .map_err(Self::map_service_error)
+            .map_err(|err| self.map_service_error(err))
     }
 
     async fn version(&self) -> Result<VersionInfo, ErrorObject<'static>> {
@@ -191,4 +1003,302 @@ impl UnitsJsonRpcApiServer for JsonRpcServerImpl {
             build_time: env!("BUILD_TIME").to_string(),
         })
     }
-}
\ No newline at end of file
+}
+
+#[async_trait]
+impl UnitsAdminRpcApiServer for JsonRpcServerImpl {
+    async fn compact(&self, auth: AdminAuth, before_slot: u64) -> Result<AdminReport, ErrorObject<'static>> {
+        self.service
+            .admin(&auth, AdminOperation::Compact { before_slot })
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn prune(&self, auth: AdminAuth, before_slot: u64) -> Result<AdminReport, ErrorObject<'static>> {
+        self.service
+            .admin(&auth, AdminOperation::Prune { before_slot })
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn snapshot(&self, auth: AdminAuth, name: String) -> Result<AdminReport, ErrorObject<'static>> {
+        self.service
+            .admin(&auth, AdminOperation::Snapshot { name })
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn scrub(&self, auth: AdminAuth) -> Result<AdminReport, ErrorObject<'static>> {
+        self.service
+            .admin(&auth, AdminOperation::Scrub)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn repair_slot_order(&self, auth: AdminAuth) -> Result<AdminReport, ErrorObject<'static>> {
+        self.service
+            .admin(&auth, AdminOperation::RepairSlotOrder)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn pause_controller(&self, auth: AdminAuth, controller_id: UnitsObjectId) -> Result<AdminReport, ErrorObject<'static>> {
+        self.service
+            .admin(&auth, AdminOperation::PauseController { controller_id })
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn resume_controller(&self, auth: AdminAuth, controller_id: UnitsObjectId) -> Result<AdminReport, ErrorObject<'static>> {
+        self.service
+            .admin(&auth, AdminOperation::ResumeController { controller_id })
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn set_controller_policy(&self, auth: AdminAuth, policy: ControllerPolicy) -> Result<AdminReport, ErrorObject<'static>> {
+        self.service
+            .admin(&auth, AdminOperation::SetControllerPolicy { policy })
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn truncate_wal(&self, auth: AdminAuth) -> Result<AdminReport, ErrorObject<'static>> {
+        self.service
+            .admin(&auth, AdminOperation::TruncateWal)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn regenerate_state_proofs(&self, auth: AdminAuth, start_slot: u64, end_slot: u64) -> Result<AdminReport, ErrorObject<'static>> {
+        self.service
+            .admin(&auth, AdminOperation::RegenerateStateProofs { start_slot, end_slot })
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn pause_maintenance(&self, auth: AdminAuth) -> Result<AdminReport, ErrorObject<'static>> {
+        self.service
+            .admin(&auth, AdminOperation::PauseMaintenance)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn resume_maintenance(&self, auth: AdminAuth) -> Result<AdminReport, ErrorObject<'static>> {
+        self.service
+            .admin(&auth, AdminOperation::ResumeMaintenance)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn maintenance_status(&self, auth: AdminAuth) -> Result<MaintenanceStatus, ErrorObject<'static>> {
+        self.service
+            .maintenance_status(&auth)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn place_legal_hold(&self, auth: AdminAuth, controller_id: UnitsObjectId) -> Result<AdminReport, ErrorObject<'static>> {
+        self.service
+            .admin(&auth, AdminOperation::PlaceLegalHold { controller_id })
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn release_legal_hold(&self, auth: AdminAuth, controller_id: UnitsObjectId) -> Result<AdminReport, ErrorObject<'static>> {
+        self.service
+            .admin(&auth, AdminOperation::ReleaseLegalHold { controller_id })
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn legal_hold_audit(&self, auth: AdminAuth, controller_id: Option<UnitsObjectId>) -> Result<LegalHoldAudit, ErrorObject<'static>> {
+        self.service
+            .legal_hold_audit(&auth, controller_id.as_ref())
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn index_recommendations(&self, auth: AdminAuth) -> Result<Vec<IndexRecommendation>, ErrorObject<'static>> {
+        self.service
+            .index_recommendations(&auth)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn create_index(&self, auth: AdminAuth, controller_id: UnitsObjectId) -> Result<AdminReport, ErrorObject<'static>> {
+        self.service
+            .admin(&auth, AdminOperation::CreateIndex { controller_id })
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn set_resource_class(&self, auth: AdminAuth, controller_id: UnitsObjectId, class: ResourceClass) -> Result<AdminReport, ErrorObject<'static>> {
+        self.service
+            .admin(&auth, AdminOperation::SetResourceClass { controller_id, class })
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+}
+
+#[async_trait]
+impl UnitsTokenRpcApiServer for JsonRpcServerImpl {
+    async fn get_balance(
+        &self,
+        owner_id: String,
+        token_id: String,
+        scan_cursor: Option<String>,
+    ) -> Result<TokenBalance, ErrorObject<'static>> {
+        let owner_id = Self::parse_object_id(&owner_id)?;
+        let token_id = Self::parse_object_id(&token_id)?;
+        self.service
+            .get_token_balance(&self.service.request_context(), &owner_id, &token_id, scan_cursor.as_deref())
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn get_holders(
+        &self,
+        token_id: String,
+        page: u32,
+        scan_cursor: Option<String>,
+    ) -> Result<TokenHolders, ErrorObject<'static>> {
+        let token_id = Self::parse_object_id(&token_id)?;
+        self.service
+            .get_token_holders(&self.service.request_context(), &token_id, page, scan_cursor.as_deref())
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+}
+
+#[async_trait]
+impl UnitsAccountRpcApiServer for JsonRpcServerImpl {
+    async fn get_activity(
+        &self,
+        account_id: String,
+        cursor: Option<String>,
+        scan_cursor: Option<String>,
+    ) -> Result<ActivityPage, ErrorObject<'static>> {
+        let account_id = Self::parse_object_id(&account_id)?;
+        self.service
+            .get_account_activity(&self.service.request_context(), &account_id, cursor.as_deref(), scan_cursor.as_deref())
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+}
+
+#[async_trait]
+impl UnitsCollectionRpcApiServer for JsonRpcServerImpl {
+    async fn create(&self, owner_id: String, name: String) -> Result<Collection, ErrorObject<'static>> {
+        let owner_id = Self::parse_object_id(&owner_id)?;
+        self.service
+            .create_collection(owner_id, &name)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn update(&self, collection_id: String, owner_id: String, add: Vec<String>, remove: Vec<String>) -> Result<Collection, ErrorObject<'static>> {
+        let collection_id = Self::parse_object_id(&collection_id)?;
+        let owner_id = Self::parse_object_id(&owner_id)?;
+        let add = add.iter().map(|id| Self::parse_object_id(id)).collect::<Result<Vec<_>, _>>()?;
+        let remove = remove.iter().map(|id| Self::parse_object_id(id)).collect::<Result<Vec<_>, _>>()?;
+        self.service
+            .update_collection(&collection_id, &owner_id, &add, &remove)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn get_members(&self, collection_id: String, page: u32) -> Result<CollectionMembers, ErrorObject<'static>> {
+        let collection_id = Self::parse_object_id(&collection_id)?;
+        self.service
+            .get_collection_members(&self.service.request_context(), &collection_id, page)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn get_proof(&self, collection_id: String) -> Result<CollectionProof, ErrorObject<'static>> {
+        let collection_id = Self::parse_object_id(&collection_id)?;
+        self.service
+            .get_collection_proof(&collection_id)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+}
+
+#[async_trait]
+impl UnitsWatchRpcApiServer for JsonRpcServerImpl {
+    async fn register(
+        &self,
+        object_id: Option<String>,
+        controller_id: Option<String>,
+        filter: Option<WatchFilter>,
+        webhook_url: Option<String>,
+    ) -> Result<Watch, ErrorObject<'static>> {
+        let target = match (object_id, controller_id) {
+            (Some(object_id), None) => WatchTarget::Object { object_id: Self::parse_object_id(&object_id)? },
+            (None, Some(controller_id)) => WatchTarget::Controller { controller_id: Self::parse_object_id(&controller_id)? },
+            _ => {
+                return Err(ErrorObject::owned(
+                    ErrorCode::InvalidParams.code(),
+                    "Pass exactly one of object_id and controller_id",
+                    None::<()>,
+                ))
+            }
+        };
+        let delivery = match webhook_url {
+            Some(url) => WatchDelivery::Webhook { url },
+            None => WatchDelivery::WebSocket,
+        };
+        self.service
+            .register_watch(target, filter.unwrap_or_default(), delivery)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn remove(&self, watch_id: String) -> Result<bool, ErrorObject<'static>> {
+        self.service
+            .remove_watch(&watch_id)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn get(&self, watch_id: String) -> Result<Watch, ErrorObject<'static>> {
+        self.service
+            .get_watch(&watch_id)
+            .await
+            .map_err(|err| self.map_service_error(err))
+    }
+
+    async fn subscribe(&self, pending: PendingSubscriptionSink, watch_id: String, since_sequence: Option<u64>) -> SubscriptionResult {
+        let (backlog, mut live) = match self.service.subscribe_watch(&watch_id, since_sequence.unwrap_or(0)) {
+            Ok(stream) => stream,
+            Err(err) => {
+                pending.reject(self.map_service_error(err)).await;
+                return Ok(());
+            }
+        };
+        let sink = pending.accept().await?;
+
+        let mut sent = since_sequence.unwrap_or(0);
+        for notification in backlog {
+            sent = notification.sequence;
+            sink.send(SubscriptionMessage::from_json(&notification)?).await?;
+        }
+        loop {
+            tokio::select! {
+                received = live.recv() => match received {
+                    Ok(notification) if notification.watch_id == watch_id && notification.sequence > sent => {
+                        sent = notification.sequence;
+                        sink.send(SubscriptionMessage::from_json(&notification)?).await?;
+                    }
+                    Ok(_) => {}
+                    // Missed notifications are in the backlog; the client
+                    // resubscribes from the last sequence it saw
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => return Err("Subscriber lagged behind".into()),
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => return Ok(()),
+                },
+                _ = sink.closed() => return Ok(()),
+            }
+        }
+    }
+}
@@ -3,15 +3,21 @@
 //! This library provides the core service layer for the UNITS system,
 //! including transaction processing, object management, and proof generation.
 
+pub mod client;
 pub mod config;
+pub mod context;
+pub mod scan;
 pub mod error;
 pub mod json_rpc;
 pub mod server;
 pub mod service;
 pub mod services;
+pub mod signing;
+pub mod verify;
 
 // Re-export commonly used types
 pub use config::Config;
+pub use context::RequestContext;
 pub use error::{ServiceError, ServiceResult};
 pub use service::UnitsService;
 pub use services::{MinimalServiceFactory, MinimalServiceContainer};
\ No newline at end of file
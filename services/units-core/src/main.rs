@@ -1,15 +1,20 @@
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use log::info;
 use std::net::SocketAddr;
 use tokio::signal;
 
 mod config;
+mod context;
+mod scan;
+mod debugger;
 mod error;
 mod json_rpc;
 mod server;
 mod service;
 mod services;
+mod signing;
+mod verify;
 
 use config::Config;
 use server::UnitsServer;
@@ -29,6 +34,17 @@ struct Args {
     /// Log level
     #[arg(long, default_value = "info")]
     log_level: String,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a controller module under the VM debugger
+    DebugModule(debugger::DebugArgs),
+    /// Fetch an object from a node and verify it locally against its state root
+    Verify(verify::VerifyArgs),
 }
 
 #[tokio::main]
@@ -40,6 +56,12 @@ async fn main() -> Result<()> {
         env_logger::Env::default().default_filter_or(&args.log_level)
     ).init();
 
+    match args.command {
+        Some(Command::DebugModule(debug_args)) => return debugger::run(debug_args),
+        Some(Command::Verify(verify_args)) => return verify::run(verify_args).await,
+        None => {}
+    }
+
     info!("Starting UNITS Core service");
 
     // Load configuration
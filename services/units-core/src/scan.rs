@@ -0,0 +1,193 @@
+//! Resource guards for store scans made while serving a request
+//!
+//! A query that walks the object store takes time proportional to the
+//! store, not to its answer. Wrapping the walk in a [`GuardedScan`] bounds
+//! it: the scan yields its thread every so often, and stops once it has
+//! visited `max_rows` objects or run for `max_scan_ms`. A stopped scan hands
+//! back a continuation cursor naming the last object it visited; passing the
+//! cursor to the next request resumes the scan just after it, so a large
+//! query completes over several bounded calls instead of pinning a core.
+//!
+//! The request's deadline still applies: past it the scan fails rather than
+//! returning partial results the client is no longer waiting for.
+
+use std::ops::Bound;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use units_core_types::{StorageError, UnitsObject, UnitsObjectId};
+
+use crate::context::RequestContext;
+use crate::error::{ServiceError, ServiceResult};
+
+/// Why a scan returned before visiting every object
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScanStop {
+    RowLimit,
+    TimeLimit,
+}
+
+/// ID bounds resuming a scan after `cursor`, or covering every ID without one
+pub fn resume_bounds(cursor: Option<&str>) -> ServiceResult<(Bound<UnitsObjectId>, Bound<UnitsObjectId>)> {
+    let Some(cursor) = cursor else {
+        return Ok((Bound::Unbounded, Bound::Unbounded));
+    };
+    let after = hex::decode(cursor)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| ServiceError::invalid_request(format!("Invalid scan cursor: {}", cursor)))?;
+    Ok((Bound::Excluded(UnitsObjectId::new(after)), Bound::Unbounded))
+}
+
+/// Object iterator stopping at the row, time and deadline bounds of a request
+///
+/// The wrapped iterator must yield objects in ascending ID order, as the
+/// store's `iter` and `iter_range` do, for continuations to be exact.
+pub struct GuardedScan<'c, I> {
+    objects: I,
+    ctx: &'c RequestContext,
+    max_rows: usize,
+    max_duration: Duration,
+    yield_every: usize,
+    started: Instant,
+    rows: usize,
+    last_id: Option<UnitsObjectId>,
+    stopped: Option<ScanStop>,
+}
+
+impl<'c, I> GuardedScan<'c, I>
+where
+    I: Iterator<Item = Result<UnitsObject, StorageError>>,
+{
+    /// Guard `objects` with the scan limits of `ctx`
+    pub fn new(objects: I, ctx: &'c RequestContext) -> Self {
+        let limits = &ctx.scan;
+        Self {
+            objects,
+            ctx,
+            max_rows: limits.max_rows.max(1),
+            max_duration: Duration::from_millis(limits.max_scan_ms),
+            yield_every: limits.yield_every,
+            started: Instant::now(),
+            rows: 0,
+            last_id: None,
+            stopped: None,
+        }
+    }
+
+    /// Why the scan ended early, or `None` if it ran to the end
+    #[allow(dead_code)]
+    pub fn stopped(&self) -> Option<ScanStop> {
+        self.stopped
+    }
+
+    /// Objects visited so far
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Cursor resuming after the last object visited, if the scan ended early
+    pub fn continuation(&self) -> Option<String> {
+        self.stopped?;
+        self.last_id.map(|id| hex::encode(id.bytes()))
+    }
+
+    fn over_budget(&self) -> Option<ScanStop> {
+        if self.rows >= self.max_rows {
+            Some(ScanStop::RowLimit)
+        } else if self.rows > 0 && self.started.elapsed() >= self.max_duration {
+            Some(ScanStop::TimeLimit)
+        } else {
+            None
+        }
+    }
+}
+
+impl<I> Iterator for GuardedScan<'_, I>
+where
+    I: Iterator<Item = Result<UnitsObject, StorageError>>,
+{
+    type Item = ServiceResult<UnitsObject>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stopped.is_some() {
+            return None;
+        }
+        if let Err(err) = self.ctx.check() {
+            return Some(Err(err));
+        }
+        if self.yield_every > 0 && self.rows > 0 && self.rows % self.yield_every == 0 {
+            std::thread::yield_now();
+        }
+
+        let object = match self.objects.next()? {
+            Ok(object) => object,
+            Err(err) => return Some(Err(err.into())),
+        };
+        // Only report a stop when something is left, so an exhausted scan
+        // never hands out a cursor leading nowhere
+        if let Some(stop) = self.over_budget() {
+            self.stopped = Some(stop);
+            return None;
+        }
+        self.rows += 1;
+        self.last_id = Some(*object.id());
+        Some(Ok(object))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScanConfig;
+
+    fn objects(count: u8) -> Vec<Result<UnitsObject, StorageError>> {
+        (1..=count)
+            .map(|seed| {
+                let id = UnitsObjectId::new([seed; 32]);
+                Ok(UnitsObject::new_data(id, id, vec![seed]))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_scan_stops_at_row_limit_and_resumes() {
+        let limits = ScanConfig { max_rows: 2, ..ScanConfig::default() };
+        let ctx = RequestContext::new().with_scan_limits(limits.clone());
+
+        let mut scan = GuardedScan::new(objects(5).into_iter(), &ctx);
+        let first: Vec<_> = scan.by_ref().map(|object| *object.unwrap().id()).collect();
+        assert_eq!(first, vec![UnitsObjectId::new([1; 32]), UnitsObjectId::new([2; 32])]);
+        assert_eq!(scan.stopped(), Some(ScanStop::RowLimit));
+
+        let cursor = scan.continuation().unwrap();
+        let bounds = resume_bounds(Some(&cursor)).unwrap();
+        let rest = objects(5).into_iter().filter(|object| {
+            use std::ops::RangeBounds;
+            bounds.contains(object.as_ref().unwrap().id())
+        });
+        let ctx = RequestContext::new().with_scan_limits(ScanConfig { max_rows: 3, ..limits });
+        let mut scan = GuardedScan::new(rest, &ctx);
+        assert_eq!(scan.by_ref().count(), 3);
+        // Exactly at the limit with nothing left is a complete scan
+        assert_eq!(scan.stopped(), None);
+        assert_eq!(scan.continuation(), None);
+
+        assert!(resume_bounds(Some("zz")).is_err());
+    }
+
+    #[test]
+    fn test_scan_honours_time_limit_and_deadline() {
+        let limits = ScanConfig { max_scan_ms: 0, ..ScanConfig::default() };
+        let ctx = RequestContext::new().with_scan_limits(limits);
+        let mut scan = GuardedScan::new(objects(3).into_iter(), &ctx);
+        // Always makes progress before giving up on time
+        assert_eq!(scan.by_ref().count(), 1);
+        assert_eq!(scan.stopped(), Some(ScanStop::TimeLimit));
+
+        let expired = RequestContext::new().with_timeout(Duration::ZERO);
+        let mut scan = GuardedScan::new(objects(3).into_iter(), &expired);
+        assert!(matches!(scan.next(), Some(Err(ServiceError::DeadlineExceeded { .. }))));
+    }
+}
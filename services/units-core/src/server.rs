@@ -2,11 +2,12 @@ use anyhow::Result;
 use std::net::SocketAddr;
 use std::sync::Arc;
 
-use units_runtime_impl::MockRuntime;
+use units_runtime_impl::{MockRuntime, WriteQuota, WriteStats};
 use units_storage_impl::ConsolidatedUnitsStorage;
 
 use crate::config::Config;
 use crate::service::UnitsService;
+use crate::signing::NodeSigner;
 
 pub struct UnitsServer {
     service: UnitsService,
@@ -14,14 +15,33 @@ pub struct UnitsServer {
 
 impl UnitsServer {
     pub async fn new(config: Config) -> Result<Self> {
+        config.retention.validate()?;
+        config.shadow.validate()?;
+        config
+            .pipeline
+            .validate()
+            .map_err(|e| anyhow::anyhow!("pipeline.{}", e))?;
+
         // Initialize storage based on config
         let storage = match config.storage.storage_type.as_str() {
             "memory" => {
-                Arc::new(ConsolidatedUnitsStorage::create())
+                Arc::new(
+                    ConsolidatedUnitsStorage::with_history_depth(config.storage.history_depth)
+                        .with_receipt_codec(config.storage.receipt_codec.clone())
+                        .with_slot_ordering(config.storage.slot_ordering)
+                        .with_hash_algorithm(config.storage.hash_algorithm)
+                        .with_index_advisor(config.storage.indexes.clone())
+                )
             }
             "file" => {
-                // Would initialize file-based storage here
-                Arc::new(ConsolidatedUnitsStorage::create())
+                // Would initialize file-based storage, and its WAL codec, here
+                Arc::new(
+                    ConsolidatedUnitsStorage::create()
+                        .with_receipt_codec(config.storage.receipt_codec.clone())
+                        .with_slot_ordering(config.storage.slot_ordering)
+                        .with_hash_algorithm(config.storage.hash_algorithm)
+                        .with_index_advisor(config.storage.indexes.clone())
+                )
             }
             _ => {
                 anyhow::bail!("Unsupported storage type: {}", config.storage.storage_type);
@@ -29,10 +49,24 @@ impl UnitsServer {
         };
 
         // Initialize runtime (using mock for now)
-        let runtime: Arc<dyn units_core_types::Runtime + Send + Sync> = Arc::new(MockRuntime::new());
+        let runtime: Arc<dyn units_core_types::Runtime + Send + Sync> = Arc::new(build_runtime(&config));
+        let shadow_runtime = Arc::new(build_runtime(&config));
+
+        // Load the node key before the config moves into the service
+        let signer = if config.signing.enabled {
+            let key_file = config.signing.key_file.as_deref()
+                .ok_or_else(|| anyhow::anyhow!("Response signing is enabled but signing.key_file is not set"))?;
+            Some(Arc::new(NodeSigner::from_key_file(key_file)?))
+        } else {
+            None
+        };
 
         // Create service
-        let service = UnitsService::new(storage, runtime, config);
+        let mut service = UnitsService::new(storage, runtime, config)
+            .with_shadow_runtime(shadow_runtime);
+        if let Some(signer) = signer {
+            service = service.with_signer(signer);
+        }
 
         Ok(Self { service })
     }
@@ -42,6 +76,8 @@ impl UnitsServer {
         addr: SocketAddr,
     ) -> Result<impl std::future::Future<Output = ()>> {
         use crate::json_rpc::JsonRpcServerImpl;
+
+        self.service.start().await?;
         
         let server_impl = JsonRpcServerImpl::new(self.service.clone());
         let server = server_impl.start(addr).await?;
@@ -50,4 +86,47 @@ impl UnitsServer {
             server.await
         })
     }
-}
\ No newline at end of file
+}
+
+/// Runtime with the effect processors and storage rent `config` selects
+///
+/// Shadow runtimes are built the same way, so vetoes, annotations and
+/// deposits are part of what shadow execution compares.
+fn build_runtime(config: &Config) -> MockRuntime {
+    let processors = &config.effect_processors;
+    let mut runtime = MockRuntime::new();
+    if let Some(rent) = config.rent.storage_rent() {
+        runtime = runtime.with_storage_rent(rent);
+    }
+    if processors.max_effects.is_some() || processors.max_bytes_written.is_some() {
+        runtime = runtime.with_effect_processor(Arc::new(WriteQuota {
+            max_effects: processors.max_effects,
+            max_bytes_written: processors.max_bytes_written,
+        }));
+    }
+    if processors.write_stats {
+        runtime = runtime.with_effect_processor(Arc::new(WriteStats));
+    }
+    runtime
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use units_core_types::{Runtime, StorageRentConfig};
+
+    #[test]
+    fn test_rent_section_enables_storage_rent() {
+        let mut config = Config::default();
+        assert_eq!(build_runtime(&config).storage_rent_config(), None);
+
+        let section: crate::config::RentConfig =
+            toml::from_str("enabled = true\nbase_deposit = 10\ndeposit_per_byte = 2").unwrap();
+        config.rent = section;
+        assert_eq!(build_runtime(&config).storage_rent_config(), Some(StorageRentConfig::new(10, 2)));
+
+        // Configured prices are ignored while rent is disabled
+        config.rent.enabled = false;
+        assert_eq!(build_runtime(&config).storage_rent_config(), None);
+    }
+}
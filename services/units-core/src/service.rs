@@ -1,19 +1,61 @@
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use units_core_types::id::UnitsObjectId;
-use units_core_types::objects::UnitsObject;
+use units_core_types::objects::{UnitsObject, VersionedObject};
 use units_core_types::transaction::{Transaction, TransactionReceipt, TransactionHash};
-use units_core_types::{Runtime, SlotNumber, ObjectStorage};
-use units_storage_impl::ConsolidatedUnitsStorage;
+use units_core_types::{Runtime, SlotNumber, ObjectStorage, ProofStorage, MerkleNode, UnitsObjectProof, FeeEstimate, StateProof, ReceiptPage};
+use units_core_types::{ModuleArtifact, ModuleEntry, ModuleErrorCode, ModuleRegistry, PrefetchRule, MODULE_REGISTRY_ID};
+use units_core_types::{DepositLedger, FeeLedger, IdDerivationRegistry, ParallelScheduler, DEPOSIT_LEDGER_ID, FEE_LEDGER_ID};
+use units_core_types::{AuthorizerRegistry, AUTHORIZER_REGISTRY_ID};
+use units_proofs::{with_proof_engine, HashAlgorithm, ProofEngine};
+use units_storage_impl::{ConsolidatedUnitsStorage, IndexRecommendation, QueryPattern};
 
 use crate::config::Config;
+use crate::context::RequestContext;
+use crate::signing::{NodeSigner, ResponseSignature};
 use crate::error::ServiceResult;
-use crate::services::{MinimalServiceFactory, MinimalServiceContainer};
+use crate::services::{MinimalServiceFactory, MinimalServiceContainer, ReadReplica, ReadMetadata, SandboxManager, SandboxInfo, SandboxChange};
+use crate::services::{Finality, SlotStatus};
+use crate::services::{AdminConsole, AdminAuth, AdminOperation, AdminReport, LegalHoldAudit};
+use crate::services::{TokenQueryService, TokenBalance, TokenHolders};
+use crate::services::{ActivityFeed, ActivityPage, TransactionGraph};
+use crate::services::{LoadMonitor, MaintenanceScheduler, MaintenanceStatus, RetentionManager, WebhookDispatcher};
+use crate::services::{ResponseCache, ResponseCacheStats, ShadowExecutor, ShadowReport};
+use crate::services::{Attestation, AttestationService, SlotSummary};
+use crate::services::{Collection, CollectionMembers, CollectionService};
+use crate::services::{Watch, WatchDelivery, WatchFilter, WatchNotification, WatchRegistry, WatchTarget};
+use crate::services::preflight;
+use crate::verify::{CollectionProof, ExistenceReceipt, ObjectEvidence, SlotSummaryReceipt};
+
+/// Most receipts or state proofs returned by one chunked range query
+pub const MAX_RANGE_CHUNK: usize = 1000;
+
+/// Most objects one light sync may watch
+pub const MAX_WATCHED_OBJECTS: usize = 256;
 
 /// Core UNITS service that handles business logic
 #[derive(Clone)]
 pub struct UnitsService {
     services: Arc<MinimalServiceContainer>,
+    replica: Option<Arc<ReadReplica>>,
+    sandboxes: Arc<SandboxManager>,
+    signer: Option<Arc<NodeSigner>>,
+    admin: Arc<AdminConsole>,
+    tokens: Arc<TokenQueryService>,
+    activity: Arc<ActivityFeed>,
+    attestations: Arc<AttestationService>,
+    collections: Arc<CollectionService>,
+    retention: Arc<RetentionManager>,
+    maintenance: Arc<MaintenanceScheduler>,
+    #[allow(dead_code)]
+    webhooks: Arc<WebhookDispatcher>,
+    watches: Arc<WatchRegistry>,
+    responses: Arc<ResponseCache>,
+    /// Latest committed slot and root, echoed with every RPC response
+    context: Arc<std::sync::RwLock<ResponseContext>>,
+    id_schemes: Arc<IdDerivationRegistry>,
+    shadow: Option<Arc<ShadowExecutor>>,
     config: Config,
 }
 
@@ -23,38 +65,356 @@ impl UnitsService {
         runtime: Arc<dyn Runtime + Send + Sync>,
         config: Config,
     ) -> Self {
-        // Create all services using the minimal factory
+        // Create all services using the minimal factory, sizing transaction batches from config
         let services = MinimalServiceFactory::create_minimal_services(
             runtime,
             storage,
-        ).expect("Failed to create services");
+        )
+        .expect("Failed to create services")
+        .with_batch_config(config.pipeline.clone())
+        .with_finality_depth(config.finality.depth);
+        services
+            .transaction_service
+            .set_scheduler(ParallelScheduler::new(config.scheduler.clone()));
+        if config.storage.persistent_locks {
+            #[cfg(feature = "sqlite")]
+            match config.storage.data_dir.as_deref() {
+                Some(data_dir) => match services.transaction_service.open_lock_table(data_dir) {
+                    Ok(recovery) => log::info!(
+                        "Lock table recovered: {} locks retained, {} released",
+                        recovery.retained,
+                        recovery.released
+                    ),
+                    Err(error) => log::error!("Cannot open the lock table, keeping locks in memory only: {}", error),
+                },
+                None => log::error!("storage.persistent_locks needs storage.data_dir to be set"),
+            }
+            #[cfg(not(feature = "sqlite"))]
+            log::error!("storage.persistent_locks needs the sqlite feature");
+        }
+
+        let replica = config.replica.enabled.then(|| {
+            Arc::new(ReadReplica::new(
+                config.replica.clone(),
+                services.storage.clone(),
+                services.slot_service.clone(),
+            ))
+        });
+
+        let sandboxes = Arc::new(SandboxManager::new(
+            config.sandbox.clone(),
+            services.storage.clone(),
+            services.runtime.clone(),
+            services.slot_service.clone(),
+        ));
+        let admin = Arc::new(AdminConsole::new(config.admin.clone(), config.controllers.clone()));
+        let tokens = Arc::new(TokenQueryService::new(
+            services.storage.clone(),
+            units_core_types::constants::TOKEN_CONTROLLER_ID,
+        ));
+        let activity = Arc::new(ActivityFeed::new(services.storage.clone()));
+        let attestations = Arc::new(AttestationService::new(
+            services.storage.clone(),
+            services.slot_service.clone(),
+            units_core_types::constants::ATTEST_CONTROLLER_ID,
+        ));
+        let collections = Arc::new(CollectionService::new(services.storage.clone()));
+        let retention = Arc::new(RetentionManager::new(config.retention.clone(), services.storage.clone()));
+        let maintenance = Arc::new(MaintenanceScheduler::new(
+            config.maintenance.clone(),
+            retention.clone(),
+            services.storage.clone(),
+            services.slot_service.clone(),
+            config.storage.slot_ordering,
+        ));
+        // An unreadable sequence file is left untouched for the operator
+        let webhooks = WebhookDispatcher::open(config.webhooks.clone(), config.storage.data_dir.as_deref())
+            .unwrap_or_else(|error| {
+                log::error!("Cannot load webhook sequences, restarting them: {}", error);
+                WebhookDispatcher::new(config.webhooks.clone())
+            });
+        let webhooks = Arc::new(webhooks);
+        // A registration file that cannot be read is left untouched for the
+        // operator rather than overwritten by new registrations
+        let watches = WatchRegistry::open(config.watches.clone(), config.storage.data_dir.as_deref())
+            .unwrap_or_else(|error| {
+                log::error!("Cannot load watch registrations, keeping new ones in memory: {}", error);
+                WatchRegistry::in_memory(config.watches.clone())
+            });
+        let mut id_schemes = IdDerivationRegistry::with_defaults();
+        for scheme in &config.id_schemes {
+            if let Err(error) = id_schemes.register(Box::new(scheme.clone())) {
+                log::error!("Skipping configured ID scheme: {}", error);
+            }
+        }
         
         Self {
             services: Arc::new(services),
+            replica,
+            sandboxes,
+            signer: None,
+            admin,
+            tokens,
+            activity,
+            attestations,
+            collections,
+            retention,
+            maintenance,
+            webhooks,
+            watches: Arc::new(watches),
+            responses: Arc::new(ResponseCache::new(config.response_cache.clone())),
+            context: Arc::default(),
+            id_schemes: Arc::new(id_schemes),
+            shadow: None,
             config,
         }
     }
     
+    /// Sign read responses with `signer`
+    pub fn with_signer(mut self, signer: Arc<NodeSigner>) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Re-execute sampled transactions on `runtime`, if shadow mode is enabled
+    ///
+    /// The shadow runtime must be separate from the node's own, so the
+    /// re-execution cannot observe or disturb committed state.
+    pub fn with_shadow_runtime(mut self, runtime: Arc<dyn Runtime + Send + Sync>) -> Self {
+        if self.config.shadow.enabled {
+            let shadow = Arc::new(ShadowExecutor::new(runtime, self.config.shadow.clone()));
+            self.services.transaction_service.set_shadow(shadow.clone());
+            self.shadow = Some(shadow);
+        }
+        self
+    }
+
+    /// Shadow checks run so far and their most recent divergences
+    pub async fn shadow_report(&self) -> ServiceResult<ShadowReport> {
+        Ok(match &self.shadow {
+            Some(shadow) => shadow.report(),
+            None => ShadowReport {
+                sample_rate: self.config.shadow.sample_rate,
+                ..ShadowReport::default()
+            },
+        })
+    }
+
+    /// Context for a new request, due within the configured request timeout
+    pub fn request_context(&self) -> RequestContext {
+        RequestContext::new()
+            .with_timeout(std::time::Duration::from_secs(self.config.server.request_timeout_secs))
+            .with_scan_limits(self.config.scan.clone())
+    }
+
+    /// Public key this node signs responses with
+    pub async fn node_identity(&self) -> ServiceResult<NodeIdentity> {
+        Ok(NodeIdentity {
+            node_key: self.signer.as_ref().map(|signer| hex::encode(signer.public_key())),
+            signing_enabled: self.signer.is_some(),
+        })
+    }
+
+    /// Attest to a response at the current slot, if signing is enabled
+    ///
+    /// The attestation commits to the object root of the latest committed
+    /// state proof, so it pins the state the response was served from.
+    pub async fn sign_response<T: serde::Serialize>(&self, response: &T) -> ServiceResult<Option<ResponseSignature>> {
+        let Some(signer) = &self.signer else {
+            return Ok(None);
+        };
+
+        use units_core_types::UnitsStorage;
+        let slot = self.services.slot_service.current_slot();
+        let state_root = match self.services.storage.proofs().get_state_proof(slot)? {
+            Some(state_proof) => Some(
+                ProofEngine::new()
+                    .state_proof_data(&state_proof)
+                    .map_err(|e| crate::error::ServiceError::Storage(e.into()))?
+                    .object_root,
+            ),
+            None => None,
+        };
+
+        signer.sign_response(response, slot, state_root).map(Some)
+    }
+
     /// Start all services
+    ///
+    /// Storage passes its preflight checks first, repairing what the
+    /// preflight config allows. Anything left unrepaired refuses startup
+    /// with the diagnostic report.
     pub async fn start(&self) -> ServiceResult<()> {
-        // Simple implementation - no-op for now
+        if self.config.preflight.enabled {
+            let report = preflight::run(&self.services.storage, self.config.preflight.repair)?;
+            for finding in &report.findings {
+                log::warn!("Preflight: {}", finding);
+            }
+            if !report.passed() {
+                return Err(crate::error::ServiceError::PreflightFailed { report: Box::new(report) });
+            }
+            if !report.findings.is_empty() {
+                self.responses.clear();
+            }
+        }
+
+        if let Some(replica) = &self.replica {
+            replica.start_refresh();
+        }
+        self.maintenance.start();
+        self.start_slot_timer();
         Ok(())
     }
 
+    /// Advance a slot every configured interval until the process exits
+    fn start_slot_timer(&self) {
+        use tokio::time::{interval, Duration, MissedTickBehavior};
+        let interval_ms = self.config.slot_timer.interval_ms;
+        if interval_ms == 0 {
+            return;
+        }
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_millis(interval_ms));
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            // The first tick completes immediately; a slot lasts a full interval
+            ticker.tick().await;
+
+            loop {
+                ticker.tick().await;
+
+                if let Err(e) = service.advance_slot().await {
+                    log::error!("Failed to advance slot: {:?}", e);
+                }
+            }
+        });
+    }
+
     /// Get object by ID
     pub async fn get_object(&self, object_id: &UnitsObjectId) -> ServiceResult<UnitsObject> {
+        self.get_object_with_metadata(object_id).await.map(|(object, _)| object)
+    }
+
+    /// Get object by ID along with read staleness metadata
+    ///
+    /// Served from the read replica when one is enabled and fresh enough,
+    /// otherwise from the primary store.
+    pub async fn get_object_with_metadata(
+        &self,
+        object_id: &UnitsObjectId,
+    ) -> ServiceResult<(UnitsObject, ReadMetadata)> {
         use units_core_types::UnitsStorage;
-        self.services.storage
-            .objects()
-            .get(object_id)
-            .map_err(crate::error::ServiceError::Storage)?
-            .ok_or_else(|| crate::error::ServiceError::object_not_found(hex::encode(object_id.bytes())))
+        let (object, metadata) = match &self.replica {
+            Some(replica) => replica.get_object(object_id).await?,
+            None => {
+                let object = self.services.storage
+                    .objects()
+                    .get(object_id)
+                    .map_err(crate::error::ServiceError::Storage)?;
+                let version = self.services.storage
+                    .objects()
+                    .version(object_id)
+                    .map_err(crate::error::ServiceError::Storage)?;
+                let slot = self.services.slot_service.current_slot();
+                (object, ReadMetadata {
+                    source: crate::services::ReadSource::Primary,
+                    snapshot_slot: slot,
+                    slots_behind: 0,
+                    staleness_ms: 0,
+                    max_staleness_ms: 0,
+                    version,
+                    finality: self.services.slot_service.read_finality(slot),
+                })
+            }
+        };
+
+        let object = object
+            .ok_or_else(|| crate::error::ServiceError::object_not_found(hex::encode(object_id.bytes())))?;
+        Ok((object, metadata))
+    }
+
+    /// Request and slot load watched by the maintenance scheduler
+    pub fn load_monitor(&self) -> Arc<LoadMonitor> {
+        self.maintenance.load().clone()
+    }
+
+    /// Whether background maintenance is running and the load it last saw
+    pub async fn maintenance_status(&self, auth: &AdminAuth) -> ServiceResult<MaintenanceStatus> {
+        self.admin.authorize(auth)?;
+        Ok(self.maintenance.status())
+    }
+
+    /// Query patterns hot and costly enough to deserve an index, most
+    /// objects visited first
+    pub async fn index_recommendations(&self, auth: &AdminAuth) -> ServiceResult<Vec<IndexRecommendation>> {
+        self.admin.authorize(auth)?;
+        Ok(self.services.storage.index_advisor().recommendations())
+    }
+
+    /// Controllers under legal hold and what they retain, only for
+    /// `controller_id` if given
+    pub async fn legal_hold_audit(&self, auth: &AdminAuth, controller_id: Option<&UnitsObjectId>) -> ServiceResult<LegalHoldAudit> {
+        self.admin.authorize(auth)?;
+        let storage = self.services.storage.inner();
+        Ok(LegalHoldAudit {
+            holds: storage.legal_holds().into_iter().filter(|id| controller_id.is_none_or(|held| held == id)).collect(),
+            retained: storage.retained_objects(controller_id),
+        })
+    }
+
+    /// Refresh the read replica snapshot immediately
+    pub async fn refresh_replica(&self) -> ServiceResult<()> {
+        match &self.replica {
+            Some(replica) => replica.refresh().await,
+            None => Ok(()),
+        }
     }
 
     /// Submit transaction to the transaction pool
+    ///
+    /// Fails with `ServiceError::Backpressure` when the pool is saturated, and
+    /// rejects transactions invoking a controller an admin has paused.
+    /// Transactions invoking a controller outside the node's controller
+    /// policy fail with `ServiceError::ControllerNotAllowed` and leave a
+    /// failed receipt carrying the same error.
     pub async fn submit_transaction(&self, transaction: Transaction) -> ServiceResult<TransactionHash> {
-        // Simple implementation - just return the hash
-        Ok(transaction.hash)
+        if let Some(instruction) = transaction.instructions.iter().find(|i| self.admin.is_paused(&i.controller_id)) {
+            return Err(crate::error::ServiceError::invalid_request(
+                format!("Controller {} is paused", instruction.controller_id)
+            ));
+        }
+        if let Some(instruction) = transaction.instructions.iter().find(|i| !self.admin.is_permitted(&i.controller_id)) {
+            return Err(self.reject_transaction(&transaction, instruction.controller_id));
+        }
+        transaction.check_memo().map_err(crate::error::ServiceError::invalid_request)?;
+        // Refuse forged sponsorships before they take a place in the queue;
+        // the runtime checks again, along with the sponsor's balance
+        if let Some(sponsorship) = &transaction.sponsorship {
+            sponsorship
+                .verify(&transaction)
+                .map_err(|reason| crate::error::ServiceError::invalid_request(format!("Invalid fee sponsorship: {}", reason)))?;
+        }
+        self.services.transaction_service.submit_transaction(transaction).await
+    }
+
+    /// Record a failed receipt for a transaction refused at admission
+    fn reject_transaction(&self, transaction: &Transaction, controller_id: UnitsObjectId) -> crate::error::ServiceError {
+        use units_core_types::{ReceiptStorage, UnitsStorage};
+
+        let error = crate::error::ServiceError::ControllerNotAllowed { controller_id };
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let slot = self.services.slot_service.current_slot();
+        let mut receipt = TransactionReceipt::new(transaction.hash, slot, false, timestamp);
+        receipt.memo = transaction.memo.clone();
+        receipt.set_error(error.to_string());
+        if let Err(e) = self.services.storage.receipts().store_receipt(&receipt) {
+            log::warn!("Failed to store rejection receipt: {}", e);
+        }
+        error
     }
 
     /// Get transaction from pool
@@ -63,22 +423,91 @@ impl UnitsService {
     }
 
     /// Get transaction receipt
-    pub async fn get_transaction_receipt(&self, _tx_hash: &TransactionHash) -> ServiceResult<TransactionReceipt> {
-        Err(crate::error::ServiceError::invalid_request("Not implemented in simple version"))
+    pub async fn get_transaction_receipt(&self, tx_hash: &TransactionHash) -> ServiceResult<TransactionReceipt> {
+        use units_core_types::{ReceiptStorage, UnitsStorage};
+
+        self.responses
+            .get_or_compute("getTransactionReceipt", tx_hash, || async {
+                self.services
+                    .storage
+                    .receipts()
+                    .get_receipt(tx_hash)?
+                    .ok_or_else(|| crate::error::ServiceError::object_not_found(hex::encode(tx_hash)))
+            })
+            .await
+    }
+
+    /// Load of the transaction queue, reported with RPC errors
+    pub fn node_load(&self) -> crate::error::NodeLoad {
+        self.services.transaction_service.load()
     }
 
     /// Get current slot number
+    /// Latest processed, confirmed and finalized slots
+    pub async fn get_slot_status(&self) -> ServiceResult<SlotStatus> {
+        Ok(self.services.slot_service.status())
+    }
+
     pub async fn get_current_slot(&self) -> ServiceResult<SlotNumber> {
         Ok(0) // Simple implementation
     }
 
+    /// Suggest a priority fee for inclusion within `target_slots` slots
+    pub async fn get_fee_estimate(&self, target_slots: u64) -> ServiceResult<FeeEstimate> {
+        Ok(self.services.transaction_service.fee_estimate(target_slots))
+    }
+
+    /// Balance an account holds in the fee ledger for sponsoring fees
+    pub async fn get_fee_balance(&self, account_id: &UnitsObjectId) -> ServiceResult<u64> {
+        use units_core_types::UnitsStorage;
+        match self.services.storage.objects().get(&FEE_LEDGER_ID)? {
+            Some(object) => Ok(FeeLedger::from_object(&object)
+                .map_err(|e| crate::error::ServiceError::Internal(e.into()))?
+                .balance_of(account_id)),
+            None => Ok(0),
+        }
+    }
+
+    /// Balance an account holds in the deposit ledger for storage deposits
+    pub async fn get_deposit_balance(&self, account_id: &UnitsObjectId) -> ServiceResult<u64> {
+        use units_core_types::UnitsStorage;
+        match self.services.storage.objects().get(&DEPOSIT_LEDGER_ID)? {
+            Some(object) => Ok(DepositLedger::from_object(&object)
+                .map_err(|e| crate::error::ServiceError::Internal(e.into()))?
+                .balance_of(account_id)),
+            None => Ok(0),
+        }
+    }
+
+    /// Object ID of an external identifier, such as an ISIN or DID
+    ///
+    /// Uses the namespace's latest scheme version unless `version` is given.
+    pub async fn derive_object_id(&self, namespace: &str, identifier: &str, version: Option<u16>) -> ServiceResult<DerivedObjectId> {
+        let version = match version {
+            Some(version) => version,
+            None => self.id_schemes.latest_version(namespace).ok_or_else(|| {
+                crate::error::ServiceError::invalid_request(format!("Unknown ID namespace {}", namespace))
+            })?,
+        };
+        let object_id = self
+            .id_schemes
+            .derive_versioned(namespace, version, identifier)
+            .map_err(|error| crate::error::ServiceError::invalid_request(error.to_string()))?;
+        Ok(DerivedObjectId {
+            namespace: namespace.to_string(),
+            version,
+            object_id: hex::encode(*object_id),
+        })
+    }
+
     /// Get service statistics
     pub async fn get_service_stats(&self) -> ServiceResult<ServiceStats> {
         Ok(ServiceStats {
             current_slot: 0,
-            pending_transactions: 0,
-            cached_objects: 0,
+            pending_transactions: self.services.transaction_service.pending_count() as u64,
+            cached_objects: self.services.storage.executables().stats().entries as u64,
             latest_proven_slot: 0,
+            response_cache: self.responses.stats(),
         })
     }
 
@@ -91,14 +520,752 @@ impl UnitsService {
             slot: 0,
             object_count: 0,
             pending_transactions: 0,
+            wal_durability: self.config.storage.wal_durability,
         })
     }
     
-    /// Advance to next slot manually
+    /// Advance to next slot manually, executing one batch of pending transactions
+    ///
+    /// The slot is closed with a state proof over the latest object proofs
+    /// and the hashes of the transactions it executed, whose receipts are
+    /// stored. Ephemeral objects expiring at the slot are deleted first.
     pub async fn advance_slot(&self) -> ServiceResult<SlotNumber> {
-        Ok(1) // Simple implementation
+        use units_core_types::{ReceiptStorage, UnitsStorage};
+        let started = std::time::Instant::now();
+        let slot = self.services.slot_service.advance_slot().await?;
+        let receipts = self.services.transaction_service.execute_next_batch(slot).await?;
+
+        let expired = self.services.storage
+            .objects()
+            .purge_expired(slot)
+            .map_err(crate::error::ServiceError::Storage)?;
+        if !expired.is_empty() {
+            log::debug!("Purged {} expired ephemeral objects at slot {}", expired.len(), slot);
+        }
+
+        // Index receipts so they can be looked up by hash and by object
+        for receipt in &receipts {
+            self.services.storage
+                .receipts()
+                .store_receipt(receipt)
+                .map_err(crate::error::ServiceError::Storage)?;
+        }
+
+        // Written before the state proof so the proof covers it
+        self.services.slot_service.record_summary(&self.services.storage, slot, &receipts)?;
+
+        let transaction_hashes: Vec<TransactionHash> = receipts
+            .iter()
+            .map(|receipt| receipt.transaction_hash)
+            .collect();
+        let state_proof = self.services.storage
+            .commit_state_proof(slot, &transaction_hashes)
+            .map_err(crate::error::ServiceError::Storage)?;
+        self.services.slot_service.confirm_slot(slot);
+        let object_root = ProofEngine::new()
+            .state_proof_data(&state_proof)
+            .map_err(|e| crate::error::ServiceError::Storage(e.into()))?
+            .object_root;
+        *self.context.write().unwrap() = ResponseContext { slot, state_root: Some(object_root) };
+
+        // Notify once the receipts are committed under the slot's state proof
+        self.webhooks.dispatch(&receipts, self.signer.as_deref())?;
+        self.notify_watches(slot, &receipts)?;
+
+        // The maintenance scheduler takes over retention when enabled
+        if !self.maintenance.is_enabled() {
+            let pruned = self.retention.enforce(slot)?;
+            if pruned.object_versions + pruned.state_proofs + pruned.receipts > 0 {
+                log::debug!(
+                    "Retention at slot {} pruned {} object versions, {} state proofs and {} receipts",
+                    slot, pruned.object_versions, pruned.state_proofs, pruned.receipts
+                );
+            }
+        }
+        self.maintenance.load().record_slot(started.elapsed());
+        self.responses.close_slot(slot);
+
+        Ok(slot)
+    }
+
+    /// Slot and object root of the latest committed state proof
+    ///
+    /// The JSON-RPC server echoes it with every response so clients can
+    /// tell when a node serves older state than one they already saw.
+    pub fn response_context(&self) -> ResponseContext {
+        *self.context.read().unwrap()
+    }
+
+    /// Get the state root committed for a slot
+    pub async fn get_state_root(&self, slot: SlotNumber) -> ServiceResult<StateRoot> {
+        self.responses
+            .get_or_compute("getStateRoot", &slot, || self.load_state_root(slot))
+            .await
+    }
+
+    async fn load_state_root(&self, slot: SlotNumber) -> ServiceResult<StateRoot> {
+        use units_core_types::UnitsStorage;
+        let state_proof = self.services.storage
+            .proofs()
+            .get_state_proof(slot)
+            .map_err(crate::error::ServiceError::Storage)?
+            .ok_or_else(|| crate::error::ServiceError::invalid_request(
+                format!("No state proof for slot {}", slot)
+            ))?;
+
+        let data = ProofEngine::new()
+            .state_proof_data(&state_proof)
+            .map_err(|e| crate::error::ServiceError::Storage(e.into()))?;
+
+        Ok(StateRoot {
+            slot,
+            object_root: hex::encode(data.object_root),
+            transaction_root: hex::encode(data.transaction_root),
+            hash_algorithm: data.hash_algorithm,
+            state_proof_hash: hex::encode(state_proof.hash()),
+            prev_state_proof_hash: state_proof.prev_state_proof_hash.map(hex::encode),
+            signature: self.signer.as_ref().map(|signer| signer.sign_state_proof(&state_proof)),
+            object_count: state_proof.object_ids.len() as u64,
+            receipt_count: data.transaction_count,
+            finality: self.services.slot_service.finality(slot),
+        })
+    }
+
+    /// Receipts in `[start_slot, end_slot]`, at most about `max_receipts` at a time
+    ///
+    /// Chunks end on a slot boundary so a slot is never split; a slot larger
+    /// than the limit is returned whole. `next_slot` is where the following
+    /// chunk starts, absent once the range is exhausted.
+    pub async fn get_receipts_chunk(
+        &self,
+        ctx: &RequestContext,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+        max_receipts: usize,
+    ) -> ServiceResult<ReceiptChunk> {
+        ctx.check()?;
+        self.responses
+            .get_or_compute("getReceiptsChunk", &(start_slot, end_slot, max_receipts), || {
+                self.load_receipts_chunk(ctx, start_slot, end_slot, max_receipts)
+            })
+            .await
+    }
+
+    async fn load_receipts_chunk(
+        &self,
+        ctx: &RequestContext,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+        max_receipts: usize,
+    ) -> ServiceResult<ReceiptChunk> {
+        use units_core_types::{ReceiptStorage, UnitsStorage};
+
+        let limit = max_receipts.clamp(1, MAX_RANGE_CHUNK);
+        let mut receipts = Vec::new();
+        let mut slots = self.services.storage.receipts().iter_receipts_by_slot(start_slot, end_slot);
+        while let Some(slot_receipts) = slots.next() {
+            ctx.check()?;
+            let (slot, slot_receipts) = slot_receipts?;
+            if !receipts.is_empty() && receipts.len() + slot_receipts.len() > limit {
+                return Ok(ReceiptChunk { receipts, next_slot: Some(slot) });
+            }
+            receipts.extend(slot_receipts);
+            if receipts.len() >= limit {
+                let next_slot = slots.next().transpose()?.map(|(slot, _)| slot);
+                return Ok(ReceiptChunk { receipts, next_slot });
+            }
+        }
+        Ok(ReceiptChunk { receipts, next_slot: None })
+    }
+
+    /// Up to `limit` receipts in `[start_slot, end_slot]` following the receipt `after`
+    ///
+    /// Unlike `get_receipts_chunk` pages may split a slot, so they stay
+    /// bounded however many receipts a slot holds. Pass `next` back as
+    /// `after` to continue.
+    pub async fn get_receipts_page(
+        &self,
+        ctx: &RequestContext,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+        after: Option<TransactionHash>,
+        limit: usize,
+    ) -> ServiceResult<ReceiptPage> {
+        use units_core_types::{ReceiptStorage, UnitsStorage};
+
+        ctx.check()?;
+        let limit = limit.clamp(1, MAX_RANGE_CHUNK);
+        self.responses
+            .get_or_compute("getReceiptsPage", &(start_slot, end_slot, after, limit), || async {
+                Ok(self.services.storage.receipts().get_receipts_range_page(start_slot, end_slot, after, limit)?)
+            })
+            .await
+    }
+
+    /// Up to `limit` receipts affecting `object_id` following the receipt `after`
+    pub async fn get_object_receipts(
+        &self,
+        ctx: &RequestContext,
+        object_id: &UnitsObjectId,
+        after: Option<TransactionHash>,
+        limit: usize,
+    ) -> ServiceResult<ReceiptPage> {
+        use units_core_types::{ReceiptStorage, UnitsStorage};
+
+        ctx.check()?;
+        let limit = limit.clamp(1, MAX_RANGE_CHUNK);
+        self.responses
+            .get_or_compute("getObjectReceipts", &(object_id, after, limit), || async {
+                Ok(self.services.storage.receipts().get_receipts_for_object_page(object_id, None, None, after, limit)?)
+            })
+            .await
+    }
+
+    /// Dependency graph of the transactions in `[start_slot, end_slot]`
+    ///
+    /// Covers the receipts of one full-size receipts chunk, so long ranges
+    /// continue from `next_slot`; edges only join transactions within the
+    /// same response.
+    pub async fn get_transaction_graph(
+        &self,
+        ctx: &RequestContext,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+    ) -> ServiceResult<TransactionGraph> {
+        let chunk = self.get_receipts_chunk(ctx, start_slot, end_slot, MAX_RANGE_CHUNK).await?;
+        Ok(TransactionGraph::from_receipts(&chunk.receipts, chunk.next_slot))
+    }
+
+    /// State proofs in `[start_slot, end_slot]`, at most `max_proofs` at a time
+    pub async fn get_state_proofs_chunk(
+        &self,
+        ctx: &RequestContext,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+        max_proofs: usize,
+    ) -> ServiceResult<StateProofChunk> {
+        ctx.check()?;
+        self.responses
+            .get_or_compute("getStateProofsChunk", &(start_slot, end_slot, max_proofs), || {
+                self.load_state_proofs_chunk(ctx, start_slot, end_slot, max_proofs)
+            })
+            .await
+    }
+
+    async fn load_state_proofs_chunk(
+        &self,
+        ctx: &RequestContext,
+        start_slot: SlotNumber,
+        end_slot: SlotNumber,
+        max_proofs: usize,
+    ) -> ServiceResult<StateProofChunk> {
+        use units_core_types::UnitsStorage;
+
+        let limit = max_proofs.clamp(1, MAX_RANGE_CHUNK);
+        let mut proofs = self
+            .services
+            .storage
+            .proofs()
+            .iter_state_proofs(start_slot, end_slot)
+            .take(limit + 1)
+            .map(|proof| {
+                ctx.check()?;
+                Ok(proof?)
+            })
+            .collect::<ServiceResult<Vec<_>>>()?;
+        let next_slot = if proofs.len() > limit {
+            proofs.pop().map(|proof| proof.slot)
+        } else {
+            None
+        };
+        Ok(StateProofChunk { proofs, next_slot })
+    }
+
+    /// Merkle path proving an object's inclusion in the latest committed state root
+    ///
+    /// The path is only valid while the object is unchanged since that
+    /// commit, so reads between its write and the next slot fail as retryable.
+    pub async fn get_object_inclusion(&self, object_id: &UnitsObjectId) -> ServiceResult<ObjectInclusion> {
+        let (state_proof, object_root) = self.latest_state_proof()?;
+        let slot = state_proof.slot;
+        let object = self.get_object(object_id).await?;
+        let path = self.object_root_path(&state_proof, &object, &object_root)?;
+
+        Ok(ObjectInclusion {
+            slot,
+            finality: self.services.slot_service.finality(slot),
+            path,
+        })
+    }
+
+    /// Watched objects changed since `since_slot`, with inclusion proofs
+    /// against the latest committed state root
+    ///
+    /// An object counts as changed when the transaction behind its latest
+    /// proof executed after `since_slot`. Writes made outside a transaction
+    /// cannot be placed in a slot, so they are always sent. Deleted objects
+    /// come back without a state or path, and IDs that never existed are
+    /// left out. Clients pass the returned slot on their next sync.
+    pub async fn light_sync(&self, since_slot: SlotNumber, object_ids: &[UnitsObjectId]) -> ServiceResult<LightSync> {
+        use units_core_types::{ObjectStorage, UnitsStorage};
+        if object_ids.len() > MAX_WATCHED_OBJECTS {
+            return Err(crate::error::ServiceError::invalid_request(format!(
+                "At most {} objects can be watched, got {}",
+                MAX_WATCHED_OBJECTS,
+                object_ids.len()
+            )));
+        }
+
+        let storage = &self.services.storage;
+        let (state_proof, object_root) = self.latest_state_proof()?;
+        let slot = state_proof.slot;
+        let mut changes = Vec::new();
+        for object_id in object_ids.iter().collect::<std::collections::BTreeSet<_>>() {
+            let Some(proof) = storage.inner().get_latest_proof(object_id) else {
+                continue;
+            };
+            if matches!(storage.written_at(&proof), Some(written) if written <= since_slot) {
+                continue;
+            }
+
+            let change = match storage.objects().get(object_id)? {
+                Some(object) => {
+                    let path = self.object_root_path(&state_proof, &object, &object_root)?;
+                    ObjectChange { object_id: *object_id, object: Some(object), path: Some(path) }
+                }
+                None => ObjectChange { object_id: *object_id, object: None, path: None },
+            };
+            changes.push(change);
+        }
+
+        Ok(LightSync {
+            slot,
+            finality: self.services.slot_service.finality(slot),
+            root: hex::encode(object_root),
+            changes,
+        })
+    }
+
+    /// Register a watch on an object or controller, returning its ID
+    ///
+    /// The ID is the only handle on the watch; whoever holds it can
+    /// subscribe to or remove it.
+    pub async fn register_watch(&self, target: WatchTarget, filter: WatchFilter, delivery: WatchDelivery) -> ServiceResult<Watch> {
+        self.watches.register(target, filter, delivery)
+    }
+
+    /// Remove a watch, returning whether it existed
+    pub async fn remove_watch(&self, watch_id: &str) -> ServiceResult<bool> {
+        self.watches.remove(watch_id)
+    }
+
+    pub async fn get_watch(&self, watch_id: &str) -> ServiceResult<Watch> {
+        self.watches
+            .get(watch_id)
+            .ok_or_else(|| crate::error::ServiceError::invalid_request(format!("No watch {}", watch_id)))
+    }
+
+    /// Notifications of a WebSocket watch after sequence `since`, and a
+    /// receiver of live notifications of every watch
+    pub fn subscribe_watch(
+        &self,
+        watch_id: &str,
+        since: u64,
+    ) -> ServiceResult<(Vec<WatchNotification>, tokio::sync::broadcast::Receiver<WatchNotification>)> {
+        self.watches.subscribe(watch_id, since)
+    }
+
+    /// Run a committed slot's receipts past the watches, POSTing the
+    /// webhook notifications in the background
+    fn notify_watches(&self, slot: SlotNumber, receipts: &[TransactionReceipt]) -> ServiceResult<()> {
+        let mut deliveries = Vec::new();
+        for (url, notification) in self.watches.notify(slot, receipts)? {
+            let body = serde_json::to_vec(&notification)?;
+            deliveries.push(self.webhooks.delivery(&url, notification.sequence, body, self.signer.as_deref()));
+        }
+        if !deliveries.is_empty() {
+            self.webhooks.send_all(deliveries);
+        }
+        Ok(())
+    }
+
+    /// Latest committed state proof and its object root
+    fn latest_state_proof(&self) -> ServiceResult<(StateProof, [u8; 32])> {
+        use units_core_types::UnitsStorage;
+        let state_proof = self.services.storage
+            .proofs()
+            .get_state_proof_history(0, SlotNumber::MAX)?
+            .into_iter()
+            .max_by_key(|proof| proof.slot)
+            .ok_or_else(|| crate::error::ServiceError::invalid_request("No state root has been committed"))?;
+
+        let object_root = ProofEngine::new()
+            .state_proof_data(&state_proof)
+            .map_err(|e| crate::error::ServiceError::Storage(e.into()))?
+            .object_root;
+        Ok((state_proof, object_root))
+    }
+
+    /// Merkle path of `object`'s latest proof to the object root of `state_proof`
+    ///
+    /// The path only exists while the object is unchanged since that
+    /// commit, so a write since then fails as retryable.
+    fn object_root_path(
+        &self,
+        state_proof: &StateProof,
+        object: &UnitsObject,
+        object_root: &[u8; 32],
+    ) -> ServiceResult<ObjectRootPath> {
+        let storage = &self.services.storage;
+        let proof = storage
+            .inner()
+            .get_latest_proof(&object.id)
+            .ok_or_else(|| crate::error::ServiceError::object_not_found(object.id.to_string()))?;
+        let nodes = storage.committed_path(state_proof, &proof)?;
+
+        let valid = match &nodes {
+            Some(nodes) => with_proof_engine!(storage.hash_algorithm(), |engine| engine
+                .verify_object_against_root(object, &proof, nodes, object_root))
+                .map_err(|e| crate::error::ServiceError::Storage(e.into()))?,
+            None => false,
+        };
+        let Some(nodes) = nodes.filter(|_| valid) else {
+            return Err(crate::error::ServiceError::service_unavailable(format!(
+                "Objects changed since slot {}; retry after the next slot",
+                state_proof.slot
+            )));
+        };
+
+        Ok(ObjectRootPath {
+            proof,
+            nodes,
+            root: hex::encode(object_root),
+        })
+    }
+
+    /// Verify an object against an object root using its Merkle path
+    ///
+    /// Uses only the supplied data, so callers can check provenance against
+    /// roots they anchored elsewhere without trusting this node's state.
+    pub async fn verify_object_against_root(
+        &self,
+        object: &UnitsObject,
+        path: &ObjectRootPath,
+    ) -> ServiceResult<ObjectRootVerification> {
+        let root: [u8; 32] = hex::decode(&path.root)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| crate::error::ServiceError::invalid_request("Root must be 32 hex-encoded bytes"))?;
+
+        let valid = with_proof_engine!(self.services.storage.hash_algorithm(), |engine| engine
+            .verify_object_against_root(object, &path.proof, &path.nodes, &root))
+            .map_err(|e| crate::error::ServiceError::Storage(e.into()))?;
+
+        Ok(ObjectRootVerification {
+            valid,
+            slot: path.proof.slot,
+        })
     }
     
+    /// Fork current state into a new simulation sandbox
+    pub async fn create_sandbox(&self) -> ServiceResult<SandboxInfo> {
+        self.sandboxes.create().await
+    }
+
+    /// List open simulation sandboxes
+    pub async fn list_sandboxes(&self) -> ServiceResult<Vec<SandboxInfo>> {
+        Ok(self.sandboxes.list().await)
+    }
+
+    /// Execute a transaction against a sandbox instead of the primary store
+    pub async fn sandbox_execute_transaction(
+        &self,
+        ctx: &RequestContext,
+        namespace: &str,
+        transaction: Transaction,
+    ) -> ServiceResult<TransactionReceipt> {
+        self.sandboxes.execute(ctx, namespace, transaction).await
+    }
+
+    /// Get an object as a sandbox sees it
+    pub async fn sandbox_get_object(&self, namespace: &str, object_id: &UnitsObjectId) -> ServiceResult<VersionedObject> {
+        self.sandboxes.get_object(namespace, object_id).await
+    }
+
+    /// Objects a sandbox has changed relative to the primary store
+    pub async fn sandbox_get_changes(&self, namespace: &str) -> ServiceResult<Vec<SandboxChange>> {
+        self.sandboxes.changes(namespace).await
+    }
+
+    /// Receipts of the transactions executed in a sandbox
+    pub async fn sandbox_get_receipts(&self, namespace: &str) -> ServiceResult<Vec<TransactionReceipt>> {
+        self.sandboxes.receipts(namespace).await
+    }
+
+    /// Discard a sandbox and its staged changes
+    pub async fn discard_sandbox(&self, namespace: &str) -> ServiceResult<bool> {
+        Ok(self.sandboxes.discard(namespace).await)
+    }
+
+    /// Run an admin operation, or forecast it when `auth.dry_run` is set
+    ///
+    /// A dry run of a destructive operation returns the confirmation token
+    /// that the real run must present.
+    pub async fn admin(&self, auth: &AdminAuth, operation: AdminOperation) -> ServiceResult<AdminReport> {
+        self.admin.authorize(auth)?;
+        if !auth.dry_run {
+            self.admin.confirm(auth, &operation)?;
+        }
+
+        let (affected, details) = self.apply_admin(&operation, auth.dry_run)?;
+        let confirmation_token = if auth.dry_run {
+            self.admin.issue_confirmation(&operation)
+        } else {
+            None
+        };
+        if !auth.dry_run {
+            log::info!("Admin operation {:?} affected {}", operation, affected);
+            // Pruning, compaction and regeneration rewrite what queries return
+            self.responses.clear();
+        }
+
+        Ok(AdminReport {
+            operation,
+            dry_run: auth.dry_run,
+            affected,
+            details,
+            confirmation_token,
+        })
+    }
+
+    /// Count, and unless `dry_run` perform, the changes of an admin operation
+    fn apply_admin(&self, operation: &AdminOperation, dry_run: bool) -> ServiceResult<(u64, Vec<String>)> {
+        use units_core_types::{HistoricalStorage, UnitsStorage, WriteAheadLog};
+        let storage = &self.services.storage;
+
+        let affected = match operation {
+            AdminOperation::Compact { before_slot } => {
+                if dry_run {
+                    storage.inner().compactable_versions(*before_slot)
+                } else {
+                    storage.historical().compact_history(*before_slot)?
+                }
+            }
+            AdminOperation::Prune { before_slot } => {
+                self.retention.check_prune(*before_slot)?;
+                if dry_run {
+                    match before_slot.checked_sub(1) {
+                        Some(last) => storage.proofs().get_state_proof_history(0, last)?.len(),
+                        None => 0,
+                    }
+                } else {
+                    storage.proofs().prune_state_proofs(*before_slot)
+                }
+            }
+            AdminOperation::Snapshot { name } => {
+                let path = self.snapshot_path(name)?;
+                if path.exists() {
+                    return Err(crate::error::ServiceError::invalid_request(
+                        format!("Snapshot {} already exists", path.display())
+                    ));
+                }
+
+                let archives = storage
+                    .inner()
+                    .object_ids()
+                    .iter()
+                    .map(|id| storage.export_object(id))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if !dry_run {
+                    let bytes = bincode::serialize(&archives)
+                        .map_err(|e| crate::error::ServiceError::Internal(e.into()))?;
+                    std::fs::write(&path, bytes)
+                        .map_err(|e| crate::error::ServiceError::Internal(e.into()))?;
+                }
+                return Ok((archives.len() as u64, vec![path.display().to_string()]));
+            }
+            AdminOperation::Scrub => {
+                // Read-only, so a dry run does the same work
+                let ids = storage.inner().object_ids();
+                let step = (ids.len() / 10).max(1);
+                let progress = |progress: units_storage_impl::VerifyProgress| {
+                    if progress.verified % step == 0 {
+                        log::info!(
+                            "Scrub verified {}/{} objects, {} failed",
+                            progress.verified,
+                            progress.total,
+                            progress.failed
+                        );
+                    }
+                };
+                let failures = with_proof_engine!(storage.hash_algorithm(), |engine| storage.verify_objects(
+                    &ids,
+                    &engine.with_slot_ordering(self.config.storage.slot_ordering),
+                    progress,
+                ));
+                let failures = failures.into_iter().map(|(id, error)| format!("{}: {}", id, error)).collect();
+                return Ok((ids.len() as u64, failures));
+            }
+            AdminOperation::RepairSlotOrder => {
+                let regressions = storage.inner().slot_regressions();
+                let details = regressions
+                    .iter()
+                    .map(|r| format!("{}: proof {} at slot {} follows slot {}", r.object_id, r.index, r.slot, r.prev_slot))
+                    .collect();
+                let repaired = if dry_run {
+                    regressions.iter().map(|r| r.object_id).collect::<std::collections::HashSet<_>>().len()
+                } else {
+                    storage.inner().repair_slot_order()
+                };
+                return Ok((repaired as u64, details));
+            }
+            AdminOperation::PauseController { controller_id } => {
+                let changed = if dry_run {
+                    !self.admin.is_paused(controller_id)
+                } else {
+                    self.admin.pause_controller(*controller_id)
+                };
+                changed as usize
+            }
+            AdminOperation::ResumeController { controller_id } => {
+                let changed = if dry_run {
+                    self.admin.is_paused(controller_id)
+                } else {
+                    self.admin.resume_controller(controller_id)
+                };
+                changed as usize
+            }
+            AdminOperation::SetControllerPolicy { policy } => {
+                let details = self.admin.controller_policy_changes(policy);
+                if !dry_run {
+                    self.admin.set_controller_policy(policy.clone());
+                }
+                return Ok((details.len() as u64, details));
+            }
+            AdminOperation::TruncateWal => match storage.wal() {
+                Some(wal) if dry_run => wal.entry_count()?,
+                Some(wal) => wal.truncate()?,
+                None => 0,
+            },
+            AdminOperation::RegenerateStateProofs { start_slot, end_slot } => {
+                let slots = if dry_run {
+                    storage.missing_state_proofs(*start_slot, *end_slot)?
+                } else {
+                    storage.regenerate_state_proofs(*start_slot, *end_slot)?
+                };
+                let details = slots.iter().map(|slot| slot.to_string()).collect();
+                return Ok((slots.len() as u64, details));
+            }
+            AdminOperation::PauseMaintenance => {
+                let changed = if dry_run {
+                    !self.maintenance.is_paused()
+                } else {
+                    self.maintenance.pause()
+                };
+                changed as usize
+            }
+            AdminOperation::ResumeMaintenance => {
+                let changed = if dry_run {
+                    self.maintenance.is_paused()
+                } else {
+                    self.maintenance.resume()
+                };
+                changed as usize
+            }
+            AdminOperation::FundFeeAccount { account_id, amount } => {
+                let mut ledger = match storage.objects().get(&FEE_LEDGER_ID)? {
+                    Some(object) => FeeLedger::from_object(&object)
+                        .map_err(|e| crate::error::ServiceError::Internal(e.into()))?,
+                    None => FeeLedger::default(),
+                };
+                ledger.fund(*account_id, *amount);
+                if !dry_run {
+                    let object = ledger.to_object().map_err(|e| crate::error::ServiceError::Internal(e.into()))?;
+                    storage.objects().set(&object, None)?;
+                }
+                return Ok((1, vec![format!("{} balance {}", account_id, ledger.balance_of(account_id))]));
+            }
+            AdminOperation::FundDepositAccount { account_id, amount } => {
+                use units_core_types::LockManager;
+                // Transactions changing deposits hold this lock while they commit
+                let _ledger = storage.locks().lock(&DEPOSIT_LEDGER_ID)?;
+                let mut ledger = match storage.objects().get(&DEPOSIT_LEDGER_ID)? {
+                    Some(object) => DepositLedger::from_object(&object)
+                        .map_err(|e| crate::error::ServiceError::Internal(e.into()))?,
+                    None => DepositLedger::default(),
+                };
+                ledger.fund(*account_id, *amount);
+                if !dry_run {
+                    let object = ledger.to_object().map_err(|e| crate::error::ServiceError::Internal(e.into()))?;
+                    storage.objects().set(&object, None)?;
+                }
+                return Ok((1, vec![format!("{} balance {}", account_id, ledger.balance_of(account_id))]));
+            }
+            AdminOperation::CreateIndex { controller_id } => {
+                let pattern = QueryPattern::Controller { controller_id: *controller_id };
+                if dry_run {
+                    !storage.index_advisor().is_indexed(&pattern) as usize
+                } else {
+                    storage.create_controller_index(*controller_id)?
+                }
+            }
+            AdminOperation::SetResourceClass { controller_id, class } => {
+                let registry = self.module_registry()?;
+                if !registry.modules.contains_key(controller_id) {
+                    return Err(crate::error::ServiceError::invalid_request(
+                        format!("Controller {} is not deployed", controller_id)
+                    ));
+                }
+                let previous = registry.resource_class(controller_id);
+                if !dry_run && previous != *class {
+                    self.update_module_registry(|registry| Ok(registry.set_resource_class(controller_id, *class)))?;
+                }
+                let details = vec![format!("{} runs as {:?}, was {:?}", controller_id, class, previous)];
+                return Ok(((previous != *class) as u64, details));
+            }
+            AdminOperation::PlaceLegalHold { controller_id } => {
+                let changed = if dry_run {
+                    !storage.inner().is_held(controller_id)
+                } else {
+                    storage.inner().place_legal_hold(*controller_id)
+                };
+                changed as usize
+            }
+            AdminOperation::ReleaseLegalHold { controller_id } => {
+                if !storage.inner().is_held(controller_id) {
+                    return Err(crate::error::ServiceError::invalid_request(
+                        format!("Controller {} is not under a legal hold", controller_id)
+                    ));
+                }
+                // Reports the retained objects that the release discards
+                let retained = storage.inner().retained_objects(Some(controller_id));
+                let details = retained
+                    .iter()
+                    .map(|kept| format!("{} deleted at slot {}", kept.object.id, kept.deletion_proof.slot))
+                    .collect();
+                if !dry_run {
+                    storage.inner().release_legal_hold(controller_id);
+                }
+                return Ok((retained.len() as u64, details));
+            }
+        };
+
+        Ok((affected as u64, Vec::new()))
+    }
+
+    /// Location of a named snapshot inside the data directory
+    fn snapshot_path(&self, name: &str) -> ServiceResult<std::path::PathBuf> {
+        let data_dir = self.config.storage.data_dir.as_deref().ok_or_else(|| {
+            crate::error::ServiceError::invalid_request("Snapshots need storage.data_dir to be set")
+        })?;
+        if std::path::Path::new(name).file_name() != Some(std::ffi::OsStr::new(name)) {
+            return Err(crate::error::ServiceError::invalid_request(
+                "Snapshot name must be a plain file name"
+            ));
+        }
+        Ok(std::path::Path::new(data_dir).join(name))
+    }
+
     /// Create a new object
     pub async fn create_object(
         &self,
@@ -114,15 +1281,380 @@ impl UnitsService {
                 units_core_types::objects::UnitsObject::new_data(id, controller_id, data),
             units_core_types::objects::ObjectType::Executable(vm_type) => 
                 units_core_types::objects::UnitsObject::new_executable(id, controller_id, vm_type, data),
+            units_core_types::objects::ObjectType::Ephemeral { expires_at_slot } => 
+                units_core_types::objects::UnitsObject::new_ephemeral(id, controller_id, data, expires_at_slot),
         };
         
         // Store in storage
         use units_core_types::UnitsStorage;
         let _proof = self.services.storage.objects().set(&object, None)
             .map_err(crate::error::ServiceError::Storage)?;
+
+        if object.is_executable() {
+            self.update_module_registry(|registry| {
+                registry.record_deployment(&object, self.services.slot_service.current_slot());
+                Ok(())
+            })?;
+        }
         
         Ok(object)
     }
+
+    /// Amount of `token_id` held by `owner_id`
+    pub async fn get_token_balance(
+        &self,
+        ctx: &RequestContext,
+        owner_id: &UnitsObjectId,
+        token_id: &UnitsObjectId,
+        scan_cursor: Option<&str>,
+    ) -> ServiceResult<TokenBalance> {
+        self.tokens.get_balance(ctx, owner_id, token_id, scan_cursor)
+    }
+
+    /// One page of the holders of `token_id`
+    pub async fn get_token_holders(
+        &self,
+        ctx: &RequestContext,
+        token_id: &UnitsObjectId,
+        page: u32,
+        scan_cursor: Option<&str>,
+    ) -> ServiceResult<TokenHolders> {
+        self.tokens.get_holders(ctx, token_id, page, scan_cursor)
+    }
+
+    /// Page of an account's activity, newest first, older than `cursor`
+    pub async fn get_account_activity(
+        &self,
+        ctx: &RequestContext,
+        account_id: &UnitsObjectId,
+        cursor: Option<&str>,
+        scan_cursor: Option<&str>,
+    ) -> ServiceResult<ActivityPage> {
+        self.activity.get_activity(ctx, account_id, cursor, scan_cursor)
+    }
+
+    /// Record that a document with `document_hash` exists, keeping the earliest attestation
+    pub async fn attest_document(&self, document_hash: [u8; 32], attester_id: UnitsObjectId) -> ServiceResult<Attestation> {
+        self.attestations.attest(document_hash, attester_id)
+    }
+
+    /// Everything needed to check offline that `document_hash` existed by its attested slot
+    ///
+    /// Fails until the slot covering the attestation has been committed.
+    pub async fn get_existence_receipt(&self, document_hash: &[u8; 32]) -> ServiceResult<ExistenceReceipt> {
+        let attestation = self
+            .attestations
+            .get(document_hash)?
+            .ok_or_else(|| crate::error::ServiceError::object_not_found(hex::encode(document_hash)))?;
+        let (object, _) = self.get_object_with_metadata(&attestation.object_id).await?;
+        let inclusion = self.get_object_inclusion(&attestation.object_id).await?;
+        let state_root = self.get_state_root(inclusion.slot).await?;
+        let signature = self.sign_response(&object).await?;
+
+        Ok(ExistenceReceipt {
+            attestation,
+            evidence: ObjectEvidence { object, signature, inclusion, state_root },
+        })
+    }
+
+    /// Usage summary of a closed slot, with the evidence to check it against the slot's state root
+    pub async fn get_slot_summary(&self, slot: SlotNumber) -> ServiceResult<SlotSummaryReceipt> {
+        let summary = self
+            .services
+            .slot_service
+            .get_summary(&self.services.storage, slot)?
+            .ok_or_else(|| crate::error::ServiceError::invalid_request(
+                format!("No summary for slot {}", slot)
+            ))?;
+        let object_id = SlotSummary::object_id(slot);
+        let (object, _) = self.get_object_with_metadata(&object_id).await?;
+        let inclusion = self.get_object_inclusion(&object_id).await?;
+        let state_root = self.get_state_root(inclusion.slot).await?;
+        let signature = self.sign_response(&object).await?;
+
+        Ok(SlotSummaryReceipt {
+            summary,
+            evidence: ObjectEvidence { object, signature, inclusion, state_root },
+        })
+    }
+
+    /// Create an empty collection named `name` owned by `owner_id`
+    pub async fn create_collection(&self, owner_id: UnitsObjectId, name: &str) -> ServiceResult<Collection> {
+        self.collections.create(owner_id, name)
+    }
+
+    /// Add and remove members of a collection on behalf of its owner
+    pub async fn update_collection(
+        &self,
+        collection_id: &UnitsObjectId,
+        owner_id: &UnitsObjectId,
+        add: &[UnitsObjectId],
+        remove: &[UnitsObjectId],
+    ) -> ServiceResult<Collection> {
+        self.collections.update_members(collection_id, owner_id, add, remove)
+    }
+
+    /// One page of the member objects of a collection
+    pub async fn get_collection_members(
+        &self,
+        ctx: &RequestContext,
+        collection_id: &UnitsObjectId,
+        page: u32,
+    ) -> ServiceResult<CollectionMembers> {
+        self.collections.members_page(ctx, collection_id, page)
+    }
+
+    /// A collection with the latest proof of each member, all under the latest state root
+    ///
+    /// Fails until the collection and every member are covered by a committed
+    /// state proof, and while any of them has changed since.
+    pub async fn get_collection_proof(&self, collection_id: &UnitsObjectId) -> ServiceResult<CollectionProof> {
+        use units_core_types::UnitsStorage;
+        let (object, _) = self.get_object_with_metadata(collection_id).await?;
+        let collection = Collection::from_object(&object)
+            .ok_or_else(|| crate::error::ServiceError::object_not_found(collection_id.to_string()))?;
+        let inclusion = self.get_object_inclusion(collection_id).await?;
+        let state_root = self.get_state_root(inclusion.slot).await?;
+        let signature = self.sign_response(&object).await?;
+
+        let state_proof = self.services.storage.proofs().get_state_proof(inclusion.slot)?.ok_or_else(|| {
+            crate::error::ServiceError::invalid_request(format!("No state proof for slot {}", inclusion.slot))
+        })?;
+        let data = ProofEngine::new()
+            .state_proof_data(&state_proof)
+            .map_err(|e| crate::error::ServiceError::Storage(e.into()))?;
+        let mut members = Vec::with_capacity(collection.members.len());
+        for member_id in &collection.members {
+            let proof = self
+                .services
+                .storage
+                .inner()
+                .get_latest_proof(member_id)
+                .ok_or_else(|| crate::error::ServiceError::object_not_found(member_id.to_string()))?;
+            let Some(nodes) = self.services.storage.committed_path(&state_proof, &proof)? else {
+                return Err(crate::error::ServiceError::service_unavailable(format!(
+                    "Members changed since slot {}; retry after the next slot",
+                    inclusion.slot
+                )));
+            };
+            members.push(ObjectRootPath { proof, nodes, root: hex::encode(data.object_root) });
+        }
+
+        let member_proofs: Vec<_> = members.iter().map(|path| (path.proof.object_id, path.proof.clone())).collect();
+        Ok(CollectionProof {
+            collection,
+            evidence: ObjectEvidence { object, signature, inclusion, state_root },
+            group_root: hex::encode(with_proof_engine!(data.hash_algorithm, |engine| engine.group_root(&member_proofs))),
+            members,
+        })
+    }
+
+    /// List deployed controllers from the module registry
+    pub async fn list_controllers(&self) -> ServiceResult<Vec<ModuleEntry>> {
+        Ok(self.module_registry()?.entries().cloned().collect())
+    }
+
+    /// Deploy a packaged module as the controller at `controller_id`
+    ///
+    /// `artifact` is the encoded output of `units-build`; its ABI metadata is
+    /// registered along with the code.
+    pub async fn deploy_module_artifact(
+        &self,
+        controller_id: UnitsObjectId,
+        artifact: &[u8],
+    ) -> ServiceResult<ModuleEntry> {
+        use units_core_types::UnitsStorage;
+        let artifact = ModuleArtifact::decode(artifact)
+            .map_err(|err| crate::error::ServiceError::invalid_request(err.to_string()))?;
+        let controller = artifact.to_controller(controller_id);
+        self.services.storage.objects().set(&controller, None)?;
+
+        self.update_module_registry(|registry| {
+            let slot = self.services.slot_service.current_slot();
+            registry
+                .record_artifact(&controller, &artifact, slot)
+                .cloned()
+                .ok_or_else(|| crate::error::ServiceError::invalid_request("Module artifact is not executable"))
+        })
+    }
+
+    /// Replace the table naming a deployed controller's exit codes
+    ///
+    /// Receipts of instructions failing with a listed code carry its name
+    /// and message.
+    pub async fn set_controller_error_codes(
+        &self,
+        controller_id: &UnitsObjectId,
+        error_codes: BTreeMap<u32, ModuleErrorCode>,
+    ) -> ServiceResult<ModuleEntry> {
+        self.update_module_registry(|registry| {
+            if !registry.set_error_codes(controller_id, error_codes) {
+                return Err(crate::error::ServiceError::invalid_request(
+                    format!("Controller {} is not deployed", controller_id)
+                ));
+            }
+            Ok(registry.modules[controller_id].clone())
+        })
+    }
+
+    /// Publish (or clear) where a deployed controller's ABI can be fetched
+    pub async fn set_controller_abi(
+        &self,
+        controller_id: &UnitsObjectId,
+        location: Option<String>,
+    ) -> ServiceResult<ModuleEntry> {
+        self.update_module_registry(|registry| {
+            if !registry.set_abi_location(controller_id, location) {
+                return Err(crate::error::ServiceError::invalid_request(
+                    format!("Controller {} is not deployed", controller_id)
+                ));
+            }
+            Ok(registry.modules[controller_id].clone())
+        })
+    }
+
+    /// Declare the objects a controller function reads beyond its targets
+    pub async fn set_prefetch_rules(
+        &self,
+        controller_id: &UnitsObjectId,
+        function: &str,
+        rules: Vec<PrefetchRule>,
+    ) -> ServiceResult<ModuleEntry> {
+        self.update_module_registry(|registry| {
+            if !registry.set_prefetch_rules(controller_id, function, rules) {
+                return Err(crate::error::ServiceError::invalid_request(
+                    format!("Controller {} is not deployed", controller_id)
+                ));
+            }
+            Ok(registry.modules[controller_id].clone())
+        })
+    }
+
+    /// Set or, with `None`, remove metadata `key` of an object of `controller_id`
+    ///
+    /// Returns the object's metadata after the change.
+    pub async fn set_object_metadata(
+        &self,
+        controller_id: &UnitsObjectId,
+        object_id: &UnitsObjectId,
+        key: &str,
+        value: Option<&str>,
+    ) -> ServiceResult<BTreeMap<String, String>> {
+        use units_core_types::UnitsStorage;
+        let object = self.services.storage
+            .objects()
+            .get(object_id)?
+            .ok_or_else(|| crate::error::ServiceError::object_not_found(object_id.to_string()))?;
+        if object.controller_id() != controller_id {
+            return Err(crate::error::ServiceError::unauthorized(
+                format!("Object {} is not controlled by {}", object_id, controller_id)
+            ));
+        }
+        let metadata = self.services.storage.metadata();
+        metadata
+            .annotate(&object, key, value)
+            .map_err(|err| crate::error::ServiceError::invalid_request(err.to_string()))?;
+        Ok(metadata.metadata(object_id))
+    }
+
+    /// Metadata of an object, empty if it has none
+    pub async fn get_object_metadata(&self, object_id: &UnitsObjectId) -> ServiceResult<BTreeMap<String, String>> {
+        Ok(self.services.storage.metadata().metadata(object_id))
+    }
+
+    /// Objects of `controller_id` whose metadata maps `key` to `value`
+    pub async fn find_by_metadata(
+        &self,
+        controller_id: &UnitsObjectId,
+        key: &str,
+        value: &str,
+    ) -> ServiceResult<Vec<UnitsObjectId>> {
+        let found = self.services.storage.metadata().find(controller_id, key, value);
+        let pattern = QueryPattern::MetadataKey { controller_id: *controller_id, key: key.to_string() };
+        self.services.storage.record_query(&pattern, found.len());
+        Ok(found)
+    }
+
+    /// Designate the controller `authorizer` to approve every instruction
+    /// touching `account_id` or the objects it controls, or with `None`
+    /// stop requiring approval
+    ///
+    /// `account_id` must be controlled by `controller_id`, and `authorizer`
+    /// must be executable. Returns the previous authorizer.
+    pub async fn set_account_authorizer(
+        &self,
+        controller_id: &UnitsObjectId,
+        account_id: &UnitsObjectId,
+        authorizer: Option<UnitsObjectId>,
+    ) -> ServiceResult<Option<UnitsObjectId>> {
+        use units_core_types::{LockManager, UnitsStorage};
+        let account = self.services.storage
+            .objects()
+            .get(account_id)?
+            .ok_or_else(|| crate::error::ServiceError::object_not_found(account_id.to_string()))?;
+        if account.controller_id() != controller_id {
+            return Err(crate::error::ServiceError::unauthorized(
+                format!("Object {} is not controlled by {}", account_id, controller_id)
+            ));
+        }
+        if let Some(authorizer) = &authorizer {
+            let executable = self.services.storage.objects().get(authorizer)?.is_some_and(|object| object.vm_type().is_some());
+            if !executable {
+                return Err(crate::error::ServiceError::invalid_request(
+                    format!("Authorizer {} is not an executable controller", authorizer)
+                ));
+            }
+        }
+
+        let _guard = self.services.storage.locks().lock(&AUTHORIZER_REGISTRY_ID)?;
+        let mut registry = self.authorizer_registry()?;
+        let previous = registry.set(*account_id, authorizer);
+        self.services.storage.objects().set(&registry.to_object()?, None)?;
+        Ok(previous)
+    }
+
+    /// Authorizer designated by an account, if any
+    pub async fn get_account_authorizer(&self, account_id: &UnitsObjectId) -> ServiceResult<Option<UnitsObjectId>> {
+        Ok(self.authorizer_registry()?.authorizer_of(account_id).copied())
+    }
+
+    fn authorizer_registry(&self) -> ServiceResult<AuthorizerRegistry> {
+        use units_core_types::UnitsStorage;
+        match self.services.storage.objects().get(&AUTHORIZER_REGISTRY_ID)? {
+            Some(object) => Ok(AuthorizerRegistry::from_object(&object)?),
+            None => Ok(AuthorizerRegistry::default()),
+        }
+    }
+
+    fn module_registry(&self) -> ServiceResult<ModuleRegistry> {
+        use units_core_types::UnitsStorage;
+        match self.services.storage.objects().get(&MODULE_REGISTRY_ID)? {
+            Some(object) => Ok(ModuleRegistry::from_object(&object)?),
+            None => Ok(ModuleRegistry::default()),
+        }
+    }
+
+    /// Read-modify-write the registry object under its object lock
+    fn update_module_registry<T>(
+        &self,
+        update: impl FnOnce(&mut ModuleRegistry) -> ServiceResult<T>,
+    ) -> ServiceResult<T> {
+        use units_core_types::{LockManager, UnitsStorage};
+        let _guard = self.services.storage.locks().lock(&MODULE_REGISTRY_ID)?;
+
+        let mut registry = self.module_registry()?;
+        let result = update(&mut registry)?;
+        self.services.storage.objects().set(&registry.to_object()?, None)?;
+        Ok(result)
+    }
+}
+
+/// State a node had committed when it answered a request
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResponseContext {
+    pub slot: SlotNumber,
+    /// Object root of the slot's state proof; absent before the first slot closes
+    pub state_root: Option<[u8; 32]>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
@@ -131,12 +1663,128 @@ pub struct HealthStatus {
     pub slot: u64,
     pub object_count: u64,
     pub pending_transactions: u64,
+    /// When write-ahead log entries are fsynced
+    #[serde(default)]
+    pub wal_durability: units_storage_impl::WalDurability,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub struct ServiceStats {
     pub current_slot: SlotNumber,
     pub pending_transactions: u64,
+    /// Controller executables held by the executable cache
     pub cached_objects: u64,
     pub latest_proven_slot: SlotNumber,
-}
\ No newline at end of file
+    /// Receipt and proof queries answered from the response cache
+    #[serde(default)]
+    pub response_cache: ResponseCacheStats,
+}
+
+/// Signing identity of this node
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct NodeIdentity {
+    /// Hex-encoded Ed25519 public key, absent when signing is disabled
+    pub node_key: Option<String>,
+    pub signing_enabled: bool,
+}
+
+/// State root committed for a slot
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct StateRoot {
+    pub slot: SlotNumber,
+    /// Hex-encoded Merkle root over the latest object proofs
+    pub object_root: String,
+    /// Hex-encoded Merkle root over the slot's transaction hashes
+    pub transaction_root: String,
+    /// Hash function both roots, and the object proofs under them, were computed with
+    #[serde(default)]
+    pub hash_algorithm: HashAlgorithm,
+    /// Hex-encoded hash of the state proof itself
+    pub state_proof_hash: String,
+    pub prev_state_proof_hash: Option<String>,
+    /// Hex-encoded node signature over the state proof, absent when signing is disabled
+    pub signature: Option<String>,
+    pub object_count: u64,
+    pub receipt_count: u64,
+    /// How settled the slot is as of the response
+    #[serde(default)]
+    pub finality: Finality,
+}
+
+/// One chunk of a receipt range query
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ReceiptChunk {
+    /// Receipts in slot order, then by transaction hash
+    pub receipts: Vec<TransactionReceipt>,
+    /// First slot of the next chunk, absent when the range is exhausted
+    pub next_slot: Option<SlotNumber>,
+}
+
+/// One chunk of a state proof range query
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct StateProofChunk {
+    /// State proofs in slot order
+    pub proofs: Vec<StateProof>,
+    /// First slot of the next chunk, absent when the range is exhausted
+    pub next_slot: Option<SlotNumber>,
+}
+
+/// Merkle path proving an object's inclusion under an object root
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ObjectRootPath {
+    /// Latest proof of the object, which forms the Merkle leaf
+    pub proof: UnitsObjectProof,
+    /// Sibling hashes from the leaf up to the root
+    pub nodes: Vec<MerkleNode>,
+    /// Hex-encoded object root to verify against
+    pub root: String,
+}
+
+/// Object ID derived from an external identifier
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DerivedObjectId {
+    pub namespace: String,
+    /// Scheme version the ID was derived under
+    pub version: u16,
+    /// Hex-encoded object ID
+    pub object_id: String,
+}
+
+/// Inclusion of an object in the state root committed for `slot`
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ObjectInclusion {
+    pub slot: SlotNumber,
+    /// How settled the slot is as of the response
+    #[serde(default)]
+    pub finality: Finality,
+    pub path: ObjectRootPath,
+}
+
+/// Watched object whose state changed since a light client last synced
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ObjectChange {
+    pub object_id: UnitsObjectId,
+    /// Current state, absent once the object has been deleted
+    pub object: Option<UnitsObject>,
+    /// Inclusion of the current state under the response's root
+    pub path: Option<ObjectRootPath>,
+}
+
+/// Changes to a light client's watched objects as of the state root committed for `slot`
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct LightSync {
+    pub slot: SlotNumber,
+    /// How settled the slot is as of the response
+    #[serde(default)]
+    pub finality: Finality,
+    /// Hex-encoded object root every path verifies against
+    pub root: String,
+    pub changes: Vec<ObjectChange>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ObjectRootVerification {
+    pub valid: bool,
+    /// Slot of the object proof that was verified
+    pub slot: SlotNumber,
+}
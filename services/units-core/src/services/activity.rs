@@ -0,0 +1,192 @@
+//! Account activity timeline built from receipts and proof history
+//!
+//! An account's activity covers the account object and every object it
+//! controls: the receipts of transactions that touched them and each state
+//! change recorded in their proof chains. Entries are merged newest first
+//! and paged with an opaque cursor that stays valid as new activity arrives.
+//!
+//! Finding the controlled objects scans the store under the request's scan
+//! limits. When the scan stops early the page covers only the objects found
+//! so far, and a separate scan cursor continues with the rest.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use units_core_types::{ObjectStorage, ReceiptStorage, SlotNumber, TransactionHash, UnitsObjectId, UnitsStorage};
+use units_storage_impl::ConsolidatedUnitsStorage;
+
+use crate::context::RequestContext;
+use crate::error::{ServiceError, ServiceResult};
+use crate::scan::{resume_bounds, GuardedScan};
+
+/// Entries returned per page of `get_activity`
+pub const ACTIVITY_PAGE_SIZE: usize = 50;
+
+/// What happened in one timeline entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Activity {
+    /// A transaction touched one of the account's objects
+    Transaction {
+        transaction_hash: TransactionHash,
+        success: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error_message: Option<String>,
+    },
+    /// One of the account's objects changed state
+    ObjectChange {
+        object_id: UnitsObjectId,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        transaction_hash: Option<TransactionHash>,
+    },
+}
+
+/// Timeline entry at the slot it happened in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivityEntry {
+    pub slot: SlotNumber,
+    #[serde(flatten)]
+    pub activity: Activity,
+}
+
+/// One page of an account's timeline, newest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityPage {
+    pub account_id: UnitsObjectId,
+    pub entries: Vec<ActivityEntry>,
+    /// Cursor for the next, older page, absent on the last page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    /// Set when the object scan stopped early; pass it back for the activity
+    /// of the controlled objects after it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scan_cursor: Option<String>,
+}
+
+/// Sort key placing newer slots first, then transactions before the
+/// object changes they caused
+type Position = (Reverse<SlotNumber>, u8, [u8; 32]);
+
+impl ActivityEntry {
+    fn position(&self) -> Position {
+        match &self.activity {
+            Activity::Transaction { transaction_hash, .. } => (Reverse(self.slot), 0, *transaction_hash),
+            Activity::ObjectChange { object_id, .. } => (Reverse(self.slot), 1, **object_id),
+        }
+    }
+}
+
+fn encode_cursor((Reverse(slot), rank, key): &Position) -> String {
+    format!("{}:{}:{}", slot, rank, hex::encode(key))
+}
+
+fn decode_cursor(cursor: &str) -> ServiceResult<Position> {
+    let invalid = || ServiceError::invalid_request(format!("Invalid activity cursor: {}", cursor));
+    let mut parts = cursor.splitn(3, ':');
+    let slot = parts.next().and_then(|part| part.parse().ok()).ok_or_else(invalid)?;
+    let rank = parts.next().and_then(|part| part.parse().ok()).ok_or_else(invalid)?;
+    let key = parts
+        .next()
+        .and_then(|part| hex::decode(part).ok())
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(invalid)?;
+    Ok((Reverse(slot), rank, key))
+}
+
+/// Builds account timelines from the receipt store and proof history
+pub struct ActivityFeed {
+    storage: Arc<ConsolidatedUnitsStorage>,
+}
+
+impl ActivityFeed {
+    pub fn new(storage: Arc<ConsolidatedUnitsStorage>) -> Self {
+        Self { storage }
+    }
+
+    /// Page of `account_id`'s activity older than `cursor`, or the newest page
+    ///
+    /// Without `scan_cursor` the timeline covers the account object and the
+    /// objects it controls up to where the scan stopped; with it, only the
+    /// controlled objects after the cursor.
+    pub fn get_activity(
+        &self,
+        ctx: &RequestContext,
+        account_id: &UnitsObjectId,
+        cursor: Option<&str>,
+        scan_cursor: Option<&str>,
+    ) -> ServiceResult<ActivityPage> {
+        let after = cursor.map(decode_cursor).transpose()?;
+
+        let (object_ids, scan_cursor) = self.account_objects(ctx, account_id, scan_cursor)?;
+        let mut timeline = BTreeMap::new();
+        for object_id in object_ids {
+            ctx.check()?;
+            for receipt in self.storage.receipts().get_receipts_for_object(&object_id, None, None)? {
+                let entry = ActivityEntry {
+                    slot: receipt.slot,
+                    activity: Activity::Transaction {
+                        transaction_hash: receipt.transaction_hash,
+                        success: receipt.success,
+                        error_message: receipt.error_message,
+                    },
+                };
+                timeline.insert(entry.position(), entry);
+            }
+            for proof in self.storage.inner().get_proof_chain(&object_id) {
+                let entry = ActivityEntry {
+                    slot: proof.slot,
+                    activity: Activity::ObjectChange {
+                        object_id,
+                        transaction_hash: proof.transaction_hash,
+                    },
+                };
+                timeline.insert(entry.position(), entry);
+            }
+        }
+
+        let mut entries: Vec<ActivityEntry> = timeline
+            .into_iter()
+            .filter(|(position, _)| after.as_ref().map_or(true, |after| position > after))
+            .map(|(_, entry)| entry)
+            .take(ACTIVITY_PAGE_SIZE + 1)
+            .collect();
+        let next_cursor = if entries.len() > ACTIVITY_PAGE_SIZE {
+            entries.truncate(ACTIVITY_PAGE_SIZE);
+            entries.last().map(|entry| encode_cursor(&entry.position()))
+        } else {
+            None
+        };
+
+        Ok(ActivityPage {
+            account_id: *account_id,
+            entries,
+            next_cursor,
+            scan_cursor,
+        })
+    }
+
+    /// The account object and the objects it controls, from `scan_cursor`
+    /// on, and the cursor resuming the scan if it stopped early
+    fn account_objects(
+        &self,
+        ctx: &RequestContext,
+        account_id: &UnitsObjectId,
+        scan_cursor: Option<&str>,
+    ) -> ServiceResult<(Vec<UnitsObjectId>, Option<String>)> {
+        let mut ids = Vec::new();
+        if scan_cursor.is_none() {
+            ids.push(*account_id);
+        }
+        let objects = self.storage.objects();
+        let mut scan = GuardedScan::new(objects.iter_range(resume_bounds(scan_cursor)?), ctx);
+        for object in scan.by_ref() {
+            let object = object?;
+            if object.controller_id() == account_id && object.id() != account_id {
+                ids.push(*object.id());
+            }
+        }
+        Ok((ids, scan.continuation()))
+    }
+}
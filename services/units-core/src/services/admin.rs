@@ -0,0 +1,248 @@
+//! Admin role checks and confirmation tokens for operational RPCs
+//!
+//! Every admin call presents the configured admin key. Destructive
+//! operations run in two steps: a dry run reports what would change and
+//! issues a one-time confirmation token bound to that exact operation, and
+//! the real run must present the token before it expires.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use units_core_types::{ResourceClass, SlotNumber, UnitsObjectId};
+use units_storage_impl::RetainedObject;
+
+use crate::config::{AdminConfig, ControllerPolicy};
+use crate::error::{ServiceError, ServiceResult};
+
+/// Operational action exposed under the admin namespace
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum AdminOperation {
+    /// Drop object versions made redundant by a history cutoff
+    Compact { before_slot: SlotNumber },
+    /// Drop state proofs committed before a slot
+    Prune { before_slot: SlotNumber },
+    /// Write every object with its proof chain to a file in the data directory
+    Snapshot { name: String },
+    /// Verify the proof chain of every object
+    Scrub,
+    /// Rewrite proof chains whose slots break the configured slot ordering
+    RepairSlotOrder,
+    /// Reject new transactions that invoke a controller
+    PauseController { controller_id: UnitsObjectId },
+    /// Accept transactions for a paused controller again
+    ResumeController { controller_id: UnitsObjectId },
+    /// Replace the controller allow and deny lists
+    SetControllerPolicy { policy: ControllerPolicy },
+    /// Drop every entry of the write-ahead log
+    TruncateWal,
+    /// Rebuild the state proofs missing between two slots
+    RegenerateStateProofs { start_slot: SlotNumber, end_slot: SlotNumber },
+    /// Hold back background maintenance jobs
+    PauseMaintenance,
+    /// Let background maintenance jobs run again
+    ResumeMaintenance,
+    /// Credit an account in the fee ledger, from which sponsors pay fees
+    FundFeeAccount { account_id: UnitsObjectId, amount: u64 },
+    /// Credit an account in the deposit ledger, from which storage deposits are paid
+    FundDepositAccount { account_id: UnitsObjectId, amount: u64 },
+    /// Keep a controller's objects, hidden from reads, when they are deleted
+    PlaceLegalHold { controller_id: UnitsObjectId },
+    /// Lift a legal hold and discard the objects it retained
+    ReleaseLegalHold { controller_id: UnitsObjectId },
+    /// Index the objects of a controller, so scans over them skip the rest of the store
+    CreateIndex { controller_id: UnitsObjectId },
+    /// Run a deployed controller with the execution budgets of another class
+    SetResourceClass { controller_id: UnitsObjectId, class: ResourceClass },
+}
+
+impl AdminOperation {
+    /// Whether the operation discards data and so needs a confirmation token
+    pub fn is_destructive(&self) -> bool {
+        matches!(
+            self,
+            Self::Compact { .. } | Self::Prune { .. } | Self::RepairSlotOrder | Self::TruncateWal | Self::ReleaseLegalHold { .. }
+        )
+    }
+}
+
+/// Credentials and run mode sent with every admin call
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AdminAuth {
+    pub api_key: String,
+    /// Report what the operation would do without doing it
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Token from a dry run, required to run a destructive operation
+    #[serde(default)]
+    pub confirmation: Option<String>,
+}
+
+/// Outcome (or, for a dry run, forecast) of an admin operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminReport {
+    pub operation: AdminOperation,
+    pub dry_run: bool,
+    /// Versions, proofs, objects or log entries the operation touches
+    pub affected: u64,
+    /// Operation-specific findings, such as objects failing a scrub
+    pub details: Vec<String>,
+    /// Token to present when running a destructive operation for real
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmation_token: Option<String>,
+}
+
+/// Controllers under legal hold and the deleted objects kept for them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegalHoldAudit {
+    pub holds: Vec<UnitsObjectId>,
+    /// Retained objects, oldest deletion first
+    pub retained: Vec<RetainedObject>,
+}
+
+/// Token issued by a dry run, waiting to be redeemed
+struct PendingConfirmation {
+    operation: AdminOperation,
+    issued_at: Instant,
+}
+
+/// Admin role check, confirmation tokens and controller admission
+pub struct AdminConsole {
+    config: AdminConfig,
+    confirmations: Mutex<HashMap<String, PendingConfirmation>>,
+    paused_controllers: RwLock<HashSet<UnitsObjectId>>,
+    controller_policy: RwLock<ControllerPolicy>,
+    next_nonce: AtomicU64,
+}
+
+impl AdminConsole {
+    pub fn new(config: AdminConfig, controller_policy: ControllerPolicy) -> Self {
+        Self {
+            config,
+            confirmations: Mutex::new(HashMap::new()),
+            paused_controllers: RwLock::new(HashSet::new()),
+            controller_policy: RwLock::new(controller_policy),
+            next_nonce: AtomicU64::new(0),
+        }
+    }
+
+    /// Check that the caller holds the admin role
+    pub fn authorize(&self, auth: &AdminAuth) -> ServiceResult<()> {
+        if !self.config.enabled {
+            return Err(ServiceError::unauthorized("Admin RPCs are disabled"));
+        }
+        let Some(api_key) = &self.config.api_key else {
+            return Err(ServiceError::unauthorized("No admin key is configured"));
+        };
+
+        // Compare digests so the check takes the same time for any key
+        if Sha256::digest(api_key) != Sha256::digest(&auth.api_key) {
+            return Err(ServiceError::unauthorized("Invalid admin key"));
+        }
+        Ok(())
+    }
+
+    /// Issue a confirmation token for a destructive operation
+    pub fn issue_confirmation(&self, operation: &AdminOperation) -> Option<String> {
+        if !operation.is_destructive() {
+            return None;
+        }
+
+        let nonce = self.next_nonce.fetch_add(1, Ordering::Relaxed);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos())
+            .unwrap_or_default();
+        let digest = Sha256::new()
+            .chain_update(nonce.to_le_bytes())
+            .chain_update(nanos.to_le_bytes())
+            .chain_update(format!("{:?}", operation))
+            .finalize();
+        let token = hex::encode(&digest[..16]);
+
+        let ttl = self.confirmation_ttl();
+        let mut confirmations = self.confirmations.lock().unwrap();
+        confirmations.retain(|_, pending| pending.issued_at.elapsed() < ttl);
+        confirmations.insert(token.clone(), PendingConfirmation {
+            operation: operation.clone(),
+            issued_at: Instant::now(),
+        });
+        Some(token)
+    }
+
+    /// Redeem the caller's confirmation token if `operation` needs one
+    ///
+    /// A token is single-use and only valid for the operation, with the
+    /// same parameters, that its dry run described.
+    pub fn confirm(&self, auth: &AdminAuth, operation: &AdminOperation) -> ServiceResult<()> {
+        if !operation.is_destructive() {
+            return Ok(());
+        }
+
+        let token = auth.confirmation.as_deref().ok_or_else(|| {
+            ServiceError::invalid_request("Destructive operation requires a confirmation token from a dry run")
+        })?;
+        let pending = self.confirmations.lock().unwrap().remove(token).ok_or_else(|| {
+            ServiceError::invalid_request("Unknown or already used confirmation token")
+        })?;
+
+        if pending.issued_at.elapsed() >= self.confirmation_ttl() {
+            return Err(ServiceError::invalid_request("Confirmation token has expired"));
+        }
+        if &pending.operation != operation {
+            return Err(ServiceError::invalid_request("Confirmation token was issued for a different operation"));
+        }
+        Ok(())
+    }
+
+    fn confirmation_ttl(&self) -> Duration {
+        Duration::from_secs(self.config.confirmation_ttl_secs)
+    }
+
+    /// Pause a controller, returning whether it was running
+    pub fn pause_controller(&self, controller_id: UnitsObjectId) -> bool {
+        self.paused_controllers.write().unwrap().insert(controller_id)
+    }
+
+    /// Resume a controller, returning whether it was paused
+    pub fn resume_controller(&self, controller_id: &UnitsObjectId) -> bool {
+        self.paused_controllers.write().unwrap().remove(controller_id)
+    }
+
+    pub fn is_paused(&self, controller_id: &UnitsObjectId) -> bool {
+        self.paused_controllers.read().unwrap().contains(controller_id)
+    }
+
+    /// Whether the controller policy admits `controller_id`
+    pub fn is_permitted(&self, controller_id: &UnitsObjectId) -> bool {
+        self.controller_policy.read().unwrap().permits(controller_id)
+    }
+
+    /// Describe each admission change replacing the policy with `policy` makes
+    pub fn controller_policy_changes(&self, policy: &ControllerPolicy) -> Vec<String> {
+        let current = self.controller_policy.read().unwrap();
+        let mut changes = Vec::new();
+        match (&current.allowed, &policy.allowed) {
+            (None, None) => {}
+            (None, Some(after)) => changes.push(format!("admit only {} listed controllers", after.len())),
+            (Some(_), None) => changes.push("admit every controller".to_string()),
+            (Some(before), Some(after)) => {
+                changes.extend(after.iter().filter(|id| !before.contains(id)).map(|id| format!("allow {}", id)));
+                changes.extend(before.iter().filter(|id| !after.contains(id)).map(|id| format!("unallow {}", id)));
+            }
+        }
+        let (before, after) = (&current.denied, &policy.denied);
+        changes.extend(after.iter().filter(|id| !before.contains(id)).map(|id| format!("deny {}", id)));
+        changes.extend(before.iter().filter(|id| !after.contains(id)).map(|id| format!("undeny {}", id)));
+        changes
+    }
+
+    /// Replace the controller policy, returning the one it replaced
+    pub fn set_controller_policy(&self, policy: ControllerPolicy) -> ControllerPolicy {
+        std::mem::replace(&mut *self.controller_policy.write().unwrap(), policy)
+    }
+}
@@ -0,0 +1,109 @@
+//! Proof-of-existence records for external documents
+//!
+//! A client submits the hash of a document; the node stores it as an
+//! object of the attest controller, laid out as the attest module's
+//! `AttestationData`, at an ID derived from the hash. The next state proof
+//! then covers the record, which pins the time the document was known to
+//! exist. The document itself never reaches the node.
+
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use attest::{attestation_id, AttestationData};
+use serde::{Deserialize, Serialize};
+use units_core_types::{ObjectStorage, UnitsObject, UnitsObjectId, UnitsStorage};
+use units_kernel_sdk::{decode_versioned, encode_versioned};
+use units_storage_impl::ConsolidatedUnitsStorage;
+
+use super::minimal_services::MinimalSlotService;
+use crate::error::{ServiceError, ServiceResult};
+
+/// An attested document hash and when it was first seen
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attestation {
+    pub object_id: UnitsObjectId,
+    /// Hex-encoded hash of the document
+    pub document_hash: String,
+    pub attester_id: UnitsObjectId,
+    /// Slot whose state proof covers the record
+    pub slot: u64,
+    /// Unix time in seconds the record was written
+    pub timestamp: u64,
+}
+
+impl Attestation {
+    fn from_data(object_id: UnitsObjectId, data: AttestationData) -> Self {
+        Self {
+            object_id,
+            document_hash: hex::encode(data.document_hash),
+            attester_id: data.attester_id.into(),
+            slot: data.slot,
+            timestamp: data.timestamp,
+        }
+    }
+
+    /// Decode an attestation record, failing if `object` is not one
+    pub fn from_object(object: &UnitsObject, controller_id: &UnitsObjectId) -> Option<Self> {
+        if object.controller_id() != controller_id {
+            return None;
+        }
+        let data: AttestationData = decode_versioned(object.data()).ok()?;
+        (*object.id() == UnitsObjectId::from(attestation_id(&data.document_hash)))
+            .then(|| Self::from_data(*object.id(), data))
+    }
+}
+
+/// Writes and reads attestation records of the attest controller
+pub struct AttestationService {
+    storage: Arc<ConsolidatedUnitsStorage>,
+    slot_service: Arc<MinimalSlotService>,
+    controller_id: UnitsObjectId,
+}
+
+impl AttestationService {
+    pub fn new(
+        storage: Arc<ConsolidatedUnitsStorage>,
+        slot_service: Arc<MinimalSlotService>,
+        controller_id: UnitsObjectId,
+    ) -> Self {
+        Self { storage, slot_service, controller_id }
+    }
+
+    /// Attest to `document_hash`, or return the existing attestation
+    ///
+    /// The earliest attestation stands, so submitting a hash again never
+    /// moves its recorded slot forward.
+    pub fn attest(&self, document_hash: [u8; 32], attester_id: UnitsObjectId) -> ServiceResult<Attestation> {
+        if let Some(existing) = self.get(&document_hash)? {
+            return Ok(existing);
+        }
+
+        let object_id: UnitsObjectId = attestation_id(&document_hash).into();
+        let data = AttestationData {
+            document_hash,
+            attester_id: attester_id.into(),
+            // Writes land in the state proof of the slot being built
+            slot: self.slot_service.current_slot() + 1,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+        };
+        let encoded = encode_versioned(&data)
+            .map_err(|_| ServiceError::Internal(anyhow::anyhow!("Failed to encode attestation")))?;
+        let object = UnitsObject::new_data(object_id, self.controller_id, encoded);
+        self.storage.objects().set(&object, None)?;
+
+        Ok(Attestation::from_data(object_id, data))
+    }
+
+    /// Attestation of `document_hash`, if it has been attested
+    pub fn get(&self, document_hash: &[u8; 32]) -> ServiceResult<Option<Attestation>> {
+        let object_id: UnitsObjectId = attestation_id(document_hash).into();
+        Ok(self
+            .storage
+            .objects()
+            .get(&object_id)?
+            .and_then(|object| Attestation::from_object(&object, &self.controller_id)))
+    }
+}
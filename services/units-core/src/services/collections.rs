@@ -0,0 +1,214 @@
+//! Named sets of objects with provable membership
+//!
+//! A collection is an object of the system loader listing the IDs of its
+//! members, kept sorted so the list itself is canonical. It lives at an ID
+//! derived from its owner and name. Because the state proof covers the
+//! collection object like any other, its evidence proves which objects
+//! belong to it, and applications can reason about sets (all NFTs in a
+//! series, all balances of a fund) instead of single objects.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use units_core_types::{ObjectStorage, UnitsObject, UnitsObjectId, UnitsStorage, SYSTEM_LOADER_ID};
+use units_storage_impl::ConsolidatedUnitsStorage;
+
+use crate::context::RequestContext;
+use crate::error::{ServiceError, ServiceResult};
+
+/// Members returned per page of `members_page`
+pub const MEMBERS_PAGE_SIZE: usize = 100;
+
+/// Most members a single collection may hold
+pub const MAX_COLLECTION_MEMBERS: usize = 10_000;
+
+/// An owner's named set of objects
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Collection {
+    pub id: UnitsObjectId,
+    pub owner_id: UnitsObjectId,
+    pub name: String,
+    /// Member IDs in ascending order, without duplicates
+    pub members: Vec<UnitsObjectId>,
+}
+
+impl Collection {
+    /// ID of the collection `name` of `owner_id`
+    pub fn object_id(owner_id: &UnitsObjectId, name: &str) -> UnitsObjectId {
+        UnitsObjectId::new(UnitsObjectId::create_object_id(
+            &[b"collection", owner_id.bytes(), name.as_bytes()],
+            0,
+        ))
+    }
+
+    #[allow(dead_code)]
+    pub fn contains(&self, member_id: &UnitsObjectId) -> bool {
+        self.members.binary_search(member_id).is_ok()
+    }
+
+    pub fn to_object(&self) -> ServiceResult<UnitsObject> {
+        let data = bincode::serialize(self)
+            .map_err(|_| ServiceError::Internal(anyhow::anyhow!("Failed to encode collection")))?;
+        Ok(UnitsObject::new_data(self.id, SYSTEM_LOADER_ID, data))
+    }
+
+    /// Decode a collection object, failing if `object` is not one
+    pub fn from_object(object: &UnitsObject) -> Option<Self> {
+        if *object.controller_id() != SYSTEM_LOADER_ID {
+            return None;
+        }
+        let collection: Self = bincode::deserialize(object.data()).ok()?;
+        let canonical = collection.members.windows(2).all(|pair| pair[0] < pair[1]);
+        (canonical
+            && collection.id == *object.id()
+            && collection.id == Self::object_id(&collection.owner_id, &collection.name))
+            .then_some(collection)
+    }
+}
+
+/// One page of a collection's member objects, in ID order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionMembers {
+    pub collection_id: UnitsObjectId,
+    pub page: u32,
+    pub total_members: u64,
+    pub members: Vec<UnitsObject>,
+}
+
+/// Creates collections and maintains their member lists
+pub struct CollectionService {
+    storage: Arc<ConsolidatedUnitsStorage>,
+}
+
+impl CollectionService {
+    pub fn new(storage: Arc<ConsolidatedUnitsStorage>) -> Self {
+        Self { storage }
+    }
+
+    /// Create an empty collection, failing if the owner already has one by that name
+    pub fn create(&self, owner_id: UnitsObjectId, name: &str) -> ServiceResult<Collection> {
+        if name.is_empty() {
+            return Err(ServiceError::invalid_request("Collection name must not be empty"));
+        }
+        let id = Collection::object_id(&owner_id, name);
+        if self.storage.objects().get(&id)?.is_some() {
+            return Err(ServiceError::invalid_request(format!("Collection {} already exists", name)));
+        }
+
+        let collection = Collection { id, owner_id, name: name.to_string(), members: Vec::new() };
+        self.storage.objects().set(&collection.to_object()?, None)?;
+        Ok(collection)
+    }
+
+    pub fn get(&self, collection_id: &UnitsObjectId) -> ServiceResult<Option<Collection>> {
+        Ok(self
+            .storage
+            .objects()
+            .get(collection_id)?
+            .and_then(|object| Collection::from_object(&object)))
+    }
+
+    /// Add and remove members on behalf of the collection's owner
+    ///
+    /// Only existing objects can join. Removals apply after additions, so an
+    /// ID in both lists ends up outside the collection.
+    pub fn update_members(
+        &self,
+        collection_id: &UnitsObjectId,
+        owner_id: &UnitsObjectId,
+        add: &[UnitsObjectId],
+        remove: &[UnitsObjectId],
+    ) -> ServiceResult<Collection> {
+        let mut collection = self.require(collection_id)?;
+        if collection.owner_id != *owner_id {
+            return Err(ServiceError::unauthorized(format!(
+                "{} does not own collection {}",
+                owner_id, collection.name
+            )));
+        }
+
+        for member_id in add {
+            if *member_id == collection.id || self.storage.objects().get(member_id)?.is_none() {
+                return Err(ServiceError::invalid_request(format!("Cannot add {} to a collection", member_id)));
+            }
+        }
+        collection.members.extend_from_slice(add);
+        collection.members.sort();
+        collection.members.dedup();
+        collection.members.retain(|member_id| !remove.contains(member_id));
+        if collection.members.len() > MAX_COLLECTION_MEMBERS {
+            return Err(ServiceError::invalid_request(format!(
+                "Collections hold at most {} members",
+                MAX_COLLECTION_MEMBERS
+            )));
+        }
+
+        self.storage.objects().set(&collection.to_object()?, None)?;
+        Ok(collection)
+    }
+
+    /// Page `page` (from 0) of the objects in a collection
+    ///
+    /// Members deleted since they joined are skipped but still counted.
+    pub fn members_page(
+        &self,
+        ctx: &RequestContext,
+        collection_id: &UnitsObjectId,
+        page: u32,
+    ) -> ServiceResult<CollectionMembers> {
+        let collection = self.require(collection_id)?;
+        let mut members = Vec::new();
+        for member_id in collection.members.iter().skip(page as usize * MEMBERS_PAGE_SIZE).take(MEMBERS_PAGE_SIZE) {
+            ctx.check()?;
+            members.extend(self.storage.objects().get(member_id)?);
+        }
+
+        Ok(CollectionMembers {
+            collection_id: *collection_id,
+            page,
+            total_members: collection.members.len() as u64,
+            members,
+        })
+    }
+
+    fn require(&self, collection_id: &UnitsObjectId) -> ServiceResult<Collection> {
+        self.get(collection_id)?
+            .ok_or_else(|| ServiceError::object_not_found(collection_id.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_members_stay_sorted_and_owned() {
+        let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+        let service = CollectionService::new(storage.clone());
+        let owner = UnitsObjectId::new([1; 32]);
+        let ids: Vec<_> = [9u8, 4, 7].iter().map(|seed| UnitsObjectId::new([*seed; 32])).collect();
+        for id in &ids {
+            storage.objects().set(&UnitsObject::new_data(*id, owner, vec![]), None).unwrap();
+        }
+
+        let series = service.create(owner, "series").unwrap();
+        assert!(service.create(owner, "series").is_err());
+        let updated = service.update_members(&series.id, &owner, &ids, &ids[..1]).unwrap();
+        assert_eq!(updated.members, vec![ids[1], ids[2]]);
+        assert!(updated.contains(&ids[2]) && !updated.contains(&ids[0]));
+        assert_eq!(service.get(&series.id).unwrap(), Some(updated));
+
+        // Only the owner edits, and only existing objects join
+        let stranger = UnitsObjectId::new([2; 32]);
+        assert!(matches!(
+            service.update_members(&series.id, &stranger, &[], &ids),
+            Err(ServiceError::Unauthorized { .. })
+        ));
+        let missing = UnitsObjectId::new([3; 32]);
+        assert!(service.update_members(&series.id, &owner, &[missing], &[]).is_err());
+
+        let page = service.members_page(&RequestContext::new(), &series.id, 0).unwrap();
+        assert_eq!(page.total_members, 2);
+        assert_eq!(page.members.iter().map(|object| *object.id()).collect::<Vec<_>>(), vec![ids[1], ids[2]]);
+    }
+}
@@ -0,0 +1,137 @@
+//! Transaction dependency graphs built from receipts
+//!
+//! A transaction depends on another when it read or overwrote a state the
+//! other wrote. Receipts name the states involved by hash: each effect
+//! carries the hash of the image it replaced, and each read the hash of the
+//! image it saw. Matching those against the hashes other effects produced
+//! follows the order transactions actually ran in, even within a slot,
+//! whose receipts are stored by transaction hash.
+//!
+//! Only writes by transactions in the graph are matched, so a transaction
+//! whose inputs were written before the range has no incoming edges.
+//! Receipts stored before reads were recorded contribute write edges only.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use units_core_types::{SlotNumber, TransactionHash, TransactionReceipt, UnitsObjectId};
+
+/// Transaction in a dependency graph
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionNode {
+    pub transaction_hash: TransactionHash,
+    pub slot: SlotNumber,
+    pub success: bool,
+}
+
+/// How a transaction used a state another one wrote
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DependencyKind {
+    /// Read the state without writing the object
+    ReadAfterWrite,
+    /// Replaced the state with one of its own
+    WriteAfterWrite,
+}
+
+/// `to` used the state of `object_id` that `from` wrote
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DependencyEdge {
+    pub from: TransactionHash,
+    pub to: TransactionHash,
+    pub object_id: UnitsObjectId,
+    pub kind: DependencyKind,
+}
+
+/// Dependencies among the transactions of a slot range
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransactionGraph {
+    /// Transactions in slot order, then by transaction hash
+    pub transactions: Vec<TransactionNode>,
+    /// Edges in the order of the transactions they lead to
+    pub edges: Vec<DependencyEdge>,
+    /// First slot of the next part of the range, absent when the range is exhausted
+    pub next_slot: Option<SlotNumber>,
+}
+
+impl TransactionGraph {
+    /// Graph of `receipts`, each of which may only depend on the others
+    pub fn from_receipts(receipts: &[TransactionReceipt], next_slot: Option<SlotNumber>) -> Self {
+        // Failed receipts carry no effects, so every writer here committed
+        let mut writers: HashMap<(UnitsObjectId, [u8; 32]), TransactionHash> = HashMap::new();
+        for receipt in receipts {
+            for effect in &receipt.effects {
+                if let Some(after_hash) = effect.after_hash {
+                    writers.insert((effect.object_id, after_hash), receipt.transaction_hash);
+                }
+            }
+        }
+
+        let mut graph = Self { next_slot, ..Self::default() };
+        for receipt in receipts {
+            let to = receipt.transaction_hash;
+            graph.transactions.push(TransactionNode {
+                transaction_hash: to,
+                slot: receipt.slot,
+                success: receipt.success,
+            });
+
+            let writes = receipt
+                .effects
+                .iter()
+                .filter_map(|effect| Some((effect.object_id, effect.before_hash?, DependencyKind::WriteAfterWrite)));
+            let reads = receipt
+                .reads
+                .iter()
+                .filter_map(|read| Some((read.object_id, read.state_hash?, DependencyKind::ReadAfterWrite)));
+            for (object_id, state_hash, kind) in writes.chain(reads) {
+                match writers.get(&(object_id, state_hash)) {
+                    Some(&from) if from != to => graph.edges.push(DependencyEdge { from, to, object_id, kind }),
+                    _ => {}
+                }
+            }
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use units_core_types::{ObjectRead, UnitsObject};
+
+    fn object(id: u8, data: u8) -> UnitsObject {
+        UnitsObject::new_data(UnitsObjectId::new([id; 32]), UnitsObjectId::new([9; 32]), vec![data])
+    }
+
+    fn write(hash: u8, slot: SlotNumber, before: &UnitsObject, after: &UnitsObject) -> TransactionReceipt {
+        let mut receipt = TransactionReceipt::new([hash; 32], slot, true, 0);
+        receipt.add_object_effect([hash; 32], after.id, Some(before.clone()), Some(after.clone()));
+        receipt
+    }
+
+    #[test]
+    fn test_edges_follow_state_hashes_not_receipt_order() {
+        let (v0, v1, v2) = (object(1, 0), object(1, 1), object(1, 2));
+        // Stored by hash, so the later write is listed first
+        let second = write(1, 3, &v1, &v2);
+        let first = write(2, 3, &v0, &v1);
+        let mut reader = TransactionReceipt::new([3; 32], 4, false, 0);
+        reader.reads.push(ObjectRead { object_id: v2.id, state_hash: Some(v2.state_hash()) });
+        // Reading a state written before the range is not an edge
+        let mut early = TransactionReceipt::new([4; 32], 4, true, 0);
+        early.reads.push(ObjectRead { object_id: v0.id, state_hash: Some(v0.state_hash()) });
+
+        let graph = TransactionGraph::from_receipts(&[second, first, reader, early], Some(5));
+        assert_eq!(graph.transactions.len(), 4);
+        assert!(!graph.transactions[2].success);
+        assert_eq!(graph.next_slot, Some(5));
+        assert_eq!(
+            graph.edges,
+            vec![
+                DependencyEdge { from: [2; 32], to: [1; 32], object_id: v1.id, kind: DependencyKind::WriteAfterWrite },
+                DependencyEdge { from: [1; 32], to: [3; 32], object_id: v2.id, kind: DependencyKind::ReadAfterWrite },
+            ]
+        );
+    }
+}
@@ -0,0 +1,373 @@
+//! Background maintenance held back while the node is busy
+//!
+//! Compaction, pruning and scrubbing contend with RPC traffic and slot
+//! processing for the same storage locks. The scheduler gives each job its
+//! own interval and, at every check, runs the jobs that are due only while
+//! in-flight requests, the request rate and the time the last slot took to
+//! close are all under the configured thresholds. A job held back stays due
+//! and runs at the first quiet check. Admins can pause the scheduler outright.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::time::{interval, MissedTickBehavior};
+use units_proofs::{with_proof_engine, SlotOrdering};
+use units_storage_impl::ConsolidatedUnitsStorage;
+
+use crate::config::MaintenanceConfig;
+use crate::error::ServiceResult;
+use super::minimal_services::MinimalSlotService;
+use super::RetentionManager;
+
+/// Background job run by the scheduler
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MaintenanceJob {
+    /// Drop object versions older than the history window
+    Compact,
+    /// Drop receipts and state proofs older than their windows
+    Prune,
+    /// Verify every object's proof chain
+    Scrub,
+}
+
+impl MaintenanceJob {
+    const ALL: [Self; 3] = [Self::Compact, Self::Prune, Self::Scrub];
+
+    fn interval(&self, config: &MaintenanceConfig) -> Option<Duration> {
+        let interval_ms = match self {
+            Self::Compact => config.compact_interval_ms,
+            Self::Prune => config.prune_interval_ms,
+            Self::Scrub => config.scrub_interval_ms,
+        };
+        interval_ms.map(Duration::from_millis)
+    }
+}
+
+/// Live load the scheduler weighs its jobs against
+///
+/// The RPC layer brackets every call with `begin_request`, and slot
+/// processing reports how long each slot took to close.
+#[derive(Debug, Default)]
+pub struct LoadMonitor {
+    in_flight: AtomicUsize,
+    requests: AtomicU64,
+    last_slot_ms: AtomicU64,
+}
+
+impl LoadMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a request as in flight until the returned guard drops
+    pub fn begin_request(self: &Arc<Self>) -> RequestGuard {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+        RequestGuard(self.clone())
+    }
+
+    /// Record how long the latest slot took to close
+    #[allow(dead_code)]
+    pub fn record_slot(&self, duration: Duration) {
+        self.last_slot_ms.store(duration.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Acquire)
+    }
+
+    /// Requests begun since the monitor was created
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    pub fn last_slot_ms(&self) -> u64 {
+        self.last_slot_ms.load(Ordering::Relaxed)
+    }
+}
+
+/// Marks one request in flight
+pub struct RequestGuard(Arc<LoadMonitor>);
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Load observed at one scheduler check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoadSample {
+    pub in_flight: usize,
+    pub requests_per_sec: u64,
+    pub last_slot_ms: u64,
+}
+
+/// Outcome of one job run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceRun {
+    pub job: MaintenanceJob,
+    /// Versions, receipts and proofs removed, or objects failing a scrub
+    pub affected: usize,
+    pub error: Option<String>,
+}
+
+/// Scheduler state reported to admins
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceStatus {
+    pub enabled: bool,
+    pub paused: bool,
+    /// Checks at which due jobs were held back by load
+    pub deferred: u64,
+    /// Load at the latest check
+    pub load: Option<LoadSample>,
+}
+
+struct SchedulerState {
+    /// When each job last ran, absent until its first run
+    last_run: HashMap<MaintenanceJob, Instant>,
+    /// Request count and time of the previous check, for the request rate
+    last_check: (Instant, u64),
+    last_load: Option<LoadSample>,
+    deferred: u64,
+}
+
+/// Runs maintenance jobs on their intervals while load allows
+pub struct MaintenanceScheduler {
+    config: MaintenanceConfig,
+    load: Arc<LoadMonitor>,
+    retention: Arc<RetentionManager>,
+    storage: Arc<ConsolidatedUnitsStorage>,
+    slot_service: Arc<MinimalSlotService>,
+    slot_ordering: SlotOrdering,
+    paused: AtomicBool,
+    state: Mutex<SchedulerState>,
+}
+
+impl MaintenanceScheduler {
+    pub fn new(
+        config: MaintenanceConfig,
+        retention: Arc<RetentionManager>,
+        storage: Arc<ConsolidatedUnitsStorage>,
+        slot_service: Arc<MinimalSlotService>,
+        slot_ordering: SlotOrdering,
+    ) -> Self {
+        let now = Instant::now();
+        Self {
+            config,
+            load: Arc::new(LoadMonitor::new()),
+            retention,
+            storage,
+            slot_service,
+            slot_ordering,
+            paused: AtomicBool::new(false),
+            // Intervals count from startup, so nothing runs while the node warms up
+            state: Mutex::new(SchedulerState {
+                last_run: MaintenanceJob::ALL.into_iter().map(|job| (job, now)).collect(),
+                last_check: (now, 0),
+                last_load: None,
+                deferred: 0,
+            }),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Load the scheduler weighs jobs against
+    pub fn load(&self) -> &Arc<LoadMonitor> {
+        &self.load
+    }
+
+    /// Check for due jobs every `check_interval_ms`, if maintenance is enabled
+    pub fn start(self: &Arc<Self>) {
+        if !self.config.enabled {
+            return;
+        }
+        let scheduler = self.clone();
+
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_millis(scheduler.config.check_interval_ms.max(1)));
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            loop {
+                ticker.tick().await;
+                for run in scheduler.tick(Instant::now()) {
+                    match &run.error {
+                        Some(error) => log::error!("Maintenance job {:?} failed: {}", run.job, error),
+                        None => log::debug!("Maintenance job {:?} touched {} items", run.job, run.affected),
+                    }
+                }
+            }
+        });
+    }
+
+    /// Stop running jobs, returning whether the scheduler was running
+    pub fn pause(&self) -> bool {
+        !self.paused.swap(true, Ordering::AcqRel)
+    }
+
+    /// Run jobs again, returning whether the scheduler was paused
+    pub fn resume(&self) -> bool {
+        self.paused.swap(false, Ordering::AcqRel)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Acquire)
+    }
+
+    pub fn status(&self) -> MaintenanceStatus {
+        let state = self.state.lock().unwrap();
+        MaintenanceStatus {
+            enabled: self.config.enabled,
+            paused: self.is_paused(),
+            deferred: state.deferred,
+            load: state.last_load,
+        }
+    }
+
+    /// Sample load and run the jobs due at `now` if it is under every threshold
+    pub fn tick(&self, now: Instant) -> Vec<MaintenanceRun> {
+        let due: Vec<MaintenanceJob> = {
+            let mut state = self.state.lock().unwrap();
+            let load = self.sample(&mut state, now);
+            state.last_load = Some(load);
+
+            let due: Vec<_> = MaintenanceJob::ALL
+                .into_iter()
+                .filter(|job| match job.interval(&self.config) {
+                    Some(interval) => state.last_run.get(job).map_or(true, |last| now.duration_since(*last) >= interval),
+                    None => false,
+                })
+                .collect();
+            if due.is_empty() || self.is_paused() {
+                return Vec::new();
+            }
+            if !self.is_quiet(&load) {
+                state.deferred += 1;
+                log::debug!("Deferring maintenance {:?} under load {:?}", due, load);
+                return Vec::new();
+            }
+            for job in &due {
+                state.last_run.insert(*job, now);
+            }
+            due
+        };
+
+        due.into_iter()
+            .map(|job| match self.run(job) {
+                Ok(affected) => MaintenanceRun { job, affected, error: None },
+                Err(e) => MaintenanceRun { job, affected: 0, error: Some(e.to_string()) },
+            })
+            .collect()
+    }
+
+    fn sample(&self, state: &mut SchedulerState, now: Instant) -> LoadSample {
+        let requests = self.load.requests();
+        let (last_at, last_requests) = state.last_check;
+        let elapsed_ms = now.saturating_duration_since(last_at).as_millis().max(1) as u64;
+        state.last_check = (now, requests);
+
+        LoadSample {
+            in_flight: self.load.in_flight(),
+            requests_per_sec: (requests - last_requests) * 1000 / elapsed_ms,
+            last_slot_ms: self.load.last_slot_ms(),
+        }
+    }
+
+    fn is_quiet(&self, load: &LoadSample) -> bool {
+        load.in_flight <= self.config.max_in_flight_requests
+            && load.requests_per_sec <= self.config.max_requests_per_sec
+            && load.last_slot_ms <= self.config.max_slot_duration_ms
+    }
+
+    fn run(&self, job: MaintenanceJob) -> ServiceResult<usize> {
+        let current_slot = self.slot_service.current_slot();
+        match job {
+            MaintenanceJob::Compact => self.retention.compact(current_slot),
+            MaintenanceJob::Prune => {
+                let report = self.retention.prune(current_slot)?;
+                Ok(report.receipts + report.state_proofs)
+            }
+            MaintenanceJob::Scrub => {
+                let ids = self.storage.inner().object_ids();
+                let failures = with_proof_engine!(self.storage.hash_algorithm(), |engine| self
+                    .storage
+                    .verify_objects(&ids, &engine.with_slot_ordering(self.slot_ordering), |_| {}));
+                for (id, error) in &failures {
+                    log::warn!("Scrub found a broken proof chain for {}: {}", id, error);
+                }
+                Ok(failures.len())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RetentionConfig;
+
+    fn scheduler(config: MaintenanceConfig) -> MaintenanceScheduler {
+        let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+        let retention = Arc::new(RetentionManager::new(RetentionConfig::default(), storage.clone()));
+        MaintenanceScheduler::new(config, retention, storage, Arc::new(MinimalSlotService::new()), SlotOrdering::default())
+    }
+
+    #[test]
+    fn test_jobs_wait_for_quiet_load() {
+        let config = MaintenanceConfig {
+            enabled: true,
+            max_in_flight_requests: 1,
+            compact_interval_ms: Some(10),
+            prune_interval_ms: None,
+            scrub_interval_ms: Some(1_000),
+            ..MaintenanceConfig::default()
+        };
+        let scheduler = scheduler(config);
+        let start = Instant::now();
+        assert!(scheduler.tick(start).is_empty(), "Jobs ran before their interval");
+
+        // Busy: the due job is held back and stays due
+        let busy = [scheduler.load().begin_request(), scheduler.load().begin_request()];
+        assert!(scheduler.tick(start + Duration::from_millis(20)).is_empty());
+        assert_eq!(scheduler.status().deferred, 1);
+        assert_eq!(scheduler.status().load.unwrap().in_flight, 2);
+        drop(busy);
+
+        let runs = scheduler.tick(start + Duration::from_millis(25));
+        assert_eq!(runs.iter().map(|run| run.job).collect::<Vec<_>>(), vec![MaintenanceJob::Compact]);
+        assert!(runs[0].error.is_none());
+
+        // A slow slot holds jobs back too
+        scheduler.load().record_slot(Duration::from_secs(2));
+        assert!(scheduler.tick(start + Duration::from_millis(40)).is_empty());
+        scheduler.load().record_slot(Duration::from_millis(1));
+        assert_eq!(scheduler.tick(start + Duration::from_millis(45)).len(), 1);
+    }
+
+    #[test]
+    fn test_paused_scheduler_runs_nothing() {
+        let config = MaintenanceConfig {
+            enabled: true,
+            compact_interval_ms: Some(10),
+            ..MaintenanceConfig::default()
+        };
+        let scheduler = scheduler(config);
+        let start = Instant::now();
+
+        assert!(scheduler.pause());
+        assert!(!scheduler.pause());
+        assert!(scheduler.tick(start + Duration::from_millis(20)).is_empty());
+        assert_eq!(scheduler.status().deferred, 0);
+
+        assert!(scheduler.resume());
+        assert_eq!(scheduler.tick(start + Duration::from_millis(30)).len(), 1);
+    }
+}
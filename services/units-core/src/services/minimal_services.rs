@@ -3,19 +3,78 @@
 //! These are simplified versions of the full services that demonstrate
 //! the architecture without complex dependencies.
 
-use std::sync::Arc;
-use crate::error::ServiceResult;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use crate::error::{NodeLoad, ServiceError, ServiceResult};
+use super::transaction_service::{execute_atomically, execute_with_write_locks};
+use super::shadow::ShadowExecutor;
+use super::slot_summary::SlotSummary;
 use units_core_types::{
     UnitsObjectId, UnitsObject, ObjectStorage,
-    TransactionHash, Transaction,
+    TransactionHash, Transaction, TransactionReceipt,
     SlotNumber, Runtime,
+    AdaptiveBatchConfig, AdaptiveBatchSizer, Admission,
+    FeeEstimate, FeeMarket, SlotFeeStats,
+    AccessIntent, AccessSet, BasicConflictChecker, ConflictChecker, ModuleRegistry, ParallelScheduler,
+    ParallelSchedulerConfig, MODULE_REGISTRY_ID,
 };
 use units_storage_impl::ConsolidatedUnitsStorage;
+#[cfg(feature = "sqlite")]
+use units_core_types::{LockType, PersistentLockManager};
+#[cfg(feature = "sqlite")]
+use units_storage_impl::{LockRecovery, SqliteLockManager};
+use serde::{Deserialize, Serialize};
+
+/// Confirmed slots a slot must be buried under before it is finalized, by default
+pub const DEFAULT_FINALITY_DEPTH: u64 = 32;
+
+/// Lock table file under the data directory
+#[cfg(feature = "sqlite")]
+pub const LOCK_TABLE_FILE: &str = "locks.db";
+
+/// How settled the state at a slot is
+///
+/// Ordered from least to most settled, so `finality >= Finality::Confirmed`
+/// reads as "at least confirmed".
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Finality {
+    /// Executed, but not yet covered by a state proof; may still change
+    #[default]
+    Processed,
+    /// Covered by a committed state proof
+    Confirmed,
+    /// Confirmed and buried under the finality depth; never changes
+    Finalized,
+}
+
+/// Latest slot at each finality level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlotStatus {
+    pub processed_slot: SlotNumber,
+    pub confirmed_slot: SlotNumber,
+    pub finalized_slot: SlotNumber,
+}
 
 /// Minimal transaction service
 pub struct MinimalTransactionService {
     runtime: Arc<dyn Runtime + Send + Sync>,
     storage: Arc<ConsolidatedUnitsStorage>,
+    /// Transactions waiting to be executed, highest priority fee first and
+    /// in submission order among equal fees
+    pending: Mutex<VecDeque<Transaction>>,
+    /// Per-slot batch sizing driven by measured execution latency
+    sizer: Mutex<AdaptiveBatchSizer>,
+    /// Inclusion behaviour of recent slots, for fee estimates
+    fee_market: Mutex<FeeMarket>,
+    /// Re-executes a sample of transactions to check determinism
+    shadow: Mutex<Option<Arc<ShadowExecutor>>>,
+    /// Runs each batch's non-conflicting transactions concurrently
+    scheduler: Mutex<ParallelScheduler>,
+    /// Durable record of the locks held by executing transactions
+    #[cfg(feature = "sqlite")]
+    lock_table: Mutex<Option<Arc<SqliteLockManager>>>,
 }
 
 impl MinimalTransactionService {
@@ -23,12 +82,219 @@ impl MinimalTransactionService {
         runtime: Arc<dyn Runtime + Send + Sync>,
         storage: Arc<ConsolidatedUnitsStorage>,
     ) -> Self {
-        Self { runtime, storage }
+        Self::with_batch_config(runtime, storage, AdaptiveBatchConfig::default())
+    }
+
+    pub fn with_batch_config(
+        runtime: Arc<dyn Runtime + Send + Sync>,
+        storage: Arc<ConsolidatedUnitsStorage>,
+        batch_config: AdaptiveBatchConfig,
+    ) -> Self {
+        Self {
+            runtime,
+            storage,
+            pending: Mutex::new(VecDeque::new()),
+            sizer: Mutex::new(AdaptiveBatchSizer::new(batch_config)),
+            fee_market: Mutex::new(FeeMarket::default()),
+            shadow: Mutex::new(None),
+            scheduler: Mutex::new(ParallelScheduler::new(ParallelSchedulerConfig::default())),
+            #[cfg(feature = "sqlite")]
+            lock_table: Mutex::new(None),
+        }
+    }
+
+    /// Record each transaction's write locks in the lock table under
+    /// `data_dir` while it executes
+    ///
+    /// Locks a previous run left behind for transactions that are not
+    /// queued here, because the node crashed while executing them, are
+    /// released first.
+    #[cfg(feature = "sqlite")]
+    pub fn open_lock_table(&self, data_dir: &str) -> ServiceResult<LockRecovery> {
+        let lock_table = SqliteLockManager::open(&std::path::Path::new(data_dir).join(LOCK_TABLE_FILE))?;
+        let pending = self.pending.lock().unwrap();
+        let recovery = lock_table.recover(|hash| pending.iter().any(|transaction| transaction.hash == *hash))?;
+        drop(pending);
+        *self.lock_table.lock().unwrap() = Some(Arc::new(lock_table));
+        Ok(recovery)
+    }
+
+    /// Compare a sample of executed transactions against `shadow`
+    pub fn set_shadow(&self, shadow: Arc<ShadowExecutor>) {
+        *self.shadow.lock().unwrap() = Some(shadow);
     }
 
+    /// Execute batches with `scheduler`
+    pub fn set_scheduler(&self, scheduler: ParallelScheduler) {
+        *self.scheduler.lock().unwrap() = scheduler;
+    }
+
+    /// Queue a transaction, or signal backpressure if the queue is saturated
     pub async fn submit_transaction(&self, transaction: Transaction) -> ServiceResult<TransactionHash> {
-        // Simple implementation - just return the hash
-        Ok(transaction.hash)
+        let mut pending = self.pending.lock().unwrap();
+        match self.sizer.lock().unwrap().admit(pending.len()) {
+            Admission::Accept => {
+                let hash = transaction.hash;
+                let position = pending.partition_point(|queued| queued.priority_fee >= transaction.priority_fee);
+                pending.insert(position, transaction);
+                Ok(hash)
+            }
+            Admission::Backpressure { retry_after_ms } => {
+                Err(ServiceError::backpressure(retry_after_ms))
+            }
+        }
+    }
+
+    /// Execute up to one batch of pending transactions and record its latency
+    ///
+    /// Each transaction's write set is locked before the runtime executes it
+    /// and released once execution finishes. A transaction whose locks cannot
+    /// be acquired in time gets a failed receipt carrying the lock timeout.
+    pub async fn execute_next_batch(&self, slot: SlotNumber) -> ServiceResult<Vec<TransactionReceipt>> {
+        let batch: Vec<Transaction> = {
+            let batch_size = self.sizer.lock().unwrap().batch_size();
+            let mut pending = self.pending.lock().unwrap();
+            let queue_depth = pending.len();
+            let take = batch_size.min(queue_depth);
+            let batch: Vec<Transaction> = pending.drain(..take).collect();
+
+            self.fee_market.lock().unwrap().record_slot(SlotFeeStats {
+                slot,
+                queue_depth,
+                included_fees: batch.iter().map(|transaction| transaction.priority_fee).collect(),
+                left_pending: pending.len(),
+            });
+            batch
+        };
+
+        let started = Instant::now();
+        let access = self.access_sets(&batch)?;
+        let scheduler = self.scheduler.lock().unwrap().clone();
+        let receipts: Vec<TransactionReceipt> = scheduler
+            .execute(batch, &access, |transaction| self.execute_locked(transaction, slot))
+            .into_iter()
+            .collect::<ServiceResult<_>>()?;
+        self.sizer.lock().unwrap().record_slot(receipts.len(), started.elapsed());
+
+        Ok(receipts)
+    }
+
+    /// Objects each transaction of `batch` may read or write
+    ///
+    /// Besides what the transaction names, this covers the objects its
+    /// functions prefetch per the module registry. Prefetched objects count
+    /// as written, since modules commonly update them. The deposit ledger is
+    /// left out: only transactions whose deposits change write it, and they
+    /// lock it once they have run (see `execute_atomically`).
+    fn access_sets(&self, batch: &[Transaction]) -> ServiceResult<Vec<AccessSet>> {
+        use units_core_types::UnitsStorage;
+        let registry = match self.storage.objects().get(&MODULE_REGISTRY_ID)? {
+            Some(object) => Some(ModuleRegistry::from_object(&object)?),
+            None => None,
+        };
+        let checker = BasicConflictChecker::new();
+        Ok(batch
+            .iter()
+            .map(|transaction| {
+                let mut access = checker.access_set(transaction);
+                for instruction in &transaction.instructions {
+                    for id in registry.iter().flat_map(|registry| registry.prefetch_ids(instruction)) {
+                        access.insert(id, AccessIntent::Write);
+                    }
+                }
+                access
+            })
+            .collect())
+    }
+
+    /// Execute one transaction while holding locks on its write set
+    ///
+    /// The transaction runs atomically and its writes are committed before
+    /// the locks are released. Sampled transactions are first replayed on
+    /// the shadow runtime against the same state, and the two receipts are
+    /// compared once the locks are released; lock failures never reach
+    /// either runtime.
+    fn execute_locked(&self, transaction: Transaction, slot: SlotNumber) -> ServiceResult<TransactionReceipt> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let shadow = self.shadow.lock().unwrap().clone().filter(|shadow| shadow.samples(&transaction));
+        let mut replayed = None;
+        let receipt = execute_with_write_locks(&self.storage, &transaction, slot, timestamp, |_locks| {
+            #[cfg(feature = "sqlite")]
+            let lock_table = self.record_locks(&transaction.hash, _locks)?;
+            replayed = shadow.as_ref().map(|shadow| {
+                use units_core_types::UnitsStorage;
+                shadow.replay(&transaction, self.storage.objects(), slot, timestamp)
+            });
+            let executed = execute_atomically(self.runtime.as_ref(), &self.storage, &transaction, slot, timestamp);
+            #[cfg(feature = "sqlite")]
+            if let Some(lock_table) = lock_table {
+                lock_table.release_transaction_locks(&transaction.hash)?;
+            }
+            executed
+        })?;
+
+        match (shadow, replayed) {
+            (Some(shadow), Some(Ok(replayed))) => {
+                shadow.check(&replayed, &receipt);
+            }
+            (_, Some(Err(error))) => {
+                log::warn!("Shadow replay of {} failed: {}", hex::encode(transaction.hash), error);
+            }
+            _ => {}
+        }
+        Ok(receipt)
+    }
+
+    /// Record the write locks `guards` hold for transaction `hash` in the
+    /// lock table, returning the table to release them from afterwards
+    #[cfg(feature = "sqlite")]
+    fn record_locks(
+        &self,
+        hash: &TransactionHash,
+        guards: &[units_storage_impl::SimpleLockGuard<'_>],
+    ) -> Result<Option<Arc<SqliteLockManager>>, units_core_types::StorageError> {
+        let Some(lock_table) = self.lock_table.lock().unwrap().clone() else {
+            return Ok(None);
+        };
+        let recorded = guards
+            .iter()
+            .try_for_each(|guard| lock_table.acquire_lock(guard.object_id(), LockType::Write, hash, None).map(drop));
+        if let Err(error) = recorded {
+            lock_table.release_transaction_locks(hash)?;
+            return Err(error);
+        }
+        Ok(Some(lock_table))
+    }
+
+    /// Number of transactions waiting for execution
+    pub fn pending_count(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    /// How full the pending queue is relative to its admission capacity
+    pub fn load(&self) -> NodeLoad {
+        let queued = self.pending.lock().unwrap().len();
+        NodeLoad::from_queue(queued, self.sizer.lock().unwrap().queue_capacity())
+    }
+
+    /// Current number of transactions admitted per slot
+    pub fn batch_size(&self) -> usize {
+        self.sizer.lock().unwrap().batch_size()
+    }
+
+    /// Suggest a priority fee for inclusion within `target_slots` slots
+    pub fn fee_estimate(&self, target_slots: u64) -> FeeEstimate {
+        let pending_fees: Vec<u64> = self.pending
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|transaction| transaction.priority_fee)
+            .collect();
+        let batch_size = self.batch_size();
+        self.fee_market.lock().unwrap().estimate(&pending_fees, batch_size, target_slots)
     }
 
     pub async fn get_transaction(&self, _hash: &TransactionHash) -> ServiceResult<Transaction> {
@@ -68,6 +334,8 @@ impl MinimalObjectService {
                 units_core_types::objects::UnitsObject::new_data(id, controller_id, data),
             units_core_types::objects::ObjectType::Executable(vm_type) => 
                 units_core_types::objects::UnitsObject::new_executable(id, controller_id, vm_type, data),
+            units_core_types::objects::ObjectType::Ephemeral { expires_at_slot } => 
+                units_core_types::objects::UnitsObject::new_ephemeral(id, controller_id, data, expires_at_slot),
         };
         
         // Store in storage
@@ -82,12 +350,22 @@ impl MinimalObjectService {
 /// Minimal slot service
 pub struct MinimalSlotService {
     current_slot: std::sync::atomic::AtomicU64,
+    /// Latest slot whose state proof has been committed
+    confirmed_slot: std::sync::atomic::AtomicU64,
+    finality_depth: u64,
 }
 
 impl MinimalSlotService {
     pub fn new() -> Self {
+        Self::with_finality_depth(DEFAULT_FINALITY_DEPTH)
+    }
+
+    /// Finalize slots once `finality_depth` later slots are confirmed
+    pub fn with_finality_depth(finality_depth: u64) -> Self {
         Self {
             current_slot: std::sync::atomic::AtomicU64::new(0),
+            confirmed_slot: std::sync::atomic::AtomicU64::new(0),
+            finality_depth,
         }
     }
 
@@ -99,6 +377,66 @@ impl MinimalSlotService {
         let new_slot = self.current_slot.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
         Ok(new_slot)
     }
+
+    /// Record that `slot`'s state proof has been committed
+    pub fn confirm_slot(&self, slot: SlotNumber) {
+        self.confirmed_slot.fetch_max(slot, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn status(&self) -> SlotStatus {
+        let confirmed_slot = self.confirmed_slot.load(std::sync::atomic::Ordering::Relaxed);
+        SlotStatus {
+            processed_slot: self.current_slot(),
+            confirmed_slot,
+            finalized_slot: confirmed_slot.saturating_sub(self.finality_depth),
+        }
+    }
+
+    /// Finality of the state as of `slot`
+    pub fn finality(&self, slot: SlotNumber) -> Finality {
+        let status = self.status();
+        if slot <= status.finalized_slot {
+            Finality::Finalized
+        } else if slot <= status.confirmed_slot {
+            Finality::Confirmed
+        } else {
+            Finality::Processed
+        }
+    }
+
+    /// Finality of state read from the store while `slot` was current
+    ///
+    /// Such reads can include writes bound for the next slot's state proof,
+    /// so they only settle as that slot does.
+    pub fn read_finality(&self, slot: SlotNumber) -> Finality {
+        self.finality(slot.saturating_add(1))
+    }
+
+    /// Write the usage summary of `slot`, ahead of its state proof
+    pub fn record_summary(
+        &self,
+        storage: &ConsolidatedUnitsStorage,
+        slot: SlotNumber,
+        receipts: &[TransactionReceipt],
+    ) -> ServiceResult<SlotSummary> {
+        use units_core_types::UnitsStorage;
+        let summary = SlotSummary::from_receipts(slot, receipts);
+        storage.objects().set(&summary.to_object(), None)?;
+        Ok(summary)
+    }
+
+    /// Usage summary recorded for `slot`, if it has closed
+    pub fn get_summary(
+        &self,
+        storage: &ConsolidatedUnitsStorage,
+        slot: SlotNumber,
+    ) -> ServiceResult<Option<SlotSummary>> {
+        use units_core_types::UnitsStorage;
+        Ok(storage
+            .objects()
+            .get(&SlotSummary::object_id(slot))?
+            .and_then(|object| SlotSummary::from_object(&object)))
+    }
 }
 
 /// Minimal service container
@@ -128,6 +466,22 @@ impl MinimalServiceContainer {
         }
     }
 
+    /// Size transaction batches with `batch_config`, before the transaction service is shared
+    pub fn with_batch_config(mut self, batch_config: AdaptiveBatchConfig) -> Self {
+        self.transaction_service = Arc::new(MinimalTransactionService::with_batch_config(
+            self.runtime.clone(),
+            self.storage.clone(),
+            batch_config,
+        ));
+        self
+    }
+
+    /// Track finality with `finality_depth`, before the slot service is shared
+    pub fn with_finality_depth(mut self, finality_depth: u64) -> Self {
+        self.slot_service = Arc::new(MinimalSlotService::with_finality_depth(finality_depth));
+        self
+    }
+
     pub async fn health_check(&self) -> ServiceResult<MinimalHealthReport> {
         Ok(MinimalHealthReport {
             status: "healthy".to_string(),
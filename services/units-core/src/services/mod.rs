@@ -26,4 +26,61 @@ pub mod simple_build_test;
 
 // Minimal working services
 pub mod minimal_services;
-pub use minimal_services::{MinimalServiceFactory, MinimalServiceContainer};
\ No newline at end of file
+pub use minimal_services::{MinimalServiceFactory, MinimalServiceContainer, Finality, SlotStatus};
+
+// Snapshot read replica for RPC reads
+pub mod read_replica;
+pub use read_replica::{ReadReplica, ReadMetadata, ReadSource};
+// Copy-on-write simulation sandboxes
+pub mod sandbox;
+pub use sandbox::{SandboxManager, SandboxInfo, SandboxChange};
+// Admin role checks and confirmation tokens
+pub mod admin;
+pub use admin::{AdminConsole, AdminOperation, AdminAuth, AdminReport, LegalHoldAudit};
+// Typed token balance and holder queries
+pub mod token_query;
+pub use token_query::{TokenQueryService, TokenBalance, TokenHolders};
+// Account activity timelines
+pub mod activity;
+pub use activity::{ActivityFeed, ActivityPage};
+// Transaction dependency graphs for explorers
+pub mod dependency_graph;
+pub use dependency_graph::TransactionGraph;
+// Storage invariant checks run before serving traffic
+pub mod preflight;
+pub use preflight::PreflightReport;
+// Per-store retention windows
+pub mod retention;
+pub use retention::RetentionManager;
+// Background compaction, pruning and scrubbing, held back under load
+pub mod maintenance;
+pub use maintenance::{LoadMonitor, MaintenanceScheduler, MaintenanceStatus};
+// Proof-of-existence records for document hashes
+pub mod attestation;
+pub use attestation::{Attestation, AttestationService};
+// Owner-maintained object sets with provable membership
+pub mod collections;
+pub use collections::{Collection, CollectionMembers, CollectionService};
+// Per-slot resource usage records, written as slots advance
+#[allow(dead_code)]
+pub mod slot_summary;
+pub use slot_summary::SlotSummary;
+// Signed receipt notifications, sent as slots advance
+#[allow(dead_code)]
+pub mod webhooks;
+pub use webhooks::WebhookDispatcher;
+
+// Durable object and controller watches, notified as slots advance
+#[allow(dead_code)]
+pub mod watches;
+pub use watches::{Watch, WatchDelivery, WatchFilter, WatchNotification, WatchRegistry, WatchTarget};
+
+// Receipt and proof query answers, dropped as slots close
+#[allow(dead_code)]
+pub mod response_cache;
+pub use response_cache::{ResponseCache, ResponseCacheStats};
+
+// Shadow re-execution of sampled transactions, run as slots advance
+#[allow(dead_code)]
+pub mod shadow;
+pub use shadow::{ShadowExecutor, ShadowReport};
@@ -48,6 +48,9 @@ impl ObjectValidator {
             ObjectType::Data => {
                 // Basic data objects have minimal validation
             }
+            ObjectType::Ephemeral { .. } => {
+                // Scratch data; expiry is enforced by storage
+            }
         }
 
         // Validate controller - all objects have a controller
@@ -137,6 +140,7 @@ impl ObjectService {
         let object = match object_type {
             ObjectType::Data => UnitsObject::new_data(id, controller_id, data),
             ObjectType::Executable(vm_type) => UnitsObject::new_executable(id, controller_id, vm_type, data),
+            ObjectType::Ephemeral { expires_at_slot } => UnitsObject::new_ephemeral(id, controller_id, data, expires_at_slot),
         };
 
         // Validate
@@ -168,6 +172,7 @@ impl ObjectService {
         let object = match &object.object_type {
             ObjectType::Data => UnitsObject::new_data(*object.id(), updated_controller, updated_data),
             ObjectType::Executable(vm_type) => UnitsObject::new_executable(*object.id(), updated_controller, *vm_type, updated_data),
+            ObjectType::Ephemeral { expires_at_slot } => UnitsObject::new_ephemeral(*object.id(), updated_controller, updated_data, *expires_at_slot),
         };
 
         // Validate updated object
@@ -0,0 +1,206 @@
+//! Storage checks run before the node serves traffic
+//!
+//! Preflight looks for what a crash or a bad restore leaves behind: object,
+//! receipt and state proof stores that disagree on the latest slot, a
+//! damaged write-ahead log, and locks no transaction holds any more. Each
+//! finding is repaired when that is safe and enabled. Anything left
+//! unrepaired keeps the node from starting, and the report says why.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use units_core_types::{ProofStorage, ReceiptStorage, SlotNumber, UnitsStorage, WalTail, WriteAheadLog};
+use units_storage_impl::ConsolidatedUnitsStorage;
+
+use crate::error::ServiceResult;
+
+/// Object IDs quoted in a finding before the rest are only counted
+const QUOTED_IDS: usize = 3;
+
+/// Invariant a finding broke
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PreflightCheck {
+    /// Objects, receipts and state proofs agree on the slots committed
+    SlotConsistency,
+    /// Every write-ahead log entry decodes, bar a torn final one
+    WalTail,
+    /// No object is locked before anything has executed
+    LockTable,
+}
+
+/// One broken invariant and whether preflight fixed it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreflightFinding {
+    pub check: PreflightCheck,
+    pub detail: String,
+    pub repaired: bool,
+}
+
+impl fmt::Display for PreflightFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = if self.repaired { "repaired" } else { "unrepaired" };
+        write!(f, "[{:?}, {}] {}", self.check, status, self.detail)
+    }
+}
+
+/// What preflight found in storage
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PreflightReport {
+    pub latest_state_proof_slot: Option<SlotNumber>,
+    pub latest_receipt_slot: Option<SlotNumber>,
+    /// Latest slot of a transaction behind an object's current proof
+    pub latest_object_slot: Option<SlotNumber>,
+    /// Condition of the write-ahead log, absent if storage keeps none
+    pub wal: Option<WalTail>,
+    pub findings: Vec<PreflightFinding>,
+}
+
+impl PreflightReport {
+    /// Whether storage is fit to serve, every finding having been repaired
+    pub fn passed(&self) -> bool {
+        self.findings.iter().all(|finding| finding.repaired)
+    }
+
+    fn find(&mut self, check: PreflightCheck, detail: String, repaired: bool) {
+        self.findings.push(PreflightFinding { check, detail, repaired });
+    }
+}
+
+impl fmt::Display for PreflightReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let slot = |slot: Option<SlotNumber>| slot.map_or("none".to_string(), |slot| slot.to_string());
+        writeln!(
+            f,
+            "Latest slots: state proofs {}, receipts {}, objects {}",
+            slot(self.latest_state_proof_slot),
+            slot(self.latest_receipt_slot),
+            slot(self.latest_object_slot)
+        )?;
+        if let Some(wal) = &self.wal {
+            writeln!(f, "Write-ahead log: {} entries, {} torn bytes", wal.entries, wal.torn_bytes)?;
+        }
+        for finding in &self.findings {
+            writeln!(f, "{}", finding)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check `storage`, fixing what can be fixed if `repair` is set
+///
+/// Errors are reserved for storage that cannot be read at all; broken
+/// invariants are reported as findings.
+pub fn run(storage: &ConsolidatedUnitsStorage, repair: bool) -> ServiceResult<PreflightReport> {
+    let mut report = PreflightReport::default();
+    check_slots(storage, repair, &mut report)?;
+    check_wal(storage, repair, &mut report);
+    check_locks(storage, repair, &mut report);
+    Ok(report)
+}
+
+fn check_slots(storage: &ConsolidatedUnitsStorage, repair: bool, report: &mut PreflightReport) -> ServiceResult<()> {
+    let state_proofs = storage.proofs().get_state_proof_history(0, SlotNumber::MAX)?;
+    let latest = state_proofs.into_iter().max_by_key(|proof| proof.slot);
+    let mut receipt_slots = storage.receipts().iter_receipts_by_slot(0, SlotNumber::MAX).map(|slot| slot.map(|(slot, _)| slot));
+    let earliest_receipt_slot = receipt_slots.next().transpose()?;
+    report.latest_receipt_slot = receipt_slots.last().transpose()?.or(earliest_receipt_slot);
+    report.latest_state_proof_slot = latest.as_ref().map(|proof| proof.slot);
+    report.latest_object_slot = storage
+        .inner()
+        .latest_proofs()
+        .iter()
+        .filter_map(|(_, proof)| storage.written_at(proof))
+        .max();
+
+    // Proof chains are never dropped, so a committed object without one was lost
+    if let Some(latest) = &latest {
+        let lost: Vec<_> = latest
+            .object_ids
+            .iter()
+            .filter(|id| storage.inner().get_proof_chain(id).is_empty())
+            .collect();
+        if !lost.is_empty() {
+            let quoted: Vec<String> = lost.iter().take(QUOTED_IDS).map(|id| id.to_string()).collect();
+            report.find(
+                PreflightCheck::SlotConsistency,
+                format!(
+                    "State proof for slot {} commits to {} objects missing from the object store: {}",
+                    latest.slot,
+                    lost.len(),
+                    quoted.join(", ")
+                ),
+                false,
+            );
+        }
+    }
+
+    let gaps = storage.state_proof_gaps()?;
+    if let (Some(&first), Some(&last)) = (gaps.first(), gaps.last()) {
+        if repair {
+            storage.regenerate_state_proofs(first, last)?;
+        }
+        report.find(
+            PreflightCheck::SlotConsistency,
+            format!("{} state proofs missing between slots {} and {}", gaps.len(), first, last),
+            repair,
+        );
+    }
+
+    // Writes past the last state proof belong to a slot that never closed
+    let written = report.latest_receipt_slot.max(report.latest_object_slot);
+    if let Some(written) = written.filter(|&written| Some(written) > report.latest_state_proof_slot) {
+        let start = match report.latest_state_proof_slot {
+            Some(slot) => slot + 1,
+            None => earliest_receipt_slot.unwrap_or(written).min(written),
+        };
+        if repair {
+            storage.regenerate_state_proofs(start, written)?;
+        }
+        report.find(
+            PreflightCheck::SlotConsistency,
+            format!(
+                "Receipts and object writes reach slot {} but state proofs end at {}",
+                written,
+                report.latest_state_proof_slot.map_or("none".to_string(), |slot| slot.to_string())
+            ),
+            repair,
+        );
+    }
+    Ok(())
+}
+
+fn check_wal(storage: &ConsolidatedUnitsStorage, repair: bool, report: &mut PreflightReport) {
+    let Some(wal) = storage.wal() else {
+        return;
+    };
+    match wal.check_tail(repair) {
+        Ok(tail) => {
+            if tail.torn_bytes > 0 {
+                report.find(
+                    PreflightCheck::WalTail,
+                    format!("{} bytes of an unfinished entry follow the last of {} entries", tail.torn_bytes, tail.entries),
+                    tail.truncated,
+                );
+            }
+            report.wal = Some(tail);
+        }
+        Err(error) => report.find(PreflightCheck::WalTail, error.to_string(), false),
+    }
+}
+
+fn check_locks(storage: &ConsolidatedUnitsStorage, repair: bool, report: &mut PreflightReport) {
+    let held = storage.locks().held();
+    if held.is_empty() {
+        return;
+    }
+    if repair {
+        storage.locks().release_all();
+    }
+    let quoted: Vec<String> = held.iter().take(QUOTED_IDS).map(|id| id.to_string()).collect();
+    report.find(
+        PreflightCheck::LockTable,
+        format!("{} objects locked before any transaction ran: {}", held.len(), quoted.join(", ")),
+        repair,
+    );
+}
@@ -0,0 +1,187 @@
+//! Read replica serving RPC reads from a storage snapshot
+//!
+//! The replica periodically brings a snapshot of the primary object store
+//! up to date, copying only the objects written since the last refresh.
+//! Reads see one snapshot in its entirety (snapshot isolation) and report
+//! how stale it is, while writes keep going to the primary store.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tokio::time::{interval, MissedTickBehavior};
+
+use serde::{Deserialize, Serialize};
+use units_core_types::{ObjectStorage, SlotNumber, UnitsObject, UnitsObjectId, UnitsStorage};
+use units_storage_impl::ConsolidatedUnitsStorage;
+
+use crate::config::ReplicaConfig;
+use crate::error::{ServiceError, ServiceResult};
+use super::minimal_services::{Finality, MinimalSlotService};
+
+/// Where a read was served from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadSource {
+    Primary,
+    Replica,
+}
+
+/// Staleness metadata attached to read responses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadMetadata {
+    /// Store that answered the read
+    pub source: ReadSource,
+    /// Slot at which the data was captured
+    pub snapshot_slot: SlotNumber,
+    /// Slots elapsed since the data was captured
+    pub slots_behind: u64,
+    /// Age of the data in milliseconds
+    pub staleness_ms: u64,
+    /// Upper bound on staleness for replica reads
+    pub max_staleness_ms: u64,
+    /// Write version of the object as of the read, for use as an expected
+    /// version in a later transaction
+    #[serde(default)]
+    pub version: u64,
+    /// How settled the returned state is; never overstated
+    #[serde(default)]
+    pub finality: Finality,
+}
+
+/// Immutable copy of the object store at a point in time
+#[derive(Clone)]
+struct Snapshot {
+    objects: HashMap<UnitsObjectId, UnitsObject>,
+    /// Write version of every captured object
+    versions: HashMap<UnitsObjectId, u64>,
+    /// Position in the primary's write sequence the copy is current to
+    sequence: u64,
+    slot: SlotNumber,
+    taken_at: Instant,
+}
+
+impl Snapshot {
+    fn empty(slot: SlotNumber) -> Self {
+        Self {
+            objects: HashMap::new(),
+            versions: HashMap::new(),
+            sequence: 0,
+            slot,
+            taken_at: Instant::now(),
+        }
+    }
+
+    /// Bring the copy up to date with the primary, copying only the objects
+    /// written since it was last brought up to date
+    fn catch_up(&mut self, primary: &ConsolidatedUnitsStorage, slot: SlotNumber) {
+        // Each object comes with its version, read in the same view
+        let changes = primary.inner().changes_since(self.sequence);
+        for (id, current) in changes.objects {
+            match current {
+                Some((object, version)) => {
+                    self.objects.insert(id, object);
+                    self.versions.insert(id, version);
+                }
+                None => {
+                    self.objects.remove(&id);
+                    self.versions.remove(&id);
+                }
+            }
+        }
+        self.sequence = changes.sequence;
+        self.slot = slot;
+        self.taken_at = Instant::now();
+    }
+}
+
+/// Snapshot-backed read replica of the primary object store
+pub struct ReadReplica {
+    config: ReplicaConfig,
+    primary: Arc<ConsolidatedUnitsStorage>,
+    slot_service: Arc<MinimalSlotService>,
+    snapshot: RwLock<Arc<Snapshot>>,
+}
+
+impl ReadReplica {
+    pub fn new(
+        config: ReplicaConfig,
+        primary: Arc<ConsolidatedUnitsStorage>,
+        slot_service: Arc<MinimalSlotService>,
+    ) -> Self {
+        let slot = slot_service.current_slot();
+        let mut snapshot = Snapshot::empty(slot);
+        snapshot.catch_up(&primary, slot);
+
+        Self {
+            config,
+            primary,
+            slot_service,
+            snapshot: RwLock::new(Arc::new(snapshot)),
+        }
+    }
+
+    /// Bring the snapshot up to date with the primary
+    ///
+    /// Only objects written since the last refresh are copied. The snapshot
+    /// is updated in place unless a reader still holds it, in which case
+    /// that reader keeps the old snapshot and the update goes to a copy.
+    pub async fn refresh(&self) -> ServiceResult<()> {
+        let slot = self.slot_service.current_slot();
+        let mut snapshot = self.snapshot.write().await;
+        Arc::make_mut(&mut snapshot).catch_up(&self.primary, slot);
+        Ok(())
+    }
+
+    /// Start refreshing the snapshot in the background
+    pub fn start_refresh(self: &Arc<Self>) {
+        let replica = self.clone();
+
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_millis(replica.config.refresh_interval_ms));
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            loop {
+                ticker.tick().await;
+
+                if let Err(e) = replica.refresh().await {
+                    log::error!("Failed to refresh read replica: {:?}", e);
+                }
+            }
+        });
+    }
+
+    /// Read an object, preferring the snapshot while it is fresh enough
+    pub async fn get_object(
+        &self,
+        id: &UnitsObjectId,
+    ) -> ServiceResult<(Option<UnitsObject>, ReadMetadata)> {
+        let snapshot = self.snapshot.read().await.clone();
+        let staleness_ms = snapshot.taken_at.elapsed().as_millis() as u64;
+        let current_slot = self.slot_service.current_slot();
+
+        if staleness_ms > self.config.max_staleness_ms {
+            let object = self.primary.objects().get(id).map_err(ServiceError::Storage)?;
+            let version = self.primary.objects().version(id).map_err(ServiceError::Storage)?;
+            return Ok((object, ReadMetadata {
+                source: ReadSource::Primary,
+                snapshot_slot: current_slot,
+                slots_behind: 0,
+                staleness_ms: 0,
+                max_staleness_ms: self.config.max_staleness_ms,
+                version,
+                finality: self.slot_service.read_finality(current_slot),
+            }));
+        }
+
+        Ok((snapshot.objects.get(id).cloned(), ReadMetadata {
+            source: ReadSource::Replica,
+            snapshot_slot: snapshot.slot,
+            slots_behind: current_slot.saturating_sub(snapshot.slot),
+            staleness_ms,
+            max_staleness_ms: self.config.max_staleness_ms,
+            version: snapshot.versions.get(id).copied().unwrap_or_default(),
+            finality: self.slot_service.read_finality(snapshot.slot),
+        }))
+    }
+}
@@ -0,0 +1,168 @@
+//! Cached answers to receipt and proof queries
+//!
+//! Explorers and dashboards ask for the same receipts, state roots and
+//! proof ranges over and over. Answers are cached under the method, its
+//! parameters and the last closed slot. Closing a slot drops them all: the
+//! slot adds receipts and a state proof, and retention may prune old ones,
+//! so any cached range or lookup could now read differently.
+
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use units_core_types::SlotNumber;
+
+use crate::config::ResponseCacheConfig;
+use crate::error::ServiceResult;
+
+/// Hit and miss counts of a [`ResponseCache`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseCacheStats {
+    /// Queries answered from the cache
+    pub hits: u64,
+    /// Queries that went to storage
+    pub misses: u64,
+    /// Answers currently cached
+    pub entries: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    method: &'static str,
+    /// Parameters serialized as JSON
+    params: String,
+    slot: SlotNumber,
+}
+
+#[derive(Default)]
+struct Entries {
+    /// Last slot closed; answers computed before it closed are not cached
+    closed_slot: SlotNumber,
+    by_key: HashMap<CacheKey, Arc<dyn Any + Send + Sync>>,
+    /// Insertion order, for evicting the oldest answer when full
+    order: VecDeque<CacheKey>,
+}
+
+/// Query answers keyed by method, parameters and slot
+pub struct ResponseCache {
+    config: ResponseCacheConfig,
+    entries: Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ResponseCache {
+    pub fn new(config: ResponseCacheConfig) -> Self {
+        Self {
+            config,
+            entries: Mutex::new(Entries::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Answer to `method` with `params`, computed by `compute` unless cached
+    ///
+    /// Errors are returned but not cached, so a receipt that is not found
+    /// yet is looked up again next time.
+    pub async fn get_or_compute<P, T, F, Fut>(&self, method: &'static str, params: &P, compute: F) -> ServiceResult<T>
+    where
+        P: Serialize,
+        T: Clone + Send + Sync + 'static,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = ServiceResult<T>>,
+    {
+        if !self.config.enabled {
+            return compute().await;
+        }
+        let Ok(params) = serde_json::to_string(params) else {
+            return compute().await;
+        };
+
+        let key = {
+            let entries = self.entries.lock().unwrap();
+            let key = CacheKey { method, params, slot: entries.closed_slot };
+            if let Some(answer) = entries.by_key.get(&key).and_then(|answer| answer.downcast_ref::<T>()) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(answer.clone());
+            }
+            key
+        };
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let answer = compute().await?;
+        let mut entries = self.entries.lock().unwrap();
+        if self.config.max_entries > 0 && key.slot == entries.closed_slot && !entries.by_key.contains_key(&key) {
+            if entries.by_key.len() >= self.config.max_entries {
+                if let Some(oldest) = entries.order.pop_front() {
+                    entries.by_key.remove(&oldest);
+                }
+            }
+            entries.by_key.insert(key.clone(), Arc::new(answer.clone()));
+            entries.order.push_back(key);
+        }
+        Ok(answer)
+    }
+
+    /// Drop every answer as `slot` closes
+    pub fn close_slot(&self, slot: SlotNumber) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.closed_slot = slot;
+        entries.by_key.clear();
+        entries.order.clear();
+    }
+
+    /// Drop every answer, after storage changed outside of a slot closing
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.by_key.clear();
+        entries.order.clear();
+    }
+
+    pub fn stats(&self) -> ResponseCacheStats {
+        ResponseCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().unwrap().by_key.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ServiceError;
+
+    #[tokio::test]
+    async fn test_answers_are_cached_until_the_slot_closes() {
+        let cache = ResponseCache::new(ResponseCacheConfig { enabled: true, max_entries: 2 });
+        let computed = AtomicU64::new(0);
+        let query = |value: u64| {
+            let computed = &computed;
+            async move {
+                computed.fetch_add(1, Ordering::Relaxed);
+                Ok::<_, ServiceError>(value)
+            }
+        };
+
+        assert_eq!(cache.get_or_compute("a", &1u8, || query(10)).await.unwrap(), 10);
+        assert_eq!(cache.get_or_compute("a", &1u8, || query(11)).await.unwrap(), 10);
+        assert_eq!(cache.get_or_compute("a", &2u8, || query(20)).await.unwrap(), 20);
+        assert_eq!(computed.load(Ordering::Relaxed), 2);
+
+        // Errors are not cached
+        let failed = cache
+            .get_or_compute("b", &1u8, || async { Err::<u64, _>(ServiceError::invalid_request("missing")) })
+            .await;
+        assert!(failed.is_err());
+        assert_eq!(cache.get_or_compute("b", &1u8, || query(30)).await.unwrap(), 30);
+
+        // The oldest answer made room, and closing a slot drops the rest
+        assert_eq!(cache.stats(), ResponseCacheStats { hits: 1, misses: 4, entries: 2 });
+        cache.close_slot(1);
+        assert_eq!(cache.get_or_compute("a", &2u8, || query(21)).await.unwrap(), 21);
+    }
+}
@@ -0,0 +1,105 @@
+//! Independent retention windows for object history, state proofs and receipts
+//!
+//! Each slot the manager prunes the stores whose window has moved past
+//! their oldest data. Pruning stops short of any state proof that retained
+//! data still depends on: the newest state proof, which the next one chains
+//! to, and the proofs committing receipts that are kept.
+
+use std::sync::Arc;
+
+use units_core_types::{HistoricalStorage, ProofStorage, ReceiptStorage, SlotNumber, UnitsStorage};
+use units_storage_impl::ConsolidatedUnitsStorage;
+
+use crate::config::RetentionConfig;
+use crate::error::{ServiceError, ServiceResult};
+
+/// What one retention pass removed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionReport {
+    pub object_versions: usize,
+    pub state_proofs: usize,
+    pub receipts: usize,
+}
+
+/// Applies the configured retention windows to storage
+pub struct RetentionManager {
+    config: RetentionConfig,
+    storage: Arc<ConsolidatedUnitsStorage>,
+}
+
+impl RetentionManager {
+    pub fn new(config: RetentionConfig, storage: Arc<ConsolidatedUnitsStorage>) -> Self {
+        Self { config, storage }
+    }
+
+    /// Prune everything older than its window as of `current_slot`
+    #[allow(dead_code)]
+    pub fn enforce(&self, current_slot: SlotNumber) -> ServiceResult<RetentionReport> {
+        let mut report = self.prune(current_slot)?;
+        report.object_versions = self.compact(current_slot)?;
+        Ok(report)
+    }
+
+    /// Drop object versions older than the history window, returning how many
+    pub fn compact(&self, current_slot: SlotNumber) -> ServiceResult<usize> {
+        match Self::cutoff(current_slot, self.config.object_history_slots) {
+            Some(before_slot) => Ok(self.storage.historical().compact_history(before_slot)?),
+            None => Ok(0),
+        }
+    }
+
+    /// Drop receipts and state proofs older than their windows
+    pub fn prune(&self, current_slot: SlotNumber) -> ServiceResult<RetentionReport> {
+        let mut report = RetentionReport::default();
+
+        // Receipts go first so the state proof floor reflects what is kept
+        if let Some(before_slot) = Self::cutoff(current_slot, self.config.receipt_slots) {
+            report.receipts = self.storage.receipts().cleanup_receipts_before(before_slot)?;
+        }
+        if let Some(before_slot) = Self::cutoff(current_slot, self.config.state_proof_slots) {
+            let before_slot = before_slot.min(self.state_proof_floor()?.unwrap_or(SlotNumber::MAX));
+            report.state_proofs = self.storage.proofs().prune_state_proofs(before_slot);
+        }
+
+        Ok(report)
+    }
+
+    /// First slot a window of `window` slots keeps as of `current_slot`
+    fn cutoff(current_slot: SlotNumber, window: Option<u64>) -> Option<SlotNumber> {
+        window.map(|slots| current_slot.saturating_sub(slots - 1))
+    }
+
+    /// Refuse to prune state proofs before `before_slot` if that breaks verification
+    pub fn check_prune(&self, before_slot: SlotNumber) -> ServiceResult<()> {
+        match self.state_proof_floor()? {
+            Some(floor) if before_slot > floor => Err(ServiceError::invalid_request(format!(
+                "Pruning state proofs before slot {} would break verification; the oldest needed is at slot {}",
+                before_slot, floor
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Oldest slot whose state proof must be kept, if any
+    fn state_proof_floor(&self) -> ServiceResult<Option<SlotNumber>> {
+        let newest_proof = self
+            .storage
+            .proofs()
+            .get_state_proof_history(0, SlotNumber::MAX)?
+            .into_iter()
+            .map(|proof| proof.slot)
+            .max();
+        let oldest_receipt = self
+            .storage
+            .receipts()
+            .iter_receipts_by_slot(0, SlotNumber::MAX)
+            .next()
+            .transpose()?
+            .map(|(slot, _)| slot);
+
+        Ok(match (newest_proof, oldest_receipt) {
+            (Some(newest), Some(oldest)) => Some(newest.min(oldest)),
+            (newest, oldest) => newest.or(oldest),
+        })
+    }
+}
@@ -0,0 +1,330 @@
+//! Simulation sandboxes for staging changes against current state
+//!
+//! A sandbox is an ephemeral namespace forked copy-on-write from the primary
+//! store. Transactions run in it with the real runtime (VM execution, effect
+//! validation, deposit accounting) but their effects only land in the
+//! sandbox, so clients can inspect the outcome and then discard it.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+use units_core_types::{
+    BatchOp, ObjectStorage, Runtime, SlotNumber, Transaction, TransactionReceipt, TransactionView,
+    UnitsObject, UnitsObjectId, VersionedObject,
+};
+use units_storage_impl::{ConsolidatedUnitsStorage, OverlayObjectStorage};
+
+use crate::config::SandboxConfig;
+use crate::context::RequestContext;
+use crate::error::{ServiceError, ServiceResult};
+use super::minimal_services::MinimalSlotService;
+
+/// Summary of a sandbox namespace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxInfo {
+    pub namespace: String,
+    /// Slot of the primary store when the sandbox was forked
+    pub forked_at_slot: SlotNumber,
+    /// Transactions executed in the sandbox so far
+    pub transaction_count: usize,
+}
+
+/// Object staged in a sandbox; `object` is `None` for deletions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxChange {
+    pub object_id: UnitsObjectId,
+    pub object: Option<UnitsObject>,
+}
+
+/// One ephemeral fork of the primary store
+struct Sandbox {
+    objects: OverlayObjectStorage<ConsolidatedUnitsStorage>,
+    forked_at_slot: SlotNumber,
+    receipts: Mutex<Vec<TransactionReceipt>>,
+}
+
+/// Creates, runs and discards sandbox namespaces
+pub struct SandboxManager {
+    config: SandboxConfig,
+    storage: Arc<ConsolidatedUnitsStorage>,
+    runtime: Arc<dyn Runtime + Send + Sync>,
+    slot_service: Arc<MinimalSlotService>,
+    sandboxes: RwLock<HashMap<String, Arc<Sandbox>>>,
+    next_id: AtomicU64,
+}
+
+impl SandboxManager {
+    pub fn new(
+        config: SandboxConfig,
+        storage: Arc<ConsolidatedUnitsStorage>,
+        runtime: Arc<dyn Runtime + Send + Sync>,
+        slot_service: Arc<MinimalSlotService>,
+    ) -> Self {
+        Self {
+            config,
+            storage,
+            runtime,
+            slot_service,
+            sandboxes: RwLock::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Fork the primary store into a new sandbox namespace
+    pub async fn create(&self) -> ServiceResult<SandboxInfo> {
+        let mut sandboxes = self.sandboxes.write().await;
+        if sandboxes.len() >= self.config.max_namespaces {
+            return Err(ServiceError::service_unavailable(format!(
+                "Sandbox limit of {} namespaces reached", self.config.max_namespaces
+            )));
+        }
+
+        let namespace = format!("sandbox-{}", self.next_id.fetch_add(1, Ordering::Relaxed));
+        let sandbox = Arc::new(Sandbox {
+            objects: OverlayObjectStorage::new(self.storage.clone()),
+            forked_at_slot: self.slot_service.current_slot(),
+            receipts: Mutex::new(Vec::new()),
+        });
+        let info = Self::info(&namespace, &sandbox);
+        sandboxes.insert(namespace, sandbox);
+
+        Ok(info)
+    }
+
+    /// Execute a transaction inside a sandbox
+    ///
+    /// Execution failures are reported in the receipt and leave the sandbox
+    /// unchanged.
+    ///
+    /// Nothing runs once `ctx` has expired, and a transaction that finishes
+    /// after the deadline is not applied: its client has already given up
+    /// on the result. The runtime's own time limit bounds the run itself.
+    pub async fn execute(
+        &self,
+        ctx: &RequestContext,
+        namespace: &str,
+        transaction: Transaction,
+    ) -> ServiceResult<TransactionReceipt> {
+        ctx.check()?;
+        let sandbox = self.get(namespace).await?;
+        let slot = self.slot_service.current_slot();
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+
+        let receipt = execute_against(self.runtime.as_ref(), &sandbox.objects, &transaction, slot, timestamp, ctx)?;
+        sandbox.receipts.lock().unwrap().push(receipt.clone());
+
+        Ok(receipt)
+    }
+
+    /// Read an object and its write version as the sandbox sees them
+    pub async fn get_object(&self, namespace: &str, id: &UnitsObjectId) -> ServiceResult<VersionedObject> {
+        let sandbox = self.get(namespace).await?;
+        let object = sandbox
+            .objects
+            .get(id)?
+            .ok_or_else(|| ServiceError::object_not_found(hex::encode(id.bytes())))?;
+        let version = sandbox.objects.version(id)?;
+        Ok(VersionedObject { object, version })
+    }
+
+    /// Objects changed in the sandbox relative to the primary store
+    pub async fn changes(&self, namespace: &str) -> ServiceResult<Vec<SandboxChange>> {
+        let mut changes: Vec<SandboxChange> = self
+            .get(namespace)
+            .await?
+            .objects
+            .changes()
+            .into_iter()
+            .map(|(object_id, object)| SandboxChange { object_id, object })
+            .collect();
+        changes.sort_by_key(|change| change.object_id);
+        Ok(changes)
+    }
+
+    /// Receipts of the transactions executed in a sandbox, oldest first
+    pub async fn receipts(&self, namespace: &str) -> ServiceResult<Vec<TransactionReceipt>> {
+        Ok(self.get(namespace).await?.receipts.lock().unwrap().clone())
+    }
+
+    /// Describe every open sandbox
+    pub async fn list(&self) -> Vec<SandboxInfo> {
+        let mut infos: Vec<SandboxInfo> = self
+            .sandboxes
+            .read()
+            .await
+            .iter()
+            .map(|(namespace, sandbox)| Self::info(namespace, sandbox))
+            .collect();
+        infos.sort_by(|a, b| a.namespace.cmp(&b.namespace));
+        infos
+    }
+
+    /// Discard a sandbox and everything staged in it
+    ///
+    /// Returns whether the namespace existed.
+    pub async fn discard(&self, namespace: &str) -> bool {
+        self.sandboxes.write().await.remove(namespace).is_some()
+    }
+
+    async fn get(&self, namespace: &str) -> ServiceResult<Arc<Sandbox>> {
+        self.sandboxes
+            .read()
+            .await
+            .get(namespace)
+            .cloned()
+            .ok_or_else(|| ServiceError::invalid_request(format!("Unknown sandbox namespace: {}", namespace)))
+    }
+
+    fn info(namespace: &str, sandbox: &Sandbox) -> SandboxInfo {
+        SandboxInfo {
+            namespace: namespace.to_string(),
+            forked_at_slot: sandbox.forked_at_slot,
+            transaction_count: sandbox.receipts.lock().unwrap().len(),
+        }
+    }
+}
+
+/// Execute a transaction's instructions against `storage` through the runtime
+///
+/// Instructions run atomically over a shared view (see
+/// `Runtime::execute_transaction_atomic`). Effects are written to `storage`
+/// only if every instruction succeeds; otherwise the receipt carries the
+/// first error and nothing is written. A commit that fails part way is
+/// undone (see `commit_writes`).
+pub(crate) fn execute_against<S: ObjectStorage>(
+    runtime: &dyn Runtime,
+    storage: &S,
+    transaction: &Transaction,
+    slot: SlotNumber,
+    timestamp: u64,
+    ctx: &RequestContext,
+) -> ServiceResult<TransactionReceipt> {
+    let load = |id: &UnitsObjectId| storage.get(id);
+    let versions = |id: &UnitsObjectId| storage.version(id);
+    let mut view = TransactionView::new(&load).with_versions(&versions);
+    let mut receipt = runtime.execute_transaction_atomic(transaction, &mut view, slot, timestamp)?;
+    receipt.record_reads(transaction, |id| view.get(id))?;
+    // Drop the writes of a run that outlived its request
+    ctx.check()?;
+
+    let ops: Vec<BatchOp> = view
+        .into_writes()
+        .into_iter()
+        .map(|(id, object)| object.map_or(BatchOp::Delete(id), BatchOp::Set))
+        .collect();
+    for (id, proof) in super::transaction_service::commit_writes(storage, &ops, transaction.hash)? {
+        receipt.add_proof(id, proof);
+    }
+
+    Ok(receipt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use units_core_types::error::RuntimeError;
+    use units_core_types::{ExecutionOutput, Instruction, ObjectEffect, ResourceClass, VMExecutionError, VMExecutor, VMType, Verifier};
+    use units_runtime_impl::MockRuntime;
+    use units_storage_impl::{ChaosConfig, ChaosStorage, InMemoryObjectStorage};
+
+    /// Appends a byte to every target object
+    struct AppendRuntime(MockRuntime);
+
+    impl Runtime for AppendRuntime {
+        fn get_vm_executor(&self, vm_type: VMType) -> Option<Box<dyn VMExecutor>> {
+            self.0.get_vm_executor(vm_type)
+        }
+
+        fn execute_transaction(&self, transaction: Transaction) -> TransactionReceipt {
+            self.0.execute_transaction(transaction)
+        }
+
+        fn execute_instruction_in_class(
+            &self,
+            instruction: &Instruction,
+            objects: HashMap<UnitsObjectId, UnitsObject>,
+            _class: ResourceClass,
+            _slot: u64,
+            _timestamp: u64,
+        ) -> Result<ExecutionOutput, VMExecutionError> {
+            let effects = instruction
+                .target_objects
+                .iter()
+                .filter_map(|id| objects.get(id))
+                .map(|before| {
+                    let mut after = before.clone();
+                    after.data.push(1);
+                    ObjectEffect::modification(before.clone(), after)
+                })
+                .collect();
+            Ok(ExecutionOutput { effects, ..Default::default() })
+        }
+
+        fn get_transaction(&self, hash: &[u8; 32]) -> Option<Transaction> {
+            self.0.get_transaction(hash)
+        }
+
+        fn get_transaction_receipt(&self, hash: &[u8; 32]) -> Option<TransactionReceipt> {
+            self.0.get_transaction_receipt(hash)
+        }
+
+        fn rollback_transaction(&self, hash: &[u8; 32]) -> Result<bool, RuntimeError> {
+            self.0.rollback_transaction(hash)
+        }
+
+        fn get_verifier(&self) -> &dyn Verifier {
+            self.0.get_verifier()
+        }
+    }
+
+    #[test]
+    fn test_commits_stay_consistent_under_injected_faults() {
+        let config = ChaosConfig::new(42).with_error_rate(0.1).with_partial_write_rate(0.3);
+        let storage = ChaosStorage::new(InMemoryObjectStorage::new(), config);
+        let runtime = AppendRuntime(MockRuntime::new());
+        let controller = UnitsObjectId::new([1; 32]);
+        let targets: Vec<_> = (2..5).map(|byte| UnitsObjectId::new([byte; 32])).collect();
+
+        storage.set_enabled(false);
+        storage.set(&UnitsObject::new_data(controller, controller, vec![]), None).unwrap();
+        for id in &targets {
+            storage.set(&UnitsObject::new_data(*id, controller, vec![0]), None).unwrap();
+        }
+        storage.set_enabled(true);
+
+        let mut committed = Vec::new();
+        for hash in 0..64u8 {
+            let instruction = Instruction::new(controller, "append".to_string(), targets.clone(), vec![]);
+            let transaction = Transaction::new(vec![instruction], [hash; 32]);
+            if let Ok(receipt) = execute_against(&runtime, &storage, &transaction, 1, 0, &RequestContext::new()) {
+                if receipt.success {
+                    committed.push(receipt);
+                }
+            }
+        }
+        storage.set_enabled(false);
+        let stats = storage.stats();
+        assert!(stats.injected_errors > 0 && stats.partial_writes > 0, "{:?}", stats);
+        assert!(!committed.is_empty());
+
+        for id in &targets {
+            // Every committed transaction, and only those, changed the object
+            let object = storage.get(id).unwrap().unwrap();
+            assert_eq!(object.data.len(), 1 + committed.len());
+
+            // Its proof chain links up and holds every receipt's proof
+            let chain = storage.inner().get_proof_chain(id);
+            assert!(chain.windows(2).all(|pair| pair[1].prev_proof_hash == Some(pair[0].hash())));
+            for receipt in &committed {
+                assert!(chain.iter().any(|proof| proof.hash() == receipt.object_proofs[id].hash()));
+            }
+        }
+    }
+}
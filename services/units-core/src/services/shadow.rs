@@ -0,0 +1,190 @@
+//! Shadow re-execution of committed transactions
+//!
+//! A sampled fraction of transactions is executed a second time on a
+//! separate runtime and the two receipts are compared. Anything that should
+//! be a pure function of the transaction must match byte for byte: the
+//! outcome, the error, every effect and event, the VM metrics and the
+//! annotations of effect processors. Timestamps and proofs are left out,
+//! since they depend on when and where a receipt was committed. A
+//! divergence is logged as an error and kept for inspection.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use units_core_types::{
+    ObjectStorage, Runtime, SlotNumber, StorageError, Transaction, TransactionHash, TransactionReceipt,
+    TransactionView, UnitsObjectId,
+};
+
+use crate::config::ShadowConfig;
+
+/// A transaction whose shadow receipt differed from the committed one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Divergence {
+    pub transaction_hash: TransactionHash,
+    pub slot: SlotNumber,
+    /// First part of the receipt that differed
+    pub field: String,
+    pub detail: String,
+}
+
+/// Counts of shadow checks and the most recent divergences
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShadowReport {
+    pub enabled: bool,
+    pub sample_rate: f64,
+    pub checked: u64,
+    pub diverged: u64,
+    /// Most recent divergences, oldest first
+    pub recent: Vec<Divergence>,
+}
+
+/// Re-executes sampled transactions on a shadow runtime
+pub struct ShadowExecutor {
+    runtime: Arc<dyn Runtime + Send + Sync>,
+    config: ShadowConfig,
+    checked: Mutex<u64>,
+    divergences: Mutex<(u64, VecDeque<Divergence>)>,
+}
+
+impl ShadowExecutor {
+    pub fn new(runtime: Arc<dyn Runtime + Send + Sync>, config: ShadowConfig) -> Self {
+        Self {
+            runtime,
+            config,
+            checked: Mutex::new(0),
+            divergences: Mutex::new((0, VecDeque::new())),
+        }
+    }
+
+    /// Whether `transaction` falls in the sample
+    ///
+    /// Sampling is keyed on the transaction hash, so every node running the
+    /// same rate checks the same transactions.
+    pub fn samples(&self, transaction: &Transaction) -> bool {
+        let key = u64::from_le_bytes(transaction.hash[..8].try_into().unwrap());
+        (key as f64) < self.config.sample_rate * u64::MAX as f64
+    }
+
+    /// Execute `transaction` on the shadow runtime against `storage`
+    ///
+    /// Its writes are discarded, so this must run before the committed
+    /// execution's writes reach `storage`, while both see the same state.
+    pub fn replay<S: ObjectStorage>(
+        &self,
+        transaction: &Transaction,
+        storage: &S,
+        slot: SlotNumber,
+        timestamp: u64,
+    ) -> Result<TransactionReceipt, StorageError> {
+        let load = |id: &UnitsObjectId| storage.get(id);
+        let versions = |id: &UnitsObjectId| storage.version(id);
+        let mut view = TransactionView::new(&load).with_versions(&versions);
+        self.runtime.execute_transaction_atomic(transaction, &mut view, slot, timestamp)
+    }
+
+    /// Compare a shadow receipt from [`Self::replay`] against the committed one
+    pub fn check(&self, shadow: &TransactionReceipt, committed: &TransactionReceipt) -> Option<Divergence> {
+        *self.checked.lock().unwrap() += 1;
+
+        let (field, detail) = first_difference(committed, shadow)?;
+        let divergence = Divergence {
+            transaction_hash: committed.transaction_hash,
+            slot: committed.slot,
+            field: field.to_string(),
+            detail,
+        };
+        log::error!(
+            "Shadow execution of {} diverged at slot {} in {}: {}",
+            hex::encode(divergence.transaction_hash),
+            divergence.slot,
+            divergence.field,
+            divergence.detail
+        );
+
+        let mut divergences = self.divergences.lock().unwrap();
+        divergences.0 += 1;
+        divergences.1.push_back(divergence.clone());
+        while divergences.1.len() > self.config.max_divergences {
+            divergences.1.pop_front();
+        }
+        Some(divergence)
+    }
+
+    pub fn report(&self) -> ShadowReport {
+        let divergences = self.divergences.lock().unwrap();
+        ShadowReport {
+            enabled: true,
+            sample_rate: self.config.sample_rate,
+            checked: *self.checked.lock().unwrap(),
+            diverged: divergences.0,
+            recent: divergences.1.iter().cloned().collect(),
+        }
+    }
+}
+
+/// First deterministic part of two receipts that differs, with a description
+fn first_difference(committed: &TransactionReceipt, shadow: &TransactionReceipt) -> Option<(&'static str, String)> {
+    if committed.success != shadow.success {
+        return Some(("success", format!("committed {}, shadow {}", committed.success, shadow.success)));
+    }
+    if committed.error_message != shadow.error_message {
+        return Some((
+            "error_message",
+            format!("committed {:?}, shadow {:?}", committed.error_message, shadow.error_message),
+        ));
+    }
+    if committed.effects.len() != shadow.effects.len() {
+        return Some((
+            "effects",
+            format!("committed {} effects, shadow {}", committed.effects.len(), shadow.effects.len()),
+        ));
+    }
+    for (index, (a, b)) in committed.effects.iter().zip(&shadow.effects).enumerate() {
+        if bincode::serialize(a).ok() != bincode::serialize(b).ok() {
+            return Some(("effects", format!("effect {} on {} differs", index, a.object_id)));
+        }
+    }
+    if committed.events != shadow.events {
+        return Some(("events", format!("committed {} events, shadow {}", committed.events.len(), shadow.events.len())));
+    }
+    if committed.instruction_metrics != shadow.instruction_metrics {
+        return Some((
+            "instruction_metrics",
+            format!("committed {:?}, shadow {:?}", committed.instruction_metrics, shadow.instruction_metrics),
+        ));
+    }
+    if committed.annotations != shadow.annotations {
+        return Some((
+            "annotations",
+            format!("committed {:?}, shadow {:?}", committed.annotations, shadow.annotations),
+        ));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receipt(success: bool) -> TransactionReceipt {
+        TransactionReceipt::new([1; 32], 7, success, 100)
+    }
+
+    #[test]
+    fn test_differences_ignore_commit_time() {
+        let mut later = receipt(true);
+        later.timestamp = 200;
+        assert_eq!(first_difference(&receipt(true), &later), None);
+
+        let (field, _) = first_difference(&receipt(true), &receipt(false)).unwrap();
+        assert_eq!(field, "success");
+
+        let mut failed = receipt(true);
+        failed.error_message = Some("out of gas".to_string());
+        let (field, detail) = first_difference(&receipt(true), &failed).unwrap();
+        assert_eq!(field, "error_message");
+        assert!(detail.contains("out of gas"));
+    }
+}
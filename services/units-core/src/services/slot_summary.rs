@@ -0,0 +1,89 @@
+//! Per-slot resource usage records
+//!
+//! As each slot closes, the slot service writes a `SlotSummary` object of
+//! the system loader at an ID derived from the slot number. The slot's state
+//! proof covers it like any other write, so usage analytics can be checked
+//! against a state root instead of trusting the node's API.
+
+use serde::{Deserialize, Serialize};
+use units_core_types::{
+    SlotNumber, TransactionReceipt, UnitsObject, UnitsObjectId, SYSTEM_LOADER_ID,
+};
+
+/// Resource usage of the transactions executed in one slot
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlotSummary {
+    pub slot: SlotNumber,
+    pub transaction_count: u64,
+    pub failed_count: u64,
+    /// VM instructions retired across all transactions
+    pub gas_used: u64,
+    /// Object data bytes left behind by successful transactions
+    pub bytes_written: u64,
+}
+
+impl SlotSummary {
+    /// ID of the summary object for `slot`
+    pub fn object_id(slot: SlotNumber) -> UnitsObjectId {
+        UnitsObjectId::new(UnitsObjectId::create_object_id(
+            &[b"slot_summary", &slot.to_le_bytes()],
+            0,
+        ))
+    }
+
+    pub fn from_receipts(slot: SlotNumber, receipts: &[TransactionReceipt]) -> Self {
+        let mut summary = Self { slot, ..Self::default() };
+        for receipt in receipts {
+            summary.transaction_count += 1;
+            summary.gas_used = summary
+                .gas_used
+                .saturating_add(receipt.total_metrics().instructions_executed);
+            if !receipt.success {
+                summary.failed_count += 1;
+                continue;
+            }
+            summary.bytes_written += receipt
+                .effects
+                .iter()
+                .filter_map(|effect| effect.after_image.as_ref())
+                .map(|object| object.data().len() as u64)
+                .sum::<u64>();
+        }
+        summary
+    }
+
+    pub fn to_object(self) -> UnitsObject {
+        // Serializing plain integers cannot fail
+        let data = bincode::serialize(&self).expect("slot summary encodes");
+        UnitsObject::new_data(Self::object_id(self.slot), SYSTEM_LOADER_ID, data)
+    }
+
+    /// Decode a summary object, failing if `object` is not one
+    pub fn from_object(object: &UnitsObject) -> Option<Self> {
+        if *object.controller_id() != SYSTEM_LOADER_ID {
+            return None;
+        }
+        let summary: Self = bincode::deserialize(object.data()).ok()?;
+        (*object.id() == Self::object_id(summary.slot)).then_some(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_roundtrips_through_its_object() {
+        let mut failed = TransactionReceipt::new([2; 32], 9, false, 0);
+        failed.error_message = Some("out of gas".to_string());
+        let receipts = vec![TransactionReceipt::new([1; 32], 9, true, 0), failed];
+
+        let summary = SlotSummary::from_receipts(9, &receipts);
+        assert_eq!((summary.transaction_count, summary.failed_count), (2, 1));
+
+        let object = summary.to_object();
+        assert_eq!(*object.id(), SlotSummary::object_id(9));
+        assert_eq!(SlotSummary::from_object(&object), Some(summary));
+        assert_ne!(SlotSummary::object_id(9), SlotSummary::object_id(10));
+    }
+}
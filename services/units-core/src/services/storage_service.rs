@@ -209,6 +209,9 @@ impl ObjectManager {
             units_core_types::objects::ObjectType::Data => {
                 // Data object validation
             }
+            units_core_types::objects::ObjectType::Ephemeral { .. } => {
+                // Scratch data validation
+            }
         }
 
         Ok(())
@@ -0,0 +1,179 @@
+//! Token balances and holdings read from token module state
+//!
+//! Balances live in objects controlled by the token controller, laid out as
+//! the token module's `BalanceData`. Queries find them by scanning the
+//! controller's objects, so wallets can ask for an owner's balance without
+//! knowing which object holds it. Scans are bounded per request; a result
+//! built from a partial scan carries the cursor resuming it. Each scan is
+//! reported to the storage's index advisor, and walks only the controller's
+//! objects once it has an index.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use token::{BalanceData, TokenData};
+use units_core_types::{ObjectStorage, StorageError, UnitsObject, UnitsObjectId, UnitsStorage};
+use units_kernel_sdk::decode_versioned;
+use units_storage_impl::{ConsolidatedUnitsStorage, QueryPattern};
+
+use crate::context::RequestContext;
+use crate::error::{ServiceError, ServiceResult};
+use crate::scan::{resume_bounds, GuardedScan};
+
+/// Holders returned per page of `get_holders`
+pub const HOLDERS_PAGE_SIZE: usize = 100;
+
+/// Amount of a token held by one owner
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenBalance {
+    pub token_id: UnitsObjectId,
+    pub owner_id: UnitsObjectId,
+    /// Amount in the token's smallest unit
+    pub amount: u64,
+    pub decimals: u8,
+    pub symbol: String,
+    /// Set when the scan stopped early: `amount` only counts balance objects
+    /// up to here, and passing it back counts the rest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scan_cursor: Option<String>,
+}
+
+/// One page of a token's holders, largest balance first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenHolders {
+    pub token_id: UnitsObjectId,
+    pub page: u32,
+    /// Holders with a non-zero balance across all pages
+    pub total_holders: u64,
+    pub holders: Vec<TokenBalance>,
+    /// Set when the scan stopped early: holders only reflect balance objects
+    /// up to here, and passing it back aggregates the rest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scan_cursor: Option<String>,
+}
+
+/// Typed queries over the objects of the token controller
+pub struct TokenQueryService {
+    storage: Arc<ConsolidatedUnitsStorage>,
+    controller_id: UnitsObjectId,
+}
+
+impl TokenQueryService {
+    pub fn new(storage: Arc<ConsolidatedUnitsStorage>, controller_id: UnitsObjectId) -> Self {
+        Self { storage, controller_id }
+    }
+
+    /// Balance of `owner_id` in `token_id`, zero when the owner holds none
+    pub fn get_balance(
+        &self,
+        ctx: &RequestContext,
+        owner_id: &UnitsObjectId,
+        token_id: &UnitsObjectId,
+        scan_cursor: Option<&str>,
+    ) -> ServiceResult<TokenBalance> {
+        let token = self.token_data(token_id)?;
+        let (balances, scan_cursor) = self.balances(ctx, token_id, scan_cursor)?;
+        let amount = balances.get(owner_id).copied().unwrap_or_default();
+        Ok(TokenBalance {
+            scan_cursor,
+            ..Self::balance(*token_id, *owner_id, amount, &token)
+        })
+    }
+
+    /// Page `page` (from 0) of the owners holding `token_id`
+    pub fn get_holders(
+        &self,
+        ctx: &RequestContext,
+        token_id: &UnitsObjectId,
+        page: u32,
+        scan_cursor: Option<&str>,
+    ) -> ServiceResult<TokenHolders> {
+        let token = self.token_data(token_id)?;
+        let (balances, scan_cursor) = self.balances(ctx, token_id, scan_cursor)?;
+        let mut holders: Vec<(UnitsObjectId, u64)> = balances
+            .into_iter()
+            .filter(|(_, amount)| *amount > 0)
+            .collect();
+        // Owners break ties so pages are stable between calls
+        holders.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let total_holders = holders.len() as u64;
+        let holders = holders
+            .into_iter()
+            .skip(page as usize * HOLDERS_PAGE_SIZE)
+            .take(HOLDERS_PAGE_SIZE)
+            .map(|(owner_id, amount)| Self::balance(*token_id, owner_id, amount, &token))
+            .collect();
+
+        Ok(TokenHolders {
+            token_id: *token_id,
+            page,
+            total_holders,
+            holders,
+            scan_cursor,
+        })
+    }
+
+    /// Decode the token object, failing if it is not a token
+    fn token_data(&self, token_id: &UnitsObjectId) -> ServiceResult<TokenData> {
+        let object = self
+            .storage
+            .objects()
+            .get(token_id)?
+            .filter(|object| object.controller_id() == &self.controller_id)
+            .ok_or_else(|| ServiceError::object_not_found(token_id.to_string()))?;
+        decode_versioned(object.data())
+            .map_err(|_| ServiceError::invalid_request(format!("Object {} is not a token", token_id)))
+    }
+
+    /// Amount held per owner of `token_id`, summed over balance objects
+    /// after `scan_cursor`, and the cursor resuming the scan if it stopped early
+    fn balances(
+        &self,
+        ctx: &RequestContext,
+        token_id: &UnitsObjectId,
+        scan_cursor: Option<&str>,
+    ) -> ServiceResult<(BTreeMap<UnitsObjectId, u64>, Option<String>)> {
+        let objects = self.storage.objects();
+        let bounds = resume_bounds(scan_cursor)?;
+        let source: Box<dyn Iterator<Item = Result<UnitsObject, StorageError>> + '_> =
+            match self.storage.index_advisor().controller_objects(&self.controller_id, bounds.0) {
+                Some(ids) => Box::new(ids.into_iter().filter_map(|id| objects.get(&id).transpose())),
+                None => objects.iter_range(bounds),
+            };
+        let mut scan = GuardedScan::new(source, ctx);
+        let mut balances = BTreeMap::new();
+        for object in scan.by_ref() {
+            let object = object?;
+            if object.controller_id() != &self.controller_id {
+                continue;
+            }
+            let Some(balance) = Self::decode_balance(&object) else {
+                continue;
+            };
+            if UnitsObjectId::from(balance.token_id) == *token_id {
+                let amount = balances.entry(balance.owner_id.into()).or_insert(0u64);
+                *amount = amount.saturating_add(balance.amount);
+            }
+        }
+        self.storage.record_query(&QueryPattern::Controller { controller_id: self.controller_id }, scan.rows());
+        Ok((balances, scan.continuation()))
+    }
+
+    /// Read `object` as a balance, skipping token and other module objects
+    fn decode_balance(object: &UnitsObject) -> Option<BalanceData> {
+        borsh::from_slice(object.data()).ok()
+    }
+
+    fn balance(token_id: UnitsObjectId, owner_id: UnitsObjectId, amount: u64, token: &TokenData) -> TokenBalance {
+        TokenBalance {
+            token_id,
+            owner_id,
+            amount,
+            decimals: token.decimals,
+            symbol: token.symbol.clone(),
+            scan_cursor: None,
+        }
+    }
+}
@@ -8,12 +8,12 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use units_core_types::{
-    Runtime, ObjectStorage,
+    Runtime, ObjectStorage, LockManager, UnitsStorage, StorageError,
     Transaction, TransactionHash, TransactionReceipt,
-    ConflictResult,
-    UnitsObjectId, UnitsObject, SlotNumber,
+    ConflictChecker, BasicConflictChecker,
+    UnitsObjectId, UnitsObject, UnitsObjectProof, SlotNumber, TransactionView, BatchOp, DEPOSIT_LEDGER_ID,
 };
-use units_storage_impl::ConsolidatedUnitsStorage;
+use units_storage_impl::{ConsolidatedUnitsStorage, SimpleLockGuard};
 
 use crate::error::{ServiceError, ServiceResult};
 
@@ -91,106 +91,169 @@ pub struct PoolStats {
     pub max_pool_size: usize,
 }
 
-/// Transaction executor that coordinates with runtime
-pub struct TransactionExecutor {
-    runtime: Arc<dyn Runtime + Send + Sync>,
-    storage: Arc<ConsolidatedUnitsStorage>,
+/// Lock every object a transaction declares it may write
+///
+/// The guards must be held until the transaction's effects are committed or
+/// rolled back; dropping them releases the locks.
+fn lock_write_set<'a>(
+    storage: &'a ConsolidatedUnitsStorage,
+    transaction: &Transaction,
+) -> Result<Vec<SimpleLockGuard<'a>>, StorageError> {
+    let write_set: Vec<UnitsObjectId> = BasicConflictChecker::new()
+        .extract_write_objects(transaction)
+        .into_iter()
+        .collect();
+    storage.locks().lock_many(&write_set)
 }
 
-impl TransactionExecutor {
-    pub fn new(
-        runtime: Arc<dyn Runtime + Send + Sync>,
-        storage: Arc<ConsolidatedUnitsStorage>,
-    ) -> Self {
-        Self { runtime, storage }
-    }
-
-    /// Execute a single transaction
-    pub async fn execute_transaction(
-        &self,
-        transaction: Transaction,
-        _slot: SlotNumber,
-        _timestamp: u64,
-    ) -> ServiceResult<TransactionReceipt> {
-        // Check for conflicts first
-        match self.runtime.check_conflicts(&transaction)? {
-            ConflictResult::Conflict(conflicts) => {
-                return Err(ServiceError::transaction_failed(
-                    format!("Transaction conflicts with: {:?}", conflicts)
-                ));
-            }
-            ConflictResult::ReadOnly => {
-                // Read-only transactions can proceed without locks
-            }
-            ConflictResult::NoConflict => {
-                // No conflicts, proceed with execution
-            }
-        }
-
-        // Gather all required objects for the transaction
-        let mut objects = HashMap::new();
-        for instruction in &transaction.instructions {
-            // Load controller object
-            let controller = self.load_object(&instruction.controller_id).await?;
-            objects.insert(instruction.controller_id, controller);
-
-            // Load target objects
-            for input_id in &instruction.target_objects {
-                if !objects.contains_key(input_id) {
-                    let obj = self.load_object(input_id).await?;
-                    objects.insert(*input_id, obj);
-                }
-            }
+/// Run `execute` while holding the locks on `transaction`'s write set
+///
+/// The locks are released once `execute` returns, by which time the
+/// transaction's writes are committed or rolled back. A write set that
+/// cannot be locked in time, or only by deadlocking, fails the transaction
+/// with a receipt instead, without running `execute`.
+pub(crate) fn execute_with_write_locks<'a>(
+    storage: &'a ConsolidatedUnitsStorage,
+    transaction: &Transaction,
+    slot: SlotNumber,
+    timestamp: u64,
+    execute: impl FnOnce(&[SimpleLockGuard<'a>]) -> ServiceResult<TransactionReceipt>,
+) -> ServiceResult<TransactionReceipt> {
+    let locks = match lock_write_set(storage, transaction) {
+        Ok(guards) => guards,
+        Err(error @ (StorageError::LockTimeout(_) | StorageError::DeadlockDetected(_))) => {
+            return Ok(lock_failure_receipt(transaction, slot, timestamp, error));
         }
+        Err(error) => return Err(ServiceError::Storage(error)),
+    };
+    execute(&locks)
+}
 
-        // Execute through runtime
-        let receipt = self.runtime.execute_transaction(transaction);
+/// Attempts at putting one object back after a commit failed part way
+const RESTORE_ATTEMPTS: usize = 8;
+
+/// Write a transaction's `ops` to `storage` as one batch
+///
+/// Backends without atomic batches may fail part way through. The objects
+/// are then written back to their states from before the batch, so a
+/// failed commit leaves none of the transaction's writes behind.
+pub(crate) fn commit_writes<S: ObjectStorage>(
+    storage: &S,
+    ops: &[BatchOp],
+    transaction_hash: TransactionHash,
+) -> Result<HashMap<UnitsObjectId, UnitsObjectProof>, StorageError> {
+    let before = ops
+        .iter()
+        .map(|op| Ok((op.object_id(), storage.get(&op.object_id())?)))
+        .collect::<Result<Vec<_>, StorageError>>()?;
+    storage.apply_batch(ops, transaction_hash).inspect_err(|_| restore(storage, &before))
+}
 
-        // Apply effects to storage if successful
-        if receipt.success {
-            for (object_id, proof) in &receipt.object_proofs {
-                // The proof contains the new state that was applied
-                // In a real implementation, we'd verify the proof here
-                let _ = object_id;
-                let _ = proof;
-            }
+/// Write each object back to its state in `before` unless it already is
+fn restore<S: ObjectStorage>(storage: &S, before: &[(UnitsObjectId, Option<UnitsObject>)]) {
+    for (id, state) in before {
+        let restored = (0..RESTORE_ATTEMPTS).any(|_| match storage.get(id) {
+            Ok(current) if current == *state => true,
+            Ok(_) => match state {
+                Some(object) => storage.set(object, None).is_ok(),
+                None => storage.delete(id, None).is_ok(),
+            },
+            Err(_) => false,
+        });
+        if !restored {
+            log::error!("Failed to restore object {} after a partial commit", id);
         }
-
-        Ok(receipt)
     }
+}
 
-    /// Execute a batch of transactions
-    pub async fn execute_batch(
-        &self,
-        transactions: Vec<Transaction>,
-        slot: SlotNumber,
-        timestamp: u64,
-    ) -> Vec<ServiceResult<TransactionReceipt>> {
-        let mut results = Vec::new();
+/// Failed receipt for a transaction whose write set could not be locked
+fn lock_failure_receipt(
+    transaction: &Transaction,
+    slot: SlotNumber,
+    timestamp: u64,
+    error: StorageError,
+) -> TransactionReceipt {
+    let mut receipt = TransactionReceipt::new(transaction.hash, slot, false, timestamp);
+    receipt.memo = transaction.memo.clone();
+    receipt.set_error(error.to_string());
+    receipt
+}
 
-        for transaction in transactions {
-            let result = self.execute_transaction(transaction, slot, timestamp).await;
-            results.push(result);
+/// Execute `transaction` atomically and commit its writes to `storage`
+///
+/// Every instruction runs over one shared view, so its writes are committed
+/// all together or, if any instruction fails, not at all. The caller holds
+/// the locks on the transaction's write set.
+///
+/// Only transactions whose storage deposits change write the deposit
+/// ledger, so only they lock it, once they have run. One that saw the
+/// ledger move while it ran runs again under the lock.
+pub(crate) fn execute_atomically(
+    runtime: &dyn Runtime,
+    storage: &ConsolidatedUnitsStorage,
+    transaction: &Transaction,
+    slot: SlotNumber,
+    timestamp: u64,
+) -> ServiceResult<TransactionReceipt> {
+    let objects = storage.objects();
+    let ledger_version = objects.version(&DEPOSIT_LEDGER_ID)?;
+    let (mut receipt, mut ops) = stage(runtime, storage, transaction, slot, timestamp)?;
+
+    let writes_ledger = ops.iter().any(|op| op.object_id() == DEPOSIT_LEDGER_ID);
+    let _ledger = if writes_ledger || objects.version(&DEPOSIT_LEDGER_ID)? != ledger_version {
+        let guard = match storage.locks().lock(&DEPOSIT_LEDGER_ID) {
+            Ok(guard) => guard,
+            Err(error @ (StorageError::LockTimeout(_) | StorageError::DeadlockDetected(_))) => {
+                return Ok(lock_failure_receipt(transaction, slot, timestamp, error));
+            }
+            Err(error) => return Err(ServiceError::Storage(error)),
+        };
+        if objects.version(&DEPOSIT_LEDGER_ID)? != ledger_version {
+            (receipt, ops) = stage(runtime, storage, transaction, slot, timestamp)?;
         }
+        Some(guard)
+    } else {
+        None
+    };
 
-        results
+    for (object_id, proof) in commit_writes(objects, &ops, transaction.hash)? {
+        receipt.add_proof(object_id, proof);
     }
 
-    /// Load object from storage
-    async fn load_object(&self, id: &UnitsObjectId) -> ServiceResult<UnitsObject> {
-        use units_core_types::UnitsStorage;
-        self.storage
-            .objects()
-            .get(id)
-            .map_err(ServiceError::Storage)?
-            .ok_or_else(|| ServiceError::object_not_found(hex::encode(id.bytes())))
-    }
+    Ok(receipt)
 }
 
-/// Main transaction service that combines pool and executor
+/// Run `transaction` over a view of `storage`, returning its receipt and
+/// the writes left to commit
+fn stage(
+    runtime: &dyn Runtime,
+    storage: &ConsolidatedUnitsStorage,
+    transaction: &Transaction,
+    slot: SlotNumber,
+    timestamp: u64,
+) -> ServiceResult<(TransactionReceipt, Vec<BatchOp>)> {
+    let objects = storage.objects();
+    let load = |id: &UnitsObjectId| objects.get(id);
+    let versions = |id: &UnitsObjectId| objects.version(id);
+    let mut view = TransactionView::new(&load)
+        .with_versions(&versions)
+        .with_executables(storage.executables());
+    let mut receipt = runtime.execute_transaction_atomic(transaction, &mut view, slot, timestamp)?;
+    receipt.record_reads(transaction, |id| view.get(id))?;
+
+    let ops = view
+        .into_writes()
+        .into_iter()
+        .map(|(object_id, object)| object.map_or(BatchOp::Delete(object_id), BatchOp::Set))
+        .collect();
+    Ok((receipt, ops))
+}
+
+/// Main transaction service that combines the pool with execution
 pub struct TransactionService {
     pool: Arc<TransactionPool>,
-    executor: Arc<TransactionExecutor>,
+    runtime: Arc<dyn Runtime + Send + Sync>,
+    storage: Arc<ConsolidatedUnitsStorage>,
     slot_number: Arc<RwLock<SlotNumber>>,
 }
 
@@ -201,11 +264,11 @@ impl TransactionService {
         max_pool_size: usize,
     ) -> Self {
         let pool = Arc::new(TransactionPool::new(max_pool_size));
-        let executor = Arc::new(TransactionExecutor::new(runtime, storage));
         
         Self {
             pool,
-            executor,
+            runtime,
+            storage,
             slot_number: Arc::new(RwLock::new(0)),
         }
     }
@@ -233,7 +296,10 @@ impl TransactionService {
         for transaction in transactions {
             let hash = transaction.hash;
             
-            match self.executor.execute_transaction(transaction, slot, timestamp).await {
+            let executed = execute_with_write_locks(&self.storage, &transaction, slot, timestamp, |_| {
+                execute_atomically(self.runtime.as_ref(), &self.storage, &transaction, slot, timestamp)
+            });
+            match executed {
                 Ok(receipt) => {
                     // Remove from pool and store receipt
                     self.pool.remove_transaction(&hash).await;
@@ -0,0 +1,398 @@
+//! Durable watches on objects and controllers
+//!
+//! A client registers a watch on one object, or on every object of a
+//! controller, with a filter evaluated on the node: which fields must have
+//! changed and how large the object's data must be. Each committed effect
+//! that passes the filter becomes a numbered notification, delivered over a
+//! `watch_subscribe` WebSocket subscription or POSTed to a webhook signed
+//! like receipt webhooks.
+//!
+//! Registrations and their sequence numbers are written to `watches.json`
+//! in the storage data directory, so watches keep firing, with unbroken
+//! sequences, after the node restarts. Without a data directory they live
+//! in memory only. WebSocket notifications are also kept in a bounded
+//! backlog per watch, which a subscriber replays from the last sequence it
+//! saw when it reconnects.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::broadcast;
+use units_core_types::transaction::{TransactionEffect, TransactionHash, TransactionReceipt};
+use units_core_types::{SlotNumber, UnitsObject, UnitsObjectId};
+
+use crate::config::WatchConfig;
+use crate::error::{ServiceError, ServiceResult};
+
+/// File in the storage data directory holding the registrations
+pub const WATCHES_FILE: &str = "watches.json";
+
+/// Notifications buffered for live subscribers before slow ones lag
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// What a watch observes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum WatchTarget {
+    /// One object
+    Object { object_id: UnitsObjectId },
+    /// Every object controlled by the controller, before or after the change
+    Controller { controller_id: UnitsObjectId },
+}
+
+/// Object fields a filter can require to have changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObjectField {
+    Data,
+    Controller,
+    ObjectType,
+}
+
+impl ObjectField {
+    fn changed(self, before: &UnitsObject, after: &UnitsObject) -> bool {
+        match self {
+            ObjectField::Data => before.data != after.data,
+            ObjectField::Controller => before.controller_id != after.controller_id,
+            ObjectField::ObjectType => before.object_type != after.object_type,
+        }
+    }
+}
+
+/// Conditions an effect must meet to notify a watch
+///
+/// Creations and deletions change every field. Data sizes are those of the
+/// object after the change, or before it for deletions.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchFilter {
+    /// Notify only when one of these fields changed; empty matches any change
+    pub changed_fields: Vec<ObjectField>,
+    /// Notify only when the data is at least this many bytes
+    pub min_data_size: Option<usize>,
+    /// Notify only when the data is at most this many bytes
+    pub max_data_size: Option<usize>,
+}
+
+impl WatchFilter {
+    pub fn matches(&self, effect: &TransactionEffect) -> bool {
+        let (before, after) = (effect.before_image.as_ref(), effect.after_image.as_ref());
+        let Some(object) = after.or(before) else {
+            return false;
+        };
+        let size = object.data.len();
+        if self.min_data_size.is_some_and(|min| size < min) || self.max_data_size.is_some_and(|max| size > max) {
+            return false;
+        }
+        match (before, after) {
+            (Some(before), Some(after)) if self.changed_fields.is_empty() => before != after,
+            (Some(before), Some(after)) => self.changed_fields.iter().any(|field| field.changed(before, after)),
+            _ => true,
+        }
+    }
+}
+
+/// Where a watch's notifications go
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum WatchDelivery {
+    /// To `watch_subscribe` subscribers of the watch
+    WebSocket,
+    /// POSTed to `url`, signed when the node has a signing key
+    Webhook { url: String },
+}
+
+/// A registered watch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Watch {
+    pub id: String,
+    pub target: WatchTarget,
+    pub filter: WatchFilter,
+    pub delivery: WatchDelivery,
+    /// Sequence of the last notification produced, 0 before the first
+    pub sequence: u64,
+}
+
+impl Watch {
+    fn observes(&self, effect: &TransactionEffect) -> bool {
+        match &self.target {
+            WatchTarget::Object { object_id } => effect.object_id == *object_id,
+            WatchTarget::Controller { controller_id } => [&effect.before_image, &effect.after_image]
+                .into_iter()
+                .flatten()
+                .any(|object| object.controller_id == *controller_id),
+        }
+    }
+}
+
+/// A change seen by a watch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchNotification {
+    pub watch_id: String,
+    /// Position among the watch's notifications, starting at 1
+    pub sequence: u64,
+    pub slot: SlotNumber,
+    pub transaction_hash: TransactionHash,
+    pub object_id: UnitsObjectId,
+    /// The object before the change, absent for creations
+    pub before: Option<UnitsObject>,
+    /// The object after the change, absent for deletions
+    pub after: Option<UnitsObject>,
+}
+
+/// Registered watches and the notifications they produce
+pub struct WatchRegistry {
+    config: WatchConfig,
+    /// Registration file, absent when watches are kept in memory only
+    path: Option<PathBuf>,
+    watches: RwLock<BTreeMap<String, Watch>>,
+    /// Recent WebSocket notifications of each watch, oldest first
+    backlog: Mutex<HashMap<String, VecDeque<WatchNotification>>>,
+    notifications: broadcast::Sender<WatchNotification>,
+    id_counter: AtomicU64,
+}
+
+impl WatchRegistry {
+    /// Registry keeping its watches in memory only
+    pub fn in_memory(config: WatchConfig) -> Self {
+        Self {
+            config,
+            path: None,
+            watches: RwLock::new(BTreeMap::new()),
+            backlog: Mutex::new(HashMap::new()),
+            notifications: broadcast::channel(CHANNEL_CAPACITY).0,
+            id_counter: AtomicU64::new(0),
+        }
+    }
+
+    /// Registry persisting to `data_dir`, loading the watches saved there
+    pub fn open(config: WatchConfig, data_dir: Option<&str>) -> ServiceResult<Self> {
+        let path = data_dir.map(|dir| PathBuf::from(dir).join(WATCHES_FILE));
+        let watches = match &path {
+            Some(path) if path.exists() => {
+                let json = std::fs::read(path).map_err(|e| ServiceError::Internal(e.into()))?;
+                let watches: Vec<Watch> = serde_json::from_slice(&json)?;
+                watches.into_iter().map(|watch| (watch.id.clone(), watch)).collect()
+            }
+            _ => BTreeMap::new(),
+        };
+        Ok(Self {
+            path,
+            watches: RwLock::new(watches),
+            ..Self::in_memory(config)
+        })
+    }
+
+    /// Register a watch, returning it with its new ID
+    pub fn register(&self, target: WatchTarget, filter: WatchFilter, delivery: WatchDelivery) -> ServiceResult<Watch> {
+        if let WatchDelivery::Webhook { url } = &delivery {
+            if !url.starts_with("http://") {
+                return Err(ServiceError::invalid_request("Watch webhooks must be http:// URLs"));
+            }
+        }
+        if filter.min_data_size.zip(filter.max_data_size).is_some_and(|(min, max)| min > max) {
+            return Err(ServiceError::invalid_request("min_data_size exceeds max_data_size"));
+        }
+
+        let mut watches = self.watches.write().unwrap();
+        if watches.len() >= self.config.max_watches {
+            return Err(ServiceError::invalid_request(format!(
+                "The node holds at most {} watches",
+                self.config.max_watches
+            )));
+        }
+        let watch = Watch {
+            id: self.next_id(&target),
+            target,
+            filter,
+            delivery,
+            sequence: 0,
+        };
+        watches.insert(watch.id.clone(), watch.clone());
+        self.save(&watches)?;
+        Ok(watch)
+    }
+
+    /// Remove watch `id`, returning whether it existed
+    pub fn remove(&self, id: &str) -> ServiceResult<bool> {
+        let mut watches = self.watches.write().unwrap();
+        if watches.remove(id).is_none() {
+            return Ok(false);
+        }
+        self.backlog.lock().unwrap().remove(id);
+        self.save(&watches)?;
+        Ok(true)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Watch> {
+        self.watches.read().unwrap().get(id).cloned()
+    }
+
+    /// Match the effects of a committed slot's `receipts` against every watch
+    ///
+    /// WebSocket notifications are published to subscribers and the backlog.
+    /// Returned are the webhook notifications, with the URL to POST each to.
+    pub fn notify(&self, slot: SlotNumber, receipts: &[TransactionReceipt]) -> ServiceResult<Vec<(String, WatchNotification)>> {
+        let mut watches = self.watches.write().unwrap();
+        if watches.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut published = Vec::new();
+        let mut webhooks = Vec::new();
+        for effect in receipts.iter().filter(|receipt| receipt.success).flat_map(|receipt| &receipt.effects) {
+            for watch in watches.values_mut() {
+                if !watch.observes(effect) || !watch.filter.matches(effect) {
+                    continue;
+                }
+                watch.sequence += 1;
+                let notification = WatchNotification {
+                    watch_id: watch.id.clone(),
+                    sequence: watch.sequence,
+                    slot,
+                    transaction_hash: effect.transaction_hash,
+                    object_id: effect.object_id,
+                    before: effect.before_image.clone(),
+                    after: effect.after_image.clone(),
+                };
+                match &watch.delivery {
+                    WatchDelivery::WebSocket => published.push(notification),
+                    WatchDelivery::Webhook { url } => webhooks.push((url.clone(), notification)),
+                }
+            }
+        }
+        if published.is_empty() && webhooks.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.save(&watches)?;
+        drop(watches);
+
+        let mut backlog = self.backlog.lock().unwrap();
+        for notification in published {
+            let buffered = backlog.entry(notification.watch_id.clone()).or_default();
+            if buffered.len() >= self.config.backlog_per_watch {
+                buffered.pop_front();
+            }
+            buffered.push_back(notification.clone());
+            // No subscribers is not an error; the backlog keeps the notification
+            let _ = self.notifications.send(notification);
+        }
+        Ok(webhooks)
+    }
+
+    /// Live notifications of every watch, and the backlog of watch `id`
+    /// after sequence `since`
+    ///
+    /// Subscribing before reading the backlog means nothing is missed in
+    /// between; live notifications at or below the backlog's last sequence
+    /// are repeats to skip.
+    pub fn subscribe(
+        &self,
+        id: &str,
+        since: u64,
+    ) -> ServiceResult<(Vec<WatchNotification>, broadcast::Receiver<WatchNotification>)> {
+        match self.get(id) {
+            Some(Watch { delivery: WatchDelivery::WebSocket, .. }) => {}
+            Some(_) => return Err(ServiceError::invalid_request(format!("Watch {} delivers to a webhook", id))),
+            None => return Err(ServiceError::invalid_request(format!("No watch {}", id))),
+        }
+        let receiver = self.notifications.subscribe();
+        let backlog = self.backlog.lock().unwrap().get(id).map_or_else(Vec::new, |buffered| {
+            buffered.iter().filter(|notification| notification.sequence > since).cloned().collect()
+        });
+        Ok((backlog, receiver))
+    }
+
+    /// Write the registrations through a temporary file, so a crash never
+    /// leaves a torn file behind
+    fn save(&self, watches: &BTreeMap<String, Watch>) -> ServiceResult<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let json = serde_json::to_vec_pretty(&watches.values().collect::<Vec<_>>())?;
+        let temporary = path.with_extension("json.tmp");
+        std::fs::write(&temporary, json)
+            .and_then(|()| std::fs::rename(&temporary, path))
+            .map_err(|e| ServiceError::Internal(e.into()))
+    }
+
+    /// Unguessable watch ID: a hash of the target, the time and a counter
+    fn next_id(&self, target: &WatchTarget) -> String {
+        let counter = self.id_counter.fetch_add(1, Ordering::Relaxed);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos())
+            .unwrap_or_default();
+        let digest = Sha256::new()
+            .chain_update(format!("{:?}", target))
+            .chain_update(now.to_le_bytes())
+            .chain_update(counter.to_le_bytes())
+            .finalize();
+        hex::encode(&digest[..16])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn effect(before: Option<UnitsObject>, after: Option<UnitsObject>) -> TransactionEffect {
+        let object_id = *before.as_ref().or(after.as_ref()).unwrap().id();
+        let mut receipt = TransactionReceipt::new([1; 32], 1, true, 0);
+        receipt.add_object_effect([1; 32], object_id, before, after);
+        receipt.effects.remove(0)
+    }
+
+    #[test]
+    fn test_filters_check_changed_fields_and_sizes() {
+        let id = UnitsObjectId::new([1; 32]);
+        let controller = UnitsObjectId::new([2; 32]);
+        let small = UnitsObject::new_data(id, controller, vec![0; 4]);
+        let large = UnitsObject::new_data(id, controller, vec![0; 64]);
+        let moved = UnitsObject::new_data(id, UnitsObjectId::new([3; 32]), vec![0; 4]);
+
+        let any = WatchFilter::default();
+        assert!(any.matches(&effect(Some(small.clone()), Some(large.clone()))));
+        assert!(!any.matches(&effect(Some(small.clone()), Some(small.clone()))));
+        assert!(any.matches(&effect(None, Some(small.clone()))));
+
+        let controller_changes = WatchFilter { changed_fields: vec![ObjectField::Controller], ..Default::default() };
+        assert!(!controller_changes.matches(&effect(Some(small.clone()), Some(large.clone()))));
+        assert!(controller_changes.matches(&effect(Some(small.clone()), Some(moved))));
+
+        let large_only = WatchFilter { min_data_size: Some(32), ..Default::default() };
+        assert!(large_only.matches(&effect(Some(small.clone()), Some(large.clone()))));
+        assert!(!large_only.matches(&effect(Some(large.clone()), Some(small.clone()))));
+        assert!(large_only.matches(&effect(Some(large), None)));
+    }
+
+    #[test]
+    fn test_watches_survive_reopening() {
+        let dir = std::env::temp_dir().join(format!("units-watches-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let data_dir = dir.to_str();
+
+        let object = UnitsObject::new_data(UnitsObjectId::new([1; 32]), UnitsObjectId::new([2; 32]), vec![1]);
+        let mut receipt = TransactionReceipt::new([1; 32], 1, true, 0);
+        receipt.add_object_effect([1; 32], object.id, None, Some(object.clone()));
+
+        let registry = WatchRegistry::open(WatchConfig::default(), data_dir).unwrap();
+        let watch = registry
+            .register(WatchTarget::Object { object_id: object.id }, WatchFilter::default(), WatchDelivery::WebSocket)
+            .unwrap();
+        registry.notify(1, &[receipt.clone()]).unwrap();
+
+        let reopened = WatchRegistry::open(WatchConfig::default(), data_dir).unwrap();
+        assert_eq!(reopened.get(&watch.id).map(|watch| watch.sequence), Some(1));
+        reopened.notify(2, &[receipt]).unwrap();
+        let (backlog, _) = reopened.subscribe(&watch.id, 0).unwrap();
+        assert_eq!(backlog.iter().map(|n| (n.sequence, n.slot)).collect::<Vec<_>>(), vec![(2, 2)]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,281 @@
+//! Signed transaction receipt webhooks
+//!
+//! Every executed receipt is POSTed as JSON to each configured endpoint.
+//! The body carries a sequence number that increases by one per endpoint,
+//! so receivers can drop duplicates and notice gaps. When the node has a
+//! signing key each delivery is signed with it over the body, a timestamp
+//! and a one-time nonce, which receivers check to reject forged or replayed
+//! notifications. With a data directory, sequences are saved there and
+//! carry on across restarts; without one they restart with the node.
+//!
+//! Each endpoint has one queue, drained by a single task, so its
+//! notifications arrive in sequence order even across slots.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hyper::{Body, Client, Method, Request};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use units_core_types::transaction::TransactionReceipt;
+
+use crate::config::WebhookConfig;
+use crate::error::{ServiceError, ServiceResult};
+use crate::signing::{verify_signature, NodeSigner};
+
+/// Domain separator prefixed to every signed webhook message
+const WEBHOOK_DOMAIN: &[u8] = b"units-webhook-v1";
+
+/// File under the data directory holding the last sequence sent to each endpoint
+pub const WEBHOOK_SEQUENCES_FILE: &str = "webhook_sequences.json";
+
+/// Header carrying the delivery's unix timestamp in milliseconds
+pub const TIMESTAMP_HEADER: &str = "x-units-timestamp";
+/// Header carrying the hex-encoded delivery nonce
+pub const NONCE_HEADER: &str = "x-units-nonce";
+/// Header carrying the hex-encoded Ed25519 signature
+pub const SIGNATURE_HEADER: &str = "x-units-signature";
+/// Header carrying the hex-encoded node key that signed the delivery
+pub const NODE_KEY_HEADER: &str = "x-units-node-key";
+
+/// JSON body of a receipt notification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    /// Position of this notification among those sent to the endpoint
+    pub sequence: u64,
+    pub receipt: TransactionReceipt,
+}
+
+/// One notification ready to send to an endpoint
+#[derive(Debug, Clone)]
+pub struct WebhookDelivery {
+    pub endpoint: String,
+    pub sequence: u64,
+    /// Serialized `WebhookPayload`, sent as is
+    pub body: Vec<u8>,
+    pub timestamp_ms: u64,
+    pub nonce: [u8; 16],
+    /// Signature and signing key, absent when response signing is disabled
+    pub signature: Option<([u8; 64], [u8; 32])>,
+}
+
+impl WebhookDelivery {
+    /// Check the signature over this delivery against `node_key`
+    pub fn verify(&self, node_key: &[u8; 32]) -> bool {
+        match &self.signature {
+            Some((signature, _)) => verify_webhook(node_key, &self.body, self.timestamp_ms, &self.nonce, signature),
+            None => false,
+        }
+    }
+}
+
+/// Verify a webhook signature as a receiver would, from the raw body and headers
+pub fn verify_webhook(node_key: &[u8; 32], body: &[u8], timestamp_ms: u64, nonce: &[u8; 16], signature: &[u8; 64]) -> bool {
+    verify_signature(node_key, &webhook_message(body, timestamp_ms, nonce), signature)
+}
+
+fn webhook_message(body: &[u8], timestamp_ms: u64, nonce: &[u8; 16]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(WEBHOOK_DOMAIN.len() + 32 + 8 + 16);
+    message.extend_from_slice(WEBHOOK_DOMAIN);
+    message.extend_from_slice(&Sha256::digest(body));
+    message.extend_from_slice(&timestamp_ms.to_le_bytes());
+    message.extend_from_slice(nonce);
+    message
+}
+
+/// Sends receipt notifications to the configured endpoints
+pub struct WebhookDispatcher {
+    config: WebhookConfig,
+    client: Client<hyper::client::HttpConnector>,
+    /// Last sequence taken for each endpoint
+    sequences: Mutex<BTreeMap<String, u64>>,
+    /// Where `sequences` is saved, if anywhere
+    path: Option<PathBuf>,
+    /// Deliveries waiting to be sent, per endpoint
+    queues: Mutex<HashMap<String, UnboundedSender<WebhookDelivery>>>,
+    nonce_counter: AtomicU64,
+}
+
+impl WebhookDispatcher {
+    /// Dispatcher whose sequences restart with the node
+    pub fn new(config: WebhookConfig) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+            sequences: Mutex::new(BTreeMap::new()),
+            path: None,
+            queues: Mutex::new(HashMap::new()),
+            nonce_counter: AtomicU64::new(0),
+        }
+    }
+
+    /// Dispatcher continuing the sequences saved under `data_dir`, or
+    /// restarting them without one
+    pub fn open(config: WebhookConfig, data_dir: Option<&str>) -> ServiceResult<Self> {
+        let path = data_dir.map(|dir| PathBuf::from(dir).join(WEBHOOK_SEQUENCES_FILE));
+        let sequences = match &path {
+            Some(path) if path.exists() => {
+                let json = std::fs::read(path).map_err(|e| ServiceError::Internal(e.into()))?;
+                serde_json::from_slice(&json)?
+            }
+            _ => BTreeMap::new(),
+        };
+        Ok(Self {
+            sequences: Mutex::new(sequences),
+            path,
+            ..Self::new(config)
+        })
+    }
+
+    /// Build the deliveries of `receipt`, one per endpoint, taking the next sequence of each
+    pub fn prepare(&self, receipt: &TransactionReceipt, signer: Option<&NodeSigner>) -> ServiceResult<Vec<WebhookDelivery>> {
+        self.prepare_all(std::slice::from_ref(receipt), signer)
+    }
+
+    /// Build the deliveries of `receipts`, in order, saving the sequences
+    /// taken before returning them
+    fn prepare_all(&self, receipts: &[TransactionReceipt], signer: Option<&NodeSigner>) -> ServiceResult<Vec<WebhookDelivery>> {
+        let mut sequences = self.sequences.lock().unwrap();
+        let mut deliveries = Vec::with_capacity(receipts.len() * self.config.endpoints.len());
+        for receipt in receipts {
+            for endpoint in &self.config.endpoints {
+                let sequence = sequences.entry(endpoint.clone()).or_insert(0);
+                *sequence += 1;
+                let body = serde_json::to_vec(&WebhookPayload {
+                    sequence: *sequence,
+                    receipt: receipt.clone(),
+                })?;
+                deliveries.push(self.delivery(endpoint, *sequence, body, signer));
+            }
+        }
+        self.save(&sequences)?;
+        Ok(deliveries)
+    }
+
+    /// Write the sequences through a temporary file, so a crash never
+    /// leaves a torn file behind
+    fn save(&self, sequences: &BTreeMap<String, u64>) -> ServiceResult<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let temporary = path.with_extension("json.tmp");
+        std::fs::write(&temporary, serde_json::to_vec_pretty(sequences)?)
+            .and_then(|()| std::fs::rename(&temporary, path))
+            .map_err(|e| ServiceError::Internal(e.into()))
+    }
+
+    /// Timestamp, nonce and sign `body` for sending to `endpoint`
+    pub fn delivery(&self, endpoint: &str, sequence: u64, body: Vec<u8>, signer: Option<&NodeSigner>) -> WebhookDelivery {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default();
+        let nonce = self.next_nonce(endpoint, sequence);
+        let signature = signer.map(|signer| {
+            (signer.sign(&webhook_message(&body, timestamp_ms, &nonce)), signer.public_key())
+        });
+
+        WebhookDelivery {
+            endpoint: endpoint.to_string(),
+            sequence,
+            body,
+            timestamp_ms,
+            nonce,
+            signature,
+        }
+    }
+
+    /// Notify every endpoint of `receipts` in the background
+    ///
+    /// Deliveries to one endpoint are sent in sequence order; failures are
+    /// logged and not retried.
+    pub fn dispatch(&self, receipts: &[TransactionReceipt], signer: Option<&NodeSigner>) -> ServiceResult<()> {
+        if self.config.endpoints.is_empty() || receipts.is_empty() {
+            return Ok(());
+        }
+
+        let deliveries = self.prepare_all(receipts, signer)?;
+        self.send_all(deliveries);
+        Ok(())
+    }
+
+    /// Queue `deliveries` for sending in the background, after those queued
+    /// before for the same endpoint
+    pub fn send_all(&self, deliveries: Vec<WebhookDelivery>) {
+        let mut queues = self.queues.lock().unwrap();
+        for delivery in deliveries {
+            let queue = queues
+                .entry(delivery.endpoint.clone())
+                .or_insert_with(|| self.spawn_sender(&delivery.endpoint));
+            // The sending task ends with the runtime that ran it; start another
+            if let Err(unsent) = queue.send(delivery) {
+                *queue = self.spawn_sender(&unsent.0.endpoint);
+                let _ = queue.send(unsent.0);
+            }
+        }
+    }
+
+    /// Start the task sending `endpoint`'s deliveries one at a time, returning its queue
+    fn spawn_sender(&self, endpoint: &str) -> UnboundedSender<WebhookDelivery> {
+        let (queue, mut deliveries) = unbounded_channel::<WebhookDelivery>();
+        let client = self.client.clone();
+        let timeout = Duration::from_millis(self.config.timeout_ms);
+        let endpoint = endpoint.to_string();
+        tokio::spawn(async move {
+            while let Some(delivery) = deliveries.recv().await {
+                let sequence = delivery.sequence;
+                match tokio::time::timeout(timeout, send(&client, delivery)).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(error)) => log::warn!("Webhook {} #{} failed: {}", endpoint, sequence, error),
+                    Err(_) => log::warn!("Webhook {} #{} timed out", endpoint, sequence),
+                }
+            }
+        });
+        queue
+    }
+
+    /// Nonce unique to this delivery: a hash of the endpoint, sequence, time and a counter
+    fn next_nonce(&self, endpoint: &str, sequence: u64) -> [u8; 16] {
+        let counter = self.nonce_counter.fetch_add(1, Ordering::Relaxed);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos())
+            .unwrap_or_default();
+        let digest = Sha256::new()
+            .chain_update(endpoint)
+            .chain_update(sequence.to_le_bytes())
+            .chain_update(now.to_le_bytes())
+            .chain_update(counter.to_le_bytes())
+            .finalize();
+        let mut nonce = [0u8; 16];
+        nonce.copy_from_slice(&digest[..16]);
+        nonce
+    }
+}
+
+async fn send(client: &Client<hyper::client::HttpConnector>, delivery: WebhookDelivery) -> ServiceResult<()> {
+    let mut request = Request::builder()
+        .method(Method::POST)
+        .uri(&delivery.endpoint)
+        .header("content-type", "application/json")
+        .header(TIMESTAMP_HEADER, delivery.timestamp_ms.to_string())
+        .header(NONCE_HEADER, hex::encode(delivery.nonce));
+    if let Some((signature, node_key)) = &delivery.signature {
+        request = request
+            .header(SIGNATURE_HEADER, hex::encode(signature))
+            .header(NODE_KEY_HEADER, hex::encode(node_key));
+    }
+    let request = request
+        .body(Body::from(delivery.body))
+        .map_err(|e| ServiceError::Internal(e.into()))?;
+
+    let response = client.request(request).await.map_err(|e| ServiceError::Internal(e.into()))?;
+    if !response.status().is_success() {
+        return Err(ServiceError::Internal(anyhow::anyhow!("Endpoint answered {}", response.status())));
+    }
+    Ok(())
+}
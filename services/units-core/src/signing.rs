@@ -0,0 +1,151 @@
+//! Node signing key and signed RPC responses
+//!
+//! When signing is enabled the node attests to read responses by signing
+//! (response hash, slot, state root) with its Ed25519 key. Gateways and
+//! caches can keep the attestation alongside the response to prove which
+//! node served it and at what state. The same key signs each slot's state
+//! proof, so state roots served by the node are authenticated as well.
+
+use std::path::Path;
+
+use anyhow::Context;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use units_core_types::{SlotNumber, StateProof};
+use units_proofs::{Ed25519Signer, ProofEngine, ProofSigner};
+
+use crate::error::ServiceResult;
+
+/// Domain separator prefixed to every signed response message
+const RESPONSE_DOMAIN: &[u8] = b"units-rpc-response-v1";
+
+/// Ed25519 key the node signs responses and state proofs with
+pub struct NodeSigner {
+    key: Ed25519Signer,
+}
+
+impl NodeSigner {
+    /// Derive the key from a 32-byte Ed25519 seed
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        Self {
+            key: Ed25519Signer::from_seed(seed),
+        }
+    }
+
+    /// Load the key from a file holding the hex-encoded seed
+    pub fn from_key_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read signing key {}", path.display()))?;
+        let seed: [u8; 32] = hex::decode(contents.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .with_context(|| format!("Signing key {} must be 32 hex-encoded bytes", path.display()))?;
+        Ok(Self::from_seed(seed))
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        self.key.public_key()
+    }
+
+    /// Sign a message
+    pub fn sign(&self, message: &[u8]) -> [u8; 64] {
+        self.key.sign(message)
+    }
+
+    /// Sign a state proof's slot root, hex-encoded
+    pub fn sign_state_proof(&self, state_proof: &StateProof) -> String {
+        hex::encode(ProofEngine::new().sign_state_proof(state_proof, &self.key).signature)
+    }
+
+    /// Sign a response observed at `slot` under `state_root`
+    pub fn sign_response<T: Serialize>(
+        &self,
+        response: &T,
+        slot: SlotNumber,
+        state_root: Option<[u8; 32]>,
+    ) -> ServiceResult<ResponseSignature> {
+        let response_hash = response_hash(response)?;
+        let message = response_message(&response_hash, slot, state_root.as_ref());
+
+        Ok(ResponseSignature {
+            node_key: hex::encode(self.public_key()),
+            slot,
+            state_root: state_root.map(hex::encode),
+            response_hash: hex::encode(response_hash),
+            signature: hex::encode(self.sign(&message)),
+        })
+    }
+}
+
+impl ProofSigner for NodeSigner {
+    fn public_key(&self) -> [u8; 32] {
+        self.key.public_key()
+    }
+
+    fn sign(&self, message: &[u8]) -> [u8; 64] {
+        self.key.sign(message)
+    }
+}
+
+/// Verify an Ed25519 signature
+pub fn verify_signature(public_key: &[u8; 32], message: &[u8], signature: &[u8; 64]) -> bool {
+    units_core_types::ed25519::verify(public_key, message, signature)
+}
+
+/// Hash of a response's JSON encoding
+pub fn response_hash<T: Serialize>(response: &T) -> ServiceResult<[u8; 32]> {
+    let bytes = serde_json::to_vec(response)?;
+    Ok(Sha256::digest(bytes).into())
+}
+
+fn response_message(response_hash: &[u8; 32], slot: SlotNumber, state_root: Option<&[u8; 32]>) -> Vec<u8> {
+    let mut message = Vec::with_capacity(RESPONSE_DOMAIN.len() + 72);
+    message.extend_from_slice(RESPONSE_DOMAIN);
+    message.extend_from_slice(response_hash);
+    message.extend_from_slice(&slot.to_le_bytes());
+    message.extend_from_slice(state_root.unwrap_or(&[0u8; 32]));
+    message
+}
+
+/// Node attestation attached to a signed response
+///
+/// Byte fields are hex-encoded. `state_root` is the object root committed
+/// for `slot`, absent before the node has committed one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseSignature {
+    pub node_key: String,
+    pub slot: SlotNumber,
+    pub state_root: Option<String>,
+    pub response_hash: String,
+    pub signature: String,
+}
+
+impl ResponseSignature {
+    /// Check that this attestation signs `response` under its node key
+    pub fn verify<T: Serialize>(&self, response: &T) -> bool {
+        let decode = |value: &str| hex::decode(value).ok();
+        let (Some(node_key), Some(signature)) = (
+            decode(&self.node_key).and_then(|bytes| <[u8; 32]>::try_from(bytes).ok()),
+            decode(&self.signature).and_then(|bytes| <[u8; 64]>::try_from(bytes).ok()),
+        ) else {
+            return false;
+        };
+        let state_root = match &self.state_root {
+            Some(root) => match decode(root).and_then(|bytes| <[u8; 32]>::try_from(bytes).ok()) {
+                Some(root) => Some(root),
+                None => return false,
+            },
+            None => None,
+        };
+
+        match response_hash(response) {
+            Ok(hash) if hex::encode(hash) == self.response_hash => {
+                let message = response_message(&hash, self.slot, state_root.as_ref());
+                verify_signature(&node_key, &message, &signature)
+            }
+            _ => false,
+        }
+    }
+}
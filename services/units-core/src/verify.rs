@@ -0,0 +1,271 @@
+//! Verify objects served by an untrusted node
+//!
+//! The client fetches an object, its inclusion path, the state root it is
+//! committed under and the node's signature over JSON-RPC, then checks all
+//! of it locally with `units-proofs`. Nothing the node computed is taken on
+//! trust: a node can only make verification fail, not pass.
+
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::http_client::HttpClientBuilder;
+use jsonrpsee::rpc_params;
+use serde::{Deserialize, Serialize};
+use units_core_types::objects::UnitsObject;
+use units_core_types::constants::ATTEST_CONTROLLER_ID;
+use units_core_types::{SlotNumber, UnitsObjectId};
+use units_proofs::with_proof_engine;
+
+use crate::json_rpc::ObjectReadResponse;
+use crate::service::{ObjectInclusion, ObjectRootPath, StateRoot};
+use crate::services::{Attestation, Collection, SlotSummary};
+use crate::signing::ResponseSignature;
+
+/// Everything a node serves about one object
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectEvidence {
+    pub object: UnitsObject,
+    /// Node attestation over the object, absent when the node does not sign
+    pub signature: Option<ResponseSignature>,
+    pub inclusion: ObjectInclusion,
+    pub state_root: StateRoot,
+}
+
+/// Timestamped proof that a document existed, checkable without the node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExistenceReceipt {
+    pub attestation: Attestation,
+    /// Evidence for the attestation record under the state root covering it
+    pub evidence: ObjectEvidence,
+}
+
+/// Resource usage of a slot, checkable against the state root covering it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlotSummaryReceipt {
+    pub summary: SlotSummary,
+    /// Evidence for the summary object
+    pub evidence: ObjectEvidence,
+}
+
+/// A collection and every member's latest proof, under one state root
+///
+/// The collection's evidence pins its member list; each member path then
+/// pins that member's current state to the same root. `group_root` is the
+/// Merkle root over just the members' leaves, a compact commitment to the
+/// group at this slot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionProof {
+    pub collection: Collection,
+    /// Evidence for the collection object
+    pub evidence: ObjectEvidence,
+    /// Hex-encoded Merkle root over the members' latest proofs
+    pub group_root: String,
+    /// Path from each member's proof to the state root, in member order
+    pub members: Vec<ObjectRootPath>,
+}
+
+/// Outcome of checking the signature on an object read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SignatureCheck {
+    /// Valid, and by the expected key if one was given
+    Valid,
+    /// Malformed, not over this object, or contradicting the state root
+    Invalid,
+    /// Valid, but by a key other than the expected one
+    UnexpectedKey,
+    /// The node did not sign the read
+    Missing,
+}
+
+/// Result of each local check on an object's evidence
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationReport {
+    pub object_id: UnitsObjectId,
+    pub slot: SlotNumber,
+    /// The object hashes to its latest proof
+    pub object_proof_valid: bool,
+    /// The proof's leaf hashes up the path to the committed object root
+    pub included_in_state_root: bool,
+    pub signature: SignatureCheck,
+}
+
+impl VerificationReport {
+    /// Whether every check passed; a missing signature passes unless one is required
+    pub fn is_valid(&self, require_signature: bool) -> bool {
+        self.object_proof_valid
+            && self.included_in_state_root
+            && match self.signature {
+                SignatureCheck::Valid => true,
+                SignatureCheck::Missing => !require_signature,
+                SignatureCheck::Invalid | SignatureCheck::UnexpectedKey => false,
+            }
+    }
+}
+
+/// Check an object's evidence locally, optionally requiring signatures by `node_key`
+pub fn verify_evidence(evidence: &ObjectEvidence, node_key: Option<&[u8; 32]>) -> Result<VerificationReport> {
+    let path = &evidence.inclusion.path;
+    let object_root = decode_hash(&evidence.state_root.object_root)?;
+
+    let (object_proof_valid, included_in_state_root) = with_proof_engine!(evidence.state_root.hash_algorithm, |engine| {
+        let object_proof_valid = path.proof.object_id == *evidence.object.id()
+            && engine.verify_object_proof(&evidence.object, &path.proof)?;
+        // The path's own root is the node's claim; check against the published state root instead
+        let included_in_state_root = evidence.state_root.slot == evidence.inclusion.slot
+            && engine.verify_object_against_root(&evidence.object, &path.proof, &path.nodes, &object_root)?;
+        (object_proof_valid, included_in_state_root)
+    });
+
+    let signature = match &evidence.signature {
+        None => SignatureCheck::Missing,
+        Some(signature) => {
+            let consistent_root = signature.slot != evidence.state_root.slot
+                || signature.state_root.as_deref() == Some(evidence.state_root.object_root.as_str());
+            if !signature.verify(&evidence.object) || !consistent_root {
+                SignatureCheck::Invalid
+            } else if node_key.is_some_and(|key| signature.node_key != hex::encode(key)) {
+                SignatureCheck::UnexpectedKey
+            } else {
+                SignatureCheck::Valid
+            }
+        }
+    };
+
+    Ok(VerificationReport {
+        object_id: *evidence.object.id(),
+        slot: evidence.inclusion.slot,
+        object_proof_valid,
+        included_in_state_root,
+        signature,
+    })
+}
+
+/// Check that `receipt` proves `document_hash` existed by the receipt's slot
+///
+/// The record must be the attest controller's object at the ID derived from
+/// the hash and commit to the hash itself; its evidence is then checked as
+/// for any object. Only the decoded record is trusted, never the summary
+/// the node sends alongside it.
+pub fn verify_existence(
+    receipt: &ExistenceReceipt,
+    document_hash: &[u8; 32],
+    node_key: Option<&[u8; 32]>,
+) -> Result<bool> {
+    let record_matches = Attestation::from_object(&receipt.evidence.object, &ATTEST_CONTROLLER_ID)
+        .is_some_and(|record| {
+            record.document_hash == hex::encode(document_hash)
+                && record.slot <= receipt.evidence.inclusion.slot
+                && record == receipt.attestation
+        });
+    Ok(record_matches && verify_evidence(&receipt.evidence, node_key)?.is_valid(node_key.is_some()))
+}
+
+/// Check that `proof` commits to the current state of exactly the collection's members
+///
+/// As for existence receipts, the member list is taken from the decoded
+/// collection object, never from the summary sent alongside it.
+#[allow(dead_code)]
+pub fn verify_collection_proof(proof: &CollectionProof, node_key: Option<&[u8; 32]>) -> Result<bool> {
+    let Some(collection) = Collection::from_object(&proof.evidence.object) else {
+        return Ok(false);
+    };
+    if collection != proof.collection
+        || !verify_evidence(&proof.evidence, node_key)?.is_valid(node_key.is_some())
+    {
+        return Ok(false);
+    }
+
+    let object_root = decode_hash(&proof.evidence.state_root.object_root)?;
+    let member_ids = proof.members.iter().map(|path| path.proof.object_id);
+    if !member_ids.eq(collection.members.iter().copied()) {
+        return Ok(false);
+    }
+    with_proof_engine!(proof.evidence.state_root.hash_algorithm, |engine| {
+        for path in &proof.members {
+            if !engine.verify_proof_against_root(&path.proof, &path.nodes, &object_root)? {
+                return Ok(false);
+            }
+        }
+
+        let member_proofs: Vec<_> = proof.members.iter().map(|path| (path.proof.object_id, path.proof.clone())).collect();
+        Ok(hex::encode(engine.group_root(&member_proofs)) == proof.group_root)
+    })
+}
+
+/// Fetch an object's evidence from the node at `rpc_url`
+pub async fn fetch_evidence(rpc_url: &str, object_id: &UnitsObjectId) -> Result<ObjectEvidence> {
+    let client = HttpClientBuilder::default()
+        .build(rpc_url)
+        .with_context(|| format!("Invalid RPC URL {}", rpc_url))?;
+    let id = hex::encode(object_id.bytes());
+
+    let inclusion: ObjectInclusion = client.request("getObjectInclusion", rpc_params![&id]).await?;
+    let state_root: StateRoot = client.request("getStateRoot", rpc_params![inclusion.slot]).await?;
+    let read: ObjectReadResponse = client.request("getObjectWithMetadata", rpc_params![&id]).await?;
+
+    Ok(ObjectEvidence {
+        object: read.object,
+        signature: read.signature,
+        inclusion,
+        state_root,
+    })
+}
+
+/// Arguments for the `verify` subcommand
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// Hex-encoded ID of the object to verify, or the document hash with `--document`
+    object_id: String,
+
+    /// Verify the existence receipt of an attested document hash instead of an object
+    #[arg(long)]
+    document: bool,
+
+    /// JSON-RPC endpoint of the node to check
+    #[arg(long, default_value = "http://127.0.0.1:8080")]
+    rpc_url: String,
+
+    /// Hex-encoded node key the read must be signed with
+    #[arg(long)]
+    node_key: Option<String>,
+}
+
+/// Fetch and verify an object, printing the report and failing if any check fails
+pub async fn run(args: VerifyArgs) -> Result<()> {
+    let node_key = args.node_key.as_deref().map(decode_hash).transpose()?;
+    if args.document {
+        let document_hash = decode_hash(&args.object_id)?;
+        let client = HttpClientBuilder::default()
+            .build(&args.rpc_url)
+            .with_context(|| format!("Invalid RPC URL {}", args.rpc_url))?;
+        let receipt: ExistenceReceipt = client
+            .request("getExistenceReceipt", rpc_params![&args.object_id])
+            .await?;
+        println!("{}", serde_json::to_string_pretty(&receipt.attestation)?);
+        return if verify_existence(&receipt, &document_hash, node_key.as_ref())? {
+            Ok(())
+        } else {
+            Err(anyhow!("Existence receipt for {} failed verification", args.object_id))
+        };
+    }
+
+    let object_id = decode_hash(&args.object_id).map(UnitsObjectId::new)?;
+
+    let evidence = fetch_evidence(&args.rpc_url, &object_id).await?;
+    let report = verify_evidence(&evidence, node_key.as_ref())?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    if report.is_valid(node_key.is_some()) {
+        Ok(())
+    } else {
+        Err(anyhow!("Object {} failed verification", object_id))
+    }
+}
+
+fn decode_hash(value: &str) -> Result<[u8; 32]> {
+    hex::decode(value)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| anyhow!("Expected 32 hex-encoded bytes, got {}", value))
+}
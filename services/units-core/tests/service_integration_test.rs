@@ -6,14 +6,15 @@
 use std::sync::Arc;
 
 use units_core_types::{
-    UnitsObjectId, Transaction, Instruction, CommitmentLevel,
+    UnitsObjectId, Transaction, Instruction, CommitmentLevel, ProofStorage, UnitsStorage,
 };
 use units_core_types::objects::{ObjectType, VMType};
 use units_storage_impl::ConsolidatedUnitsStorage;
 use units_runtime_impl::MockRuntime;
 
 use units_core_service::services::{MinimalServiceFactory, MinimalServiceContainer};
-use units_core_service::config::Config;
+use units_core_service::config::{Config, ScanConfig};
+use units_core_service::RequestContext;
 use units_core_service::service::UnitsService;
 
 #[tokio::test]
@@ -53,6 +54,7 @@ async fn test_units_service_creation() {
     assert_eq!(health.slot, 0);
     assert_eq!(health.object_count, 0);
     assert_eq!(health.pending_transactions, 0);
+    assert_eq!(health.wal_durability, units_storage_impl::WalDurability::Always);
 }
 
 #[tokio::test]
@@ -107,6 +109,10 @@ async fn test_transaction_operations() {
         hash: [99u8; 32],
         instructions: vec![instruction],
         commitment_level: CommitmentLevel::Committed,
+        priority_fee: 0,
+        expected_versions: vec![],
+        sponsorship: None,
+        memo: None,
     };
     
     // Submit transaction - this should work with minimal implementation
@@ -209,4 +215,2342 @@ async fn test_executable_object_creation() {
         ObjectType::Executable(vm_type) => assert_eq!(vm_type, VMType::RiscV),
         _ => panic!("Expected executable object type"),
     }
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_read_replica_snapshot_reads() {
+    use units_core_service::services::ReadSource;
+
+    let runtime = Arc::new(MockRuntime::new());
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let mut config = Config::default();
+    config.replica.enabled = true;
+    config.replica.max_staleness_ms = 60_000;
+    let service = UnitsService::new(storage.clone(), runtime.clone(), config);
+
+    // Writes go to the primary and are invisible until the snapshot refreshes
+    let object_id = UnitsObjectId::new([7u8; 32]);
+    service.create_object(object_id, ObjectType::Data, b"replicated".to_vec(), None, None)
+        .await
+        .expect("Failed to create object");
+    assert!(service.get_object(&object_id).await.is_err());
+
+    service.refresh_replica().await.expect("Failed to refresh replica");
+    let (object, metadata) = service.get_object_with_metadata(&object_id)
+        .await
+        .expect("Failed to read from replica");
+    assert_eq!(object.data(), b"replicated");
+    assert_eq!(metadata.source, ReadSource::Replica);
+    assert_eq!(metadata.slots_behind, 0);
+    assert!(metadata.staleness_ms <= metadata.max_staleness_ms);
+
+    // Later refreshes pick up updates and deletions
+    use units_core_types::{ObjectStorage, UnitsObject, UnitsStorage};
+
+    let other_id = UnitsObjectId::new([8u8; 32]);
+    service.create_object(other_id, ObjectType::Data, b"other".to_vec(), None, None)
+        .await
+        .expect("Failed to create object");
+    storage.objects()
+        .set(&UnitsObject::new_data(object_id, object_id, b"updated".to_vec()), None)
+        .expect("Failed to update object");
+    service.refresh_replica().await.expect("Failed to refresh replica");
+    let (object, metadata) = service.get_object_with_metadata(&object_id)
+        .await
+        .expect("Failed to read from replica");
+    assert_eq!(object.data(), b"updated");
+    assert_eq!(metadata.version, 2);
+    assert_eq!(service.get_object(&other_id).await.expect("Failed to read from replica").data(), b"other");
+
+    storage.objects().delete(&object_id, None).expect("Failed to delete object");
+    service.refresh_replica().await.expect("Failed to refresh replica");
+    assert!(service.get_object(&object_id).await.is_err());
+}
+
+#[tokio::test]
+async fn test_submission_backpressure() {
+    use units_core_service::ServiceError;
+
+    let runtime = Arc::new(MockRuntime::new());
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let mut config = Config::default();
+    config.pipeline.min_batch_size = 1;
+    config.pipeline.initial_batch_size = 1;
+    config.pipeline.max_queued_slots = 1;
+    let service = UnitsService::new(storage.clone(), runtime.clone(), config);
+
+    let transaction = |hash: u8| Transaction {
+        hash: [hash; 32],
+        instructions: vec![],
+        commitment_level: CommitmentLevel::Processing,
+        priority_fee: 0,
+        expected_versions: vec![],
+        sponsorship: None,
+        memo: None,
+    };
+
+    service.submit_transaction(transaction(1)).await.expect("First submission should be admitted");
+    match service.submit_transaction(transaction(2)).await {
+        Err(ServiceError::Backpressure { retry_after_ms }) => assert!(retry_after_ms > 0),
+        other => panic!("Expected backpressure, got {:?}", other),
+    }
+
+    // Executing a slot drains the queue and admits new work again
+    service.advance_slot().await.expect("Failed to advance slot");
+    service.submit_transaction(transaction(2)).await.expect("Submission should be admitted after drain");
+}
+
+#[tokio::test]
+async fn test_rpc_errors_carry_retry_metadata() {
+    use units_core_service::error::NodeLoad;
+    use units_core_service::json_rpc::{JsonRpcServerImpl, RetryMetadata, UnitsJsonRpcApiServer};
+
+    let runtime = Arc::new(MockRuntime::new());
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let mut config = Config::default();
+    config.pipeline.min_batch_size = 1;
+    config.pipeline.initial_batch_size = 1;
+    config.pipeline.max_queued_slots = 1;
+    let server = JsonRpcServerImpl::new(UnitsService::new(storage, runtime, config));
+    let transaction = |hash: u8| Transaction::new(vec![], [hash; 32]);
+
+    // Backpressure is retryable after the queue's own hint
+    UnitsJsonRpcApiServer::submit_transaction(&server, transaction(1)).await.unwrap();
+    let error = UnitsJsonRpcApiServer::submit_transaction(&server, transaction(2)).await.unwrap_err();
+    let metadata = RetryMetadata::try_from(&error).expect("Error should carry retry metadata");
+    assert!(metadata.retryable);
+    assert!(metadata.retry_after_ms.unwrap() > 0);
+    assert_eq!(metadata.node_load, NodeLoad::Saturated);
+
+    // A missing object will not appear by retrying
+    let missing = hex::encode([0x42; 32]);
+    let error = UnitsJsonRpcApiServer::get_object(&server, missing).await.unwrap_err();
+    let metadata = RetryMetadata::try_from(&error).unwrap();
+    assert!(!metadata.retryable);
+    assert_eq!(metadata.retry_after_ms, None);
+}
+
+#[tokio::test]
+async fn test_execution_locks_write_set() {
+    use units_core_service::services::minimal_services::MinimalTransactionService;
+    use units_core_types::{LockManager, ObjectStorage, UnitsStorage};
+
+    let runtime = Arc::new(AppendRuntime(MockRuntime::new()));
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = MinimalTransactionService::new(runtime, storage.clone());
+
+    let controller = UnitsObjectId::new([1; 32]);
+    let target = UnitsObjectId::new([9; 32]);
+    storage.objects().set(&units_core_types::UnitsObject::new_data(controller, controller, vec![]), None).unwrap();
+    storage.objects().set(&units_core_types::UnitsObject::new_data(target, controller, vec![0]), None).unwrap();
+    let transaction = |hash: u8| Transaction {
+        hash: [hash; 32],
+        instructions: vec![Instruction::new(
+            controller,
+            "append".to_string(),
+            vec![target],
+            vec![],
+        )],
+        commitment_level: CommitmentLevel::Processing,
+        priority_fee: 0,
+        expected_versions: vec![],
+        sponsorship: None,
+        memo: None,
+    };
+
+    // While another holder owns the target, execution fails with a lock timeout
+    let guard = storage.locks().lock(&target).expect("Failed to lock target");
+    service.submit_transaction(transaction(1)).await.expect("Submission failed");
+    let receipts = service.execute_next_batch(7).await.expect("Batch failed");
+    assert_eq!(receipts.len(), 1);
+    assert!(!receipts[0].success);
+    assert_eq!(receipts[0].slot, 7);
+    assert!(receipts[0].error_message.as_deref().unwrap().contains("Timed out waiting for lock"));
+    drop(guard);
+
+    // Once released, the transaction executes and leaves the target unlocked
+    service.submit_transaction(transaction(2)).await.expect("Submission failed");
+    let receipts = service.execute_next_batch(8).await.expect("Batch failed");
+    assert!(receipts[0].success, "{:?}", receipts[0].error_message);
+    assert_eq!(storage.objects().get(&target).unwrap().unwrap().data, vec![0, 1]);
+    assert!(!storage.locks().is_locked(&target));
+}
+
+#[tokio::test]
+async fn test_advance_slot_commits_transaction_effects() {
+    let runtime = Arc::new(AppendRuntime(MockRuntime::new()));
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage.clone(), runtime, Config::default());
+
+    let controller = UnitsObjectId::new([1; 32]);
+    let target = UnitsObjectId::new([2; 32]);
+    service.create_object(controller, ObjectType::Data, vec![], None, None).await.unwrap();
+    service.create_object(target, ObjectType::Data, vec![7], Some(controller), None).await.unwrap();
+
+    let instruction = Instruction::new(controller, "append".to_string(), vec![target], vec![]);
+    let hash = service.submit_transaction(Transaction::new(vec![instruction], [5; 32])).await.unwrap();
+    let slot = service.advance_slot().await.expect("Failed to advance slot");
+
+    // The batch's writes are committed and the receipt names its transaction
+    assert_eq!(service.get_object(&target).await.unwrap().data, vec![7, 1]);
+    let receipt = service.get_transaction_receipt(&hash).await.expect("Missing receipt");
+    assert_eq!(receipt.transaction_hash, [5; 32]);
+    assert_eq!(receipt.slot, slot);
+    assert!(receipt.success, "{:?}", receipt.error_message);
+    assert_eq!(receipt.effects.len(), 1);
+    let proof = &receipt.object_proofs[&target];
+    assert_eq!(proof.transaction_hash, Some([5; 32]));
+    assert_eq!(storage.inner().get_latest_proof(&target).unwrap().hash(), proof.hash());
+}
+
+#[tokio::test]
+async fn test_failed_instruction_rolls_back_committed_batch() {
+    let runtime = Arc::new(AppendRuntime(MockRuntime::new()));
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage, runtime, Config::default());
+
+    let (first, second) = (UnitsObjectId::new([1; 32]), UnitsObjectId::new([3; 32]));
+    let shared = UnitsObjectId::new([2; 32]);
+    service.create_object(first, ObjectType::Data, vec![], None, None).await.unwrap();
+    service.create_object(second, ObjectType::Data, vec![], None, None).await.unwrap();
+    service.create_object(shared, ObjectType::Data, vec![0], Some(first), None).await.unwrap();
+    let append = |controller| Instruction::new(controller, "append".to_string(), vec![shared], vec![]);
+
+    // Both instructions commit together, the second seeing the first's write
+    let chained = service.submit_transaction(Transaction::new(vec![append(first), append(second)], [1; 32])).await.unwrap();
+    // The missing controller fails the last instruction after two succeeded
+    let missing = UnitsObjectId::new([9; 32]);
+    let failing = Transaction::new(vec![append(first), append(second), append(missing)], [2; 32]);
+    let failed = service.submit_transaction(failing).await.unwrap();
+    service.advance_slot().await.expect("Failed to advance slot");
+
+    let receipt = service.get_transaction_receipt(&chained).await.unwrap();
+    assert!(receipt.success, "{:?}", receipt.error_message);
+    assert_eq!(receipt.effects.len(), 1);
+    assert_eq!(receipt.effects[0].after_image.as_ref().unwrap().data, vec![0, 1, 1]);
+
+    let receipt = service.get_transaction_receipt(&failed).await.unwrap();
+    assert!(!receipt.success);
+    assert!(receipt.effects.is_empty() && receipt.object_proofs.is_empty());
+    assert_eq!(service.get_object(&shared).await.unwrap().data, vec![0, 1, 1]);
+}
+
+#[tokio::test]
+async fn test_slot_timer_advances_slots() {
+    let runtime = Arc::new(AppendRuntime(MockRuntime::new()));
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let mut config = Config::default();
+    config.slot_timer.interval_ms = 10;
+    let service = UnitsService::new(storage, runtime, config);
+
+    let controller = UnitsObjectId::new([1; 32]);
+    let target = UnitsObjectId::new([2; 32]);
+    service.create_object(controller, ObjectType::Data, vec![], None, None).await.unwrap();
+    service.create_object(target, ObjectType::Data, vec![7], Some(controller), None).await.unwrap();
+    let instruction = Instruction::new(controller, "append".to_string(), vec![target], vec![]);
+    let hash = service.submit_transaction(Transaction::new(vec![instruction], [5; 32])).await.unwrap();
+
+    // Nobody calls advance_slot; the timer started with the service executes the batch
+    service.start().await.unwrap();
+    let mut receipt = None;
+    for _ in 0..200 {
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        if let Ok(found) = service.get_transaction_receipt(&hash).await {
+            receipt = Some(found);
+            break;
+        }
+    }
+    let receipt = receipt.expect("Slot timer never executed the transaction");
+    assert!(receipt.success, "{:?}", receipt.error_message);
+    assert!(receipt.slot >= 1);
+    assert_eq!(service.get_object(&target).await.unwrap().data, vec![7, 1]);
+}
+
+#[tokio::test]
+async fn test_state_root_and_object_verification() {
+    use units_core_service::service::ObjectRootPath;
+
+    let runtime = Arc::new(MockRuntime::new());
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage.clone(), runtime, Config::default());
+
+    let ids: Vec<UnitsObjectId> = (1..=3u8).map(|seed| UnitsObjectId::new([seed; 32])).collect();
+    for id in &ids {
+        service
+            .create_object(*id, ObjectType::Data, vec![1, 2, 3], None, None)
+            .await
+            .expect("Failed to create object");
+    }
+
+    let slot = service.advance_slot().await.expect("Failed to advance slot");
+    let root = service.get_state_root(slot).await.expect("Missing state root");
+    assert_eq!(root.slot, slot);
+    // The three objects and the slot's summary
+    assert_eq!(root.object_count, 4);
+    assert_eq!(root.receipt_count, 0);
+    assert!(root.prev_state_proof_hash.is_none());
+    assert!(service.get_state_root(slot + 1).await.is_err());
+
+    // An object and its path verify against the published root
+    let object = service.get_object(&ids[1]).await.expect("Failed to get object");
+    let proof = storage.inner().get_latest_proof(&ids[1]).unwrap();
+    let state_proof = storage.proofs().get_state_proof(slot).unwrap().unwrap();
+    let mut path = ObjectRootPath {
+        nodes: storage.committed_path(&state_proof, &proof).unwrap().unwrap(),
+        proof,
+        root: root.object_root.clone(),
+    };
+    let verification = service.verify_object_against_root(&object, &path).await.unwrap();
+    assert!(verification.valid);
+
+    // The same path does not verify a different root
+    path.root = hex::encode([0u8; 32]);
+    assert!(!service.verify_object_against_root(&object, &path).await.unwrap().valid);
+    path.root = "zz".to_string();
+    assert!(service.verify_object_against_root(&object, &path).await.is_err());
+
+    // The next slot chains to this one
+    let next = service.advance_slot().await.expect("Failed to advance slot");
+    let next_root = service.get_state_root(next).await.unwrap();
+    assert_eq!(next_root.prev_state_proof_hash, Some(root.state_proof_hash));
+}
+
+#[tokio::test]
+async fn test_ephemeral_objects_expire_with_slots() {
+    let runtime = Arc::new(MockRuntime::new());
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage, runtime, Config::default());
+
+    let slot = service.advance_slot().await.expect("Failed to advance slot");
+    let (data, scratch) = (UnitsObjectId::new([1; 32]), UnitsObjectId::new([2; 32]));
+    service.create_object(data, ObjectType::Data, vec![1], None, None).await.unwrap();
+    let expiry = ObjectType::Ephemeral { expires_at_slot: slot + 2 };
+    service.create_object(scratch, expiry, vec![2], None, None).await.unwrap();
+
+    // Live but left out of the state root until it expires, which holds
+    // only the data object and both slot summaries
+    let next = service.advance_slot().await.unwrap();
+    assert_eq!(service.get_state_root(next).await.unwrap().object_count, 3);
+    assert!(service.get_object(&scratch).await.is_ok());
+
+    service.advance_slot().await.unwrap();
+    assert!(service.get_object(&scratch).await.is_err());
+    assert!(service.get_object(&data).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_priority_ordering_and_fee_estimate() {
+    let runtime = Arc::new(MockRuntime::new());
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let mut config = Config::default();
+    config.pipeline.min_batch_size = 2;
+    config.pipeline.initial_batch_size = 2;
+    config.pipeline.max_batch_size = 2;
+    let service = UnitsService::new(storage, runtime, config);
+
+    // Nothing is waiting, so inclusion is free
+    let estimate = service.get_fee_estimate(1).await.unwrap();
+    assert_eq!(estimate.priority_fee, 0);
+    assert_eq!(estimate.batch_size, 2);
+
+    for (hash, fee) in [(1u8, 5u64), (2, 1), (3, 9), (4, 5)] {
+        service
+            .submit_transaction(Transaction::new(vec![], [hash; 32]).with_priority_fee(fee))
+            .await
+            .expect("Submission failed");
+    }
+
+    // Four queued at two per slot: next-slot inclusion must beat the second highest fee
+    let estimate = service.get_fee_estimate(1).await.unwrap();
+    assert_eq!(estimate.queue_depth, 4);
+    assert_eq!(estimate.priority_fee, 6);
+    assert_eq!(service.get_fee_estimate(2).await.unwrap().priority_fee, 2);
+
+    // The first slot takes the two highest fees and was congested
+    service.advance_slot().await.expect("Failed to advance slot");
+    let estimate = service.get_fee_estimate(3).await.unwrap();
+    assert_eq!(estimate.slots_sampled, 1);
+    assert_eq!(estimate.congested_slots, 1);
+    assert_eq!(estimate.recent_fees.min, 5);
+    assert_eq!(estimate.recent_fees.max, 9);
+    assert_eq!(estimate.priority_fee, 5);
+}
+
+/// Runtime whose instructions append a byte to every target object
+struct AppendRuntime(MockRuntime);
+
+impl units_core_types::Runtime for AppendRuntime {
+    fn get_vm_executor(&self, vm_type: VMType) -> Option<Box<dyn units_core_types::VMExecutor>> {
+        self.0.get_vm_executor(vm_type)
+    }
+
+    fn execute_transaction(&self, transaction: Transaction) -> units_core_types::TransactionReceipt {
+        self.0.execute_transaction(transaction)
+    }
+
+    fn execute_instruction_in_class(
+        &self,
+        instruction: &Instruction,
+        objects: std::collections::HashMap<UnitsObjectId, units_core_types::UnitsObject>,
+        _class: units_core_types::ResourceClass,
+        _slot: u64,
+        _timestamp: u64,
+    ) -> Result<units_core_types::ExecutionOutput, units_core_types::VMExecutionError> {
+        if !objects.contains_key(&instruction.controller_id) {
+            return Err(units_core_types::VMExecutionError::InvalidBytecode("Controller object not found".to_string()));
+        }
+        let effects = instruction
+            .target_objects
+            .iter()
+            .filter_map(|id| objects.get(id))
+            .map(|before| {
+                let mut after = before.clone();
+                after.data.push(1);
+                units_core_types::ObjectEffect::modification(before.clone(), after)
+            })
+            .collect();
+        Ok(units_core_types::ExecutionOutput { effects, ..Default::default() })
+    }
+
+    fn get_transaction(&self, hash: &units_core_types::TransactionHash) -> Option<Transaction> {
+        self.0.get_transaction(hash)
+    }
+
+    fn get_transaction_receipt(&self, hash: &units_core_types::TransactionHash) -> Option<units_core_types::TransactionReceipt> {
+        self.0.get_transaction_receipt(hash)
+    }
+
+    fn rollback_transaction(&self, hash: &units_core_types::TransactionHash) -> Result<bool, units_core_types::error::RuntimeError> {
+        self.0.rollback_transaction(hash)
+    }
+
+    fn get_verifier(&self) -> &dyn units_core_types::Verifier {
+        self.0.get_verifier()
+    }
+}
+
+#[tokio::test]
+async fn test_sandbox_isolates_execution() {
+    let runtime = Arc::new(AppendRuntime(MockRuntime::new()));
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let mut config = Config::default();
+    config.sandbox.max_namespaces = 1;
+    let service = UnitsService::new(storage, runtime, config);
+
+    let controller = UnitsObjectId::new([1; 32]);
+    let target = UnitsObjectId::new([2; 32]);
+    service.create_object(controller, ObjectType::Data, vec![], None, None).await.unwrap();
+    service.create_object(target, ObjectType::Data, vec![7], Some(controller), None).await.unwrap();
+
+    let sandbox = service.create_sandbox().await.expect("Failed to create sandbox");
+    assert!(service.create_sandbox().await.is_err(), "Namespace limit not enforced");
+
+    // Effects land in the sandbox only
+    let instruction = Instruction::new(controller, "append".to_string(), vec![target], vec![]);
+    let receipt = service
+        .sandbox_execute_transaction(&RequestContext::new(), &sandbox.namespace, Transaction::new(vec![instruction.clone(); 2], [1; 32]))
+        .await
+        .unwrap();
+    assert!(receipt.success);
+    assert_eq!(receipt.effects.len(), 1);
+    assert_eq!(service.sandbox_get_object(&sandbox.namespace, &target).await.unwrap().object.data, vec![7, 1, 1]);
+    assert_eq!(service.get_object(&target).await.unwrap().data, vec![7]);
+
+    // A failing transaction leaves the sandbox as it was
+    let missing = Instruction::new(UnitsObjectId::new([9; 32]), "append".to_string(), vec![target], vec![]);
+    let receipt = service
+        .sandbox_execute_transaction(&RequestContext::new(), &sandbox.namespace, Transaction::new(vec![instruction, missing], [2; 32]))
+        .await
+        .unwrap();
+    assert!(!receipt.success);
+    assert!(receipt.effects.is_empty());
+
+    let changes = service.sandbox_get_changes(&sandbox.namespace).await.unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].object.as_ref().unwrap().data, vec![7, 1, 1]);
+    assert_eq!(service.sandbox_get_receipts(&sandbox.namespace).await.unwrap().len(), 2);
+
+    // Discarding frees the namespace
+    assert!(service.discard_sandbox(&sandbox.namespace).await.unwrap());
+    assert!(!service.discard_sandbox(&sandbox.namespace).await.unwrap());
+    assert!(service.sandbox_get_object(&sandbox.namespace, &target).await.is_err());
+    assert!(service.create_sandbox().await.is_ok());
+}
+
+#[tokio::test]
+async fn test_multi_instruction_transaction_is_atomic() {
+    let runtime = Arc::new(AppendRuntime(MockRuntime::new()));
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage, runtime, Config::default());
+
+    let (first, second) = (UnitsObjectId::new([1; 32]), UnitsObjectId::new([3; 32]));
+    let (shared, own) = (UnitsObjectId::new([2; 32]), UnitsObjectId::new([4; 32]));
+    service.create_object(first, ObjectType::Data, vec![], None, None).await.unwrap();
+    service.create_object(second, ObjectType::Data, vec![], None, None).await.unwrap();
+    service.create_object(shared, ObjectType::Data, vec![0], Some(first), None).await.unwrap();
+    service.create_object(own, ObjectType::Data, vec![0], Some(second), None).await.unwrap();
+    let sandbox = service.create_sandbox().await.unwrap();
+
+    // Two controllers in one transaction; the second sees the first's write
+    let instructions = vec![
+        Instruction::new(first, "append".to_string(), vec![shared], vec![]),
+        Instruction::new(second, "append".to_string(), vec![shared, own], vec![]),
+    ];
+    let receipt = service
+        .sandbox_execute_transaction(&RequestContext::new(), &sandbox.namespace, Transaction::new(instructions, [1; 32]))
+        .await
+        .unwrap();
+    assert!(receipt.success, "{:?}", receipt.error_message);
+    assert_eq!(receipt.instruction_metrics.len(), 2);
+    // One net effect per object, from its state before the transaction
+    assert_eq!(receipt.effects.len(), 2);
+    assert_eq!(receipt.effects[0].before_image.as_ref().unwrap().data, vec![0]);
+    assert_eq!(receipt.effects[0].after_image.as_ref().unwrap().data, vec![0, 1, 1]);
+    assert_eq!(receipt.object_proofs.len(), 2);
+    assert!(receipt.effects.iter().all(|effect| effect.matches_proof(&receipt.object_proofs[&effect.object_id])));
+    assert_eq!(service.sandbox_get_object(&sandbox.namespace, &shared).await.unwrap().object.data, vec![0, 1, 1]);
+
+    // A later failure undoes the earlier instructions and names the culprit
+    let instructions = vec![
+        Instruction::new(first, "append".to_string(), vec![shared], vec![]),
+        Instruction::new(UnitsObjectId::new([9; 32]), "append".to_string(), vec![own], vec![]),
+    ];
+    let receipt = service
+        .sandbox_execute_transaction(&RequestContext::new(), &sandbox.namespace, Transaction::new(instructions, [2; 32]))
+        .await
+        .unwrap();
+    assert!(!receipt.success);
+    assert!(receipt.effects.is_empty() && receipt.object_proofs.is_empty());
+    assert!(receipt.error_message.unwrap().starts_with("Instruction 1 (append) failed"));
+    assert_eq!(service.sandbox_get_object(&sandbox.namespace, &shared).await.unwrap().object.data, vec![0, 1, 1]);
+}
+
+#[tokio::test]
+async fn test_expected_versions_guard_execution() {
+    let runtime = Arc::new(AppendRuntime(MockRuntime::new()));
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage, runtime, Config::default());
+
+    let controller = UnitsObjectId::new([1; 32]);
+    let target = UnitsObjectId::new([2; 32]);
+    service.create_object(controller, ObjectType::Data, vec![], None, None).await.unwrap();
+    service.create_object(target, ObjectType::Data, vec![0], Some(controller), None).await.unwrap();
+    let (_, metadata) = service.get_object_with_metadata(&target).await.unwrap();
+    assert_eq!(metadata.version, 1);
+
+    let sandbox = service.create_sandbox().await.unwrap();
+    let read = service.sandbox_get_object(&sandbox.namespace, &target).await.unwrap();
+    assert_eq!(read.version, 1);
+
+    // Two clients build on the same read; only the first one commits
+    let instruction = Instruction::new(controller, "append".to_string(), vec![target], vec![]);
+    let transaction = |hash: u8| {
+        Transaction::new(vec![instruction.clone()], [hash; 32]).with_expected_version(target, read.version)
+    };
+    let receipt = service.sandbox_execute_transaction(&RequestContext::new(), &sandbox.namespace, transaction(1)).await.unwrap();
+    assert!(receipt.success, "{:?}", receipt.error_message);
+
+    let receipt = service.sandbox_execute_transaction(&RequestContext::new(), &sandbox.namespace, transaction(2)).await.unwrap();
+    assert!(!receipt.success);
+    assert!(receipt.effects.is_empty());
+    assert_eq!(
+        receipt.error_message.as_deref(),
+        Some(format!("Version precondition failed for {}: expected 1, found 2", target).as_str())
+    );
+
+    let current = service.sandbox_get_object(&sandbox.namespace, &target).await.unwrap();
+    assert_eq!((current.object.data, current.version), (vec![0, 1], 2));
+}
+
+#[tokio::test]
+async fn test_module_registry_lists_deployed_controllers() {
+    let runtime = Arc::new(MockRuntime::new());
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage, runtime, Config::default());
+    assert!(service.list_controllers().await.unwrap().is_empty());
+
+    let controller = UnitsObjectId::new([4; 32]);
+    let deploy = |code: &'static [u8]| {
+        service.create_object(controller, ObjectType::Executable(VMType::RiscV), code.to_vec(), None, None)
+    };
+    deploy(b"v1").await.unwrap();
+    service
+        .create_object(UnitsObjectId::new([5; 32]), ObjectType::Data, vec![1], Some(controller), None)
+        .await
+        .unwrap();
+
+    // Only executables are registered
+    let modules = service.list_controllers().await.unwrap();
+    assert_eq!(modules.len(), 1);
+    assert_eq!(modules[0].controller_id, controller);
+    assert_eq!(modules[0].version, 1);
+
+    let entry = service.set_controller_abi(&controller, Some("https://abi.example/token.json".into())).await.unwrap();
+    assert_eq!(entry.abi_location.as_deref(), Some("https://abi.example/token.json"));
+    assert!(service.set_controller_abi(&UnitsObjectId::new([5; 32]), None).await.is_err());
+
+    let invalid_username = units_core_types::ModuleErrorCode {
+        name: "ERROR_INVALID_USERNAME".to_string(),
+        message: Some("Invalid username".to_string()),
+    };
+    let codes = std::collections::BTreeMap::from([(1001, invalid_username)]);
+    let entry = service.set_controller_error_codes(&controller, codes).await.unwrap();
+    assert_eq!(entry.error_codes[&1001].name, "ERROR_INVALID_USERNAME");
+
+    // Upgrades bump the version and keep the ABI location
+    deploy(b"v2").await.unwrap();
+    let modules = service.list_controllers().await.unwrap();
+    assert_eq!(modules[0].version, 2);
+    assert_ne!(modules[0].code_hash, entry.code_hash);
+    assert!(modules[0].abi_location.is_some());
+    assert_eq!(modules[0].error_codes.len(), 1);
+}
+
+#[tokio::test]
+async fn test_signed_responses() {
+    use units_core_service::signing::{verify_signature, NodeSigner};
+    use units_core_types::{ProofStorage, UnitsStorage};
+
+    // RFC 8032 test 1
+    let seed = hex::decode("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60").unwrap();
+    let signer = NodeSigner::from_seed(seed.try_into().unwrap());
+    assert_eq!(
+        hex::encode(signer.public_key()),
+        "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"
+    );
+    let signature = signer.sign(b"");
+    assert_eq!(
+        hex::encode(signature),
+        concat!(
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155",
+            "5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+        )
+    );
+    assert!(verify_signature(&signer.public_key(), b"", &signature));
+    assert!(!verify_signature(&signer.public_key(), b"x", &signature));
+
+    let runtime = Arc::new(MockRuntime::new());
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let unsigned = UnitsService::new(storage.clone(), runtime.clone(), Config::default());
+    assert!(!unsigned.node_identity().await.unwrap().signing_enabled);
+    assert!(unsigned.sign_response(&1u8).await.unwrap().is_none());
+
+    let service = UnitsService::new(storage.clone(), runtime, Config::default())
+        .with_signer(Arc::new(NodeSigner::from_seed([7; 32])));
+    let id = UnitsObjectId::new([1; 32]);
+    let object = service.create_object(id, ObjectType::Data, vec![1, 2], None, None).await.unwrap();
+
+    // Before any slot is committed there is no state root to attest to
+    let attestation = service.sign_response(&object).await.unwrap().unwrap();
+    assert_eq!(attestation.slot, 0);
+    assert!(attestation.state_root.is_none());
+    assert!(attestation.verify(&object));
+
+    service.advance_slot().await.unwrap();
+    let attestation = service.sign_response(&object).await.unwrap().unwrap();
+    assert_eq!(attestation.slot, 1);
+    assert_eq!(attestation.state_root, Some(service.get_state_root(1).await.unwrap().object_root));
+    assert_eq!(Some(attestation.node_key.clone()), service.node_identity().await.unwrap().node_key);
+    assert!(attestation.verify(&object));
+
+    // Tampering with the response or the attested state breaks verification
+    let mut altered = object.clone();
+    altered.data.push(3);
+    assert!(!attestation.verify(&altered));
+    let mut moved = attestation.clone();
+    moved.slot = 2;
+    assert!(!moved.verify(&object));
+
+    // The slot's state root is signed too, and verifies against the state proof
+    let root = service.get_state_root(1).await.unwrap();
+    let state_proof = storage.proofs().get_state_proof(1).unwrap().unwrap();
+    let signature = units_proofs::StateProofSignature {
+        signer: NodeSigner::from_seed([7; 32]).public_key(),
+        signature: hex::decode(root.signature.unwrap()).unwrap(),
+    };
+    let engine = units_proofs::ProofEngine::new();
+    let trusted = [signature.signer];
+    assert_eq!(
+        engine.verify_state_proof_signature(&state_proof, &signature, &trusted),
+        units_core_types::VerificationResult::Valid
+    );
+}
+
+#[tokio::test]
+async fn test_admin_operations_need_role_and_confirmation() {
+    use units_core_service::services::{AdminAuth, AdminOperation};
+
+    let runtime = Arc::new(MockRuntime::new());
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let mut config = Config::default();
+    config.admin.enabled = true;
+    config.admin.api_key = Some("secret".to_string());
+    let service = UnitsService::new(storage, runtime, config);
+
+    let controller = UnitsObjectId::new([1; 32]);
+    service.create_object(controller, ObjectType::Data, vec![1], None, None).await.unwrap();
+    for _ in 0..3 {
+        service.advance_slot().await.unwrap();
+    }
+
+    let auth = |dry_run: bool, confirmation: Option<String>| AdminAuth {
+        api_key: "secret".to_string(),
+        dry_run,
+        confirmation,
+    };
+    let prune = AdminOperation::Prune { before_slot: 3 };
+
+    // Wrong key is rejected outright
+    let intruder = AdminAuth { api_key: "guess".to_string(), dry_run: true, confirmation: None };
+    assert!(service.admin(&intruder, AdminOperation::Scrub).await.is_err());
+
+    // Destructive operations need a token from a dry run of the same operation
+    assert!(service.admin(&auth(false, None), prune.clone()).await.is_err());
+    let plan = service.admin(&auth(true, None), prune.clone()).await.unwrap();
+    assert_eq!(plan.affected, 2);
+    let token = plan.confirmation_token.expect("Dry run issued no token");
+    assert!(service.get_state_root(1).await.is_ok(), "Dry run changed state");
+
+    let other = AdminOperation::Prune { before_slot: 4 };
+    assert!(service.admin(&auth(false, Some(token.clone())), other).await.is_err());
+    let token = service.admin(&auth(true, None), prune.clone()).await.unwrap().confirmation_token;
+    let report = service.admin(&auth(false, token.clone()), prune.clone()).await.unwrap();
+    assert_eq!(report.affected, 2);
+    assert!(service.get_state_root(2).await.is_err());
+    assert!(service.get_state_root(3).await.is_ok());
+    assert!(service.admin(&auth(false, token), prune).await.is_err(), "Token was reusable");
+
+    // Non-destructive operations run without a token
+    // The controller and the summaries of the three slots
+    let scrub = service.admin(&auth(false, None), AdminOperation::Scrub).await.unwrap();
+    assert_eq!(scrub.affected, 4);
+    assert!(scrub.details.is_empty());
+
+    // Paused controllers reject new transactions until resumed
+    let pause = AdminOperation::PauseController { controller_id: controller };
+    assert_eq!(service.admin(&auth(false, None), pause).await.unwrap().affected, 1);
+    let instruction = Instruction::new(controller, "noop".to_string(), vec![], vec![]);
+    assert!(service.submit_transaction(Transaction::new(vec![instruction.clone()], [1; 32])).await.is_err());
+
+    let resume = AdminOperation::ResumeController { controller_id: controller };
+    assert_eq!(service.admin(&auth(false, None), resume).await.unwrap().affected, 1);
+    assert!(service.submit_transaction(Transaction::new(vec![instruction], [1; 32])).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_missing_state_proofs_are_regenerated() {
+    use units_core_service::services::{AdminAuth, AdminOperation};
+    use units_core_types::{ProofStorage, UnitsStorage};
+
+    let runtime = Arc::new(MockRuntime::new());
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let mut config = Config::default();
+    config.admin.enabled = true;
+    config.admin.api_key = Some("secret".to_string());
+    let service = UnitsService::new(storage.clone(), runtime, config);
+
+    service.create_object(UnitsObjectId::new([1; 32]), ObjectType::Data, vec![1], None, None).await.unwrap();
+    for _ in 0..3 {
+        service.advance_slot().await.unwrap();
+    }
+    // Read from storage, as the service caches the answer until the next slot
+    let committed = storage.proofs().get_state_proof(2).unwrap().unwrap();
+    let committed_receipts = units_proofs::ProofEngine::new().state_proof_data(&committed).unwrap().transaction_count;
+
+    // Lose slot 2's state proof
+    let first = storage.proofs().get_state_proof(1).unwrap().unwrap();
+    storage.proofs().prune_state_proofs(3);
+    storage.proofs().store_state_proof(&first).unwrap();
+
+    let auth = AdminAuth { api_key: "secret".to_string(), dry_run: true, confirmation: None };
+    let regenerate = AdminOperation::RegenerateStateProofs { start_slot: 0, end_slot: 3 };
+    let plan = service.admin(&auth, regenerate).await.unwrap();
+    assert_eq!(plan.details, vec!["0", "2"]);
+    assert!(plan.confirmation_token.is_none());
+    assert!(service.get_state_root(2).await.is_err(), "Dry run regenerated a proof");
+
+    // Startup fills the gap between the earliest and latest slots
+    service.start().await.unwrap();
+    // Slot summaries are written outside transactions, so the regenerated
+    // proof also covers the one written at slot 3
+    let regenerated = service.get_state_root(2).await.unwrap();
+    assert_eq!(regenerated.prev_state_proof_hash, committed.prev_state_proof_hash.map(hex::encode));
+    assert_eq!(regenerated.object_count, committed.object_ids.len() as u64 + 1);
+    assert_eq!(regenerated.receipt_count, committed_receipts);
+    assert!(service.get_state_root(0).await.is_err());
+}
+
+#[tokio::test]
+async fn test_preflight_refuses_unrepaired_storage_and_repairs_otherwise() {
+    use units_core_service::error::ServiceError;
+    use units_core_service::services::preflight::PreflightCheck;
+    use units_core_types::{LockManager, UnitsStorage};
+
+    let runtime = Arc::new(MockRuntime::new());
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage.clone(), runtime.clone(), Config::default());
+    service.create_object(UnitsObjectId::new([1; 32]), ObjectType::Data, vec![1], None, None).await.unwrap();
+    service.advance_slot().await.unwrap();
+
+    // A lock left behind by a guard that never dropped
+    std::mem::forget(storage.locks().lock(&UnitsObjectId::new([1; 32])).unwrap());
+
+    let mut config = Config::default();
+    config.preflight.repair = false;
+    let cautious = UnitsService::new(storage.clone(), runtime.clone(), config);
+    match cautious.start().await {
+        Err(ServiceError::PreflightFailed { report }) => {
+            assert_eq!(report.findings.len(), 1);
+            assert_eq!(report.findings[0].check, PreflightCheck::LockTable);
+            assert!(!report.findings[0].repaired);
+            assert!(report.to_string().contains("1 objects locked"));
+        }
+        other => panic!("expected preflight failure, got {:?}", other),
+    }
+    assert!(storage.locks().is_locked(&UnitsObjectId::new([1; 32])));
+
+    let service = UnitsService::new(storage.clone(), runtime, Config::default());
+    service.start().await.unwrap();
+    assert!(storage.locks().held().is_empty());
+}
+
+#[tokio::test]
+async fn test_maintenance_takes_over_retention_and_can_be_paused() {
+    use units_core_service::services::{AdminAuth, AdminOperation};
+
+    let runtime = Arc::new(MockRuntime::new());
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let mut config = Config::default();
+    config.admin.enabled = true;
+    config.admin.api_key = Some("secret".to_string());
+    config.retention.state_proof_slots = Some(1);
+    config.retention.receipt_slots = Some(1);
+    config.maintenance.enabled = true;
+    let service = UnitsService::new(storage, runtime, config);
+
+    // Retention waits for the scheduler instead of running at every slot
+    for _ in 0..3 {
+        service.advance_slot().await.unwrap();
+    }
+    assert!(service.get_state_root(1).await.is_ok());
+
+    let auth = AdminAuth { api_key: "secret".to_string(), dry_run: false, confirmation: None };
+    assert!(!service.maintenance_status(&auth).await.unwrap().paused);
+    assert_eq!(service.admin(&auth, AdminOperation::PauseMaintenance).await.unwrap().affected, 1);
+    assert_eq!(service.admin(&auth, AdminOperation::PauseMaintenance).await.unwrap().affected, 0);
+    assert!(service.maintenance_status(&auth).await.unwrap().paused);
+    assert_eq!(service.admin(&auth, AdminOperation::ResumeMaintenance).await.unwrap().affected, 1);
+
+    let intruder = AdminAuth { api_key: "guess".to_string(), ..auth };
+    assert!(service.maintenance_status(&intruder).await.is_err());
+}
+
+#[tokio::test]
+async fn test_controller_policy_gates_admission() {
+    use units_core_service::config::ControllerPolicy;
+    use units_core_service::error::ServiceError;
+    use units_core_service::services::{AdminAuth, AdminOperation};
+
+    let allowed = UnitsObjectId::new([1; 32]);
+    let other = UnitsObjectId::new([2; 32]);
+    let runtime = Arc::new(MockRuntime::new());
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let mut config = Config::default();
+    config.admin.enabled = true;
+    config.admin.api_key = Some("secret".to_string());
+    config.controllers.allowed = Some(vec![allowed]);
+    let service = UnitsService::new(storage, runtime, config);
+
+    let transaction = |controller: UnitsObjectId, fee_payer: u8| {
+        Transaction::new(vec![Instruction::new(controller, "noop".to_string(), vec![], vec![])], [fee_payer; 32])
+    };
+    assert!(service.submit_transaction(transaction(allowed, 1)).await.is_ok());
+
+    // Unlisted controllers are refused and leave a failed receipt
+    let rejected = transaction(other, 2);
+    let error = service.submit_transaction(rejected.clone()).await.unwrap_err();
+    assert!(matches!(error, ServiceError::ControllerNotAllowed { controller_id } if controller_id == other));
+    let receipt = service.get_transaction_receipt(&rejected.hash).await.unwrap();
+    assert!(!receipt.success);
+    assert_eq!(receipt.commitment_level, CommitmentLevel::Failed);
+    assert_eq!(receipt.error_message, Some(error.to_string()));
+
+    // Opening the allow list at runtime admits the controller, but deny wins
+    let auth = AdminAuth { api_key: "secret".to_string(), dry_run: false, confirmation: None };
+    let open = AdminOperation::SetControllerPolicy {
+        policy: ControllerPolicy { allowed: None, denied: vec![allowed] },
+    };
+    let report = service.admin(&auth, open).await.unwrap();
+    assert_eq!(report.affected, 2);
+    assert!(service.submit_transaction(transaction(other, 3)).await.is_ok());
+    assert!(matches!(
+        service.submit_transaction(transaction(allowed, 4)).await,
+        Err(ServiceError::ControllerNotAllowed { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_token_balance_queries() {
+    use token::{BalanceData, TokenData};
+    use units_core_types::constants::TOKEN_CONTROLLER_ID;
+
+    let runtime = Arc::new(MockRuntime::new());
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage, runtime, Config::default());
+
+    let token_id = UnitsObjectId::new([0x70; 32]);
+    let token = TokenData {
+        total_supply: 1_000,
+        decimals: 6,
+        name: "Test".to_string(),
+        symbol: "TST".to_string(),
+        is_frozen: false,
+    };
+    let token_data = units_kernel_sdk::encode_versioned(&token).unwrap();
+    service.create_object(token_id, ObjectType::Data, token_data, Some(TOKEN_CONTROLLER_ID), None).await.unwrap();
+
+    // Balance objects sit at arbitrary IDs; the query finds them by owner
+    let owners = [UnitsObjectId::new([0xa1; 32]), UnitsObjectId::new([0xa2; 32]), UnitsObjectId::new([0xa3; 32])];
+    for (i, (owner, amount)) in owners.iter().zip([300u64, 700, 0]).enumerate() {
+        let balance = BalanceData { token_id: token_id.into(), owner_id: (*owner).into(), amount };
+        let id = UnitsObjectId::new([0xb0 + i as u8; 32]);
+        service.create_object(id, ObjectType::Data, borsh::to_vec(&balance).unwrap(), Some(TOKEN_CONTROLLER_ID), None).await.unwrap();
+    }
+
+    let balance = service.get_token_balance(&RequestContext::new(), &owners[0], &token_id, None).await.unwrap();
+    assert_eq!((balance.amount, balance.decimals, balance.symbol.as_str()), (300, 6, "TST"));
+    let stranger = UnitsObjectId::new([0xee; 32]);
+    assert_eq!(service.get_token_balance(&RequestContext::new(), &stranger, &token_id, None).await.unwrap().amount, 0);
+    assert!(service.get_token_balance(&RequestContext::new(), &owners[0], &stranger, None).await.is_err());
+
+    // Holders are ordered by balance and exclude empty accounts
+    let holders = service.get_token_holders(&RequestContext::new(), &token_id, 0, None).await.unwrap();
+    assert_eq!(holders.total_holders, 2);
+    let listed: Vec<_> = holders.holders.iter().map(|h| (h.owner_id, h.amount)).collect();
+    assert_eq!(listed, vec![(owners[1], 700), (owners[0], 300)]);
+    assert!(service.get_token_holders(&RequestContext::new(), &token_id, 1, None).await.unwrap().holders.is_empty());
+
+    // A scan cut short by the row limit resumes from its cursor
+    let bounded = || RequestContext::new().with_scan_limits(ScanConfig { max_rows: 2, ..ScanConfig::default() });
+    let first = service.get_token_balance(&bounded(), &owners[1], &token_id, None).await.unwrap();
+    let mut scan_cursor = first.scan_cursor.clone();
+    assert!(scan_cursor.is_some());
+    let mut amount = first.amount;
+    while let Some(cursor) = scan_cursor {
+        let next = service.get_token_balance(&bounded(), &owners[1], &token_id, Some(&cursor)).await.unwrap();
+        amount += next.amount;
+        scan_cursor = next.scan_cursor;
+    }
+    assert_eq!(amount, 700);
+}
+
+#[tokio::test]
+async fn test_hot_controller_scans_are_indexed() {
+    use token::BalanceData;
+    use units_core_service::services::{AdminAuth, AdminOperation};
+    use units_core_types::constants::TOKEN_CONTROLLER_ID;
+    use units_storage_impl::{IndexAdvisorConfig, QueryPattern};
+
+    let runtime = Arc::new(MockRuntime::new());
+    let advisor = IndexAdvisorConfig { min_queries: 2, min_average_scanned: 4, auto_create: true };
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory().with_index_advisor(advisor));
+    let mut config = Config::default();
+    config.admin.enabled = true;
+    config.admin.api_key = Some("secret".to_string());
+    let service = UnitsService::new(storage.clone(), runtime, config);
+    let auth = AdminAuth { api_key: "secret".to_string(), dry_run: false, confirmation: None };
+
+    let token = token::TokenData {
+        total_supply: 1_000,
+        decimals: 0,
+        name: "Test".to_string(),
+        symbol: "TST".to_string(),
+        is_frozen: false,
+    };
+    let token_id = UnitsObjectId::new([0x70; 32]);
+    let token_data = units_kernel_sdk::encode_versioned(&token).unwrap();
+    service.create_object(token_id, ObjectType::Data, token_data, Some(TOKEN_CONTROLLER_ID), None).await.unwrap();
+    let owner = UnitsObjectId::new([0xa1; 32]);
+    let balance = |amount: u64| borsh::to_vec(&BalanceData { token_id: token_id.into(), owner_id: owner.into(), amount }).unwrap();
+    service.create_object(UnitsObjectId::new([0xb0; 32]), ObjectType::Data, balance(5), Some(TOKEN_CONTROLLER_ID), None).await.unwrap();
+    for byte in 0x10..0x18 {
+        service.create_object(UnitsObjectId::new([byte; 32]), ObjectType::Data, vec![byte], None, None).await.unwrap();
+    }
+
+    // The second full scan qualifies the token controller, which gets indexed
+    let pattern = QueryPattern::Controller { controller_id: TOKEN_CONTROLLER_ID };
+    for _ in 0..2 {
+        assert_eq!(service.get_token_balance(&RequestContext::new(), &owner, &token_id, None).await.unwrap().amount, 5);
+    }
+    let recommendations = service.index_recommendations(&auth).await.unwrap();
+    assert_eq!(recommendations.len(), 1);
+    assert_eq!((&recommendations[0].pattern, recommendations[0].indexed), (&pattern, true));
+
+    // Later balance objects join the index, and the scan visits only the controller's objects
+    service.create_object(UnitsObjectId::new([0xb1; 32]), ObjectType::Data, balance(7), Some(TOKEN_CONTROLLER_ID), None).await.unwrap();
+    assert_eq!(service.get_token_balance(&RequestContext::new(), &owner, &token_id, None).await.unwrap().amount, 12);
+    assert_eq!(storage.index_advisor().recommendations()[0].scanned, recommendations[0].scanned + 3);
+
+    // Admins can index a controller before its queries qualify
+    let other = UnitsObjectId::new([0xc0; 32]);
+    let report = service.admin(&auth, AdminOperation::CreateIndex { controller_id: other }).await.unwrap();
+    assert_eq!(report.affected, 0);
+    assert_eq!(storage.index_advisor().indexed_controllers().len(), 2);
+}
+
+#[tokio::test]
+async fn test_admins_assign_controller_resource_classes() {
+    use units_core_service::services::{AdminAuth, AdminOperation};
+    use units_core_types::{ModuleRegistry, ObjectStorage, ResourceClass, UnitsStorage, MODULE_REGISTRY_ID};
+
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let mut config = Config::default();
+    config.admin.enabled = true;
+    config.admin.api_key = Some("secret".to_string());
+    let service = UnitsService::new(storage.clone(), Arc::new(MockRuntime::new()), config);
+    let analytics = UnitsObjectId::new([0xa1; 32]);
+    service.create_object(analytics, ObjectType::Executable(VMType::RiscV), vec![1], None, None).await.unwrap();
+    let registered = || {
+        let object = storage.objects().get(&MODULE_REGISTRY_ID).unwrap().unwrap();
+        ModuleRegistry::from_object(&object).unwrap().resource_class(&analytics)
+    };
+    assert_eq!(registered(), ResourceClass::Standard);
+
+    // A dry run reports the change without making it
+    let set = AdminOperation::SetResourceClass { controller_id: analytics, class: ResourceClass::Large };
+    let mut auth = AdminAuth { api_key: "secret".to_string(), dry_run: true, confirmation: None };
+    assert_eq!(service.admin(&auth, set.clone()).await.unwrap().affected, 1);
+    assert_eq!(registered(), ResourceClass::Standard);
+    auth.dry_run = false;
+    assert_eq!(service.admin(&auth, set.clone()).await.unwrap().affected, 1);
+    assert_eq!(registered(), ResourceClass::Large);
+    assert_eq!(service.admin(&auth, set).await.unwrap().affected, 0);
+
+    let undeployed = AdminOperation::SetResourceClass { controller_id: UnitsObjectId::new([0xa2; 32]), class: ResourceClass::Small };
+    assert!(service.admin(&auth, undeployed).await.is_err());
+}
+
+#[tokio::test]
+async fn test_account_activity_feed_pages_newest_first() {
+    use units_core_service::services::activity::{Activity, ActivityPage};
+    use units_core_types::{ReceiptStorage, TransactionEffect, TransactionReceipt, UnitsObject, UnitsStorage};
+
+    let runtime = Arc::new(MockRuntime::new());
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage.clone(), runtime, Config::default());
+
+    let account = UnitsObjectId::new([0xac; 32]);
+    let owned = UnitsObjectId::new([0x0b; 32]);
+    let unrelated = UnitsObjectId::new([0x99; 32]);
+    service.create_object(account, ObjectType::Data, vec![], None, None).await.unwrap();
+    let object = service.create_object(owned, ObjectType::Data, vec![1], Some(account), None).await.unwrap();
+    service.create_object(unrelated, ObjectType::Data, vec![], None, None).await.unwrap();
+
+    // Sixty transactions touch the owned object, one per slot
+    for slot in 1..=60u64 {
+        let mut receipt = TransactionReceipt::new([slot as u8; 32], slot, slot % 2 == 0, 0);
+        receipt.add_effect(TransactionEffect::new_modification([slot as u8; 32], object.clone(), object.clone()));
+        storage.receipts().store_receipt(&receipt).unwrap();
+    }
+    let mut elsewhere = TransactionReceipt::new([0xff; 32], 61, true, 0);
+    elsewhere.add_effect(TransactionEffect::new_creation([0xff; 32], UnitsObject::new_data(unrelated, unrelated, vec![])));
+    storage.receipts().store_receipt(&elsewhere).unwrap();
+
+    let first: ActivityPage = service.get_account_activity(&RequestContext::new(), &account, None, None).await.unwrap();
+    assert_eq!(first.entries.len(), 50);
+
+    // The second page resumes after the cursor and holds the rest
+    let cursor = first.next_cursor.expect("First page should have a cursor");
+    let second = service.get_account_activity(&RequestContext::new(), &account, Some(&cursor), None).await.unwrap();
+    assert!(second.next_cursor.is_none());
+    let entries: Vec<_> = first.entries.into_iter().chain(second.entries).collect();
+    assert!(entries.windows(2).all(|pair| pair[0].slot >= pair[1].slot));
+
+    let transactions: Vec<(u64, bool)> = entries
+        .iter()
+        .filter_map(|entry| match entry.activity {
+            Activity::Transaction { success, .. } => Some((entry.slot, success)),
+            _ => None,
+        })
+        .collect();
+    let expected: Vec<(u64, bool)> = (1..=60u64).rev().map(|slot| (slot, slot % 2 == 0)).collect();
+    assert_eq!(transactions, expected);
+
+    // Creating the account and its object are the only state changes
+    let changed: Vec<UnitsObjectId> = entries
+        .iter()
+        .filter_map(|entry| match entry.activity {
+            Activity::ObjectChange { object_id, .. } => Some(object_id),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(changed.len(), 2);
+    assert!(changed.contains(&account) && changed.contains(&owned));
+
+    assert!(service.get_account_activity(&RequestContext::new(), &account, Some("bogus"), None).await.is_err());
+}
+
+#[tokio::test]
+async fn test_range_chunks_cover_receipts_and_state_proofs() {
+    use units_core_types::{ReceiptStorage, TransactionReceipt, UnitsStorage};
+
+    let runtime = Arc::new(MockRuntime::new());
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage.clone(), runtime, Config::default());
+
+    // Three receipts in each of slots 1..=10
+    for slot in 1..=10u64 {
+        for i in 0..3u8 {
+            let receipt = TransactionReceipt::new([slot as u8 * 3 + i; 32], slot, true, 0);
+            storage.receipts().store_receipt(&receipt).unwrap();
+        }
+    }
+
+    // Chunks of at most seven receipts stop at slot boundaries
+    let mut receipts = Vec::new();
+    let mut start = 1;
+    let mut chunks = 0;
+    loop {
+        let chunk = service.get_receipts_chunk(&RequestContext::new(), start, 10, 7).await.unwrap();
+        assert_eq!(chunk.receipts.len() % 3, 0);
+        assert!(chunk.receipts.len() <= 7);
+        receipts.extend(chunk.receipts);
+        chunks += 1;
+        match chunk.next_slot {
+            Some(next) => start = next,
+            None => break,
+        }
+    }
+    assert_eq!(chunks, 5);
+    assert_eq!(receipts.len(), 30);
+    assert!(receipts.windows(2).all(|pair| pair[0].slot <= pair[1].slot));
+
+    // A slot larger than the limit is still returned whole
+    let chunk = service.get_receipts_chunk(&RequestContext::new(), 4, 10, 1).await.unwrap();
+    assert_eq!(chunk.receipts.len(), 3);
+    assert_eq!(chunk.next_slot, Some(5));
+
+    // Pages split slots and pick up after the cursor receipt
+    let mut paged = Vec::new();
+    let mut after = None;
+    loop {
+        let page = service.get_receipts_page(&RequestContext::new(), 1, 10, after, 7).await.unwrap();
+        assert!(page.receipts.len() <= 7);
+        paged.extend(page.receipts);
+        after = page.next;
+        if after.is_none() {
+            break;
+        }
+    }
+    let hashes = |receipts: &[TransactionReceipt]| receipts.iter().map(|r| r.transaction_hash).collect::<Vec<_>>();
+    assert_eq!(hashes(&paged), hashes(&receipts));
+    let unknown = service.get_receipts_page(&RequestContext::new(), 1, 10, Some([0xff; 32]), 7).await;
+    assert!(unknown.is_err());
+
+    for _ in 0..5 {
+        service.advance_slot().await.unwrap();
+    }
+    let first = service.get_state_proofs_chunk(&RequestContext::new(), 0, u64::MAX, 2).await.unwrap();
+    assert_eq!(first.proofs.len(), 2);
+    let next = first.next_slot.expect("More state proofs should remain");
+    let rest = service.get_state_proofs_chunk(&RequestContext::new(), next, u64::MAX, 1000).await.unwrap();
+    assert!(rest.next_slot.is_none());
+    let slots: Vec<u64> = first.proofs.iter().chain(&rest.proofs).map(|proof| proof.slot).collect();
+    assert_eq!(slots.len(), 5);
+    assert!(slots.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[tokio::test]
+async fn test_retention_windows_prune_stores_independently() {
+    use units_core_service::services::{AdminAuth, AdminOperation};
+    use units_core_types::{ReceiptStorage, TransactionReceipt, UnitsStorage};
+
+    // Windows that let receipts outlive the state proofs committing them are rejected
+    let mut retention = Config::default().retention;
+    assert!(retention.validate().is_ok());
+    retention.state_proof_slots = Some(2);
+    assert!(retention.validate().is_err());
+    retention.receipt_slots = Some(3);
+    assert!(retention.validate().is_err());
+    retention.receipt_slots = Some(2);
+    assert!(retention.validate().is_ok());
+    retention.object_history_slots = Some(0);
+    assert!(retention.validate().is_err());
+
+    // Receipts are pruned while every state proof is kept
+    let runtime = Arc::new(MockRuntime::new());
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let mut config = Config::default();
+    config.retention.receipt_slots = Some(2);
+    config.admin.enabled = true;
+    config.admin.api_key = Some("secret".to_string());
+    let service = UnitsService::new(storage.clone(), runtime, config);
+
+    for slot in 1..=5u64 {
+        let receipt = TransactionReceipt::new([slot as u8; 32], slot, true, 0);
+        storage.receipts().store_receipt(&receipt).unwrap();
+        service.advance_slot().await.unwrap();
+    }
+    let kept: Vec<u64> = storage.receipts().get_receipts_range(0, 10).unwrap().iter().map(|r| r.slot).collect();
+    assert_eq!(kept.len(), 2);
+    assert!(kept.iter().all(|slot| *slot >= 4));
+    for slot in 1..=5 {
+        assert!(service.get_state_root(slot).await.is_ok(), "State proof {} was pruned", slot);
+    }
+
+    // Pruning the state proofs committing kept receipts is refused
+    let auth = AdminAuth { api_key: "secret".to_string(), dry_run: true, confirmation: None };
+    assert!(service.admin(&auth, AdminOperation::Prune { before_slot: 5 }).await.is_err());
+    let plan = service.admin(&auth, AdminOperation::Prune { before_slot: 4 }).await.unwrap();
+    assert_eq!(plan.affected, 3);
+}
+
+#[tokio::test]
+async fn test_receipt_webhooks_are_signed_and_sequenced() {
+    use axum::{body::Bytes, http::HeaderMap, routing::post, Router};
+    use units_core_service::services::webhooks::{
+        verify_webhook, WebhookPayload, NONCE_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER,
+    };
+    use units_core_service::signing::NodeSigner;
+
+    // Receiver that forwards each delivery to the test
+    let (sender, mut received) = tokio::sync::mpsc::unbounded_channel::<(HeaderMap, Bytes)>();
+    let app = Router::new().route(
+        "/hook",
+        post(move |headers: HeaderMap, body: Bytes| {
+            let sender = sender.clone();
+            async move {
+                sender.send((headers, body)).unwrap();
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}/hook", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let data_dir = std::env::temp_dir().join(format!("units-webhook-test-{}", std::process::id()));
+    std::fs::create_dir_all(&data_dir).unwrap();
+    let signer = Arc::new(NodeSigner::from_seed([5; 32]));
+    let node_key = signer.public_key();
+    let mut config = Config::default();
+    config.webhooks.endpoints = vec![endpoint];
+    config.storage.data_dir = Some(data_dir.to_str().unwrap().to_string());
+    let start = || {
+        UnitsService::new(
+            Arc::new(ConsolidatedUnitsStorage::new_in_memory()),
+            Arc::new(MockRuntime::new()),
+            config.clone(),
+        )
+        .with_signer(signer.clone())
+    };
+    let transfer = |hash: u8| Transaction {
+        hash: [hash; 32],
+        instructions: vec![Instruction {
+            controller_id: UnitsObjectId::new([10; 32]),
+            target_function: "transfer".to_string(),
+            target_objects: vec![UnitsObjectId::new([20; 32])],
+            params: vec![],
+        }],
+        commitment_level: CommitmentLevel::Committed,
+        priority_fee: 0,
+        expected_versions: vec![],
+        sponsorship: None,
+        memo: None,
+    };
+
+    // Sequences of the next `count` deliveries, checking each signature
+    async fn receive(
+        received: &mut tokio::sync::mpsc::UnboundedReceiver<(HeaderMap, Bytes)>,
+        node_key: &[u8; 32],
+        count: usize,
+    ) -> Vec<u64> {
+        let header = |headers: &HeaderMap, name: &str| headers[name].to_str().unwrap().to_string();
+        let mut sequences = Vec::new();
+        for _ in 0..count {
+            let (headers, body) = tokio::time::timeout(std::time::Duration::from_secs(5), received.recv())
+                .await
+                .expect("Webhook was not delivered")
+                .unwrap();
+            let timestamp: u64 = header(&headers, TIMESTAMP_HEADER).parse().unwrap();
+            let nonce: [u8; 16] = hex::decode(header(&headers, NONCE_HEADER)).unwrap().try_into().unwrap();
+            let signature: [u8; 64] = hex::decode(header(&headers, SIGNATURE_HEADER)).unwrap().try_into().unwrap();
+            assert!(verify_webhook(node_key, &body, timestamp, &nonce, &signature));
+
+            // A replayed signature does not cover a different body or timestamp
+            assert!(!verify_webhook(node_key, b"{}", timestamp, &nonce, &signature));
+            assert!(!verify_webhook(node_key, &body, timestamp + 1, &nonce, &signature));
+
+            let payload: WebhookPayload = serde_json::from_slice(&body).unwrap();
+            sequences.push(payload.sequence);
+        }
+        sequences
+    }
+
+    // One receipt per slot, then one more after the node restarts
+    let service = start();
+    for hash in [1u8, 2] {
+        service.submit_transaction(transfer(hash)).await.unwrap();
+        service.advance_slot().await.unwrap();
+    }
+    let mut sequences = receive(&mut received, &node_key, 2).await;
+    drop(service);
+    let service = start();
+    service.submit_transaction(transfer(3)).await.unwrap();
+    service.advance_slot().await.unwrap();
+    sequences.extend(receive(&mut received, &node_key, 1).await);
+    std::fs::remove_dir_all(&data_dir).unwrap();
+
+    // Slots are delivered in order, and the restarted node carries on the sequence
+    assert_eq!(sequences, vec![1, 2, 3]);
+}
+
+#[tokio::test]
+async fn test_verify_object_from_untrusted_node() {
+    use units_core_service::json_rpc::JsonRpcServerImpl;
+    use units_core_service::signing::NodeSigner;
+    use units_core_service::verify::{fetch_evidence, verify_evidence, SignatureCheck};
+
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let signer = NodeSigner::from_seed([3; 32]);
+    let node_key = signer.public_key();
+    let service = UnitsService::new(storage, Arc::new(MockRuntime::new()), Config::default())
+        .with_signer(Arc::new(signer));
+
+    let ids: Vec<UnitsObjectId> = (1..=3u8).map(|seed| UnitsObjectId::new([seed; 32])).collect();
+    for id in &ids {
+        service.create_object(*id, ObjectType::Data, vec![1, 2, 3], None, None).await.unwrap();
+    }
+    service.advance_slot().await.unwrap();
+
+    let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let server = JsonRpcServerImpl::new(service.clone()).start(addr).await.unwrap();
+    tokio::spawn(server);
+
+    let evidence = fetch_evidence(&format!("http://{}", addr), &ids[1]).await.unwrap();
+    let report = verify_evidence(&evidence, Some(&node_key)).unwrap();
+    assert!(report.object_proof_valid && report.included_in_state_root);
+    assert_eq!(report.signature, SignatureCheck::Valid);
+    assert!(report.is_valid(true));
+
+    // A different key, or tampered data, fails locally
+    let report = verify_evidence(&evidence, Some(&[9; 32])).unwrap();
+    assert_eq!(report.signature, SignatureCheck::UnexpectedKey);
+    let mut tampered = evidence.clone();
+    tampered.object.data = vec![6, 6, 6];
+    let report = verify_evidence(&tampered, None).unwrap();
+    assert!(!report.object_proof_valid && !report.included_in_state_root);
+    assert_eq!(report.signature, SignatureCheck::Invalid);
+
+    // A root the node claims in the path counts for nothing
+    let mut forged = evidence;
+    forged.state_root.object_root = hex::encode([0u8; 32]);
+    assert!(!verify_evidence(&forged, None).unwrap().is_valid(false));
+
+    // Paths are refused for objects written since the commit, not for the rest
+    service.create_object(ids[0], ObjectType::Data, vec![4], None, None).await.unwrap();
+    assert!(service.get_object_inclusion(&ids[0]).await.is_err());
+    assert!(service.get_object_inclusion(&ids[1]).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_keccak_nodes_serve_evidence_clients_can_check() {
+    use units_core_service::verify::{verify_evidence, ObjectEvidence};
+    use units_proofs::HashAlgorithm;
+
+    let mut config = Config::default();
+    config.storage.hash_algorithm = HashAlgorithm::Keccak256;
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory().with_hash_algorithm(config.storage.hash_algorithm));
+    let service = UnitsService::new(storage, Arc::new(MockRuntime::new()), config);
+    let id = UnitsObjectId::new([1; 32]);
+    service.create_object(id, ObjectType::Data, vec![1, 2, 3], None, None).await.unwrap();
+    service.advance_slot().await.unwrap();
+
+    // The state root names its algorithm, so clients check the proofs with it
+    let inclusion = service.get_object_inclusion(&id).await.unwrap();
+    let state_root = service.get_state_root(inclusion.slot).await.unwrap();
+    assert_eq!(state_root.hash_algorithm, HashAlgorithm::Keccak256);
+    let object = service.get_object(&id).await.unwrap();
+    let mut evidence = ObjectEvidence { object, signature: None, inclusion, state_root };
+    assert!(verify_evidence(&evidence, None).unwrap().is_valid(false));
+    assert!(service.verify_object_against_root(&evidence.object, &evidence.inclusion.path).await.unwrap().valid);
+
+    // Checked as Blake3, the same proofs fail
+    evidence.state_root.hash_algorithm = HashAlgorithm::Blake3;
+    assert!(!verify_evidence(&evidence, None).unwrap().is_valid(false));
+}
+
+#[tokio::test]
+async fn test_clients_detect_nodes_serving_older_state() {
+    use units_core_service::client::{FleetClient, StaleRead};
+    use units_core_service::json_rpc::JsonRpcServerImpl;
+    use units_core_service::services::SlotStatus;
+
+    // Two nodes behind one balancer, the second a slot behind
+    let mut endpoints = Vec::new();
+    let mut nodes = Vec::new();
+    for slots in [2, 1] {
+        let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+        let service = UnitsService::new(storage, Arc::new(MockRuntime::new()), Config::default());
+        for _ in 0..slots {
+            service.advance_slot().await.unwrap();
+        }
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        tokio::spawn(JsonRpcServerImpl::new(service.clone()).start(addr).await.unwrap());
+        endpoints.push(format!("http://{}", addr));
+        nodes.push(service);
+    }
+
+    let client = FleetClient::new(endpoints.clone());
+    let (status, served) = client.request::<SlotStatus>("getSlotStatus", serde_json::json!([])).await.unwrap();
+    assert_eq!((status.confirmed_slot, served), (2, nodes[0].response_context()));
+    assert!(served.state_root.is_some());
+
+    // The lagging node's answer is refused rather than returned
+    let error = client.request::<SlotStatus>("getSlotStatus", serde_json::json!([])).await.unwrap_err();
+    let stale = error.downcast_ref::<StaleRead>().unwrap();
+    assert_eq!((stale.served.slot, stale.latest.slot), (1, 2));
+    assert_eq!(client.tracker().latest(), Some(served));
+
+    // With retries the request moves on to a node that has caught up
+    let client = FleetClient::new(endpoints).with_stale_retry(true);
+    client.request::<SlotStatus>("getSlotStatus", serde_json::json!([])).await.unwrap();
+    let (status, served) = client.request::<SlotStatus>("getSlotStatus", serde_json::json!([])).await.unwrap();
+    assert_eq!((status.confirmed_slot, served.slot), (2, 2));
+}
+
+#[tokio::test]
+async fn test_light_sync_sends_only_changed_objects() {
+    use units_core_service::service::MAX_WATCHED_OBJECTS;
+    use units_core_types::{ObjectStorage, ReceiptStorage, SlotNumber, TransactionReceipt, UnitsObject, UnitsStorage};
+
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage.clone(), Arc::new(MockRuntime::new()), Config::default());
+    let (a, b, unknown) = (UnitsObjectId::new([1; 32]), UnitsObjectId::new([2; 32]), UnitsObjectId::new([3; 32]));
+    let controller = UnitsObjectId::new([9; 32]);
+
+    // Stand in for transactions executed in the slot about to close
+    let execute = |tx: u8, slot: SlotNumber, writes: &[(UnitsObjectId, Option<u8>)]| {
+        for (id, data) in writes {
+            match data {
+                Some(byte) => storage.objects().set(&UnitsObject::new_data(*id, controller, vec![*byte]), Some([tx; 32])),
+                None => storage.objects().delete(id, Some([tx; 32])),
+            }
+            .unwrap();
+        }
+        storage.receipts().store_receipt(&TransactionReceipt::new([tx; 32], slot, true, 0)).unwrap();
+    };
+
+    execute(1, 1, &[(a, Some(1)), (b, Some(1))]);
+    service.advance_slot().await.unwrap();
+    let sync = service.light_sync(0, &[a, b, unknown]).await.unwrap();
+    assert_eq!(sync.slot, 1);
+    assert_eq!(sync.changes.iter().map(|change| change.object_id).collect::<Vec<_>>(), vec![a, b]);
+    for change in &sync.changes {
+        let path = change.path.as_ref().unwrap();
+        assert_eq!(path.root, sync.root);
+        let verification = service.verify_object_against_root(change.object.as_ref().unwrap(), path).await.unwrap();
+        assert!(verification.valid);
+    }
+    assert!(service.light_sync(1, &[a, b]).await.unwrap().changes.is_empty());
+
+    // Only what changed after the client's slot comes back, deletions without a path
+    execute(2, 2, &[(b, Some(2)), (a, None)]);
+    service.advance_slot().await.unwrap();
+    let sync = service.light_sync(1, &[b, a]).await.unwrap();
+    assert_eq!(sync.slot, 2);
+    assert_eq!(sync.changes.len(), 2);
+    assert!(sync.changes[0].object.is_none() && sync.changes[0].path.is_none());
+    assert_eq!(sync.changes[1].object.as_ref().unwrap().data, vec![2]);
+    assert!(service.light_sync(2, &[a, b]).await.unwrap().changes.is_empty());
+
+    let watched = vec![a; MAX_WATCHED_OBJECTS + 1];
+    assert!(service.light_sync(0, &watched).await.is_err());
+}
+
+#[tokio::test]
+async fn test_shadow_execution_reports_divergence() {
+    use units_core_types::error::RuntimeError;
+    use units_core_types::{Runtime, TransactionHash, TransactionReceipt, VMExecutor, Verifier};
+
+    /// Runtime that fails every transaction, standing in for a nondeterministic build
+    struct FailingRuntime(MockRuntime);
+
+    impl Runtime for FailingRuntime {
+        fn get_vm_executor(&self, vm_type: VMType) -> Option<Box<dyn VMExecutor>> {
+            self.0.get_vm_executor(vm_type)
+        }
+
+        fn execute_transaction(&self, transaction: Transaction) -> TransactionReceipt {
+            TransactionReceipt::new(transaction.hash, 0, false, 0)
+        }
+
+        fn execute_transaction_atomic(
+            &self,
+            transaction: &Transaction,
+            _view: &mut units_core_types::TransactionView<'_>,
+            slot: u64,
+            timestamp: u64,
+        ) -> Result<TransactionReceipt, units_core_types::StorageError> {
+            Ok(TransactionReceipt::new(transaction.hash, slot, false, timestamp))
+        }
+
+        fn get_transaction(&self, hash: &TransactionHash) -> Option<Transaction> {
+            self.0.get_transaction(hash)
+        }
+
+        fn get_transaction_receipt(&self, hash: &TransactionHash) -> Option<TransactionReceipt> {
+            self.0.get_transaction_receipt(hash)
+        }
+
+        fn rollback_transaction(&self, hash: &TransactionHash) -> Result<bool, RuntimeError> {
+            self.0.rollback_transaction(hash)
+        }
+
+        fn get_verifier(&self) -> &dyn Verifier {
+            self.0.get_verifier()
+        }
+    }
+
+    let mut config = Config::default();
+    config.shadow.sample_rate = 1.5;
+    assert!(config.shadow.validate().is_err());
+    config.shadow.enabled = true;
+    config.shadow.sample_rate = 1.0;
+    config.shadow.max_divergences = 1;
+
+    // Re-executing on an identical runtime agrees with every committed receipt
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage, Arc::new(MockRuntime::new()), config.clone())
+        .with_shadow_runtime(Arc::new(MockRuntime::new()));
+    for hash in 1..=3u8 {
+        service.submit_transaction(Transaction::new(vec![], [hash; 32])).await.unwrap();
+    }
+    service.advance_slot().await.unwrap();
+    let report = service.shadow_report().await.unwrap();
+    assert!(report.enabled);
+    assert_eq!((report.checked, report.diverged), (3, 0));
+
+    // A diverging runtime is caught, keeping only the most recent divergences
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage, Arc::new(MockRuntime::new()), config.clone())
+        .with_shadow_runtime(Arc::new(FailingRuntime(MockRuntime::new())));
+    for hash in 1..=2u8 {
+        service.submit_transaction(Transaction::new(vec![], [hash; 32])).await.unwrap();
+    }
+    service.advance_slot().await.unwrap();
+    let report = service.shadow_report().await.unwrap();
+    assert_eq!((report.checked, report.diverged), (2, 2));
+    assert_eq!(report.recent.len(), 1);
+    assert_eq!(report.recent[0].field, "success");
+
+    // Nothing is checked when the mode is off
+    config.shadow.enabled = false;
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage, Arc::new(MockRuntime::new()), config)
+        .with_shadow_runtime(Arc::new(FailingRuntime(MockRuntime::new())));
+    service.submit_transaction(Transaction::new(vec![], [1; 32])).await.unwrap();
+    service.advance_slot().await.unwrap();
+    let report = service.shadow_report().await.unwrap();
+    assert!(!report.enabled);
+    assert_eq!(report.checked, 0);
+}
+
+#[tokio::test]
+async fn test_document_existence_receipts() {
+    use units_core_service::signing::NodeSigner;
+    use units_core_service::verify::verify_existence;
+
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let signer = NodeSigner::from_seed([5; 32]);
+    let node_key = signer.public_key();
+    let service = UnitsService::new(storage, Arc::new(MockRuntime::new()), Config::default())
+        .with_signer(Arc::new(signer));
+
+    let document_hash = [42; 32];
+    let attester = UnitsObjectId::new([7; 32]);
+    let attestation = service.attest_document(document_hash, attester).await.unwrap();
+    assert_eq!(attestation.slot, 1);
+    assert_eq!(attestation.attester_id, attester);
+
+    // No receipt until the covering slot is committed
+    assert!(service.get_existence_receipt(&document_hash).await.is_err());
+    service.advance_slot().await.unwrap();
+    service.advance_slot().await.unwrap();
+
+    // Attesting again keeps the earliest record
+    let again = service.attest_document(document_hash, UnitsObjectId::new([8; 32])).await.unwrap();
+    assert_eq!(again, attestation);
+
+    let receipt = service.get_existence_receipt(&document_hash).await.unwrap();
+    assert_eq!(receipt.attestation, attestation);
+    assert!(verify_existence(&receipt, &document_hash, Some(&node_key)).unwrap());
+
+    // The receipt proves nothing about another document, nor with a doctored summary
+    assert!(!verify_existence(&receipt, &[43; 32], None).unwrap());
+    let mut doctored = receipt.clone();
+    doctored.attestation.slot = 0;
+    assert!(!verify_existence(&doctored, &document_hash, None).unwrap());
+    assert!(service.get_existence_receipt(&[43; 32]).await.is_err());
+}
+
+#[tokio::test]
+async fn test_slot_summaries_are_covered_by_state_proofs() {
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage, Arc::new(MockRuntime::new()), Config::default());
+
+    for hash in [1u8, 2] {
+        service.submit_transaction(Transaction::new(vec![], [hash; 32])).await.unwrap();
+    }
+    assert!(service.get_slot_summary(1).await.is_err());
+    service.advance_slot().await.unwrap();
+
+    let receipt = service.get_slot_summary(1).await.unwrap();
+    assert_eq!(receipt.summary.slot, 1);
+    assert_eq!(receipt.summary.transaction_count, 2);
+    assert_eq!(receipt.summary.failed_count, 0);
+
+    // The summary is written ahead of the slot's state proof, which covers it
+    assert_eq!(receipt.evidence.inclusion.slot, 1);
+    assert_eq!(receipt.evidence.state_root.slot, 1);
+    assert!(service.get_slot_summary(2).await.is_err());
+}
+
+#[tokio::test]
+async fn test_expired_request_context_cancels_scans_and_simulations() {
+    use std::time::Duration;
+    use units_core_service::ServiceError;
+    use units_core_types::{ReceiptStorage, TransactionReceipt, UnitsStorage};
+
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage.clone(), Arc::new(MockRuntime::new()), Config::default());
+    storage.receipts().store_receipt(&TransactionReceipt::new([1; 32], 1, true, 0)).unwrap();
+
+    let live = service.request_context();
+    assert!(live.remaining().is_some());
+    assert_eq!(service.get_receipts_chunk(&live, 1, 10, 10).await.unwrap().receipts.len(), 1);
+
+    let expired = RequestContext::new().with_principal("indexer").with_timeout(Duration::ZERO);
+    let error = service.get_receipts_chunk(&expired, 1, 10, 10).await.unwrap_err();
+    assert!(matches!(error, ServiceError::DeadlineExceeded { ref trace_id } if *trace_id == expired.trace_id));
+    assert!(service.get_account_activity(&expired, &UnitsObjectId::new([1; 32]), None, None).await.is_err());
+
+    // A simulation past its deadline leaves the sandbox untouched
+    let sandbox = service.create_sandbox().await.unwrap();
+    let transaction = Transaction::new(vec![], [2; 32]);
+    assert!(service.sandbox_execute_transaction(&expired, &sandbox.namespace, transaction).await.is_err());
+    assert!(service.sandbox_get_receipts(&sandbox.namespace).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_collection_proofs_cover_every_member() {
+    use units_core_service::verify::verify_collection_proof;
+
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage, Arc::new(MockRuntime::new()), Config::default());
+    let owner = UnitsObjectId::new([1; 32]);
+    let mut items = Vec::new();
+    for seed in 10..14u8 {
+        let id = UnitsObjectId::new([seed; 32]);
+        service.create_object(id, ObjectType::Data, vec![seed], Some(owner), None).await.unwrap();
+        items.push(id);
+    }
+
+    let series = service.create_collection(owner, "series").await.unwrap();
+    service.update_collection(&series.id, &owner, &items[..3], &[]).await.unwrap();
+    let page = service.get_collection_members(&RequestContext::new(), &series.id, 0).await.unwrap();
+    assert_eq!(page.total_members, 3);
+
+    // Nothing is provable until a state proof covers the collection
+    assert!(service.get_collection_proof(&series.id).await.is_err());
+    service.advance_slot().await.unwrap();
+
+    let proof = service.get_collection_proof(&series.id).await.unwrap();
+    assert_eq!(proof.collection.members, items[..3]);
+    assert!(verify_collection_proof(&proof, None).unwrap());
+
+    // Dropping a member, or claiming an outsider, breaks the proof
+    let mut short = proof.clone();
+    short.members.pop();
+    assert!(!verify_collection_proof(&short, None).unwrap());
+    let mut padded = proof.clone();
+    padded.collection.members.push(items[3]);
+    assert!(!verify_collection_proof(&padded, None).unwrap());
+
+    // A member changed after the slot cannot be proven until the next one
+    service.create_object(items[0], ObjectType::Data, vec![0], Some(owner), None).await.unwrap();
+    assert!(service.get_collection_proof(&series.id).await.is_err());
+    service.advance_slot().await.unwrap();
+    let next = service.get_collection_proof(&series.id).await.unwrap();
+    assert!(verify_collection_proof(&next, None).unwrap());
+    assert_ne!(next.group_root, proof.group_root);
+}
+
+#[tokio::test]
+async fn test_reads_are_labelled_with_slot_finality() {
+    use units_core_service::services::Finality;
+
+    let mut config = Config::default();
+    config.finality.depth = 2;
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage, Arc::new(MockRuntime::new()), config);
+
+    let object_id = UnitsObjectId::new([5u8; 32]);
+    service.create_object(object_id, ObjectType::Data, vec![1], None, None).await.unwrap();
+    let (_, metadata) = service.get_object_with_metadata(&object_id).await.unwrap();
+    assert_eq!(metadata.finality, Finality::Processed);
+
+    // The write is confirmed with the next state proof, while reads of the
+    // live store stay processed since they may see the following slot's writes
+    let slot = service.advance_slot().await.unwrap();
+    let (_, metadata) = service.get_object_with_metadata(&object_id).await.unwrap();
+    assert_eq!(metadata.finality, Finality::Processed);
+    assert_eq!(service.get_state_root(slot).await.unwrap().finality, Finality::Confirmed);
+    assert_eq!(service.get_object_inclusion(&object_id).await.unwrap().finality, Finality::Confirmed);
+
+    // ...and finalized once buried under the finality depth
+    for _ in 0..2 {
+        service.advance_slot().await.unwrap();
+    }
+    let status = service.get_slot_status().await.unwrap();
+    assert_eq!((status.processed_slot, status.confirmed_slot, status.finalized_slot), (slot + 2, slot + 2, slot));
+    assert_eq!(service.get_state_root(slot).await.unwrap().finality, Finality::Finalized);
+    assert_eq!(service.get_state_root(slot + 1).await.unwrap().finality, Finality::Confirmed);
+}
+
+#[tokio::test]
+async fn test_find_objects_by_metadata() {
+    use units_core_service::error::ServiceError;
+    use units_core_types::constants::TOKEN_CONTROLLER_ID;
+    use units_core_types::{ObjectStorage, UnitsStorage};
+
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage.clone(), Arc::new(MockRuntime::new()), Config::default());
+
+    let (test, gold) = (UnitsObjectId::new([0x71; 32]), UnitsObjectId::new([0x72; 32]));
+    service.create_object(test, ObjectType::Data, vec![], Some(TOKEN_CONTROLLER_ID), None).await.unwrap();
+    service.create_object(gold, ObjectType::Data, vec![], Some(TOKEN_CONTROLLER_ID), None).await.unwrap();
+    service.set_object_metadata(&TOKEN_CONTROLLER_ID, &test, "symbol", Some("TEST")).await.unwrap();
+    let metadata = service.set_object_metadata(&TOKEN_CONTROLLER_ID, &gold, "symbol", Some("GOLD")).await.unwrap();
+    assert_eq!(metadata.get("symbol").map(String::as_str), Some("GOLD"));
+
+    assert_eq!(service.find_by_metadata(&TOKEN_CONTROLLER_ID, "symbol", "TEST").await.unwrap(), vec![test]);
+    let stranger = UnitsObjectId::new([0xee; 32]);
+    assert!(service.find_by_metadata(&stranger, "symbol", "TEST").await.unwrap().is_empty());
+
+    // Only the object's controller may annotate it
+    assert!(matches!(
+        service.set_object_metadata(&stranger, &test, "symbol", Some("FAKE")).await,
+        Err(ServiceError::Unauthorized { .. })
+    ));
+
+    // Deleting the object drops it from the index
+    storage.objects().delete(&test, None).unwrap();
+    assert!(service.find_by_metadata(&TOKEN_CONTROLLER_ID, "symbol", "TEST").await.unwrap().is_empty());
+    assert!(service.get_object_metadata(&test).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_deploy_module_artifact_registers_its_abi() {
+    use units_core_types::{ModuleAbi, ModuleArtifact};
+
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage, Arc::new(MockRuntime::new()), Config::default());
+
+    let abi = ModuleAbi {
+        functions: vec!["transfer".to_string()],
+        abi_location: Some("https://abi.example/module.json".to_string()),
+        ..ModuleAbi::default()
+    };
+    let artifact = ModuleArtifact::new(VMType::RiscV, b"\x7fELF module".to_vec(), abi);
+    let controller = UnitsObjectId::new([0x4d; 32]);
+    let entry = service.deploy_module_artifact(controller, &artifact.encode().unwrap()).await.unwrap();
+    assert_eq!((entry.version, entry.code_hash), (1, artifact.code_hash));
+    assert_eq!(entry.abi_location.as_deref(), Some("https://abi.example/module.json"));
+
+    let deployed = service.get_object(&controller).await.unwrap();
+    assert_eq!(deployed.data(), artifact.code.as_slice());
+    assert!(service.deploy_module_artifact(controller, b"not an artifact").await.is_err());
+}
+
+/// Runtime whose transactions write each instruction's params as the new
+/// data of its targets
+struct WriteParamsRuntime(MockRuntime);
+
+impl units_core_types::Runtime for WriteParamsRuntime {
+    fn get_vm_executor(&self, vm_type: VMType) -> Option<Box<dyn units_core_types::VMExecutor>> {
+        self.0.get_vm_executor(vm_type)
+    }
+
+    fn execute_transaction(&self, transaction: Transaction) -> units_core_types::TransactionReceipt {
+        self.0.execute_transaction(transaction)
+    }
+
+    fn execute_instruction_in_class(
+        &self,
+        instruction: &Instruction,
+        objects: std::collections::HashMap<UnitsObjectId, units_core_types::UnitsObject>,
+        _class: units_core_types::ResourceClass,
+        _slot: u64,
+        _timestamp: u64,
+    ) -> Result<units_core_types::ExecutionOutput, units_core_types::VMExecutionError> {
+        let effects = instruction
+            .target_objects
+            .iter()
+            .map(|target| {
+                let after = units_core_types::UnitsObject::new_data(*target, instruction.controller_id, instruction.params.clone());
+                match objects.get(target) {
+                    Some(before) => units_core_types::ObjectEffect::modification(before.clone(), after),
+                    None => units_core_types::ObjectEffect::creation(after),
+                }
+            })
+            .collect();
+        Ok(units_core_types::ExecutionOutput { effects, ..Default::default() })
+    }
+
+    fn get_transaction(&self, hash: &units_core_types::TransactionHash) -> Option<Transaction> {
+        self.0.get_transaction(hash)
+    }
+
+    fn get_transaction_receipt(&self, hash: &units_core_types::TransactionHash) -> Option<units_core_types::TransactionReceipt> {
+        self.0.get_transaction_receipt(hash)
+    }
+
+    fn rollback_transaction(&self, hash: &units_core_types::TransactionHash) -> Result<bool, units_core_types::error::RuntimeError> {
+        self.0.rollback_transaction(hash)
+    }
+
+    fn get_verifier(&self) -> &dyn units_core_types::Verifier {
+        self.0.get_verifier()
+    }
+}
+
+#[tokio::test]
+async fn test_watches_filter_changes_and_survive_restarts() {
+    use units_core_service::services::{WatchDelivery, WatchFilter, WatchTarget};
+
+    let data_dir = std::env::temp_dir().join(format!("units-watch-test-{}", std::process::id()));
+    std::fs::create_dir_all(&data_dir).unwrap();
+    let mut config = Config::default();
+    config.storage.data_dir = Some(data_dir.to_str().unwrap().to_string());
+
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage.clone(), Arc::new(WriteParamsRuntime(MockRuntime::new())), config.clone());
+    let controller = UnitsObjectId::new([1; 32]);
+    let (small, large) = (UnitsObjectId::new([2; 32]), UnitsObjectId::new([3; 32]));
+
+    // Only writes leaving at least 8 bytes pass the filter
+    let filter = WatchFilter { min_data_size: Some(8), ..Default::default() };
+    let watch = service
+        .register_watch(WatchTarget::Controller { controller_id: controller }, filter, WatchDelivery::WebSocket)
+        .await
+        .unwrap();
+    let write = |hash: u8| {
+        Transaction::new(
+            vec![
+                Instruction::new(controller, "write".to_string(), vec![small], vec![hash; 2]),
+                Instruction::new(controller, "write".to_string(), vec![large], vec![hash; 8]),
+            ],
+            [hash; 32],
+        )
+    };
+
+    service.submit_transaction(write(1)).await.unwrap();
+    service.advance_slot().await.unwrap();
+    let (backlog, _) = service.subscribe_watch(&watch.id, 0).unwrap();
+    assert_eq!(backlog.len(), 1);
+    assert_eq!((backlog[0].sequence, backlog[0].object_id), (1, large));
+
+    // A restarted node keeps the watch and continues its sequence
+    drop(service);
+    let service = UnitsService::new(storage, Arc::new(WriteParamsRuntime(MockRuntime::new())), config);
+    assert_eq!(service.get_watch(&watch.id).await.unwrap().sequence, 1);
+    service.submit_transaction(write(2)).await.unwrap();
+    service.advance_slot().await.unwrap();
+    let (backlog, _) = service.subscribe_watch(&watch.id, 1).unwrap();
+    assert_eq!(backlog.iter().map(|n| n.sequence).collect::<Vec<_>>(), vec![2]);
+
+    assert!(service.remove_watch(&watch.id).await.unwrap());
+    assert!(service.subscribe_watch(&watch.id, 0).is_err());
+    std::fs::remove_dir_all(data_dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_admin_funds_deposit_accounts() {
+    use units_core_service::services::{AdminAuth, AdminOperation};
+
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let mut config = Config::default();
+    config.admin.enabled = true;
+    config.admin.api_key = Some("secret".to_string());
+    let service = UnitsService::new(storage, Arc::new(MockRuntime::new()), config);
+    let account = UnitsObjectId::new([4; 32]);
+    let fund = AdminOperation::FundDepositAccount { account_id: account, amount: 25 };
+
+    // A dry run reports the balance it would leave without crediting it
+    let dry_run = AdminAuth { api_key: "secret".to_string(), dry_run: true, confirmation: None };
+    let report = service.admin(&dry_run, fund.clone()).await.unwrap();
+    assert_eq!(report.details, vec![format!("{} balance 25", account)]);
+    assert_eq!(service.get_deposit_balance(&account).await.unwrap(), 0);
+
+    let auth = AdminAuth { api_key: "secret".to_string(), dry_run: false, confirmation: None };
+    service.admin(&auth, fund.clone()).await.unwrap();
+    service.admin(&auth, fund).await.unwrap();
+    assert_eq!(service.get_deposit_balance(&account).await.unwrap(), 50);
+    assert_eq!(service.get_fee_balance(&account).await.unwrap(), 0);
+}
+
+/// Runtime running every controller as [`WriteParams`], charging the
+/// storage rent of the wrapped runtime
+struct RentRuntime(MockRuntime);
+
+impl units_core_types::Runtime for RentRuntime {
+    fn get_vm_executor(&self, _vm_type: VMType) -> Option<Box<dyn units_core_types::VMExecutor>> {
+        Some(Box::new(WriteParams))
+    }
+
+    fn execute_transaction(&self, transaction: Transaction) -> units_core_types::TransactionReceipt {
+        self.0.execute_transaction(transaction)
+    }
+
+    fn storage_rent_config(&self) -> Option<units_core_types::StorageRentConfig> {
+        self.0.storage_rent_config()
+    }
+
+    fn get_transaction(&self, hash: &units_core_types::TransactionHash) -> Option<Transaction> {
+        self.0.get_transaction(hash)
+    }
+
+    fn get_transaction_receipt(&self, hash: &units_core_types::TransactionHash) -> Option<units_core_types::TransactionReceipt> {
+        self.0.get_transaction_receipt(hash)
+    }
+
+    fn rollback_transaction(&self, hash: &units_core_types::TransactionHash) -> Result<bool, units_core_types::error::RuntimeError> {
+        self.0.rollback_transaction(hash)
+    }
+
+    fn get_verifier(&self) -> &dyn units_core_types::Verifier {
+        self.0.get_verifier()
+    }
+}
+
+/// Writes each instruction's params as the data of its targets
+struct WriteParams;
+
+impl units_core_types::VMExecutor for WriteParams {
+    fn vm_type(&self) -> VMType {
+        VMType::RiscV
+    }
+
+    fn load_and_execute(
+        &self,
+        _bytecode: &[u8],
+        context: &units_core_types::ExecutionContext,
+    ) -> Result<Vec<units_core_types::ObjectEffect>, units_core_types::VMExecutionError> {
+        let instruction = &context.instruction;
+        Ok(instruction
+            .target_objects
+            .iter()
+            .map(|id| {
+                let after = units_core_types::UnitsObject::new_data(*id, instruction.controller_id, instruction.params.clone());
+                match context.objects.get(id) {
+                    Some(before) => units_core_types::ObjectEffect::modification(before.clone(), after),
+                    None => units_core_types::ObjectEffect::creation(after),
+                }
+            })
+            .collect())
+    }
+}
+
+#[tokio::test]
+async fn test_only_deposit_changes_lock_the_deposit_ledger() {
+    use units_core_service::services::{AdminAuth, AdminOperation};
+    use units_core_types::{ObjectStorage, StorageRentConfig, UnitsStorage, DEPOSIT_LEDGER_ID};
+
+    let runtime = Arc::new(RentRuntime(MockRuntime::new().with_storage_rent(StorageRentConfig::new(10, 1))));
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let mut config = Config::default();
+    config.admin.enabled = true;
+    config.admin.api_key = Some("secret".to_string());
+    let service = UnitsService::new(storage.clone(), runtime, config);
+
+    let controller = UnitsObjectId::new([1; 32]);
+    service.create_object(controller, ObjectType::Executable(VMType::RiscV), vec![], None, None).await.unwrap();
+    let auth = AdminAuth { api_key: "secret".to_string(), dry_run: false, confirmation: None };
+    service.admin(&auth, AdminOperation::FundDepositAccount { account_id: controller, amount: 1000 }).await.unwrap();
+
+    let targets: Vec<UnitsObjectId> = (10..26u8).map(|seed| UnitsObjectId::new([seed; 32])).collect();
+    let write = |hash: u8, target: UnitsObjectId, data: Vec<u8>| {
+        Transaction::new(vec![Instruction::new(controller, "write".to_string(), vec![target], data)], [hash; 32])
+    };
+
+    // Creations no longer conflict on the ledger, so they run in parallel,
+    // yet every one of them is charged its 14
+    for (index, target) in targets.iter().enumerate() {
+        service.submit_transaction(write(index as u8 + 1, *target, vec![1; 4])).await.unwrap();
+    }
+    service.advance_slot().await.unwrap();
+    for index in 0..targets.len() {
+        let receipt = service.get_transaction_receipt(&[index as u8 + 1; 32]).await.unwrap();
+        assert!(receipt.success, "{:?}", receipt.error_message);
+    }
+    assert_eq!(service.get_deposit_balance(&controller).await.unwrap(), 1000 - 16 * 14);
+
+    // Rewrites at the same size leave the ledger untouched
+    let version = storage.objects().version(&DEPOSIT_LEDGER_ID).unwrap();
+    for (index, target) in targets.iter().enumerate() {
+        service.submit_transaction(write(index as u8 + 100, *target, vec![2; 4])).await.unwrap();
+    }
+    service.advance_slot().await.unwrap();
+    for (index, target) in targets.iter().enumerate() {
+        let receipt = service.get_transaction_receipt(&[index as u8 + 100; 32]).await.unwrap();
+        assert!(receipt.success, "{:?}", receipt.error_message);
+        assert!(receipt.effects.iter().all(|effect| effect.object_id != DEPOSIT_LEDGER_ID));
+        assert_eq!(service.get_object(target).await.unwrap().data, vec![2; 4]);
+    }
+    assert_eq!(storage.objects().version(&DEPOSIT_LEDGER_ID).unwrap(), version);
+    assert_eq!(service.get_deposit_balance(&controller).await.unwrap(), 1000 - 16 * 14);
+}
+
+#[tokio::test]
+async fn test_sponsor_pays_fees_for_another_signer() {
+    use units_core_service::services::{AdminAuth, AdminOperation};
+    use units_core_service::signing::NodeSigner;
+    use units_core_types::{FeeLedger, FeeSponsorship, FEE_LEDGER_ID};
+
+    let runtime = Arc::new(AppendRuntime(MockRuntime::new()));
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let mut config = Config::default();
+    config.admin.enabled = true;
+    config.admin.api_key = Some("secret".to_string());
+    let service = UnitsService::new(storage, runtime, config);
+
+    let sponsor = NodeSigner::from_seed([8; 32]);
+    let payer = UnitsObjectId::new(sponsor.public_key());
+    let auth = AdminAuth { api_key: "secret".to_string(), dry_run: false, confirmation: None };
+    service.admin(&auth, AdminOperation::FundFeeAccount { account_id: payer, amount: 10 }).await.unwrap();
+    assert_eq!(service.get_fee_balance(&payer).await.unwrap(), 10);
+
+    let controller = UnitsObjectId::new([1; 32]);
+    let target = UnitsObjectId::new([2; 32]);
+    service.create_object(controller, ObjectType::Data, vec![], None, None).await.unwrap();
+    service.create_object(target, ObjectType::Data, vec![0], Some(controller), None).await.unwrap();
+    let sponsored = |hash: u8, fee: u64, max_fee: u64, nonce: u64, signer: &NodeSigner| {
+        let instruction = Instruction::new(controller, "append".to_string(), vec![target], vec![]);
+        let transaction = Transaction::new(vec![instruction], [hash; 32]).with_priority_fee(fee);
+        let signature = signer.sign(&FeeSponsorship::commitment(&transaction, nonce, max_fee));
+        transaction.with_sponsorship(FeeSponsorship {
+            payer_key: sponsor.public_key(),
+            max_fee,
+            nonce,
+            signature: signature.to_vec(),
+        })
+    };
+    let balance = |ledger: units_core_types::objects::VersionedObject| FeeLedger::from_object(&ledger.object).unwrap().balance_of(&payer);
+
+    // The sponsor, not the submitter, is charged the priority fee
+    let sandbox = service.create_sandbox().await.unwrap();
+    let receipt = service
+        .sandbox_execute_transaction(&RequestContext::new(), &sandbox.namespace, sponsored(1, 4, 5, 0, &sponsor))
+        .await
+        .unwrap();
+    assert!(receipt.success, "{:?}", receipt.error_message);
+    let fee = receipt.fee.unwrap();
+    assert_eq!((fee.payer, fee.amount, fee.sponsored), (payer, 4, true));
+    assert_eq!(balance(service.sandbox_get_object(&sandbox.namespace, &FEE_LEDGER_ID).await.unwrap()), 6);
+
+    // Replaying the sponsored transaction charges nothing: its nonce is spent
+    let receipt = service
+        .sandbox_execute_transaction(&RequestContext::new(), &sandbox.namespace, sponsored(1, 4, 5, 0, &sponsor))
+        .await
+        .unwrap();
+    assert!(receipt.error_message.unwrap().contains("nonce"));
+    assert_eq!(balance(service.sandbox_get_object(&sandbox.namespace, &FEE_LEDGER_ID).await.unwrap()), 6);
+
+    // Nor can the sponsorship be grafted onto other instructions under the same hash
+    let mut grafted = sponsored(6, 4, 5, 1, &sponsor);
+    grafted.instructions[0].params = vec![1];
+    let receipt = service
+        .sandbox_execute_transaction(&RequestContext::new(), &sandbox.namespace, grafted.clone())
+        .await
+        .unwrap();
+    assert!(receipt.error_message.unwrap().starts_with("Invalid fee sponsorship"));
+    assert!(service.submit_transaction(grafted).await.is_err());
+
+    // A fee above what the sponsor signed for, or beyond its balance, fails
+    let receipt = service
+        .sandbox_execute_transaction(&RequestContext::new(), &sandbox.namespace, sponsored(2, 6, 5, 1, &sponsor))
+        .await
+        .unwrap();
+    assert!(receipt.error_message.unwrap().starts_with("Invalid fee sponsorship"));
+    let receipt = service
+        .sandbox_execute_transaction(&RequestContext::new(), &sandbox.namespace, sponsored(3, 7, 7, 1, &sponsor))
+        .await
+        .unwrap();
+    assert!(receipt.error_message.unwrap().starts_with("Fee sponsor cannot pay"));
+    assert_eq!(balance(service.sandbox_get_object(&sandbox.namespace, &FEE_LEDGER_ID).await.unwrap()), 6);
+
+    // Signatures by anyone but the sponsor are refused at admission
+    let forged = sponsored(4, 1, 1, 0, &NodeSigner::from_seed([9; 32]));
+    assert!(service.submit_transaction(forged).await.is_err());
+    assert!(service.submit_transaction(sponsored(5, 1, 1, 0, &sponsor)).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_legal_holds_retain_deleted_objects_until_released() {
+    use units_core_service::services::{AdminAuth, AdminOperation};
+    use units_core_types::ObjectStorage;
+
+    let runtime = Arc::new(MockRuntime::new());
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let mut config = Config::default();
+    config.admin.enabled = true;
+    config.admin.api_key = Some("secret".to_string());
+    let service = UnitsService::new(storage.clone(), runtime, config);
+    let auth = |dry_run: bool, confirmation: Option<String>| AdminAuth {
+        api_key: "secret".to_string(),
+        dry_run,
+        confirmation,
+    };
+
+    let controller = UnitsObjectId::new([1; 32]);
+    let target = UnitsObjectId::new([2; 32]);
+    service.create_object(controller, ObjectType::Data, vec![], None, None).await.unwrap();
+    service.create_object(target, ObjectType::Data, vec![7], Some(controller), None).await.unwrap();
+
+    let hold = AdminOperation::PlaceLegalHold { controller_id: controller };
+    assert_eq!(service.admin(&auth(false, None), hold).await.unwrap().affected, 1);
+    storage.inner().delete(&target, None).unwrap();
+
+    // Gone for readers, but listed by the audit with its deletion proof
+    assert!(service.get_object(&target).await.is_err());
+    let audit = service.legal_hold_audit(&auth(false, None), None).await.unwrap();
+    assert_eq!(audit.holds, vec![controller]);
+    assert_eq!(audit.retained.len(), 1);
+    assert_eq!((audit.retained[0].object.id, audit.retained[0].object.data.clone()), (target, vec![7]));
+
+    // Releasing discards what was retained, so it needs confirmation
+    let release = AdminOperation::ReleaseLegalHold { controller_id: controller };
+    assert!(service.admin(&auth(false, None), release.clone()).await.is_err());
+    let plan = service.admin(&auth(true, None), release.clone()).await.unwrap();
+    assert_eq!(plan.affected, 1);
+    let report = service.admin(&auth(false, plan.confirmation_token), release.clone()).await.unwrap();
+    assert_eq!(report.affected, 1);
+    let audit = service.legal_hold_audit(&auth(false, None), None).await.unwrap();
+    assert!(audit.holds.is_empty() && audit.retained.is_empty());
+    assert!(service.admin(&auth(true, None), release).await.is_err());
+}
+
+#[tokio::test]
+async fn test_receipt_and_proof_queries_are_cached_until_the_slot_closes() {
+    use units_core_types::{ReceiptStorage, TransactionReceipt, UnitsStorage};
+
+    let runtime = Arc::new(MockRuntime::new());
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage.clone(), runtime, Config::default());
+    service.advance_slot().await.unwrap();
+
+    for _ in 0..3 {
+        service.get_state_root(1).await.unwrap();
+        service.get_receipts_chunk(&RequestContext::new(), 0, 5, 10).await.unwrap();
+    }
+    let stats = service.get_service_stats().await.unwrap().response_cache;
+    assert_eq!((stats.hits, stats.misses, stats.entries), (4, 2, 2));
+
+    // A lookup that failed is retried, not served from the cache
+    let receipt = TransactionReceipt::new([9; 32], 2, true, 0);
+    assert!(service.get_transaction_receipt(&receipt.transaction_hash).await.is_err());
+    storage.receipts().store_receipt(&receipt).unwrap();
+    assert!(service.get_transaction_receipt(&receipt.transaction_hash).await.is_ok());
+
+    // Closing a slot drops cached ranges that it may have extended
+    assert!(service.get_receipts_chunk(&RequestContext::new(), 0, 5, 10).await.unwrap().receipts.is_empty());
+    service.advance_slot().await.unwrap();
+    assert_eq!(service.get_service_stats().await.unwrap().response_cache.entries, 0);
+    assert_eq!(service.get_receipts_chunk(&RequestContext::new(), 0, 5, 10).await.unwrap().receipts.len(), 1);
+}
+
+#[tokio::test]
+async fn test_external_identifiers_derive_configured_object_ids() {
+    use units_core_types::{IdCase, IdDerivationRegistry, NamespacedScheme};
+
+    let mut config = Config::default();
+    let lei = NamespacedScheme { namespace: "lei".to_string(), version: 1, case: IdCase::Upper, length: Some(20), prefix: None };
+    config.id_schemes = vec![lei.clone(), NamespacedScheme::isin()];
+    let service = UnitsService::new(Arc::new(ConsolidatedUnitsStorage::new_in_memory()), Arc::new(MockRuntime::new()), config);
+
+    // Configured schemes join the built-in ones, whose IDs anyone can reproduce
+    let derived = service.derive_object_id("lei", "5493001kjtiigc8y1r12", None).await.unwrap();
+    assert_eq!(derived.version, 1);
+    let mut registry = IdDerivationRegistry::new();
+    registry.register(Box::new(lei)).unwrap();
+    assert_eq!(derived.object_id, hex::encode(*registry.derive("lei", "5493001KJTIIGC8Y1R12").unwrap()));
+    let isin = service.derive_object_id("isin", "US0378331005", Some(1)).await.unwrap();
+    assert_eq!(isin.object_id, hex::encode(*IdDerivationRegistry::with_defaults().derive("isin", "US0378331005").unwrap()));
+
+    assert!(service.derive_object_id("lei", "short", None).await.is_err());
+    assert!(service.derive_object_id("isin", "US0378331005", Some(2)).await.is_err());
+    assert!(service.derive_object_id("cusip", "037833100", None).await.is_err());
+}
+
+#[tokio::test]
+async fn test_transaction_memo_is_carried_into_the_receipt() {
+    use units_core_types::MAX_MEMO_LEN;
+
+    let service = UnitsService::new(
+        Arc::new(ConsolidatedUnitsStorage::new_in_memory()),
+        Arc::new(AppendRuntime(MockRuntime::new())),
+        Config::default(),
+    );
+    let controller = UnitsObjectId::new([1; 32]);
+    let target = UnitsObjectId::new([2; 32]);
+    service.create_object(controller, ObjectType::Data, vec![], None, None).await.unwrap();
+    service.create_object(target, ObjectType::Data, vec![0], Some(controller), None).await.unwrap();
+    let transfer = |hash: u8, memo: Vec<u8>| {
+        let instruction = Instruction::new(controller, "append".to_string(), vec![target], vec![]);
+        Transaction::new(vec![instruction], [hash; 32]).with_memo(memo)
+    };
+
+    let sandbox = service.create_sandbox().await.unwrap();
+    let receipt = service
+        .sandbox_execute_transaction(&RequestContext::new(), &sandbox.namespace, transfer(1, b"INV-1001".to_vec()))
+        .await
+        .unwrap();
+    assert!(receipt.success, "{:?}", receipt.error_message);
+    assert_eq!(receipt.memo.as_deref(), Some(&b"INV-1001"[..]));
+
+    // Oversized memos are refused at admission and fail if executed anyway
+    assert!(service.submit_transaction(transfer(2, vec![0; MAX_MEMO_LEN + 1])).await.is_err());
+    let receipt = service
+        .sandbox_execute_transaction(&RequestContext::new(), &sandbox.namespace, transfer(3, vec![0; MAX_MEMO_LEN + 1]))
+        .await
+        .unwrap();
+    assert!(!receipt.success);
+}
+
+#[tokio::test]
+async fn test_transaction_graph_links_writers_to_later_readers_and_writers() {
+    use units_core_service::services::dependency_graph::DependencyKind;
+    use units_core_types::{ReceiptStorage, UnitsStorage};
+
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let service = UnitsService::new(storage.clone(), Arc::new(AppendRuntime(MockRuntime::new())), Config::default());
+    let controller = UnitsObjectId::new([1; 32]);
+    let target = UnitsObjectId::new([2; 32]);
+    let missing = UnitsObjectId::new([3; 32]);
+    service.create_object(controller, ObjectType::Data, vec![], None, None).await.unwrap();
+    service.create_object(target, ObjectType::Data, vec![0], Some(controller), None).await.unwrap();
+    let append = |hash: u8, targets: Vec<UnitsObjectId>| {
+        Transaction::new(vec![Instruction::new(controller, "append".to_string(), targets, vec![])], [hash; 32])
+    };
+
+    // The later write sorts first by hash, and also reads an object that does not exist
+    let sandbox = service.create_sandbox().await.unwrap();
+    let mut receipts = Vec::new();
+    for transaction in [append(9, vec![target]), append(4, vec![target, missing])] {
+        let mut receipt = service
+            .sandbox_execute_transaction(&RequestContext::new(), &sandbox.namespace, transaction)
+            .await
+            .unwrap();
+        assert!(receipt.success, "{:?}", receipt.error_message);
+        receipt.slot = 2;
+        storage.receipts().store_receipt(&receipt).unwrap();
+        receipts.push(receipt);
+    }
+    assert!(receipts[0].reads.is_empty());
+    assert_eq!(receipts[1].reads.len(), 1);
+    assert_eq!((receipts[1].reads[0].object_id, receipts[1].reads[0].state_hash), (missing, None));
+
+    let graph = service.get_transaction_graph(&RequestContext::new(), 0, 5).await.unwrap();
+    let hashes: Vec<_> = graph.transactions.iter().map(|node| node.transaction_hash).collect();
+    assert_eq!(hashes, vec![[4; 32], [9; 32]]);
+    assert_eq!(graph.edges.len(), 1);
+    assert_eq!((graph.edges[0].from, graph.edges[0].to), ([9; 32], [4; 32]));
+    assert_eq!((graph.edges[0].object_id, graph.edges[0].kind), (target, DependencyKind::WriteAfterWrite));
+    assert!(graph.next_slot.is_none());
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_lock_table_is_recovered_on_restart() {
+    use units_core_service::services::minimal_services::LOCK_TABLE_FILE;
+    use units_core_types::{LockType, PersistentLockManager};
+    use units_storage_impl::SqliteLockManager;
+
+    let data_dir = std::env::temp_dir().join(format!("units-lock-table-test-{}", std::process::id()));
+    std::fs::create_dir_all(&data_dir).unwrap();
+    let mut config = Config::default();
+    config.storage.data_dir = Some(data_dir.to_str().unwrap().to_string());
+    config.storage.persistent_locks = true;
+    let path = data_dir.join(LOCK_TABLE_FILE);
+    let lock_table = || SqliteLockManager::open(&path).unwrap();
+
+    let storage = Arc::new(ConsolidatedUnitsStorage::new_in_memory());
+    let runtime = Arc::new(LockTableProbe { runtime: MockRuntime::new(), path: path.clone(), seen: Default::default() });
+    let service = UnitsService::new(storage.clone(), runtime.clone(), config.clone());
+    let target = UnitsObjectId::new([9; 32]);
+    let write = |hash: u8| {
+        Transaction::new(
+            vec![Instruction::new(UnitsObjectId::new([1; 32]), "write".to_string(), vec![target], vec![])],
+            [hash; 32],
+        )
+    };
+
+    // Write locks are in the table while the transaction executes, and only then
+    service.submit_transaction(write(1)).await.unwrap();
+    service.advance_slot().await.unwrap();
+    assert_eq!(*runtime.seen.lock().unwrap(), vec![1]);
+    assert!(lock_table().all_locks().unwrap().is_empty());
+
+    // A crash mid-execution leaves the transaction's locks behind...
+    lock_table().acquire_lock(&target, LockType::Write, &[2; 32], None).unwrap();
+    drop(service);
+
+    // ...which the restarted node releases, as nothing is executing it any more
+    let service = UnitsService::new(storage, runtime.clone(), config);
+    assert!(lock_table().all_locks().unwrap().is_empty());
+    service.submit_transaction(write(3)).await.unwrap();
+    service.advance_slot().await.unwrap();
+    assert_eq!(*runtime.seen.lock().unwrap(), vec![1, 1]);
+
+    std::fs::remove_dir_all(data_dir).unwrap();
+}
+
+/// Runtime recording how many locks the lock table at `path` holds for
+/// each transaction as it executes
+#[cfg(feature = "sqlite")]
+struct LockTableProbe {
+    runtime: MockRuntime,
+    path: std::path::PathBuf,
+    seen: std::sync::Mutex<Vec<usize>>,
+}
+
+#[cfg(feature = "sqlite")]
+impl units_core_types::Runtime for LockTableProbe {
+    fn get_vm_executor(&self, vm_type: VMType) -> Option<Box<dyn units_core_types::VMExecutor>> {
+        self.runtime.get_vm_executor(vm_type)
+    }
+
+    fn execute_transaction(&self, transaction: Transaction) -> units_core_types::TransactionReceipt {
+        use units_core_types::PersistentLockManager;
+
+        let lock_table = units_storage_impl::SqliteLockManager::open(&self.path).unwrap();
+        self.seen.lock().unwrap().push(lock_table.get_transaction_locks(&transaction.hash).count());
+        self.runtime.execute_transaction(transaction)
+    }
+
+    fn get_transaction(&self, hash: &units_core_types::TransactionHash) -> Option<Transaction> {
+        self.runtime.get_transaction(hash)
+    }
+
+    fn get_transaction_receipt(&self, hash: &units_core_types::TransactionHash) -> Option<units_core_types::TransactionReceipt> {
+        self.runtime.get_transaction_receipt(hash)
+    }
+
+    fn rollback_transaction(&self, hash: &units_core_types::TransactionHash) -> Result<bool, units_core_types::error::RuntimeError> {
+        self.runtime.rollback_transaction(hash)
+    }
+
+    fn get_verifier(&self) -> &dyn units_core_types::Verifier {
+        self.runtime.get_verifier()
+    }
+}
@@ -0,0 +1,327 @@
+//! Validation and stripping of kernel module ELF images
+//!
+//! The node's RISC-V executor runs statically linked RV32IMAC executables
+//! and loads nothing but their `PT_LOAD` segments. Validation rejects images
+//! it could not run, or could only run until they reach an instruction of an
+//! extension the VM lacks: floating point, vector, RV64-only operations and
+//! instructions longer than 32 bits. Stripping drops the section headers and
+//! everything past the last loaded byte, such as symbols and debug info.
+
+use std::fmt;
+
+const ELF_MAGIC: &[u8; 4] = b"\x7fELF";
+const ELFCLASS32: u8 = 1;
+const ELFDATA2LSB: u8 = 1;
+const ET_EXEC: u16 = 2;
+const EM_RISCV: u16 = 243;
+const ELF32_HEADER_SIZE: usize = 52;
+const ELF32_PHDR_SIZE: usize = 32;
+
+const PT_LOAD: u32 = 1;
+const PT_DYNAMIC: u32 = 2;
+const PT_INTERP: u32 = 3;
+const PF_X: u32 = 1;
+
+/// Compressed instructions; the only `e_flags` bit the VM accepts
+const EF_RISCV_RVC: u32 = 0x1;
+const EF_RISCV_FLOAT_ABI: u32 = 0x6;
+const EF_RISCV_RVE: u32 = 0x8;
+
+/// Why an image cannot be deployed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElfError {
+    Malformed(String),
+    /// Not a static little-endian RV32 executable
+    WrongTarget(String),
+    /// Built for the hardware float ABI
+    FloatAbi,
+    /// Instruction of an extension the VM does not implement
+    UnsupportedInstruction { address: u32, word: u32, extension: &'static str },
+    MisalignedEntry(u32),
+    EntryOutsideCode(u32),
+}
+
+impl fmt::Display for ElfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed(reason) => write!(f, "Malformed ELF: {}", reason),
+            Self::WrongTarget(reason) => write!(f, "Not a riscv32imac executable: {}", reason),
+            Self::FloatAbi => f.write_str("Built for a hardware float ABI; use the soft-float riscv32imac target"),
+            Self::UnsupportedInstruction { address, word, extension } => write!(
+                f,
+                "Unsupported {} instruction {:#010x} at {:#010x}",
+                extension, word, address
+            ),
+            Self::MisalignedEntry(entry) => write!(f, "Entry point {:#010x} is not 4-byte aligned", entry),
+            Self::EntryOutsideCode(entry) => {
+                write!(f, "Entry point {:#010x} is not in an executable segment", entry)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ElfError {}
+
+/// One `PT_LOAD` segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    pub offset: usize,
+    pub vaddr: u32,
+    pub file_size: usize,
+    pub mem_size: usize,
+    pub executable: bool,
+}
+
+/// Facts about an image that passed validation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleImage {
+    pub entry: u32,
+    pub segments: Vec<Segment>,
+    /// Whether the image was linked with compressed instructions
+    pub compressed: bool,
+}
+
+/// Check that `elf` is a riscv32imac executable the VM can run
+pub fn validate(elf: &[u8]) -> Result<ModuleImage, ElfError> {
+    if elf.len() < ELF32_HEADER_SIZE || &elf[..4] != ELF_MAGIC {
+        return Err(ElfError::Malformed("missing ELF header".to_string()));
+    }
+    if elf[4] != ELFCLASS32 || elf[5] != ELFDATA2LSB {
+        return Err(ElfError::WrongTarget("expected a 32-bit little-endian ELF".to_string()));
+    }
+    if u16_at(elf, 16)? != ET_EXEC {
+        return Err(ElfError::WrongTarget("expected a statically linked executable".to_string()));
+    }
+    if u16_at(elf, 18)? != EM_RISCV {
+        return Err(ElfError::WrongTarget("machine is not RISC-V".to_string()));
+    }
+
+    let flags = u32_at(elf, 36)?;
+    if flags & EF_RISCV_FLOAT_ABI != 0 {
+        return Err(ElfError::FloatAbi);
+    }
+    if flags & EF_RISCV_RVE != 0 {
+        return Err(ElfError::WrongTarget("RV32E has too few registers".to_string()));
+    }
+    if flags & !EF_RISCV_RVC != 0 {
+        return Err(ElfError::WrongTarget(format!("unsupported ELF flags {:#x}", flags)));
+    }
+
+    let entry = u32_at(elf, 24)?;
+    if entry % 4 != 0 {
+        return Err(ElfError::MisalignedEntry(entry));
+    }
+
+    let segments = segments(elf)?;
+    if !segments
+        .iter()
+        .any(|segment| segment.executable && (segment.vaddr as u64..segment.vaddr as u64 + segment.file_size as u64).contains(&(entry as u64)))
+    {
+        return Err(ElfError::EntryOutsideCode(entry));
+    }
+    for segment in segments.iter().filter(|segment| segment.executable) {
+        check_instructions(&elf[segment.offset..segment.offset + segment.file_size], segment.vaddr)?;
+    }
+
+    Ok(ModuleImage { entry, segments, compressed: flags & EF_RISCV_RVC != 0 })
+}
+
+/// Copy of a validated `elf` without section headers or trailing unloaded data
+pub fn strip(elf: &[u8], image: &ModuleImage) -> Vec<u8> {
+    let program_headers = u32_at(elf, 28).unwrap_or(0) as usize
+        + u16_at(elf, 44).unwrap_or(0) as usize * ELF32_PHDR_SIZE;
+    let end = image
+        .segments
+        .iter()
+        .map(|segment| segment.offset + segment.file_size)
+        .fold(ELF32_HEADER_SIZE.max(program_headers), usize::max)
+        .min(elf.len());
+
+    let mut stripped = elf[..end].to_vec();
+    // e_shoff, then e_shentsize, e_shnum and e_shstrndx
+    stripped[32..36].fill(0);
+    stripped[46..52].fill(0);
+    stripped
+}
+
+fn segments(elf: &[u8]) -> Result<Vec<Segment>, ElfError> {
+    let phoff = u32_at(elf, 28)? as usize;
+    let phentsize = u16_at(elf, 42)? as usize;
+    let phnum = u16_at(elf, 44)? as usize;
+    if phentsize != ELF32_PHDR_SIZE || phnum == 0 {
+        return Err(ElfError::Malformed("no program headers".to_string()));
+    }
+
+    let mut segments = Vec::new();
+    for index in 0..phnum {
+        let header = phoff + index * ELF32_PHDR_SIZE;
+        match u32_at(elf, header)? {
+            PT_LOAD => {}
+            PT_DYNAMIC | PT_INTERP => {
+                return Err(ElfError::WrongTarget("dynamically linked".to_string()));
+            }
+            _ => continue,
+        }
+        let segment = Segment {
+            offset: u32_at(elf, header + 4)? as usize,
+            vaddr: u32_at(elf, header + 8)?,
+            file_size: u32_at(elf, header + 16)? as usize,
+            mem_size: u32_at(elf, header + 20)? as usize,
+            executable: u32_at(elf, header + 24)? & PF_X != 0,
+        };
+        if segment.offset.checked_add(segment.file_size).map_or(true, |end| end > elf.len()) {
+            return Err(ElfError::Malformed(format!("segment {} extends beyond the file", index)));
+        }
+        if segment.file_size > segment.mem_size {
+            return Err(ElfError::Malformed(format!("segment {} is larger on disk than in memory", index)));
+        }
+        segments.push(segment);
+    }
+    if segments.is_empty() {
+        return Err(ElfError::Malformed("no loadable segments".to_string()));
+    }
+    Ok(segments)
+}
+
+/// Walk the instruction stream of a code segment
+fn check_instructions(code: &[u8], base: u32) -> Result<(), ElfError> {
+    let mut offset = 0;
+    while offset + 2 <= code.len() {
+        let address = base.wrapping_add(offset as u32);
+        let half = u16::from_le_bytes([code[offset], code[offset + 1]]);
+        if half & 0b11 != 0b11 {
+            if let Some(extension) = compressed_extension(half) {
+                return Err(ElfError::UnsupportedInstruction { address, word: half as u32, extension });
+            }
+            offset += 2;
+            continue;
+        }
+        if half & 0b11100 == 0b11100 {
+            return Err(ElfError::UnsupportedInstruction { address, word: half as u32, extension: "long (48-bit or wider)" });
+        }
+        let Some(bytes) = code.get(offset..offset + 4) else {
+            return Err(ElfError::Malformed(format!("truncated instruction at {:#010x}", address)));
+        };
+        let word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        if let Some(extension) = extension(word) {
+            return Err(ElfError::UnsupportedInstruction { address, word, extension });
+        }
+        offset += 4;
+    }
+    Ok(())
+}
+
+/// Unsupported extension a 32-bit instruction belongs to, by major opcode
+fn extension(word: u32) -> Option<&'static str> {
+    match word & 0x7f {
+        // LOAD-FP and STORE-FP also carry vector loads and stores
+        0x07 | 0x27 | 0x43 | 0x47 | 0x4b | 0x4f | 0x53 => Some("floating-point"),
+        0x57 => Some("vector"),
+        0x1b | 0x3b => Some("RV64"),
+        _ => None,
+    }
+}
+
+/// Unsupported extension a compressed instruction belongs to
+///
+/// In RV32C the odd `funct3` values of quadrants 0 and 2 are the
+/// floating-point loads and stores.
+fn compressed_extension(half: u16) -> Option<&'static str> {
+    let quadrant = half & 0b11;
+    let funct3 = half >> 13;
+    ((quadrant == 0b00 || quadrant == 0b10) && funct3 & 1 == 1).then_some("floating-point")
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> Result<u16, ElfError> {
+    bytes
+        .get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| ElfError::Malformed(format!("truncated at offset {}", offset)))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Result<u32, ElfError> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| ElfError::Malformed(format!("truncated at offset {}", offset)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE_ADDR: u32 = 0x1_0000;
+    /// addi a0, zero, 0
+    const LI_A0_0: u32 = 0x0000_0513;
+    /// ecall
+    const ECALL: u32 = 0x0000_0073;
+
+    /// Static RV32 executable with one code segment and trailing section data
+    fn executable(code: &[u32], entry: u32, flags: u32) -> Vec<u8> {
+        let code: Vec<u8> = code.iter().flat_map(|word| word.to_le_bytes()).collect();
+        let code_offset = ELF32_HEADER_SIZE + ELF32_PHDR_SIZE;
+        let mut elf = vec![0u8; code_offset];
+        elf[..4].copy_from_slice(ELF_MAGIC);
+        elf[4] = ELFCLASS32;
+        elf[5] = ELFDATA2LSB;
+        elf[6] = 1;
+        elf[16..18].copy_from_slice(&ET_EXEC.to_le_bytes());
+        elf[18..20].copy_from_slice(&EM_RISCV.to_le_bytes());
+        elf[24..28].copy_from_slice(&entry.to_le_bytes());
+        elf[28..32].copy_from_slice(&(ELF32_HEADER_SIZE as u32).to_le_bytes());
+        elf[32..36].copy_from_slice(&((code_offset + code.len() + 16) as u32).to_le_bytes());
+        elf[36..40].copy_from_slice(&flags.to_le_bytes());
+        elf[42..44].copy_from_slice(&(ELF32_PHDR_SIZE as u16).to_le_bytes());
+        elf[44..46].copy_from_slice(&1u16.to_le_bytes());
+        elf[48..50].copy_from_slice(&1u16.to_le_bytes());
+
+        let header = ELF32_HEADER_SIZE;
+        elf[header..header + 4].copy_from_slice(&PT_LOAD.to_le_bytes());
+        elf[header + 4..header + 8].copy_from_slice(&(code_offset as u32).to_le_bytes());
+        elf[header + 8..header + 12].copy_from_slice(&CODE_ADDR.to_le_bytes());
+        elf[header + 16..header + 20].copy_from_slice(&(code.len() as u32).to_le_bytes());
+        elf[header + 20..header + 24].copy_from_slice(&(code.len() as u32).to_le_bytes());
+        elf[header + 24..header + 28].copy_from_slice(&(PF_X | 0x4).to_le_bytes());
+
+        elf.extend_from_slice(&code);
+        // Stand-in for symbols and section headers
+        elf.extend_from_slice(&[0xaa; 56]);
+        elf
+    }
+
+    #[test]
+    fn test_valid_module_is_stripped_to_its_segments() {
+        let elf = executable(&[LI_A0_0, ECALL], CODE_ADDR, EF_RISCV_RVC);
+        let image = validate(&elf).unwrap();
+        assert_eq!((image.entry, image.segments.len(), image.compressed), (CODE_ADDR, 1, true));
+
+        let stripped = strip(&elf, &image);
+        assert_eq!(stripped.len(), elf.len() - 56);
+        assert_eq!(u32_at(&stripped, 32).unwrap(), 0);
+        assert_eq!(u16_at(&stripped, 48).unwrap(), 0);
+        assert_eq!(validate(&stripped).unwrap(), image);
+    }
+
+    #[test]
+    fn test_rejects_what_the_vm_cannot_run() {
+        // fadd.s, then c.flwsp among otherwise valid code
+        let elf = executable(&[LI_A0_0, 0x0000_0053], CODE_ADDR, 0);
+        assert!(matches!(
+            validate(&elf),
+            Err(ElfError::UnsupportedInstruction { address, extension: "floating-point", .. }) if address == CODE_ADDR + 4
+        ));
+        let elf = executable(&[LI_A0_0, 0x0001_6002], CODE_ADDR, EF_RISCV_RVC);
+        assert!(matches!(validate(&elf), Err(ElfError::UnsupportedInstruction { extension: "floating-point", .. })));
+        let elf = executable(&[0x0000_053b, ECALL], CODE_ADDR, 0);
+        assert!(matches!(validate(&elf), Err(ElfError::UnsupportedInstruction { extension: "RV64", .. })));
+
+        assert_eq!(validate(&executable(&[ECALL], CODE_ADDR, 0x2)), Err(ElfError::FloatAbi));
+        assert_eq!(validate(&executable(&[ECALL], CODE_ADDR + 2, 0)), Err(ElfError::MisalignedEntry(CODE_ADDR + 2)));
+        assert_eq!(validate(&executable(&[ECALL], CODE_ADDR + 8, 0)), Err(ElfError::EntryOutsideCode(CODE_ADDR + 8)));
+
+        let mut elf = executable(&[ECALL], CODE_ADDR, 0);
+        elf[4] = 2;
+        assert!(matches!(validate(&elf), Err(ElfError::WrongTarget(_))));
+        assert!(matches!(validate(b"\x7fELF"), Err(ElfError::Malformed(_))));
+    }
+}
@@ -0,0 +1,220 @@
+//! Cross-compilation and packaging of UNITS kernel modules
+//!
+//! `build` compiles a module crate for `riscv32imac-unknown-none-elf` and
+//! packages the result; `package` does the same for an ELF built elsewhere.
+//! Packaging validates the image against what the node's VM can run, strips
+//! it, and bundles it with ABI metadata into a module artifact the node
+//! deploys with the `deployModule` RPC; WebAssembly modules are bundled
+//! as they are. `inspect` summarises an artifact.
+//! `wrap` turns a raw instruction stream into an RVBC image instead.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use units_core_types::{ModuleAbi, ModuleArtifact, VMType};
+use units_runtime_impl::rvbc;
+
+mod elf;
+
+/// Target triple kernel modules are built for
+const MODULE_TARGET: &str = "riscv32imac-unknown-none-elf";
+
+/// Leading bytes of a WebAssembly binary module
+const WASM_MAGIC: &[u8; 4] = b"\0asm";
+
+#[derive(Parser)]
+#[command(name = "units-build")]
+#[command(about = "Build, validate and package UNITS kernel modules")]
+struct Args {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Cross-compile a module crate and package it
+    Build {
+        /// Cargo.toml of the module crate
+        #[arg(long, default_value = "Cargo.toml")]
+        manifest_path: PathBuf,
+
+        /// Binary target to build
+        #[arg(long)]
+        bin: String,
+
+        /// Comma-separated features to enable
+        #[arg(long)]
+        features: Option<String>,
+
+        /// JSON file of ABI metadata to bundle
+        #[arg(long)]
+        abi: Option<PathBuf>,
+
+        /// Where to write the artifact, `<bin>.umod` by default
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Validate and package an already built ELF or WebAssembly module
+    Package {
+        /// The module's ELF executable or `.wasm` file
+        elf: PathBuf,
+
+        /// JSON file of ABI metadata to bundle
+        #[arg(long)]
+        abi: Option<PathBuf>,
+
+        /// Where to write the artifact
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Print a summary of an artifact
+    Inspect {
+        artifact: PathBuf,
+    },
+    /// Wrap raw RISC-V instructions into a validated RVBC image
+    Wrap {
+        /// File of little-endian instruction words
+        code: PathBuf,
+
+        /// Byte offset into the code of the first instruction to run
+        #[arg(long, default_value_t = 0)]
+        entry: u32,
+
+        /// Where to write the image
+        #[arg(long)]
+        out: PathBuf,
+    },
+}
+
+fn main() -> Result<()> {
+    match Args::parse().command {
+        Commands::Build { manifest_path, bin, features, abi, out } => {
+            let elf = cross_compile(&manifest_path, &bin, features.as_deref())?;
+            let out = out.unwrap_or_else(|| PathBuf::from(format!("{}.umod", bin)));
+            package(&elf, abi.as_deref(), &out)
+        }
+        Commands::Package { elf, abi, out } => package(&elf, abi.as_deref(), &out),
+        Commands::Inspect { artifact } => inspect(&artifact),
+        Commands::Wrap { code, entry, out } => wrap(&code, entry, &out),
+    }
+}
+
+/// Build `bin` for the module target, returning the path of its ELF
+fn cross_compile(manifest_path: &Path, bin: &str, features: Option<&str>) -> Result<PathBuf> {
+    let mut cargo = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()));
+    cargo
+        .args(["build", "--release", "--no-default-features", "--target", MODULE_TARGET, "--bin", bin])
+        .arg("--manifest-path")
+        .arg(manifest_path);
+    if let Some(features) = features {
+        cargo.args(["--features", features]);
+    }
+    let status = cargo.status().context("Cannot run cargo")?;
+    anyhow::ensure!(status.success(), "cargo build failed for {}", manifest_path.display());
+
+    Ok(target_directory(manifest_path)?.join(MODULE_TARGET).join("release").join(bin))
+}
+
+/// Cargo's target directory for the package at `manifest_path`
+fn target_directory(manifest_path: &Path) -> Result<PathBuf> {
+    let output = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()))
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .arg("--manifest-path")
+        .arg(manifest_path)
+        .output()
+        .context("Cannot run cargo metadata")?;
+    anyhow::ensure!(output.status.success(), "cargo metadata failed for {}", manifest_path.display());
+
+    let metadata: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    metadata["target_directory"]
+        .as_str()
+        .map(PathBuf::from)
+        .context("cargo metadata did not report a target directory")
+}
+
+fn package(elf_path: &Path, abi_path: Option<&Path>, out: &Path) -> Result<()> {
+    let bytes = std::fs::read(elf_path).with_context(|| format!("Cannot read {}", elf_path.display()))?;
+    let abi = match abi_path {
+        Some(path) => {
+            let json = std::fs::read_to_string(path).with_context(|| format!("Cannot read {}", path.display()))?;
+            serde_json::from_str(&json).with_context(|| format!("Invalid ABI metadata in {}", path.display()))?
+        }
+        None => ModuleAbi::default(),
+    };
+
+    // The executor validates WebAssembly itself when it compiles the module
+    if bytes.starts_with(WASM_MAGIC) {
+        let artifact = ModuleArtifact::new(VMType::Wasm, bytes, abi);
+        std::fs::write(out, artifact.encode()?).with_context(|| format!("Cannot write {}", out.display()))?;
+        println!("Packaged WebAssembly module {} ({} bytes) as {}", elf_path.display(), artifact.code.len(), out.display());
+        println!("Code hash {}", hex::encode(artifact.code_hash));
+        return Ok(());
+    }
+
+    let image = elf::validate(&bytes).with_context(|| format!("{} cannot be deployed", elf_path.display()))?;
+    let code = elf::strip(&bytes, &image);
+    let artifact = ModuleArtifact::new(VMType::RiscV, code, abi);
+    std::fs::write(out, artifact.encode()?).with_context(|| format!("Cannot write {}", out.display()))?;
+    println!(
+        "Packaged {} ({} bytes, {} stripped) as {}",
+        elf_path.display(),
+        artifact.code.len(),
+        bytes.len() - artifact.code.len(),
+        out.display()
+    );
+    println!("Entry {:#010x}, code hash {}", image.entry, hex::encode(artifact.code_hash));
+    Ok(())
+}
+
+fn inspect(path: &Path) -> Result<()> {
+    let bytes = std::fs::read(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    let artifact = ModuleArtifact::decode(&bytes)?;
+
+    println!("VM:        {:?}", artifact.vm_type);
+    println!("Code:      {} bytes, hash {}", artifact.code.len(), hex::encode(artifact.code_hash));
+    if artifact.vm_type == VMType::RiscV {
+        let image = elf::validate(&artifact.code).context("Artifact code cannot be deployed")?;
+        println!(
+            "Entry:     {:#010x}{}",
+            image.entry,
+            if image.compressed { ", compressed instructions" } else { "" }
+        );
+        for segment in &image.segments {
+            println!(
+                "Segment:   {:#010x} {} bytes ({} in memory){}",
+                segment.vaddr,
+                segment.file_size,
+                segment.mem_size,
+                if segment.executable { ", executable" } else { "" }
+            );
+        }
+    }
+    println!("Functions: {}", artifact.abi.functions.join(", "));
+    if let Some(location) = &artifact.abi.abi_location {
+        println!("ABI:       {}", location);
+    }
+    for (function, rules) in &artifact.abi.prefetch {
+        println!("Prefetch:  {} ({} rules)", function, rules.len());
+    }
+    for (code, error) in &artifact.abi.error_codes {
+        println!("Error:     {} {}", code, error.name);
+    }
+    Ok(())
+}
+
+fn wrap(code_path: &Path, entry: u32, out: &Path) -> Result<()> {
+    let code = std::fs::read(code_path).with_context(|| format!("Cannot read {}", code_path.display()))?;
+    let image = rvbc::encode(&code, entry).with_context(|| format!("{} cannot be wrapped", code_path.display()))?;
+    std::fs::write(out, &image).with_context(|| format!("Cannot write {}", out.display()))?;
+    println!(
+        "Wrapped {} ({} bytes) as RVBC v{} image {}",
+        code_path.display(),
+        code.len(),
+        rvbc::FORMAT_VERSION,
+        out.display()
+    );
+    println!("Entry {:#010x}", rvbc::CODE_BASE_ADDR + entry);
+    Ok(())
+}
@@ -0,0 +1,228 @@
+//! Load and soak test generator for UNITS nodes
+//!
+//! Sends a weighted mix of token transfers, account updates and object
+//! reads to a node's JSON-RPC endpoint at a target rate, then reports
+//! latency percentiles and error rates per operation. Long runs with
+//! periodic reports double as soak tests.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use jsonrpsee::rpc_params;
+use log::info;
+use tokio::sync::Semaphore;
+use tokio::time::MissedTickBehavior;
+use units_core_types::{UnitsObjectId, ACCOUNT_CONTROLLER_ID, TOKEN_CONTROLLER_ID};
+
+mod mix;
+mod report;
+mod workload;
+
+use mix::{OperationKind, WorkloadMix};
+use report::Recorder;
+use workload::{Request, Workload, WorkloadConfig};
+
+#[derive(Parser)]
+#[command(name = "units-loadgen")]
+#[command(about = "Generate transaction and read load against a UNITS node")]
+struct Args {
+    /// JSON-RPC endpoint of the node
+    #[arg(long, default_value = "http://127.0.0.1:8080")]
+    url: String,
+
+    /// Target requests per second
+    #[arg(long, default_value_t = 100)]
+    tps: u32,
+
+    /// Length of the run in seconds
+    #[arg(long, default_value_t = 60)]
+    duration_secs: u64,
+
+    /// Operation weights, e.g. `transfer=60,account=20,read=20`
+    #[arg(long, default_value = "transfer=60,account=20,read=20")]
+    mix: WorkloadMix,
+
+    /// Maximum requests in flight; ticks beyond this are counted as dropped
+    #[arg(long, default_value_t = 64)]
+    concurrency: usize,
+
+    /// Number of token balances transfers move between
+    #[arg(long, default_value_t = 1000)]
+    balances: usize,
+
+    /// Number of accounts updates and reads target
+    #[arg(long, default_value_t = 1000)]
+    accounts: usize,
+
+    /// Hex-encoded token controller ID
+    #[arg(long, value_parser = parse_object_id)]
+    token_controller: Option<UnitsObjectId>,
+
+    /// Hex-encoded account controller ID
+    #[arg(long, value_parser = parse_object_id)]
+    account_controller: Option<UnitsObjectId>,
+
+    /// Seed for the request stream; equal seeds send equal requests
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+
+    /// Seconds between interim reports (0 disables them)
+    #[arg(long, default_value_t = 10)]
+    report_interval_secs: u64,
+
+    /// Per-request timeout in milliseconds
+    #[arg(long, default_value_t = 5000)]
+    timeout_ms: u64,
+
+    /// Print the final report as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Exit with an error when the overall error rate exceeds this fraction
+    #[arg(long)]
+    max_error_rate: Option<f64>,
+
+    /// Log level
+    #[arg(long, default_value = "info")]
+    log_level: String,
+}
+
+fn parse_object_id(value: &str) -> Result<UnitsObjectId, String> {
+    let bytes: [u8; 32] = hex::decode(value)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| "Object ID must be 32 hex-encoded bytes".to_string())?;
+    Ok(UnitsObjectId::new(bytes))
+}
+
+/// Outcomes of the whole run and of the current report window
+#[derive(Default)]
+struct Recorders {
+    total: Recorder,
+    window: Recorder,
+}
+
+impl Recorders {
+    fn record(&mut self, kind: OperationKind, latency: Duration, success: bool) {
+        self.total.record(kind, latency, success);
+        self.window.record(kind, latency, success);
+    }
+
+    fn record_dropped(&mut self) {
+        self.total.record_dropped();
+        self.window.record_dropped();
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or(&args.log_level)
+    ).init();
+
+    anyhow::ensure!(args.tps > 0, "--tps must be positive");
+    anyhow::ensure!(args.concurrency > 0, "--concurrency must be positive");
+
+    let client = Arc::new(
+        HttpClientBuilder::default()
+            .request_timeout(Duration::from_millis(args.timeout_ms))
+            .build(&args.url)
+            .with_context(|| format!("Cannot create client for {}", args.url))?,
+    );
+
+    let mut workload = Workload::new(
+        WorkloadConfig {
+            token_controller: args.token_controller.unwrap_or(TOKEN_CONTROLLER_ID),
+            account_controller: args.account_controller.unwrap_or(ACCOUNT_CONTROLLER_ID),
+            balances: args.balances,
+            accounts: args.accounts,
+        },
+        args.mix.clone(),
+        args.seed,
+    );
+
+    info!(
+        "Sending {} tps to {} for {}s (mix {:?})",
+        args.tps, args.url, args.duration_secs, args.mix
+    );
+
+    let recorders = Arc::new(Mutex::new(Recorders::default()));
+    let in_flight = Arc::new(Semaphore::new(args.concurrency));
+    let mut ticker = tokio::time::interval(Duration::from_secs_f64(1.0 / args.tps as f64));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Burst);
+
+    let started = Instant::now();
+    let run_for = Duration::from_secs(args.duration_secs);
+    let report_every = Duration::from_secs(args.report_interval_secs);
+    let mut window_started = started;
+
+    while started.elapsed() < run_for {
+        ticker.tick().await;
+
+        if !report_every.is_zero() && window_started.elapsed() >= report_every {
+            let window = std::mem::take(&mut recorders.lock().unwrap().window);
+            print!("{}", window.summarize(window_started.elapsed()).render());
+            window_started = Instant::now();
+        }
+
+        let (kind, request) = workload.next_request();
+        let Ok(permit) = in_flight.clone().try_acquire_owned() else {
+            recorders.lock().unwrap().record_dropped();
+            continue;
+        };
+
+        let client = client.clone();
+        let recorders = recorders.clone();
+        tokio::spawn(async move {
+            let sent = Instant::now();
+            let success = send(&client, request).await;
+            recorders.lock().unwrap().record(kind, sent.elapsed(), success);
+            drop(permit);
+        });
+    }
+
+    // Let in-flight requests finish before reporting
+    let _drained = in_flight.acquire_many(args.concurrency as u32).await?;
+    let report = recorders.lock().unwrap().total.summarize(started.elapsed());
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", report.render());
+    }
+
+    if let Some(max_error_rate) = args.max_error_rate {
+        let error_rate = report.errors as f64 / report.requests.max(1) as f64;
+        anyhow::ensure!(
+            error_rate <= max_error_rate,
+            "Error rate {:.4} exceeds the allowed {:.4}",
+            error_rate,
+            max_error_rate
+        );
+    }
+    Ok(())
+}
+
+/// Send one request, returning whether the node accepted it
+async fn send(client: &HttpClient, request: Request) -> bool {
+    let result = match request {
+        Request::Submit(transaction) => client
+            .request::<String, _>("submitTransaction", rpc_params![transaction])
+            .await
+            .map(drop),
+        Request::Read(id) => client
+            .request::<serde_json::Value, _>("getObject", rpc_params![hex::encode(id.bytes())])
+            .await
+            .map(drop),
+    };
+
+    if let Err(error) = &result {
+        log::debug!("Request failed: {}", error);
+    }
+    result.is_ok()
+}
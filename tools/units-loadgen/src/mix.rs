@@ -0,0 +1,145 @@
+//! Weighted operation mix and the deterministic generator driving it
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+/// Kind of request sent to the node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    /// Token transfer between two balances
+    Transfer,
+    /// Account profile update
+    AccountUpdate,
+    /// Object read
+    Read,
+}
+
+impl OperationKind {
+    pub const ALL: [OperationKind; 3] = [Self::Transfer, Self::AccountUpdate, Self::Read];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Transfer => "transfer",
+            Self::AccountUpdate => "account",
+            Self::Read => "read",
+        }
+    }
+}
+
+impl fmt::Display for OperationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Relative weights of each operation kind, e.g. `transfer=60,account=20,read=20`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkloadMix {
+    weights: Vec<(OperationKind, u32)>,
+    total: u32,
+}
+
+impl WorkloadMix {
+    /// Pick an operation kind in proportion to its weight
+    pub fn pick(&self, rng: &mut SplitMix64) -> OperationKind {
+        let mut roll = (rng.next_u64() % self.total as u64) as u32;
+        for (kind, weight) in &self.weights {
+            if roll < *weight {
+                return *kind;
+            }
+            roll -= weight;
+        }
+        unreachable!("roll is below the total weight")
+    }
+}
+
+impl FromStr for WorkloadMix {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut weights = Vec::new();
+        for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let (name, weight) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Expected <operation>=<weight>, got '{}'", entry))?;
+            let kind = OperationKind::ALL
+                .into_iter()
+                .find(|kind| kind.as_str() == name.trim())
+                .ok_or_else(|| format!("Unknown operation '{}' (expected transfer, account or read)", name))?;
+            let weight: u32 = weight
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid weight '{}' for {}", weight, kind))?;
+            if weights.iter().any(|(existing, _)| *existing == kind) {
+                return Err(format!("Operation {} listed twice", kind));
+            }
+            if weight > 0 {
+                weights.push((kind, weight));
+            }
+        }
+
+        let total = weights.iter().map(|(_, weight)| weight).sum();
+        if total == 0 {
+            return Err("Mix must give at least one operation a non-zero weight".to_string());
+        }
+        Ok(Self { weights, total })
+    }
+}
+
+/// Small seeded generator so runs with the same seed send the same requests
+#[derive(Debug, Clone)]
+pub struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform index below `len`
+    pub fn below(&mut self, len: usize) -> usize {
+        (self.next_u64() % len as u64) as usize
+    }
+
+    pub fn fill(&mut self, bytes: &mut [u8]) {
+        for chunk in bytes.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next_u64().to_le_bytes()[..chunk.len()]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_pick() {
+        let mix: WorkloadMix = "transfer=3, read=1, account=0".parse().unwrap();
+        let mut rng = SplitMix64::new(7);
+        let mut counts = [0usize; 3];
+        for _ in 0..4000 {
+            match mix.pick(&mut rng) {
+                OperationKind::Transfer => counts[0] += 1,
+                OperationKind::AccountUpdate => counts[1] += 1,
+                OperationKind::Read => counts[2] += 1,
+            }
+        }
+        assert_eq!(counts[1], 0);
+        assert!((2800..3200).contains(&counts[0]), "transfers: {}", counts[0]);
+
+        assert!("transfer".parse::<WorkloadMix>().is_err());
+        assert!("mint=1".parse::<WorkloadMix>().is_err());
+        assert!("read=0".parse::<WorkloadMix>().is_err());
+        assert!("read=1,read=2".parse::<WorkloadMix>().is_err());
+    }
+}
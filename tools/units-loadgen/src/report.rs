@@ -0,0 +1,163 @@
+//! Latency and error accounting for a load run
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::mix::OperationKind;
+
+/// Raw samples of one operation kind
+#[derive(Debug, Clone, Default)]
+struct Samples {
+    latencies_us: Vec<u64>,
+    errors: u64,
+}
+
+/// Collects request outcomes over a measurement window
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    samples: BTreeMap<OperationKind, Samples>,
+    /// Requests skipped because every in-flight slot was busy
+    dropped: u64,
+}
+
+impl Recorder {
+    pub fn record(&mut self, kind: OperationKind, latency: Duration, success: bool) {
+        let samples = self.samples.entry(kind).or_default();
+        samples.latencies_us.push(latency.as_micros() as u64);
+        if !success {
+            samples.errors += 1;
+        }
+    }
+
+    pub fn record_dropped(&mut self) {
+        self.dropped += 1;
+    }
+
+    /// Summarize the window, which lasted `elapsed`
+    pub fn summarize(&self, elapsed: Duration) -> Report {
+        let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+        let operations = self
+            .samples
+            .iter()
+            .map(|(kind, samples)| {
+                let mut sorted = samples.latencies_us.clone();
+                sorted.sort_unstable();
+                let requests = sorted.len() as u64;
+                OperationReport {
+                    operation: *kind,
+                    requests,
+                    errors: samples.errors,
+                    error_rate: samples.errors as f64 / requests.max(1) as f64,
+                    throughput: requests as f64 / secs,
+                    p50_us: percentile(&sorted, 50.0),
+                    p90_us: percentile(&sorted, 90.0),
+                    p99_us: percentile(&sorted, 99.0),
+                    max_us: sorted.last().copied().unwrap_or_default(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let requests: u64 = operations.iter().map(|op| op.requests).sum();
+        let errors: u64 = operations.iter().map(|op| op.errors).sum();
+        Report {
+            elapsed_secs: elapsed.as_secs_f64(),
+            requests,
+            errors,
+            dropped: self.dropped,
+            achieved_tps: requests as f64 / secs,
+            operations,
+        }
+    }
+}
+
+/// Nearest-rank percentile of sorted samples
+fn percentile(sorted: &[u64], pct: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationReport {
+    pub operation: OperationKind,
+    pub requests: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    /// Completed requests per second
+    pub throughput: f64,
+    pub p50_us: u64,
+    pub p90_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+}
+
+/// Summary of a measurement window
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub elapsed_secs: f64,
+    pub requests: u64,
+    pub errors: u64,
+    /// Requests the generator could not send without exceeding its concurrency
+    pub dropped: u64,
+    pub achieved_tps: f64,
+    pub operations: Vec<OperationReport>,
+}
+
+impl Report {
+    /// Human-readable table of the report
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "{:.1}s: {} requests ({:.1} tps), {} errors, {} dropped\n",
+            self.elapsed_secs, self.requests, self.achieved_tps, self.errors, self.dropped
+        );
+        out.push_str(&format!(
+            "  {:<10} {:>8} {:>8} {:>7} {:>10} {:>10} {:>10} {:>10}\n",
+            "operation", "requests", "errors", "err%", "p50(us)", "p90(us)", "p99(us)", "max(us)"
+        ));
+        for op in &self.operations {
+            out.push_str(&format!(
+                "  {:<10} {:>8} {:>8} {:>6.2}% {:>10} {:>10} {:>10} {:>10}\n",
+                op.operation.as_str(),
+                op.requests,
+                op.errors,
+                op.error_rate * 100.0,
+                op.p50_us,
+                op.p90_us,
+                op.p99_us,
+                op.max_us
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_and_error_rate() {
+        let mut recorder = Recorder::default();
+        for ms in 1..=100 {
+            recorder.record(OperationKind::Read, Duration::from_millis(ms), ms % 10 != 0);
+        }
+        recorder.record_dropped();
+
+        let report = recorder.summarize(Duration::from_secs(2));
+        assert_eq!(report.requests, 100);
+        assert_eq!(report.errors, 10);
+        assert_eq!(report.dropped, 1);
+        assert_eq!(report.achieved_tps, 50.0);
+
+        let read = &report.operations[0];
+        assert_eq!(read.p50_us, 50_000);
+        assert_eq!(read.p90_us, 90_000);
+        assert_eq!(read.p99_us, 99_000);
+        assert_eq!(read.max_us, 100_000);
+        assert_eq!(read.error_rate, 0.1);
+    }
+}
@@ -0,0 +1,178 @@
+//! Request generation over a fixed pool of target objects
+//!
+//! Targets are derived deterministically from their pool index, so a node
+//! can be seeded with the same token, balance and account objects ahead of
+//! a run (see `Workload::token_id` and friends). Transactions against
+//! objects the node does not have still exercise submission and execution,
+//! but show up as failed receipts rather than RPC errors.
+
+use account::crypto::Signature;
+use account::{UpdateAccountParams, FN_UPDATE_ACCOUNT};
+use token::{TokenFunction, TransferParams};
+use units_core_types::{Instruction, Transaction, UnitsObjectId};
+
+use crate::mix::{OperationKind, SplitMix64, WorkloadMix};
+
+/// One request to send to the node
+#[derive(Debug, Clone)]
+pub enum Request {
+    Submit(Transaction),
+    Read(UnitsObjectId),
+}
+
+/// Controllers and pool sizes a workload targets
+#[derive(Debug, Clone)]
+pub struct WorkloadConfig {
+    pub token_controller: UnitsObjectId,
+    pub account_controller: UnitsObjectId,
+    pub balances: usize,
+    pub accounts: usize,
+}
+
+/// Generates a seeded stream of requests following a mix
+pub struct Workload {
+    config: WorkloadConfig,
+    mix: WorkloadMix,
+    rng: SplitMix64,
+    token: UnitsObjectId,
+    balances: Vec<UnitsObjectId>,
+    accounts: Vec<UnitsObjectId>,
+    sequence: u64,
+}
+
+impl Workload {
+    pub fn new(config: WorkloadConfig, mix: WorkloadMix, seed: u64) -> Self {
+        let balances = (0..config.balances.max(2)).map(Self::balance_id).collect();
+        let accounts = (0..config.accounts.max(1)).map(Self::account_id).collect();
+        Self {
+            config,
+            mix,
+            rng: SplitMix64::new(seed),
+            token: Self::token_id(),
+            balances,
+            accounts,
+            sequence: 0,
+        }
+    }
+
+    /// Token object every transfer moves
+    pub fn token_id() -> UnitsObjectId {
+        UnitsObjectId::find_uid(&[b"units-loadgen", b"token"]).0
+    }
+
+    /// Balance object at `index` of the pool
+    pub fn balance_id(index: usize) -> UnitsObjectId {
+        UnitsObjectId::find_uid(&[b"units-loadgen", b"balance", &(index as u64).to_le_bytes()]).0
+    }
+
+    /// Account object at `index` of the pool
+    pub fn account_id(index: usize) -> UnitsObjectId {
+        UnitsObjectId::find_uid(&[b"units-loadgen", b"account", &(index as u64).to_le_bytes()]).0
+    }
+
+    /// Next request and the kind it counts towards
+    pub fn next_request(&mut self) -> (OperationKind, Request) {
+        let kind = self.mix.pick(&mut self.rng);
+        self.sequence += 1;
+
+        let request = match kind {
+            OperationKind::Transfer => {
+                let from = self.rng.below(self.balances.len());
+                // Offset by at least one so a transfer never targets one balance twice
+                let to = (from + 1 + self.rng.below(self.balances.len() - 1)) % self.balances.len();
+                let params = borsh::to_vec(&TransferParams { amount: 1 }).expect("transfer params encode");
+                let instruction = Instruction::new(
+                    self.config.token_controller,
+                    TokenFunction::TransferToken.as_str().to_string(),
+                    vec![self.token, self.balances[from], self.balances[to]],
+                    params,
+                );
+                Request::Submit(self.transaction(instruction))
+            }
+            OperationKind::AccountUpdate => {
+                let account = self.accounts[self.rng.below(self.accounts.len())];
+                let params = borsh::to_vec(&UpdateAccountParams {
+                    account_id: account_sdk_id(&account),
+                    username: None,
+                    display_name: Some(format!("loadgen-{}", self.sequence)),
+                    metadata: None,
+                    // The generator holds no owner keys, so updates go
+                    // unsigned and exercise the rejection path
+                    expires_at: 0,
+                    signature: Signature::new([0; 64]),
+                })
+                .expect("account params encode");
+                let instruction = Instruction::new(
+                    self.config.account_controller,
+                    FN_UPDATE_ACCOUNT.to_string(),
+                    vec![account],
+                    params,
+                );
+                Request::Submit(self.transaction(instruction))
+            }
+            OperationKind::Read => {
+                let pool = self.balances.len() + self.accounts.len();
+                let index = self.rng.below(pool);
+                let id = match index.checked_sub(self.balances.len()) {
+                    Some(account) => self.accounts[account],
+                    None => self.balances[index],
+                };
+                Request::Read(id)
+            }
+        };
+
+        (kind, request)
+    }
+
+    fn transaction(&mut self, instruction: Instruction) -> Transaction {
+        let mut hash = [0u8; 32];
+        self.rng.fill(&mut hash);
+        Transaction::new(vec![instruction], hash)
+    }
+}
+
+fn account_sdk_id(id: &UnitsObjectId) -> units_kernel_sdk::UnitsObjectId {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(id.bytes());
+    units_kernel_sdk::UnitsObjectId::new(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workload(seed: u64) -> Workload {
+        let config = WorkloadConfig {
+            token_controller: UnitsObjectId::new([1; 32]),
+            account_controller: UnitsObjectId::new([2; 32]),
+            balances: 4,
+            accounts: 4,
+        };
+        Workload::new(config, "transfer=1,account=1,read=1".parse().unwrap(), seed)
+    }
+
+    #[test]
+    fn test_requests_are_seeded_and_well_formed() {
+        let (mut a, mut b) = (workload(3), workload(3));
+        for _ in 0..200 {
+            let (kind, request) = a.next_request();
+            let (other_kind, other) = b.next_request();
+            assert_eq!(kind, other_kind);
+
+            match (kind, request, other) {
+                (OperationKind::Transfer, Request::Submit(tx), Request::Submit(other)) => {
+                    assert_eq!(tx.hash, other.hash);
+                    let targets = &tx.instructions[0].target_objects;
+                    assert_eq!(targets[0], Workload::token_id());
+                    assert_ne!(targets[1], targets[2]);
+                }
+                (OperationKind::AccountUpdate, Request::Submit(tx), Request::Submit(_)) => {
+                    let params: UpdateAccountParams = borsh::from_slice(&tx.instructions[0].params).unwrap();
+                    assert_eq!(params.account_id.bytes(), tx.instructions[0].target_objects[0].bytes());
+                }
+                (OperationKind::Read, Request::Read(id), Request::Read(other)) => assert_eq!(id, other),
+                (kind, _, _) => panic!("{} produced the wrong request type", kind),
+            }
+        }
+    }
+}